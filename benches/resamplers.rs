@@ -3,11 +3,25 @@ extern crate rubato;
 
 use rubato::sinc_interpolator::ScalarInterpolator;
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe")
+))]
 use rubato::sinc_interpolator::sinc_interpolator_avx::AvxInterpolator;
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe")
+))]
 use rubato::sinc_interpolator::sinc_interpolator_neon::NeonInterpolator;
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe")
+))]
 use rubato::sinc_interpolator::sinc_interpolator_sse::SseInterpolator;
 
 use rubato::{
@@ -15,6 +29,7 @@ use rubato::{
     WindowFunction,
 };
 
+#[cfg(feature = "f64")]
 fn bench_fftfixedin(c: &mut Criterion) {
     let chunksize = 1024;
     let mut resampler = FftFixedIn::<f64>::new(44100, 192000, 1024, 2, 1).unwrap();
@@ -24,6 +39,7 @@ fn bench_fftfixedin(c: &mut Criterion) {
     });
 }
 
+#[cfg(feature = "f32")]
 fn bench_fftfixedin_32(c: &mut Criterion) {
     let chunksize = 1024;
     let mut resampler = FftFixedIn::<f32>::new(44100, 192000, 1024, 2, 1).unwrap();
@@ -76,6 +92,7 @@ macro_rules! bench_async_resampler {
     };
 }
 
+#[cfg(feature = "f32")]
 bench_async_resampler!(
     f32,
     ScalarInterpolator,
@@ -84,6 +101,7 @@ bench_async_resampler!(
     "scalar async cubic   32",
     infallible
 );
+#[cfg(feature = "f32")]
 bench_async_resampler!(
     f32,
     ScalarInterpolator,
@@ -92,6 +110,7 @@ bench_async_resampler!(
     "scalar async linear  32",
     infallible
 );
+#[cfg(feature = "f32")]
 bench_async_resampler!(
     f32,
     ScalarInterpolator,
@@ -100,6 +119,7 @@ bench_async_resampler!(
     "scalar async nearest 32",
     infallible
 );
+#[cfg(feature = "f64")]
 bench_async_resampler!(
     f64,
     ScalarInterpolator,
@@ -108,6 +128,7 @@ bench_async_resampler!(
     "scalar async cubic   64",
     infallible
 );
+#[cfg(feature = "f64")]
 bench_async_resampler!(
     f64,
     ScalarInterpolator,
@@ -116,6 +137,7 @@ bench_async_resampler!(
     "scalar async linear  64",
     infallible
 );
+#[cfg(feature = "f64")]
 bench_async_resampler!(
     f64,
     ScalarInterpolator,
@@ -125,7 +147,13 @@ bench_async_resampler!(
     infallible
 );
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     SseInterpolator,
@@ -133,7 +161,13 @@ bench_async_resampler!(
     bench_sse_async_cubic_32,
     "sse async cubic   32"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     SseInterpolator,
@@ -141,7 +175,13 @@ bench_async_resampler!(
     bench_sse_async_linear_32,
     "sse async linear  32"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     SseInterpolator,
@@ -149,7 +189,13 @@ bench_async_resampler!(
     bench_sse_async_nearest_32,
     "sse async nearest 32"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     SseInterpolator,
@@ -157,7 +203,13 @@ bench_async_resampler!(
     bench_sse_async_cubic_64,
     "sse async cubic   64"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     SseInterpolator,
@@ -165,7 +217,13 @@ bench_async_resampler!(
     bench_sse_async_linear_64,
     "sse async linear  64"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     SseInterpolator,
@@ -174,7 +232,13 @@ bench_async_resampler!(
     "sse async nearest 64"
 );
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     AvxInterpolator,
@@ -182,7 +246,13 @@ bench_async_resampler!(
     bench_avx_async_cubic_32,
     "avx async cubic   32"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     AvxInterpolator,
@@ -190,7 +260,13 @@ bench_async_resampler!(
     bench_avx_async_linear_32,
     "avx async linear  32"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     AvxInterpolator,
@@ -198,7 +274,13 @@ bench_async_resampler!(
     bench_avx_async_nearest_32,
     "avx async nearest 32"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     AvxInterpolator,
@@ -206,7 +288,13 @@ bench_async_resampler!(
     bench_avx_async_cubic_64,
     "avx async cubic   64"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     AvxInterpolator,
@@ -214,7 +302,13 @@ bench_async_resampler!(
     bench_avx_async_linear_64,
     "avx async linear  64"
 );
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    feature = "simd_sse3",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     AvxInterpolator,
@@ -223,7 +317,12 @@ bench_async_resampler!(
     "avx async nearest 64"
 );
 
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     NeonInterpolator,
@@ -231,7 +330,12 @@ bench_async_resampler!(
     bench_neon_async_cubic_32,
     "neon async cubic   32"
 );
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     NeonInterpolator,
@@ -239,7 +343,12 @@ bench_async_resampler!(
     bench_neon_async_linear_32,
     "neon async linear  32"
 );
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe"),
+    feature = "f32"
+))]
 bench_async_resampler!(
     f32,
     NeonInterpolator,
@@ -247,7 +356,12 @@ bench_async_resampler!(
     bench_neon_async_nearest_32,
     "neon async nearest 32"
 );
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     NeonInterpolator,
@@ -255,7 +369,12 @@ bench_async_resampler!(
     bench_neon_async_cubic_64,
     "neon async cubic   64"
 );
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     NeonInterpolator,
@@ -263,7 +382,12 @@ bench_async_resampler!(
     bench_neon_async_linear_64,
     "neon async linear  64"
 );
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    not(feature = "forbid_unsafe"),
+    feature = "f64"
+))]
 bench_async_resampler!(
     f64,
     NeonInterpolator,
@@ -289,60 +413,70 @@ macro_rules! bench_fast_async_resampler {
     };
 }
 
+#[cfg(feature = "f32")]
 bench_fast_async_resampler!(
     f32,
     PolynomialDegree::Septic,
     bench_fast_async_septic_32,
     "fast async septic  32"
 );
+#[cfg(feature = "f32")]
 bench_fast_async_resampler!(
     f32,
     PolynomialDegree::Quintic,
     bench_fast_async_quintic_32,
     "fast async quintic  32"
 );
+#[cfg(feature = "f32")]
 bench_fast_async_resampler!(
     f32,
     PolynomialDegree::Cubic,
     bench_fast_async_cubic_32,
     "fast async cubic   32"
 );
+#[cfg(feature = "f32")]
 bench_fast_async_resampler!(
     f32,
     PolynomialDegree::Linear,
     bench_fast_async_linear_32,
     "fast async linear  32"
 );
+#[cfg(feature = "f32")]
 bench_fast_async_resampler!(
     f32,
     PolynomialDegree::Nearest,
     bench_fast_async_nearest_32,
     "fast async nearest 32"
 );
+#[cfg(feature = "f64")]
 bench_fast_async_resampler!(
     f64,
     PolynomialDegree::Septic,
     bench_fast_async_septic_64,
     "fast async septic  64"
 );
+#[cfg(feature = "f64")]
 bench_fast_async_resampler!(
     f64,
     PolynomialDegree::Quintic,
     bench_fast_async_quintic_64,
     "fast async quintic  64"
 );
+#[cfg(feature = "f64")]
 bench_fast_async_resampler!(
     f64,
     PolynomialDegree::Cubic,
     bench_fast_async_cubic_64,
     "fast async cubic   64"
 );
+#[cfg(feature = "f64")]
 bench_fast_async_resampler!(
     f64,
     PolynomialDegree::Linear,
     bench_fast_async_linear_64,
     "fast async linear  64"
 );
+#[cfg(feature = "f64")]
 bench_fast_async_resampler!(
     f64,
     PolynomialDegree::Nearest,
@@ -350,68 +484,85 @@ bench_fast_async_resampler!(
     "fast async nearest 64"
 );
 
-#[cfg(target_arch = "x86_64")]
-criterion_group!(
-    benches,
-    bench_fftfixedin,
-    bench_fftfixedin_32,
-    bench_fast_async_septic_32,
-    bench_fast_async_quintic_32,
-    bench_fast_async_cubic_32,
-    bench_fast_async_linear_32,
-    bench_fast_async_nearest_32,
-    bench_fast_async_septic_64,
-    bench_fast_async_quintic_64,
-    bench_fast_async_cubic_64,
-    bench_fast_async_linear_64,
-    bench_fast_async_nearest_64,
-    bench_scalar_async_cubic_32,
-    bench_scalar_async_linear_32,
-    bench_scalar_async_nearest_32,
-    bench_scalar_async_cubic_64,
-    bench_scalar_async_linear_64,
-    bench_scalar_async_nearest_64,
-    bench_sse_async_cubic_32,
-    bench_sse_async_linear_32,
-    bench_sse_async_nearest_32,
-    bench_sse_async_cubic_64,
-    bench_sse_async_linear_64,
-    bench_sse_async_nearest_64,
-    bench_avx_async_cubic_32,
-    bench_avx_async_linear_32,
-    bench_avx_async_nearest_32,
-    bench_avx_async_cubic_64,
-    bench_avx_async_linear_64,
-    bench_avx_async_nearest_64,
-);
+#[cfg(feature = "f64")]
+fn bench_f64(c: &mut Criterion) {
+    bench_fftfixedin(c);
+    bench_fast_async_septic_64(c);
+    bench_fast_async_quintic_64(c);
+    bench_fast_async_cubic_64(c);
+    bench_fast_async_linear_64(c);
+    bench_fast_async_nearest_64(c);
+    bench_scalar_async_cubic_64(c);
+    bench_scalar_async_linear_64(c);
+    bench_scalar_async_nearest_64(c);
+    #[cfg(all(
+        target_arch = "x86_64",
+        feature = "simd_avx",
+        feature = "simd_sse3",
+        not(feature = "forbid_unsafe")
+    ))]
+    {
+        bench_sse_async_cubic_64(c);
+        bench_sse_async_linear_64(c);
+        bench_sse_async_nearest_64(c);
+        bench_avx_async_cubic_64(c);
+        bench_avx_async_linear_64(c);
+        bench_avx_async_nearest_64(c);
+    }
+    #[cfg(all(
+        target_arch = "aarch64",
+        feature = "simd_neon",
+        not(feature = "forbid_unsafe")
+    ))]
+    {
+        bench_neon_async_cubic_64(c);
+        bench_neon_async_linear_64(c);
+        bench_neon_async_nearest_64(c);
+    }
+}
 
-#[cfg(target_arch = "aarch64")]
-criterion_group!(
-    benches,
-    bench_fftfixedin,
-    bench_fftfixedin_32,
-    bench_fast_async_septic_32,
-    bench_fast_async_quintic_32,
-    bench_fast_async_cubic_32,
-    bench_fast_async_linear_32,
-    bench_fast_async_nearest_32,
-    bench_fast_async_septic_64,
-    bench_fast_async_quintic_64,
-    bench_fast_async_cubic_64,
-    bench_fast_async_linear_64,
-    bench_fast_async_nearest_64,
-    bench_scalar_async_cubic_32,
-    bench_scalar_async_linear_32,
-    bench_scalar_async_nearest_32,
-    bench_scalar_async_cubic_64,
-    bench_scalar_async_linear_64,
-    bench_scalar_async_nearest_64,
-    bench_neon_async_cubic_32,
-    bench_neon_async_linear_32,
-    bench_neon_async_nearest_32,
-    bench_neon_async_cubic_64,
-    bench_neon_async_linear_64,
-    bench_neon_async_nearest_64,
-);
+#[cfg(feature = "f32")]
+fn bench_f32(c: &mut Criterion) {
+    bench_fftfixedin_32(c);
+    bench_fast_async_septic_32(c);
+    bench_fast_async_quintic_32(c);
+    bench_fast_async_cubic_32(c);
+    bench_fast_async_linear_32(c);
+    bench_fast_async_nearest_32(c);
+    bench_scalar_async_cubic_32(c);
+    bench_scalar_async_linear_32(c);
+    bench_scalar_async_nearest_32(c);
+    #[cfg(all(
+        target_arch = "x86_64",
+        feature = "simd_avx",
+        feature = "simd_sse3",
+        not(feature = "forbid_unsafe")
+    ))]
+    {
+        bench_sse_async_cubic_32(c);
+        bench_sse_async_linear_32(c);
+        bench_sse_async_nearest_32(c);
+        bench_avx_async_cubic_32(c);
+        bench_avx_async_linear_32(c);
+        bench_avx_async_nearest_32(c);
+    }
+    #[cfg(all(
+        target_arch = "aarch64",
+        feature = "simd_neon",
+        not(feature = "forbid_unsafe")
+    ))]
+    {
+        bench_neon_async_cubic_32(c);
+        bench_neon_async_linear_32(c);
+        bench_neon_async_nearest_32(c);
+    }
+}
+
+#[cfg(all(feature = "f32", feature = "f64"))]
+criterion_group!(benches, bench_f64, bench_f32);
+#[cfg(all(feature = "f32", not(feature = "f64")))]
+criterion_group!(benches, bench_f32);
+#[cfg(all(feature = "f64", not(feature = "f32")))]
+criterion_group!(benches, bench_f64);
 
 criterion_main!(benches);
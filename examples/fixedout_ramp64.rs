@@ -118,13 +118,12 @@ fn main() {
     let window = WindowFunction::Blackman2;
 
     let f_cutoff = calculate_cutoff(sinc_len, window);
-    let params = SincInterpolationParameters {
-        sinc_len,
-        f_cutoff,
-        interpolation,
-        oversampling_factor,
-        window,
-    };
+    let mut params = SincInterpolationParameters::default();
+    params.sinc_len = sinc_len;
+    params.f_cutoff = f_cutoff;
+    params.interpolation = interpolation;
+    params.oversampling_factor = oversampling_factor;
+    params.window = window;
 
     let chunksize = 1024;
     let target_ratio = final_ratio / 100.0;
@@ -133,13 +133,12 @@ fn main() {
             let window = WindowFunction::Blackman2;
 
             let f_cutoff = calculate_cutoff(sinc_len, window);
-            let params = SincInterpolationParameters {
-                sinc_len,
-                f_cutoff,
-                interpolation,
-                oversampling_factor,
-                window,
-            };
+            let mut params = SincInterpolationParameters::default();
+            params.sinc_len = sinc_len;
+            params.f_cutoff = f_cutoff;
+            params.interpolation = interpolation;
+            params.oversampling_factor = oversampling_factor;
+            params.window = window;
             Box::new(SincFixedIn::<f64>::new(f_ratio, 1.1, params, 1024, channels).unwrap())
         }
         "SincFixedOut" => {
@@ -149,13 +148,12 @@ fn main() {
             let window = WindowFunction::Blackman2;
 
             let f_cutoff = calculate_cutoff(sinc_len, window);
-            let params = SincInterpolationParameters {
-                sinc_len,
-                f_cutoff,
-                interpolation,
-                oversampling_factor,
-                window,
-            };
+            let mut params = SincInterpolationParameters::default();
+            params.sinc_len = sinc_len;
+            params.f_cutoff = f_cutoff;
+            params.interpolation = interpolation;
+            params.oversampling_factor = oversampling_factor;
+            params.window = window;
             Box::new(SincFixedOut::<f64>::new(f_ratio, 1.1, params, 1024, channels).unwrap())
         }
         "FastFixedIn" => {
@@ -0,0 +1,394 @@
+use crate::error::{ResampleResult, ResamplerConstructionError};
+use crate::{process_wrapped, Resampler, ResamplerConfig, Sample};
+
+/// Weight of each new measurement in the smoothed latency estimate.
+const LATENCY_SMOOTHING: f64 = 0.05;
+/// Relative ratio correction per relative latency error.
+const LATENCY_GAIN: f64 = 0.1;
+
+/// A wrapper for an asynchronous [Resampler] that adjusts the resampling ratio
+/// to keep the latency of an input buffer, for example a jitter buffer, at a target value.
+///
+/// The latency is the number of input frames that have arrived but not yet been consumed by the resampler.
+/// Arriving frames are reported with [frames_arrived](AdaptiveLatency::frames_arrived),
+/// and the frames consumed by each call to [process_into_buffer](Resampler::process_into_buffer)
+/// are subtracted automatically.
+/// After each processed chunk, the relative resample ratio is updated via
+/// [set_resample_ratio_relative](Resampler::set_resample_ratio_relative) with ramping enabled.
+/// A latency above the target lowers the ratio so that the input is consumed faster,
+/// and a latency below the target raises it.
+/// The change of the relative ratio per chunk is limited to `max_slew`
+/// and the ratio is kept within `1 / max_relative_ratio` to `max_relative_ratio`.
+///
+/// The constructor returns an error if the wrapped resampler is synchronous,
+/// or if `max_relative_ratio` is larger than the maximum relative ratio of the wrapped resampler.
+/// ```
+/// use rubato::{AdaptiveLatency, FastFixedOut, PolynomialDegree, Resampler};
+/// let resampler = FastFixedOut::<f64>::new(1.0, 1.1, PolynomialDegree::Cubic, 480, 1).unwrap();
+/// let mut resampler = AdaptiveLatency::new(resampler, 1920, 1.01, 0.001).unwrap();
+/// // A packet of 960 frames arrives from the network.
+/// resampler.frames_arrived(960);
+/// let packet = vec![vec![0.0f64; resampler.input_frames_next()]; 1];
+/// let _waves_out = resampler.process(&packet, None).unwrap();
+/// println!("Current latency {} frames", resampler.latency());
+/// ```
+pub struct AdaptiveLatency<R> {
+    resampler: R,
+    target_latency: usize,
+    max_relative_ratio: f64,
+    max_slew: f64,
+    buffered: usize,
+    latency_average: f64,
+    relative_ratio: f64,
+}
+
+impl<R> AdaptiveLatency<R> {
+    /// Create a new AdaptiveLatency wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The asynchronous resampler to wrap.
+    /// - `target_latency`: Target latency, in input frames.
+    /// - `max_relative_ratio`: Maximum relative ratio, must be >= 1.0
+    ///   and not larger than the maximum of the wrapped resampler.
+    /// - `max_slew`: Maximum change of the relative ratio per processed chunk.
+    pub fn new<T>(
+        mut resampler: R,
+        target_latency: usize,
+        max_relative_ratio: f64,
+        max_slew: f64,
+    ) -> Result<Self, ResamplerConstructionError>
+    where
+        T: Sample,
+        R: Resampler<T>,
+    {
+        if max_relative_ratio < 1.0 {
+            return Err(ResamplerConstructionError::InvalidRelativeRatio(
+                max_relative_ratio,
+            ));
+        }
+        // Check that the wrapped resampler accepts the whole range of the latency control
+        let supported = resampler
+            .set_resample_ratio_relative(max_relative_ratio, false)
+            .is_ok()
+            && resampler
+                .set_resample_ratio_relative(1.0 / max_relative_ratio, false)
+                .is_ok();
+        if resampler.set_resample_ratio_relative(1.0, false).is_err() || !supported {
+            return Err(ResamplerConstructionError::UnsupportedRelativeRatio(
+                max_relative_ratio,
+            ));
+        }
+        debug!(
+            "Create new AdaptiveLatency, target_latency: {}, max_relative_ratio: {}, max_slew: {}",
+            target_latency, max_relative_ratio, max_slew
+        );
+        Ok(AdaptiveLatency {
+            resampler,
+            target_latency,
+            max_relative_ratio,
+            max_slew: max_slew.abs(),
+            buffered: 0,
+            latency_average: target_latency as f64,
+            relative_ratio: 1.0,
+        })
+    }
+
+    /// Report that a number of input frames has arrived, for example when a packet is received.
+    pub fn frames_arrived(&mut self, frames: usize) {
+        self.buffered += frames;
+    }
+
+    /// Set the current latency directly, for example after a buffer was flushed.
+    pub fn set_latency(&mut self, frames: usize) {
+        self.buffered = frames;
+        self.latency_average = frames as f64;
+    }
+
+    /// Get the current latency, as the number of input frames that have arrived but not yet been consumed.
+    pub fn latency(&self) -> usize {
+        self.buffered
+    }
+
+    /// Get the smoothed latency estimate that is used for adjusting the ratio, in input frames.
+    pub fn latency_average(&self) -> f64 {
+        self.latency_average
+    }
+
+    /// Get the target latency, in input frames.
+    pub fn target_latency(&self) -> usize {
+        self.target_latency
+    }
+
+    /// Set a new target latency, in input frames.
+    pub fn set_target_latency(&mut self, target_latency: usize) {
+        self.target_latency = target_latency;
+    }
+
+    /// Get the relative resample ratio that is currently applied.
+    pub fn relative_ratio(&self) -> f64 {
+        self.relative_ratio
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Update the latency estimate after `frames` input frames were consumed,
+    /// and calculate the new relative ratio.
+    fn update(&mut self, frames: usize) -> f64 {
+        self.buffered = self.buffered.saturating_sub(frames);
+        self.latency_average += LATENCY_SMOOTHING * (self.buffered as f64 - self.latency_average);
+        let target = (self.target_latency as f64).max(1.0);
+        let error = (self.latency_average - self.target_latency as f64) / target;
+        let wanted = (1.0 - LATENCY_GAIN * error)
+            .min(self.max_relative_ratio)
+            .max(1.0 / self.max_relative_ratio);
+        let new_ratio = wanted
+            .min(self.relative_ratio + self.max_slew)
+            .max(self.relative_ratio - self.max_slew);
+        trace!(
+            "AdaptiveLatency, latency: {}, average: {}, relative ratio: {}",
+            self.buffered,
+            self.latency_average,
+            new_ratio
+        );
+        new_ratio
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<T, Vin, Vout>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        T: Sample,
+        R: Resampler<T>,
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[T]>,
+    {
+        let (frames_in, frames_out) = process_wrapped(
+            &mut self.resampler,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            scratch,
+        )?;
+        let new_ratio = self.update(frames_in);
+        // The ratio is clamped to a range that the constructor checked,
+        // so the update can only fail at the limits because of rounding, and then the ratio is kept.
+        if new_ratio != self.relative_ratio
+            && self
+                .resampler
+                .set_resample_ratio_relative(new_ratio, true)
+                .is_ok()
+        {
+            self.relative_ratio = new_ratio;
+        }
+        Ok((frames_in, frames_out))
+    }
+}
+
+impl<T, R> Resampler<T> for AdaptiveLatency<R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler
+            .set_resample_ratio_relative(rel_ratio, ramp)?;
+        self.relative_ratio = rel_ratio;
+        Ok(())
+    }
+
+    /// Change the maximum relative ratio of the wrapped resampler,
+    /// see [Resampler::set_max_resample_ratio_relative].
+    /// The range used by the latency control is changed to the same value.
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)?;
+        self.max_relative_ratio = max_resample_ratio_relative;
+        Ok(())
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    /// Load the history of the wrapped resampler. Like a reset, this resets the relative ratio.
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)?;
+        self.relative_ratio = 1.0;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "AdaptiveLatency")
+    }
+
+    /// Reset the wrapped resampler and the relative ratio. The latency estimate is kept.
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.relative_ratio = 1.0;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{AdaptiveLatency, FastFixedOut, FftFixedIn, PolynomialDegree, Resampler};
+
+    fn make_resampler() -> AdaptiveLatency<FastFixedOut<f64>> {
+        let resampler =
+            FastFixedOut::<f64>::new(1.0, 1.1, PolynomialDegree::Linear, 256, 1).unwrap();
+        AdaptiveLatency::new(resampler, 1024, 1.05, 0.001).unwrap()
+    }
+
+    fn process_chunk(resampler: &mut AdaptiveLatency<FastFixedOut<f64>>, arrived: usize) -> f64 {
+        resampler.frames_arrived(arrived);
+        let waves = vec![vec![0.0f64; resampler.input_frames_next()]; 1];
+        resampler.process(&waves, None).unwrap();
+        resampler.relative_ratio()
+    }
+
+    #[test]
+    fn stable_at_target() {
+        let mut resampler = make_resampler();
+        resampler.set_latency(1024);
+        for _ in 0..20 {
+            let frames = resampler.input_frames_next();
+            let ratio = process_chunk(&mut resampler, frames);
+            assert!((ratio - 1.0).abs() < 1.0e-3, "ratio {}", ratio);
+        }
+    }
+
+    #[test]
+    fn too_high_latency() {
+        let mut resampler = make_resampler();
+        resampler.set_latency(4096);
+        let mut prev_ratio = 1.0;
+        for _ in 0..20 {
+            let frames = resampler.input_frames_next();
+            let ratio = process_chunk(&mut resampler, frames);
+            assert!(ratio < 1.0);
+            assert!(prev_ratio - ratio <= 0.001 + 1.0e-12);
+            prev_ratio = ratio;
+        }
+        assert!(resampler.latency_average() > 1024.0);
+        // The input is now consumed faster than it arrives
+        assert!(resampler.input_frames_next() > 256);
+    }
+
+    #[test]
+    fn too_low_latency() {
+        let mut resampler = make_resampler();
+        resampler.set_latency(0);
+        for _ in 0..100 {
+            process_chunk(&mut resampler, 256);
+        }
+        let ratio = resampler.relative_ratio();
+        assert!(ratio > 1.0 && ratio <= 1.05, "ratio {}", ratio);
+    }
+
+    #[test]
+    fn invalid_relative_ratio() {
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        assert!(AdaptiveLatency::new(resampler, 1024, 0.5, 0.001).is_err());
+    }
+
+    #[test]
+    fn unsupported_resamplers() {
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        let err = AdaptiveLatency::new(resampler, 1024, 1.05, 0.001)
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), 116);
+        let resampler =
+            FastFixedOut::<f64>::new(1.0, 1.1, PolynomialDegree::Linear, 256, 1).unwrap();
+        let err = AdaptiveLatency::new(resampler, 1024, 1.2, 0.001)
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), 116);
+        let resampler =
+            FastFixedOut::<f64>::new(1.0, 1.1, PolynomialDegree::Linear, 256, 1).unwrap();
+        assert!(AdaptiveLatency::new(resampler, 1024, 1.1, 0.001).is_ok());
+    }
+}
@@ -1,5 +1,14 @@
+use crate::denormal::flush_scope;
 use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
-use crate::{update_mask_from_buffers, validate_buffers, Resampler, Sample};
+use crate::interpolation::{
+    exact_high_rates, fixed_out_buffer_sizes, ratio_from_sample_rates, validate_max_relative_ratio,
+    ChunkState, RationalPhase,
+};
+use crate::{
+    buffer_size, check_memory_limit, heap_size, heap_size_nested, load_history, ramp_input_frames,
+    validate_and_update_mask, validate_history, Contiguous, InputChannels, Resampler,
+    ResamplerAlgorithm, ResamplerConfig, Sample, Split,
+};
 
 const POLYNOMIAL_LEN_U: usize = 8;
 const POLYNOMIAL_LEN_I: isize = 8;
@@ -11,9 +20,38 @@ macro_rules! t {
     };
 }
 
+/// Get `len` frames of channel `chan` of the buffer, starting at `start`.
+/// The callers make sure that the frames are within the buffer,
+/// and the bounds are only checked when the `forbid_unsafe` feature is enabled.
+#[inline(always)]
+fn frames<T>(buffer: &[Vec<T>], chan: usize, start: usize, len: usize) -> &[T] {
+    #[cfg(not(feature = "forbid_unsafe"))]
+    unsafe {
+        buffer.get_unchecked(chan).get_unchecked(start..start + len)
+    }
+    #[cfg(feature = "forbid_unsafe")]
+    &buffer[chan][start..start + len]
+}
+
+/// Write frame `n` of output channel `chan`, with the same bounds checking as [frames].
+#[inline(always)]
+fn write_frame<T, V: AsMut<[T]>>(wave_out: &mut [V], chan: usize, n: usize, value: T) {
+    #[cfg(not(feature = "forbid_unsafe"))]
+    unsafe {
+        *wave_out
+            .get_unchecked_mut(chan)
+            .as_mut()
+            .get_unchecked_mut(n) = value;
+    }
+    #[cfg(feature = "forbid_unsafe")]
+    {
+        wave_out[chan].as_mut()[n] = value;
+    }
+}
+
 /// Degree of the polynomial used for interpolation.
 /// A higher degree gives a higher quality result, while taking longer to compute.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PolynomialDegree {
     /// Septic polynomial, fitted using 8 sample points.
     Septic,
@@ -48,9 +86,12 @@ pub struct FastFixedIn<T> {
     resample_ratio_original: f64,
     target_ratio: f64,
     max_relative_ratio: f64,
+    rational_phase: Option<RationalPhase>,
     buffer: Vec<Vec<T>>,
     interpolation: PolynomialDegree,
     channel_mask: Vec<bool>,
+    unity_bypass: bool,
+    zero_latency: bool,
 }
 
 /// An asynchronous resampler that return a fixed number of audio frames.
@@ -78,9 +119,12 @@ pub struct FastFixedOut<T> {
     resample_ratio_original: f64,
     target_ratio: f64,
     max_relative_ratio: f64,
+    rational_phase: Option<RationalPhase>,
     buffer: Vec<Vec<T>>,
     interpolation: PolynomialDegree,
     channel_mask: Vec<bool>,
+    unity_bypass: bool,
+    ratio_envelope: Vec<f64>,
 }
 
 /// Perform septic polynomial interpolation to get value at x.
@@ -181,6 +225,47 @@ where
     yvals[0] + x * (yvals[1] - yvals[0])
 }
 
+/// Get the weights that the interpolation of degree `degree` applies to the input frames
+/// around an output frame at the position `fraction`, for `0 <= fraction < 1`,
+/// between the input frames at 0 and 1.
+/// The weights are given for the input frames at -3 to 4 for [PolynomialDegree::Septic],
+/// -2 to 3 for Quintic, -1 to 2 for Cubic, 0 and 1 for Linear, and 0 for Nearest.
+pub fn polynomial_weights<T>(degree: &PolynomialDegree, fraction: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    let interp: fn(T, &[T]) -> T = match degree {
+        PolynomialDegree::Septic => interp_septic,
+        PolynomialDegree::Quintic => interp_quintic,
+        PolynomialDegree::Cubic => interp_cubic,
+        PolynomialDegree::Linear => interp_lin,
+        PolynomialDegree::Nearest => return vec![T::one()],
+    };
+    let nbr_points = match degree {
+        PolynomialDegree::Septic => 8,
+        PolynomialDegree::Quintic => 6,
+        PolynomialDegree::Cubic => 4,
+        _ => 2,
+    };
+    // The interpolation is linear in the input values, so interpolating a unit impulse
+    // gives the weight of the frame at the position of the impulse.
+    let mut impulse = vec![T::zero(); nbr_points];
+    (0..nbr_points)
+        .map(|n| {
+            impulse[n] = T::one();
+            let weight = interp(T::coerce(fraction), &impulse);
+            impulse[n] = T::zero();
+            weight
+        })
+        .collect()
+}
+
+/// Returns true if the output frames of a chunk fall exactly on input frames,
+/// so that the input can be copied instead of interpolated.
+fn can_bypass(enabled: bool, resample_ratio: f64, target_ratio: f64, idx: f64) -> bool {
+    enabled && resample_ratio == 1.0 && target_ratio == 1.0 && idx.fract() == 0.0
+}
+
 fn validate_ratios(
     resample_ratio: f64,
     max_resample_ratio_relative: f64,
@@ -222,7 +307,8 @@ where
 
         validate_ratios(resample_ratio, max_resample_ratio_relative)?;
 
-        let buffer = vec![vec![T::zero(); chunk_size + 2 * POLYNOMIAL_LEN_U]; nbr_channels];
+        // The extra space at the end is used for extrapolated frames in the zero latency mode
+        let buffer = vec![vec![T::zero(); chunk_size + 4 * POLYNOMIAL_LEN_U]; nbr_channels];
 
         let channel_mask = vec![true; nbr_channels];
 
@@ -234,39 +320,238 @@ where
             resample_ratio_original: resample_ratio,
             target_ratio: resample_ratio,
             max_relative_ratio: max_resample_ratio_relative,
+            rational_phase: None,
             buffer,
             interpolation: interpolation_type,
             channel_mask,
+            unity_bypass: false,
+            zero_latency: false,
         })
     }
+
+    /// Create a new FastFixedIn like [FastFixedIn::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory.
+    ///
+    /// The memory of the buffers is estimated before anything is allocated,
+    /// and [ResamplerConstructionError::MemoryLimitExceeded] is returned if the estimate exceeds the limit.
+    /// This avoids allocating gigabytes for an absurd chunk size,
+    /// for example when the parameters are given by a user.
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0.
+    /// - `interpolation_type`: Degree of polynomial used for interpolation, see [PolynomialDegree].
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: PolynomialDegree,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let required = buffer_size::<T>(
+            nbr_channels,
+            chunk_size.saturating_add(4 * POLYNOMIAL_LEN_U),
+        )
+        .saturating_add(nbr_channels);
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            interpolation_type,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new FastFixedIn from a pair of sample rates, that may be fractional.
+    ///
+    /// This is useful for rates that are not whole numbers,
+    /// like the pull-down rates 44100 * 1000 / 1001 Hz and 48000 * 1000 / 1001 Hz.
+    /// The resample ratio is `sample_rate_output / sample_rate_input`.
+    /// When both rates are whole numbers and one of them is at least [HIGH_SAMPLE_RATE](crate::HIGH_SAMPLE_RATE),
+    /// the positions are tracked exactly, like after calling `set_exact_ratio`.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be finite and > 0.
+    /// - `sample_rate_output`: Output sample rate, must be finite and > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the starting ratio, must be >= 1.0.
+    /// - `interpolation_type`: Degree of polynomial used for interpolation, see [PolynomialDegree].
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_sample_rates(
+        sample_rate_input: f64,
+        sample_rate_output: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: PolynomialDegree,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resample_ratio = ratio_from_sample_rates(sample_rate_input, sample_rate_output)?;
+        let mut resampler = Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            interpolation_type,
+            chunk_size,
+            nbr_channels,
+        )?;
+        if let Some((input, output)) = exact_high_rates(sample_rate_input, sample_rate_output) {
+            debug!(
+                "High sample rates {} and {} Hz, tracking the positions exactly",
+                input, output
+            );
+            resampler
+                .set_exact_ratio(input, output)
+                .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        }
+        Ok(resampler)
+    }
+
+    /// Enable or disable copying the input directly to the output while the ratio is 1.0.
+    ///
+    /// At a ratio of exactly 1.0, the interpolation only reproduces the input frames.
+    /// With the bypass enabled, they are instead copied directly, which saves nearly all the processing.
+    /// The bypass is used for chunks where both the current and the target ratio are 1.0,
+    /// and the output frames fall exactly on input frames.
+    /// This is the case for a resampler created with a ratio of 1.0, until the ratio is changed,
+    /// and again after it has been reset.
+    /// When the ratio is changed away from 1.0, the interpolation takes over without any jump in time.
+    /// The bypass is disabled by default.
+    pub fn set_unity_bypass(&mut self, enabled: bool) {
+        self.unity_bypass = enabled;
+    }
+
+    /// Returns true if copying the input directly to the output at a ratio of 1.0 is enabled.
+    pub fn unity_bypass(&self) -> bool {
+        self.unity_bypass
+    }
+
+    /// Get the weights that the interpolation applies to the input frames around a point,
+    /// for an output frame at the fractional position `fraction` between two input frames.
+    /// See [polynomial_weights] for the order of the weights.
+    pub fn polynomial_weights(&self, fraction: f64) -> Vec<T> {
+        polynomial_weights(&self.interpolation, fraction)
+    }
+
+    /// Enable or disable the zero latency mode, and reset the resampler.
+    ///
+    /// Each output frame is normally calculated once all the input frames around it are available,
+    /// which delays the output by a few frames.
+    /// In the zero latency mode, the output frames are instead calculated up to about the newest input frame,
+    /// and the few following frames needed by the polynomial are extrapolated from the last input frames.
+    /// This removes the delay, at the cost of a somewhat lower quality for the output frames
+    /// near the end of each chunk.
+    /// The [output_delay](Resampler::output_delay) is zero in this mode.
+    ///
+    /// Changing the mode changes the timing of the output, and the resampler is therefore reset.
+    /// The zero latency mode is disabled by default.
+    pub fn set_zero_latency(&mut self, enabled: bool) {
+        self.zero_latency = enabled;
+        self.reset();
+    }
+
+    /// Returns true if the zero latency mode is enabled.
+    pub fn zero_latency(&self) -> bool {
+        self.zero_latency
+    }
+
+    /// Get the position of the output frame before the first one, after creating or resetting.
+    fn initial_index(&self) -> f64 {
+        if self.zero_latency {
+            // The first output frame is at the first input frame
+            -1.0 / self.resample_ratio_original
+        } else {
+            -(POLYNOMIAL_LEN_I / 2) as f64
+        }
+    }
+
+    /// Get the number of input frames that are held back at the end of each chunk,
+    /// waiting for the following frames.
+    fn held_back_frames(&self) -> usize {
+        if self.zero_latency {
+            0
+        } else {
+            POLYNOMIAL_LEN_U
+        }
+    }
+
+    /// Extrapolate the input frames following the newest ones, for the zero latency mode.
+    /// The slope of the last two frames is continued, and halved for each extrapolated frame.
+    fn extrapolate(buf: &mut [T], end: usize) {
+        let mut value = buf[end - 1];
+        let mut slope = buf[end - 1] - buf[end - 2];
+        for sample in buf[end..end + 2 * POLYNOMIAL_LEN_U].iter_mut() {
+            slope *= t!(0.5);
+            value += slope;
+            *sample = value;
+        }
+    }
+
+    /// Set an exact resample ratio, given by a pair of integer sample rates.
+    ///
+    /// The position in the input is then tracked with integer arithmetic,
+    /// so that the output stays aligned with the ratio `sample_rate_output / sample_rate_input`
+    /// over streams of any length, instead of slowly drifting because of floating point rounding.
+    /// The ratio is applied from the start of the next chunk, without ramping,
+    /// and must be within the range allowed by the constructor.
+    /// Changing the ratio in any other way, or resetting the resampler, ends the exact tracking.
+    pub fn set_exact_ratio(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> ResampleResult<()> {
+        self.set_resample_ratio(sample_rate_output as f64 / sample_rate_input as f64, false)?;
+        self.rational_phase = Some(RationalPhase::new(
+            sample_rate_input,
+            sample_rate_output,
+            self.last_index,
+        ));
+        Ok(())
+    }
+
+    /// Get the number of input frames needed to produce at least `output_frames` frames of output,
+    /// starting from the current state of the resampler.
+    ///
+    /// This is intended for pull-based hosts that must deliver a given number of output frames.
+    /// The input is consumed in chunks of `chunk_size` frames, so the result is a multiple of the chunk size.
+    /// A ratio change that is being ramped is included in the calculation.
+    /// The result is valid until the resample ratio is changed, or the resampler is reset.
+    pub fn input_frames_needed_for(&self, output_frames: usize) -> usize {
+        ChunkState {
+            chunk_size: self.chunk_size,
+            filter_len: self.held_back_frames(),
+            last_index: self.last_index,
+            resample_ratio: self.resample_ratio,
+            target_ratio: self.target_ratio,
+        }
+        .fixed_in_frames_needed(output_frames)
+    }
 }
 
-impl<T> Resampler<T> for FastFixedIn<T>
+impl<T> FastFixedIn<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
         &mut self,
-        wave_in: &[Vin],
+        wave_in: &I,
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
         // Set length to chunksize*ratio plus a safety margin of 10 elements.
         let needed_len = (self.chunk_size as f64
             * (0.5 * self.resample_ratio + 0.5 * self.target_ratio)
             + 10.0) as usize;
 
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.chunk_size,
             needed_len,
         )?;
@@ -276,10 +561,18 @@ where
             buf.copy_within(self.chunk_size..self.chunk_size + 2 * POLYNOMIAL_LEN_U, 0);
         }
 
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                self.buffer[chan][2 * POLYNOMIAL_LEN_U..2 * POLYNOMIAL_LEN_U + self.chunk_size]
-                    .copy_from_slice(&wave_in[chan].as_ref()[..self.chunk_size]);
+        for (chan, chan_in, _) in layout.active_channels(&self.channel_mask) {
+            wave_in.copy_frames(
+                chan_in,
+                0,
+                &mut self.buffer[chan]
+                    [2 * POLYNOMIAL_LEN_U..2 * POLYNOMIAL_LEN_U + self.chunk_size],
+            );
+            if self.zero_latency {
+                Self::extrapolate(
+                    &mut self.buffer[chan],
+                    2 * POLYNOMIAL_LEN_U + self.chunk_size,
+                );
             }
         }
 
@@ -288,8 +581,9 @@ where
         let approximate_nbr_frames =
             self.chunk_size as f64 * (0.5 * self.resample_ratio + 0.5 * self.target_ratio);
         let t_ratio_increment = (t_ratio_end - t_ratio) / approximate_nbr_frames;
-        let end_idx =
-            self.chunk_size as isize - (POLYNOMIAL_LEN_I + 1) - t_ratio_end.ceil() as isize;
+        let end_idx = self.chunk_size as isize
+            - (self.held_back_frames() as isize + 1)
+            - t_ratio_end.ceil() as isize;
         //println!(
         //    "start ratio {}, end_ratio {}, frames {}, t_increment {}",
         //    t_ratio,
@@ -302,7 +596,24 @@ where
 
         let mut n = 0;
 
+        let bypass = can_bypass(
+            self.unity_bypass,
+            self.resample_ratio,
+            self.target_ratio,
+            idx,
+        );
+        // Flush denormals in the inner loops only, if requested by FlushDenormals
+        let flush = flush_scope();
         match self.interpolation {
+            _ if bypass => {
+                n = (end_idx as f64 - idx).max(0.0) as usize;
+                let start = (idx as isize + 1 + 2 * POLYNOMIAL_LEN_I) as usize;
+                for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                    wave_out[chan_out].as_mut()[..n]
+                        .copy_from_slice(&self.buffer[chan][start..start + n]);
+                }
+                idx += n as f64;
+            }
             PolynomialDegree::Septic => {
                 while idx < end_idx as f64 {
                     t_ratio += t_ratio_increment;
@@ -311,19 +622,14 @@ where
                     let start_idx = idx_floor as isize - 3;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 8) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_septic(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            8,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_septic(frac_offset, buf));
                     }
                     n += 1;
                 }
@@ -336,19 +642,14 @@ where
                     let start_idx = idx_floor as isize - 2;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 6) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_quintic(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            6,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_quintic(frac_offset, buf));
                     }
                     n += 1;
                 }
@@ -361,19 +662,14 @@ where
                     let start_idx = idx_floor as isize - 1;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 4) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_cubic(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            4,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_cubic(frac_offset, buf));
                     }
                     n += 1;
                 }
@@ -386,19 +682,14 @@ where
                     let start_idx = idx_floor as isize;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 2) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_lin(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            2,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_lin(frac_offset, buf));
                     }
                     n += 1;
                 }
@@ -408,27 +699,27 @@ where
                     t_ratio += t_ratio_increment;
                     idx += t_ratio;
                     let start_idx = idx.floor() as isize;
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let point = self
-                                    .buffer
-                                    .get_unchecked(chan)
-                                    .get_unchecked((start_idx + 2 * POLYNOMIAL_LEN_I) as usize);
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = *point;
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let point = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            1,
+                        )[0];
+                        write_frame(wave_out, chan_out, n, point);
                     }
                     n += 1;
                 }
             }
         }
 
+        drop(flush);
+
         // store last index for next iteration
-        self.last_index = idx - self.chunk_size as f64;
+        self.last_index = match self.rational_phase.as_mut() {
+            Some(phase) => phase.advance(n, self.chunk_size),
+            None => idx - self.chunk_size as f64,
+        };
         self.resample_ratio = self.target_ratio;
         trace!(
             "Resampling channels {:?}, {} frames in, {} frames out",
@@ -438,6 +729,29 @@ where
         );
         Ok((self.chunk_size, n))
     }
+}
+
+impl<T> Resampler<T> for FastFixedIn<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask)
+    }
 
     fn output_frames_max(&self) -> usize {
         // Set length to chunksize*ratio plus a safety margin of 10 elements.
@@ -451,9 +765,19 @@ where
     }
 
     fn output_delay(&self) -> usize {
+        if self.zero_latency {
+            return 0;
+        }
         (POLYNOMIAL_LEN_U as f64 * self.resample_ratio / 2.0) as usize
     }
 
+    fn input_delay(&self) -> usize {
+        if self.zero_latency {
+            return 0;
+        }
+        POLYNOMIAL_LEN_U / 2
+    }
+
     fn nbr_channels(&self) -> usize {
         self.nbr_channels
     }
@@ -471,6 +795,7 @@ where
         if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
             && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
         {
+            self.rational_phase = None;
             if !ramp {
                 self.resample_ratio = new_ratio;
             }
@@ -490,12 +815,82 @@ where
         self.set_resample_ratio(new_ratio, ramp)
     }
 
+    /// Change the maximum relative ratio, see [Resampler::set_max_resample_ratio_relative].
+    /// The internal buffer does not depend on the ratio, only the output buffers need to be longer
+    /// for a wider range.
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        validate_max_relative_ratio(
+            max_resample_ratio_relative,
+            self.resample_ratio_original,
+            self.resample_ratio,
+            self.target_ratio,
+        )?;
+        trace!(
+            "Change maximum relative ratio to {}",
+            max_resample_ratio_relative
+        );
+        self.max_relative_ratio = max_resample_ratio_relative;
+        Ok(())
+    }
+
+    /// Change the chunk size, see [Resampler::set_chunk_size].
+    /// The input frames that are kept for interpolation are moved to the end of
+    /// the resized internal buffer.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        let history_len = 2 * POLYNOMIAL_LEN_U;
+        for buf in self.buffer.iter_mut() {
+            buf.copy_within(self.chunk_size..self.chunk_size + history_len, 0);
+            buf.resize(chunk_size + 4 * POLYNOMIAL_LEN_U, T::zero());
+            buf.copy_within(0..history_len, chunk_size);
+        }
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    /// Load the history, see [Resampler::set_history].
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        validate_history(self.nbr_channels, history)?;
+        self.reset();
+        load_history(
+            &mut self.buffer,
+            self.chunk_size,
+            2 * POLYNOMIAL_LEN_U,
+            history,
+        );
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        heap_size_nested(&self.buffer) + heap_size(&self.channel_mask)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "FastFixedIn",
+            ResamplerAlgorithm::Polynomial {
+                degree: self.interpolation,
+            },
+            Some(self.resample_ratio),
+        )
+    }
+
     fn reset(&mut self) {
+        self.rational_phase = None;
         self.buffer
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
         self.channel_mask.iter_mut().for_each(|val| *val = true);
-        self.last_index = -(POLYNOMIAL_LEN_I / 2) as f64;
+        self.last_index = self.initial_index();
         self.resample_ratio = self.resample_ratio_original;
         self.target_ratio = self.resample_ratio_original;
     }
@@ -526,11 +921,12 @@ where
         );
         validate_ratios(resample_ratio, max_resample_ratio_relative)?;
 
-        let needed_input_size =
-            (chunk_size as f64 / resample_ratio).ceil() as usize + 2 + POLYNOMIAL_LEN_U / 2;
-        let buffer_channel_length = ((max_resample_ratio_relative + 1.0) * needed_input_size as f64)
-            as usize
-            + 2 * POLYNOMIAL_LEN_U;
+        let (needed_input_size, buffer_channel_length) = fixed_out_buffer_sizes(
+            chunk_size,
+            resample_ratio,
+            max_resample_ratio_relative,
+            POLYNOMIAL_LEN_U,
+        );
         let buffer = vec![vec![T::zero(); buffer_channel_length]; nbr_channels];
         let channel_mask = vec![true; nbr_channels];
 
@@ -544,34 +940,223 @@ where
             resample_ratio_original: resample_ratio,
             target_ratio: resample_ratio,
             max_relative_ratio: max_resample_ratio_relative,
+            rational_phase: None,
             buffer,
             interpolation: interpolation_type,
             channel_mask,
+            unity_bypass: false,
+
+            ratio_envelope: Vec::with_capacity(chunk_size),
         })
     }
+
+    /// Create a new FastFixedOut like [FastFixedOut::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory, see [FastFixedIn::try_new_with_limit].
+    /// The input buffer of FastFixedOut grows with `max_resample_ratio_relative`,
+    /// which is included in the estimate.
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0.
+    /// - `interpolation_type`: Degree of polynomial used for interpolation, see [PolynomialDegree].
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: PolynomialDegree,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        let (_, buffer_frames) = fixed_out_buffer_sizes(
+            chunk_size,
+            resample_ratio,
+            max_resample_ratio_relative,
+            POLYNOMIAL_LEN_U,
+        );
+        let required = buffer_size::<T>(nbr_channels, buffer_frames)
+            .saturating_add(nbr_channels)
+            .saturating_add(buffer_size::<f64>(1, chunk_size));
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            interpolation_type,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new FastFixedOut from a pair of sample rates, that may be fractional.
+    ///
+    /// This is useful for rates that are not whole numbers,
+    /// like the pull-down rates 44100 * 1000 / 1001 Hz and 48000 * 1000 / 1001 Hz.
+    /// The resample ratio is `sample_rate_output / sample_rate_input`.
+    /// When both rates are whole numbers and one of them is at least [HIGH_SAMPLE_RATE](crate::HIGH_SAMPLE_RATE),
+    /// the positions are tracked exactly, like after calling `set_exact_ratio`.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be finite and > 0.
+    /// - `sample_rate_output`: Output sample rate, must be finite and > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the starting ratio, must be >= 1.0.
+    /// - `interpolation_type`: Degree of polynomial used for interpolation, see [PolynomialDegree].
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_sample_rates(
+        sample_rate_input: f64,
+        sample_rate_output: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: PolynomialDegree,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resample_ratio = ratio_from_sample_rates(sample_rate_input, sample_rate_output)?;
+        let mut resampler = Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            interpolation_type,
+            chunk_size,
+            nbr_channels,
+        )?;
+        if let Some((input, output)) = exact_high_rates(sample_rate_input, sample_rate_output) {
+            debug!(
+                "High sample rates {} and {} Hz, tracking the positions exactly",
+                input, output
+            );
+            resampler
+                .set_exact_ratio(input, output)
+                .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        }
+        Ok(resampler)
+    }
+
+    /// Enable or disable copying the input directly to the output while the ratio is 1.0.
+    ///
+    /// At a ratio of exactly 1.0, the interpolation only reproduces the input frames.
+    /// With the bypass enabled, they are instead copied directly, which saves nearly all the processing.
+    /// The bypass is used for chunks where both the current and the target ratio are 1.0,
+    /// and the output frames fall exactly on input frames.
+    /// This is the case for a resampler created with a ratio of 1.0, until the ratio is changed,
+    /// and again after it has been reset.
+    /// When the ratio is changed away from 1.0, the interpolation takes over without any jump in time.
+    /// The bypass is disabled by default.
+    pub fn set_unity_bypass(&mut self, enabled: bool) {
+        self.unity_bypass = enabled;
+    }
+
+    /// Returns true if copying the input directly to the output at a ratio of 1.0 is enabled.
+    pub fn unity_bypass(&self) -> bool {
+        self.unity_bypass
+    }
+
+    /// Get the weights that the interpolation applies to the input frames around a point,
+    /// for an output frame at the fractional position `fraction` between two input frames.
+    /// See [polynomial_weights] for the order of the weights.
+    pub fn polynomial_weights(&self, fraction: f64) -> Vec<T> {
+        polynomial_weights(&self.interpolation, fraction)
+    }
+
+    /// Set an exact resample ratio, given by a pair of integer sample rates.
+    ///
+    /// The position in the input is then tracked with integer arithmetic,
+    /// so that the output stays aligned with the ratio `sample_rate_output / sample_rate_input`
+    /// over streams of any length, instead of slowly drifting because of floating point rounding.
+    /// The ratio is applied from the start of the next chunk, without ramping,
+    /// and must be within the range allowed by the constructor.
+    /// Changing the ratio in any other way, or resetting the resampler, ends the exact tracking.
+    pub fn set_exact_ratio(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> ResampleResult<()> {
+        self.set_resample_ratio(sample_rate_output as f64 / sample_rate_input as f64, false)?;
+        self.rational_phase = Some(RationalPhase::new(
+            sample_rate_input,
+            sample_rate_output,
+            self.last_index,
+        ));
+        Ok(())
+    }
+
+    /// Get the number of input frames needed to produce at least `output_frames` frames of output,
+    /// starting from the current state of the resampler.
+    ///
+    /// This is intended for pull-based hosts that must deliver a given number of output frames.
+    /// The output is produced in chunks of `chunk_size` frames, and the result is the total
+    /// number of input frames for the chunks needed to reach `output_frames`.
+    /// The first of these chunks needs [input_frames_next](Resampler::input_frames_next) frames.
+    /// A ratio change that is being ramped is included in the calculation.
+    /// The result is valid until the resample ratio is changed, or the resampler is reset.
+    pub fn input_frames_needed_for(&self, output_frames: usize) -> usize {
+        ChunkState {
+            chunk_size: self.chunk_size,
+            filter_len: POLYNOMIAL_LEN_U,
+            last_index: self.last_index,
+            resample_ratio: self.resample_ratio,
+            target_ratio: self.target_ratio,
+        }
+        .fixed_out_frames_needed(output_frames, self.needed_input_size)
+    }
+
+    /// Set the resample ratio separately for each output frame of the next chunk.
+    ///
+    /// The `ratios` slice must contain one absolute ratio per output frame, `chunk_size` values in total,
+    /// and each ratio must be within the range allowed by [Resampler::set_resample_ratio].
+    /// The envelope only applies to the next chunk, and the last ratio is kept after that.
+    /// See [SincFixedOut::set_ratio_envelope](crate::SincFixedOut::set_ratio_envelope) for details.
+    pub fn set_ratio_envelope(&mut self, ratios: &[f64]) -> ResampleResult<()> {
+        if ratios.len() != self.chunk_size {
+            return Err(ResampleError::WrongEnvelopeLength {
+                expected: self.chunk_size,
+                actual: ratios.len(),
+            });
+        }
+        if let Some(ratio) = ratios.iter().find(|ratio| {
+            !(**ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio
+                && **ratio / self.resample_ratio_original <= self.max_relative_ratio)
+        }) {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: *ratio,
+                original: self.resample_ratio_original,
+                max_relative_ratio: self.max_relative_ratio,
+            });
+        }
+        trace!(
+            "Set ratio envelope, {} to {}",
+            ratios[0],
+            ratios[ratios.len() - 1]
+        );
+        self.ratio_envelope.clear();
+        self.ratio_envelope.extend_from_slice(ratios);
+        self.rational_phase = None;
+        let t_total: f64 = ratios.iter().map(|ratio| 1.0 / ratio).sum();
+        self.resample_ratio = ratios[ratios.len() - 1];
+        self.target_ratio = self.resample_ratio;
+        self.needed_input_size =
+            ((self.last_index + t_total + POLYNOMIAL_LEN_U as f64).ceil() + 2.0) as usize;
+        Ok(())
+    }
 }
 
-impl<T> Resampler<T> for FastFixedOut<T>
+impl<T> FastFixedOut<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
         &mut self,
-        wave_in: &[Vin],
+        wave_in: &I,
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.needed_input_size,
             self.chunk_size,
         )?;
@@ -583,14 +1168,14 @@ where
         }
         self.current_buffer_fill = self.needed_input_size;
 
-        for (chan, wave_in) in wave_in
-            .iter()
-            .enumerate()
-            .filter(|(chan, _)| self.channel_mask[*chan])
-        {
-            debug_assert!(self.chunk_size <= wave_out[chan].as_mut().len());
-            self.buffer[chan][2 * POLYNOMIAL_LEN_U..2 * POLYNOMIAL_LEN_U + self.needed_input_size]
-                .copy_from_slice(&wave_in.as_ref()[..self.needed_input_size]);
+        for (chan, chan_in, chan_out) in layout.active_channels(&self.channel_mask) {
+            debug_assert!(self.chunk_size <= wave_out[chan_out].as_mut().len());
+            wave_in.copy_frames(
+                chan_in,
+                0,
+                &mut self.buffer[chan]
+                    [2 * POLYNOMIAL_LEN_U..2 * POLYNOMIAL_LEN_U + self.needed_input_size],
+            );
         }
 
         let mut idx = self.last_index;
@@ -598,135 +1183,154 @@ where
         let t_ratio_end = 1.0 / self.target_ratio;
         let t_ratio_increment = (t_ratio_end - t_ratio) / self.chunk_size as f64;
 
+        let bypass = self.ratio_envelope.is_empty()
+            && can_bypass(
+                self.unity_bypass,
+                self.resample_ratio,
+                self.target_ratio,
+                idx,
+            );
+        // Flush denormals in the inner loops only, if requested by FlushDenormals
+        let flush = flush_scope();
         match self.interpolation {
+            _ if bypass => {
+                let start = (idx as isize + 1 + 2 * POLYNOMIAL_LEN_I) as usize;
+                for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                    wave_out[chan_out].as_mut()[..self.chunk_size]
+                        .copy_from_slice(&self.buffer[chan][start..start + self.chunk_size]);
+                }
+                idx += self.chunk_size as f64;
+            }
             PolynomialDegree::Septic => {
                 for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
+                    if self.ratio_envelope.is_empty() {
+                        t_ratio += t_ratio_increment;
+                        idx += t_ratio;
+                    } else {
+                        idx += 1.0 / self.ratio_envelope[n];
+                    }
                     let idx_floor = idx.floor();
                     let start_idx = idx_floor as isize - 3;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 8) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_septic(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            8,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_septic(frac_offset, buf));
                     }
                 }
             }
             PolynomialDegree::Quintic => {
                 for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
+                    if self.ratio_envelope.is_empty() {
+                        t_ratio += t_ratio_increment;
+                        idx += t_ratio;
+                    } else {
+                        idx += 1.0 / self.ratio_envelope[n];
+                    }
                     let idx_floor = idx.floor();
                     let start_idx = idx_floor as isize - 2;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 6) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_quintic(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            6,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_quintic(frac_offset, buf));
                     }
                 }
             }
             PolynomialDegree::Cubic => {
                 for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
+                    if self.ratio_envelope.is_empty() {
+                        t_ratio += t_ratio_increment;
+                        idx += t_ratio;
+                    } else {
+                        idx += 1.0 / self.ratio_envelope[n];
+                    }
                     let idx_floor = idx.floor();
                     let start_idx = idx_floor as isize - 1;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 4) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_cubic(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            4,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_cubic(frac_offset, buf));
                     }
                 }
             }
             PolynomialDegree::Linear => {
                 for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
+                    if self.ratio_envelope.is_empty() {
+                        t_ratio += t_ratio_increment;
+                        idx += t_ratio;
+                    } else {
+                        idx += 1.0 / self.ratio_envelope[n];
+                    }
                     let idx_floor = idx.floor();
                     let start_idx = idx_floor as isize;
                     let frac = idx - idx_floor;
                     let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let buf = self.buffer.get_unchecked(chan).get_unchecked(
-                                    (start_idx + 2 * POLYNOMIAL_LEN_I) as usize
-                                        ..(start_idx + 2 * POLYNOMIAL_LEN_I + 2) as usize,
-                                );
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = interp_lin(frac_offset, buf);
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let buf = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            2,
+                        );
+                        write_frame(wave_out, chan_out, n, interp_lin(frac_offset, buf));
                     }
                 }
             }
             PolynomialDegree::Nearest => {
                 for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
+                    if self.ratio_envelope.is_empty() {
+                        t_ratio += t_ratio_increment;
+                        idx += t_ratio;
+                    } else {
+                        idx += 1.0 / self.ratio_envelope[n];
+                    }
                     let start_idx = idx.floor() as isize;
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            unsafe {
-                                let point = self
-                                    .buffer
-                                    .get_unchecked(chan)
-                                    .get_unchecked((start_idx + 2 * POLYNOMIAL_LEN_I) as usize);
-                                *wave_out
-                                    .get_unchecked_mut(chan)
-                                    .as_mut()
-                                    .get_unchecked_mut(n) = *point;
-                            }
-                        }
+                    for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                        let point = frames(
+                            &self.buffer,
+                            chan,
+                            (start_idx + 2 * POLYNOMIAL_LEN_I) as usize,
+                            1,
+                        )[0];
+                        write_frame(wave_out, chan_out, n, point);
                     }
                 }
             }
         }
 
+        drop(flush);
+
         // store last index for next iteration
         let input_frames_used = self.needed_input_size;
-        self.last_index = idx - self.current_buffer_fill as f64;
+        self.ratio_envelope.clear();
+        self.last_index = match self.rational_phase.as_mut() {
+            Some(phase) => phase.advance(self.chunk_size, self.current_buffer_fill),
+            None => idx - self.current_buffer_fill as f64,
+        };
         self.resample_ratio = self.target_ratio;
-        self.needed_input_size = (self.last_index as f32
+        // Add the margin before converting, since the rest can be negative for short chunks
+        self.needed_input_size = ((self.last_index as f32
             + self.chunk_size as f32 / self.resample_ratio as f32
             + POLYNOMIAL_LEN_U as f32)
-            .ceil() as usize
-            + 2;
+            .ceil()
+            + 2.0) as usize;
         trace!(
             "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
             active_channels_mask,
@@ -737,9 +1341,32 @@ where
         );
         Ok((input_frames_used, self.chunk_size))
     }
+}
+
+impl<T> Resampler<T> for FastFixedOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask)
+    }
 
     fn input_frames_max(&self) -> usize {
-        (self.chunk_size as f64 * self.resample_ratio_original * self.max_relative_ratio).ceil()
+        (self.chunk_size as f64 / self.resample_ratio_original * self.max_relative_ratio).ceil()
             as usize
             + 2
             + POLYNOMIAL_LEN_U / 2
@@ -765,21 +1392,27 @@ where
         (POLYNOMIAL_LEN_U as f64 * self.resample_ratio / 2.0) as usize
     }
 
+    fn input_delay(&self) -> usize {
+        POLYNOMIAL_LEN_U / 2
+    }
+
     fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
         trace!("Change resample ratio to {}", new_ratio);
         if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
             && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
         {
+            self.rational_phase = None;
             if !ramp {
                 self.resample_ratio = new_ratio;
             }
             self.target_ratio = new_ratio;
-            self.needed_input_size = (self.last_index as f32
-                + self.chunk_size as f32
-                    / (0.5 * self.resample_ratio as f32 + 0.5 * self.target_ratio as f32))
-                .ceil() as usize
-                + POLYNOMIAL_LEN_U
-                + 2;
+            self.ratio_envelope.clear();
+            self.needed_input_size = ((self.last_index as f32
+                + ramp_input_frames(self.chunk_size, self.resample_ratio, self.target_ratio)
+                    as f32
+                + POLYNOMIAL_LEN_U as f32)
+                .ceil()
+                + 2.0) as usize;
             Ok(())
         } else {
             Err(ResampleError::RatioOutOfBounds {
@@ -795,7 +1428,95 @@ where
         self.set_resample_ratio(new_ratio, ramp)
     }
 
+    /// Change the maximum relative ratio, see [Resampler::set_max_resample_ratio_relative].
+    /// The internal buffer is extended when the range is widened, and is not shrunk.
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        validate_max_relative_ratio(
+            max_resample_ratio_relative,
+            self.resample_ratio_original,
+            self.resample_ratio,
+            self.target_ratio,
+        )?;
+        trace!(
+            "Change maximum relative ratio to {}",
+            max_resample_ratio_relative
+        );
+        self.max_relative_ratio = max_resample_ratio_relative;
+        let buffer_channel_length = ((self.max_relative_ratio + 1.0)
+            * self.input_frames_max() as f64) as usize
+            + 2 * POLYNOMIAL_LEN_U;
+        for buf in self.buffer.iter_mut() {
+            if buf.len() < buffer_channel_length {
+                buf.resize(buffer_channel_length, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the chunk size, see [Resampler::set_chunk_size].
+    /// A ratio envelope that was set for the next chunk is discarded.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        self.chunk_size = chunk_size;
+        self.ratio_envelope.clear();
+        self.ratio_envelope.reserve(chunk_size);
+        self.needed_input_size = ((self.last_index as f32
+            + ramp_input_frames(self.chunk_size, self.resample_ratio, self.target_ratio) as f32
+            + POLYNOMIAL_LEN_U as f32)
+            .ceil()
+            + 2.0) as usize;
+        // The interpolation history stays in place, only the length of the buffer may need to grow
+        let buffer_channel_length = ((self.max_relative_ratio + 1.0)
+            * self.input_frames_max() as f64) as usize
+            + 2 * POLYNOMIAL_LEN_U;
+        for buf in self.buffer.iter_mut() {
+            if buf.len() < buffer_channel_length {
+                buf.resize(buffer_channel_length, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the history, see [Resampler::set_history].
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        validate_history(self.nbr_channels, history)?;
+        self.reset();
+        load_history(
+            &mut self.buffer,
+            self.current_buffer_fill,
+            2 * POLYNOMIAL_LEN_U,
+            history,
+        );
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        heap_size_nested(&self.buffer)
+            + heap_size(&self.channel_mask)
+            + heap_size(&self.ratio_envelope)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "FastFixedOut",
+            ResamplerAlgorithm::Polynomial {
+                degree: self.interpolation,
+            },
+            Some(self.resample_ratio),
+        )
+    }
+
     fn reset(&mut self) {
+        self.rational_phase = None;
         self.buffer
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
@@ -804,6 +1525,7 @@ where
             + 2
             + POLYNOMIAL_LEN_U / 2;
         self.current_buffer_fill = self.needed_input_size;
+        self.ratio_envelope.clear();
         self.last_index = -(POLYNOMIAL_LEN_I / 2) as f64;
         self.channel_mask.iter_mut().for_each(|val| *val = true);
         self.resample_ratio = self.resample_ratio_original;
@@ -811,7 +1533,7 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     use crate::check_output;
     use crate::PolynomialDegree;
@@ -970,6 +1692,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn input_frames_during_ramp_fo() {
+        let mut resampler =
+            FastFixedOut::<f64>::new(1.0, 4.0, PolynomialDegree::Cubic, 1024, 1).unwrap();
+        resampler.set_resample_ratio_relative(0.25, true).unwrap();
+        for _ in 0..3 {
+            let frames = resampler.input_frames_next();
+            assert!(frames <= resampler.input_frames_max());
+            let waves = vec![vec![0.0f64; frames]; 1];
+            let out = resampler.process(&waves, None).unwrap();
+            assert_eq!(out[0].len(), 1024);
+            // The interpolation must not have stepped past the end of the new input
+            assert!(resampler.last_index < 0.0);
+            resampler.set_resample_ratio_relative(4.0, true).unwrap();
+        }
+    }
+
     #[test]
     fn make_resampler_fo() {
         let mut resampler =
@@ -1151,4 +1890,280 @@ mod tests {
             FastFixedIn::<f64>::new(8.0, 1.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
         check_output!(check_fo_output, resampler);
     }
+
+    #[test]
+    fn zero_latency() {
+        let input: Vec<f64> = (0..4096).map(|n| (n as f64 * 0.02).sin()).collect();
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        resampler.set_zero_latency(true);
+        assert_eq!(resampler.output_delay(), 0);
+        let mut output = Vec::new();
+        for wave in input.chunks_exact(256) {
+            let out = resampler.process(&[wave], None).unwrap();
+            output.extend_from_slice(&out[0]);
+        }
+        assert!(output.len() >= 4095);
+        assert_eq!(output[..], input[..output.len()]);
+
+        // With extrapolation, the output stays close to the ideal output
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.5, 2.0, PolynomialDegree::Septic, 256, 1).unwrap();
+        resampler.set_zero_latency(true);
+        let mut output = Vec::new();
+        for wave in input.chunks_exact(256) {
+            output.extend_from_slice(&resampler.process(&[wave], None).unwrap()[0]);
+        }
+        assert!(output.len() >= 6140);
+        // Skip the start, where the polynomial also covers the silence before the input
+        for (n, value) in output.iter().enumerate().skip(8) {
+            let expected = (n as f64 / 1.5 * 0.02).sin();
+            assert!((value - expected).abs() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn unity_bypass() {
+        let input: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.02).sin()).collect();
+        let make_degree = |n| match n {
+            0 => PolynomialDegree::Septic,
+            _ => PolynomialDegree::Cubic,
+        };
+        for n in 0..2 {
+            let mut reference = FastFixedIn::<f64>::new(1.0, 2.0, make_degree(n), 256, 1).unwrap();
+            let mut resampler = FastFixedIn::<f64>::new(1.0, 2.0, make_degree(n), 256, 1).unwrap();
+            resampler.set_unity_bypass(true);
+            for (chunk, wave) in input.chunks_exact(256).enumerate() {
+                if chunk == 20 {
+                    reference.set_resample_ratio(1.01, true).unwrap();
+                    resampler.set_resample_ratio(1.01, true).unwrap();
+                }
+                let out_ref = reference.process(&[wave], None).unwrap();
+                let out = resampler.process(&[wave], None).unwrap();
+                assert_eq!(out_ref[0].len(), out[0].len());
+                for (a, b) in out_ref[0].iter().zip(out[0].iter()) {
+                    assert!((a - b).abs() < 1.0e-12);
+                }
+            }
+        }
+        let mut reference =
+            FastFixedOut::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let mut resampler =
+            FastFixedOut::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        resampler.set_unity_bypass(true);
+        let mut pos = 0;
+        for chunk in 0..40 {
+            if chunk == 20 {
+                reference.set_resample_ratio(0.99, true).unwrap();
+                resampler.set_resample_ratio(0.99, true).unwrap();
+            }
+            let frames = reference.input_frames_next();
+            assert_eq!(frames, resampler.input_frames_next());
+            let wave = &input[pos..pos + frames];
+            let out_ref = reference.process(&[wave], None).unwrap();
+            let out = resampler.process(&[wave], None).unwrap();
+            for (a, b) in out_ref[0].iter().zip(out[0].iter()) {
+                assert!((a - b).abs() < 1.0e-12);
+            }
+            pos += frames;
+        }
+    }
+
+    #[test]
+    fn fractional_sample_rates() {
+        let sample_rate_in = 48000.0 * 1000.0 / 1001.0;
+        let resampler = FastFixedIn::<f64>::new_with_sample_rates(
+            sample_rate_in,
+            48000.0,
+            1.0,
+            PolynomialDegree::Cubic,
+            1024,
+            2,
+        )
+        .unwrap();
+        assert!((resampler.resample_ratio - 1.001).abs() < 1.0e-12);
+        let resampler = FastFixedOut::<f64>::new_with_sample_rates(
+            44100.0,
+            sample_rate_in,
+            1.0,
+            PolynomialDegree::Cubic,
+            1024,
+            2,
+        )
+        .unwrap();
+        assert!((resampler.resample_ratio - sample_rate_in / 44100.0).abs() < 1.0e-12);
+        for (input, output) in [(0.0, 48000.0), (44100.0, f64::NAN), (-1.0, 48000.0)] {
+            let err = FastFixedIn::<f64>::new_with_sample_rates(
+                input,
+                output,
+                1.0,
+                PolynomialDegree::Cubic,
+                1024,
+                2,
+            );
+            assert_eq!(err.err().map(|err| err.code()), Some(107));
+        }
+    }
+
+    #[test]
+    fn input_frames_needed_fi() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(0.8, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let waves = vec![vec![0.0f64; 256]; 1];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_resample_ratio_relative(1.5, true).unwrap();
+        let needed = resampler.input_frames_needed_for(1000);
+        assert_eq!(needed % 256, 0);
+        let mut produced = 0;
+        let mut consumed = 0;
+        while produced < 1000 {
+            produced += resampler.process(&waves, None).unwrap()[0].len();
+            consumed += 256;
+        }
+        assert_eq!(consumed, needed);
+    }
+
+    #[test]
+    fn ratio_envelope() {
+        let input: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut reference =
+            FastFixedOut::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let mut resampler =
+            FastFixedOut::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let mut pos_ref = 0;
+        let mut pos = 0;
+        for chunk in 0..8 {
+            let ratio = 1.0 + 0.1 * chunk as f64;
+            reference.set_resample_ratio(ratio, false).unwrap();
+            resampler.set_ratio_envelope(&[ratio; 256]).unwrap();
+            let frames_ref = reference.input_frames_next();
+            let frames = resampler.input_frames_next();
+            let out_ref = reference
+                .process(&[&input[pos_ref..pos_ref + frames_ref]], None)
+                .unwrap();
+            let out = resampler
+                .process(&[&input[pos..pos + frames]], None)
+                .unwrap();
+            pos_ref += frames_ref;
+            pos += frames;
+            for (a, b) in out_ref[0].iter().zip(out[0].iter()) {
+                assert!((a - b).abs() < 1.0e-9);
+            }
+        }
+        assert_eq!(
+            resampler
+                .set_ratio_envelope(&[1.0; 100])
+                .unwrap_err()
+                .code(),
+            207
+        );
+        let mut ratios = vec![1.0; 256];
+        ratios[100] = 2.5;
+        assert_eq!(
+            resampler.set_ratio_envelope(&ratios).unwrap_err().code(),
+            200
+        );
+    }
+
+    #[test]
+    fn input_frames_needed_fo() {
+        let mut resampler =
+            FastFixedOut::<f64>::new(0.8, 2.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let waves = vec![vec![0.0f64; resampler.input_frames_max()]; 1];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_resample_ratio_relative(1.5, true).unwrap();
+        let needed = resampler.input_frames_needed_for(1000);
+        let mut produced = 0;
+        let mut consumed = 0;
+        while produced < 1000 {
+            let frames = resampler.input_frames_next();
+            produced += resampler.process(&[&waves[0][..frames]], None).unwrap()[0].len();
+            consumed += frames;
+        }
+        assert_eq!(consumed, needed);
+    }
+
+    #[test]
+    fn polynomial_weights() {
+        for degree in [
+            PolynomialDegree::Septic,
+            PolynomialDegree::Quintic,
+            PolynomialDegree::Cubic,
+            PolynomialDegree::Linear,
+        ] {
+            let resampler = FastFixedIn::<f64>::new(1.2, 1.0, degree, 1024, 2).unwrap();
+            // At a fraction of zero, the output is the input frame at 0
+            let weights = resampler.polynomial_weights(0.0);
+            let zero = (weights.len() - 1) / 2;
+            for (n, weight) in weights.iter().enumerate() {
+                let expected = if n == zero { 1.0 } else { 0.0 };
+                assert!((weight - expected).abs() < 1.0e-12, "{:?}", weights);
+            }
+            let weights = resampler.polynomial_weights(0.3);
+            assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn exact_ratio() {
+        // 441 input frames are exactly 480 output frames at 44.1 to 48 kHz
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 1.1, PolynomialDegree::Cubic, 441, 1).unwrap();
+        resampler.set_exact_ratio(44100, 48000).unwrap();
+        let waves = vec![vec![0.0f64; 441]; 1];
+        resampler.process(&waves, None).unwrap();
+        let last_index = resampler.last_index;
+        for _ in 0..1000 {
+            assert_eq!(resampler.process(&waves, None).unwrap()[0].len(), 480);
+            assert_eq!(resampler.last_index, last_index);
+        }
+        let mut resampler =
+            FastFixedOut::<f64>::new(1.0, 1.1, PolynomialDegree::Cubic, 480, 1).unwrap();
+        resampler.set_exact_ratio(44100, 48000).unwrap();
+        let waves = vec![vec![0.0f64; resampler.input_frames_max()]; 1];
+        let frames = resampler.input_frames_next();
+        resampler.process(&[&waves[0][..frames]], None).unwrap();
+        let last_index = resampler.last_index;
+        for _ in 0..1000 {
+            let frames = resampler.input_frames_next();
+            assert_eq!(frames, 441);
+            resampler.process(&[&waves[0][..frames]], None).unwrap();
+            assert_eq!(resampler.last_index, last_index);
+        }
+        // Outside the allowed range
+        assert!(resampler.set_exact_ratio(44100, 96000).is_err());
+        assert!(resampler.set_exact_ratio(0, 48000).is_err());
+    }
+
+    #[test]
+    fn memory_limit() {
+        let resampler = FastFixedOut::<f64>::try_new_with_limit(
+            usize::MAX,
+            1.2,
+            2.0,
+            PolynomialDegree::Cubic,
+            1024,
+            2,
+        )
+        .unwrap();
+        let used = resampler.memory_usage();
+        let err = FastFixedOut::<f64>::try_new_with_limit(
+            used / 2,
+            1.2,
+            2.0,
+            PolynomialDegree::Cubic,
+            1024,
+            2,
+        );
+        assert_eq!(err.err().map(|err| err.code()), Some(114));
+        let err = FastFixedIn::<f32>::try_new_with_limit(
+            1 << 30,
+            1.2,
+            1.0,
+            PolynomialDegree::Cubic,
+            1 << 40,
+            2,
+        );
+        assert_eq!(err.err().map(|err| err.code()), Some(114));
+    }
 }
@@ -1,17 +1,66 @@
+use crate::denormal::flush_scope;
 use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
 use crate::interpolation::*;
-#[cfg(target_arch = "x86_64")]
+use crate::sinc::{
+    compensate_droop, make_equiripple_sincs, make_sincs, normalize_gain, prototype_sincs,
+};
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    any(not(feature = "forced_backend"), feature = "force_avx")
+))]
 use crate::sinc_interpolator::sinc_interpolator_avx::AvxInterpolator;
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(
+    target_arch = "aarch64",
+    feature = "simd_neon",
+    any(not(feature = "forced_backend"), feature = "force_neon")
+))]
 use crate::sinc_interpolator::sinc_interpolator_neon::NeonInterpolator;
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_sse3",
+    any(not(feature = "forced_backend"), feature = "force_sse3")
+))]
 use crate::sinc_interpolator::sinc_interpolator_sse::SseInterpolator;
-use crate::sinc_interpolator::{ScalarInterpolator, SincInterpolator};
-use crate::windows::WindowFunction;
-use crate::{update_mask_from_buffers, validate_buffers, Resampler, Sample};
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    feature = "simd_sse2",
+    any(not(feature = "forced_backend"), feature = "force_sse2")
+))]
+use crate::sinc_interpolator::sinc_interpolator_sse2::Sse2Interpolator;
+use crate::sinc_interpolator::{InterpolatorBackend, ScalarInterpolator, SincInterpolator};
+use crate::windows::{calculate_cutoff, calculate_sinc_len, select_window, WindowFunction};
+use crate::{
+    buffer_size, check_memory_limit, heap_size, heap_size_nested, load_history, ramp_input_frames,
+    validate_and_update_mask, validate_history, BufferLayout, Contiguous, InputChannels, Resampler,
+    ResamplerAlgorithm, ResamplerConfig, Sample, Split,
+};
+use num_integer as integer;
+use std::sync::Arc;
+
+/// Maximum length of the oversampled prototype filter for equiripple designs.
+const MAX_EQUIRIPPLE_LEN: usize = 4096;
+/// Default number of output frames processed as one block by the sinc resamplers.
+const DEFAULT_BLOCK_SIZE: usize = 1;
+/// Maximum number of exact phases of the upsampling-optimized sinc resampler.
+const MAX_UPSAMPLING_PHASES: usize = 1024;
 
 /// A struct holding the parameters for sinc interpolation.
+///
+/// New parameters may be added in later versions, so the struct can't be built
+/// with a struct expression outside of this crate.
+/// Start from [default](SincInterpolationParameters::default),
+/// from [from_specification](SincInterpolationParameters::from_specification)
+/// or from one of the presets, and change the fields as needed.
+/// ```
+/// use rubato::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+/// let mut params = SincInterpolationParameters::default();
+/// params.sinc_len = 128;
+/// params.interpolation = SincInterpolationType::Cubic;
+/// params.window = WindowFunction::Blackman2;
+/// ```
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct SincInterpolationParameters {
     /// Length of the windowed sinc interpolation filter.
     /// Higher values can allow a higher cut-off frequency leading to less high frequency roll-off
@@ -19,7 +68,8 @@ pub struct SincInterpolationParameters {
     /// The value will be rounded up to the nearest multiple of 8.
     pub sinc_len: usize,
     /// Relative cutoff frequency of the sinc interpolation filter
-    /// (relative to the lowest one of fs_in/2 or fs_out/2). Start at 0.95, and increase if needed.
+    /// (relative to the lowest one of fs_in/2 or fs_out/2, or to fs_in/2 if `allow_aliasing` is set).
+    /// Start at 0.95, and increase if needed.
     pub f_cutoff: f32,
     /// The number of intermediate points to use for interpolation.
     /// Higher values use more memory for storing the sinc filters.
@@ -31,6 +81,173 @@ pub struct SincInterpolationParameters {
     pub interpolation: SincInterpolationType,
     /// Window function to use.
     pub window: WindowFunction,
+    /// Compensate for the roll-off of the sinc filter below the cutoff frequency.
+    /// When enabled, a gentle inverse-droop shelf is fused into the sinc filter coefficients,
+    /// which flattens the passband response up to about 90% of the cutoff frequency.
+    /// This slightly reduces the stopband attenuation. Start with `false`.
+    pub droop_compensation: bool,
+    /// Design method for the interpolation filter, see `FilterDesign`.
+    pub filter_design: FilterDesign,
+    /// Allow aliasing when downsampling.
+    /// Normally the cutoff of the filter is placed relative to fs_out/2 when downsampling,
+    /// to remove the frequencies that the output sample rate can't represent.
+    /// When enabled, the cutoff is instead always relative to fs_in/2,
+    /// so that the filter only interpolates between the input samples.
+    /// Frequencies above fs_out/2 are then folded back into the output as aliasing.
+    /// This is useful when the signal is already band-limited, for example slowly varying control
+    /// or sensor data, and for effects where aliasing is wanted.
+    /// Has no effect when upsampling. Start with `false`.
+    pub allow_aliasing: bool,
+    /// Convention for normalizing the gain of the filter, see `GainNormalization`.
+    /// Start with `GainNormalization::Dc` for windowed sinc filters,
+    /// and `GainNormalization::Passband` for equiripple filters.
+    pub normalization: GainNormalization,
+}
+
+impl Default for SincInterpolationParameters {
+    /// A good starting point for audio: a 256 point windowed sinc filter with the
+    /// [BlackmanHarris2](WindowFunction::BlackmanHarris2) window, a relative cutoff of 0.95,
+    /// and linear interpolation between 256 intermediate points.
+    /// The droop compensation and aliasing are disabled,
+    /// and the gain is normalized at DC.
+    fn default() -> Self {
+        SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            oversampling_factor: 256,
+            interpolation: SincInterpolationType::Linear,
+            window: WindowFunction::BlackmanHarris2,
+            droop_compensation: false,
+            filter_design: FilterDesign::WindowedSinc,
+            allow_aliasing: false,
+            normalization: GainNormalization::Dc,
+        }
+    }
+}
+
+impl SincInterpolationParameters {
+    /// Create a set of parameters from a filter specification.
+    /// The window function is selected with [select_window](crate::select_window),
+    /// and the sinc length with [calculate_sinc_len](crate::calculate_sinc_len),
+    /// to give the shortest sinc filter that meets the specification.
+    /// The cutoff frequency is then calculated with [calculate_cutoff](crate::calculate_cutoff).
+    ///
+    /// Parameters are:
+    /// - `transition_bandwidth`: Width of the transition band, relative to the lowest one of fs_in/2 or fs_out/2.
+    ///   The transition band ends at that frequency. Must be > 0 and < 1.
+    /// - `attenuation`: The minimum stopband attenuation in dB.
+    /// - `interpolation`: Interpolation type, see `SincInterpolationType`.
+    /// - `oversampling_factor`: The number of intermediate points to use for interpolation.
+    pub fn from_specification(
+        transition_bandwidth: f32,
+        attenuation: f32,
+        interpolation: SincInterpolationType,
+        oversampling_factor: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let window = select_window(attenuation).ok_or(
+            ResamplerConstructionError::InvalidAttenuation(attenuation as f64),
+        )?;
+        let sinc_len = calculate_sinc_len(transition_bandwidth, window).ok_or(
+            ResamplerConstructionError::InvalidTransitionBandwidth(transition_bandwidth as f64),
+        )?;
+        let f_cutoff = calculate_cutoff(sinc_len, window);
+        debug!(
+            "Selected window {:?}, sinc length {} and cutoff {} for transition bandwidth {} and attenuation {}",
+            window, sinc_len, f_cutoff, transition_bandwidth, attenuation
+        );
+        Ok(SincInterpolationParameters {
+            sinc_len,
+            f_cutoff,
+            oversampling_factor,
+            interpolation,
+            window,
+            ..Default::default()
+        })
+    }
+
+    /// Create a set of parameters for resampling speech, for example to and from
+    /// the 8 and 16 kHz rates of telephony and speech recognition.
+    ///
+    /// Speech does not need the full band and the high attenuation of music,
+    /// and these presets use short sincs with the cutoff placed to keep the speech band flat.
+    /// The transition band then extends a little above the Nyquist frequency,
+    /// so some aliasing remains just below it, where speech has very little energy.
+    /// The cutoff is relative to the lowest one of fs_in/2 or fs_out/2, like for other parameters,
+    /// so the preset should match the lower one of the two sample rates.
+    /// Linear interpolation with an oversampling factor of 256 is used,
+    /// which needs only two short dot products per output frame.
+    ///
+    /// On an x86_64 server cpu with AVX, resampling one second of mono f32 audio
+    /// from 48 kHz to 16 kHz with a [SincFixedIn] takes about 0.9 ms with the narrowband preset,
+    /// and 1.0 ms with the wideband preset.
+    /// This compares to about 1.4 ms for a specification of 100 dB attenuation
+    /// and a transition bandwidth of 0.1, and 2.9 ms for 140 dB and 0.05.
+    ///
+    /// Parameters are:
+    /// - `preset`: The speech preset, see `SpeechPreset`.
+    pub fn speech(preset: SpeechPreset) -> Self {
+        let (sinc_len, f_cutoff) = match preset {
+            SpeechPreset::Narrowband => (64, 0.92),
+            SpeechPreset::Wideband => (96, 0.95),
+        };
+        SincInterpolationParameters {
+            sinc_len,
+            f_cutoff,
+            oversampling_factor: 256,
+            interpolation: SincInterpolationType::Linear,
+            window: WindowFunction::Blackman,
+            ..Default::default()
+        }
+    }
+}
+
+/// Filter presets for speech, see [SincInterpolationParameters::speech].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechPreset {
+    /// Narrowband speech, like 8 kHz telephony, with a passband up to 3.4 kHz at 8 kHz.
+    /// The sinc is 64 points long. At 8 kHz, the response is down by 1 dB at 3.4 kHz,
+    /// and by more than 75 dB from 4.4 kHz.
+    Narrowband,
+    /// Wideband speech, like 16 kHz speech recognition front ends, with a passband up to 7 kHz at 16 kHz.
+    /// The sinc is 96 points long. At 16 kHz, the response is down by 1 dB at 7 kHz,
+    /// and by more than 75 dB from 9.5 kHz.
+    Wideband,
+}
+
+/// Design methods for the interpolation filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterDesign {
+    /// A sinc function multiplied by the selected window function.
+    /// The design is fast for any sinc length and oversampling factor.
+    WindowedSinc,
+    /// An equiripple filter designed with the Parks-McClellan (Remez exchange) algorithm.
+    /// For a given sinc length, this gives a higher stopband attenuation than a windowed sinc.
+    /// The transition band is placed symmetrically around the cutoff frequency,
+    /// and ends at the lowest one of fs_in/2 or fs_out/2.
+    /// The window function is not used.
+    /// The cutoff must be between 0.5 and 1.0, and the product of the sinc length
+    /// and the oversampling factor must not exceed 4096 since the design time grows quickly with the filter length.
+    Equiripple,
+}
+
+/// Conventions for normalizing the gain of the interpolation filter.
+///
+/// The conventions differ by a small fraction of a dB, because of the passband ripple
+/// and the droop of the filter near the cutoff.
+/// Using the same convention as the other tools in a processing chain keeps the levels
+/// consistent over repeated conversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GainNormalization {
+    /// Exact unity gain at DC.
+    /// Constant signals and very low frequencies keep exactly the same level.
+    Dc,
+    /// Unity average gain over the passband, so that the ripple and droop are centered around unity.
+    /// The passband extends to 90% of the cutoff frequency for windowed sinc filters,
+    /// and to the passband edge for equiripple filters.
+    Passband,
+    /// Unity average power gain over the passband,
+    /// so that the energy of broadband signals in the passband is preserved.
+    Energy,
 }
 
 /// Interpolation methods that can be selected. For asynchronous interpolation where the
@@ -41,7 +258,7 @@ pub struct SincInterpolationParameters {
 /// It's more efficient to combine the sinc filters with some other interpolation technique.
 /// Then sinc filters are used to provide a fixed number of interpolated points between input samples,
 /// and then the new value is calculated by interpolation between those points.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SincInterpolationType {
     /// For cubic interpolation, the four nearest intermediate points are calculated
     /// using sinc interpolation.
@@ -91,10 +308,16 @@ pub struct SincFixedIn<T> {
     resample_ratio_original: f64,
     target_ratio: f64,
     max_relative_ratio: f64,
+    rational_phase: Option<RationalPhase>,
     interpolator: Box<dyn SincInterpolator<T>>,
     buffer: Vec<Vec<T>>,
     interpolation: SincInterpolationType,
     channel_mask: Vec<bool>,
+    block_size: usize,
+    positions: Vec<FramePosition<T>>,
+    unity_bypass: bool,
+    bypassing: bool,
+    fade: InterpolationFade<T>,
 }
 
 /// An asynchronous resampler that return a fixed number of audio frames.
@@ -121,61 +344,476 @@ pub struct SincFixedOut<T> {
     resample_ratio_original: f64,
     target_ratio: f64,
     max_relative_ratio: f64,
+    rational_phase: Option<RationalPhase>,
     interpolator: Box<dyn SincInterpolator<T>>,
     buffer: Vec<Vec<T>>,
     interpolation: SincInterpolationType,
     channel_mask: Vec<bool>,
+    block_size: usize,
+    positions: Vec<FramePosition<T>>,
+    unity_bypass: bool,
+    bypassing: bool,
+    fade: InterpolationFade<T>,
+    ratio_envelope: Vec<f64>,
 }
 
 pub fn make_interpolator<T>(
-    sinc_len: usize,
     resample_ratio: f64,
-    f_cutoff: f32,
+    parameters: &SincInterpolationParameters,
+) -> Result<Box<dyn SincInterpolator<T>>, ResamplerConstructionError>
+where
+    T: Sample,
+{
+    let sincs = make_interpolator_sincs::<T>(resample_ratio, parameters)?;
+    Ok(select_interpolator(sincs))
+}
+
+/// Create an interpolator from an externally designed prototype filter,
+/// instead of one of the built-in filter designs.
+/// Give the interpolator to the `new_with_interpolator` constructor of
+/// [SincFixedIn] or [SincFixedOut].
+///
+/// The prototype is the impulse response of the filter at the oversampled rate,
+/// given from the first to the last tap. Its cutoff must be below the lowest one of
+/// fs_in/2 or fs_out/2. The taps are used as they are, except that the overall gain
+/// is scaled to give unity gain at DC, so designs with either an interpolation gain
+/// of `oversampling_factor` or a gain of one can be used directly.
+/// The prototype is padded with zeros at both ends, so that the number of taps per sinc
+/// becomes a multiple of 8.
+///
+/// Parameters are:
+/// - `prototype`: The taps of the prototype filter.
+/// - `oversampling_factor`: The oversampling factor the prototype was designed for.
+///
+/// ```
+/// use rubato::{make_prototype_interpolator, Resampler, SincFixedIn, SincInterpolationType};
+/// // A short lowpass, designed elsewhere for an oversampling factor of 4
+/// let prototype = vec![0.25f64, 0.5, 0.75, 1.0, 0.75, 0.5, 0.25];
+/// let interpolator = make_prototype_interpolator(&prototype, 4).unwrap();
+/// assert_eq!(interpolator.nbr_sincs(), 4);
+/// let resampler = SincFixedIn::<f64>::new_with_interpolator(
+///     1.5,
+///     1.0,
+///     SincInterpolationType::Linear,
+///     interpolator,
+///     1024,
+///     2,
+/// )
+/// .unwrap();
+/// ```
+pub fn make_prototype_interpolator<T>(
+    prototype: &[T],
     oversampling_factor: usize,
-    window: WindowFunction,
-) -> Box<dyn SincInterpolator<T>>
+) -> Result<Box<dyn SincInterpolator<T>>, ResamplerConstructionError>
+where
+    T: Sample,
+{
+    let sincs = prototype_sincs(prototype, oversampling_factor).ok_or(
+        ResamplerConstructionError::InvalidPrototype(prototype.len()),
+    )?;
+    Ok(select_interpolator(sincs))
+}
+
+/// Generate Rust source code for a static table with the sincs that a sinc resampler
+/// would use for the given resample ratio and parameters.
+///
+/// This is intended to be called from a build script, for configurations that are known
+/// at compile time. The generated table is then included in the binary,
+/// and an interpolator is made from it with [make_table_interpolator].
+/// This removes the filter design from the construction of the resampler,
+/// which then only copies the table.
+/// Note that the table uses `size_of::<T>() * sinc_len * oversampling_factor` bytes in the binary.
+///
+/// The source defines `pub static <name>: [[T; sinc_len]; oversampling_factor]`,
+/// where the sinc length is rounded up to a multiple of 8.
+/// The values are written with enough digits to be read back exactly.
+///
+/// Parameters are:
+/// - `name`: The name of the static, for example `SINC_TABLE_44100_48000`.
+/// - `resample_ratio`: Ratio between output and input sample rates, must be > 0.
+/// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+///
+/// In `build.rs`, with rubato also added as a build dependency:
+/// ```no_run
+/// use rubato::{sinc_table_source, SincInterpolationParameters, SincInterpolationType};
+/// let parameters =
+///     SincInterpolationParameters::from_specification(0.1, 100.0, SincInterpolationType::Linear, 128)
+///         .unwrap();
+/// let source = sinc_table_source::<f32>("SINC_TABLE", 48000.0 / 44100.0, &parameters).unwrap();
+/// let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("sinc_table.rs");
+/// std::fs::write(path, source).unwrap();
+/// ```
+/// And in the crate, using the same interpolation type:
+/// ```ignore
+/// include!(concat!(env!("OUT_DIR"), "/sinc_table.rs"));
+///
+/// let interpolator = make_table_interpolator(&SINC_TABLE).unwrap();
+/// let resampler = SincFixedIn::<f32>::new_with_interpolator(
+///     48000.0 / 44100.0,
+///     1.0,
+///     SincInterpolationType::Linear,
+///     interpolator,
+///     1024,
+///     2,
+/// )
+/// .unwrap();
+/// ```
+pub fn sinc_table_source<T>(
+    name: &str,
+    resample_ratio: f64,
+    parameters: &SincInterpolationParameters,
+) -> Result<String, ResamplerConstructionError>
+where
+    T: Sample,
+{
+    if resample_ratio <= 0.0 {
+        return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+    }
+    let sincs = make_interpolator_sincs::<T>(resample_ratio, parameters)?;
+    let type_name = std::any::type_name::<T>();
+    let mut source = format!(
+        "// Generated by rubato for a resample ratio of {} with {:?}\n",
+        resample_ratio, parameters
+    );
+    source.push_str(&format!(
+        "pub static {}: [[{}; {}]; {}] = [\n",
+        name,
+        type_name,
+        sincs[0].len(),
+        sincs.len()
+    ));
+    for sinc in sincs.iter() {
+        source.push_str("    [");
+        for (n, value) in sinc.iter().enumerate() {
+            if n > 0 {
+                source.push_str(", ");
+            }
+            source.push_str(&format!("{:?}", value));
+        }
+        source.push_str("],\n");
+    }
+    source.push_str("];\n");
+    Ok(source)
+}
+
+/// Make an interpolator from a precalculated sinc table,
+/// for example one generated by [sinc_table_source],
+/// to use with the `new_with_interpolator` constructors of [SincFixedIn] and [SincFixedOut].
+/// The table holds one sinc per oversampling step,
+/// and the length of the sincs must be a multiple of 8.
+///
+/// Parameters are:
+/// - `table`: The sinc table.
+pub fn make_table_interpolator<T, const N: usize>(
+    table: &[[T; N]],
+) -> Result<Box<dyn SincInterpolator<T>>, ResamplerConstructionError>
+where
+    T: Sample,
+{
+    if table.is_empty() || N == 0 || N % 8 != 0 {
+        return Err(ResamplerConstructionError::InvalidSincTable(N));
+    }
+    let sincs = table.iter().map(|sinc| sinc.to_vec()).collect::<Vec<_>>();
+    Ok(select_interpolator(sincs))
+}
+
+/// An immutable bank of sinc filters that can be shared by many resamplers.
+///
+/// When many streams are resampled with the same settings, each [SincFixedIn] or [SincFixedOut]
+/// would normally calculate and store its own copy of the sinc filters.
+/// For long filters with a high oversampling factor, these dominate both the construction time
+/// and the memory usage. A filter bank is instead calculated once, and is then shared
+/// between the resamplers, that each only hold their own input history and phase.
+/// Cloning a filter bank is cheap, and does not copy the filters.
+///
+/// Create the resamplers with the `new_with_bank` constructors of [SincFixedIn] and [SincFixedOut].
+/// The filters are never modified after construction, so the bank can be shared between threads.
+/// The memory of the shared filters is not included in [Resampler::memory_usage]
+/// of the resamplers, it is given by [memory_usage](SincFilterBank::memory_usage) instead.
+/// ```
+/// use rubato::{Resampler, SincFilterBank, SincFixedIn, SincInterpolationParameters};
+/// let parameters = SincInterpolationParameters::default();
+/// let bank = SincFilterBank::<f32>::new(48000.0 / 8000.0, &parameters).unwrap();
+/// let streams = (0..100)
+///     .map(|_| SincFixedIn::new_with_bank(&bank, 1.0, 160, 1).unwrap())
+///     .collect::<Vec<_>>();
+/// assert!(streams[0].memory_usage() < bank.memory_usage());
+/// ```
+pub struct SincFilterBank<T> {
+    interpolator: Arc<dyn SincInterpolator<T> + Sync>,
+    resample_ratio: f64,
+    interpolation: SincInterpolationType,
+}
+
+impl<T> SincFilterBank<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFilterBank
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates that the filters are designed for, must be > 0.
+    ///   This is also the starting ratio of the resamplers that use the bank.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    pub fn new(
+        resample_ratio: f64,
+        parameters: &SincInterpolationParameters,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincFilterBank, ratio: {}, parameters: {:?}",
+            resample_ratio, parameters
+        );
+        validate_ratios(resample_ratio, 1.0)?;
+        let sincs = make_interpolator_sincs::<T>(resample_ratio, parameters)?;
+        Ok(SincFilterBank {
+            interpolator: Arc::from(select_interpolator(sincs)),
+            resample_ratio,
+            interpolation: parameters.interpolation,
+        })
+    }
+
+    /// Get the ratio between output and input sample rates that the filters are designed for.
+    pub fn resample_ratio(&self) -> f64 {
+        self.resample_ratio
+    }
+
+    /// Get the approximate heap memory used by the shared filters, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.interpolator.memory_usage()
+    }
+
+    /// Get the number of resamplers and other clones that share the filters.
+    pub fn shared_count(&self) -> usize {
+        Arc::strong_count(&self.interpolator)
+    }
+}
+
+impl<T> Clone for SincFilterBank<T> {
+    fn clone(&self) -> Self {
+        SincFilterBank {
+            interpolator: Arc::clone(&self.interpolator),
+            resample_ratio: self.resample_ratio,
+            interpolation: self.interpolation,
+        }
+    }
+}
+
+impl<T> SincInterpolator<T> for SincFilterBank<T>
+where
+    T: Sample,
+{
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        self.interpolator
+            .get_sinc_interpolated(wave, index, subindex)
+    }
+
+    fn get_sinc_interpolated_stereo(
+        &self,
+        left: &[T],
+        right: &[T],
+        index: usize,
+        subindex: usize,
+    ) -> (T, T) {
+        self.interpolator
+            .get_sinc_interpolated_stereo(left, right, index, subindex)
+    }
+
+    fn len(&self) -> usize {
+        self.interpolator.len()
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.interpolator.nbr_sincs()
+    }
+
+    fn backend(&self) -> InterpolatorBackend {
+        self.interpolator.backend()
+    }
+
+    /// The filters are shared, and are not counted as memory used by each resampler.
+    fn memory_usage(&self) -> usize {
+        0
+    }
+}
+
+/// Calculate the sincs of an interpolator for the given ratio and parameters.
+/// The cutoff is scaled down by the ratio when downsampling.
+pub(crate) fn make_interpolator_sincs<T>(
+    resample_ratio: f64,
+    parameters: &SincInterpolationParameters,
+) -> Result<Vec<Vec<T>>, ResamplerConstructionError>
 where
     T: Sample,
 {
-    let sinc_len = 8 * (((sinc_len as f32) / 8.0).ceil() as usize);
-    let f_cutoff = if resample_ratio >= 1.0 {
-        f_cutoff
+    let sinc_len = padded_sinc_len(parameters.sinc_len);
+    let f_scale = if parameters.allow_aliasing {
+        debug!("Aliasing allowed, skipping band-limiting to the output sample rate");
+        1.0
     } else {
-        f_cutoff * resample_ratio as f32
+        resample_ratio.min(1.0) as f32
+    };
+    let f_cutoff = parameters.f_cutoff * f_scale;
+    let mut sincs = match parameters.filter_design {
+        FilterDesign::WindowedSinc => make_sincs::<T>(
+            sinc_len,
+            parameters.oversampling_factor,
+            f_cutoff,
+            parameters.window,
+        ),
+        FilterDesign::Equiripple => {
+            if parameters.f_cutoff <= 0.5 || parameters.f_cutoff >= 1.0 {
+                return Err(ResamplerConstructionError::InvalidCutoff(
+                    parameters.f_cutoff as f64,
+                ));
+            }
+            let totpoints = sinc_len * parameters.oversampling_factor;
+            if totpoints > MAX_EQUIRIPPLE_LEN {
+                return Err(ResamplerConstructionError::InvalidFilterLength(totpoints));
+            }
+            let f_pass = (2.0 * parameters.f_cutoff - 1.0) * f_scale;
+            make_equiripple_sincs::<T>(sinc_len, parameters.oversampling_factor, f_pass, f_scale)
+        }
+    };
+    if parameters.droop_compensation {
+        compensate_droop(&mut sincs, f_cutoff);
+    }
+    let f_pass = match parameters.filter_design {
+        FilterDesign::WindowedSinc => 0.9 * f_cutoff,
+        FilterDesign::Equiripple => (2.0 * parameters.f_cutoff - 1.0) * f_scale,
     };
+    normalize_gain(&mut sincs, f_pass, parameters.normalization);
+    Ok(sincs)
+}
+
+/// Select the fastest interpolator for the sample type.
+/// The SIMD interpolators are provided by the sample type, see [Sample::simd_interpolator].
+/// The scalar interpolator is used when there is none.
+pub(crate) fn select_interpolator<T>(sincs: Vec<Vec<T>>) -> Box<dyn SincInterpolator<T> + Sync>
+where
+    T: Sample,
+{
+    let interpolator = T::simd_interpolator(&sincs)
+        .unwrap_or_else(|| Box::new(ScalarInterpolator::<T>::from_sincs(sincs)));
+    debug!(
+        "Selected the {} interpolator backend",
+        interpolator.backend()
+    );
+    interpolator
+}
+
+/// Select the fastest SIMD interpolator supported by the CPU, by runtime detection of the CPU features.
+#[cfg(not(feature = "forced_backend"))]
+#[cfg_attr(not(any(feature = "f32", feature = "f64")), allow(dead_code))]
+// The sincs are unused when none of the SIMD interpolators are built for the target
+#[allow(unused_variables)]
+pub(crate) fn select_simd_interpolator<T>(
+    sincs: &[Vec<T>],
+) -> Option<Box<dyn SincInterpolator<T> + Sync>>
+where
+    T: Sample
+        + crate::sinc_interpolator::AvxSample
+        + crate::sinc_interpolator::SseSample
+        + crate::sinc_interpolator::Sse2Sample
+        + crate::sinc_interpolator::NeonSample,
+{
+    #[cfg(all(target_arch = "x86_64", feature = "simd_avx"))]
+    if let Ok(interpolator) = AvxInterpolator::<T>::from_sincs(sincs.to_vec()) {
+        return Some(Box::new(interpolator));
+    }
 
-    #[cfg(target_arch = "x86_64")]
-    if let Ok(interpolator) =
-        AvxInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    #[cfg(all(target_arch = "x86_64", feature = "simd_sse3"))]
+    if let Ok(interpolator) = SseInterpolator::<T>::from_sincs(sincs.to_vec()) {
+        return Some(Box::new(interpolator));
     }
 
-    #[cfg(target_arch = "x86_64")]
-    if let Ok(interpolator) =
-        SseInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        feature = "simd_sse2"
+    ))]
+    if let Ok(interpolator) = Sse2Interpolator::<T>::from_sincs(sincs.to_vec()) {
+        return Some(Box::new(interpolator));
     }
 
-    #[cfg(target_arch = "aarch64")]
-    if let Ok(interpolator) =
-        NeonInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    #[cfg(all(target_arch = "aarch64", feature = "simd_neon"))]
+    if let Ok(interpolator) = NeonInterpolator::<T>::from_sincs(sincs.to_vec()) {
+        return Some(Box::new(interpolator));
     }
 
-    Box::new(ScalarInterpolator::<T>::new(
-        sinc_len,
-        oversampling_factor,
-        f_cutoff,
-        window,
+    None
+}
+
+/// Always use the scalar interpolator, as selected by the `force_scalar` feature.
+#[cfg(feature = "force_scalar")]
+#[cfg_attr(not(any(feature = "f32", feature = "f64")), allow(dead_code))]
+pub(crate) fn select_simd_interpolator<T>(
+    _sincs: &[Vec<T>],
+) -> Option<Box<dyn SincInterpolator<T> + Sync>>
+where
+    T: Sample,
+{
+    None
+}
+
+/// Always use the AVX interpolator, as selected by the `force_avx` feature.
+#[cfg(feature = "force_avx")]
+#[cfg_attr(not(any(feature = "f32", feature = "f64")), allow(dead_code))]
+pub(crate) fn select_simd_interpolator<T>(
+    sincs: &[Vec<T>],
+) -> Option<Box<dyn SincInterpolator<T> + Sync>>
+where
+    T: Sample + crate::sinc_interpolator::AvxSample,
+{
+    Some(Box::new(
+        AvxInterpolator::<T>::from_sincs(sincs.to_vec())
+            .expect("AVX and FMA are enabled at compile time"),
+    ))
+}
+
+/// Always use the SSE3 interpolator, as selected by the `force_sse3` feature.
+#[cfg(feature = "force_sse3")]
+#[cfg_attr(not(any(feature = "f32", feature = "f64")), allow(dead_code))]
+pub(crate) fn select_simd_interpolator<T>(
+    sincs: &[Vec<T>],
+) -> Option<Box<dyn SincInterpolator<T> + Sync>>
+where
+    T: Sample + crate::sinc_interpolator::SseSample,
+{
+    Some(Box::new(
+        SseInterpolator::<T>::from_sincs(sincs.to_vec()).expect("SSE3 is enabled at compile time"),
+    ))
+}
+
+/// Always use the SSE2 interpolator, as selected by the `force_sse2` feature.
+#[cfg(feature = "force_sse2")]
+#[cfg_attr(not(any(feature = "f32", feature = "f64")), allow(dead_code))]
+pub(crate) fn select_simd_interpolator<T>(
+    sincs: &[Vec<T>],
+) -> Option<Box<dyn SincInterpolator<T> + Sync>>
+where
+    T: Sample + crate::sinc_interpolator::Sse2Sample,
+{
+    Some(Box::new(
+        Sse2Interpolator::<T>::from_sincs(sincs.to_vec()).expect("SSE2 is enabled at compile time"),
+    ))
+}
+
+/// Always use the Neon interpolator, as selected by the `force_neon` feature.
+#[cfg(feature = "force_neon")]
+#[cfg_attr(not(any(feature = "f32", feature = "f64")), allow(dead_code))]
+pub(crate) fn select_simd_interpolator<T>(
+    sincs: &[Vec<T>],
+) -> Option<Box<dyn SincInterpolator<T> + Sync>>
+where
+    T: Sample + crate::sinc_interpolator::NeonSample,
+{
+    Some(Box::new(
+        NeonInterpolator::<T>::from_sincs(sincs.to_vec()).expect("Neon is enabled at compile time"),
     ))
 }
 
 /// Perform cubic polynomial interpolation to get value at x.
 /// Input points are assumed to be at x = -1, 0, 1, 2
-fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
+pub(crate) fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
 where
     T: Sample,
 {
@@ -192,7 +830,7 @@ where
 
 /// Perform cubic polynomial interpolation to get value at x.
 /// Input points are assumed to be at x = 0, 1, 2
-fn interp_quad<T>(x: T, yvals: &[T; 3]) -> T
+pub(crate) fn interp_quad<T>(x: T, yvals: &[T; 3]) -> T
 where
     T: Sample,
 {
@@ -204,134 +842,1153 @@ where
 }
 
 /// Linear interpolation between two points at x=0 and x=1
-fn interp_lin<T>(x: T, yvals: &[T; 2]) -> T
+pub(crate) fn interp_lin<T>(x: T, yvals: &[T; 2]) -> T
 where
     T: Sample,
 {
     yvals[0] + x * (yvals[1] - yvals[0])
 }
 
-fn validate_ratios(
-    resample_ratio: f64,
-    max_resample_ratio_relative: f64,
-) -> Result<(), ResamplerConstructionError> {
-    if resample_ratio <= 0.0 {
-        return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
-    }
-    if max_resample_ratio_relative < 1.0 {
-        return Err(ResamplerConstructionError::InvalidRelativeRatio(
-            max_resample_ratio_relative,
-        ));
-    }
-    Ok(())
+/// The position of an output frame in the oversampled sinc interpolated data.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FramePosition<T> {
+    pub(crate) nearest: [(isize, isize); 4],
+    pub(crate) frac: T,
 }
 
-impl<T> SincFixedIn<T>
+/// Calculate the position of an output frame at fractional input index `idx`.
+pub(crate) fn frame_position<T>(
+    idx: f64,
+    oversampling_factor: usize,
+    interpolation: &SincInterpolationType,
+) -> FramePosition<T>
 where
     T: Sample,
 {
-    /// Create a new SincFixedIn
-    ///
-    /// Parameters are:
-    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
-    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
-    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
-    /// - `chunk_size`: Size of input data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
-    pub fn new(
-        resample_ratio: f64,
-        max_resample_ratio_relative: f64,
-        parameters: SincInterpolationParameters,
-        chunk_size: usize,
-        nbr_channels: usize,
-    ) -> Result<Self, ResamplerConstructionError> {
-        debug!(
-            "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
-            resample_ratio, chunk_size, nbr_channels, parameters
-        );
-
-        let interpolator = make_interpolator(
-            parameters.sinc_len,
-            resample_ratio,
-            parameters.f_cutoff,
-            parameters.oversampling_factor,
-            parameters.window,
-        );
-
-        Self::new_with_interpolator(
-            resample_ratio,
-            max_resample_ratio_relative,
-            parameters.interpolation,
-            interpolator,
-            chunk_size,
-            nbr_channels,
-        )
+    let factor = oversampling_factor as isize;
+    let mut nearest = [(0isize, 0isize); 4];
+    match interpolation {
+        SincInterpolationType::Cubic => get_nearest_times_4(idx, factor, &mut nearest),
+        SincInterpolationType::Quadratic => {
+            let mut nearest_3 = [(0isize, 0isize); 3];
+            get_nearest_times_3(idx, factor, &mut nearest_3);
+            nearest[..3].copy_from_slice(&nearest_3);
+        }
+        SincInterpolationType::Linear => {
+            let mut nearest_2 = [(0isize, 0isize); 2];
+            get_nearest_times_2(idx, factor, &mut nearest_2);
+            nearest[..2].copy_from_slice(&nearest_2);
+        }
+        SincInterpolationType::Nearest => nearest[0] = get_nearest_time(idx, factor),
     }
+    let frac = idx * oversampling_factor as f64 - (idx * oversampling_factor as f64).floor();
+    FramePosition {
+        nearest,
+        frac: T::coerce(frac),
+    }
+}
 
-    /// Create a new SincFixedIn using an existing Interpolator
-    ///
-    /// Parameters are:
-    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
-    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio` * 10.0 and `resample_ratio` / 10.0.
-    /// - `interpolation_type`: Parameters for interpolation, see `SincInterpolationParameters`.
-    /// - `interpolator`: The interpolator to use.
-    /// - `chunk_size`: Size of output data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
-    pub fn new_with_interpolator(
-        resample_ratio: f64,
-        max_resample_ratio_relative: f64,
-        interpolation_type: SincInterpolationType,
-        interpolator: Box<dyn SincInterpolator<T>>,
-        chunk_size: usize,
-        nbr_channels: usize,
-    ) -> Result<Self, ResamplerConstructionError> {
-        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
-        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
-
-        let channel_mask = vec![true; nbr_channels];
-
-        Ok(SincFixedIn {
-            nbr_channels,
-            chunk_size,
-            last_index: -((interpolator.len() / 2) as f64),
-            resample_ratio,
-            resample_ratio_original: resample_ratio,
-            target_ratio: resample_ratio,
-            max_relative_ratio: max_resample_ratio_relative,
-            interpolator,
-            buffer,
-            interpolation: interpolation_type,
-            channel_mask,
-        })
+/// Check that the sincs of all frame positions of a block fit in a buffer of `buf_len` frames,
+/// where a position at index 0 uses the sinc starting at `2 * sinc_len`.
+/// The positions increase through the block, so only the first and the last need to be checked.
+/// This keeps the bounds checks of the interpolators from panicking while processing.
+fn check_block_range<T>(
+    interpolation: &SincInterpolationType,
+    positions: &[FramePosition<T>],
+    sinc_len: isize,
+    buf_len: usize,
+) -> ResampleResult<()> {
+    let nbr_points = match interpolation {
+        SincInterpolationType::Cubic => 4,
+        SincInterpolationType::Quadratic => 3,
+        SincInterpolationType::Linear => 2,
+        SincInterpolationType::Nearest => 1,
+    };
+    let (first, last) = match (positions.first(), positions.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Ok(()),
+    };
+    let start = first.nearest[..nbr_points]
+        .iter()
+        .map(|point| point.0)
+        .min()
+        .unwrap_or(0);
+    let end = last.nearest[..nbr_points]
+        .iter()
+        .map(|point| point.0)
+        .max()
+        .unwrap_or(0);
+    if start + 2 * sinc_len < 0 || end + 3 * sinc_len >= buf_len as isize {
+        return Err(ResampleError::InterpolationOutOfRange);
     }
+    Ok(())
 }
 
-impl<T> Resampler<T> for SincFixedIn<T>
+/// Calculate the output frames of a single channel for a block of frame positions.
+pub(crate) fn interpolate_block<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+    buf: &[T],
+    positions: &[FramePosition<T>],
+    wave_out: &mut [T],
+) -> ResampleResult<()>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    let sinc_len = interpolator.len() as isize;
+    check_block_range(interpolation, positions, sinc_len, buf.len())?;
+    let point = |n: &(isize, isize)| {
+        interpolator.get_sinc_interpolated(buf, (n.0 + 2 * sinc_len) as usize, n.1 as usize)
+    };
+    let frames = positions.iter().zip(wave_out.iter_mut());
+    match interpolation {
+        SincInterpolationType::Cubic => {
+            for (pos, value) in frames {
+                let points = [
+                    point(&pos.nearest[0]),
+                    point(&pos.nearest[1]),
+                    point(&pos.nearest[2]),
+                    point(&pos.nearest[3]),
+                ];
+                *value = interp_cubic(pos.frac, &points);
+            }
+        }
+        SincInterpolationType::Quadratic => {
+            for (pos, value) in frames {
+                let points = [
+                    point(&pos.nearest[0]),
+                    point(&pos.nearest[1]),
+                    point(&pos.nearest[2]),
+                ];
+                *value = interp_quad(pos.frac, &points);
+            }
+        }
+        SincInterpolationType::Linear => {
+            for (pos, value) in frames {
+                let points = [point(&pos.nearest[0]), point(&pos.nearest[1])];
+                *value = interp_lin(pos.frac, &points);
+            }
+        }
+        SincInterpolationType::Nearest => {
+            for (pos, value) in frames {
+                *value = point(&pos.nearest[0]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Calculate the output frames of both channels of a stereo stream for a block of frame positions.
+/// This gives the same result as calling [interpolate_block] for each channel,
+/// but uses [get_sinc_interpolated_stereo](SincInterpolator::get_sinc_interpolated_stereo)
+/// so that the interpolator can process both channels in one pass over the sinc.
+pub(crate) fn interpolate_block_stereo<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+    bufs: [&[T]; 2],
+    positions: &[FramePosition<T>],
+    wave_out: [&mut [T]; 2],
+) -> ResampleResult<()>
+where
+    T: Sample,
+{
+    let sinc_len = interpolator.len() as isize;
+    let buf_len = bufs[0].len().min(bufs[1].len());
+    check_block_range(interpolation, positions, sinc_len, buf_len)?;
+    let point = |n: &(isize, isize)| {
+        interpolator.get_sinc_interpolated_stereo(
+            bufs[0],
+            bufs[1],
+            (n.0 + 2 * sinc_len) as usize,
+            n.1 as usize,
+        )
+    };
+    let [left, right] = wave_out;
+    let frames = positions.iter().zip(left.iter_mut().zip(right.iter_mut()));
+    match interpolation {
+        SincInterpolationType::Cubic => {
+            for (pos, (left, right)) in frames {
+                let p0 = point(&pos.nearest[0]);
+                let p1 = point(&pos.nearest[1]);
+                let p2 = point(&pos.nearest[2]);
+                let p3 = point(&pos.nearest[3]);
+                *left = interp_cubic(pos.frac, &[p0.0, p1.0, p2.0, p3.0]);
+                *right = interp_cubic(pos.frac, &[p0.1, p1.1, p2.1, p3.1]);
+            }
+        }
+        SincInterpolationType::Quadratic => {
+            for (pos, (left, right)) in frames {
+                let p0 = point(&pos.nearest[0]);
+                let p1 = point(&pos.nearest[1]);
+                let p2 = point(&pos.nearest[2]);
+                *left = interp_quad(pos.frac, &[p0.0, p1.0, p2.0]);
+                *right = interp_quad(pos.frac, &[p0.1, p1.1, p2.1]);
+            }
+        }
+        SincInterpolationType::Linear => {
+            for (pos, (left, right)) in frames {
+                let p0 = point(&pos.nearest[0]);
+                let p1 = point(&pos.nearest[1]);
+                *left = interp_lin(pos.frac, &[p0.0, p1.0]);
+                *right = interp_lin(pos.frac, &[p0.1, p1.1]);
+            }
+        }
+        SincInterpolationType::Nearest => {
+            for (pos, (left, right)) in frames {
+                (*left, *right) = point(&pos.nearest[0]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The output that a crossfade starts from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FadeFrom {
+    /// The output interpolated with the given type.
+    Interpolated(SincInterpolationType),
+    /// The input copied by the unity bypass.
+    Bypassed,
+}
+
+/// A crossfade from an earlier output to the output that is currently calculated.
+/// The buffers are kept between fades, so that a fade can be started while processing
+/// without allocating.
+pub(crate) struct InterpolationFade<T> {
+    from: Option<FadeFrom>,
+    fade_frames: usize,
+    done: usize,
+    next: Option<(SincInterpolationType, usize)>,
+    positions: Vec<FramePosition<T>>,
+    scratch: Vec<T>,
+}
+
+impl<T> InterpolationFade<T>
+where
+    T: Sample,
+{
+    fn new(block_size: usize) -> Self {
+        InterpolationFade {
+            from: None,
+            fade_frames: 0,
+            done: 0,
+            next: None,
+            positions: Vec::with_capacity(block_size),
+            scratch: vec![T::zero(); block_size],
+        }
+    }
+
+    /// Allocate the buffers for blocks of up to `block_size` frames.
+    fn set_block_size(&mut self, block_size: usize) {
+        self.positions.reserve(block_size);
+        if self.scratch.len() < block_size {
+            self.scratch.resize(block_size, T::zero());
+        }
+    }
+
+    /// Get the heap memory of the buffers in bytes.
+    fn memory_usage(&self) -> usize {
+        heap_size(&self.positions) + heap_size(&self.scratch)
+    }
+
+    /// Start a fade over `fade_frames` output frames, from the output given by `from`.
+    /// A `fade_frames` of zero stops any running fade.
+    fn start(&mut self, from: FadeFrom, fade_frames: usize) {
+        self.from = if fade_frames > 0 { Some(from) } else { None };
+        self.fade_frames = fade_frames;
+        self.done = 0;
+        self.positions.clear();
+    }
+
+    /// Change the interpolation type from `interpolation` to `next`, with a fade over `fade_frames` frames.
+    /// A change made while a fade is running is started when that fade is complete,
+    /// so that the output never jumps. Only the last of several waiting changes is kept.
+    fn change_type(
         &mut self,
-        wave_in: &[Vin],
-        wave_out: &mut [Vout],
-        active_channels_mask: Option<&[bool]>,
-    ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
+        interpolation: &mut SincInterpolationType,
+        next: SincInterpolationType,
+        fade_frames: usize,
+    ) {
+        if self.is_active() {
+            self.next = if next != *interpolation {
+                Some((next, fade_frames))
+            } else {
+                None
+            };
+        } else if next != *interpolation {
+            self.start(FadeFrom::Interpolated(*interpolation), fade_frames);
+            *interpolation = next;
+        }
+    }
+
+    /// Get the interpolation type of a change that waits for the running fade, if any.
+    fn next_type(&self) -> Option<SincInterpolationType> {
+        self.next.map(|(next, _)| next)
+    }
+
+    /// Start a fade between the bypassed and the interpolated output,
+    /// when the bypass is entered or left.
+    /// A waiting change of the interpolation type is made at once,
+    /// since the bypassed output does not depend on the type.
+    fn switch_bypass(
+        &mut self,
+        interpolation: &mut SincInterpolationType,
+        bypassing: bool,
+        fade_frames: usize,
+    ) {
+        let from = if bypassing {
+            FadeFrom::Interpolated(*interpolation)
+        } else {
+            FadeFrom::Bypassed
+        };
+        self.start(from, fade_frames);
+        if let Some((next, _)) = self.next.take() {
+            *interpolation = next;
+        }
+    }
+
+    /// Stop any running fade, and make a waiting change of the interpolation type at once.
+    fn stop(&mut self, interpolation: &mut SincInterpolationType) {
+        self.from = None;
+        self.positions.clear();
+        if let Some((next, _)) = self.next.take() {
+            *interpolation = next;
+        }
+    }
+
+    /// Returns true while a fade is running.
+    fn is_active(&self) -> bool {
+        self.from.is_some()
+    }
+
+    /// Add the position of the next output frame, for the output faded from.
+    fn push(&mut self, idx: f64, oversampling_factor: usize) {
+        let position = match self.from {
+            Some(FadeFrom::Interpolated(interpolation)) => {
+                frame_position(idx, oversampling_factor, &interpolation)
+            }
+            Some(FadeFrom::Bypassed) => bypassed_position(idx, oversampling_factor),
+            None => return,
+        };
+        self.positions.push(position);
+    }
+
+    /// Mix the output of a block, calculated with the current settings,
+    /// with the output faded from.
+    fn apply(
+        &mut self,
+        interpolator: &dyn SincInterpolator<T>,
+        buf: &[T],
+        wave_out: &mut [T],
+    ) -> ResampleResult<()> {
+        let from = match self.from {
+            Some(from) => from,
+            None => return Ok(()),
+        };
+        let old = self
+            .scratch
+            .get_mut(..self.positions.len())
+            .ok_or(ResampleError::InterpolationOutOfRange)?;
+        match from {
+            FadeFrom::Interpolated(interpolation) => {
+                interpolate_block(interpolator, &interpolation, buf, &self.positions, old)?
+            }
+            FadeFrom::Bypassed => {
+                interpolate_bypassed(buf, interpolator.len(), &self.positions, old)?
+            }
+        }
+        for (m, (value, old)) in wave_out.iter_mut().zip(old.iter()).enumerate() {
+            let gain = (self.done + m + 1).min(self.fade_frames) as f64 / self.fade_frames as f64;
+            *value = *old + (*value - *old) * T::coerce(gain);
+        }
+        Ok(())
+    }
+
+    /// Move on after a block. When the fade is complete,
+    /// a waiting change of the interpolation type is started.
+    fn advance(&mut self, interpolation: &mut SincInterpolationType) {
+        if !self.is_active() {
+            return;
+        }
+        self.done += self.positions.len();
+        self.positions.clear();
+        if self.done >= self.fade_frames {
+            self.from = None;
+            if let Some((next, fade_frames)) = self.next.take() {
+                self.change_type(interpolation, next, fade_frames);
+            }
+        }
+    }
+}
+
+/// Get the position before the first output frame of a new or reset resampler.
+/// With the unity bypass enabled, this is moved back by one sinc step,
+/// so that at a ratio of 1.0 the output frames fall where they can be copied from the input.
+fn start_index(sinc_len: usize, oversampling_factor: usize, unity_bypass: bool) -> f64 {
+    let start = -((sinc_len / 2) as f64);
+    if unity_bypass {
+        start - 1.0 / oversampling_factor as f64
+    } else {
+        start
+    }
+}
+
+/// Returns the input frame at `idx` plus one sinc step, if the output frames of a chunk
+/// can be copied from the input instead of interpolated.
+/// The sinc of the last oversampling step is centered on an input frame.
+/// At a ratio of 1.0, and positions one step before whole input frames,
+/// the interpolated output is therefore delayed by a whole number of frames,
+/// and copying the input gives the same delay.
+fn bypass_frame(
+    enabled: bool,
+    resample_ratio: f64,
+    target_ratio: f64,
+    idx: f64,
+    oversampling_factor: usize,
+) -> Option<isize> {
+    if !enabled || resample_ratio != 1.0 || target_ratio != 1.0 {
+        return None;
+    }
+    let frame = idx + 1.0 / oversampling_factor as f64;
+    // Allow for the rounding of oversampling factors that are not powers of two
+    if (frame - frame.round()).abs() < 1.0e-9 {
+        Some(frame.round() as isize)
+    } else {
+        None
+    }
+}
+
+/// Get the position of the input frame that the unity bypass copies for an output frame at `idx`,
+/// continued linearly between input frames when `idx` is not on the phase of the bypass.
+fn bypassed_position<T>(idx: f64, oversampling_factor: usize) -> FramePosition<T>
+where
+    T: Sample,
+{
+    let frame = idx + 1.0 / oversampling_factor as f64;
+    FramePosition {
+        nearest: [(frame.floor() as isize, 0); 4],
+        frac: T::coerce(frame - frame.floor()),
+    }
+}
+
+/// Calculate the output frames of a single channel that the unity bypass gives
+/// for a block of positions from [bypassed_position].
+fn interpolate_bypassed<T>(
+    buf: &[T],
+    sinc_len: usize,
+    positions: &[FramePosition<T>],
+    wave_out: &mut [T],
+) -> ResampleResult<()>
+where
+    T: Sample,
+{
+    // The center of the sinc of the last oversampling step, relative to the start of the buffer
+    let offset = (2 * sinc_len + sinc_len / 2) as isize - 1;
+    for (pos, value) in positions.iter().zip(wave_out.iter_mut()) {
+        let index = pos.nearest[0].0 + offset;
+        if index < 0 || index as usize + 1 >= buf.len() {
+            return Err(ResampleError::InterpolationOutOfRange);
+        }
+        let (this, next) = (buf[index as usize], buf[index as usize + 1]);
+        *value = this + (next - this) * pos.frac;
+    }
+    Ok(())
+}
+
+/// Copy `nbr_frames` input frames to the output, starting from the whole input frame `frame`
+/// given by [bypass_frame].
+fn copy_bypass<T, Vout: AsMut<[T]>>(
+    buf: &[T],
+    wave_out: &mut Vout,
+    frame: isize,
+    sinc_len: usize,
+    nbr_frames: usize,
+) where
+    T: Sample,
+{
+    let start = (frame + (2 * sinc_len + sinc_len / 2) as isize) as usize;
+    wave_out.as_mut()[..nbr_frames].copy_from_slice(&buf[start..start + nbr_frames]);
+}
+
+/// Crossfade the first frames of a bypassed chunk of `nbr_frames` frames,
+/// for the output frames following position `idx`, from the output given by the fade.
+#[allow(clippy::too_many_arguments)]
+fn fade_bypassed<T, Vout: AsMut<[T]>>(
+    fade: &mut InterpolationFade<T>,
+    interpolation: &mut SincInterpolationType,
+    interpolator: &dyn SincInterpolator<T>,
+    buffer: &[Vec<T>],
+    wave_out: &mut [Vout],
+    layout: BufferLayout,
+    channel_mask: &[bool],
+    idx: f64,
+    nbr_frames: usize,
+) -> ResampleResult<()>
+where
+    T: Sample,
+{
+    let oversampling_factor = interpolator.nbr_sincs();
+    let mut n = 0;
+    while fade.is_active() && n < nbr_frames {
+        // The fade buffers hold at least one block
+        let block_len = fade.scratch.len().min(nbr_frames - n);
+        for m in n..n + block_len {
+            fade.push(idx + (m + 1) as f64, oversampling_factor);
+        }
+        for (chan, _, chan_out) in layout.active_channels(channel_mask) {
+            let block_out = &mut wave_out[chan_out].as_mut()[n..n + block_len];
+            fade.apply(interpolator, &buffer[chan], block_out)?;
+        }
+        fade.advance(interpolation);
+        n += block_len;
+    }
+    Ok(())
+}
+
+/// Get the largest number of input frames per chunk that keeps the latency
+/// of a sinc resampler within `max_latency` input frames.
+fn chunk_size_for_latency(
+    max_latency: usize,
+    parameters: &SincInterpolationParameters,
+) -> Result<usize, ResamplerConstructionError> {
+    // The filter length is rounded up in the same way as when the sincs are made
+    let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+    let filter_delay = sinc_len / 2;
+    if filter_delay >= max_latency {
+        return Err(ResamplerConstructionError::LatencyBudgetTooSmall {
+            budget: max_latency,
+            filter_delay,
+        });
+    }
+    Ok(max_latency - filter_delay)
+}
+
+/// Maximum total number of filter coefficients, `sinc_len * oversampling_factor`,
+/// for the filters made by the high-rate constructors.
+const MAX_HIGH_RATE_COEFFICIENTS: usize = 1 << 20;
+
+/// Adapt the filter parameters for converting between high sample rates.
+///
+/// When downsampling, the cutoff is scaled down to the output rate,
+/// and the transition band becomes wider relative to the output Nyquist frequency.
+/// The sinc length is then increased by the inverse of the ratio, to keep the transition band
+/// relative to the output rate the same as given by the parameters.
+/// The oversampling factor is reduced if needed, to keep the total size of the filter bank bounded.
+fn high_rate_parameters(
+    resample_ratio: f64,
+    parameters: SincInterpolationParameters,
+) -> SincInterpolationParameters {
+    let mut parameters = parameters;
+    if resample_ratio < 1.0 && !parameters.allow_aliasing {
+        let sinc_len = (parameters.sinc_len as f64 / resample_ratio).ceil() as usize;
+        parameters.sinc_len = 8 * ((sinc_len + 7) / 8);
+    }
+    let max_coefficients = match parameters.filter_design {
+        FilterDesign::WindowedSinc => MAX_HIGH_RATE_COEFFICIENTS,
+        FilterDesign::Equiripple => MAX_EQUIRIPPLE_LEN,
+    };
+    let max_oversampling = (max_coefficients / parameters.sinc_len).max(1);
+    if parameters.oversampling_factor > max_oversampling {
+        debug!(
+            "Reducing the oversampling factor from {} to {} for a sinc length of {}",
+            parameters.oversampling_factor, max_oversampling, parameters.sinc_len
+        );
+        parameters.oversampling_factor = max_oversampling;
+    }
+    parameters
+}
+
+/// Check the sample rates given to the high-rate constructors and get the resample ratio.
+fn high_rate_ratio(
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+) -> Result<f64, ResamplerConstructionError> {
+    if sample_rate_input == 0 || sample_rate_output == 0 {
+        return Err(ResamplerConstructionError::InvalidSampleRate {
+            input: sample_rate_input,
+            output: sample_rate_output,
+        });
+    }
+    Ok(sample_rate_output as f64 / sample_rate_input as f64)
+}
+
+fn validate_ratios(
+    resample_ratio: f64,
+    max_resample_ratio_relative: f64,
+) -> Result<(), ResamplerConstructionError> {
+    if resample_ratio <= 0.0 {
+        return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+    }
+    if max_resample_ratio_relative < 1.0 {
+        return Err(ResamplerConstructionError::InvalidRelativeRatio(
+            max_resample_ratio_relative,
+        ));
+    }
+    Ok(())
+}
+
+/// Round a sinc length up to a multiple of 8, the length of the generated sincs.
+fn padded_sinc_len(sinc_len: usize) -> usize {
+    8 * ((sinc_len + 7) / 8)
+}
+
+/// Estimate the heap memory of a sinc resampler in bytes, before it is built.
+/// This counts the filter table, the input buffer of `buffer_frames` frames per channel,
+/// and the smaller work buffers.
+fn estimate_sinc_memory<T>(
+    parameters: &SincInterpolationParameters,
+    buffer_frames: usize,
+    nbr_channels: usize,
+) -> usize
+where
+    T: Sample,
+{
+    buffer_size::<T>(
+        parameters.oversampling_factor,
+        padded_sinc_len(parameters.sinc_len),
+    )
+    .saturating_add(buffer_size::<T>(nbr_channels, buffer_frames))
+    .saturating_add(nbr_channels)
+    .saturating_add(buffer_size::<FramePosition<T>>(2, DEFAULT_BLOCK_SIZE))
+    .saturating_add(buffer_size::<T>(1, DEFAULT_BLOCK_SIZE))
+}
+
+/// Check that an interpolator given to a constructor has at least one sinc with at least one point.
+fn validate_interpolator<T>(
+    interpolator: &dyn SincInterpolator<T>,
+) -> Result<(), ResamplerConstructionError> {
+    if interpolator.is_empty() || interpolator.nbr_sincs() == 0 {
+        return Err(ResamplerConstructionError::EmptyFilter);
+    }
+    Ok(())
+}
+
+impl<T> SincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedIn
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        let interpolator = make_interpolator(resample_ratio, &parameters)?;
+
+        Self::new_with_interpolator(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new SincFixedIn like [SincFixedIn::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory.
+    ///
+    /// The memory of the filter and the buffers is estimated from the parameters
+    /// before anything is allocated, and [ResamplerConstructionError::MemoryLimitExceeded]
+    /// is returned if the estimate exceeds the limit.
+    /// This avoids allocating gigabytes for an absurd chunk size or oversampling factor,
+    /// for example when the parameters are given by a user.
+    /// The estimate is close to the [memory_usage](Resampler::memory_usage) of the new resampler.
+    /// Note that the filter is temporarily stored twice while it is designed.
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        let buffer_frames = chunk_size.saturating_add(2 * padded_sinc_len(parameters.sinc_len));
+        let required = estimate_sinc_memory::<T>(&parameters, buffer_frames, nbr_channels);
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new SincFixedIn from a pair of sample rates, that may be fractional.
+    ///
+    /// This is useful for rates that are not whole numbers,
+    /// like the pull-down rates 44100 * 1000 / 1001 Hz and 48000 * 1000 / 1001 Hz.
+    /// The resample ratio is `sample_rate_output / sample_rate_input`.
+    /// When both rates are whole numbers and one of them is at least [HIGH_SAMPLE_RATE](crate::HIGH_SAMPLE_RATE),
+    /// the positions are tracked exactly, like after calling `set_exact_ratio`.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be finite and > 0.
+    /// - `sample_rate_output`: Output sample rate, must be finite and > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the starting ratio, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_sample_rates(
+        sample_rate_input: f64,
+        sample_rate_output: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resample_ratio = ratio_from_sample_rates(sample_rate_input, sample_rate_output)?;
+        let mut resampler = Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )?;
+        if let Some((input, output)) = exact_high_rates(sample_rate_input, sample_rate_output) {
+            debug!(
+                "High sample rates {} and {} Hz, tracking the positions exactly",
+                input, output
+            );
+            resampler
+                .set_exact_ratio(input, output)
+                .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        }
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedIn for high sample rates, like 352.8, 384 or 768 kHz.
+    ///
+    /// The filter given by `parameters` is adapted to the pair of rates.
+    /// When downsampling, the sinc length is increased by the inverse of the ratio,
+    /// so that the transition band relative to the output Nyquist frequency stays as specified.
+    /// Without this, converting for example 768 kHz to 48 kHz with a filter designed
+    /// for a normal ratio leaves a transition band 16 times as wide at the output rate.
+    /// The oversampling factor is reduced if needed to keep the filter bank size reasonable.
+    /// The positions of the output frames are tracked exactly, like after [SincFixedIn::set_exact_ratio].
+    ///
+    /// The long filters need large chunks to be processed efficiently.
+    /// The chunk size must be at least the adapted sinc length,
+    /// otherwise [ResamplerConstructionError::ChunkSizeTooSmall] is returned.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the starting ratio, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation at normal rates, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_high_rate(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resample_ratio = high_rate_ratio(sample_rate_input, sample_rate_output)?;
+        let parameters = high_rate_parameters(resample_ratio, parameters);
+        let minimum = parameters.sinc_len;
+        if chunk_size < minimum {
+            return Err(ResamplerConstructionError::ChunkSizeTooSmall {
+                chunk_size,
+                minimum,
+            });
+        }
+        let mut resampler = Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )?;
+        resampler
+            .set_exact_ratio(sample_rate_input, sample_rate_output)
+            .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedIn from a latency budget.
+    ///
+    /// The filter is given by `parameters`, typically made from a quality target with
+    /// [SincInterpolationParameters::from_specification]. The chunk size is then chosen
+    /// as the largest that keeps the latency within `max_latency`.
+    /// The latency is the time from when an input frame is given to the resampler,
+    /// until the corresponding output frame is returned, in input frames.
+    /// It is the time to fill a chunk, plus the delay of the filter,
+    /// which is half the sinc length.
+    /// An error is returned if the delay of the filter alone exceeds the budget.
+    /// Use a shorter sinc, for example by allowing a wider transition band,
+    /// to fit a smaller budget.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `max_latency`: Maximum latency in input frames. For a budget in milliseconds,
+    ///   use `sample_rate_input * milliseconds / 1000`.
+    /// - `nbr_channels`: Number of channels in input/output.
+    ///
+    /// ```
+    /// use rubato::{Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType};
+    /// // At most 10 ms of latency at 48 kHz, with at least 100 dB of stopband attenuation
+    /// let parameters =
+    ///     SincInterpolationParameters::from_specification(0.1, 100.0, SincInterpolationType::Linear, 128)
+    ///         .unwrap();
+    /// let resampler =
+    ///     SincFixedIn::<f32>::new_with_latency_budget(44100.0 / 48000.0, 1.0, parameters, 480, 2).unwrap();
+    /// assert!(resampler.input_frames_max() < 480);
+    /// ```
+    pub fn new_with_latency_budget(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        max_latency: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let chunk_size = chunk_size_for_latency(max_latency, &parameters)?;
+        debug!(
+            "Selected chunk size {} for a latency budget of {} frames",
+            chunk_size, max_latency
+        );
+        Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new SincFixedIn that is optimized for increasing the sample rate
+    /// by a fixed ratio, given by a pair of integer sample rates.
+    ///
+    /// When upsampling from `sample_rate_input` to `sample_rate_output`,
+    /// the output frames only fall on `sample_rate_output / gcd` different positions
+    /// between two input frames, for example 160 when going from 44100 to 48000 Hz.
+    /// The sincs are then made for exactly these positions, used as the oversampling factor,
+    /// and each output frame is calculated from a single sinc without any interpolation.
+    /// This needs one dot product per output frame, instead of the two to four needed
+    /// by the linear, quadratic and cubic interpolation, at the cost of a larger table of sincs.
+    /// The filter has its cutoff at the input Nyquist frequency,
+    /// and the positions are tracked exactly as with [set_exact_ratio](SincFixedIn::set_exact_ratio).
+    ///
+    /// The `sinc_len`, `f_cutoff`, `window`, `filter_design` and `droop_compensation`
+    /// values of `parameters` are used, while the oversampling factor and interpolation type
+    /// are replaced. If the number of positions exceeds 1024, the parameters are instead used
+    /// as they are, with exact position tracking.
+    /// The ratio can't be changed, and an error is returned if the output rate
+    /// is not higher than the input rate.
+    /// Resetting the resampler ends the exact tracking,
+    /// call [set_exact_ratio](SincFixedIn::set_exact_ratio) to start it again.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > `sample_rate_input`.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_upsampling(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if sample_rate_input == 0 || sample_rate_output == 0 {
+            return Err(ResamplerConstructionError::InvalidSampleRate {
+                input: sample_rate_input,
+                output: sample_rate_output,
+            });
+        }
+        let resample_ratio = sample_rate_output as f64 / sample_rate_input as f64;
+        if sample_rate_output <= sample_rate_input {
+            return Err(ResamplerConstructionError::UnsupportedRatio(resample_ratio));
+        }
+        let phases = sample_rate_output / integer::gcd(sample_rate_input, sample_rate_output);
+        let parameters = if phases <= MAX_UPSAMPLING_PHASES {
+            debug!("Using {} exact phases for upsampling", phases);
+            SincInterpolationParameters {
+                oversampling_factor: phases,
+                interpolation: SincInterpolationType::Nearest,
+                ..parameters
+            }
         } else {
-            update_mask_from_buffers(&mut self.channel_mask);
+            debug!(
+                "Too many phases for upsampling, {}, using the parameters as given",
+                phases
+            );
+            parameters
         };
+        let mut resampler = Self::new(resample_ratio, 1.0, parameters, chunk_size, nbr_channels)?;
+        resampler
+            .set_exact_ratio(sample_rate_input, sample_rate_output)
+            .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedIn using an existing Interpolator
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio` * 10.0 and `resample_ratio` / 10.0.
+    /// - `interpolation_type`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `interpolator`: The interpolator to use, either one made by this crate or a custom kernel,
+    ///   see [SincInterpolator]. It must have at least one sinc with at least one point.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_interpolator(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: SincInterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        validate_interpolator(interpolator.as_ref())?;
+        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
+
+        let channel_mask = vec![true; nbr_channels];
+
+        Ok(SincFixedIn {
+            nbr_channels,
+            chunk_size,
+            last_index: -((interpolator.len() / 2) as f64),
+            resample_ratio,
+            resample_ratio_original: resample_ratio,
+            target_ratio: resample_ratio,
+            max_relative_ratio: max_resample_ratio_relative,
+            rational_phase: None,
+            interpolator,
+            buffer,
+            interpolation: interpolation_type,
+            channel_mask,
+            block_size: DEFAULT_BLOCK_SIZE,
+            unity_bypass: false,
+            bypassing: false,
+            fade: InterpolationFade::new(DEFAULT_BLOCK_SIZE),
+            positions: Vec::with_capacity(DEFAULT_BLOCK_SIZE),
+        })
+    }
+
+    /// Create a new SincFixedIn that uses a shared filter bank, see [SincFilterBank].
+    /// This only allocates the buffers for the input history, and not the filters.
+    ///
+    /// Parameters are:
+    /// - `bank`: The shared filters. The starting ratio is the one of the bank.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the ratio of the bank, must be >= 1.0.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_bank(
+        bank: &SincFilterBank<T>,
+        max_resample_ratio_relative: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_interpolator(
+            bank.resample_ratio,
+            max_resample_ratio_relative,
+            bank.interpolation,
+            Box::new(bank.clone()),
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Set the number of output frames that are processed as one block.
+    ///
+    /// The positions of a block of output frames are calculated first,
+    /// and then the frames are interpolated one channel at a time.
+    /// Larger blocks keep the data of each channel in the cache for longer,
+    /// which can improve the throughput when processing many channels.
+    /// Smaller blocks use less scratch memory.
+    /// This does not change the output, or the latency which is given by the chunk size.
+    /// The default block size is 1, and a value of 0 is treated as 1.
+    /// The scratch buffer for the block is allocated here and not during processing.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        self.block_size = block_size.max(1);
+        self.positions = Vec::with_capacity(self.block_size);
+        self.fade.set_block_size(self.block_size);
+    }
 
+    /// Get the number of output frames that are processed as one block.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Change the interpolation type, with a crossfade over `fade_frames` output frames.
+    ///
+    /// The interpolation type sets how many points of the oversampled sinc filter are calculated
+    /// for each output frame, from four for [SincInterpolationType::Cubic]
+    /// down to one for [SincInterpolationType::Nearest].
+    /// Only the degree of the polynomial interpolation between these points is changed,
+    /// the length of the sinc filter stays the same.
+    /// Switching to a cheaper type reduces the processing load, at the cost of more interpolation noise.
+    /// This can be used to degrade the quality gracefully when the caller detects CPU pressure,
+    /// and to restore it again later.
+    /// The filter, the input history and the delay are the same for all types,
+    /// so the output continues without any jump in time.
+    /// While fading, the output frames are calculated with both the old and the new type,
+    /// which temporarily increases the load.
+    /// A `fade_frames` of zero switches at the start of the next chunk without a crossfade.
+    /// A change made while a crossfade is running waits until that crossfade is complete,
+    /// so that the output never jumps. Only the last of several waiting changes is kept.
+    /// The crossfade buffers are allocated by the constructor and by [set_block_size](Self::set_block_size),
+    /// and not during processing.
+    pub fn set_interpolation_type(
+        &mut self,
+        interpolation: SincInterpolationType,
+        fade_frames: usize,
+    ) {
+        debug!(
+            "Change interpolation type from {:?} to {:?}, fade frames: {}",
+            self.interpolation_type(),
+            interpolation,
+            fade_frames
+        );
+        if self.bypassing {
+            // The bypassed output does not depend on the type, so it does not need a fade
+            self.interpolation = interpolation;
+        } else {
+            self.fade
+                .change_type(&mut self.interpolation, interpolation, fade_frames);
+        }
+    }
+
+    /// Get the interpolation type, including a change that waits for a running crossfade.
+    pub fn interpolation_type(&self) -> SincInterpolationType {
+        self.fade.next_type().unwrap_or(self.interpolation)
+    }
+
+    /// Enable or disable copying the input directly to the output while the ratio is 1.0.
+    ///
+    /// At a ratio of exactly 1.0 the input frames are copied with the same delay
+    /// as the interpolated output, instead of being filtered.
+    /// This saves nearly all of the processing, but means that the anti-aliasing filter
+    /// is not applied while bypassing.
+    /// The bypass is used for chunks where both the current and the target ratio are 1.0,
+    /// and the output frames fall exactly on input frames.
+    /// This is the case for a resampler created with a ratio of 1.0, until the ratio is changed,
+    /// and again after it has been reset.
+    /// To make the output frames fall on input frames, the resampler starts one oversampling step
+    /// of the sinc filter earlier when the bypass is enabled.
+    /// Changing the setting therefore resets the resampler.
+    /// When the ratio is changed away from 1.0, the interpolation takes over without any jump in time,
+    /// with a crossfade over the length of the sinc filter.
+    /// The bypass is disabled by default.
+    pub fn set_unity_bypass(&mut self, enabled: bool) {
+        if enabled != self.unity_bypass {
+            self.unity_bypass = enabled;
+            self.reset();
+        }
+    }
+
+    /// Returns true if copying the input directly to the output at a ratio of 1.0 is enabled.
+    pub fn unity_bypass(&self) -> bool {
+        self.unity_bypass
+    }
+
+    /// Get the coefficients of the anti-aliasing filter in use,
+    /// as one vector of [SincInterpolator::len] values for each of the oversampled sincs.
+    /// See [SincInterpolator::coefficients].
+    pub fn sinc_coefficients(&self) -> Vec<Vec<T>> {
+        self.interpolator.coefficients()
+    }
+
+    /// Get the implementation used by the interpolator for the scalar products.
+    /// This shows which SIMD instructions were selected at runtime, see [InterpolatorBackend].
+    pub fn interpolator_backend(&self) -> InterpolatorBackend {
+        self.interpolator.backend()
+    }
+
+    /// Set an exact resample ratio, given by a pair of integer sample rates.
+    ///
+    /// The position in the input is then tracked with integer arithmetic,
+    /// so that the output stays aligned with the ratio `sample_rate_output / sample_rate_input`
+    /// over streams of any length, instead of slowly drifting because of floating point rounding.
+    /// The ratio is applied from the start of the next chunk, without ramping,
+    /// and must be within the range allowed by the constructor.
+    /// Changing the ratio in any other way, or resetting the resampler, ends the exact tracking.
+    pub fn set_exact_ratio(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> ResampleResult<()> {
+        self.set_resample_ratio(sample_rate_output as f64 / sample_rate_input as f64, false)?;
+        self.rational_phase = Some(RationalPhase::new(
+            sample_rate_input,
+            sample_rate_output,
+            self.last_index,
+        ));
+        Ok(())
+    }
+
+    /// Get the number of input frames needed to produce at least `output_frames` frames of output,
+    /// starting from the current state of the resampler.
+    ///
+    /// This is intended for pull-based hosts that must deliver a given number of output frames.
+    /// The input is consumed in chunks of `chunk_size` frames, so the result is a multiple of the chunk size.
+    /// A ratio change that is being ramped is included in the calculation.
+    /// The result is valid until the resample ratio is changed, or the resampler is reset.
+    pub fn input_frames_needed_for(&self, output_frames: usize) -> usize {
+        ChunkState {
+            chunk_size: self.chunk_size,
+            filter_len: self.interpolator.len(),
+            last_index: self.last_index,
+            resample_ratio: self.resample_ratio,
+            target_ratio: self.target_ratio,
+        }
+        .fixed_in_frames_needed(output_frames)
+    }
+}
+
+impl<T> SincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
         // Set length to chunksize*ratio plus a safety margin of 10 elements.
         let needed_len = (self.chunk_size as f64
             * (0.5 * self.resample_ratio + 0.5 * self.target_ratio)
             + 10.0) as usize;
 
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.chunk_size,
             needed_len,
         )?;
@@ -351,122 +2008,118 @@ where
             buf.copy_within(self.chunk_size..self.chunk_size + 2 * sinc_len, 0);
         }
 
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                debug_assert!(needed_len <= wave_out[chan].as_mut().len());
-                self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size]
-                    .copy_from_slice(&wave_in[chan].as_ref()[..self.chunk_size]);
-            }
+        for (chan, chan_in, chan_out) in layout.active_channels(&self.channel_mask) {
+            debug_assert!(needed_len <= wave_out[chan_out].as_mut().len());
+            wave_in.copy_frames(
+                chan_in,
+                0,
+                &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size],
+            );
         }
 
         let mut idx = self.last_index;
 
         let mut n = 0;
 
-        match self.interpolation {
-            SincInterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_cubic(frac_offset, &points);
-                        }
-                    }
-                    n += 1;
-                }
+        let bypass = bypass_frame(
+            self.unity_bypass,
+            self.resample_ratio,
+            self.target_ratio,
+            idx,
+            oversampling_factor,
+        );
+        if bypass.is_some() != self.bypassing {
+            // Crossfade between the bypassed and the interpolated output
+            self.bypassing = bypass.is_some();
+            self.fade
+                .switch_bypass(&mut self.interpolation, self.bypassing, sinc_len);
+        }
+        // Flush denormals in the inner loops only, if requested by FlushDenormals
+        let flush = flush_scope();
+        if let Some(frame) = bypass {
+            // Continue exactly on the phase of the bypass
+            idx = frame as f64 - 1.0 / oversampling_factor as f64;
+            n = (end_idx + 1 - frame).max(0) as usize;
+            for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                copy_bypass(
+                    &self.buffer[chan],
+                    &mut wave_out[chan_out],
+                    frame,
+                    sinc_len,
+                    n,
+                );
             }
-            SincInterpolationType::Quadratic => {
-                let mut points = [T::zero(); 3];
-                let mut nearest = [(0isize, 0isize); 3];
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_quad(frac_offset, &points);
-                        }
-                    }
-                    n += 1;
-                }
+            fade_bypassed(
+                &mut self.fade,
+                &mut self.interpolation,
+                self.interpolator.as_ref(),
+                &self.buffer,
+                wave_out,
+                layout,
+                &self.channel_mask,
+                idx,
+                n,
+            )?;
+            idx += n as f64;
+        }
+
+        loop {
+            self.positions.clear();
+            while idx < end_idx as f64 && self.positions.len() < self.block_size {
+                t_ratio += t_ratio_increment;
+                idx += t_ratio;
+                self.positions.push(frame_position(
+                    idx,
+                    oversampling_factor,
+                    &self.interpolation,
+                ));
+                self.fade.push(idx, oversampling_factor);
             }
-            SincInterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_lin(frac_offset, &points);
-                        }
-                    }
-                    n += 1;
-                }
+            if self.positions.is_empty() {
+                break;
             }
-            SincInterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            point = self.interpolator.get_sinc_interpolated(
-                                buf,
-                                (nearest.0 + 2 * sinc_len as isize) as usize,
-                                nearest.1 as usize,
-                            );
-                            wave_out[chan].as_mut()[n] = point;
-                        }
-                    }
-                    n += 1;
+            let block_len = self.positions.len();
+            if self.channel_mask == [true, true] {
+                // Stereo with both channels active, interpolate the channels together
+                let (left, right) = wave_out.split_at_mut(1);
+                let left_out = &mut left[0].as_mut()[n..n + block_len];
+                let right_out = &mut right[0].as_mut()[n..n + block_len];
+                interpolate_block_stereo(
+                    self.interpolator.as_ref(),
+                    &self.interpolation,
+                    [&self.buffer[0], &self.buffer[1]],
+                    &self.positions,
+                    [&mut *left_out, &mut *right_out],
+                )?;
+                self.fade
+                    .apply(self.interpolator.as_ref(), &self.buffer[0], left_out)?;
+                self.fade
+                    .apply(self.interpolator.as_ref(), &self.buffer[1], right_out)?;
+            } else {
+                for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                    let block_out = &mut wave_out[chan_out].as_mut()[n..n + block_len];
+                    interpolate_block(
+                        self.interpolator.as_ref(),
+                        &self.interpolation,
+                        &self.buffer[chan],
+                        &self.positions,
+                        block_out,
+                    )?;
+                    self.fade
+                        .apply(self.interpolator.as_ref(), &self.buffer[chan], block_out)?;
                 }
             }
+            self.fade.advance(&mut self.interpolation);
+            n += block_len;
         }
 
+        drop(flush);
+
         // store last index for next iteration
-        self.last_index = idx - self.chunk_size as f64;
+        self.last_index = match self.rational_phase.as_mut() {
+            Some(phase) => phase.advance(n, self.chunk_size),
+            None => idx - self.chunk_size as f64,
+        };
         self.resample_ratio = self.target_ratio;
         trace!(
             "Resampling channels {:?}, {} frames in, {} frames out",
@@ -476,6 +2129,29 @@ where
         );
         Ok((self.chunk_size, n))
     }
+}
+
+impl<T> Resampler<T> for SincFixedIn<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask)
+    }
 
     fn output_frames_max(&self) -> usize {
         // Set length to chunksize*ratio plus a safety margin of 10 elements.
@@ -492,6 +2168,10 @@ where
         (self.interpolator.len() as f64 * self.resample_ratio / 2.0) as usize
     }
 
+    fn input_delay(&self) -> usize {
+        self.interpolator.len() / 2
+    }
+
     fn nbr_channels(&self) -> usize {
         self.nbr_channels
     }
@@ -509,6 +2189,7 @@ where
         if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
             && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
         {
+            self.rational_phase = None;
             if !ramp {
                 self.resample_ratio = new_ratio;
             }
@@ -523,19 +2204,102 @@ where
         }
     }
 
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio, ramp)
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio, ramp)
+    }
+
+    /// Change the maximum relative ratio, see [Resampler::set_max_resample_ratio_relative].
+    /// The internal buffer does not depend on the ratio, only the output buffers need to be longer
+    /// for a wider range.
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        validate_max_relative_ratio(
+            max_resample_ratio_relative,
+            self.resample_ratio_original,
+            self.resample_ratio,
+            self.target_ratio,
+        )?;
+        trace!(
+            "Change maximum relative ratio to {}",
+            max_resample_ratio_relative
+        );
+        self.max_relative_ratio = max_resample_ratio_relative;
+        Ok(())
+    }
+
+    /// Change the chunk size, see [Resampler::set_chunk_size].
+    /// The input frames that are kept as filter history are moved to the end of
+    /// the resized internal buffer.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        let history_len = 2 * self.interpolator.len();
+        for buf in self.buffer.iter_mut() {
+            buf.copy_within(self.chunk_size..self.chunk_size + history_len, 0);
+            buf.resize(chunk_size + history_len, T::zero());
+            buf.copy_within(0..history_len, chunk_size);
+        }
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    /// Load the history, see [Resampler::set_history].
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        validate_history(self.nbr_channels, history)?;
+        self.reset();
+        load_history(
+            &mut self.buffer,
+            self.chunk_size,
+            2 * self.interpolator.len(),
+            history,
+        );
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.interpolator.memory_usage()
+            + heap_size_nested(&self.buffer)
+            + heap_size(&self.channel_mask)
+            + heap_size(&self.positions)
+            + self.fade.memory_usage()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "SincFixedIn",
+            ResamplerAlgorithm::Sinc {
+                sinc_len: self.interpolator.len(),
+                nbr_sincs: self.interpolator.nbr_sincs(),
+                interpolation: self.interpolation,
+                backend: self.interpolator.backend(),
+            },
+            Some(self.resample_ratio),
+        )
     }
 
     fn reset(&mut self) {
+        self.rational_phase = None;
+        self.fade.stop(&mut self.interpolation);
         self.buffer
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
         self.channel_mask.iter_mut().for_each(|val| *val = true);
-        self.last_index = -((self.interpolator.len() / 2) as f64);
+        self.last_index = start_index(
+            self.interpolator.len(),
+            self.interpolator.nbr_sincs(),
+            self.unity_bypass,
+        );
         self.resample_ratio = self.resample_ratio_original;
         self.target_ratio = self.resample_ratio_original;
+        self.bypassing = self.unity_bypass && self.resample_ratio_original == 1.0;
     }
 }
 
@@ -562,13 +2326,8 @@ where
             "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
             resample_ratio, chunk_size, nbr_channels, parameters
         );
-        let interpolator = make_interpolator(
-            parameters.sinc_len,
-            resample_ratio,
-            parameters.f_cutoff,
-            parameters.oversampling_factor,
-            parameters.window,
-        );
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        let interpolator = make_interpolator(resample_ratio, &parameters)?;
 
         Self::new_with_interpolator(
             resample_ratio,
@@ -580,13 +2339,191 @@ where
         )
     }
 
+    /// Create a new SincFixedOut like [SincFixedOut::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory.
+    ///
+    /// The memory is estimated before anything is allocated,
+    /// like for [SincFixedIn::try_new_with_limit].
+    /// The input buffer of SincFixedOut grows with `max_resample_ratio_relative`,
+    /// which is included in the estimate.
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        let (_, buffer_frames) = fixed_out_buffer_sizes(
+            chunk_size,
+            resample_ratio,
+            max_resample_ratio_relative,
+            padded_sinc_len(parameters.sinc_len),
+        );
+        let required = estimate_sinc_memory::<T>(&parameters, buffer_frames, nbr_channels)
+            .saturating_add(buffer_size::<f64>(1, chunk_size));
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new SincFixedOut from a pair of sample rates, that may be fractional.
+    ///
+    /// This is useful for rates that are not whole numbers,
+    /// like the pull-down rates 44100 * 1000 / 1001 Hz and 48000 * 1000 / 1001 Hz.
+    /// The resample ratio is `sample_rate_output / sample_rate_input`.
+    /// When both rates are whole numbers and one of them is at least [HIGH_SAMPLE_RATE](crate::HIGH_SAMPLE_RATE),
+    /// the positions are tracked exactly, like after calling `set_exact_ratio`.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be finite and > 0.
+    /// - `sample_rate_output`: Output sample rate, must be finite and > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the starting ratio, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_sample_rates(
+        sample_rate_input: f64,
+        sample_rate_output: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resample_ratio = ratio_from_sample_rates(sample_rate_input, sample_rate_output)?;
+        let mut resampler = Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )?;
+        if let Some((input, output)) = exact_high_rates(sample_rate_input, sample_rate_output) {
+            debug!(
+                "High sample rates {} and {} Hz, tracking the positions exactly",
+                input, output
+            );
+            resampler
+                .set_exact_ratio(input, output)
+                .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        }
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedOut for high sample rates, like 352.8, 384 or 768 kHz.
+    ///
+    /// The filter given by `parameters` is adapted to the pair of rates.
+    /// When downsampling, the sinc length is increased by the inverse of the ratio,
+    /// so that the transition band relative to the output Nyquist frequency stays as specified.
+    /// Without this, converting for example 768 kHz to 48 kHz with a filter designed
+    /// for a normal ratio leaves a transition band 16 times as wide at the output rate.
+    /// The oversampling factor is reduced if needed to keep the filter bank size reasonable.
+    /// The positions of the output frames are tracked exactly, like after [SincFixedOut::set_exact_ratio].
+    ///
+    /// The long filters need large chunks to be processed efficiently.
+    /// The chunk size must be at least the adapted sinc length converted to output frames,
+    /// otherwise [ResamplerConstructionError::ChunkSizeTooSmall] is returned.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the starting ratio, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation at normal rates, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_high_rate(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resample_ratio = high_rate_ratio(sample_rate_input, sample_rate_output)?;
+        let parameters = high_rate_parameters(resample_ratio, parameters);
+        let minimum = (parameters.sinc_len as f64 * resample_ratio).ceil() as usize;
+        if chunk_size < minimum {
+            return Err(ResamplerConstructionError::ChunkSizeTooSmall {
+                chunk_size,
+                minimum,
+            });
+        }
+        let mut resampler = Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )?;
+        resampler
+            .set_exact_ratio(sample_rate_input, sample_rate_output)
+            .map_err(|_| ResamplerConstructionError::UnsupportedRatio(resample_ratio))?;
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedOut from a latency budget.
+    ///
+    /// The filter is given by `parameters`, typically made from a quality target with
+    /// [SincInterpolationParameters::from_specification]. The chunk size is then chosen
+    /// as the largest that keeps the latency within `max_latency`.
+    /// The latency is the time from when an input frame is given to the resampler,
+    /// until the corresponding output frame is returned, in input frames.
+    /// It is the time to collect the input for a chunk at the starting ratio,
+    /// plus the delay of the filter, which is half the sinc length.
+    /// An error is returned if the delay of the filter alone exceeds the budget.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `max_latency`: Maximum latency in input frames. For a budget in milliseconds,
+    ///   use `sample_rate_input * milliseconds / 1000`.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_latency_budget(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        max_latency: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        let input_frames = chunk_size_for_latency(max_latency, &parameters)?;
+        let chunk_size = ((input_frames as f64 * resample_ratio).floor() as usize).max(1);
+        debug!(
+            "Selected chunk size {} for a latency budget of {} frames",
+            chunk_size, max_latency
+        );
+        Self::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
     /// Create a new SincFixedOut using an existing Interpolator
     ///
     /// Parameters are:
     /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
     /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio` * 10.0 and `resample_ratio` / 10.0.
     /// - `interpolation_type`: Parameters for interpolation, see `SincInterpolationParameters`.
-    /// - `interpolator`: The interpolator to use.
+    /// - `interpolator`: The interpolator to use, either one made by this crate or a custom kernel,
+    ///   see [SincInterpolator]. It must have at least one sinc with at least one point.
     /// - `chunk_size`: Size of output data in frames.
     /// - `nbr_channels`: Number of channels in input/output.
     pub fn new_with_interpolator(
@@ -598,12 +2535,14 @@ where
         nbr_channels: usize,
     ) -> Result<Self, ResamplerConstructionError> {
         validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        validate_interpolator(interpolator.as_ref())?;
 
-        let needed_input_size =
-            (chunk_size as f64 / resample_ratio).ceil() as usize + 2 + interpolator.len() / 2;
-        let buffer_channel_length = ((max_resample_ratio_relative + 1.0) * needed_input_size as f64)
-            as usize
-            + 2 * interpolator.len();
+        let (needed_input_size, buffer_channel_length) = fixed_out_buffer_sizes(
+            chunk_size,
+            resample_ratio,
+            max_resample_ratio_relative,
+            interpolator.len(),
+        );
         let buffer = vec![vec![T::zero(); buffer_channel_length]; nbr_channels];
         let channel_mask = vec![true; nbr_channels];
 
@@ -617,35 +2556,256 @@ where
             resample_ratio_original: resample_ratio,
             target_ratio: resample_ratio,
             max_relative_ratio: max_resample_ratio_relative,
+            rational_phase: None,
             interpolator,
             buffer,
             interpolation: interpolation_type,
             channel_mask,
+            block_size: DEFAULT_BLOCK_SIZE,
+            unity_bypass: false,
+            bypassing: false,
+            fade: InterpolationFade::new(DEFAULT_BLOCK_SIZE),
+            positions: Vec::with_capacity(DEFAULT_BLOCK_SIZE),
+            ratio_envelope: Vec::with_capacity(chunk_size),
         })
     }
+
+    /// Create a new SincFixedOut that uses a shared filter bank, see [SincFilterBank].
+    /// This only allocates the buffers for the input history, and not the filters.
+    ///
+    /// Parameters are:
+    /// - `bank`: The shared filters. The starting ratio is the one of the bank.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to the ratio of the bank, must be >= 1.0.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new_with_bank(
+        bank: &SincFilterBank<T>,
+        max_resample_ratio_relative: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_interpolator(
+            bank.resample_ratio,
+            max_resample_ratio_relative,
+            bank.interpolation,
+            Box::new(bank.clone()),
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Set the number of output frames that are processed as one block.
+    ///
+    /// The positions of a block of output frames are calculated first,
+    /// and then the frames are interpolated one channel at a time.
+    /// Larger blocks keep the data of each channel in the cache for longer,
+    /// which can improve the throughput when processing many channels.
+    /// Smaller blocks use less scratch memory.
+    /// This does not change the output, or the latency which is given by the chunk size.
+    /// The default block size is 1, and a value of 0 is treated as 1.
+    /// The scratch buffer for the block is allocated here and not during processing.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        self.block_size = block_size.max(1);
+        self.positions = Vec::with_capacity(self.block_size);
+        self.fade.set_block_size(self.block_size);
+    }
+
+    /// Get the number of output frames that are processed as one block.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Change the interpolation type, with a crossfade over `fade_frames` output frames.
+    ///
+    /// The interpolation type sets how many points of the oversampled sinc filter are calculated
+    /// for each output frame, from four for [SincInterpolationType::Cubic]
+    /// down to one for [SincInterpolationType::Nearest].
+    /// Only the degree of the polynomial interpolation between these points is changed,
+    /// the length of the sinc filter stays the same.
+    /// Switching to a cheaper type reduces the processing load, at the cost of more interpolation noise.
+    /// This can be used to degrade the quality gracefully when the caller detects CPU pressure,
+    /// and to restore it again later.
+    /// The filter, the input history and the delay are the same for all types,
+    /// so the output continues without any jump in time.
+    /// While fading, the output frames are calculated with both the old and the new type,
+    /// which temporarily increases the load.
+    /// A `fade_frames` of zero switches at the start of the next chunk without a crossfade.
+    /// A change made while a crossfade is running waits until that crossfade is complete,
+    /// so that the output never jumps. Only the last of several waiting changes is kept.
+    /// The crossfade buffers are allocated by the constructor and by [set_block_size](Self::set_block_size),
+    /// and not during processing.
+    pub fn set_interpolation_type(
+        &mut self,
+        interpolation: SincInterpolationType,
+        fade_frames: usize,
+    ) {
+        debug!(
+            "Change interpolation type from {:?} to {:?}, fade frames: {}",
+            self.interpolation_type(),
+            interpolation,
+            fade_frames
+        );
+        if self.bypassing {
+            // The bypassed output does not depend on the type, so it does not need a fade
+            self.interpolation = interpolation;
+        } else {
+            self.fade
+                .change_type(&mut self.interpolation, interpolation, fade_frames);
+        }
+    }
+
+    /// Get the interpolation type, including a change that waits for a running crossfade.
+    pub fn interpolation_type(&self) -> SincInterpolationType {
+        self.fade.next_type().unwrap_or(self.interpolation)
+    }
+
+    /// Enable or disable copying the input directly to the output while the ratio is 1.0.
+    ///
+    /// At a ratio of exactly 1.0 the input frames are copied with the same delay
+    /// as the interpolated output, instead of being filtered.
+    /// This saves nearly all of the processing, but means that the anti-aliasing filter
+    /// is not applied while bypassing.
+    /// The bypass is used for chunks where both the current and the target ratio are 1.0,
+    /// and the output frames fall exactly on input frames.
+    /// This is the case for a resampler created with a ratio of 1.0, until the ratio is changed,
+    /// and again after it has been reset.
+    /// To make the output frames fall on input frames, the resampler starts one oversampling step
+    /// of the sinc filter earlier when the bypass is enabled.
+    /// Changing the setting therefore resets the resampler.
+    /// When the ratio is changed away from 1.0, the interpolation takes over without any jump in time,
+    /// with a crossfade over the length of the sinc filter.
+    /// The bypass is disabled by default.
+    pub fn set_unity_bypass(&mut self, enabled: bool) {
+        if enabled != self.unity_bypass {
+            self.unity_bypass = enabled;
+            self.reset();
+        }
+    }
+
+    /// Returns true if copying the input directly to the output at a ratio of 1.0 is enabled.
+    pub fn unity_bypass(&self) -> bool {
+        self.unity_bypass
+    }
+
+    /// Get the coefficients of the anti-aliasing filter in use,
+    /// as one vector of [SincInterpolator::len] values for each of the oversampled sincs.
+    /// See [SincInterpolator::coefficients].
+    pub fn sinc_coefficients(&self) -> Vec<Vec<T>> {
+        self.interpolator.coefficients()
+    }
+
+    /// Get the implementation used by the interpolator for the scalar products.
+    /// This shows which SIMD instructions were selected at runtime, see [InterpolatorBackend].
+    pub fn interpolator_backend(&self) -> InterpolatorBackend {
+        self.interpolator.backend()
+    }
+
+    /// Set an exact resample ratio, given by a pair of integer sample rates.
+    ///
+    /// The position in the input is then tracked with integer arithmetic,
+    /// so that the output stays aligned with the ratio `sample_rate_output / sample_rate_input`
+    /// over streams of any length, instead of slowly drifting because of floating point rounding.
+    /// The ratio is applied from the start of the next chunk, without ramping,
+    /// and must be within the range allowed by the constructor.
+    /// Changing the ratio in any other way, or resetting the resampler, ends the exact tracking.
+    pub fn set_exact_ratio(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> ResampleResult<()> {
+        self.set_resample_ratio(sample_rate_output as f64 / sample_rate_input as f64, false)?;
+        self.rational_phase = Some(RationalPhase::new(
+            sample_rate_input,
+            sample_rate_output,
+            self.last_index,
+        ));
+        Ok(())
+    }
+
+    /// Get the number of input frames needed to produce at least `output_frames` frames of output,
+    /// starting from the current state of the resampler.
+    ///
+    /// This is intended for pull-based hosts that must deliver a given number of output frames.
+    /// The output is produced in chunks of `chunk_size` frames, and the result is the total
+    /// number of input frames for the chunks needed to reach `output_frames`.
+    /// The first of these chunks needs [input_frames_next](Resampler::input_frames_next) frames.
+    /// A ratio change that is being ramped is included in the calculation.
+    /// The result is valid until the resample ratio is changed, or the resampler is reset.
+    pub fn input_frames_needed_for(&self, output_frames: usize) -> usize {
+        ChunkState {
+            chunk_size: self.chunk_size,
+            filter_len: self.interpolator.len(),
+            last_index: self.last_index,
+            resample_ratio: self.resample_ratio,
+            target_ratio: self.target_ratio,
+        }
+        .fixed_out_frames_needed(output_frames, self.needed_input_size)
+    }
+
+    /// Set the resample ratio separately for each output frame of the next chunk.
+    ///
+    /// This renders varispeed effects and scrubbing sample-accurately,
+    /// instead of changing the ratio in steps or linear ramps between chunks.
+    /// The `ratios` slice must contain one absolute ratio per output frame, `chunk_size` values in total,
+    /// and each ratio must be within the range allowed by [Resampler::set_resample_ratio].
+    /// The number of input frames needed for the next chunk, as given by
+    /// [input_frames_next](Resampler::input_frames_next), is updated to match the envelope.
+    ///
+    /// The envelope only applies to the next call to
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    /// After that the last ratio of the envelope is kept.
+    /// Calling [set_resample_ratio](Resampler::set_resample_ratio) or [reset](Resampler::reset)
+    /// before processing discards the envelope.
+    pub fn set_ratio_envelope(&mut self, ratios: &[f64]) -> ResampleResult<()> {
+        if ratios.len() != self.chunk_size {
+            return Err(ResampleError::WrongEnvelopeLength {
+                expected: self.chunk_size,
+                actual: ratios.len(),
+            });
+        }
+        if let Some(ratio) = ratios.iter().find(|ratio| {
+            !(**ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio
+                && **ratio / self.resample_ratio_original <= self.max_relative_ratio)
+        }) {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: *ratio,
+                original: self.resample_ratio_original,
+                max_relative_ratio: self.max_relative_ratio,
+            });
+        }
+        trace!(
+            "Set ratio envelope, {} to {}",
+            ratios[0],
+            ratios[ratios.len() - 1]
+        );
+        self.ratio_envelope.clear();
+        self.ratio_envelope.extend_from_slice(ratios);
+        self.rational_phase = None;
+        let t_total: f64 = ratios.iter().map(|ratio| 1.0 / ratio).sum();
+        self.resample_ratio = ratios[ratios.len() - 1];
+        self.target_ratio = self.resample_ratio;
+        self.needed_input_size =
+            ((self.last_index + t_total + self.interpolator.len() as f64).ceil() + 2.0) as usize;
+        Ok(())
+    }
 }
 
-impl<T> Resampler<T> for SincFixedOut<T>
+impl<T> SincFixedOut<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
         &mut self,
-        wave_in: &[Vin],
+        wave_in: &I,
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.needed_input_size,
             self.chunk_size,
         )?;
@@ -660,12 +2820,13 @@ where
         }
         self.current_buffer_fill = self.needed_input_size;
 
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                debug_assert!(self.chunk_size <= wave_out[chan].as_mut().len());
-                self.buffer[chan][2 * sinc_len..2 * sinc_len + self.needed_input_size]
-                    .copy_from_slice(&wave_in[chan].as_ref()[..self.needed_input_size]);
-            }
+        for (chan, chan_in, chan_out) in layout.active_channels(&self.channel_mask) {
+            debug_assert!(self.chunk_size <= wave_out[chan_out].as_mut().len());
+            wave_in.copy_frames(
+                chan_in,
+                0,
+                &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.needed_input_size],
+            );
         }
 
         let mut idx = self.last_index;
@@ -673,113 +2834,119 @@ where
         let t_ratio_end = 1.0 / self.target_ratio;
         let t_ratio_increment = (t_ratio_end - t_ratio) / self.chunk_size as f64;
 
-        match self.interpolation {
-            SincInterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_cubic(frac_offset, &points);
-                        }
-                    }
-                }
-            }
-            SincInterpolationType::Quadratic => {
-                let mut points = [T::zero(); 3];
-                let mut nearest = [(0isize, 0isize); 3];
-                for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_quad(frac_offset, &points);
-                        }
-                    }
-                }
+        let mut n = 0;
+        let bypass = if self.ratio_envelope.is_empty() {
+            bypass_frame(
+                self.unity_bypass,
+                self.resample_ratio,
+                self.target_ratio,
+                idx,
+                oversampling_factor,
+            )
+        } else {
+            None
+        };
+        if bypass.is_some() != self.bypassing {
+            // Crossfade between the bypassed and the interpolated output
+            self.bypassing = bypass.is_some();
+            self.fade
+                .switch_bypass(&mut self.interpolation, self.bypassing, sinc_len);
+        }
+        // Flush denormals in the inner loops only, if requested by FlushDenormals
+        let flush = flush_scope();
+        if let Some(frame) = bypass {
+            // Continue exactly on the phase of the bypass
+            idx = frame as f64 - 1.0 / oversampling_factor as f64;
+            n = self.chunk_size;
+            for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                copy_bypass(
+                    &self.buffer[chan],
+                    &mut wave_out[chan_out],
+                    frame,
+                    sinc_len,
+                    n,
+                );
             }
-            SincInterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                for n in 0..self.chunk_size {
+            fade_bypassed(
+                &mut self.fade,
+                &mut self.interpolation,
+                self.interpolator.as_ref(),
+                &self.buffer,
+                wave_out,
+                layout,
+                &self.channel_mask,
+                idx,
+                n,
+            )?;
+            idx += n as f64;
+        }
+        while n < self.chunk_size {
+            self.positions.clear();
+            let block_len = self.block_size.min(self.chunk_size - n);
+            for m in n..n + block_len {
+                if self.ratio_envelope.is_empty() {
                     t_ratio += t_ratio_increment;
                     idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_lin(frac_offset, &points);
-                        }
-                    }
+                } else {
+                    idx += 1.0 / self.ratio_envelope[m];
                 }
+                self.positions.push(frame_position(
+                    idx,
+                    oversampling_factor,
+                    &self.interpolation,
+                ));
+                self.fade.push(idx, oversampling_factor);
             }
-            SincInterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                for n in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            point = self.interpolator.get_sinc_interpolated(
-                                buf,
-                                (nearest.0 + 2 * sinc_len as isize) as usize,
-                                nearest.1 as usize,
-                            );
-                            wave_out[chan].as_mut()[n] = point;
-                        }
-                    }
+            if self.channel_mask == [true, true] {
+                // Stereo with both channels active, interpolate the channels together
+                let (left, right) = wave_out.split_at_mut(1);
+                let left_out = &mut left[0].as_mut()[n..n + block_len];
+                let right_out = &mut right[0].as_mut()[n..n + block_len];
+                interpolate_block_stereo(
+                    self.interpolator.as_ref(),
+                    &self.interpolation,
+                    [&self.buffer[0], &self.buffer[1]],
+                    &self.positions,
+                    [&mut *left_out, &mut *right_out],
+                )?;
+                self.fade
+                    .apply(self.interpolator.as_ref(), &self.buffer[0], left_out)?;
+                self.fade
+                    .apply(self.interpolator.as_ref(), &self.buffer[1], right_out)?;
+            } else {
+                for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                    let block_out = &mut wave_out[chan_out].as_mut()[n..n + block_len];
+                    interpolate_block(
+                        self.interpolator.as_ref(),
+                        &self.interpolation,
+                        &self.buffer[chan],
+                        &self.positions,
+                        block_out,
+                    )?;
+                    self.fade
+                        .apply(self.interpolator.as_ref(), &self.buffer[chan], block_out)?;
                 }
             }
+            self.fade.advance(&mut self.interpolation);
+            n += block_len;
         }
 
+        drop(flush);
+
         // store last index for next iteration
         let input_frames_used = self.needed_input_size;
-        self.last_index = idx - self.current_buffer_fill as f64;
+        self.ratio_envelope.clear();
+        self.last_index = match self.rational_phase.as_mut() {
+            Some(phase) => phase.advance(self.chunk_size, self.current_buffer_fill),
+            None => idx - self.current_buffer_fill as f64,
+        };
         self.resample_ratio = self.target_ratio;
-        self.needed_input_size = (self.last_index as f32
+        // Add the margin before converting, since the rest can be negative for short chunks
+        self.needed_input_size = ((self.last_index as f32
             + self.chunk_size as f32 / self.resample_ratio as f32
             + sinc_len as f32)
-            .ceil() as usize
-            + 2;
+            .ceil()
+            + 2.0) as usize;
         trace!(
             "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
             active_channels_mask,
@@ -790,9 +2957,32 @@ where
         );
         Ok((input_frames_used, self.chunk_size))
     }
+}
+
+impl<T> Resampler<T> for SincFixedOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask)
+    }
 
     fn input_frames_max(&self) -> usize {
-        (self.chunk_size as f64 * self.resample_ratio_original * self.max_relative_ratio).ceil()
+        (self.chunk_size as f64 / self.resample_ratio_original * self.max_relative_ratio).ceil()
             as usize
             + 2
             + self.interpolator.len() / 2
@@ -818,38 +3008,140 @@ where
         (self.interpolator.len() as f64 * self.resample_ratio / 2.0) as usize
     }
 
+    fn input_delay(&self) -> usize {
+        self.interpolator.len() / 2
+    }
+
     fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
         trace!("Change resample ratio to {}", new_ratio);
         if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
             && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
         {
+            self.rational_phase = None;
             if !ramp {
                 self.resample_ratio = new_ratio;
             }
-            self.target_ratio = new_ratio;
-
-            self.needed_input_size = (self.last_index as f32
-                + self.chunk_size as f32
-                    / (0.5 * self.resample_ratio as f32 + 0.5 * self.target_ratio as f32)
-                + self.interpolator.len() as f32)
-                .ceil() as usize
-                + 2;
-            Ok(())
-        } else {
-            Err(ResampleError::RatioOutOfBounds {
-                provided: new_ratio,
-                original: self.resample_ratio_original,
-                max_relative_ratio: self.max_relative_ratio,
-            })
+            self.target_ratio = new_ratio;
+            self.ratio_envelope.clear();
+
+            self.needed_input_size = ((self.last_index as f32
+                + ramp_input_frames(self.chunk_size, self.resample_ratio, self.target_ratio)
+                    as f32
+                + self.interpolator.len() as f32)
+                .ceil()
+                + 2.0) as usize;
+            Ok(())
+        } else {
+            Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio_original,
+                max_relative_ratio: self.max_relative_ratio,
+            })
+        }
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio, ramp)
+    }
+
+    /// Change the maximum relative ratio, see [Resampler::set_max_resample_ratio_relative].
+    /// The internal buffer is extended when the range is widened, and is not shrunk.
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        validate_max_relative_ratio(
+            max_resample_ratio_relative,
+            self.resample_ratio_original,
+            self.resample_ratio,
+            self.target_ratio,
+        )?;
+        trace!(
+            "Change maximum relative ratio to {}",
+            max_resample_ratio_relative
+        );
+        self.max_relative_ratio = max_resample_ratio_relative;
+        let buffer_channel_length = ((self.max_relative_ratio + 1.0)
+            * self.input_frames_max() as f64) as usize
+            + 2 * self.interpolator.len();
+        for buf in self.buffer.iter_mut() {
+            if buf.len() < buffer_channel_length {
+                buf.resize(buffer_channel_length, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the chunk size, see [Resampler::set_chunk_size].
+    /// A ratio envelope that was set for the next chunk is discarded.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        let sinc_len = self.interpolator.len();
+        self.chunk_size = chunk_size;
+        self.ratio_envelope.clear();
+        self.ratio_envelope.reserve(chunk_size);
+        self.needed_input_size = ((self.last_index as f32
+            + ramp_input_frames(self.chunk_size, self.resample_ratio, self.target_ratio) as f32
+            + sinc_len as f32)
+            .ceil()
+            + 2.0) as usize;
+        // The filter history stays in place, only the length of the buffer may need to grow
+        let buffer_channel_length = ((self.max_relative_ratio + 1.0)
+            * self.input_frames_max() as f64) as usize
+            + 2 * sinc_len;
+        for buf in self.buffer.iter_mut() {
+            if buf.len() < buffer_channel_length {
+                buf.resize(buffer_channel_length, T::zero());
+            }
         }
+        Ok(())
     }
 
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio, ramp)
+    /// Load the history, see [Resampler::set_history].
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        validate_history(self.nbr_channels, history)?;
+        self.reset();
+        load_history(
+            &mut self.buffer,
+            self.current_buffer_fill,
+            2 * self.interpolator.len(),
+            history,
+        );
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.interpolator.memory_usage()
+            + heap_size_nested(&self.buffer)
+            + heap_size(&self.channel_mask)
+            + heap_size(&self.positions)
+            + self.fade.memory_usage()
+            + heap_size(&self.ratio_envelope)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "SincFixedOut",
+            ResamplerAlgorithm::Sinc {
+                sinc_len: self.interpolator.len(),
+                nbr_sincs: self.interpolator.nbr_sincs(),
+                interpolation: self.interpolation,
+                backend: self.interpolator.backend(),
+            },
+            Some(self.resample_ratio),
+        )
     }
 
     fn reset(&mut self) {
+        self.rational_phase = None;
+        self.fade.stop(&mut self.interpolation);
         self.buffer
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
@@ -858,22 +3150,38 @@ where
             + 2
             + self.interpolator.len() / 2;
         self.current_buffer_fill = self.needed_input_size;
-        self.last_index = -((self.interpolator.len() / 2) as f64);
+        self.ratio_envelope.clear();
+        self.last_index = start_index(
+            self.interpolator.len(),
+            self.interpolator.nbr_sincs(),
+            self.unity_bypass,
+        );
         self.channel_mask.iter_mut().for_each(|val| *val = true);
         self.resample_ratio = self.resample_ratio_original;
         self.target_ratio = self.resample_ratio_original;
+        self.bypassing = self.unity_bypass && self.resample_ratio_original == 1.0;
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
-    use super::{interp_cubic, interp_lin};
+    use super::{
+        frame_position, interp_cubic, interp_lin, interpolate_block, make_table_interpolator,
+        sinc_table_source,
+    };
     use crate::check_output;
+    use crate::sinc_interpolator::{InterpolatorBackend, ScalarInterpolator, SincInterpolator};
+    use crate::tests::make_sinc_params;
+    use crate::FilterDesign;
+    use crate::GainNormalization;
+    use crate::ResampleError;
     use crate::Resampler;
+    use crate::ResamplerConstructionError;
     use crate::SincInterpolationParameters;
     use crate::SincInterpolationType;
+    use crate::SpeechPreset;
     use crate::WindowFunction;
-    use crate::{SincFixedIn, SincFixedOut};
+    use crate::{SincFilterBank, SincFixedIn, SincFixedOut};
     use rand::Rng;
 
     #[test]
@@ -884,6 +3192,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let _resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
@@ -899,6 +3208,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let _resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let yvals = [1.0f32, 5.0f32];
@@ -914,6 +3224,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let _resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
@@ -929,6 +3240,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let _resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let yvals = [1.0f64, 5.0f64];
@@ -944,6 +3256,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let waves = vec![vec![0.0f64; 1024]; 2];
@@ -975,6 +3288,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
 
@@ -1000,6 +3314,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let waves = vec![vec![0.0f32; 1024]; 2];
@@ -1031,6 +3346,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let waves = vec![vec![0.0f64; 1024], Vec::new()];
@@ -1056,6 +3372,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 160,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler =
             SincFixedIn::<f64>::new(16000 as f64 / 96000 as f64, 1.0, params, 1024, 2).unwrap();
@@ -1089,6 +3406,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 160,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler =
             SincFixedIn::<f64>::new(192000 as f64 / 44100 as f64, 1.0, params, 1024, 2).unwrap();
@@ -1113,6 +3431,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn interpolate_block_out_of_range() {
+        let sincs = vec![vec![0.25f64; 8]; 4];
+        let interpolator = ScalarInterpolator::from_sincs(sincs);
+        let interpolation = SincInterpolationType::Cubic;
+        // The sinc for index 0 starts at frame 16 of the buffer, and the interpolators
+        // need the buffer to be longer than the end of the sinc.
+        let buf = vec![1.0f64; 25];
+        let mut out = vec![0.0; 2];
+        let positions = vec![
+            frame_position(-0.5, 4, &interpolation),
+            frame_position(0.0, 4, &interpolation),
+        ];
+        interpolate_block(&interpolator, &interpolation, &buf, &positions, &mut out).unwrap();
+        let positions = vec![
+            frame_position(0.0, 4, &interpolation),
+            frame_position(1.0, 4, &interpolation),
+        ];
+        let err = interpolate_block(&interpolator, &interpolation, &buf, &positions, &mut out)
+            .unwrap_err();
+        assert!(matches!(err, ResampleError::InterpolationOutOfRange));
+        assert_eq!(err.code(), 217);
+    }
+
+    #[test]
+    fn input_frames_during_ramp_fo() {
+        let params = make_sinc_params(SincInterpolationType::Linear);
+        let mut resampler = SincFixedOut::<f64>::new(1.0, 4.0, params, 1024, 1).unwrap();
+        resampler.set_resample_ratio_relative(0.25, true).unwrap();
+        for _ in 0..3 {
+            let frames = resampler.input_frames_next();
+            assert!(frames <= resampler.input_frames_max());
+            let waves = vec![vec![0.0f64; frames]; 1];
+            let out = resampler.process(&waves, None).unwrap();
+            assert_eq!(out[0].len(), 1024);
+            // The interpolation must not have stepped past the end of the new input
+            assert!(resampler.last_index < 0.0);
+            resampler.set_resample_ratio_relative(4.0, true).unwrap();
+        }
+    }
+
     #[test]
     fn make_resampler_fo() {
         let params = SincInterpolationParameters {
@@ -1121,6 +3480,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let frames = resampler.input_frames_next();
@@ -1140,6 +3500,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let frames = resampler.input_frames_next();
@@ -1171,6 +3532,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let frames = resampler.input_frames_next();
@@ -1190,6 +3552,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         let frames = resampler.input_frames_next();
@@ -1229,6 +3592,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 160,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f64>::new(0.125, 1.0, params, 1024, 2).unwrap();
         let frames = resampler.input_frames_next();
@@ -1277,6 +3641,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 160,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f64>::new(8.0, 1.0, params, 1024, 2).unwrap();
         let frames = resampler.input_frames_next();
@@ -1325,6 +3690,7 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         check_output!(check_fo_output, resampler);
@@ -1338,8 +3704,907 @@ mod tests {
             interpolation: SincInterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
         };
         let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
         check_output!(check_fo_output, resampler);
     }
+
+    #[test]
+    fn check_fi_output_droop_compensation() {
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            droop_compensation: true,
+            ..Default::default()
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        check_output!(check_fi_output_droop_compensation, resampler);
+    }
+
+    #[test]
+    fn check_fo_output_equiripple() {
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.9,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: FilterDesign::Equiripple,
+            normalization: GainNormalization::Passband,
+            ..Default::default()
+        };
+        let mut resampler = SincFixedOut::<f64>::new(0.8, 1.0, params, 1024, 2).unwrap();
+        check_output!(check_fo_output_equiripple, resampler);
+    }
+
+    #[test]
+    fn equiripple_invalid_parameters() {
+        let make_params = |f_cutoff, sinc_len| SincInterpolationParameters {
+            sinc_len,
+            f_cutoff,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: FilterDesign::Equiripple,
+            normalization: GainNormalization::Passband,
+            ..Default::default()
+        };
+        let result = SincFixedIn::<f64>::new(1.2, 1.0, make_params(0.4, 32), 1024, 2);
+        assert_eq!(result.err().map(|err| err.code()), Some(105));
+        let result = SincFixedIn::<f64>::new(1.2, 1.0, make_params(0.95, 256), 1024, 2);
+        assert_eq!(result.err().map(|err| err.code()), Some(106));
+    }
+
+    fn make_block_params(interpolation: usize) -> SincInterpolationParameters {
+        let interpolation = match interpolation {
+            0 => SincInterpolationType::Cubic,
+            1 => SincInterpolationType::Quadratic,
+            2 => SincInterpolationType::Linear,
+            _ => SincInterpolationType::Nearest,
+        };
+        make_sinc_params(interpolation)
+    }
+
+    #[test]
+    fn block_size_does_not_change_output() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<f64>> = (0..3)
+            .map(|_| (0..2048).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+        for interpolation in 0..4 {
+            let mut reference =
+                SincFixedIn::<f64>::new(1.2, 1.1, make_block_params(interpolation), 1024, 3)
+                    .unwrap();
+            let mut resampler =
+                SincFixedIn::<f64>::new(1.2, 1.1, make_block_params(interpolation), 1024, 3)
+                    .unwrap();
+            resampler.set_block_size(64);
+            assert_eq!(resampler.block_size(), 64);
+            for _ in 0..3 {
+                reference.set_resample_ratio_relative(1.05, true).unwrap();
+                resampler.set_resample_ratio_relative(1.05, true).unwrap();
+                assert_eq!(
+                    reference.process(&waves, None).unwrap(),
+                    resampler.process(&waves, None).unwrap()
+                );
+            }
+
+            let mut reference =
+                SincFixedOut::<f64>::new(0.8, 1.1, make_block_params(interpolation), 1000, 3)
+                    .unwrap();
+            let mut resampler =
+                SincFixedOut::<f64>::new(0.8, 1.1, make_block_params(interpolation), 1000, 3)
+                    .unwrap();
+            resampler.set_block_size(64);
+            for _ in 0..3 {
+                let frames = reference.input_frames_next();
+                assert_eq!(frames, resampler.input_frames_next());
+                let chunk: Vec<&[f64]> = waves.iter().map(|wave| &wave[..frames]).collect();
+                assert_eq!(
+                    reference.process(&chunk, None).unwrap(),
+                    resampler.process(&chunk, None).unwrap()
+                );
+            }
+        }
+    }
+
+    /// A Hann windowed sinc that is evaluated for each point, without a table.
+    struct TableFreeKernel {
+        len: usize,
+        nbr_sincs: usize,
+        cutoff: f64,
+        gain: f64,
+    }
+
+    impl TableFreeKernel {
+        fn new(len: usize, nbr_sincs: usize, cutoff: f64) -> Self {
+            let mut kernel = TableFreeKernel {
+                len,
+                nbr_sincs,
+                cutoff,
+                gain: 1.0,
+            };
+            let total: f64 = (0..nbr_sincs)
+                .map(|sub| (0..len).map(|p| kernel.point(p, sub)).sum::<f64>())
+                .sum();
+            kernel.gain = nbr_sincs as f64 / total;
+            kernel
+        }
+
+        fn point(&self, p: usize, subindex: usize) -> f64 {
+            let time = p as f64 + (self.nbr_sincs - 1 - subindex) as f64 / self.nbr_sincs as f64
+                - (self.len / 2) as f64;
+            let window = 0.5 + 0.5 * (2.0 * std::f64::consts::PI * time / self.len as f64).cos();
+            self.gain * window * crate::sinc::sinc(time * self.cutoff)
+        }
+    }
+
+    impl SincInterpolator<f64> for TableFreeKernel {
+        fn get_sinc_interpolated(&self, wave: &[f64], index: usize, subindex: usize) -> f64 {
+            wave[index..index + self.len]
+                .iter()
+                .enumerate()
+                .map(|(p, value)| value * self.point(p, subindex))
+                .sum()
+        }
+
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn nbr_sincs(&self) -> usize {
+            self.nbr_sincs
+        }
+    }
+
+    #[test]
+    fn custom_kernel() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<f64>> = vec![(0..1024).map(|_| rng.gen_range(-1.0..1.0)).collect()];
+        // The table is designed with an f32 cutoff
+        let kernel = TableFreeKernel::new(64, 32, 0.9f32 as f64);
+        assert_eq!(kernel.backend(), InterpolatorBackend::Custom);
+        let mut custom = SincFixedIn::<f64>::new_with_interpolator(
+            1.2,
+            1.1,
+            SincInterpolationType::Cubic,
+            Box::new(kernel),
+            1024,
+            1,
+        )
+        .unwrap();
+        let table = ScalarInterpolator::<f64>::new(64, 32, 0.9, WindowFunction::Hann);
+        let mut reference = SincFixedIn::<f64>::new_with_interpolator(
+            1.2,
+            1.1,
+            SincInterpolationType::Cubic,
+            Box::new(table),
+            1024,
+            1,
+        )
+        .unwrap();
+        for _ in 0..2 {
+            let out = custom.process(&waves, None).unwrap();
+            let expected = reference.process(&waves, None).unwrap();
+            assert_eq!(out[0].len(), expected[0].len());
+            for (value, expected) in out[0].iter().zip(expected[0].iter()) {
+                assert!((value - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_kernel() {
+        let kernel = TableFreeKernel {
+            len: 0,
+            nbr_sincs: 32,
+            cutoff: 0.9,
+            gain: 1.0,
+        };
+        let result = SincFixedOut::<f64>::new_with_interpolator(
+            1.2,
+            1.1,
+            SincInterpolationType::Cubic,
+            Box::new(kernel),
+            1024,
+            1,
+        );
+        assert!(matches!(
+            result,
+            Err(ResamplerConstructionError::EmptyFilter)
+        ));
+    }
+
+    #[test]
+    fn stereo_matches_mono() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<f64>> = (0..2)
+            .map(|_| (0..2048).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+        for interpolation in 0..4 {
+            let mut stereo =
+                SincFixedIn::<f64>::new(1.2, 1.1, make_block_params(interpolation), 1024, 2)
+                    .unwrap();
+            let mut left =
+                SincFixedIn::<f64>::new(1.2, 1.1, make_block_params(interpolation), 1024, 1)
+                    .unwrap();
+            let mut right =
+                SincFixedIn::<f64>::new(1.2, 1.1, make_block_params(interpolation), 1024, 1)
+                    .unwrap();
+            for _ in 0..2 {
+                let out = stereo.process(&waves, None).unwrap();
+                assert_eq!(out[0], left.process(&waves[..1], None).unwrap()[0]);
+                assert_eq!(out[1], right.process(&waves[1..], None).unwrap()[0]);
+            }
+
+            let mut stereo =
+                SincFixedOut::<f64>::new(0.8, 1.1, make_block_params(interpolation), 1000, 2)
+                    .unwrap();
+            let mut left =
+                SincFixedOut::<f64>::new(0.8, 1.1, make_block_params(interpolation), 1000, 1)
+                    .unwrap();
+            let mut right =
+                SincFixedOut::<f64>::new(0.8, 1.1, make_block_params(interpolation), 1000, 1)
+                    .unwrap();
+            for _ in 0..2 {
+                let frames = stereo.input_frames_next();
+                let chunk: Vec<&[f64]> = waves.iter().map(|wave| &wave[..frames]).collect();
+                let out = stereo.process(&chunk, None).unwrap();
+                assert_eq!(out[0], left.process(&chunk[..1], None).unwrap()[0]);
+                assert_eq!(out[1], right.process(&chunk[1..], None).unwrap()[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn parameters_from_specification() {
+        let params = SincInterpolationParameters::from_specification(
+            0.05,
+            100.0,
+            SincInterpolationType::Linear,
+            128,
+        )
+        .unwrap();
+        assert!(matches!(params.window, WindowFunction::BlackmanHarris));
+        assert!(params.f_cutoff >= 0.95);
+        assert_eq!(params.sinc_len % 8, 0);
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        check_output!(parameters_from_specification, resampler);
+        assert!(SincInterpolationParameters::from_specification(
+            0.05,
+            200.0,
+            SincInterpolationType::Linear,
+            128
+        )
+        .is_err());
+        assert!(SincInterpolationParameters::from_specification(
+            0.0,
+            100.0,
+            SincInterpolationType::Linear,
+            128
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn speech_presets() {
+        // A tone in the speech band passes, and one above the output Nyquist is removed
+        for (preset, fs_in, fs_out, pass, stop) in [
+            (SpeechPreset::Narrowband, 16000.0, 8000.0, 3400.0, 4400.0),
+            (SpeechPreset::Wideband, 48000.0, 16000.0, 7000.0, 9500.0),
+        ] {
+            // Down by about 1 dB at the top of the passband, and at least 75 dB in the stopband
+            for (freq, low, high) in [(pass, 0.88, 1.01), (stop, 0.0, 1.8e-4)] {
+                let mut resampler = SincFixedIn::<f64>::new(
+                    fs_out / fs_in,
+                    1.0,
+                    SincInterpolationParameters::speech(preset),
+                    4096,
+                    1,
+                )
+                .unwrap();
+                let wave = (0..4096)
+                    .map(|n| (2.0 * std::f64::consts::PI * freq * n as f64 / fs_in).sin())
+                    .collect::<Vec<_>>();
+                let output = resampler.process(&[wave], None).unwrap();
+                let tail = &output[0][200..];
+                let amplitude =
+                    (2.0 * tail.iter().map(|v| v * v).sum::<f64>() / tail.len() as f64).sqrt();
+                assert!(
+                    amplitude > low && amplitude < high,
+                    "{:?} {}",
+                    preset,
+                    amplitude
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fractional_sample_rates() {
+        let sample_rate_out = 44100.0 * 1000.0 / 1001.0;
+        let resampler = SincFixedIn::<f64>::new_with_sample_rates(
+            44100.0,
+            sample_rate_out,
+            1.0,
+            make_block_params(0),
+            1024,
+            2,
+        )
+        .unwrap();
+        assert!((resampler.resample_ratio - 1000.0 / 1001.0).abs() < 1.0e-12);
+        let resampler = SincFixedOut::<f64>::new_with_sample_rates(
+            44100.0,
+            sample_rate_out,
+            1.0,
+            make_block_params(0),
+            1024,
+            2,
+        )
+        .unwrap();
+        assert!((resampler.resample_ratio - 1000.0 / 1001.0).abs() < 1.0e-12);
+        let err = SincFixedOut::<f64>::new_with_sample_rates(
+            f64::INFINITY,
+            sample_rate_out,
+            1.0,
+            make_block_params(0),
+            1024,
+            2,
+        );
+        assert_eq!(err.err().map(|err| err.code()), Some(107));
+    }
+
+    #[test]
+    fn input_frames_needed_fi() {
+        let mut resampler =
+            SincFixedIn::<f64>::new(0.8, 2.0, make_block_params(0), 256, 1).unwrap();
+        let waves = vec![vec![0.0f64; 256]; 1];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_resample_ratio_relative(1.5, true).unwrap();
+        let needed = resampler.input_frames_needed_for(1000);
+        assert_eq!(needed % 256, 0);
+        let mut produced = 0;
+        let mut consumed = 0;
+        while produced < 1000 {
+            produced += resampler.process(&waves, None).unwrap()[0].len();
+            consumed += 256;
+        }
+        assert_eq!(consumed, needed);
+    }
+
+    #[test]
+    fn unity_bypass() {
+        let input: Vec<f64> = (0..8192).map(|n| (n as f64 * 0.02).sin()).collect();
+        let mut reference =
+            SincFixedIn::<f64>::new(1.0, 2.0, make_block_params(0), 256, 1).unwrap();
+        let mut resampler =
+            SincFixedIn::<f64>::new(1.0, 2.0, make_block_params(0), 256, 1).unwrap();
+        resampler.set_unity_bypass(true);
+        // Interpolate the reference at the same positions as the bypass
+        reference.last_index = resampler.last_index;
+        let fade_frames = resampler.interpolator.len();
+        let mut produced = 0;
+        let mut max_filter_error: f64 = 0.0;
+        for (chunk, wave) in input.chunks(256).enumerate() {
+            if chunk == 20 {
+                reference.set_resample_ratio(1.01, true).unwrap();
+                resampler.set_resample_ratio(1.01, true).unwrap();
+            }
+            let out_ref = reference.process(&[wave], None).unwrap();
+            let out = resampler.process(&[wave], None).unwrap();
+            assert_eq!(out_ref[0].len(), out[0].len());
+            for (m, (a, b)) in out_ref[0].iter().zip(out[0].iter()).enumerate() {
+                if chunk < 20 {
+                    // The bypass copies the input, at the delay of the interpolated output
+                    assert_eq!(*b, input[produced + m]);
+                    if chunk > 0 {
+                        max_filter_error = max_filter_error.max((a - b).abs());
+                    }
+                } else if chunk > 20 || m >= fade_frames {
+                    assert!((a - b).abs() < 1.0e-12);
+                } else {
+                    // Crossfading from the bypass
+                    assert!((a - b).abs() < 1.0e-4);
+                }
+            }
+            produced += out[0].len();
+        }
+        // Only the filter differs after the start, a delay of one sinc step would give about 1.0e-3
+        assert!(max_filter_error > 0.0);
+        assert!(max_filter_error < 1.0e-9);
+    }
+
+    #[test]
+    fn unity_bypass_fo() {
+        let input: Vec<f64> = (0..8192).map(|n| (n as f64 * 0.02).sin()).collect();
+        let mut reference =
+            SincFixedOut::<f64>::new(1.0, 2.0, make_block_params(1), 256, 1).unwrap();
+        let mut resampler =
+            SincFixedOut::<f64>::new(1.0, 2.0, make_block_params(1), 256, 1).unwrap();
+        resampler.set_unity_bypass(true);
+        reference.last_index = resampler.last_index;
+        let fade_frames = resampler.interpolator.len();
+        let mut pos = 0;
+        for chunk in 0..20 {
+            if chunk == 10 {
+                reference.set_resample_ratio(0.99, false).unwrap();
+                resampler.set_resample_ratio(0.99, false).unwrap();
+            }
+            let frames = resampler.input_frames_next();
+            assert_eq!(reference.input_frames_next(), frames);
+            let out_ref = reference
+                .process(&[&input[pos..pos + frames]], None)
+                .unwrap();
+            let out = resampler
+                .process(&[&input[pos..pos + frames]], None)
+                .unwrap();
+            for (m, (a, b)) in out_ref[0].iter().zip(out[0].iter()).enumerate() {
+                if chunk < 10 {
+                    assert_eq!(*b, input[256 * chunk + m]);
+                    if chunk > 0 {
+                        assert!((a - b).abs() < 1.0e-9);
+                    }
+                } else if chunk > 10 || m >= fade_frames {
+                    assert!((a - b).abs() < 1.0e-12);
+                } else {
+                    // Crossfading from the bypass
+                    assert!((a - b).abs() < 1.0e-4);
+                }
+            }
+            pos += frames;
+        }
+    }
+
+    #[test]
+    fn ratio_envelope() {
+        let input: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut reference =
+            SincFixedOut::<f64>::new(1.0, 2.0, make_block_params(0), 256, 1).unwrap();
+        let mut resampler =
+            SincFixedOut::<f64>::new(1.0, 2.0, make_block_params(0), 256, 1).unwrap();
+        let mut pos_ref = 0;
+        let mut pos = 0;
+        for chunk in 0..8 {
+            let ratio = 1.0 + 0.1 * chunk as f64;
+            reference.set_resample_ratio(ratio, false).unwrap();
+            resampler.set_ratio_envelope(&[ratio; 256]).unwrap();
+            let frames_ref = reference.input_frames_next();
+            let frames = resampler.input_frames_next();
+            let out_ref = reference
+                .process(&[&input[pos_ref..pos_ref + frames_ref]], None)
+                .unwrap();
+            let out = resampler
+                .process(&[&input[pos..pos + frames]], None)
+                .unwrap();
+            pos_ref += frames_ref;
+            pos += frames;
+            for (a, b) in out_ref[0].iter().zip(out[0].iter()) {
+                assert!((a - b).abs() < 1.0e-9);
+            }
+        }
+        assert_eq!(
+            resampler
+                .set_ratio_envelope(&[1.0; 100])
+                .unwrap_err()
+                .code(),
+            207
+        );
+        let mut ratios = vec![1.0; 256];
+        ratios[100] = 2.5;
+        assert_eq!(
+            resampler.set_ratio_envelope(&ratios).unwrap_err().code(),
+            200
+        );
+    }
+
+    #[test]
+    fn input_frames_needed_fo() {
+        let mut resampler =
+            SincFixedOut::<f64>::new(0.8, 2.0, make_block_params(0), 256, 1).unwrap();
+        let waves = vec![vec![0.0f64; resampler.input_frames_max()]; 1];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_resample_ratio_relative(1.5, true).unwrap();
+        let needed = resampler.input_frames_needed_for(1000);
+        let mut produced = 0;
+        let mut consumed = 0;
+        while produced < 1000 {
+            let frames = resampler.input_frames_next();
+            produced += resampler.process(&[&waves[0][..frames]], None).unwrap()[0].len();
+            consumed += frames;
+        }
+        assert_eq!(consumed, needed);
+    }
+
+    #[test]
+    fn exact_ratio_fi() {
+        let mut resampler =
+            SincFixedIn::<f64>::new(1.0, 1.1, make_block_params(0), 441, 1).unwrap();
+        resampler.set_exact_ratio(44100, 48000).unwrap();
+        let waves = vec![vec![0.0f64; 441]; 1];
+        resampler.process(&waves, None).unwrap();
+        let last_index = resampler.last_index;
+        for _ in 0..1000 {
+            assert_eq!(resampler.process(&waves, None).unwrap()[0].len(), 480);
+            assert_eq!(resampler.last_index, last_index);
+        }
+        // A new ratio leaves the exact mode
+        resampler.set_resample_ratio(1.05, false).unwrap();
+        resampler.process(&waves, None).unwrap();
+        assert_ne!(resampler.last_index, last_index);
+    }
+
+    #[test]
+    fn upsampling_optimized() {
+        let params = || SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
+        };
+        let mut resampler =
+            SincFixedIn::<f64>::new_upsampling(44100, 48000, params(), 441, 1).unwrap();
+        assert_eq!(resampler.sinc_coefficients().len(), 160);
+        // With the same phases, linear interpolation falls on the sincs,
+        // but may round a position to the neighbouring phase
+        let reference_params = SincInterpolationParameters {
+            oversampling_factor: 160,
+            interpolation: SincInterpolationType::Linear,
+            ..params()
+        };
+        let mut reference =
+            SincFixedIn::<f64>::new(48000.0 / 44100.0, 1.0, reference_params, 441, 1).unwrap();
+        reference.set_exact_ratio(44100, 48000).unwrap();
+        let input = (0..441 * 20)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<_>>();
+        for chunk in input.chunks(441) {
+            let out = resampler.process(&[chunk], None).unwrap();
+            let expected = reference.process(&[chunk], None).unwrap();
+            assert_eq!(out[0].len(), expected[0].len());
+            for (value, expected) in out[0].iter().zip(expected[0].iter()) {
+                assert!((value - expected).abs() < 1.0e-3);
+            }
+        }
+        let result = SincFixedIn::<f64>::new_upsampling(48000, 44100, params(), 441, 1);
+        assert_eq!(result.err().map(|err| err.code()), Some(111));
+        // Too many phases, the parameters are used as they are
+        let resampler = SincFixedIn::<f64>::new_upsampling(44100, 48001, params(), 441, 1).unwrap();
+        assert_eq!(resampler.sinc_coefficients().len(), 256);
+    }
+
+    #[test]
+    fn shared_filter_bank() {
+        let bank = SincFilterBank::<f64>::new(1.2, &make_block_params(0)).unwrap();
+        let mut reference =
+            SincFixedIn::<f64>::new(1.2, 1.1, make_block_params(0), 256, 2).unwrap();
+        let mut resampler = SincFixedIn::new_with_bank(&bank, 1.1, 256, 2).unwrap();
+        let mut resampler_fo = SincFixedOut::new_with_bank(&bank, 1.1, 256, 2).unwrap();
+        assert_eq!(bank.shared_count(), 3);
+        let waves = vec![(0..256).map(|n| (n as f64 * 0.1).sin()).collect::<Vec<_>>(); 2];
+        for _ in 0..3 {
+            assert_eq!(
+                resampler.process(&waves, None).unwrap(),
+                reference.process(&waves, None).unwrap()
+            );
+        }
+        let frames = resampler_fo.input_frames_next();
+        let out = resampler_fo.process(&[&waves[0][..frames], &waves[1][..frames]], None);
+        assert_eq!(out.unwrap()[0].len(), 256);
+        // The filters are shared, and only counted once
+        assert!(resampler.memory_usage() < reference.memory_usage());
+        drop(resampler);
+        assert_eq!(bank.shared_count(), 2);
+        assert!(SincFilterBank::<f64>::new(-1.0, &make_block_params(0)).is_err());
+    }
+
+    #[test]
+    fn latency_budget() {
+        // make_block_params gives a sinc length of 64, and a filter delay of 32 frames
+        let resampler =
+            SincFixedIn::<f64>::new_with_latency_budget(1.2, 1.0, make_block_params(0), 100, 2)
+                .unwrap();
+        assert_eq!(resampler.input_frames_max(), 68);
+        let resampler =
+            SincFixedOut::<f64>::new_with_latency_budget(0.5, 1.0, make_block_params(0), 100, 2)
+                .unwrap();
+        assert_eq!(resampler.output_frames_max(), 34);
+        assert!(resampler.input_frames_max() <= 68 + 64);
+        match SincFixedIn::<f64>::new_with_latency_budget(1.2, 1.0, make_block_params(0), 32, 2) {
+            Err(ResamplerConstructionError::LatencyBudgetTooSmall {
+                budget,
+                filter_delay,
+            }) => {
+                assert_eq!(budget, 32);
+                assert_eq!(filter_delay, 32);
+            }
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn allow_aliasing() {
+        // A tone at 0.75*fs_in/2, which is above fs_out/2 when downsampling by 2
+        let waves = vec![(0..1024)
+            .map(|n| (n as f64 * 0.75 * std::f64::consts::PI).sin())
+            .collect::<Vec<_>>()];
+        let mut power = Vec::new();
+        for allow_aliasing in [false, true] {
+            let mut params = make_block_params(0);
+            params.allow_aliasing = allow_aliasing;
+            let mut resampler = SincFixedIn::<f64>::new(0.5, 1.0, params, 1024, 1).unwrap();
+            resampler.process(&waves, None).unwrap();
+            let out = resampler.process(&waves, None).unwrap();
+            power.push(out[0].iter().map(|v| v * v).sum::<f64>() / out[0].len() as f64);
+        }
+        // The tone is removed by the filter, unless aliasing is allowed
+        assert!(power[0] < 1.0e-4, "power {}", power[0]);
+        assert!(power[1] > 0.1, "power {}", power[1]);
+    }
+
+    #[test]
+    fn change_interpolation_type() {
+        let mut cubic = SincFixedIn::<f64>::new(1.2, 1.0, make_block_params(0), 256, 1).unwrap();
+        let mut nearest = SincFixedIn::<f64>::new(1.2, 1.0, make_block_params(3), 256, 1).unwrap();
+        let mut resampler =
+            SincFixedIn::<f64>::new(1.2, 1.0, make_block_params(0), 256, 1).unwrap();
+        resampler.set_block_size(16);
+        let waves = vec![(0..256).map(|n| (n as f64 * 0.2).sin()).collect::<Vec<_>>()];
+        for _ in 0..2 {
+            cubic.process(&waves, None).unwrap();
+            nearest.process(&waves, None).unwrap();
+            resampler.process(&waves, None).unwrap();
+        }
+        resampler.set_interpolation_type(SincInterpolationType::Nearest, 100);
+        assert_eq!(
+            resampler.interpolation_type(),
+            SincInterpolationType::Nearest
+        );
+        let from = cubic.process(&waves, None).unwrap();
+        let to = nearest.process(&waves, None).unwrap();
+        let out = resampler.process(&waves, None).unwrap();
+        assert!((out[0][0] - from[0][0]).abs() < 0.02);
+        for n in 0..100 {
+            let (low, high) = if from[0][n] < to[0][n] {
+                (from[0][n], to[0][n])
+            } else {
+                (to[0][n], from[0][n])
+            };
+            assert!(out[0][n] >= low - 1.0e-12 && out[0][n] <= high + 1.0e-12);
+        }
+        assert_eq!(out[0][100..], to[0][100..]);
+        assert_eq!(
+            resampler.process(&waves, None).unwrap(),
+            nearest.process(&waves, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn change_interpolation_type_while_fading() {
+        let mut linear = SincFixedIn::<f64>::new(1.2, 1.0, make_block_params(0), 256, 1).unwrap();
+        let mut nearest = SincFixedIn::<f64>::new(1.2, 1.0, make_block_params(3), 256, 1).unwrap();
+        let mut resampler =
+            SincFixedIn::<f64>::new(1.2, 1.0, make_block_params(0), 256, 1).unwrap();
+        let waves = vec![(0..256).map(|n| (n as f64 * 0.2).sin()).collect::<Vec<_>>()];
+        linear.set_interpolation_type(SincInterpolationType::Linear, 400);
+        resampler.set_interpolation_type(SincInterpolationType::Linear, 400);
+        let first = linear.process(&waves, None).unwrap();
+        assert_eq!(resampler.process(&waves, None).unwrap(), first);
+        // The second change waits for the first crossfade to complete
+        resampler.set_interpolation_type(SincInterpolationType::Quadratic, 100);
+        resampler.set_interpolation_type(SincInterpolationType::Nearest, 100);
+        assert_eq!(
+            resampler.interpolation_type(),
+            SincInterpolationType::Nearest
+        );
+        let remaining = 400 - first[0].len();
+        let from = linear.process(&waves, None).unwrap();
+        nearest.process(&waves, None).unwrap();
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out[0][..remaining], from[0][..remaining]);
+        assert_ne!(out[0][remaining + 1], from[0][remaining + 1]);
+        nearest.process(&waves, None).unwrap();
+        assert_eq!(
+            resampler.process(&waves, None).unwrap(),
+            nearest.process(&waves, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn generated_sinc_table() {
+        let params = SincInterpolationParameters {
+            sinc_len: 16,
+            f_cutoff: 0.9,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 4,
+            window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
+        };
+        let source = sinc_table_source::<f64>("TABLE", 1.5, &params).unwrap();
+        assert!(source.contains("pub static TABLE: [[f64; 16]; 4] = ["));
+        // The written values read back exactly
+        let sincs = super::make_interpolator_sincs::<f64>(1.5, &params).unwrap();
+        let rows = source
+            .lines()
+            .filter(|line| line.starts_with("    ["))
+            .map(|line| {
+                line.trim()
+                    .trim_start_matches('[')
+                    .trim_end_matches("],")
+                    .split(", ")
+                    .map(|value| value.parse::<f64>().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, sincs);
+
+        // A resampler using the table gives the same output as one designing the filters
+        let mut table = [[0.0f64; 16]; 4];
+        for (row, sinc) in table.iter_mut().zip(sincs.iter()) {
+            row.copy_from_slice(sinc);
+        }
+        let interpolator = make_table_interpolator(&table).unwrap();
+        let mut resampler = SincFixedIn::<f64>::new_with_interpolator(
+            1.5,
+            1.0,
+            SincInterpolationType::Linear,
+            interpolator,
+            256,
+            1,
+        )
+        .unwrap();
+        let mut reference = SincFixedIn::<f64>::new(1.5, 1.0, params, 256, 1).unwrap();
+        let waves = vec![(0..256).map(|n| (n as f64 * 0.1).sin()).collect::<Vec<_>>()];
+        assert_eq!(
+            resampler.process(&waves, None).unwrap(),
+            reference.process(&waves, None).unwrap()
+        );
+        let empty: [[f64; 16]; 0] = [];
+        assert!(make_table_interpolator(&empty).is_err());
+        assert!(make_table_interpolator(&[[0.0f64; 12]; 4]).is_err());
+    }
+
+    #[test]
+    fn interpolator_backend() {
+        let params = SincInterpolationParameters::speech(SpeechPreset::Narrowband);
+        let resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 1).unwrap();
+        let backend = resampler.interpolator_backend();
+        assert_ne!(backend, InterpolatorBackend::Custom);
+        #[cfg(feature = "force_scalar")]
+        assert_eq!(backend, InterpolatorBackend::Scalar);
+        #[cfg(all(
+            target_arch = "x86_64",
+            feature = "simd_avx",
+            not(feature = "forced_backend")
+        ))]
+        if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
+            assert_eq!(backend, InterpolatorBackend::Avx);
+        }
+        #[cfg(all(
+            target_arch = "aarch64",
+            feature = "simd_neon",
+            not(feature = "forced_backend")
+        ))]
+        assert_eq!(backend, InterpolatorBackend::Neon);
+
+        let interpolator = ScalarInterpolator::<f32>::new(64, 16, 0.9, WindowFunction::Blackman);
+        let resampler = SincFixedOut::<f32>::new_with_interpolator(
+            1.2,
+            1.0,
+            SincInterpolationType::Linear,
+            Box::new(interpolator),
+            1024,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            resampler.interpolator_backend(),
+            InterpolatorBackend::Scalar
+        );
+        assert_eq!(InterpolatorBackend::Avx.to_string(), "avx+fma");
+    }
+
+    #[test]
+    fn high_sample_rates() {
+        let params = || {
+            let mut params = make_block_params(2);
+            params.sinc_len = 128;
+            params.f_cutoff = 0.9;
+            params.oversampling_factor = 256;
+            params
+        };
+        // A tone at 27 kHz, above the output Nyquist frequency
+        let wave = (0..32768)
+            .map(|n| (2.0 * std::f64::consts::PI * 27000.0 * n as f64 / 768000.0).sin())
+            .collect::<Vec<_>>();
+        let mut amplitudes = Vec::new();
+        for high_rate in [false, true] {
+            let mut resampler = if high_rate {
+                SincFixedIn::<f64>::new_high_rate(768000, 48000, 1.0, params(), 8192, 1).unwrap()
+            } else {
+                SincFixedIn::<f64>::new(48000.0 / 768000.0, 1.0, params(), 8192, 1).unwrap()
+            };
+            assert_eq!(resampler.rational_phase.is_some(), high_rate);
+            let mut output = Vec::new();
+            for chunk in wave.chunks(8192) {
+                output.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+            }
+            let tail = &output[output.len() / 2..];
+            amplitudes
+                .push((2.0 * tail.iter().map(|v| v * v).sum::<f64>() / tail.len() as f64).sqrt());
+        }
+        // The longer filter removes the tone much better
+        assert!(amplitudes[0] > 1.0e-2, "{:?}", amplitudes);
+        assert!(amplitudes[1] < 1.0e-4, "{:?}", amplitudes);
+
+        let resampler = SincFixedOut::<f64>::new_high_rate(768000, 48000, 1.0, params(), 512, 1);
+        assert!(resampler.is_ok());
+        let err = SincFixedIn::<f64>::new_high_rate(768000, 48000, 1.0, params(), 1024, 1);
+        assert!(matches!(
+            err,
+            Err(ResamplerConstructionError::ChunkSizeTooSmall {
+                chunk_size: 1024,
+                minimum: 2048,
+            })
+        ));
+        let err = SincFixedIn::<f64>::new_high_rate(0, 48000, 1.0, params(), 8192, 1);
+        assert_eq!(err.err().map(|err| err.code()), Some(100));
+
+        // Whole sample rates above the threshold are tracked exactly
+        let resampler =
+            SincFixedOut::<f64>::new_with_sample_rates(705600.0, 768000.0, 1.0, params(), 1024, 1)
+                .unwrap();
+        assert!(resampler.rational_phase.is_some());
+        let resampler =
+            SincFixedOut::<f64>::new_with_sample_rates(44100.0, 48000.0, 1.0, params(), 1024, 1)
+                .unwrap();
+        assert!(resampler.rational_phase.is_none());
+    }
+
+    #[test]
+    fn memory_limit() {
+        let params = || SincInterpolationParameters::speech(SpeechPreset::Wideband);
+        let resampler =
+            SincFixedIn::<f64>::try_new_with_limit(usize::MAX, 1.2, 1.0, params(), 1024, 2)
+                .unwrap();
+        let used = resampler.memory_usage();
+        let err = SincFixedIn::<f64>::try_new_with_limit(used / 2, 1.2, 1.0, params(), 1024, 2)
+            .err()
+            .unwrap();
+        match err {
+            ResamplerConstructionError::MemoryLimitExceeded { required, limit } => {
+                assert_eq!(limit, used / 2);
+                assert!(required > used * 9 / 10 && required < used * 11 / 10);
+            }
+            _ => panic!("unexpected error {}", err),
+        }
+        assert_eq!(err.code(), 114);
+
+        let resampler =
+            SincFixedOut::<f64>::try_new_with_limit(usize::MAX, 1.2, 2.0, params(), 1024, 2)
+                .unwrap();
+        let used = resampler.memory_usage();
+        let err = SincFixedOut::<f64>::try_new_with_limit(0, 1.2, 2.0, params(), 1024, 2)
+            .err()
+            .unwrap();
+        match err {
+            ResamplerConstructionError::MemoryLimitExceeded { required, .. } => {
+                assert!(required > used * 9 / 10 && required < used * 11 / 10);
+            }
+            _ => panic!("unexpected error {}", err),
+        }
+
+        // Absurd sizes are refused without trying to allocate
+        let err = SincFixedIn::<f32>::try_new_with_limit(1 << 30, 1.2, 1.0, params(), 1 << 40, 2);
+        assert_eq!(err.err().map(|err| err.code()), Some(114));
+        let mut absurd = params();
+        absurd.oversampling_factor = 1 << 40;
+        let err = SincFixedOut::<f32>::try_new_with_limit(1 << 30, 1.2, 1.0, absurd, 1024, 2);
+        assert_eq!(err.err().map(|err| err.code()), Some(114));
+    }
 }
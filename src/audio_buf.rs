@@ -0,0 +1,181 @@
+use crate::error::ResampleResult;
+use crate::{Resampler, Sample};
+use audio_core::{Buf, BufMut, Channel, ChannelMut};
+
+/// Process buffers implementing the [Buf] and [BufMut] traits of the `audio-core` crate.
+///
+/// This is implemented for every [Resampler], and is available with the `audio-core` feature.
+/// It lets applications that use the buffer types of the `audio` crate,
+/// or other implementations of the traits, call the resamplers directly.
+///
+/// Channels that are linear in memory, like those of the sequential and dynamic buffers,
+/// are given to the resampler as they are, without copying.
+/// Other channels, like those of an interleaved buffer, are copied
+/// into a temporary buffer, which allocates.
+/// The same holds for the output, where a non-linear output is written
+/// from a temporary buffer after processing.
+/// ```
+/// use audio::buf::{Dynamic, Interleaved};
+/// use rubato::{AudioBufResampler, FftFixedIn, Resampler};
+/// let mut resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 2).unwrap();
+/// let input = Interleaved::<f32>::with_topology(2, 1024);
+/// let mut output = Dynamic::<f32>::with_topology(2, resampler.output_frames_max());
+/// let expected_out = resampler.output_frames_next();
+/// let (frames_in, frames_out) = resampler
+///     .process_audio_buf(&input, &mut output, None)
+///     .unwrap();
+/// assert_eq!(frames_in, 1024);
+/// assert_eq!(frames_out, expected_out);
+/// ```
+pub trait AudioBufResampler<T>: Resampler<T>
+where
+    T: Sample,
+{
+    /// Resample a buffer of audio to a pre-allocated output buffer.
+    ///
+    /// This works like [process_into_buffer](Resampler::process_into_buffer),
+    /// with one channel of the buffers per channel of the resampler.
+    /// The output must have room for at least
+    /// [output_frames_next](Resampler::output_frames_next) frames.
+    ///
+    /// Parameters are:
+    /// - `wave_in`: The input buffer.
+    /// - `wave_out`: The output buffer.
+    /// - `active_channels_mask`: A vector of booleans determining what channels are to be processed.
+    fn process_audio_buf<I, O>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut O,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        I: Buf<Sample = T>,
+        O: BufMut<Sample = T>,
+    {
+        let owned_in: Vec<Vec<T>>;
+        let channels_in: Vec<I::Channel<'_>> = wave_in.iter_channels().collect();
+        let linear_in: Option<Vec<&[T]>> = channels_in
+            .iter()
+            .map(|chan| chan.try_as_linear())
+            .collect();
+        let slices_in = match linear_in {
+            Some(slices) => slices,
+            None => {
+                owned_in = channels_in
+                    .iter()
+                    .map(|chan| chan.iter().collect())
+                    .collect();
+                owned_in.iter().map(|chan| chan.as_slice()).collect()
+            }
+        };
+
+        let mut channels_out: Vec<O::ChannelMut<'_>> = wave_out.iter_channels_mut().collect();
+        let all_linear = channels_out
+            .iter_mut()
+            .all(|chan| chan.try_as_linear_mut().is_some());
+        if all_linear {
+            let mut slices_out: Vec<&mut [T]> = channels_out
+                .iter_mut()
+                .filter_map(|chan| chan.try_as_linear_mut())
+                .collect();
+            return self.process_into_buffer(&slices_in, &mut slices_out, active_channels_mask);
+        }
+        let mut owned_out: Vec<Vec<T>> = channels_out
+            .iter()
+            .map(|chan| vec![T::zero(); chan.len()])
+            .collect();
+        let (frames_in, frames_out) =
+            self.process_into_buffer(&slices_in, &mut owned_out, active_channels_mask)?;
+        for (chan, values) in channels_out.iter_mut().zip(owned_out.iter()) {
+            for (sample, value) in chan.iter_mut().zip(values.iter()).take(frames_out) {
+                *sample = *value;
+            }
+        }
+        Ok((frames_in, frames_out))
+    }
+}
+
+impl<T, R> AudioBufResampler<T> for R
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_fast_resampler;
+    use crate::{AudioBufResampler, FastFixedIn, ResampleError, Resampler};
+    use audio::buf::{Dynamic, Interleaved, Sequential};
+
+    fn make_resampler() -> FastFixedIn<f64> {
+        make_fast_resampler(1.5, 1.0, 256, 2)
+    }
+
+    fn make_input() -> Vec<Vec<f64>> {
+        (0..2)
+            .map(|chan| {
+                (0..256)
+                    .map(|n| (n as f64 * 0.02 * (chan + 1) as f64).sin())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buffer_layouts() {
+        let waves_in = make_input();
+        for _ in 0..2 {
+            let mut reference = make_resampler();
+            let expected = reference.process(&waves_in, None).unwrap();
+            let frames_out = expected[0].len();
+            let out_len = reference.output_frames_max();
+
+            let mut sequential_in = Sequential::<f64>::with_topology(2, 256);
+            let mut interleaved_in = Interleaved::<f64>::with_topology(2, 256);
+            for (chan, wave) in waves_in.iter().enumerate() {
+                for (n, value) in wave.iter().enumerate() {
+                    sequential_in[chan][n] = *value;
+                    *interleaved_in.sample_mut(chan, n).unwrap() = *value;
+                }
+            }
+
+            let mut resampler = make_resampler();
+            let mut output = Dynamic::<f64>::with_topology(2, out_len);
+            let result = resampler.process_audio_buf(&sequential_in, &mut output, None);
+            assert_eq!(result.unwrap(), (256, frames_out));
+            for (chan, wave) in expected.iter().enumerate() {
+                assert_eq!(output[chan][..frames_out], wave[..]);
+            }
+
+            let mut resampler = make_resampler();
+            let mut output = Interleaved::<f64>::with_topology(2, out_len);
+            let result = resampler.process_audio_buf(&interleaved_in, &mut output, None);
+            assert_eq!(result.unwrap(), (256, frames_out));
+            for (chan, wave) in expected.iter().enumerate() {
+                let channel = output.get_channel(chan).unwrap();
+                for (n, value) in wave.iter().enumerate() {
+                    assert_eq!(audio_core::Channel::get(&channel, n), Some(*value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_number_of_channels() {
+        let mut resampler = make_resampler();
+        let input = Sequential::<f64>::with_topology(1, 256);
+        let mut output = Sequential::<f64>::with_topology(2, resampler.output_frames_max());
+        assert_eq!(input.channels(), 1);
+        let err = resampler
+            .process_audio_buf(&input, &mut output, None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::WrongNumberOfInputChannels {
+                expected: 2,
+                actual: 1
+            }
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::ResamplerConstructionError;
+use std::sync::mpsc;
+use std::thread;
+
+type BuildResult<R> = Result<R, ResamplerConstructionError>;
+
+/// A resampler that is being created on a worker thread.
+///
+/// Creating a resampler designs the filters and plans the FFTs,
+/// which can take several milliseconds for long sinc filters and large FFTs.
+/// This is too long to wait for in a GUI event handler or a realtime audio callback.
+/// A PendingResampler runs the factory closure on a new thread,
+/// and the finished resampler is then picked up with [try_take](PendingResampler::try_take),
+/// that never blocks, or with [wait](PendingResampler::wait).
+/// For a callback that is called as soon as the resampler is ready, see [build_in_background].
+///
+/// If the factory panics, the result is [ResamplerConstructionError::BuilderFailed].
+/// ```
+/// use rubato::{PendingResampler, Resampler, SincFixedIn, SincInterpolationParameters, SpeechPreset};
+/// let mut pending = PendingResampler::new(|| {
+///     let parameters = SincInterpolationParameters::speech(SpeechPreset::Wideband);
+///     SincFixedIn::<f32>::new(16000.0 / 48000.0, 1.0, parameters, 480, 1)
+/// });
+/// // Keep passing the audio through, or output silence, until the resampler is ready
+/// let resampler = loop {
+///     if let Some(result) = pending.try_take() {
+///         break result.unwrap();
+///     }
+///     std::thread::sleep(std::time::Duration::from_millis(1));
+/// };
+/// assert_eq!(resampler.nbr_channels(), 1);
+/// ```
+pub struct PendingResampler<R> {
+    receiver: mpsc::Receiver<BuildResult<R>>,
+    taken: bool,
+}
+
+impl<R> PendingResampler<R>
+where
+    R: Send + 'static,
+{
+    /// Start creating a resampler on a new worker thread.
+    ///
+    /// Parameters are:
+    /// - `factory`: Closure that creates the resampler, for example by calling one of the constructors.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: FnOnce() -> BuildResult<R> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            // The receiver may already be dropped, then the result is not needed
+            let _ = sender.send(factory());
+        });
+        PendingResampler {
+            receiver,
+            taken: false,
+        }
+    }
+
+    /// Take the result if it is ready, without blocking.
+    /// Returns `None` while the resampler is still being created,
+    /// and after the result has already been taken.
+    /// This is cheap and does not allocate, and can be called from a realtime thread.
+    pub fn try_take(&mut self) -> Option<BuildResult<R>> {
+        if self.taken {
+            return None;
+        }
+        let result = match self.receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err(ResamplerConstructionError::BuilderFailed),
+        };
+        self.taken = true;
+        Some(result)
+    }
+
+    /// Block until the resampler is ready, and return it.
+    /// Returns [ResamplerConstructionError::BuilderFailed] if the result was already taken.
+    pub fn wait(self) -> BuildResult<R> {
+        if self.taken {
+            return Err(ResamplerConstructionError::BuilderFailed);
+        }
+        self.receiver
+            .recv()
+            .unwrap_or(Err(ResamplerConstructionError::BuilderFailed))
+    }
+}
+
+/// Create a resampler on a new worker thread, and give it to a callback when it is ready.
+///
+/// The callback is called on the worker thread, with the result of the factory.
+/// It can for example send the resampler to the audio thread through a channel,
+/// or notify the event loop of a GUI.
+/// If the factory panics, the callback is not called, and the returned
+/// [JoinHandle](thread::JoinHandle) gives the panic to a thread that joins it.
+/// See [PendingResampler] for picking up the resampler by polling instead.
+///
+/// Parameters are:
+/// - `factory`: Closure that creates the resampler.
+/// - `on_ready`: Closure that is called with the resampler, or with the error from the factory.
+///
+/// ```
+/// use rubato::{build_in_background, FftFixedIn};
+/// use std::sync::mpsc;
+/// let (sender, receiver) = mpsc::channel();
+/// build_in_background(
+///     || FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2),
+///     move |result| sender.send(result).unwrap(),
+/// );
+/// let resampler = receiver.recv().unwrap().unwrap();
+/// ```
+pub fn build_in_background<R, F, C>(factory: F, on_ready: C) -> thread::JoinHandle<()>
+where
+    R: Send + 'static,
+    F: FnOnce() -> BuildResult<R> + Send + 'static,
+    C: FnOnce(BuildResult<R>) + Send + 'static,
+{
+    thread::spawn(move || on_ready(factory()))
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{
+        build_in_background, FftFixedIn, PendingResampler, Resampler, ResamplerConstructionError,
+        SincFixedOut, SincInterpolationParameters, SpeechPreset,
+    };
+    use std::sync::mpsc;
+
+    #[test]
+    fn pending_resampler() {
+        let mut pending = PendingResampler::new(|| {
+            let parameters = SincInterpolationParameters::speech(SpeechPreset::Narrowband);
+            SincFixedOut::<f64>::new(0.5, 1.0, parameters, 256, 2)
+        });
+        let resampler = loop {
+            if let Some(result) = pending.try_take() {
+                break result.unwrap();
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(resampler.nbr_channels(), 2);
+        assert!(pending.try_take().is_none());
+
+        let pending = PendingResampler::new(|| FftFixedIn::<f64>::new(0, 48000, 1024, 2, 2));
+        assert_eq!(pending.wait().err().map(|err| err.code()), Some(100));
+
+        let pending = PendingResampler::<FftFixedIn<f64>>::new(|| panic!("factory failed"));
+        assert!(matches!(
+            pending.wait(),
+            Err(ResamplerConstructionError::BuilderFailed)
+        ));
+    }
+
+    #[test]
+    fn background_callback() {
+        let (sender, receiver) = mpsc::channel();
+        let handle = build_in_background(
+            || FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 1),
+            move |result| sender.send(result).unwrap(),
+        );
+        let resampler = receiver.recv().unwrap().unwrap();
+        assert_eq!(resampler.input_frames_next(), 1024);
+        handle.join().unwrap();
+    }
+}
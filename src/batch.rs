@@ -0,0 +1,288 @@
+use crate::asynchro_sinc::{
+    frame_position, interp_cubic, interp_lin, interp_quad, make_interpolator_sincs,
+};
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::SincInterpolationType;
+use crate::{heap_size, heap_size_nested, Sample, SincInterpolationParameters};
+
+/// A sinc resampler that resamples many independent mono streams with the same settings in one call.
+///
+/// This gives the same output as one [SincFixedIn](crate::SincFixedIn) per stream,
+/// but the positions of the output frames are only calculated once per chunk,
+/// and the overhead of a call is shared by all streams.
+/// The data is interleaved, with the samples of all streams for one frame stored next to each other,
+/// so that sample `frame * nbr_streams + stream` belongs to stream `stream`.
+/// The inner loops then run over the streams with the same filter coefficient,
+/// which lets the compiler vectorize them for any number of streams.
+///
+/// The input size is fixed, and the number of output frames varies from chunk to chunk.
+/// It is the same for all streams, since they share the ratio and the phase.
+/// The ratio is fixed when creating the resampler.
+/// ```
+/// use rubato::{BatchSincFixedIn, SincInterpolationParameters};
+/// let mut params = SincInterpolationParameters::default();
+/// params.sinc_len = 64;
+/// params.oversampling_factor = 128;
+/// // Resample 1000 streams from 48 kHz to 16 kHz, in chunks of 480 frames.
+/// let mut resampler = BatchSincFixedIn::<f32>::new(16000.0 / 48000.0, params, 480, 1000).unwrap();
+/// let wave_in = vec![0.0f32; 480 * 1000];
+/// let mut wave_out = vec![0.0f32; resampler.output_frames_max() * 1000];
+/// let (frames_in, frames_out) = resampler.process_into_buffer(&wave_in, &mut wave_out).unwrap();
+/// assert_eq!(frames_in, 480);
+/// assert!(frames_out <= resampler.output_frames_max());
+/// ```
+pub struct BatchSincFixedIn<T> {
+    nbr_streams: usize,
+    chunk_size: usize,
+    resample_ratio: f64,
+    sinc_len: usize,
+    sincs: Vec<Vec<T>>,
+    interpolation: SincInterpolationType,
+    buffer: Vec<T>,
+    points: Vec<Vec<T>>,
+    last_index: f64,
+}
+
+impl<T> BatchSincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Create a new BatchSincFixedIn
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates, must be > 0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_streams`: Number of mono streams.
+    pub fn new(
+        resample_ratio: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_streams: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new BatchSincFixedIn, ratio: {}, chunk_size: {}, streams: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_streams, parameters
+        );
+        if resample_ratio <= 0.0 {
+            return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+        }
+        let sincs = make_interpolator_sincs::<T>(resample_ratio, &parameters)?;
+        let sinc_len = sincs[0].len();
+        Ok(BatchSincFixedIn {
+            nbr_streams,
+            chunk_size,
+            resample_ratio,
+            sinc_len,
+            sincs,
+            interpolation: parameters.interpolation,
+            buffer: vec![T::zero(); (chunk_size + 2 * sinc_len) * nbr_streams],
+            points: vec![vec![T::zero(); nbr_streams]; 4],
+            last_index: -((sinc_len / 2) as f64),
+        })
+    }
+
+    /// Get the number of streams.
+    pub fn nbr_streams(&self) -> usize {
+        self.nbr_streams
+    }
+
+    /// Get the number of frames per stream needed for the next call to
+    /// [process_into_buffer](BatchSincFixedIn::process_into_buffer).
+    /// This is always the chunk size.
+    pub fn input_frames_next(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of frames per stream that can be produced in one call.
+    pub fn output_frames_max(&self) -> usize {
+        (self.chunk_size as f64 * self.resample_ratio + 10.0) as usize
+    }
+
+    /// Get the delay of the output, in output frames.
+    pub fn output_delay(&self) -> usize {
+        (self.sinc_len as f64 * self.resample_ratio / 2.0) as usize
+    }
+
+    /// Get the approximate heap memory used by the resampler, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        heap_size_nested(&self.sincs) + heap_size(&self.buffer) + heap_size_nested(&self.points)
+    }
+
+    /// Resample a chunk of all streams, writing to a pre-allocated output buffer.
+    ///
+    /// The input holds [input_frames_next](BatchSincFixedIn::input_frames_next) interleaved frames,
+    /// and the output must have room for [output_frames_max](BatchSincFixedIn::output_frames_max)
+    /// interleaved frames. Any samples after these are ignored.
+    /// Returns the number of input and output frames per stream.
+    pub fn process_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        let nbr_streams = self.nbr_streams;
+        if wave_in.len() < self.chunk_size * nbr_streams {
+            return Err(ResampleError::InsufficientInputBufferSize {
+                channel: 0,
+                expected: self.chunk_size * nbr_streams,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() < self.output_frames_max() * nbr_streams {
+            return Err(ResampleError::InsufficientOutputBufferSize {
+                channel: 0,
+                expected: self.output_frames_max() * nbr_streams,
+                actual: wave_out.len(),
+            });
+        }
+
+        let sinc_len = self.sinc_len;
+        let oversampling_factor = self.sincs.len();
+        let t_ratio = 1.0 / self.resample_ratio;
+        let end_idx = self.chunk_size as isize - (sinc_len as isize + 1) - t_ratio.ceil() as isize;
+        self.buffer.copy_within(
+            self.chunk_size * nbr_streams..(self.chunk_size + 2 * sinc_len) * nbr_streams,
+            0,
+        );
+        self.buffer[2 * sinc_len * nbr_streams..]
+            .copy_from_slice(&wave_in[..self.chunk_size * nbr_streams]);
+
+        let nbr_points = match self.interpolation {
+            SincInterpolationType::Cubic => 4,
+            SincInterpolationType::Quadratic => 3,
+            SincInterpolationType::Linear => 2,
+            SincInterpolationType::Nearest => 1,
+        };
+        let mut idx = self.last_index;
+        let mut n = 0;
+        while idx < end_idx as f64 {
+            idx += t_ratio;
+            let position = frame_position::<T>(idx, oversampling_factor, &self.interpolation);
+            for (nearest, points) in position.nearest[..nbr_points]
+                .iter()
+                .zip(self.points.iter_mut())
+            {
+                let start = (nearest.0 + 2 * sinc_len as isize) as usize;
+                let frames = self.buffer[start * nbr_streams..(start + sinc_len) * nbr_streams]
+                    .chunks_exact(nbr_streams);
+                points.iter_mut().for_each(|point| *point = T::zero());
+                for (coefficient, frame) in self.sincs[nearest.1 as usize].iter().zip(frames) {
+                    for (point, value) in points.iter_mut().zip(frame.iter()) {
+                        *point += *coefficient * *value;
+                    }
+                }
+            }
+            let frame_out = &mut wave_out[n * nbr_streams..(n + 1) * nbr_streams];
+            let p = &self.points;
+            for (stream, value) in frame_out.iter_mut().enumerate() {
+                *value = match self.interpolation {
+                    SincInterpolationType::Cubic => interp_cubic(
+                        position.frac,
+                        &[p[0][stream], p[1][stream], p[2][stream], p[3][stream]],
+                    ),
+                    SincInterpolationType::Quadratic => {
+                        interp_quad(position.frac, &[p[0][stream], p[1][stream], p[2][stream]])
+                    }
+                    SincInterpolationType::Linear => {
+                        interp_lin(position.frac, &[p[0][stream], p[1][stream]])
+                    }
+                    SincInterpolationType::Nearest => p[0][stream],
+                };
+            }
+            n += 1;
+        }
+        self.last_index = idx - self.chunk_size as f64;
+        trace!(
+            "Resampling {} streams, {} frames in, {} frames out",
+            nbr_streams,
+            self.chunk_size,
+            n
+        );
+        Ok((self.chunk_size, n))
+    }
+
+    /// Reset the resampler state and clear the history of all streams.
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|s| *s = T::zero());
+        self.last_index = -((self.sinc_len / 2) as f64);
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_sinc_params;
+    use crate::SincInterpolationType;
+    use crate::{BatchSincFixedIn, Resampler, SincFixedIn};
+
+    #[test]
+    fn same_as_separate_streams() {
+        let interpolations = [
+            SincInterpolationType::Cubic,
+            SincInterpolationType::Quadratic,
+            SincInterpolationType::Linear,
+            SincInterpolationType::Nearest,
+        ];
+        for interpolation in interpolations {
+            let nbr_streams = 5;
+            let mut batch = BatchSincFixedIn::<f64>::new(
+                0.7,
+                make_sinc_params(interpolation),
+                256,
+                nbr_streams,
+            )
+            .unwrap();
+            let mut separate = (0..nbr_streams)
+                .map(|_| SincFixedIn::<f64>::new(0.7, 1.0, make_sinc_params(interpolation), 256, 1))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let mut wave_out = vec![0.0; batch.output_frames_max() * nbr_streams];
+            for chunk in 0..4 {
+                let waves = (0..nbr_streams)
+                    .map(|s| {
+                        (0..256)
+                            .map(|n| ((chunk * 256 + n) as f64 * 0.01 * (s + 1) as f64).sin())
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let wave_in = (0..256 * nbr_streams)
+                    .map(|i| waves[i % nbr_streams][i / nbr_streams])
+                    .collect::<Vec<_>>();
+                let (_, frames) = batch.process_into_buffer(&wave_in, &mut wave_out).unwrap();
+                for (s, resampler) in separate.iter_mut().enumerate() {
+                    let expected = resampler.process(&[&waves[s]], None).unwrap();
+                    assert_eq!(expected[0].len(), frames);
+                    for (n, value) in expected[0].iter().enumerate() {
+                        assert!((wave_out[n * nbr_streams + s] - value).abs() < 1.0e-9);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_sizes() {
+        let mut batch = BatchSincFixedIn::<f32>::new(
+            2.0,
+            make_sinc_params(SincInterpolationType::Linear),
+            64,
+            3,
+        )
+        .unwrap();
+        let mut wave_out = vec![0.0; batch.output_frames_max() * 3];
+        let err = batch
+            .process_into_buffer(&[0.0; 64 * 2], &mut wave_out)
+            .unwrap_err();
+        assert_eq!(err.code(), 205);
+        let err = batch
+            .process_into_buffer(&[0.0; 64 * 3], &mut wave_out[..10])
+            .unwrap_err();
+        assert_eq!(err.code(), 206);
+        assert!(BatchSincFixedIn::<f32>::new(
+            0.0,
+            make_sinc_params(SincInterpolationType::Linear),
+            64,
+            3
+        )
+        .is_err());
+    }
+}
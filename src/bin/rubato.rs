@@ -0,0 +1,457 @@
+//! A command line resampler, built when the `cli` feature is enabled.
+//!
+//! Resample a raw or wav file with any of the resamplers of the library:
+//! ```text
+//! rubato --algo sincfixedin --quality high --in-rate 44100 --out-rate 48000 input.wav output.wav
+//! ```
+//! Raw files are read and written as interleaved samples, and need `--channels`, `--in-rate`
+//! and `--format`, one of `f32`, `f64` or `s16`, all little-endian.
+//! Wav files may contain 16, 24 or 32 bit integer samples, or 32 or 64 bit float samples,
+//! and are written with the same sample format as the input.
+//!
+//! After processing, the latency and throughput are printed,
+//! together with the full command line needed to reproduce the run.
+//! Include this line when reporting a bug.
+extern crate rubato;
+use rubato::{
+    implement_resampler, FastFixedIn, FastFixedOut, FftFixedIn, FftFixedInOut, FftFixedOut,
+    PolynomialDegree, SincFixedIn, SincFixedOut, SincInterpolationParameters,
+    SincInterpolationType, SpeechPreset,
+};
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+implement_resampler!(SliceResampler, &[&[T]], &mut [Vec<T>]);
+
+const USAGE: &str = "Usage: rubato [options] <input> <output>
+
+Options:
+  --algo <name>        sincfixedin, sincfixedout, fastfixedin, fastfixedout,
+                       fftfixedin, fftfixedout or fftfixedinout (default sincfixedin)
+  --quality <level>    low, medium, high or speech (default medium)
+  --in-rate <rate>     Input sample rate, read from the file header for wav input
+  --out-rate <rate>    Output sample rate
+  --channels <n>       Number of channels, read from the file header for wav input
+  --format <format>    Sample format of raw files: f32, f64 or s16 (default f32)
+  --chunk-size <n>     Chunk size in frames (default 1024)";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SampleFormat {
+    S16,
+    S24,
+    S32,
+    F32,
+    F64,
+}
+
+impl SampleFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "s16" => Some(SampleFormat::S16),
+            "f32" => Some(SampleFormat::F32),
+            "f64" => Some(SampleFormat::F64),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            SampleFormat::S16 => 2,
+            SampleFormat::S24 => 3,
+            SampleFormat::S32 | SampleFormat::F32 => 4,
+            SampleFormat::F64 => 8,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> f64 {
+        match self {
+            SampleFormat::S16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / 32768.0,
+            SampleFormat::S24 => {
+                (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f64 / 8388608.0
+            }
+            SampleFormat::S32 => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64 / 2147483648.0
+            }
+            SampleFormat::F32 => {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            SampleFormat::F64 => {
+                let mut array = [0u8; 8];
+                array.copy_from_slice(&bytes[..8]);
+                f64::from_le_bytes(array)
+            }
+        }
+    }
+
+    fn encode(&self, value: f64, output: &mut Vec<u8>) {
+        match self {
+            SampleFormat::S16 => {
+                let value = (value * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+                output.extend_from_slice(&value.to_le_bytes());
+            }
+            SampleFormat::S24 => {
+                let value = (value * 8388608.0).round().clamp(-8388608.0, 8388607.0) as i32;
+                output.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            SampleFormat::S32 => {
+                let value = (value * 2147483648.0)
+                    .round()
+                    .clamp(-2147483648.0, 2147483647.0) as i32;
+                output.extend_from_slice(&value.to_le_bytes());
+            }
+            SampleFormat::F32 => output.extend_from_slice(&(value as f32).to_le_bytes()),
+            SampleFormat::F64 => output.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+/// The parsed command line.
+struct Options {
+    algo: String,
+    quality: String,
+    in_rate: Option<usize>,
+    out_rate: usize,
+    channels: Option<usize>,
+    format: SampleFormat,
+    chunk_size: usize,
+    input: String,
+    output: String,
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+fn parse_number(name: &str, value: Option<String>) -> usize {
+    value
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or_else(|| fail(&format!("Option {} needs a positive integer", name)))
+}
+
+fn parse_options() -> Options {
+    let mut args = env::args().skip(1);
+    let mut algo = "sincfixedin".to_string();
+    let mut quality = "medium".to_string();
+    let mut in_rate = None;
+    let mut out_rate = None;
+    let mut channels = None;
+    let mut format = SampleFormat::F32;
+    let mut chunk_size = 1024;
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--algo" => algo = args.next().unwrap_or_default().to_lowercase(),
+            "--quality" => quality = args.next().unwrap_or_default().to_lowercase(),
+            "--in-rate" => in_rate = Some(parse_number(&arg, args.next())),
+            "--out-rate" => out_rate = Some(parse_number(&arg, args.next())),
+            "--channels" => channels = Some(parse_number(&arg, args.next())),
+            "--chunk-size" => chunk_size = parse_number(&arg, args.next()),
+            "--format" => {
+                format = args
+                    .next()
+                    .and_then(|f| SampleFormat::parse(&f))
+                    .unwrap_or_else(|| fail("Option --format must be one of f32, f64 or s16"))
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with("--") => fail(&format!("Unknown option {}", arg)),
+            _ => files.push(arg),
+        }
+    }
+    if files.len() != 2 {
+        fail("Please give an input and an output file");
+    }
+    let output = files.pop().unwrap();
+    let input = files.pop().unwrap();
+    Options {
+        algo,
+        quality,
+        in_rate,
+        out_rate: out_rate.unwrap_or_else(|| fail("Please give an output sample rate")),
+        channels,
+        format,
+        chunk_size,
+        input,
+        output,
+    }
+}
+
+fn is_wav(filename: &str) -> bool {
+    filename.to_lowercase().ends_with(".wav")
+}
+
+/// Read the format chunk and the location of the data chunk of a wav file.
+/// Returns the sample format, number of channels, sample rate, and the sample data.
+fn parse_wav(data: &[u8]) -> Result<(SampleFormat, usize, usize, &[u8]), String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err("Not a wav file".to_string());
+    }
+    let mut format = None;
+    let mut position = 12;
+    while position + 8 <= data.len() {
+        let id = &data[position..position + 4];
+        let size = u32::from_le_bytes([
+            data[position + 4],
+            data[position + 5],
+            data[position + 6],
+            data[position + 7],
+        ]) as usize;
+        let body = &data[position + 8..(position + 8 + size).min(data.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]) as usize;
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]) as usize;
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                if tag == 0xFFFE && body.len() >= 26 {
+                    // WAVE_FORMAT_EXTENSIBLE, the format tag is the start of the sub format GUID
+                    tag = u16::from_le_bytes([body[24], body[25]]);
+                }
+                let sample_format = match (tag, bits) {
+                    (1, 16) => SampleFormat::S16,
+                    (1, 24) => SampleFormat::S24,
+                    (1, 32) => SampleFormat::S32,
+                    (3, 32) => SampleFormat::F32,
+                    (3, 64) => SampleFormat::F64,
+                    _ => return Err(format!("Unsupported wav format {} with {} bits", tag, bits)),
+                };
+                format = Some((sample_format, channels, rate));
+            }
+            b"data" => {
+                let (sample_format, channels, rate) =
+                    format.ok_or_else(|| "Wav file has no format chunk".to_string())?;
+                return Ok((sample_format, channels, rate, body));
+            }
+            _ => {}
+        }
+        position += 8 + size + size % 2;
+    }
+    Err("Wav file has no data chunk".to_string())
+}
+
+fn wav_header(format: SampleFormat, channels: usize, rate: usize, data_len: usize) -> Vec<u8> {
+    let bytes_per_sample = format.bytes_per_sample();
+    let tag: u16 = match format {
+        SampleFormat::F32 | SampleFormat::F64 => 3,
+        _ => 1,
+    };
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len as u32).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&tag.to_le_bytes());
+    header.extend_from_slice(&(channels as u16).to_le_bytes());
+    header.extend_from_slice(&(rate as u32).to_le_bytes());
+    header.extend_from_slice(&((rate * channels * bytes_per_sample) as u32).to_le_bytes());
+    header.extend_from_slice(&((channels * bytes_per_sample) as u16).to_le_bytes());
+    header.extend_from_slice(&((8 * bytes_per_sample) as u16).to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&(data_len as u32).to_le_bytes());
+    header
+}
+
+/// Split interleaved samples into one vector per channel.
+fn deinterleave(data: &[u8], format: SampleFormat, channels: usize) -> Vec<Vec<f64>> {
+    let frame_len = channels * format.bytes_per_sample();
+    let mut waves = vec![Vec::with_capacity(data.len() / frame_len); channels];
+    for frame in data.chunks_exact(frame_len) {
+        for (wave, sample) in waves
+            .iter_mut()
+            .zip(frame.chunks_exact(format.bytes_per_sample()))
+        {
+            wave.push(format.decode(sample));
+        }
+    }
+    waves
+}
+
+fn interleave(waves: &[Vec<f64>], format: SampleFormat, start: usize, frames: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(frames * waves.len() * format.bytes_per_sample());
+    for frame in start..start + frames {
+        for wave in waves.iter() {
+            format.encode(wave.get(frame).copied().unwrap_or_default(), &mut data);
+        }
+    }
+    data
+}
+
+fn make_resampler(
+    options: &Options,
+    fs_in: usize,
+    channels: usize,
+) -> Result<Box<dyn SliceResampler<f64>>, String> {
+    let ratio = options.out_rate as f64 / fs_in as f64;
+    let chunk_size = options.chunk_size;
+    // The specification of the sinc filter, or None for the speech presets
+    let (specification, degree, sub_chunks) = match options.quality.as_str() {
+        "low" => (
+            Some((0.2, 60.0, SincInterpolationType::Linear)),
+            PolynomialDegree::Linear,
+            1,
+        ),
+        "medium" => (
+            Some((0.1, 100.0, SincInterpolationType::Quadratic)),
+            PolynomialDegree::Cubic,
+            2,
+        ),
+        "high" => (
+            Some((0.05, 140.0, SincInterpolationType::Cubic)),
+            PolynomialDegree::Septic,
+            4,
+        ),
+        "speech" => (None, PolynomialDegree::Cubic, 1),
+        other => return Err(format!("Unknown quality {}", other)),
+    };
+    let speech_preset = if fs_in.min(options.out_rate) <= 8000 {
+        SpeechPreset::Narrowband
+    } else {
+        SpeechPreset::Wideband
+    };
+    let sinc_parameters = || match specification {
+        Some((transition_bandwidth, attenuation, interpolation)) => {
+            SincInterpolationParameters::from_specification(
+                transition_bandwidth,
+                attenuation,
+                interpolation,
+                256,
+            )
+        }
+        None => Ok(SincInterpolationParameters::speech(speech_preset)),
+    };
+    let resampler: Box<dyn SliceResampler<f64>> = match options.algo.as_str() {
+        "sincfixedin" => Box::new(
+            SincFixedIn::<f64>::new(ratio, 1.0, sinc_parameters().unwrap(), chunk_size, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        "sincfixedout" => Box::new(
+            SincFixedOut::<f64>::new(ratio, 1.0, sinc_parameters().unwrap(), chunk_size, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        "fastfixedin" => Box::new(
+            FastFixedIn::<f64>::new(ratio, 1.0, degree, chunk_size, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        "fastfixedout" => Box::new(
+            FastFixedOut::<f64>::new(ratio, 1.0, degree, chunk_size, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        "fftfixedin" => Box::new(
+            FftFixedIn::<f64>::new(fs_in, options.out_rate, chunk_size, sub_chunks, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        "fftfixedout" => Box::new(
+            FftFixedOut::<f64>::new(fs_in, options.out_rate, chunk_size, sub_chunks, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        "fftfixedinout" => Box::new(
+            FftFixedInOut::<f64>::new(fs_in, options.out_rate, chunk_size, channels)
+                .map_err(|e| e.to_string())?,
+        ),
+        other => return Err(format!("Unknown resampler {}", other)),
+    };
+    Ok(resampler)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let data = fs::read(&options.input).map_err(|e| format!("{}: {}", options.input, e))?;
+    let (format, channels, fs_in, samples) = if is_wav(&options.input) {
+        parse_wav(&data)?
+    } else {
+        let channels = options
+            .channels
+            .ok_or_else(|| "Raw input needs --channels".to_string())?;
+        let fs_in = options
+            .in_rate
+            .ok_or_else(|| "Raw input needs --in-rate".to_string())?;
+        (options.format, channels, fs_in, &data[..])
+    };
+    let waves_in = deinterleave(samples, format, channels);
+    let nbr_input_frames = waves_in[0].len();
+    let mut resampler = make_resampler(options, fs_in, channels)?;
+
+    let mut waves_out = vec![Vec::new(); channels];
+    let mut buffer = vec![vec![0.0; resampler.output_frames_max()]; channels];
+    let mut slices: Vec<&[f64]> = waves_in.iter().map(|w| &w[..]).collect();
+    let delay = resampler.output_delay();
+    let nbr_output_frames =
+        (nbr_input_frames as f64 * options.out_rate as f64 / fs_in as f64).round() as usize;
+
+    let start = Instant::now();
+    while waves_out[0].len() < delay + nbr_output_frames {
+        let (nbr_in, nbr_out) = if slices[0].len() >= resampler.input_frames_next() {
+            resampler.process_into_buffer(&slices, &mut buffer, None)
+        } else if !slices[0].is_empty() {
+            resampler.process_partial_into_buffer(Some(&slices), &mut buffer, None)
+        } else {
+            resampler.process_partial_into_buffer(None, &mut buffer, None)
+        }
+        .map_err(|e| e.to_string())?;
+        for slice in slices.iter_mut() {
+            *slice = &slice[nbr_in.min(slice.len())..];
+        }
+        for (wave, buf) in waves_out.iter_mut().zip(buffer.iter()) {
+            wave.extend_from_slice(&buf[..nbr_out]);
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let mut output = interleave(&waves_out, format, delay, nbr_output_frames);
+    if is_wav(&options.output) {
+        let mut header = wav_header(format, channels, options.out_rate, output.len());
+        header.append(&mut output);
+        output = header;
+    }
+    fs::write(&options.output, output).map_err(|e| format!("{}: {}", options.output, e))?;
+
+    println!(
+        "Resampled {} frames at {} Hz to {} frames at {} Hz, {} channels",
+        nbr_input_frames, fs_in, nbr_output_frames, options.out_rate, channels
+    );
+    println!("Resampler: {}", resampler.config());
+    println!(
+        "Latency: {} input frames of filter delay ({} output frames), {} input frames of buffering, {:.2} ms in total",
+        resampler.input_delay(),
+        resampler.output_delay(),
+        resampler.buffering_delay(),
+        1000.0 * resampler.latency_seconds(fs_in as f64)
+    );
+    println!(
+        "Throughput: {:.0} frames per second, {:.1} times realtime, in {:.3} s",
+        nbr_input_frames as f64 / elapsed,
+        nbr_input_frames as f64 / fs_in as f64 / elapsed,
+        elapsed
+    );
+    let format_name = match options.format {
+        SampleFormat::S16 => "s16",
+        SampleFormat::F64 => "f64",
+        _ => "f32",
+    };
+    println!(
+        "Reproduce with: rubato --algo {} --quality {} --in-rate {} --out-rate {} --channels {} --format {} --chunk-size {} {} {}",
+        options.algo,
+        options.quality,
+        fs_in,
+        options.out_rate,
+        channels,
+        format_name,
+        options.chunk_size,
+        options.input,
+        options.output
+    );
+    Ok(())
+}
+
+fn main() {
+    let options = parse_options();
+    if let Err(message) = run(&options) {
+        eprintln!("Error: {}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::error::ResampleResult;
+use crate::{
+    PolynomialDegree, Resampler, ResamplerAlgorithm, ResamplerConfig, Sample, SincInterpolationType,
+};
+use num_integer as integer;
+use std::time::{Duration, Instant};
+
+/// The theoretical work of a resampler for one call to process, in the worst case.
+///
+/// This is calculated from the [ResamplerConfig] with [worst_case_work](ResamplerConfig::worst_case_work),
+/// and counts the operations that dominate the processing time.
+/// For the sinc, polynomial and decimating resamplers these are the multiply-adds of the filter taps,
+/// and for the FFT resamplers the FFTs and the multiplication of the spectra.
+/// The counts are for the largest chunk, with all channels active.
+/// They do not depend on the CPU, and are intended for comparing configurations
+/// and for scaling a measured time, see [measure_worst_case_time].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkWork {
+    /// Number of filter taps, or polynomial points, evaluated per output frame and channel.
+    pub taps_per_frame: usize,
+    /// Maximum number of output frames per channel.
+    pub frames: usize,
+    /// Number of channels.
+    pub nbr_channels: usize,
+    /// Lengths `(forward, inverse)` of the FFTs, or `None` if no FFTs are used.
+    pub fft_sizes: Option<(usize, usize)>,
+    /// Number of forward and inverse FFT pairs per channel.
+    pub ffts_per_channel: usize,
+    /// Number of complex multiplications of the spectra per channel.
+    pub spectrum_multiplies: usize,
+}
+
+impl ChunkWork {
+    /// Get the total number of multiply-adds of the filter taps, `taps × frames × channels`.
+    pub fn multiply_adds(&self) -> usize {
+        self.taps_per_frame * self.frames * self.nbr_channels
+    }
+
+    /// Get the total number of forward and inverse FFT pairs, over all channels.
+    pub fn nbr_ffts(&self) -> usize {
+        self.ffts_per_channel * self.nbr_channels
+    }
+}
+
+impl ResamplerConfig {
+    /// Get the theoretical worst-case work for one call to process, see [ChunkWork].
+    /// Returns `None` for resamplers implemented outside of this crate,
+    /// where the algorithm is [ResamplerAlgorithm::Custom].
+    ///
+    /// The wrappers add very little work of their own, and are not counted.
+    pub fn worst_case_work(&self) -> Option<ChunkWork> {
+        let mut work = ChunkWork {
+            taps_per_frame: 0,
+            frames: self.output_frames_max,
+            nbr_channels: self.nbr_channels,
+            fft_sizes: None,
+            ffts_per_channel: 0,
+            spectrum_multiplies: 0,
+        };
+        match self.algorithm {
+            ResamplerAlgorithm::Sinc {
+                sinc_len,
+                interpolation,
+                ..
+            } => {
+                let points = match interpolation {
+                    SincInterpolationType::Cubic => 4,
+                    SincInterpolationType::Quadratic => 3,
+                    SincInterpolationType::Linear => 2,
+                    SincInterpolationType::Nearest => 1,
+                };
+                work.taps_per_frame = sinc_len * points;
+            }
+            ResamplerAlgorithm::Polynomial { degree } => {
+                work.taps_per_frame = match degree {
+                    PolynomialDegree::Septic => 8,
+                    PolynomialDegree::Quintic => 6,
+                    PolynomialDegree::Cubic => 4,
+                    PolynomialDegree::Linear => 2,
+                    PolynomialDegree::Nearest => 1,
+                };
+            }
+            ResamplerAlgorithm::Fft {
+                fft_size_in,
+                fft_size_out,
+            } => {
+                work.fft_sizes = Some((2 * fft_size_in, 2 * fft_size_out));
+                work.ffts_per_channel = (self.output_frames_max + fft_size_out - 1) / fft_size_out;
+                work.spectrum_multiplies =
+                    work.ffts_per_channel * (fft_size_in + 1).min(fft_size_out);
+            }
+            ResamplerAlgorithm::PartitionedFft { nbr_partitions } => {
+                // The filter is applied at the common rate of the input and output
+                let common = integer::lcm(self.input_frames_max, self.output_frames_max);
+                work.fft_sizes = Some((2 * self.input_frames_max, 2 * self.output_frames_max));
+                work.ffts_per_channel = 1;
+                work.spectrum_multiplies = nbr_partitions * (common + 1);
+            }
+            ResamplerAlgorithm::Decimation { filter_len, .. } => {
+                work.taps_per_frame = filter_len;
+            }
+            ResamplerAlgorithm::Custom => return None,
+        }
+        Some(work)
+    }
+}
+
+/// Measure the worst-case execution time of one call to process, after a warm-up.
+///
+/// First `warmup` chunks are processed without measuring, to fill the caches
+/// and to let the CPU leave any power saving state.
+/// Then `runs` chunks are processed, and the longest time of a single call is returned.
+/// The chunks are as long as [input_frames_max](Resampler::input_frames_max),
+/// and contain noise, so that no shortcut for silence is taken.
+/// The measured time includes the scheduling of the measuring thread,
+/// so run it on an otherwise idle system, and add a margin before using it as a budget.
+/// Note that the time can be longer in a realtime callback, where the caches
+/// are shared with the rest of the audio processing.
+///
+/// The resampler is reset before and after the measurement.
+/// The buffers are allocated before the measurement starts.
+///
+/// Parameters are:
+/// - `resampler`: The resampler to measure.
+/// - `warmup`: Number of chunks to process before measuring.
+/// - `runs`: Number of chunks to measure.
+///
+/// ```
+/// use rubato::{measure_worst_case_time, FftFixedIn, Resampler};
+/// let mut resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 2).unwrap();
+/// let worst = measure_worst_case_time(&mut resampler, 10, 100).unwrap();
+/// let budget = 1024.0 / 44100.0;
+/// println!("Using {:.1}% of the budget", 100.0 * worst.as_secs_f64() / budget);
+/// ```
+pub fn measure_worst_case_time<T, R>(
+    resampler: &mut R,
+    warmup: usize,
+    runs: usize,
+) -> ResampleResult<Duration>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    let nbr_channels = resampler.nbr_channels();
+    let frames_in = resampler.input_frames_max();
+    // A linear congruential generator is good enough for noise that is not silence
+    let mut state = 0x2545_f491u32;
+    let waves_in: Vec<Vec<T>> = (0..nbr_channels)
+        .map(|_| {
+            (0..frames_in)
+                .map(|_| {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    T::coerce(state as f64 / u32::MAX as f64 - 0.5)
+                })
+                .collect()
+        })
+        .collect();
+    let mut waves_out = vec![vec![T::zero(); resampler.output_frames_max()]; nbr_channels];
+    resampler.reset();
+    let mut worst = Duration::from_secs(0);
+    for run in 0..warmup + runs {
+        let frames = resampler.input_frames_next();
+        let start = Instant::now();
+        let result = {
+            let chunk: Vec<&[T]> = waves_in.iter().map(|wave| &wave[..frames]).collect();
+            resampler.process_into_buffer(&chunk, &mut waves_out, None)
+        };
+        let elapsed = start.elapsed();
+        if let Err(err) = result {
+            resampler.reset();
+            return Err(err);
+        }
+        if run >= warmup && elapsed > worst {
+            worst = elapsed;
+        }
+    }
+    resampler.reset();
+    debug!(
+        "Worst case time {:?} of {} runs after {} warm-up runs",
+        worst, runs, warmup
+    );
+    Ok(worst)
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{
+        measure_worst_case_time, FastFixedIn, FftFixedIn, FftLowLatency, PolynomialDegree,
+        Resampler, ResamplerAlgorithm, SincDecimator, SincFixedOut, SincInterpolationParameters,
+        SincInterpolationType, SpeechPreset,
+    };
+
+    #[test]
+    fn worst_case_work() {
+        let mut params = SincInterpolationParameters::speech(SpeechPreset::Wideband);
+        params.interpolation = SincInterpolationType::Cubic;
+        let resampler = SincFixedOut::<f64>::new(1.5, 1.1, params, 512, 2).unwrap();
+        let config = resampler.config();
+        let sinc_len = match config.algorithm {
+            ResamplerAlgorithm::Sinc { sinc_len, .. } => sinc_len,
+            _ => panic!("not a sinc resampler"),
+        };
+        let work = config.worst_case_work().unwrap();
+        assert_eq!(work.taps_per_frame, 4 * sinc_len);
+        assert_eq!(work.frames, 512);
+        assert_eq!(work.multiply_adds(), 2 * 512 * 4 * sinc_len);
+        assert_eq!(work.fft_sizes, None);
+
+        let resampler =
+            FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Quintic, 512, 1).unwrap();
+        let work = resampler.config().worst_case_work().unwrap();
+        assert_eq!(work.taps_per_frame, 6);
+        assert_eq!(work.frames, resampler.output_frames_max());
+
+        // 1029 frames in, 1120 out, in sub chunks of 147 and 160 frames
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1029, 7, 2).unwrap();
+        let work = resampler.config().worst_case_work().unwrap();
+        assert_eq!(work.fft_sizes, Some((294, 320)));
+        assert_eq!(work.ffts_per_channel, resampler.output_frames_max() / 160);
+        assert_eq!(work.nbr_ffts(), 2 * work.ffts_per_channel);
+        assert_eq!(work.spectrum_multiplies, work.ffts_per_channel * 148);
+        assert_eq!(work.multiply_adds(), 0);
+
+        let resampler = FftLowLatency::<f64>::new(44100, 48000, 147, 4, 1).unwrap();
+        let work = resampler.config().worst_case_work().unwrap();
+        assert_eq!(work.fft_sizes, Some((294, 320)));
+        assert_eq!(work.spectrum_multiplies, 4 * (147 * 160 + 1));
+
+        let params = SincInterpolationParameters::speech(SpeechPreset::Narrowband);
+        let resampler = SincDecimator::<f64>::new(3, params, 960, 1).unwrap();
+        let work = resampler.config().worst_case_work().unwrap();
+        assert_eq!(work.taps_per_frame, 3 * 64);
+        assert_eq!(work.multiply_adds(), work.frames * 3 * 64);
+    }
+
+    #[test]
+    fn measure_time() {
+        let mut resampler =
+            FastFixedIn::<f32>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        resampler.set_resample_ratio(1.6, false).unwrap();
+        let worst = measure_worst_case_time(&mut resampler, 2, 5).unwrap();
+        assert!(worst.as_nanos() > 0);
+        // The resampler is left reset
+        assert_eq!(resampler.input_frames_next(), 256);
+        let mut output = vec![vec![0.0f32; resampler.output_frames_max()]; 2];
+        let (_, frames_out) = resampler
+            .process_into_buffer(&vec![vec![0.0f32; 256]; 2], &mut output, None)
+            .unwrap();
+        assert!(frames_out < 384);
+    }
+}
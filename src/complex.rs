@@ -0,0 +1,486 @@
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::{heap_size, heap_size_nested, Resampler, Sample};
+use num_complex::Complex;
+
+/// The layouts of complex frames that can be given to a [ComplexResampler].
+trait ComplexFrames<T>: Sized {
+    /// Number of values per complex frame.
+    const VALUES_PER_FRAME: usize;
+
+    /// Copy the real and imaginary parts of the first `re.len()` frames to `re` and `im`.
+    fn split(values: &[Self], re: &mut [T], im: &mut [T]);
+
+    /// Copy the real and imaginary parts in `re` and `im` to the first `re.len()` frames.
+    fn join(re: &[T], im: &[T], values: &mut [Self]);
+}
+
+impl<T: Sample> ComplexFrames<T> for Complex<T> {
+    const VALUES_PER_FRAME: usize = 1;
+
+    fn split(values: &[Self], re: &mut [T], im: &mut [T]) {
+        for ((value, re), im) in values.iter().zip(re.iter_mut()).zip(im.iter_mut()) {
+            *re = value.re;
+            *im = value.im;
+        }
+    }
+
+    fn join(re: &[T], im: &[T], values: &mut [Self]) {
+        for ((value, re), im) in values.iter_mut().zip(re.iter()).zip(im.iter()) {
+            *value = Complex::new(*re, *im);
+        }
+    }
+}
+
+/// Interleaved I/Q, with the real part first in each pair of values.
+impl<T: Sample> ComplexFrames<T> for T {
+    const VALUES_PER_FRAME: usize = 2;
+
+    fn split(values: &[Self], re: &mut [T], im: &mut [T]) {
+        for ((pair, re), im) in values.chunks_exact(2).zip(re.iter_mut()).zip(im.iter_mut()) {
+            *re = pair[0];
+            *im = pair[1];
+        }
+    }
+
+    fn join(re: &[T], im: &[T], values: &mut [Self]) {
+        for ((pair, re), im) in values.chunks_exact_mut(2).zip(re.iter()).zip(im.iter()) {
+            pair[0] = *re;
+            pair[1] = *im;
+        }
+    }
+}
+
+/// A wrapper for a [Resampler] that resamples complex signals,
+/// such as the baseband I/Q streams of software defined radios.
+///
+/// The filters of the resamplers are real and symmetric, so they treat positive and negative
+/// frequencies the same. Resampling a complex signal is then the same as resampling
+/// its real and imaginary parts as two channels, with the same filter and the same timing.
+/// The wrapped resampler must therefore have two channels for each complex channel.
+/// The real and imaginary parts of complex channel `n` are processed as channels `2n` and `2n + 1`.
+/// The whole band from -fs/2 to fs/2 is kept, and when downsampling the bandwidth is
+/// limited to the range from -fs_out/2 to fs_out/2.
+///
+/// The signal can be given as [Complex] values with
+/// [process_into_buffer](ComplexResampler::process_into_buffer),
+/// or as interleaved I/Q values with
+/// [process_interleaved_into_buffer](ComplexResampler::process_interleaved_into_buffer).
+/// The values are copied to and from internal buffers with one channel per part.
+/// These are allocated when the wrapper is created, and processing does not allocate,
+/// unless the wrapped resampler needs larger buffers after changing its chunk size.
+/// ```
+/// use num_complex::Complex;
+/// use rubato::{ComplexResampler, FastFixedIn, PolynomialDegree};
+/// // One complex channel, resampled from 2.4 MHz to 48 kHz
+/// let resampler = FastFixedIn::<f32>::new(0.02, 1.0, PolynomialDegree::Cubic, 4800, 2).unwrap();
+/// let mut resampler = ComplexResampler::new(resampler).unwrap();
+/// let iq = vec![vec![Complex::new(0.0f32, 0.0); 4800]];
+/// let mut waves_out = vec![vec![Complex::new(0.0f32, 0.0); resampler.output_frames_max()]];
+/// let (frames_in, frames_out) = resampler.process_into_buffer(&iq, &mut waves_out, None).unwrap();
+/// assert_eq!(frames_in, 4800);
+/// assert!(frames_out <= resampler.output_frames_max());
+/// ```
+pub struct ComplexResampler<R, T> {
+    resampler: R,
+    input_buffer: Vec<Vec<T>>,
+    output_buffer: Vec<Vec<T>>,
+    channel_mask: Vec<bool>,
+}
+
+impl<R, T> ComplexResampler<R, T>
+where
+    R: Resampler<T>,
+    T: Sample,
+{
+    /// Create a new ComplexResampler wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap, with twice as many channels as the complex signal.
+    ///
+    /// Returns an error if the resampler has an odd number of channels.
+    pub fn new(resampler: R) -> Result<Self, ResamplerConstructionError> {
+        let nbr_channels = resampler.nbr_channels();
+        if nbr_channels % 2 != 0 {
+            return Err(ResamplerConstructionError::OddNumberOfChannels(
+                nbr_channels,
+            ));
+        }
+        debug!(
+            "Create new ComplexResampler, complex channels: {}",
+            nbr_channels / 2
+        );
+        let input_buffer = vec![vec![T::zero(); resampler.input_frames_max()]; nbr_channels];
+        let output_buffer = vec![vec![T::zero(); resampler.output_frames_max()]; nbr_channels];
+        Ok(ComplexResampler {
+            resampler,
+            input_buffer,
+            output_buffer,
+            channel_mask: vec![true; nbr_channels],
+        })
+    }
+
+    /// Get the number of complex channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels() / 2
+    }
+
+    /// Get the number of complex frames per channel needed for the next call to process.
+    pub fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    /// Get the maximum number of complex frames per channel needed for a call to process.
+    pub fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    /// Get the number of complex frames per channel that the next call to process will output.
+    pub fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    /// Get the maximum number of complex frames per channel that a call to process can output.
+    pub fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    /// Get the delay of the wrapped resampler, as a number of output frames.
+    pub fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    /// Get the approximate heap memory used by the wrapper and the wrapped resampler, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+            + heap_size_nested(&self.input_buffer)
+            + heap_size_nested(&self.output_buffer)
+            + heap_size(&self.channel_mask)
+    }
+
+    /// Reset the wrapped resampler.
+    pub fn reset(&mut self) {
+        self.resampler.reset();
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    /// Use this for example to update the resample ratio.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Resample a chunk of complex frames into the provided output buffers.
+    /// This works like [Resampler::process_into_buffer], with one vector of [Complex] values
+    /// per channel, and the mask selecting complex channels.
+    /// Returns the number of input frames consumed and output frames written.
+    pub fn process_into_buffer<Vin: AsRef<[Complex<T>]>, Vout: AsMut<[Complex<T>]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_frames(wave_in, wave_out, active_channels_mask)
+    }
+
+    /// Resample a chunk of interleaved I/Q values into the provided output buffers.
+    /// Each channel is a vector of values where the real and imaginary parts alternate,
+    /// starting with the real part, so that it holds twice as many values as frames.
+    /// Otherwise this works like [process_into_buffer](ComplexResampler::process_into_buffer),
+    /// and returns the numbers of complex frames.
+    pub fn process_interleaved_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_frames(wave_in, wave_out, active_channels_mask)
+    }
+
+    /// Resample a chunk of complex frames, and return newly allocated output buffers.
+    pub fn process<V: AsRef<[Complex<T>]>>(
+        &mut self,
+        wave_in: &[V],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<Vec<Vec<Complex<T>>>> {
+        let frames = self.output_frames_next();
+        let mut wave_out =
+            vec![vec![Complex::new(T::zero(), T::zero()); frames]; self.nbr_channels()];
+        let (_, frames_out) =
+            self.process_into_buffer(wave_in, &mut wave_out, active_channels_mask)?;
+        wave_out
+            .iter_mut()
+            .for_each(|wave| wave.truncate(frames_out));
+        Ok(wave_out)
+    }
+
+    /// Split the complex input into the parts, resample them, and join the output parts.
+    fn process_frames<S, Vin, Vout>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        S: ComplexFrames<T>,
+        Vin: AsRef<[S]>,
+        Vout: AsMut<[S]>,
+    {
+        let nbr_channels = self.nbr_channels();
+        if wave_in.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        if let Some(mask) = active_channels_mask {
+            if mask.len() != nbr_channels {
+                return Err(ResampleError::WrongNumberOfMaskChannels {
+                    expected: nbr_channels,
+                    actual: mask.len(),
+                });
+            }
+        }
+        let active = |chan: usize| active_channels_mask.map(|mask| mask[chan]).unwrap_or(true);
+        let frames_in = self.resampler.input_frames_next();
+        let frames_out = self.resampler.output_frames_next();
+        for chan in (0..nbr_channels).filter(|chan| active(*chan)) {
+            let expected = frames_in * S::VALUES_PER_FRAME;
+            let actual = wave_in[chan].as_ref().len();
+            if actual < expected {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected,
+                    actual,
+                });
+            }
+            let expected = frames_out * S::VALUES_PER_FRAME;
+            let actual = wave_out[chan].as_mut().len();
+            if actual < expected {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        // The chunk size of the wrapped resampler may have been changed
+        for buffer in self.input_buffer.iter_mut() {
+            if buffer.len() < frames_in {
+                buffer.resize(frames_in, T::zero());
+            }
+        }
+        for buffer in self.output_buffer.iter_mut() {
+            if buffer.len() < frames_out {
+                buffer.resize(frames_out, T::zero());
+            }
+        }
+
+        for (chan, ((wave, parts), mask)) in wave_in
+            .iter()
+            .zip(self.input_buffer.chunks_exact_mut(2))
+            .zip(self.channel_mask.chunks_exact_mut(2))
+            .enumerate()
+        {
+            let is_active = active(chan);
+            mask.iter_mut().for_each(|value| *value = is_active);
+            if is_active {
+                let (re, im) = parts.split_at_mut(1);
+                S::split(
+                    wave.as_ref(),
+                    &mut re[0][..frames_in],
+                    &mut im[0][..frames_in],
+                );
+            }
+        }
+        let (nbr_in, nbr_out) = self.resampler.process_into_buffer(
+            &self.input_buffer,
+            &mut self.output_buffer,
+            Some(&self.channel_mask),
+        )?;
+        for chan in (0..nbr_channels).filter(|chan| active(*chan)) {
+            S::join(
+                &self.output_buffer[2 * chan][..nbr_out],
+                &self.output_buffer[2 * chan + 1][..nbr_out],
+                wave_out[chan].as_mut(),
+            );
+        }
+        trace!(
+            "Resampled complex channels {:?}, {} frames in, {} frames out",
+            active_channels_mask,
+            nbr_in,
+            nbr_out
+        );
+        Ok((nbr_in, nbr_out))
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{
+        ComplexResampler, FastFixedIn, PolynomialDegree, ResampleError, Resampler,
+        ResamplerConstructionError, SincFixedIn, SincInterpolationParameters, SpeechPreset,
+    };
+    use num_complex::Complex;
+
+    fn make_resampler(nbr_channels: usize) -> ComplexResampler<FastFixedIn<f64>, f64> {
+        let resampler =
+            FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 256, nbr_channels).unwrap();
+        ComplexResampler::new(resampler).unwrap()
+    }
+
+    #[test]
+    fn negative_frequency_tone() {
+        let make_resampler = || {
+            let params = SincInterpolationParameters::speech(SpeechPreset::Wideband);
+            ComplexResampler::new(SincFixedIn::<f64>::new(0.5, 1.0, params, 1000, 4).unwrap())
+                .unwrap()
+        };
+        let mut resampler = make_resampler();
+        let mut interleaved = make_resampler();
+        assert_eq!(resampler.nbr_channels(), 2);
+        // A tone at a negative frequency in the first channel, and silence in the second
+        let omega = -0.2 * std::f64::consts::PI;
+        let tone = (0..4000)
+            .map(|n| Complex::new((omega * n as f64).cos(), (omega * n as f64).sin()))
+            .collect::<Vec<_>>();
+        let silence = vec![Complex::new(0.0, 0.0); 1000];
+        let mut output = Vec::new();
+        for chunk in tone.chunks(1000) {
+            let wave_out = resampler.process(&[chunk, &silence], None).unwrap();
+            assert!(wave_out[1].iter().all(|value| value.norm() == 0.0));
+
+            let iq_in = chunk
+                .iter()
+                .flat_map(|value| [value.re, value.im])
+                .collect::<Vec<_>>();
+            let mut iq_out = vec![vec![0.0; 2 * interleaved.output_frames_max()]; 2];
+            let (_, frames_out) = interleaved
+                .process_interleaved_into_buffer(&[&iq_in, &vec![0.0; 2000]], &mut iq_out, None)
+                .unwrap();
+            assert_eq!(frames_out, wave_out[0].len());
+            for (value, pair) in wave_out[0].iter().zip(iq_out[0].chunks(2)) {
+                assert_eq!((value.re, value.im), (pair[0], pair[1]));
+            }
+            output.extend_from_slice(&wave_out[0]);
+        }
+        // The output tone rotates backwards at twice the rate, with unity amplitude
+        let delay = Resampler::<f64>::output_delay(resampler.inner());
+        for n in 2 * delay..output.len() - 1 {
+            assert!((output[n].norm() - 1.0).abs() < 0.01);
+            let step = output[n + 1] / output[n];
+            assert!((step.arg() - 2.0 * omega).abs() < 0.01);
+        }
+
+        let mut too_short = vec![vec![Complex::new(0.0, 0.0); 10]; 2];
+        assert!(resampler
+            .process_into_buffer(&[&silence, &silence], &mut too_short, None)
+            .is_err());
+    }
+
+    #[test]
+    fn odd_number_of_channels() {
+        let resampler = FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 256, 3).unwrap();
+        let err = ComplexResampler::new(resampler).err().unwrap();
+        assert!(matches!(
+            err,
+            ResamplerConstructionError::OddNumberOfChannels(3)
+        ));
+        assert_eq!(err.code(), 117);
+    }
+
+    #[test]
+    fn inactive_channel() {
+        let mut resampler = make_resampler(4);
+        let frames_in = resampler.input_frames_next();
+        let wave_in = vec![Complex::new(1.0, -1.0); frames_in];
+        let mut wave_out = vec![vec![Complex::new(5.0, 5.0); resampler.output_frames_max()]; 2];
+        // The inactive channel may be empty, and its output is left untouched
+        let mask = [false, true];
+        let (nbr_in, nbr_out) = resampler
+            .process_into_buffer(&[&[][..], &wave_in[..]], &mut wave_out, Some(&mask))
+            .unwrap();
+        assert_eq!(nbr_in, frames_in);
+        assert!(nbr_out > 0);
+        assert!(wave_out[0]
+            .iter()
+            .all(|value| *value == Complex::new(5.0, 5.0)));
+        assert!(wave_out[1][..nbr_out]
+            .iter()
+            .all(|value| *value != Complex::new(5.0, 5.0)));
+        // Both parts of the inactive complex channel are inactive in the wrapped resampler
+        assert_eq!(resampler.channel_mask, vec![false, false, true, true]);
+    }
+
+    #[test]
+    fn wrong_buffer_sizes() {
+        let mut resampler = make_resampler(4);
+        let frames_in = resampler.input_frames_next();
+        let frames_out = resampler.output_frames_next();
+        let wave_in = vec![vec![Complex::new(0.0, 0.0); frames_in]; 2];
+        let mut wave_out = vec![vec![Complex::new(0.0, 0.0); frames_out]; 2];
+
+        let err = resampler
+            .process_into_buffer(&wave_in[..1], &mut wave_out, None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::WrongNumberOfInputChannels {
+                expected: 2,
+                actual: 1
+            }
+        ));
+        let err = resampler
+            .process_into_buffer(&wave_in, &mut wave_out[..1], None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::WrongNumberOfOutputChannels {
+                expected: 2,
+                actual: 1
+            }
+        ));
+        let err = resampler
+            .process_into_buffer(&wave_in, &mut wave_out, Some(&[true, true, true, true]))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::WrongNumberOfMaskChannels {
+                expected: 2,
+                actual: 4
+            }
+        ));
+        let short_in = [&wave_in[0][..], &wave_in[1][..frames_in - 1]];
+        let err = resampler
+            .process_into_buffer(&short_in, &mut wave_out, None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::InsufficientInputBufferSize { channel: 1, .. }
+        ));
+
+        // The interleaved buffers need two values per frame
+        let iq_in = vec![vec![0.0; 2 * frames_in]; 2];
+        let mut iq_out = vec![vec![0.0; frames_out]; 2];
+        let err = resampler
+            .process_interleaved_into_buffer(&iq_in, &mut iq_out, None)
+            .unwrap_err();
+        assert_eq!(err.expected(), Some(2 * frames_out));
+        assert_eq!(err.actual(), Some(frames_out));
+        let mut iq_out = vec![vec![0.0; 2 * frames_out]; 2];
+        let (nbr_in, nbr_out) = resampler
+            .process_interleaved_into_buffer(&iq_in, &mut iq_out, None)
+            .unwrap();
+        assert_eq!(nbr_in, frames_in);
+        assert!(nbr_out <= frames_out);
+    }
+}
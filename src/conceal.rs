@@ -0,0 +1,446 @@
+use crate::error::ResampleResult;
+use crate::{heap_size, heap_size_nested, process_wrapped, validate_and_update_mask, Contiguous};
+use crate::{Resampler, ResamplerConfig, Sample};
+
+/// A wrapper for a [Resampler] that bridges short gaps in the input.
+///
+/// When input data is lost, for example a dropped network packet,
+/// feeding zeros to the resampler in its place leaves an abrupt step in the output
+/// that is heard as a thump or a click.
+/// This wrapper keeps a copy of the last chunk of input, and when a chunk is missing,
+/// [process_missing](ConcealDropouts::process_missing) processes a synthesized chunk instead.
+///
+/// The fill is made by repeating the last chunk of input, alternating between playing it
+/// backwards and forwards so that the fill has no steps.
+/// It is faded out during the first `fade_frames` missing frames,
+/// and is silent after that if the gap is longer.
+/// When the input returns, the first `fade_frames` frames of the new input are cross-faded
+/// with the continued fill.
+///
+/// All buffers are allocated when the wrapper is created, and processing does not allocate.
+/// ```
+/// use rubato::{ConcealDropouts, FftFixedIn, Resampler};
+/// let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+/// let mut resampler = ConcealDropouts::new(resampler, 512);
+/// let waves_in = vec![vec![0.0f64; 1024]; 2];
+/// let _waves_out = resampler.process(&waves_in, None).unwrap();
+/// // The next chunk was lost.
+/// let _waves_out = resampler.process_missing(None).unwrap();
+/// let _waves_out = resampler.process(&waves_in, None).unwrap();
+/// ```
+pub struct ConcealDropouts<R, T> {
+    resampler: R,
+    fade_frames: usize,
+    history: Vec<Vec<T>>,
+    history_frames: usize,
+    buffer: Vec<Vec<T>>,
+    channel_mask: Vec<bool>,
+    fill_pos: usize,
+    missing_frames: usize,
+    concealed_frames: u64,
+}
+
+impl<R, T> ConcealDropouts<R, T>
+where
+    R: Resampler<T>,
+    T: Sample,
+{
+    /// Create a new ConcealDropouts wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    /// - `fade_frames`: Length of the fade out of the fill, and of the cross-fade when the input returns,
+    ///   in input frames. A value of zero gives fill at full level and no cross-fade.
+    pub fn new(resampler: R, fade_frames: usize) -> Self {
+        let nbr_channels = resampler.nbr_channels();
+        let frames = resampler.input_frames_max();
+        ConcealDropouts {
+            resampler,
+            fade_frames,
+            history: vec![vec![T::zero(); frames]; nbr_channels],
+            history_frames: 0,
+            buffer: vec![vec![T::zero(); frames]; nbr_channels],
+            channel_mask: vec![true; nbr_channels],
+            fill_pos: 0,
+            missing_frames: 0,
+            concealed_frames: 0,
+        }
+    }
+
+    /// Process a synthesized chunk of input in place of a missing one, see [Resampler::process].
+    /// The length of the synthesized chunk is given by [input_frames_next](Resampler::input_frames_next).
+    pub fn process_missing(
+        &mut self,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        let frames = self.output_frames_next();
+        let mut wave_out = Vec::with_capacity(self.nbr_channels());
+        for chan in 0..self.nbr_channels() {
+            let chan_out = if active_channels_mask.map(|mask| mask[chan]).unwrap_or(true) {
+                vec![T::zero(); frames]
+            } else {
+                vec![]
+            };
+            wave_out.push(chan_out);
+        }
+        let (_, out_len) = self.process_missing_into_buffer(&mut wave_out, active_channels_mask)?;
+        for chan_out in wave_out.iter_mut() {
+            chan_out.truncate(out_len);
+        }
+        Ok(wave_out)
+    }
+
+    /// Process a synthesized chunk of input in place of a missing one,
+    /// and write the output to a pre-allocated buffer, see [Resampler::process_into_buffer].
+    pub fn process_missing_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames = self.resampler.input_frames_next();
+        for chan in 0..self.buffer.len() {
+            self.fill(chan, frames, |_, fill| fill);
+        }
+        let result =
+            self.resampler
+                .process_into_buffer(&self.buffer, wave_out, active_channels_mask)?;
+        self.fill_pos += frames;
+        self.missing_frames += frames;
+        self.concealed_frames += frames as u64;
+        trace!("Concealed {} missing input frames", frames);
+        Ok(result)
+    }
+
+    /// Get the total number of input frames that have been synthesized.
+    pub fn concealed_frames(&self) -> u64 {
+        self.concealed_frames
+    }
+
+    /// Returns true if the last processed chunk was synthesized.
+    pub fn is_concealing(&self) -> bool {
+        self.missing_frames > 0
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Write `frames` frames of fill for channel `chan` to the buffer.
+    /// Each fill sample is passed through `mix` together with its index before it is written.
+    fn fill<M>(&mut self, chan: usize, frames: usize, mix: M)
+    where
+        M: Fn(usize, T) -> T,
+    {
+        let history = &self.history[chan][..self.history_frames];
+        let buffer = &mut self.buffer[chan][..frames];
+        let len = history.len();
+        for (n, sample) in buffer.iter_mut().enumerate() {
+            let fill = if len == 0 {
+                T::zero()
+            } else {
+                // Play the history backwards from the end, then forwards, and so on
+                let pos = (self.fill_pos + n) % (2 * len);
+                let value = if pos < len {
+                    history[len - 1 - pos]
+                } else {
+                    history[pos - len]
+                };
+                let missing = self.missing_frames + n;
+                if self.fade_frames == 0 {
+                    value
+                } else if missing < self.fade_frames {
+                    value * T::coerce(1.0 - missing as f64 / self.fade_frames as f64)
+                } else {
+                    T::zero()
+                }
+            };
+            *sample = mix(n, fill);
+        }
+    }
+
+    /// Keep the stored input, and make room for the longest chunk of the wrapped resampler.
+    fn grow_buffers(&mut self) {
+        let frames = self.resampler.input_frames_max();
+        for (history, buffer) in self.history.iter_mut().zip(self.buffer.iter_mut()) {
+            if history.len() < frames {
+                history.resize(frames, T::zero());
+                buffer.resize(frames, T::zero());
+            }
+        }
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames = self.resampler.input_frames_next();
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
+            &Contiguous(wave_in),
+            wave_out,
+            frames,
+            self.resampler.output_frames_next(),
+        )?;
+        let result = if self.missing_frames > 0 && self.fade_frames > 0 {
+            // Cross-fade from the fill to the new input
+            let fade_frames = self.fade_frames;
+            let mask = std::mem::take(&mut self.channel_mask);
+            for (chan, chan_in, _) in layout.active_channels(&mask) {
+                let wave = wave_in[chan_in].as_ref();
+                self.fill(chan, frames, |n, fill| {
+                    if n < fade_frames {
+                        let weight = T::coerce(n as f64 / fade_frames as f64);
+                        wave[n] * weight + fill * (T::one() - weight)
+                    } else {
+                        wave[n]
+                    }
+                });
+            }
+            self.channel_mask = mask;
+            process_wrapped(
+                &mut self.resampler,
+                &self.buffer,
+                wave_out,
+                active_channels_mask,
+                scratch,
+            )?
+        } else {
+            process_wrapped(
+                &mut self.resampler,
+                wave_in,
+                wave_out,
+                active_channels_mask,
+                scratch,
+            )?
+        };
+        for (history, active) in self.history.iter_mut().zip(self.channel_mask.iter()) {
+            if !active {
+                history[..frames]
+                    .iter_mut()
+                    .for_each(|value| *value = T::zero());
+            }
+        }
+        for (chan, chan_in, _) in layout.active_channels(&self.channel_mask) {
+            self.history[chan][..frames].copy_from_slice(&wave_in[chan_in].as_ref()[..frames]);
+        }
+        self.history_frames = frames;
+        self.fill_pos = 0;
+        self.missing_frames = 0;
+        Ok(result)
+    }
+}
+
+impl<T, R> Resampler<T> for ConcealDropouts<R, T>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)?;
+        self.grow_buffers();
+        Ok(())
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)?;
+        self.grow_buffers();
+        Ok(())
+    }
+
+    /// Load the history of the wrapped resampler, and discard the stored input.
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)?;
+        self.history_frames = 0;
+        self.fill_pos = 0;
+        self.missing_frames = 0;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+            + heap_size_nested(&self.history)
+            + heap_size_nested(&self.buffer)
+            + heap_size(&self.channel_mask)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "ConcealDropouts")
+    }
+
+    /// Reset the wrapped resampler, and discard the stored input.
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.history_frames = 0;
+        self.fill_pos = 0;
+        self.missing_frames = 0;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::{assert_same_output, make_fast_resampler};
+    use crate::{ConcealDropouts, FastFixedIn, Resampler};
+
+    fn make_resampler() -> FastFixedIn<f64> {
+        make_fast_resampler(1.0, 1.0, 64, 1)
+    }
+
+    fn chunk(start: usize) -> Vec<Vec<f64>> {
+        vec![(start..start + 64)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect()]
+    }
+
+    #[test]
+    fn pass_input() {
+        let mut reference = make_resampler();
+        let mut resampler = ConcealDropouts::new(make_resampler(), 32);
+        let chunks: Vec<_> = (0..4).map(|n| chunk(64 * n)).collect();
+        assert_same_output(&mut reference, &mut resampler, &chunks);
+        assert_eq!(resampler.concealed_frames(), 0);
+    }
+
+    #[test]
+    fn conceal_gap() {
+        let mut resampler = ConcealDropouts::new(make_resampler(), 96);
+        resampler.process(&chunk(0), None).unwrap();
+        let last = resampler.process(&chunk(64), None).unwrap();
+        let filled = resampler.process_missing(None).unwrap();
+        assert!(resampler.is_concealing());
+        let filled2 = resampler.process_missing(None).unwrap();
+        let after = resampler.process(&chunk(256), None).unwrap();
+        assert!(!resampler.is_concealing());
+        assert_eq!(resampler.concealed_frames(), 128);
+
+        // The output has no steps larger than those of the input
+        let output: Vec<f64> = [last, filled, filled2, after]
+            .iter()
+            .flat_map(|wave| wave[0].clone())
+            .collect();
+        for pair in output.windows(2) {
+            assert!((pair[1] - pair[0]).abs() < 0.1, "step {:?}", pair);
+        }
+        // The fill fades out
+        assert!(output[64 + 100..64 + 128].iter().all(|v| v.abs() < 0.1));
+    }
+
+    #[test]
+    fn compact_buffers() {
+        let mask = [false, true];
+        let mut reference = ConcealDropouts::new(make_fast_resampler(1.0, 1.0, 64, 2), 32);
+        let mut resampler = ConcealDropouts::new(make_fast_resampler(1.0, 1.0, 64, 2), 32);
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]];
+        // A chunk, a gap, and then the input returns and is cross-faded with the fill
+        for start in [Some(0), None, Some(128), Some(192)] {
+            let (expected, (_, frames_out)) = match start {
+                Some(start) => {
+                    let wave = chunk(start).remove(0);
+                    let waves = [wave.clone(), wave.clone()];
+                    (
+                        reference.process(&waves, Some(&mask)).unwrap(),
+                        resampler
+                            .process_into_buffer(&[&wave], &mut out, Some(&mask))
+                            .unwrap(),
+                    )
+                }
+                None => (
+                    reference.process_missing(Some(&mask)).unwrap(),
+                    resampler
+                        .process_missing_into_buffer(&mut out, Some(&mask))
+                        .unwrap(),
+                ),
+            };
+            assert_eq!(expected[1][..], out[0][..frames_out]);
+        }
+    }
+
+    #[test]
+    fn conceal_without_history() {
+        let mut resampler = ConcealDropouts::new(make_resampler(), 32);
+        let filled = resampler.process_missing(None).unwrap();
+        assert!(filled[0].iter().all(|v| *v == 0.0));
+    }
+}
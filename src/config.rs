@@ -0,0 +1,227 @@
+use crate::sinc_interpolator::InterpolatorBackend;
+use crate::{PolynomialDegree, Resampler, Sample, SincInterpolationType};
+use std::fmt;
+
+/// The algorithm used by a resampler, with its main quality parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResamplerAlgorithm {
+    /// Asynchronous resampling with sinc interpolation,
+    /// see [SincFixedIn](crate::SincFixedIn) and [SincFixedOut](crate::SincFixedOut).
+    Sinc {
+        /// Length of the sinc filters.
+        sinc_len: usize,
+        /// Number of sincs used for oversampling.
+        nbr_sincs: usize,
+        /// Interpolation between the oversampled points.
+        interpolation: SincInterpolationType,
+        /// Implementation of the scalar products.
+        backend: InterpolatorBackend,
+    },
+    /// Asynchronous resampling with polynomial interpolation,
+    /// see [FastFixedIn](crate::FastFixedIn) and [FastFixedOut](crate::FastFixedOut).
+    Polynomial {
+        /// Degree of the interpolation polynomial.
+        degree: PolynomialDegree,
+    },
+    /// Synchronous resampling with FFT, see [FftFixedIn](crate::FftFixedIn),
+    /// [FftFixedOut](crate::FftFixedOut) and [FftFixedInOut](crate::FftFixedInOut).
+    Fft {
+        /// Length of the FFT of the input.
+        fft_size_in: usize,
+        /// Length of the inverse FFT of the output.
+        fft_size_out: usize,
+    },
+    /// Synchronous resampling with a partitioned FFT convolution,
+    /// see [FftLowLatency](crate::FftLowLatency).
+    PartitionedFft {
+        /// Number of partitions of the filter.
+        nbr_partitions: usize,
+    },
+    /// Synchronous decimation by an integer factor, see [SincDecimator](crate::SincDecimator).
+    Decimation {
+        /// Ratio between the input and output sample rates.
+        factor: usize,
+        /// Length of the filter at the input rate.
+        filter_len: usize,
+        /// Implementation of the scalar products.
+        backend: InterpolatorBackend,
+    },
+    /// A resampler implemented outside of this crate.
+    Custom,
+}
+
+/// A description of what a resampler is running, as returned by [Resampler::config].
+///
+/// The [Display](fmt::Display) implementation gives a one line summary for logging.
+/// ```
+/// use rubato::{FastFixedIn, PolynomialDegree, Resampler, ResamplerAlgorithm, SoftMute};
+/// let resampler = FastFixedIn::<f32>::new(2.0, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap();
+/// let resampler = SoftMute::new(resampler, 64);
+/// let config = Resampler::<f32>::config(&resampler);
+/// assert_eq!(config.name, "FastFixedIn");
+/// assert_eq!(config.wrappers, vec!["SoftMute"]);
+/// assert_eq!(
+///     config.algorithm,
+///     ResamplerAlgorithm::Polynomial { degree: PolynomialDegree::Cubic }
+/// );
+/// println!("{}", config);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResamplerConfig {
+    /// Name of the resampler type, for example "SincFixedIn".
+    pub name: &'static str,
+    /// Names of the wrappers around the resampler, starting from the outermost.
+    pub wrappers: Vec<&'static str>,
+    /// The algorithm and its quality parameters.
+    pub algorithm: ResamplerAlgorithm,
+    /// The current ratio between the output and input sample rates, if it is known.
+    pub resample_ratio: Option<f64>,
+    /// Maximum number of input frames per channel for a call to process.
+    pub input_frames_max: usize,
+    /// Maximum number of output frames per channel from a call to process.
+    pub output_frames_max: usize,
+    /// Number of channels.
+    pub nbr_channels: usize,
+}
+
+impl ResamplerConfig {
+    /// Describe a resampler, taking the frame counts and channels from the resampler itself.
+    pub(crate) fn describe<T, R>(
+        resampler: &R,
+        name: &'static str,
+        algorithm: ResamplerAlgorithm,
+        resample_ratio: Option<f64>,
+    ) -> Self
+    where
+        T: Sample,
+        R: Resampler<T> + ?Sized,
+    {
+        ResamplerConfig {
+            name,
+            wrappers: Vec::new(),
+            algorithm,
+            resample_ratio,
+            input_frames_max: resampler.input_frames_max(),
+            output_frames_max: resampler.output_frames_max(),
+            nbr_channels: resampler.nbr_channels(),
+        }
+    }
+
+    /// Add a wrapper outside the described resampler,
+    /// with the frame counts of the wrapper replacing those of the inner resampler.
+    pub(crate) fn wrapped<T, R>(mut self, wrapper: &R, name: &'static str) -> Self
+    where
+        T: Sample,
+        R: Resampler<T> + ?Sized,
+    {
+        self.wrappers.insert(0, name);
+        self.input_frames_max = wrapper.input_frames_max();
+        self.output_frames_max = wrapper.output_frames_max();
+        self
+    }
+}
+
+impl fmt::Display for ResamplerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for wrapper in self.wrappers.iter() {
+            write!(f, "{}(", wrapper)?;
+        }
+        write!(f, "{}", self.name)?;
+        for _ in self.wrappers.iter() {
+            write!(f, ")")?;
+        }
+        match self.algorithm {
+            ResamplerAlgorithm::Sinc {
+                sinc_len,
+                nbr_sincs,
+                interpolation,
+                backend,
+            } => write!(
+                f,
+                ", sinc length {}, {} sincs, {:?} interpolation, {} backend",
+                sinc_len, nbr_sincs, interpolation, backend
+            )?,
+            ResamplerAlgorithm::Polynomial { degree } => {
+                write!(f, ", {:?} polynomial interpolation", degree)?
+            }
+            ResamplerAlgorithm::Fft {
+                fft_size_in,
+                fft_size_out,
+            } => write!(f, ", fft sizes {} in, {} out", fft_size_in, fft_size_out)?,
+            ResamplerAlgorithm::PartitionedFft { nbr_partitions } => {
+                write!(f, ", {} partitions", nbr_partitions)?
+            }
+            ResamplerAlgorithm::Decimation {
+                factor,
+                filter_len,
+                backend,
+            } => write!(
+                f,
+                ", factor {}, filter length {}, {} backend",
+                factor, filter_len, backend
+            )?,
+            ResamplerAlgorithm::Custom => {}
+        }
+        if let Some(ratio) = self.resample_ratio {
+            write!(f, ", ratio {}", ratio)?;
+        }
+        write!(
+            f,
+            ", {} channels, max {} frames in, max {} frames out",
+            self.nbr_channels, self.input_frames_max, self.output_frames_max
+        )
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::sinc_interpolator::InterpolatorBackend;
+    use crate::{
+        FftFixedIn, Resampler, ResamplerAlgorithm, SincFixedIn, SincInterpolationParameters,
+        SincInterpolationType, SoftMute, SpeechPreset,
+    };
+
+    #[test]
+    fn describe_resamplers() {
+        let params = SincInterpolationParameters::speech(SpeechPreset::Wideband);
+        let resampler = SincFixedIn::<f64>::new(0.5, 1.1, params, 1024, 2).unwrap();
+        let backend = resampler.interpolator_backend();
+        let config = Resampler::<f64>::config(&SoftMute::new(resampler, 64));
+        assert_eq!(config.name, "SincFixedIn");
+        assert_eq!(config.wrappers, vec!["SoftMute"]);
+        assert_eq!(
+            config.algorithm,
+            ResamplerAlgorithm::Sinc {
+                sinc_len: 96,
+                nbr_sincs: 256,
+                interpolation: SincInterpolationType::Linear,
+                backend,
+            }
+        );
+        assert_eq!(config.resample_ratio, Some(0.5));
+        assert_eq!(config.input_frames_max, 1024);
+        assert_eq!(config.nbr_channels, 2);
+        assert_ne!(backend, InterpolatorBackend::Custom);
+
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        let config = Resampler::<f64>::config(&resampler);
+        assert_eq!(config.name, "FftFixedIn");
+        assert!(config.wrappers.is_empty());
+        assert_eq!(
+            config.algorithm,
+            ResamplerAlgorithm::Fft {
+                fft_size_in: 588,
+                fft_size_out: 640,
+            }
+        );
+        assert_eq!(config.resample_ratio, Some(48000.0 / 44100.0));
+        assert_eq!(
+            config.to_string(),
+            format!(
+                "FftFixedIn, fft sizes 588 in, 640 out, ratio {}, 1 channels, max 1024 frames in, max {} frames out",
+                48000.0 / 44100.0,
+                config.output_frames_max
+            )
+        );
+    }
+}
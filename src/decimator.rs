@@ -0,0 +1,320 @@
+use crate::asynchro_sinc::select_interpolator;
+use crate::denormal::flush_scope;
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::sinc::{make_sincs, normalize_gain};
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
+use crate::{
+    heap_size, heap_size_nested, validate_and_update_mask, Contiguous, InputChannels, Resampler,
+    ResamplerAlgorithm, ResamplerConfig, Sample, SincInterpolationParameters, Split,
+};
+
+/// A synchronous resampler that decreases the sample rate by an integer factor,
+/// and accepts a fixed number of audio frames for input.
+///
+/// The anti-aliasing filter runs at the input rate,
+/// but it is only evaluated for the input positions of the output frames,
+/// one dot product for every `decimation_factor` input frames.
+/// The frames that a filter-then-decimate structure would throw away are never calculated.
+/// This makes it much cheaper than the general resamplers for large factors,
+/// for example for analytics pipelines going from 48 kHz to 16 kHz or lower.
+///
+/// The filter is a windowed sinc, with the cutoff at `f_cutoff` times the output Nyquist frequency.
+/// It is `sinc_len * decimation_factor` frames long at the input rate,
+/// so that the `sinc_len`, `f_cutoff` and `window` values of the [SincInterpolationParameters]
+/// give the same transition band, relative to the output rate, as for the sinc resamplers.
+/// The gain is normalized according to `normalization`. The other values of the parameters are not used.
+///
+/// The number of output frames of a chunk varies when the chunk size
+/// is not a multiple of the decimation factor.
+/// ```
+/// use rubato::{Resampler, SincDecimator, SincInterpolationParameters, SincInterpolationType};
+/// let parameters =
+///     SincInterpolationParameters::from_specification(0.1, 100.0, SincInterpolationType::Linear, 128)
+///         .unwrap();
+/// // Resample 48 kHz to 16 kHz
+/// let mut resampler = SincDecimator::<f32>::new(3, parameters, 960, 2).unwrap();
+/// let waves_in = vec![vec![0.0f32; 960]; 2];
+/// let waves_out = resampler.process(&waves_in, None).unwrap();
+/// assert_eq!(waves_out[0].len(), 320);
+/// ```
+pub struct SincDecimator<T> {
+    nbr_channels: usize,
+    chunk_size: usize,
+    decimation_factor: usize,
+    interpolator: Box<dyn SincInterpolator<T>>,
+    /// The last `filter_len` input frames of the previous chunk, followed by the new chunk.
+    buffer: Vec<Vec<T>>,
+    /// Start of the filter window of the next output frame in the buffer.
+    next_start: usize,
+    channel_mask: Vec<bool>,
+}
+
+impl<T> SincDecimator<T>
+where
+    T: Sample,
+{
+    /// Create a new SincDecimator
+    ///
+    /// Parameters are:
+    /// - `decimation_factor`: Ratio between input and output sample rates, must be >= 2.
+    /// - `parameters`: Parameters for the filter, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new(
+        decimation_factor: usize,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincDecimator, factor: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            decimation_factor, chunk_size, nbr_channels, parameters
+        );
+        if decimation_factor < 2 {
+            return Err(ResamplerConstructionError::UnsupportedRatio(
+                1.0 / decimation_factor as f64,
+            ));
+        }
+        let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+        let filter_len = sinc_len * decimation_factor;
+        let f_cutoff = parameters.f_cutoff / decimation_factor as f32;
+        let mut sincs = make_sincs::<T>(filter_len, 1, f_cutoff, parameters.window);
+        normalize_gain(&mut sincs, 0.9 * f_cutoff, parameters.normalization);
+        let interpolator = select_interpolator(sincs);
+        let buffer = vec![vec![T::zero(); chunk_size + filter_len]; nbr_channels];
+        Ok(SincDecimator {
+            nbr_channels,
+            chunk_size,
+            decimation_factor,
+            interpolator,
+            buffer,
+            next_start: 0,
+            channel_mask: vec![true; nbr_channels],
+        })
+    }
+
+    /// Get the decimation factor.
+    pub fn decimation_factor(&self) -> usize {
+        self.decimation_factor
+    }
+
+    /// Get the implementation used by the filter for the scalar products,
+    /// see [InterpolatorBackend].
+    pub fn interpolator_backend(&self) -> InterpolatorBackend {
+        self.interpolator.backend()
+    }
+
+    /// Get the number of output frames for a chunk,
+    /// when the filter window of the first one starts at `start`.
+    fn output_frames_from(&self, start: usize) -> usize {
+        if start >= self.chunk_size {
+            0
+        } else {
+            (self.chunk_size - 1 - start) / self.decimation_factor + 1
+        }
+    }
+
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_out = self.output_frames_from(self.next_start);
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
+            wave_in,
+            wave_out,
+            self.chunk_size,
+            frames_out,
+        )?;
+        let history_len = self.interpolator.len();
+        for (chan, chan_in, chan_out) in layout.active_channels(&self.channel_mask) {
+            let buffer = &mut self.buffer[chan];
+            wave_in.copy_frames(chan_in, 0, &mut buffer[history_len..]);
+            let wave_out = wave_out[chan_out].as_mut();
+            // Flush denormals in the inner loop only, if requested by FlushDenormals
+            let flush = flush_scope();
+            for (n, value) in wave_out[..frames_out].iter_mut().enumerate() {
+                let start = self.next_start + n * self.decimation_factor;
+                *value = self.interpolator.get_sinc_interpolated(buffer, start, 0);
+            }
+            drop(flush);
+            buffer.copy_within(self.chunk_size..self.chunk_size + history_len, 0);
+        }
+        self.next_start = self.next_start + frames_out * self.decimation_factor - self.chunk_size;
+        trace!(
+            "Resampling channels {:?}, {} frames in, {} frames out",
+            active_channels_mask,
+            self.chunk_size,
+            frames_out,
+        );
+        Ok((self.chunk_size, frames_out))
+    }
+}
+
+impl<T> Resampler<T> for SincDecimator<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.output_frames_from(0)
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.output_frames_from(self.next_start)
+    }
+
+    fn output_delay(&self) -> usize {
+        self.interpolator.len() / 2 / self.decimation_factor
+    }
+
+    fn input_delay(&self) -> usize {
+        self.interpolator.len() / 2
+    }
+
+    /// Update the resample ratio. This is not supported by this resampler and
+    /// always returns [ResampleError::SyncNotAdjustable].
+    fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Update the resample ratio relative to the original one. This is not
+    /// supported by this resampler and always returns [ResampleError::SyncNotAdjustable].
+    fn set_resample_ratio_relative(&mut self, _rel_ratio: f64, _ramp: bool) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Change the chunk size, see [Resampler::set_chunk_size].
+    /// The input frames that are kept as filter history are not affected.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        let history_len = self.interpolator.len();
+        for buf in self.buffer.iter_mut() {
+            buf.resize(chunk_size + history_len, T::zero());
+        }
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.interpolator.memory_usage()
+            + heap_size_nested(&self.buffer)
+            + heap_size(&self.channel_mask)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "SincDecimator",
+            ResamplerAlgorithm::Decimation {
+                factor: self.decimation_factor,
+                filter_len: self.interpolator.len(),
+                backend: self.interpolator.backend(),
+            },
+            Some(1.0 / self.decimation_factor as f64),
+        )
+    }
+
+    fn reset(&mut self) {
+        self.buffer
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
+        self.channel_mask.iter_mut().for_each(|val| *val = true);
+        self.next_start = 0;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_sinc_params;
+    use crate::{Resampler, SincDecimator, SincInterpolationParameters, SincInterpolationType};
+
+    fn make_params() -> SincInterpolationParameters {
+        SincInterpolationParameters {
+            f_cutoff: 0.9,
+            oversampling_factor: 1,
+            ..make_sinc_params(SincInterpolationType::Linear)
+        }
+    }
+
+    #[test]
+    fn uneven_chunks() {
+        let input = (0..3000)
+            .map(|n| (n as f64 * 0.02).sin())
+            .collect::<Vec<_>>();
+        let mut reference = SincDecimator::<f64>::new(3, make_params(), 3000, 1).unwrap();
+        let expected = reference.process(&[&input], None).unwrap();
+        assert_eq!(expected[0].len(), 1000);
+        // The same output in chunks that are not a multiple of the factor
+        let mut resampler = SincDecimator::<f64>::new(3, make_params(), 100, 1).unwrap();
+        let mut output = Vec::new();
+        for chunk in input.chunks(100) {
+            let frames = resampler.output_frames_next();
+            let wave_out = resampler.process(&[chunk], None).unwrap();
+            assert_eq!(wave_out[0].len(), frames);
+            output.extend_from_slice(&wave_out[0]);
+        }
+        assert_eq!(output, expected[0]);
+    }
+
+    #[test]
+    fn passband_and_stopband() {
+        let delay = SincDecimator::<f64>::new(4, make_params(), 1024, 1)
+            .unwrap()
+            .output_delay();
+        // A tone in the passband at the output rate, and one above the output Nyquist
+        for (freq, expected_amplitude) in [(0.1, 1.0), (1.5, 0.0)] {
+            let mut resampler = SincDecimator::<f64>::new(4, make_params(), 1024, 1).unwrap();
+            let input = (0..4096)
+                .map(|n| (std::f64::consts::PI * freq * n as f64 / 4.0).sin())
+                .collect::<Vec<_>>();
+            let mut output = Vec::new();
+            for chunk in input.chunks(1024) {
+                output.extend_from_slice(&resampler.process(&[chunk], None).unwrap()[0]);
+            }
+            let amplitude = output[2 * delay..]
+                .iter()
+                .fold(0.0f64, |acc, value| acc.max(value.abs()));
+            assert!((amplitude - expected_amplitude).abs() < 0.01);
+        }
+        assert!(SincDecimator::<f64>::new(1, make_params(), 1024, 1).is_err());
+    }
+}
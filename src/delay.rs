@@ -0,0 +1,219 @@
+use crate::Resampler;
+use num_complex::Complex;
+use num_traits::Zero;
+use realfft::RealFftPlanner;
+
+/// Length of the test chirp, in input frames.
+const CHIRP_LEN: usize = 4096;
+/// Number of silent input frames appended after the chirp.
+const TAIL_LEN: usize = 8192;
+
+/// Evaluate the test chirp at time `t`, given in input frames.
+/// The chirp sweeps from a low frequency up to `f_max` (in cycles per input frame),
+/// with a Hann envelope to avoid transients at the ends.
+fn chirp_value(t: f64, f_max: f64) -> f64 {
+    if t < 0.0 || t > CHIRP_LEN as f64 {
+        return 0.0;
+    }
+    let f_min = 0.1 * f_max;
+    let len = CHIRP_LEN as f64;
+    let phase = 2.0 * std::f64::consts::PI * (f_min * t + 0.5 * (f_max - f_min) * t * t / len);
+    let envelope = 0.5 - 0.5 * (2.0 * std::f64::consts::PI * t / len).cos();
+    envelope * phase.sin()
+}
+
+/// Measure the delay through a processing chain by cross-correlation with a known chirp.
+///
+/// A test signal containing a chirp followed by silence is generated at the input sample rate
+/// and passed to the `process` closure, which must run it through the chain and return the complete output.
+/// The output is then correlated with the same chirp evaluated at the output sample rate,
+/// and the position of the correlation peak gives the delay.
+/// The result is given in fractional output frames, which makes it directly comparable
+/// with [output_delay](Resampler::output_delay).
+///
+/// The chirp only contains frequencies that are well below the Nyquist frequency of both sample rates.
+///
+/// Parameters are:
+/// - `resample_ratio`: The ratio between output and input sample rates of the chain.
+/// - `process`: A closure that processes a single channel input signal and returns the output signal.
+///
+/// Returns `None` if the output is too short or only contains silence.
+pub fn measure_delay<F>(resample_ratio: f64, mut process: F) -> Option<f64>
+where
+    F: FnMut(&[f64]) -> Vec<f64>,
+{
+    let f_max = 0.2 * resample_ratio.min(1.0);
+    let input: Vec<f64> = (0..CHIRP_LEN + TAIL_LEN)
+        .map(|n| chirp_value(n as f64, f_max))
+        .collect();
+    let output = process(&input);
+    let reference_len = (CHIRP_LEN as f64 * resample_ratio).ceil() as usize + 1;
+    let reference: Vec<f64> = (0..reference_len)
+        .map(|n| chirp_value(n as f64 / resample_ratio, f_max))
+        .collect();
+    if output.len() < reference_len {
+        return None;
+    }
+    let correlation = cross_correlate(&output, &reference);
+    let (peak, peak_value) =
+        correlation
+            .iter()
+            .enumerate()
+            .fold((0, 0.0), |(best, best_value), (n, value)| {
+                if *value > best_value {
+                    (n, *value)
+                } else {
+                    (best, best_value)
+                }
+            });
+    if peak_value <= 0.0 {
+        return None;
+    }
+    // Refine the peak position with parabolic interpolation
+    let offset = if peak > 0 && peak + 1 < correlation.len() {
+        let prev = correlation[peak - 1];
+        let next = correlation[peak + 1];
+        let denom = prev - 2.0 * peak_value + next;
+        if denom != 0.0 {
+            0.5 * (prev - next) / denom
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+    Some(peak as f64 + offset)
+}
+
+/// Measure the delay through a single [Resampler], by cross-correlation with a known chirp.
+///
+/// The chirp is passed through the first channel, while any other channels are fed with silence.
+/// The resampler is processed chunk by chunk, and the last frames are processed with
+/// [process_partial](Resampler::process_partial).
+/// The result is given in fractional output frames and can be compared
+/// with [output_delay](Resampler::output_delay).
+/// See [measure_delay] for details.
+///
+/// Note that the resampler is not reset before or after the measurement.
+pub fn measure_resampler_delay<R: Resampler<f64>>(
+    resampler: &mut R,
+    resample_ratio: f64,
+) -> Option<f64> {
+    let channels = resampler.nbr_channels();
+    measure_delay(resample_ratio, |input| {
+        let mut output = Vec::new();
+        let mut position = 0;
+        while position < input.len() {
+            let frames = resampler.input_frames_next();
+            let end = (position + frames).min(input.len());
+            let mut waves = vec![vec![0.0; end - position]; channels];
+            waves[0].copy_from_slice(&input[position..end]);
+            let waves_out = if end - position < frames {
+                resampler.process_partial(Some(&waves), None)
+            } else {
+                resampler.process(&waves, None)
+            };
+            match waves_out {
+                Ok(waves_out) => output.extend_from_slice(&waves_out[0]),
+                Err(_) => break,
+            }
+            position = end;
+        }
+        output
+    })
+}
+
+/// Calculate the cross-correlation of `signal` and `reference` for all non-negative lags
+/// where the reference fits within the signal.
+fn cross_correlate(signal: &[f64], reference: &[f64]) -> Vec<f64> {
+    let fft_len = (signal.len() + reference.len()).next_power_of_two();
+    let mut planner = RealFftPlanner::<f64>::new();
+    let fft = planner.plan_fft_forward(fft_len);
+    let ifft = planner.plan_fft_inverse(fft_len);
+
+    let mut signal_t = vec![0.0; fft_len];
+    signal_t[..signal.len()].copy_from_slice(signal);
+    let mut signal_f = vec![Complex::zero(); fft_len / 2 + 1];
+    fft.process(&mut signal_t, &mut signal_f).unwrap();
+
+    let mut reference_t = vec![0.0; fft_len];
+    reference_t[..reference.len()].copy_from_slice(reference);
+    let mut reference_f = vec![Complex::zero(); fft_len / 2 + 1];
+    fft.process(&mut reference_t, &mut reference_f).unwrap();
+
+    for (s, r) in signal_f.iter_mut().zip(reference_f.iter()) {
+        *s *= r.conj();
+    }
+    // The imaginary parts of the first and last bins must be zero for the inverse transform
+    signal_f[0].im = 0.0;
+    signal_f[fft_len / 2].im = 0.0;
+    let mut correlation = vec![0.0; fft_len];
+    ifft.process(&mut signal_f, &mut correlation).unwrap();
+    correlation.truncate(signal.len() - reference.len() + 1);
+    correlation
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{measure_delay, measure_resampler_delay};
+    use crate::{FastFixedIn, FftFixedIn, PolynomialDegree, Resampler};
+
+    #[test]
+    fn measure_integer_delay() {
+        let delay = measure_delay(1.0, |input| {
+            let mut output = vec![0.0; 123];
+            output.extend_from_slice(input);
+            output
+        })
+        .unwrap();
+        assert!((delay - 123.0).abs() < 0.01, "delay {}", delay);
+    }
+
+    #[test]
+    fn measure_fractional_delay() {
+        // Average of two neighboring samples, which gives a delay of 10.5 frames
+        let delay = measure_delay(1.0, |input| {
+            let mut output = vec![0.0; 10];
+            output.push(0.5 * input[0]);
+            for n in 1..input.len() {
+                output.push(0.5 * (input[n - 1] + input[n]));
+            }
+            output
+        })
+        .unwrap();
+        assert!((delay - 10.5).abs() < 0.05, "delay {}", delay);
+    }
+
+    #[test]
+    fn measure_silence() {
+        assert!(measure_delay(1.0, |input| vec![0.0; input.len()]).is_none());
+        assert!(measure_delay(1.0, |_input| vec![0.0; 10]).is_none());
+    }
+
+    #[test]
+    fn measure_fft_resampler() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let delay = measure_resampler_delay(&mut resampler, 48000.0 / 44100.0).unwrap();
+        let expected = resampler.output_delay() as f64;
+        assert!(
+            (delay - expected).abs() < 2.0,
+            "delay {} expected {}",
+            delay,
+            expected
+        );
+    }
+
+    #[test]
+    fn measure_fast_resampler() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 1.0, PolynomialDegree::Cubic, 1024, 1).unwrap();
+        let delay = measure_resampler_delay(&mut resampler, 1.0).unwrap();
+        let expected = resampler.output_delay() as f64;
+        assert!(
+            (delay - expected).abs() < 2.0,
+            "delay {} expected {}",
+            delay,
+            expected
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::asynchro_sinc::interp_cubic;
+use crate::error::{ResampleError, ResampleResult};
+use crate::{Resampler, Sample};
+
+/// A delay line for channels that are passed through at the original sample rate,
+/// to keep them time-aligned with channels that go through a [Resampler].
+///
+/// This suits workflows where only some of the channels are resampled,
+/// while the others must stay at the input rate, for example a channel bed and a set of objects.
+/// The resampled channels are delayed by the filter of the resampler,
+/// and the delay line gives the passed through channels the same delay.
+/// The delay is given in input frames. Whole frames are delayed exactly,
+/// and any fractional part is handled with cubic interpolation between the input frames.
+///
+/// The delay line must be given the same input frames as the resampler,
+/// and returns the same number of frames as it is given.
+/// The buffers are allocated when the delay line is created, and processing does not allocate.
+/// ```
+/// use rubato::{DelayLine, FastFixedIn, PolynomialDegree, Resampler};
+/// let resampler = FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+/// // Two more channels that stay at the input rate
+/// let mut delay_line = DelayLine::<f64>::matching(&resampler, 2);
+/// let passthrough_in = vec![vec![0.0f64; 256]; 2];
+/// let mut passthrough_out = vec![vec![0.0f64; 256]; 2];
+/// delay_line
+///     .process_into_buffer(&passthrough_in, &mut passthrough_out)
+///     .unwrap();
+/// ```
+pub struct DelayLine<T> {
+    buffers: Vec<Vec<T>>,
+    position: usize,
+    whole_frames: usize,
+    fraction: T,
+    delay: f64,
+}
+
+impl<T> DelayLine<T>
+where
+    T: Sample,
+{
+    /// Create a new DelayLine.
+    ///
+    /// Parameters are:
+    /// - `delay`: The delay in input frames, must be >= 0. Values below zero are clamped to zero.
+    /// - `nbr_channels`: Number of channels.
+    pub fn new(delay: f64, nbr_channels: usize) -> Self {
+        let delay = delay.max(0.0);
+        let whole_frames = delay.floor() as usize;
+        let fraction = delay - whole_frames as f64;
+        // Room for the interpolation points on both sides of the delayed position
+        let len = whole_frames + 3;
+        DelayLine {
+            buffers: vec![vec![T::zero(); len]; nbr_channels],
+            position: 0,
+            whole_frames,
+            fraction: T::coerce(fraction),
+            delay,
+        }
+    }
+
+    /// Create a new DelayLine with the delay of a resampler,
+    /// as given by [input_delay](Resampler::input_delay).
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to match.
+    /// - `nbr_channels`: Number of channels of the delay line.
+    pub fn matching<R: Resampler<T>>(resampler: &R, nbr_channels: usize) -> Self {
+        Self::new(resampler.input_delay() as f64, nbr_channels)
+    }
+
+    /// Get the delay in input frames.
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Delay the frames of `wave_in`, and write them to `wave_out`.
+    /// All input channels must have the same length,
+    /// and the output channels must be at least as long.
+    /// Returns the number of frames processed.
+    pub fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+    ) -> ResampleResult<usize> {
+        let nbr_channels = self.buffers.len();
+        if wave_in.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        let frames = wave_in.first().map(|wave| wave.as_ref().len()).unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() < frames {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if wave.as_mut().len() < frames {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual: wave.as_mut().len(),
+                });
+            }
+        }
+
+        let len = self.whole_frames + 3;
+        let interpolate = self.fraction != T::zero();
+        for (buffer, (wave_in, wave_out)) in self
+            .buffers
+            .iter_mut()
+            .zip(wave_in.iter().zip(wave_out.iter_mut()))
+        {
+            let wave_in = wave_in.as_ref();
+            let wave_out = wave_out.as_mut();
+            let mut position = self.position;
+            for (value_in, value_out) in wave_in[..frames].iter().zip(wave_out.iter_mut()) {
+                buffer[position] = *value_in;
+                // The frame that is delayed by the whole frames
+                let delayed = (position + len - self.whole_frames) % len;
+                *value_out = if !interpolate {
+                    buffer[delayed]
+                } else if self.whole_frames == 0 {
+                    let previous = buffer[(delayed + len - 1) % len];
+                    buffer[delayed] + self.fraction * (previous - buffer[delayed])
+                } else {
+                    // Points at -1, 0, 1 and 2, where the delayed position is between 0 and 1
+                    let points = [
+                        buffer[(delayed + len - 2) % len],
+                        buffer[(delayed + len - 1) % len],
+                        buffer[delayed],
+                        buffer[(delayed + 1) % len],
+                    ];
+                    interp_cubic(T::one() - self.fraction, &points)
+                };
+                position = (position + 1) % len;
+            }
+        }
+        self.position = (self.position + frames) % len;
+        Ok(frames)
+    }
+
+    /// Clear the delay line.
+    pub fn reset(&mut self) {
+        self.buffers
+            .iter_mut()
+            .for_each(|buffer| buffer.iter_mut().for_each(|value| *value = T::zero()));
+        self.position = 0;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{DelayLine, FastFixedIn, PolynomialDegree};
+
+    #[test]
+    fn whole_frames() {
+        let input = (0..100).map(|n| n as f64).collect::<Vec<_>>();
+        let mut delay_line = DelayLine::<f64>::new(5.0, 1);
+        let mut output = vec![0.0; 100];
+        // Process in uneven chunks
+        delay_line
+            .process_into_buffer(&[&input[..37]], &mut [&mut output[..37]])
+            .unwrap();
+        delay_line
+            .process_into_buffer(&[&input[37..]], &mut [&mut output[37..]])
+            .unwrap();
+        assert!(output[..5].iter().all(|v| *v == 0.0));
+        assert_eq!(output[5..], input[..95]);
+    }
+
+    #[test]
+    fn fractional_frames() {
+        // The cubic interpolation is exact for a ramp
+        let input = (0..100).map(|n| n as f64).collect::<Vec<_>>();
+        for delay in [0.25, 3.5] {
+            let mut delay_line = DelayLine::<f64>::new(delay, 1);
+            let mut output = vec![vec![0.0; 100]];
+            delay_line
+                .process_into_buffer(&[&input], &mut output)
+                .unwrap();
+            for (n, value) in output[0].iter().enumerate().skip(10) {
+                assert!((value - (n as f64 - delay)).abs() < 1.0e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn matching_resampler() {
+        let resampler = FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 64, 2).unwrap();
+        let delay_line = DelayLine::<f64>::matching(&resampler, 3);
+        assert_eq!(delay_line.delay(), 4.0);
+        assert_eq!(delay_line.nbr_channels(), 3);
+        let mut delay_line = DelayLine::<f64>::new(1.0, 2);
+        let waves = vec![vec![0.0; 10]; 2];
+        let mut out = vec![vec![0.0; 10]; 1];
+        assert!(delay_line.process_into_buffer(&waves, &mut out).is_err());
+    }
+}
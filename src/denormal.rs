@@ -0,0 +1,409 @@
+use crate::error::ResampleResult;
+use crate::{Resampler, ResamplerConfig, Sample};
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse",
+    not(feature = "forbid_unsafe")
+))]
+mod control {
+    use std::arch::asm;
+
+    /// Flush-to-zero (FTZ) and denormals-are-zero (DAZ) bits of the MXCSR register.
+    const FLUSH_BITS: u32 = 0x8040;
+
+    pub(crate) type State = u32;
+
+    pub(crate) fn read() -> State {
+        let mut csr: u32 = 0;
+        unsafe {
+            asm!("stmxcsr [{}]", in(reg) &mut csr, options(nostack, preserves_flags));
+        }
+        csr
+    }
+
+    pub(crate) fn write(csr: State) {
+        unsafe {
+            asm!("ldmxcsr [{}]", in(reg) &csr, options(nostack, preserves_flags, readonly));
+        }
+    }
+
+    pub(crate) fn with_flush(csr: State) -> State {
+        csr | FLUSH_BITS
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "forbid_unsafe")))]
+mod control {
+    use std::arch::asm;
+
+    /// Flush-to-zero (FZ) bit of the FPCR register.
+    const FLUSH_BITS: u64 = 1 << 24;
+
+    pub(crate) type State = u64;
+
+    pub(crate) fn read() -> State {
+        let fpcr: u64;
+        unsafe {
+            asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack, preserves_flags));
+        }
+        fpcr
+    }
+
+    pub(crate) fn write(fpcr: State) {
+        unsafe {
+            asm!("msr fpcr, {}", in(reg) fpcr, options(nomem, nostack, preserves_flags));
+        }
+    }
+
+    pub(crate) fn with_flush(fpcr: State) -> State {
+        fpcr | FLUSH_BITS
+    }
+}
+
+#[cfg(any(
+    not(any(
+        all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            target_feature = "sse"
+        ),
+        target_arch = "aarch64"
+    )),
+    feature = "forbid_unsafe"
+))]
+mod control {
+    /// Placeholder for the register value, there is nothing to save on these targets.
+    pub(crate) type State = u32;
+
+    pub(crate) fn read() -> State {
+        0
+    }
+
+    pub(crate) fn write(_state: State) {}
+
+    pub(crate) fn with_flush(state: State) -> State {
+        state
+    }
+}
+
+/// A scoped guard that makes the cpu flush denormal numbers to zero on the current thread.
+///
+/// Denormal (subnormal) numbers are very small floating point values that many cpus
+/// handle much slower than normal values.
+/// They appear in the filter state of a resampler when the input decays into silence,
+/// and can make the processing of a silent chunk many times slower than a normal one.
+///
+/// Creating the guard saves the floating point control register, and enables flush-to-zero
+/// (and denormals-are-zero on x86). The saved value is restored when the guard is dropped.
+/// This is supported on x86 and x86_64 with SSE, and on aarch64.
+/// On other targets, and when the `forbid_unsafe` feature is enabled, the guard does nothing.
+///
+/// The setting applies to all floating point code on the thread while the guard is alive,
+/// so the guard should be kept for as short a time as possible.
+/// The guard can't be sent to another thread, since it must restore the setting
+/// of the thread that created it.
+///
+/// Note that changing the control register from inline assembly, and keeping it changed
+/// while other Rust code runs, is outside of what the Rust language allows.
+/// The compiler assumes the default floating point environment, and may for example
+/// evaluate a floating point operation at compile time, with a different result
+/// than the same operation at run time.
+/// In practice only operations on denormal values are affected.
+/// Keep the guard around numeric loops only, and don't rely on exact results
+/// for denormal values while it is alive.
+/// The [FlushDenormals] wrapper limits the setting to the inner loops of the resamplers.
+/// ```
+/// use rubato::DenormalGuard;
+/// {
+///     let _guard = DenormalGuard::new();
+///     // Denormals are flushed to zero here
+/// }
+/// // The previous setting is restored here
+/// ```
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<rubato::DenormalGuard>();
+/// ```
+pub struct DenormalGuard {
+    saved: control::State,
+    // The floating point settings belong to the thread, this keeps the guard on it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl DenormalGuard {
+    /// Enable flush-to-zero until the returned guard is dropped.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let saved = control::read();
+        control::write(control::with_flush(saved));
+        DenormalGuard {
+            saved,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for DenormalGuard {
+    fn drop(&mut self) {
+        control::write(self.saved);
+    }
+}
+
+thread_local! {
+    /// Set while a [FlushDenormals] wrapper is processing on this thread.
+    static FLUSH_REQUESTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Flush denormals to zero in an inner processing loop, when a [FlushDenormals] wrapper
+/// is processing on this thread. The setting is restored when the returned guard is dropped.
+pub(crate) fn flush_scope() -> Option<DenormalGuard> {
+    if FLUSH_REQUESTED.with(|requested| requested.get()) {
+        Some(DenormalGuard::new())
+    } else {
+        None
+    }
+}
+
+/// Run `process` with flushing requested for the inner processing loops,
+/// and restore the previous request afterwards, also when `process` panics.
+fn with_flush_requested<F, U>(process: F) -> U
+where
+    F: FnOnce() -> U,
+{
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FLUSH_REQUESTED.with(|requested| requested.set(self.0));
+        }
+    }
+
+    let _restore = Restore(FLUSH_REQUESTED.with(|requested| requested.replace(true)));
+    process()
+}
+
+/// A wrapper for a [Resampler] that flushes denormal numbers to zero while processing.
+///
+/// The resamplers of this crate hold a [DenormalGuard] around their inner processing loops
+/// during each call to [process_into_buffer](Resampler::process_into_buffer),
+/// so that sustained silence in the input does not lead to slow processing.
+/// This also works through other wrappers, like [SoftMute](crate::SoftMute).
+/// The rest of the call, like checking the buffers and copying the input,
+/// runs with the default floating point settings, see [DenormalGuard] for why this matters.
+/// Resamplers implemented outside of this crate are not affected by the wrapper.
+/// See [DenormalGuard] for the supported targets.
+/// ```
+/// use rubato::{FftFixedIn, FlushDenormals, Resampler};
+/// let resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 2).unwrap();
+/// let mut resampler = FlushDenormals::new(resampler);
+/// let waves_in = vec![vec![0.0f32; 1024]; 2];
+/// let waves_out = resampler.process(&waves_in, None).unwrap();
+/// ```
+pub struct FlushDenormals<R> {
+    resampler: R,
+}
+
+impl<R> FlushDenormals<R> {
+    /// Create a new FlushDenormals wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    pub fn new(resampler: R) -> Self {
+        FlushDenormals { resampler }
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+}
+
+impl<T, R> Resampler<T> for FlushDenormals<R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        with_flush_requested(|| {
+            self.resampler
+                .process_into_buffer(wave_in, wave_out, active_channels_mask)
+        })
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        with_flush_requested(|| {
+            self.resampler
+                .process_split_into_buffer(wave_in, wave_out, active_channels_mask)
+        })
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        with_flush_requested(|| {
+            self.resampler.process_into_buffer_with_scratch(
+                wave_in,
+                wave_out,
+                active_channels_mask,
+                scratch,
+            )
+        })
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "FlushDenormals")
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{FastFixedIn, FlushDenormals, PolynomialDegree, Resampler};
+
+    fn halve_smallest_normal() -> f32 {
+        // Read through a volatile pointer to keep the compiler from evaluating this at compile time
+        let value = f32::MIN_POSITIVE;
+        #[cfg(not(feature = "forbid_unsafe"))]
+        let value = unsafe { std::ptr::read_volatile(&value) };
+        value * 0.5
+    }
+
+    #[cfg(all(
+        any(target_arch = "x86_64", target_arch = "aarch64"),
+        not(feature = "forbid_unsafe")
+    ))]
+    #[test]
+    fn guard_flushes_and_restores() {
+        assert!(halve_smallest_normal() > 0.0);
+        {
+            let _guard = crate::DenormalGuard::new();
+            assert_eq!(halve_smallest_normal(), 0.0);
+        }
+        assert!(halve_smallest_normal() > 0.0);
+    }
+
+    #[test]
+    fn flush_only_when_requested() {
+        assert!(super::flush_scope().is_none());
+        super::with_flush_requested(|| {
+            let _guard = super::flush_scope().unwrap();
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "aarch64"),
+                not(feature = "forbid_unsafe")
+            ))]
+            assert_eq!(halve_smallest_normal(), 0.0);
+        });
+        assert!(super::flush_scope().is_none());
+        assert!(halve_smallest_normal() > 0.0);
+    }
+
+    #[test]
+    fn same_output() {
+        let waves = vec![(0..256).map(|n| (n as f64 * 0.1).sin()).collect::<Vec<_>>(); 2];
+        let mut reference =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut resampler = FlushDenormals::new(
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap(),
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                resampler.process(&waves, None).unwrap(),
+                reference.process(&waves, None).unwrap()
+            );
+        }
+        assert!(halve_smallest_normal() > 0.0);
+    }
+}
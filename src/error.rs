@@ -4,6 +4,9 @@ use std::fmt;
 /// An identifier for a cpu feature.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CpuFeature {
+    /// x86 sse2 cpu feature.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Sse2,
     /// x86 sse3 cpu feature.
     #[cfg(target_arch = "x86_64")]
     Sse3,
@@ -22,6 +25,10 @@ impl CpuFeature {
     /// Test if the given CPU feature is detected.
     pub fn is_detected(&self) -> bool {
         match *self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            CpuFeature::Sse2 => {
+                is_x86_feature_detected!("sse2")
+            }
             #[cfg(target_arch = "x86_64")]
             CpuFeature::Sse3 => {
                 is_x86_feature_detected!("sse3")
@@ -46,6 +53,10 @@ impl CpuFeature {
 impl fmt::Display for CpuFeature {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            CpuFeature::Sse2 => {
+                write!(f, "sse2")
+            }
             #[cfg(target_arch = "x86_64")]
             CpuFeature::Sse3 => {
                 write!(f, "sse3")
@@ -79,10 +90,105 @@ impl fmt::Display for MissingCpuFeature {
 impl error::Error for MissingCpuFeature {}
 
 /// The error type returned when constructing [Resampler](crate::Resampler)
+///
+/// Each variant has a stable numeric code, see [code](ResamplerConstructionError::code).
+#[non_exhaustive]
 pub enum ResamplerConstructionError {
+    /// Error raised when one of the sample rates is zero. Code 100.
     InvalidSampleRate { input: usize, output: usize },
+    /// Error raised when the maximum relative ratio is less than one. Code 101.
     InvalidRelativeRatio(f64),
+    /// Error raised when the resample ratio is not positive. Code 102.
     InvalidRatio(f64),
+    /// Error raised when a filter can't be designed for the given transition bandwidth. Code 103.
+    InvalidTransitionBandwidth(f64),
+    /// Error raised when no window function reaches the given stopband attenuation. Code 104.
+    InvalidAttenuation(f64),
+    /// Error raised when the cutoff frequency is outside the range supported by the filter design. Code 105.
+    InvalidCutoff(f64),
+    /// Error raised when the filter is too long for the selected filter design. Code 106.
+    InvalidFilterLength(usize),
+    /// Error raised when one of the fractional sample rates is not a positive finite number. Code 107.
+    InvalidFractionalSampleRate { input: f64, output: f64 },
+    /// Error raised when a prototype filter is empty, has no gain at DC,
+    /// or is used with an oversampling factor of zero. Code 108.
+    InvalidPrototype(usize),
+    /// Error raised when the filter delay alone exceeds the latency budget, in input frames. Code 109.
+    LatencyBudgetTooSmall { budget: usize, filter_delay: usize },
+    /// Error raised when a precalculated sinc table is empty, or the length of its sincs
+    /// is not a multiple of 8. Code 110.
+    InvalidSincTable(usize),
+    /// Error raised when a resampler that is specialized for a range of ratios
+    /// is created with a ratio outside that range. Code 111.
+    UnsupportedRatio(f64),
+    /// Error raised when the chunk size is smaller than the minimum needed by the filter. Code 112.
+    ChunkSizeTooSmall { chunk_size: usize, minimum: usize },
+    /// Error raised when the worker thread of a [PendingResampler](crate::PendingResampler)
+    /// stops without delivering a resampler, because the factory panicked. Code 113.
+    BuilderFailed,
+    /// Error raised by the `try_new_with_limit` constructors when the estimated memory
+    /// of the resampler exceeds the limit, in bytes. Code 114.
+    MemoryLimitExceeded { required: usize, limit: usize },
+    /// Error raised when the filter would be empty, for example when a partitioned
+    /// filter is created with zero partitions, or a custom interpolator has no sincs. Code 115.
+    EmptyFilter,
+    /// Error raised when a wrapper that adjusts the resample ratio, like [AdaptiveLatency](crate::AdaptiveLatency),
+    /// is given a synchronous resampler, or a maximum relative ratio that the wrapped resampler
+    /// doesn't allow. Code 116.
+    UnsupportedRelativeRatio(f64),
+    /// Error raised when a [ComplexResampler](crate::ComplexResampler) is given a resampler
+    /// with an odd number of channels, which can't be paired into complex channels. Code 117.
+    OddNumberOfChannels(usize),
+}
+
+impl ResamplerConstructionError {
+    /// Get the stable numeric code of the error.
+    ///
+    /// The codes of construction errors are in the range 100 to 199.
+    /// A code is never reused for a different error, which makes it suitable
+    /// for passing errors through FFI layers and for classifying them in logs.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::InvalidSampleRate { .. } => 100,
+            Self::InvalidRelativeRatio(_) => 101,
+            Self::InvalidRatio(_) => 102,
+            Self::InvalidTransitionBandwidth(_) => 103,
+            Self::InvalidAttenuation(_) => 104,
+            Self::InvalidCutoff(_) => 105,
+            Self::InvalidFilterLength(_) => 106,
+            Self::InvalidFractionalSampleRate { .. } => 107,
+            Self::InvalidPrototype(_) => 108,
+            Self::LatencyBudgetTooSmall { .. } => 109,
+            Self::InvalidSincTable(_) => 110,
+            Self::UnsupportedRatio(_) => 111,
+            Self::ChunkSizeTooSmall { .. } => 112,
+            Self::BuilderFailed => 113,
+            Self::MemoryLimitExceeded { .. } => 114,
+            Self::EmptyFilter => 115,
+            Self::UnsupportedRelativeRatio(_) => 116,
+            Self::OddNumberOfChannels(_) => 117,
+        }
+    }
+
+    /// Get the provided input and output sample rates, as a tuple `(input, output)`,
+    /// for errors caused by invalid sample rates.
+    pub fn sample_rates(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::InvalidSampleRate { input, output } => Some((*input, *output)),
+            _ => None,
+        }
+    }
+
+    /// Get the provided value for errors caused by an invalid ratio or relative ratio.
+    pub fn provided_ratio(&self) -> Option<f64> {
+        match self {
+            Self::InvalidRelativeRatio(provided)
+            | Self::InvalidRatio(provided)
+            | Self::UnsupportedRatio(provided)
+            | Self::UnsupportedRelativeRatio(provided) => Some(*provided),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ResamplerConstructionError {
@@ -97,6 +203,51 @@ impl fmt::Display for ResamplerConstructionError {
             Self::InvalidRelativeRatio(provided) => write!(formatter,
                 "Invalid max_resample_ratio_relative provided: {}. max_resample_ratio_relative must be >= 1", provided
             ),
+            Self::InvalidTransitionBandwidth(provided) => write!(formatter,
+                "Invalid transition_bandwidth provided: {}. transition_bandwidth must be > 0 and < 1, and not require a sinc longer than 65536", provided
+            ),
+            Self::InvalidAttenuation(provided) => write!(formatter,
+                "Invalid attenuation provided: {}. No window function reaches this attenuation", provided
+            ),
+            Self::InvalidCutoff(provided) => write!(formatter,
+                "Invalid f_cutoff provided: {}. f_cutoff must be > 0.5 and < 1 for equiripple filters", provided
+            ),
+            Self::InvalidFilterLength(provided) => write!(formatter,
+                "Invalid filter length: {}. The product of sinc_len and oversampling_factor must not exceed 4096 for equiripple filters", provided
+            ),
+            Self::InvalidFractionalSampleRate{input, output} => write!(formatter,
+                "Input and output sample rates must both be finite and > 0. Provided input: {}, provided output: {}", input, output
+            ),
+            Self::InvalidPrototype(provided) => write!(formatter,
+                "Invalid prototype filter of {} taps. The prototype must not be empty, must have a finite non-zero gain at DC, and the oversampling_factor must be > 0", provided
+            ),
+            Self::LatencyBudgetTooSmall{budget, filter_delay} => write!(formatter,
+                "Latency budget of {} input frames is too small, the filter alone has a delay of {} input frames", budget, filter_delay
+            ),
+            Self::InvalidSincTable(provided) => write!(formatter,
+                "Invalid sinc table with sincs of length {}. The table must not be empty, and the length must be a multiple of 8", provided
+            ),
+            Self::UnsupportedRatio(provided) => write!(formatter,
+                "Resample ratio {} is not supported by this resampler", provided
+            ),
+            Self::ChunkSizeTooSmall{chunk_size, minimum} => write!(formatter,
+                "Chunk size of {} frames is too small, the filter needs chunks of at least {} frames", chunk_size, minimum
+            ),
+            Self::BuilderFailed => write!(formatter,
+                "The worker thread stopped without creating the resampler"
+            ),
+            Self::MemoryLimitExceeded{required, limit} => write!(formatter,
+                "The resampler would need about {} bytes of memory, which exceeds the limit of {} bytes", required, limit
+            ),
+            Self::EmptyFilter => write!(formatter,
+                "The filter is empty, it must have at least one partition and at least one tap"
+            ),
+            Self::UnsupportedRelativeRatio(provided) => write!(formatter,
+                "Relative ratio {} is not supported by the wrapped resampler. The resampler must be asynchronous, with a max_resample_ratio_relative of at least this value", provided
+            ),
+            Self::OddNumberOfChannels(provided) => write!(formatter,
+                "The wrapped resampler has {} channels, it must have two channels for each complex channel", provided
+            ),
         }
     }
 }
@@ -110,38 +261,168 @@ impl fmt::Debug for ResamplerConstructionError {
 impl error::Error for ResamplerConstructionError {}
 
 /// The error type used by `rubato`.
+///
+/// Each variant has a stable numeric code, see [code](ResampleError::code).
+#[non_exhaustive]
 pub enum ResampleError {
     /// Error raised when [Resampler::set_resample_ratio](crate::Resampler::set_resample_ratio)
     /// is called with a ratio outside the maximum range specified when
-    /// the resampler was constructed.
+    /// the resampler was constructed. Code 200.
     RatioOutOfBounds {
         provided: f64,
         original: f64,
         max_relative_ratio: f64,
     },
     /// Error raised when calling [Resampler::set_resample_ratio](crate::Resampler::set_resample_ratio)
-    /// on a synchronous resampler.
+    /// on a synchronous resampler. Code 201.
     SyncNotAdjustable,
-    /// Error raised when the number of channels of the input buffer doesn't match expected.
+    /// Error raised when the number of channels of the input buffer doesn't match expected. Code 202.
     WrongNumberOfInputChannels { expected: usize, actual: usize },
-    /// Error raised when the number of channels of the output buffer doesn't match expected.
+    /// Error raised when the number of channels of the output buffer doesn't match expected. Code 203.
     WrongNumberOfOutputChannels { expected: usize, actual: usize },
-    /// Error raised when the number of channels of the mask doesn't match expected.
+    /// Error raised when the number of channels of the mask doesn't match expected. Code 204.
     WrongNumberOfMaskChannels { expected: usize, actual: usize },
     /// Error raised when the number of frames in an input channel is less
-    /// than the minimum expected number of frames.
+    /// than the minimum expected number of frames. Code 205.
     InsufficientInputBufferSize {
         channel: usize,
         expected: usize,
         actual: usize,
     },
     /// Error raised when the number of frames in an output channel is less
-    /// than the minimum expected number of frames.
+    /// than the minimum expected number of frames. Code 206.
     InsufficientOutputBufferSize {
         channel: usize,
         expected: usize,
         actual: usize,
     },
+    /// Error raised when a ratio envelope does not have one value per output frame
+    /// of the next chunk. Code 207.
+    WrongEnvelopeLength { expected: usize, actual: usize },
+    /// Error raised when [Resampler::set_chunk_size](crate::Resampler::set_chunk_size)
+    /// is called with a chunk size of zero. Code 208.
+    InvalidChunkSize { provided: usize },
+    /// Error raised when calling [Resampler::set_chunk_size](crate::Resampler::set_chunk_size)
+    /// on a resampler where the chunk size can't be changed. Code 209.
+    ChunkSizeNotAdjustable,
+    /// Error raised when calling [Resampler::set_history](crate::Resampler::set_history)
+    /// on a resampler where the history can't be loaded directly. Code 210.
+    HistoryNotSupported,
+    /// Error raised when the internal buffer of a [FixedChunkResampler](crate::FixedChunkResampler)
+    /// runs empty or full, because the chunk sizes don't match the resample ratio. Code 211.
+    ChunkSizeMismatch { buffered: usize },
+    /// Error raised when a job started with [process_all](crate::process_all)
+    /// is cancelled with a [CancellationToken](crate::CancellationToken). Code 212.
+    Cancelled { processed: usize },
+    /// Error raised when
+    /// [Resampler::set_max_resample_ratio_relative](crate::Resampler::set_max_resample_ratio_relative)
+    /// is called with a maximum relative ratio below 1.0. Code 213.
+    InvalidRelativeRatio { provided: f64 },
+    /// Error raised when the scratch buffer given to
+    /// [Resampler::process_into_buffer_with_scratch](crate::Resampler::process_into_buffer_with_scratch)
+    /// is shorter than [Resampler::scratch_len](crate::Resampler::scratch_len). Code 214.
+    InsufficientScratchSize { expected: usize, actual: usize },
+    /// Error raised when the input buffer of a [FixedChunkResampler](crate::FixedChunkResampler)
+    /// has no room for another input chunk,
+    /// because the wrapped resampler needs more input than it had when the wrapper was created.
+    /// Code 215.
+    InputBufferFull { buffered: usize, capacity: usize },
+    /// Error raised when an FFT of a synchronous resampler fails,
+    /// because its work buffers don't have the lengths of the FFT plans.
+    /// This is an internal error, it can't be caused by the processed data. Code 216.
+    FftFailed,
+    /// Error raised when the position of an output frame of a sinc resampler
+    /// falls outside of the buffered input frames.
+    /// This is an internal error, it can't be caused by the processed data. Code 217.
+    InterpolationOutOfRange,
+}
+
+impl ResampleError {
+    /// Get the stable numeric code of the error.
+    ///
+    /// The codes of processing errors are in the range 200 to 299.
+    /// A code is never reused for a different error, which makes it suitable
+    /// for passing errors through FFI layers and for classifying them in logs.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::RatioOutOfBounds { .. } => 200,
+            Self::SyncNotAdjustable => 201,
+            Self::WrongNumberOfInputChannels { .. } => 202,
+            Self::WrongNumberOfOutputChannels { .. } => 203,
+            Self::WrongNumberOfMaskChannels { .. } => 204,
+            Self::InsufficientInputBufferSize { .. } => 205,
+            Self::InsufficientOutputBufferSize { .. } => 206,
+            Self::WrongEnvelopeLength { .. } => 207,
+            Self::InvalidChunkSize { .. } => 208,
+            Self::ChunkSizeNotAdjustable => 209,
+            Self::HistoryNotSupported => 210,
+            Self::ChunkSizeMismatch { .. } => 211,
+            Self::Cancelled { .. } => 212,
+            Self::InvalidRelativeRatio { .. } => 213,
+            Self::InsufficientScratchSize { .. } => 214,
+            Self::InputBufferFull { .. } => 215,
+            Self::FftFailed => 216,
+            Self::InterpolationOutOfRange => 217,
+        }
+    }
+
+    /// Get the channel that caused the error, for errors caused by a too short buffer.
+    pub fn channel(&self) -> Option<usize> {
+        match self {
+            Self::InsufficientInputBufferSize { channel, .. }
+            | Self::InsufficientOutputBufferSize { channel, .. } => Some(*channel),
+            _ => None,
+        }
+    }
+
+    /// Get the expected number of channels or frames, for errors caused by a wrong buffer size.
+    pub fn expected(&self) -> Option<usize> {
+        match self {
+            Self::WrongNumberOfInputChannels { expected, .. }
+            | Self::WrongNumberOfOutputChannels { expected, .. }
+            | Self::WrongNumberOfMaskChannels { expected, .. }
+            | Self::InsufficientInputBufferSize { expected, .. }
+            | Self::InsufficientOutputBufferSize { expected, .. }
+            | Self::WrongEnvelopeLength { expected, .. }
+            | Self::InsufficientScratchSize { expected, .. } => Some(*expected),
+            _ => None,
+        }
+    }
+
+    /// Get the actual number of channels or frames, for errors caused by a wrong buffer size.
+    pub fn actual(&self) -> Option<usize> {
+        match self {
+            Self::WrongNumberOfInputChannels { actual, .. }
+            | Self::WrongNumberOfOutputChannels { actual, .. }
+            | Self::WrongNumberOfMaskChannels { actual, .. }
+            | Self::InsufficientInputBufferSize { actual, .. }
+            | Self::InsufficientOutputBufferSize { actual, .. }
+            | Self::WrongEnvelopeLength { actual, .. }
+            | Self::InsufficientScratchSize { actual, .. } => Some(*actual),
+            _ => None,
+        }
+    }
+
+    /// Get the provided ratio, for errors caused by a ratio out of bounds.
+    pub fn provided_ratio(&self) -> Option<f64> {
+        match self {
+            Self::RatioOutOfBounds { provided, .. } => Some(*provided),
+            _ => None,
+        }
+    }
+
+    /// Get the allowed range of absolute ratios as a tuple `(min, max)`,
+    /// for errors caused by a ratio out of bounds.
+    pub fn allowed_ratio_range(&self) -> Option<(f64, f64)> {
+        match self {
+            Self::RatioOutOfBounds {
+                original,
+                max_relative_ratio,
+                ..
+            } => Some((original / max_relative_ratio, original * max_relative_ratio)),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ResampleError {
@@ -201,6 +482,62 @@ impl fmt::Display for ResampleError {
                     actual, channel, expected
                 )
             }
+            Self::WrongEnvelopeLength { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong length {} of ratio envelope, expected {}",
+                    actual, expected
+                )
+            }
+            Self::InvalidChunkSize { provided } => {
+                write!(f, "Invalid chunk size {}, must be > 0", provided)
+            }
+            Self::ChunkSizeNotAdjustable => {
+                write!(f, "Not possible to change the chunk size of this resampler")
+            }
+            Self::HistoryNotSupported => {
+                write!(f, "Not possible to load the history of this resampler")
+            }
+            Self::ChunkSizeMismatch { buffered } => {
+                write!(
+                    f,
+                    "The chunk sizes don't match the resample ratio, {} frames are buffered",
+                    buffered
+                )
+            }
+            Self::Cancelled { processed } => {
+                write!(f, "Cancelled after processing {} input frames", processed)
+            }
+            Self::InvalidRelativeRatio { provided } => {
+                write!(
+                    f,
+                    "Invalid maximum relative ratio {}, must be >= 1.0",
+                    provided
+                )
+            }
+            Self::InsufficientScratchSize { expected, actual } => {
+                write!(
+                    f,
+                    "Insufficient scratch buffer size {}, expected {}",
+                    actual, expected
+                )
+            }
+            Self::InputBufferFull { buffered, capacity } => {
+                write!(
+                    f,
+                    "The input buffer is full, {} of {} frames are buffered",
+                    buffered, capacity
+                )
+            }
+            Self::FftFailed => {
+                write!(f, "The FFT failed, the work buffers have the wrong length")
+            }
+            Self::InterpolationOutOfRange => {
+                write!(
+                    f,
+                    "An output frame falls outside of the buffered input frames"
+                )
+            }
         }
     }
 }
@@ -215,3 +552,106 @@ impl error::Error for ResampleError {}
 
 /// A result alias for the error type used by `rubato`.
 pub type ResampleResult<T> = ::std::result::Result<T, ResampleError>;
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{ResampleError, ResamplerConstructionError};
+
+    #[test]
+    fn error_codes() {
+        let err = ResamplerConstructionError::InvalidSampleRate {
+            input: 0,
+            output: 44100,
+        };
+        assert_eq!(err.code(), 100);
+        assert_eq!(err.sample_rates(), Some((0, 44100)));
+        assert_eq!(err.provided_ratio(), None);
+        assert_eq!(
+            ResamplerConstructionError::UnsupportedRatio(0.5).code(),
+            111
+        );
+        assert_eq!(ResamplerConstructionError::InvalidRatio(-1.0).code(), 102);
+        assert_eq!(ResamplerConstructionError::InvalidPrototype(0).code(), 108);
+        assert_eq!(
+            ResamplerConstructionError::LatencyBudgetTooSmall {
+                budget: 0,
+                filter_delay: 0
+            }
+            .code(),
+            109
+        );
+        assert_eq!(ResamplerConstructionError::InvalidSincTable(0).code(), 110);
+        assert_eq!(
+            ResamplerConstructionError::ChunkSizeTooSmall {
+                chunk_size: 0,
+                minimum: 0
+            }
+            .code(),
+            112
+        );
+        assert_eq!(ResamplerConstructionError::BuilderFailed.code(), 113);
+        assert_eq!(
+            ResamplerConstructionError::MemoryLimitExceeded {
+                required: 2,
+                limit: 1
+            }
+            .code(),
+            114
+        );
+        assert_eq!(ResamplerConstructionError::EmptyFilter.code(), 115);
+        let err = ResamplerConstructionError::UnsupportedRelativeRatio(1.5);
+        assert_eq!(err.code(), 116);
+        assert_eq!(err.provided_ratio(), Some(1.5));
+        assert_eq!(
+            ResamplerConstructionError::OddNumberOfChannels(3).code(),
+            117
+        );
+        let err = ResamplerConstructionError::InvalidFractionalSampleRate {
+            input: 0.0,
+            output: 47952.0,
+        };
+        assert_eq!(err.code(), 107);
+        assert_eq!(err.sample_rates(), None);
+
+        let err = ResampleError::InsufficientOutputBufferSize {
+            channel: 1,
+            expected: 1024,
+            actual: 512,
+        };
+        assert_eq!(err.code(), 206);
+        assert_eq!(err.channel(), Some(1));
+        assert_eq!(err.expected(), Some(1024));
+        assert_eq!(err.actual(), Some(512));
+        assert_eq!(err.allowed_ratio_range(), None);
+
+        let err = ResampleError::RatioOutOfBounds {
+            provided: 5.0,
+            original: 2.0,
+            max_relative_ratio: 2.0,
+        };
+        assert_eq!(err.code(), 200);
+        assert_eq!(err.provided_ratio(), Some(5.0));
+        assert_eq!(err.allowed_ratio_range(), Some((1.0, 4.0)));
+        assert_eq!(err.channel(), None);
+        assert_eq!(ResampleError::Cancelled { processed: 0 }.code(), 212);
+        assert_eq!(
+            ResampleError::InvalidRelativeRatio { provided: 0.5 }.code(),
+            213
+        );
+        let err = ResampleError::InsufficientScratchSize {
+            expected: 4096,
+            actual: 0,
+        };
+        assert_eq!(err.code(), 214);
+        assert_eq!(err.expected(), Some(4096));
+        assert_eq!(err.actual(), Some(0));
+        let err = ResampleError::InputBufferFull {
+            buffered: 480,
+            capacity: 743,
+        };
+        assert_eq!(err.code(), 215);
+        assert_eq!(err.expected(), None);
+        assert_eq!(ResampleError::FftFailed.code(), 216);
+        assert_eq!(ResampleError::InterpolationOutOfRange.code(), 217);
+    }
+}
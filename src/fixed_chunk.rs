@@ -0,0 +1,448 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{process_wrapped, Resampler, ResamplerConfig, Sample};
+
+/// A wrapper for a [Resampler] where both the input and the output chunk sizes are fixed
+/// to values chosen by the caller.
+///
+/// This suits pipelines where the producer delivers blocks of one fixed size,
+/// and the consumer requests blocks of another fixed size,
+/// for example 480 frames every 10 ms at 48 kHz that should become 441 frames at 44.1 kHz.
+/// The chunk sizes of the wrapped resampler are independent of the ones of the wrapper,
+/// and the two are bridged by internal buffers.
+///
+/// The chunk sizes must match the resample ratio, meaning that
+/// `chunk_size_out / chunk_size_in` must be equal to the ratio of the wrapped resampler.
+/// Small adjustments of the ratio, for example to follow a drifting clock,
+/// are absorbed by the buffers.
+/// The number of buffered output frames can be read with
+/// [buffered_output_frames](FixedChunkResampler::buffered_output_frames),
+/// and used as a measure of the accumulated drift.
+///
+/// To always have a complete output chunk available, the output buffer starts with silence,
+/// as long as the output of one input chunk and one output chunk of the wrapped resampler.
+/// Using smaller chunk sizes for the wrapped resampler therefore gives a lower latency.
+/// This is included in the [output_delay](Resampler::output_delay) of the wrapper.
+/// If the buffer runs empty or full anyway, because the chunk sizes don't match the ratio,
+/// processing returns a [ResampleError::ChunkSizeMismatch] error.
+/// The wrapper must then be reset before it is used again.
+/// The input buffer has room for the input frames needed by the wrapped resampler
+/// when the wrapper is created, plus one input chunk.
+/// If the wrapped resampler later needs more input, for example after changing
+/// its chunk size via [inner_mut](FixedChunkResampler::inner_mut),
+/// processing returns a [ResampleError::InputBufferFull] error.
+/// The buffers are allocated when the wrapper is created, and processing does not allocate.
+/// ```
+/// use rubato::{FastFixedIn, FixedChunkResampler, PolynomialDegree, Resampler};
+/// let resampler =
+///     FastFixedIn::<f64>::new(44100.0 / 48000.0, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+/// let mut resampler = FixedChunkResampler::new(resampler, 480, 441);
+/// let waves_in = vec![vec![0.0f64; 480]; 2];
+/// let waves_out = resampler.process(&waves_in, None).unwrap();
+/// assert_eq!(waves_out[0].len(), 441);
+/// ```
+pub struct FixedChunkResampler<R, T> {
+    resampler: R,
+    chunk_size_in: usize,
+    chunk_size_out: usize,
+    prefill: usize,
+    input_buffer: Vec<Vec<T>>,
+    input_fill: usize,
+    chunk_buffer: Vec<Vec<T>>,
+    output_buffer: Vec<Vec<T>>,
+    output_fill: usize,
+}
+
+impl<R, T> FixedChunkResampler<R, T>
+where
+    R: Resampler<T>,
+    T: Sample,
+{
+    /// Create a new FixedChunkResampler wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    /// - `chunk_size_in`: Size of input data in frames.
+    /// - `chunk_size_out`: Size of output data in frames.
+    pub fn new(resampler: R, chunk_size_in: usize, chunk_size_out: usize) -> Self {
+        let nbr_channels = resampler.nbr_channels();
+        // Cover the input frames held back by the wrapped resampler,
+        // as well as the variation of its output
+        let held_back = (resampler.input_frames_max() * chunk_size_out)
+            .checked_div(chunk_size_in)
+            .unwrap_or(0);
+        let prefill = held_back + resampler.output_frames_max();
+        let input_capacity = resampler.input_frames_max() + chunk_size_in;
+        // Leave plenty of room for drift before the buffer is full
+        let output_capacity = 2 * (prefill + resampler.output_frames_max() + chunk_size_out);
+        let input_buffer = vec![vec![T::zero(); input_capacity]; nbr_channels];
+        let chunk_buffer = vec![vec![T::zero(); resampler.output_frames_max()]; nbr_channels];
+        let output_buffer = vec![vec![T::zero(); output_capacity]; nbr_channels];
+        FixedChunkResampler {
+            resampler,
+            chunk_size_in,
+            chunk_size_out,
+            prefill,
+            input_buffer,
+            input_fill: 0,
+            chunk_buffer,
+            output_buffer,
+            output_fill: prefill,
+        }
+    }
+
+    /// Get the number of input frames that are buffered, waiting for a complete chunk
+    /// of the wrapped resampler.
+    pub fn buffered_input_frames(&self) -> usize {
+        self.input_fill
+    }
+
+    /// Get the number of output frames that are buffered, waiting to be returned.
+    pub fn buffered_output_frames(&self) -> usize {
+        self.output_fill
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        mut scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let nbr_channels = self.resampler.nbr_channels();
+        if wave_in.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() < self.chunk_size_in {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: self.chunk_size_in,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if wave.as_mut().len() < self.chunk_size_out {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: self.chunk_size_out,
+                    actual: wave.as_mut().len(),
+                });
+            }
+        }
+
+        let input_capacity = self.input_buffer.first().map(|b| b.len()).unwrap_or(0);
+        if self.input_fill + self.chunk_size_in > input_capacity {
+            return Err(ResampleError::InputBufferFull {
+                buffered: self.input_fill,
+                capacity: input_capacity,
+            });
+        }
+        for (wave, buffer) in wave_in.iter().zip(self.input_buffer.iter_mut()) {
+            buffer[self.input_fill..self.input_fill + self.chunk_size_in]
+                .copy_from_slice(&wave.as_ref()[..self.chunk_size_in]);
+        }
+        self.input_fill += self.chunk_size_in;
+
+        // Process all complete chunks of the wrapped resampler
+        let mut first = true;
+        while self.input_fill >= self.resampler.input_frames_next() {
+            let result = process_wrapped(
+                &mut self.resampler,
+                &self.input_buffer,
+                &mut self.chunk_buffer,
+                active_channels_mask,
+                scratch.as_deref_mut(),
+            );
+            let (frames_used, frames_produced) = match result {
+                Ok(frames) => frames,
+                Err(err) => {
+                    // Drop the new input if nothing has been processed, so that the call can be retried
+                    if first {
+                        self.input_fill -= self.chunk_size_in;
+                    }
+                    return Err(err);
+                }
+            };
+            first = false;
+            for buffer in self.input_buffer.iter_mut() {
+                buffer.copy_within(frames_used..self.input_fill, 0);
+            }
+            self.input_fill -= frames_used;
+            let output_capacity = self.output_buffer.first().map(|b| b.len()).unwrap_or(0);
+            if self.output_fill + frames_produced > output_capacity {
+                return Err(ResampleError::ChunkSizeMismatch {
+                    buffered: self.output_fill,
+                });
+            }
+            for (buffer, chunk) in self.output_buffer.iter_mut().zip(self.chunk_buffer.iter()) {
+                buffer[self.output_fill..self.output_fill + frames_produced]
+                    .copy_from_slice(&chunk[..frames_produced]);
+            }
+            self.output_fill += frames_produced;
+        }
+
+        if self.output_fill < self.chunk_size_out {
+            return Err(ResampleError::ChunkSizeMismatch {
+                buffered: self.output_fill,
+            });
+        }
+        for (wave, buffer) in wave_out.iter_mut().zip(self.output_buffer.iter_mut()) {
+            wave.as_mut()[..self.chunk_size_out].copy_from_slice(&buffer[..self.chunk_size_out]);
+            buffer.copy_within(self.chunk_size_out..self.output_fill, 0);
+        }
+        self.output_fill -= self.chunk_size_out;
+        trace!(
+            "Processed fixed chunk, {} output frames buffered",
+            self.output_fill
+        );
+        Ok((self.chunk_size_in, self.chunk_size_out))
+    }
+}
+
+impl<R, T> Resampler<T> for FixedChunkResampler<R, T>
+where
+    R: Resampler<T>,
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay() + self.prefill
+    }
+
+    fn input_delay(&self) -> usize {
+        let prefill_in = (self.prefill * self.chunk_size_in)
+            .checked_div(self.chunk_size_out)
+            .unwrap_or(0);
+        self.resampler.input_delay() + prefill_in
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    /// Change the maximum relative ratio of the wrapped resampler,
+    /// see [Resampler::set_max_resample_ratio_relative].
+    /// The internal buffers are extended when the wrapped resampler needs more room,
+    /// and the buffered frames are kept.
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)?;
+        let input_capacity = self.resampler.input_frames_max() + self.chunk_size_in;
+        let chunk_capacity = self.resampler.output_frames_max();
+        let output_capacity = 2 * (self.prefill + chunk_capacity + self.chunk_size_out);
+        for buf in self.input_buffer.iter_mut() {
+            if buf.len() < input_capacity {
+                buf.resize(input_capacity, T::zero());
+            }
+        }
+        for buf in self.chunk_buffer.iter_mut() {
+            if buf.len() < chunk_capacity {
+                buf.resize(chunk_capacity, T::zero());
+            }
+        }
+        for buf in self.output_buffer.iter_mut() {
+            if buf.len() < output_capacity {
+                buf.resize(output_capacity, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    fn set_chunk_size(&mut self, _chunk_size: usize) -> ResampleResult<()> {
+        Err(ResampleError::ChunkSizeNotAdjustable)
+    }
+
+    fn memory_usage(&self) -> usize {
+        let buffers = self
+            .input_buffer
+            .iter()
+            .chain(self.chunk_buffer.iter())
+            .chain(self.output_buffer.iter())
+            .map(|buffer| buffer.capacity())
+            .sum::<usize>();
+        self.resampler.memory_usage() + buffers * std::mem::size_of::<T>()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "FixedChunkResampler")
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.input_fill = 0;
+        self.output_buffer.iter_mut().for_each(|buffer| {
+            buffer[..self.prefill]
+                .iter_mut()
+                .for_each(|value| *value = T::zero())
+        });
+        self.output_fill = self.prefill;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{
+        FastFixedIn, FastFixedOut, FixedChunkResampler, PolynomialDegree, ResampleError, Resampler,
+    };
+
+    /// Process the input in chunks of the sizes requested by the resampler, and return the output.
+    fn process_chunks<R: Resampler<f64>>(mut resampler: R, input: &[f64]) -> Vec<f64> {
+        let mut output = Vec::new();
+        let mut position = 0;
+        while position + resampler.input_frames_next() <= input.len() {
+            let frames = resampler.input_frames_next();
+            let wave_out = resampler
+                .process(&[&input[position..position + frames]], None)
+                .unwrap();
+            output.extend_from_slice(&wave_out[0]);
+            position += frames;
+        }
+        output
+    }
+
+    fn check_fixed_sizes<R: Resampler<f64>>(make_resampler: impl Fn() -> R) {
+        let input: Vec<f64> = (0..48000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let expected = process_chunks(make_resampler(), &input);
+        let mut resampler = FixedChunkResampler::new(make_resampler(), 480, 441);
+        let prefill = resampler.buffered_output_frames();
+        let mut output = Vec::new();
+        for chunk in input.chunks_exact(480) {
+            let wave_out = resampler.process(&[chunk], None).unwrap();
+            assert_eq!(wave_out[0].len(), 441);
+            output.extend_from_slice(&wave_out[0]);
+        }
+        assert_eq!(
+            resampler.output_delay(),
+            make_resampler().output_delay() + prefill
+        );
+        // The output is the output of the wrapped resampler, after the initial silence
+        assert!(output[..prefill].iter().all(|value| *value == 0.0));
+        let compared = (output.len() - prefill).min(expected.len() / 2);
+        assert_eq!(output[prefill..prefill + compared], expected[..compared]);
+    }
+
+    #[test]
+    fn fixed_sizes() {
+        let ratio = 441.0 / 480.0;
+        check_fixed_sizes(|| {
+            FastFixedIn::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, 256, 1).unwrap()
+        });
+        check_fixed_sizes(|| {
+            FastFixedOut::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, 256, 1).unwrap()
+        });
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let resampler = FastFixedIn::<f64>::new(0.5, 1.0, PolynomialDegree::Cubic, 64, 1).unwrap();
+        // The sizes should have been 100 and 50
+        let mut resampler = FixedChunkResampler::new(resampler, 100, 60);
+        let prefill = resampler.buffered_output_frames();
+        let waves = vec![vec![0.0f64; 100]];
+        let error = (0..100)
+            .map(|_| resampler.process(&waves, None))
+            .find_map(|result| result.err())
+            .unwrap();
+        assert!(matches!(error, ResampleError::ChunkSizeMismatch { .. }));
+        assert_eq!(error.code(), 211);
+        resampler.reset();
+        assert_eq!(resampler.buffered_output_frames(), prefill);
+        assert!(resampler.process(&waves, None).is_ok());
+    }
+
+    #[test]
+    fn input_buffer_full() {
+        let ratio = 441.0 / 480.0;
+        let resampler =
+            FastFixedIn::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let capacity = resampler.input_frames_max() + 480;
+        let mut resampler = FixedChunkResampler::new(resampler, 480, 441);
+        // Make the wrapped resampler need more input than the wrapper can buffer
+        resampler.inner_mut().set_chunk_size(2048).unwrap();
+        let waves = vec![vec![0.0f64; 480]];
+        resampler.process(&waves, None).unwrap();
+        assert_eq!(resampler.buffered_input_frames(), 480);
+        let error = resampler.process(&waves, None).unwrap_err();
+        assert!(matches!(
+            error,
+            ResampleError::InputBufferFull { buffered: 480, capacity: c } if c == capacity
+        ));
+        assert_eq!(error.code(), 215);
+        assert_eq!(resampler.buffered_input_frames(), 480);
+    }
+}
@@ -0,0 +1,923 @@
+use crate::sinc_interpolator::ReferenceInterpolator;
+use crate::{
+    FastFixedIn, FftFixedIn, PolynomialDegree, Resampler, SincFixedIn, SincFixedOut,
+    SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+/// Maximum allowed deviation from the golden output values.
+///
+/// The golden values were generated with the reference implementation on x86_64.
+/// Other implementations and platforms round differently, and the sinc and FFT
+/// calculations use library functions that may differ in the last bits.
+pub const GOLDEN_TOLERANCE: f64 = 1.0e-9;
+
+/// Number of input frames per chunk for the golden test vectors.
+const CHUNK_SIZE: usize = 128;
+/// Number of chunks processed before the chunk that is compared with the golden values.
+const WARMUP_CHUNKS: usize = 2;
+
+/// The result of running one golden test vector.
+#[derive(Debug, Clone)]
+pub struct GoldenResult {
+    /// Name of the test vector.
+    pub name: &'static str,
+    /// The largest absolute deviation from the golden output values.
+    /// This is infinite if the number of output frames differs.
+    pub max_error: f64,
+}
+
+impl GoldenResult {
+    /// Returns true if the deviation is within [GOLDEN_TOLERANCE].
+    pub fn passed(&self) -> bool {
+        self.max_error <= GOLDEN_TOLERANCE
+    }
+}
+
+/// Generate the input signal of the golden test vectors.
+/// This is white noise from a linear congruential generator,
+/// which gives exactly the same values on all platforms.
+pub fn golden_input(frames: usize) -> Vec<f64> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..frames)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+        })
+        .collect()
+}
+
+fn sinc_parameters(interpolation: SincInterpolationType) -> SincInterpolationParameters {
+    SincInterpolationParameters {
+        sinc_len: 64,
+        f_cutoff: 0.95,
+        interpolation,
+        oversampling_factor: 32,
+        window: WindowFunction::BlackmanHarris2,
+        ..Default::default()
+    }
+}
+
+/// Process the golden input with a resampler, and return the output of the last chunk.
+fn process_golden<R: Resampler<f64>>(mut resampler: R) -> Vec<f64> {
+    let input = golden_input((WARMUP_CHUNKS + 1) * 4 * CHUNK_SIZE);
+    let mut position = 0;
+    let mut output = Vec::new();
+    for _ in 0..=WARMUP_CHUNKS {
+        let frames = resampler.input_frames_next();
+        output = resampler
+            .process(&[&input[position..position + frames]], None)
+            .unwrap()
+            .remove(0);
+        position += frames;
+    }
+    output
+}
+
+/// Make the resamplers of the golden test vectors and process the golden input.
+/// When `reference` is true, the sinc resamplers use the [ReferenceInterpolator],
+/// otherwise they use the interpolator that is selected for this CPU.
+fn golden_output(name: &str, reference: bool) -> Vec<f64> {
+    match name {
+        "sinc_fixed_in_cubic" => {
+            let params = sinc_parameters(SincInterpolationType::Cubic);
+            if reference {
+                let interpolator = ReferenceInterpolator::<f64>::new(
+                    params.sinc_len,
+                    params.oversampling_factor,
+                    params.f_cutoff,
+                    params.window,
+                );
+                process_golden(
+                    SincFixedIn::<f64>::new_with_interpolator(
+                        1.5,
+                        1.0,
+                        params.interpolation,
+                        Box::new(interpolator),
+                        CHUNK_SIZE,
+                        1,
+                    )
+                    .unwrap(),
+                )
+            } else {
+                process_golden(SincFixedIn::<f64>::new(1.5, 1.0, params, CHUNK_SIZE, 1).unwrap())
+            }
+        }
+        "sinc_fixed_out_linear" => {
+            let params = sinc_parameters(SincInterpolationType::Linear);
+            if reference {
+                // The cutoff is scaled by the ratio when downsampling, as in SincFixedOut::new
+                let interpolator = ReferenceInterpolator::<f64>::new(
+                    params.sinc_len,
+                    params.oversampling_factor,
+                    params.f_cutoff * 0.8,
+                    params.window,
+                );
+                process_golden(
+                    SincFixedOut::<f64>::new_with_interpolator(
+                        0.8,
+                        1.0,
+                        params.interpolation,
+                        Box::new(interpolator),
+                        CHUNK_SIZE,
+                        1,
+                    )
+                    .unwrap(),
+                )
+            } else {
+                process_golden(SincFixedOut::<f64>::new(0.8, 1.0, params, CHUNK_SIZE, 1).unwrap())
+            }
+        }
+        "fast_fixed_in_cubic" => process_golden(
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, CHUNK_SIZE, 1).unwrap(),
+        ),
+        "fft_fixed_in" => {
+            process_golden(FftFixedIn::<f64>::new(32000, 48000, CHUNK_SIZE, 2, 1).unwrap())
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Compare an output with the golden values.
+fn compare(name: &'static str, output: &[f64], expected: &[f64]) -> GoldenResult {
+    let max_error = if output.len() == expected.len() {
+        output
+            .iter()
+            .zip(expected.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max)
+    } else {
+        f64::INFINITY
+    };
+    GoldenResult { name, max_error }
+}
+
+/// Run the built-in golden test vectors, and compare the results with the stored golden output.
+///
+/// This runs a white noise signal from [golden_input] through a few typical resampler configurations,
+/// and compares the output with values generated by the reference implementation.
+/// The sinc resamplers are run twice, once with the [ReferenceInterpolator]
+/// and once with the interpolator that is selected for the current CPU.
+/// This can be used to verify an installation, or a port to a new architecture.
+/// ```
+/// let results = rubato::run_golden_vectors();
+/// for result in results.iter() {
+///     assert!(result.passed(), "{} failed, error {}", result.name, result.max_error);
+/// }
+/// ```
+pub fn run_golden_vectors() -> Vec<GoldenResult> {
+    let mut results = Vec::new();
+    for (name, reference_name, expected) in GOLDEN_VECTORS.iter() {
+        let output = golden_output(name, false);
+        results.push(compare(name, &output, expected));
+        if let Some(reference_name) = reference_name {
+            let output = golden_output(name, true);
+            results.push(compare(reference_name, &output, expected));
+        }
+    }
+    results
+}
+
+/// Golden test vectors, as (name, name of the reference variant if any, expected output).
+type GoldenVector = (&'static str, Option<&'static str>, &'static [f64]);
+
+const GOLDEN_VECTORS: [GoldenVector; 4] = [
+    (
+        "sinc_fixed_in_cubic",
+        Some("sinc_fixed_in_cubic_reference"),
+        &SINC_FIXED_IN_CUBIC,
+    ),
+    (
+        "sinc_fixed_out_linear",
+        Some("sinc_fixed_out_linear_reference"),
+        &SINC_FIXED_OUT_LINEAR,
+    ),
+    ("fast_fixed_in_cubic", None, &FAST_FIXED_IN_CUBIC),
+    ("fft_fixed_in", None, &FFT_FIXED_IN),
+];
+
+const SINC_FIXED_IN_CUBIC: [f64; 192] = [
+    5.425418108349905e-2,
+    -7.884054553053471e-1,
+    -2.290692136486414e-1,
+    6.14255616633055e-1,
+    4.6832513366535894e-1,
+    -4.7362223587420865e-1,
+    -1.1271243466187886e0,
+    -5.229026575790897e-1,
+    3.114122006796164e-1,
+    -7.062492633905737e-2,
+    -3.5506313362156294e-1,
+    3.9390989416348193e-1,
+    4.6605662686540666e-1,
+    -1.4041374147131114e-1,
+    1.428244832038713e-1,
+    6.762070115304112e-1,
+    7.727250990092779e-1,
+    8.670258505405273e-1,
+    2.3054029588874309e-1,
+    -7.953955881965877e-1,
+    -2.2955170330028843e-1,
+    9.650763930154872e-1,
+    4.976870960547274e-1,
+    -4.665767788923186e-1,
+    -1.67888211817657e-1,
+    5.171831916922395e-1,
+    6.719660575300985e-1,
+    5.843855539687647e-1,
+    4.676781173828469e-1,
+    3.6914507640184324e-1,
+    -1.7148364071942833e-2,
+    -9.377704624643254e-1,
+    -1.212979752040756e0,
+    -3.00754928555906e-2,
+    8.536796966066146e-1,
+    1.821954052843335e-1,
+    -4.9274409260171775e-1,
+    -3.6890074401339734e-1,
+    -6.502509167829861e-1,
+    -1.0175679929150345e0,
+    -1.7995228184314302e-1,
+    7.410727236721374e-1,
+    2.721486213889362e-1,
+    -4.8222581687123833e-1,
+    -3.6758111024681017e-1,
+    4.666610877498783e-2,
+    4.681703142865846e-1,
+    8.533416439097711e-1,
+    3.5365060222284705e-1,
+    -7.931512163705525e-1,
+    -9.192768506658284e-1,
+    6.236754353342993e-2,
+    5.561069126738872e-1,
+    2.607471052740331e-1,
+    1.6043912834935634e-1,
+    3.052674207105088e-1,
+    1.626191095420629e-1,
+    1.2534272781529243e-1,
+    5.877268663707511e-1,
+    7.792384513214529e-1,
+    2.919128593143166e-1,
+    6.781636745020689e-2,
+    3.919285805357992e-1,
+    1.407220467715282e-1,
+    -8.53886351641383e-1,
+    -1.0867038067963242e0,
+    2.896591179235385e-2,
+    8.730993642198746e-1,
+    3.1022655412572436e-1,
+    -9.193708679469197e-2,
+    8.840944328275785e-1,
+    1.2719115171990845e0,
+    -7.359297124120412e-2,
+    -8.180670908598845e-1,
+    -8.871899347101687e-2,
+    1.0491498482241345e-1,
+    -2.5458816703145504e-1,
+    1.113890619121026e-1,
+    1.8966563073502968e-1,
+    -5.90913127648675e-1,
+    -5.829020771343411e-1,
+    4.40183408763565e-1,
+    7.979025330894602e-1,
+    5.773512259686522e-2,
+    -6.430965655192369e-1,
+    -4.7033914719225955e-1,
+    2.5205110828474314e-1,
+    7.047723298023542e-1,
+    5.106141437426837e-1,
+    -2.9012339233153045e-1,
+    -1.019638970975312e0,
+    -5.320080302029194e-1,
+    5.675842910994144e-1,
+    3.823059457209797e-1,
+    -4.683781889533075e-1,
+    -1.22355086088493e-1,
+    4.7518376814788443e-1,
+    -3.257266954163529e-2,
+    -4.5023312409954525e-1,
+    -1.523804989239812e-1,
+    -3.0730194778344394e-1,
+    -7.918171702360588e-1,
+    -4.5676852584619637e-1,
+    3.1832454143381933e-1,
+    4.0059529751919387e-1,
+    -1.2841875851702977e-1,
+    -2.9010050979705243e-1,
+    7.079144478021952e-2,
+    -1.0950285537610735e-1,
+    -9.114706638367466e-1,
+    -6.904864613609777e-1,
+    5.807771168358756e-1,
+    7.403082113185393e-1,
+    -2.572888273512145e-1,
+    -4.225673206656323e-1,
+    9.931401058596666e-2,
+    6.344609916941854e-2,
+    -1.0593769956231265e-1,
+    -4.5636542853626595e-2,
+    -1.6355697866567181e-1,
+    -4.798844343650755e-2,
+    4.390075171374317e-1,
+    5.283651264233431e-1,
+    3.116685203158597e-1,
+    9.603341655469431e-2,
+    -5.392176481743955e-1,
+    -9.256632376929496e-1,
+    -1.0586616829050523e-1,
+    6.098040906279266e-1,
+    2.344848354060135e-2,
+    -4.112727275271467e-1,
+    2.49597011504747e-1,
+    6.537033524423266e-1,
+    1.892761139774544e-1,
+    -3.508092320402177e-1,
+    -7.970908440820895e-1,
+    -9.839936092033388e-1,
+    -2.742922431120874e-1,
+    6.039066664042889e-1,
+    4.635826509205736e-1,
+    -7.185724426717963e-2,
+    -2.8170727047307303e-1,
+    -6.705238765105779e-1,
+    -9.402019995618932e-1,
+    -5.092986625305389e-1,
+    -2.3110056654745767e-1,
+    -2.815466650028538e-1,
+    2.747814777388559e-1,
+    7.344046627021946e-1,
+    2.40910597116036e-1,
+    1.3693946615982694e-1,
+    9.2677674534114e-1,
+    9.966967694158505e-1,
+    1.0788135666156468e-1,
+    -2.640231311991266e-1,
+    2.3646744959358404e-1,
+    5.656651904915114e-1,
+    -3.5788801561112034e-2,
+    -7.878312790599921e-1,
+    -2.4360874119180545e-1,
+    8.726909917418468e-1,
+    4.4974615704674376e-1,
+    -8.438227606706122e-1,
+    -8.543199731511253e-1,
+    -3.415437912920614e-1,
+    -7.677229574890343e-1,
+    -8.771081500461785e-1,
+    3.186849839470455e-2,
+    1.542642884141537e-1,
+    -8.867858969051133e-1,
+    -1.2216992779430849e0,
+    -3.950756632487455e-1,
+    3.513507427824733e-1,
+    5.480250277871928e-1,
+    5.152305657149431e-1,
+    3.0211332105445554e-1,
+    8.577079180885756e-2,
+    3.611388997115639e-1,
+    8.887135310488469e-1,
+    7.854890783374634e-1,
+    2.6765847593263825e-1,
+    3.512015314271446e-1,
+    6.934439838665261e-1,
+    2.96549331912285e-1,
+    -5.267604416371843e-1,
+    -9.964623115163017e-1,
+    -8.121801332152526e-1,
+    -2.789691091522932e-2,
+    4.223171134030447e-1,
+    7.084166984477736e-2,
+    1.5856797327526384e-1,
+    6.699453874516468e-1,
+];
+const SINC_FIXED_OUT_LINEAR: [f64; 128] = [
+    3.6741625165396197e-1,
+    2.6930597379962473e-1,
+    7.0064254046665e-1,
+    5.245800543856911e-1,
+    -6.314602012328847e-2,
+    3.793700338595366e-1,
+    -6.106978852913814e-1,
+    6.004375728105379e-1,
+    -3.9083610336939173e-1,
+    -7.695872281923767e-1,
+    -6.069313860789797e-1,
+    -6.39125143107813e-2,
+    -8.618844792456845e-1,
+    -4.268868303293021e-1,
+    7.767357390806456e-1,
+    1.1029278260641066e-1,
+    4.6373235568538484e-1,
+    7.468786563949216e-1,
+    3.2998079118460866e-1,
+    5.678582015187943e-1,
+    -8.169330592538085e-1,
+    -5.220250381206163e-1,
+    2.2634177347666298e-1,
+    3.3971847472998257e-1,
+    6.179232669282798e-2,
+    -4.7074324978260396e-1,
+    4.977950723728448e-1,
+    3.353728491783573e-1,
+    -2.251159303397951e-1,
+    -7.823661847073304e-1,
+    -9.937042555975234e-2,
+    1.8795138759250984e-2,
+    5.319158119031109e-1,
+    1.6974227926119273e-1,
+    3.6100241680921286e-1,
+    1.0104557565516572e0,
+    6.365473362594878e-1,
+    9.742165081975318e-1,
+    -6.456515979768456e-1,
+    -5.432457305040805e-1,
+    -7.198324997954496e-1,
+    1.1403981216706391e-1,
+    5.861330034959502e-1,
+    7.206770151385424e-1,
+    8.176452375931662e-1,
+    5.665904036780429e-1,
+    -2.6418000067512476e-1,
+    -6.825094094569952e-1,
+    5.214549338242516e-1,
+    9.21959363910494e-1,
+    -5.229652955283356e-1,
+    -5.183507114406503e-1,
+    3.837992666823481e-1,
+    6.853152555235007e-1,
+    -4.3874155768848216e-1,
+    7.145301550683747e-4,
+    1.809358082849293e-1,
+    -4.388760062161327e-1,
+    8.000579144605326e-1,
+    -4.532670050918968e-1,
+    1.0549543459825397e0,
+    -8.614302216365516e-2,
+    -1.208125315520525e-1,
+    6.292295696184884e-1,
+    -8.358745729909617e-1,
+    -4.64511058011891e-1,
+    6.202219886666773e-1,
+    1.1170572572323142e-1,
+    -7.794340323837198e-2,
+    9.679136530116508e-2,
+    1.8903865522612695e-1,
+    3.643104953247157e-1,
+    5.906710721697791e-1,
+    -2.1131765667702532e-1,
+    -1.310008371408131e-1,
+    -2.035317837631887e-1,
+    -2.2662084321114073e-1,
+    5.698379582552378e-1,
+    9.450744594401342e-2,
+    1.4099084850750263e-1,
+    1.8096989831410287e-1,
+    -6.829972607970363e-1,
+    -3.2738098719052194e-1,
+    5.702132901634912e-1,
+    8.506416289519522e-1,
+    -2.7449808859542146e-1,
+    -2.4818364884473712e-1,
+    6.933130334115536e-1,
+    3.0342373261124467e-1,
+    3.0196714880818926e-1,
+    4.524855955449424e-1,
+    -4.2042926432325756e-1,
+    -8.88045189180122e-1,
+    -1.8002945698602363e-1,
+    3.167827220536902e-1,
+    -5.663307497187501e-1,
+    -4.7928195646259303e-1,
+    9.216161843602524e-1,
+    2.0717662849460808e-1,
+    -7.786570000680403e-1,
+    1.1608572205381273e-1,
+    8.36346010315493e-3,
+    -8.796731041065187e-1,
+    -3.762531109275326e-1,
+    -6.080519223997081e-1,
+    5.619857283954873e-1,
+    2.7233241210263226e-1,
+    -6.873282123585567e-2,
+    2.830583588754842e-2,
+    6.152727716151505e-2,
+    7.119546330011302e-1,
+    1.1227771434116661e-1,
+    -1.9160222510029742e-1,
+    4.781333881645507e-1,
+    -2.960287501811273e-1,
+    3.53124756697263e-1,
+    4.814112552147235e-1,
+    1.506786835083446e-1,
+    6.791048696196329e-1,
+    -3.778594683735303e-1,
+    7.901963598599712e-1,
+    4.405017354011175e-1,
+    7.643631750736506e-2,
+    -4.709358104852475e-1,
+    5.254791672586085e-2,
+    6.037582284125705e-1,
+    -3.4484460133335665e-1,
+    -6.0030165353297714e-2,
+];
+const FAST_FIXED_IN_CUBIC: [f64; 192] = [
+    -3.5020969557447956e-1,
+    -5.816442544909648e-1,
+    -9.161274238380543e-1,
+    -5.443724282096498e-1,
+    1.594776203493811e-1,
+    6.990611105917317e-1,
+    -7.894069080275054e-2,
+    -4.7718115345564127e-1,
+    -1.473712885939087e-1,
+    2.269717425683342e-1,
+    6.549991604875407e-1,
+    8.089039113958343e-1,
+    -2.721965907780927e-1,
+    -8.369772679556702e-1,
+    -4.85804160187086e-1,
+    2.3502153215668503e-1,
+    4.728770727886117e-1,
+    1.853748989296561e-1,
+    2.3675602307843283e-1,
+    2.0661406777380667e-1,
+    1.1640378492261848e-1,
+    3.8292470850952104e-1,
+    6.216528943801218e-1,
+    6.401256253188734e-1,
+    1.9731985202361088e-1,
+    1.8763803887038638e-1,
+    4.581163115242905e-1,
+    -4.345860512172466e-1,
+    -9.398085354809542e-1,
+    -6.228785311786452e-1,
+    3.9550170693155695e-1,
+    6.07849045383477e-1,
+    5.137033884409617e-2,
+    4.682346308114915e-1,
+    9.157209690147743e-1,
+    8.890383460609249e-1,
+    -4.201263544630637e-1,
+    -6.31800021181493e-1,
+    2.722915640636456e-1,
+    -1.5728008130404153e-1,
+    -1.094589459523804e-1,
+    4.1179556522601257e-1,
+    -3.370517291914342e-1,
+    -5.766025747541345e-1,
+    -2.147574536071339e-2,
+    5.451209341027268e-1,
+    4.585113510087207e-1,
+    -2.1416249608324217e-1,
+    -6.122322341431651e-1,
+    -2.2714127311329443e-1,
+    6.468954088706564e-1,
+    6.174555980289371e-1,
+    8.379808780828812e-2,
+    -5.960486273139727e-1,
+    -8.006593465384936e-1,
+    -1.549456449191048e-1,
+    7.888172604952712e-1,
+    -9.368396137121579e-2,
+    -3.2110745347270775e-1,
+    3.3378865912773564e-1,
+    1.1950221375080372e-1,
+    -1.590346086925092e-1,
+    -2.863701086212622e-1,
+    -2.9805485187304337e-1,
+    -5.101836157083885e-1,
+    -7.378102517982164e-1,
+    -6.054845511677959e-2,
+    3.319515033009833e-1,
+    1.7574669043557142e-1,
+    -1.6078861759147783e-1,
+    -1.4674328390299377e-1,
+    8.076161423732646e-2,
+    -5.638466536473881e-1,
+    -7.329244682296703e-1,
+    -1.8223965340357692e-1,
+    6.338732898223581e-1,
+    4.136910776623707e-1,
+    -5.774735684778215e-1,
+    -1.2500345485409298e-1,
+    1.0645084921571203e-1,
+    -1.342458210770121e-1,
+    -1.6684658929559205e-2,
+    -8.4813339375333e-2,
+    -2.552686482322939e-1,
+    2.4047778839352782e-1,
+    5.059700169695455e-1,
+    3.7302555083271594e-1,
+    2.83064522500443e-1,
+    -2.22139902815056e-1,
+    -8.678226312991302e-1,
+    -4.6203384249736784e-1,
+    1.387301875202324e-1,
+    4.569210100396422e-1,
+    -1.6798030301600655e-1,
+    -1.425780197883762e-1,
+    5.565249284488475e-1,
+    4.2076535656484637e-1,
+    -3.09198120931497e-2,
+    -5.535122505252222e-1,
+    -9.562164178086734e-1,
+    -6.529280228754147e-1,
+    1.832321757516059e-1,
+    5.164636753295121e-1,
+    3.177189651590695e-1,
+    -2.315362197517748e-1,
+    -4.938048727664061e-1,
+    -7.390413956045325e-1,
+    -8.756346118006045e-1,
+    -3.9315900248078706e-1,
+    -1.7257720735799512e-1,
+    -2.0299121629346104e-1,
+    5.49735952568251e-1,
+    5.608364020748123e-1,
+    -4.768857382140241e-2,
+    6.374802322240324e-1,
+    9.430554191219894e-1,
+    5.426189510540135e-1,
+    -1.6021803044088803e-2,
+    -2.7117835057908452e-2,
+    4.114765515012771e-1,
+    2.556449045764203e-1,
+    -3.08223604165215e-1,
+    -7.721088090609434e-1,
+    3.6699428891401764e-1,
+    6.610626738239352e-1,
+    -1.9992485562563933e-1,
+    -7.47065491764038e-1,
+    -7.55824406805948e-1,
+    -4.254835714151787e-1,
+    -8.205437945065476e-1,
+    -4.9816316770651925e-1,
+    3.213148805597036e-1,
+    -4.3271029169250674e-1,
+    -1.0106609998734633e0,
+    -8.782376633896345e-1,
+    -1.1165483906917335e-1,
+    4.7300673328126186e-1,
+    6.047712143310757e-1,
+    3.8831074313352004e-1,
+    1.8727899610698096e-1,
+    1.99195989643462e-1,
+    6.17637685261317e-1,
+    7.960569616805366e-1,
+    6.106055016495668e-1,
+    3.2283451421553744e-1,
+    4.3915655722367153e-1,
+    7.193434837271041e-1,
+    -1.368520334245716e-1,
+    -8.078933777560311e-1,
+    -8.840187298490487e-1,
+    -4.7104054166289644e-1,
+    7.414995241846972e-2,
+    4.4563946927679565e-1,
+    1.2638411174248707e-1,
+    2.7075491415159275e-1,
+    6.948181950930407e-1,
+    -4.57769202998025e-1,
+    -6.344715460947578e-1,
+    3.098051683846523e-1,
+    2.7177229952928744e-1,
+    3.7797723761562985e-1,
+    6.313812344124375e-1,
+    5.1300326287091566e-2,
+    -4.0688532623503565e-1,
+    -5.275427632101534e-1,
+    -6.658055008547356e-1,
+    -4.9846698326953964e-1,
+    -8.236464152624157e-2,
+    1.68427876482642e-1,
+    1.5484016179709725e-1,
+    6.2819084160394e-2,
+    6.901591228185064e-1,
+    4.765801838521551e-1,
+    -3.660774621555888e-1,
+    3.578483318223122e-1,
+    9.268680199906847e-1,
+    9.210685314341621e-1,
+    7.044797274316198e-1,
+    7.380144949313026e-1,
+    9.722675415810393e-1,
+    8.126336189830224e-1,
+    1.7532446346266998e-2,
+    -9.813385418498327e-1,
+    -6.180758634549914e-1,
+    -4.6716916693716776e-1,
+    -7.509579730387259e-1,
+    -6.478882332047227e-1,
+    -7.587887879234188e-2,
+    6.539984343043524e-1,
+    4.634410901444713e-1,
+    5.061154575139208e-1,
+    8.946633864658065e-1,
+];
+const FFT_FIXED_IN: [f64; 192] = [
+    1.623572943888535e-1,
+    7.657783375841447e-1,
+    5.040336309100449e-2,
+    -9.421404968387048e-1,
+    -8.631882901584065e-1,
+    -1.1681060255013806e-1,
+    1.3004638250808997e-1,
+    -1.1704626207719139e-1,
+    -6.0690828679762096e-2,
+    3.167227718862712e-1,
+    3.5222369274360776e-1,
+    3.626412111253312e-2,
+    1.1322170104362488e-1,
+    7.752739263991405e-1,
+    1.1384464572927224e0,
+    4.9322778266524114e-1,
+    -4.6789541879069163e-1,
+    -4.848442504667433e-1,
+    3.774917890089909e-1,
+    7.552112220405548e-1,
+    1.180191465808525e-1,
+    -4.102008686965379e-1,
+    3.5758778319249174e-2,
+    7.019244317354356e-1,
+    6.925981877600383e-1,
+    4.3352393554946567e-1,
+    4.972281544833135e-1,
+    3.163090019487112e-1,
+    -5.637798031614725e-1,
+    -1.1887342846147586e0,
+    -6.256578128632636e-1,
+    3.798358680950402e-1,
+    5.712349237685702e-1,
+    1.769282063129902e-2,
+    -4.592996046438279e-1,
+    -6.839645952866131e-1,
+    -8.015763986876506e-1,
+    -5.396917755445023e-1,
+    1.4701631945128246e-1,
+    5.372558158408369e-1,
+    1.280365398427867e-1,
+    -4.997440903364946e-1,
+    -4.474704909831321e-1,
+    3.1020992143447695e-1,
+    9.086531006845844e-1,
+    6.07696982170873e-1,
+    -3.196415961954812e-1,
+    -8.473426839770539e-1,
+    -4.6375062855564386e-1,
+    2.2203370880880707e-1,
+    4.6383075035793925e-1,
+    3.2927124598345986e-1,
+    1.998711008104283e-1,
+    1.21251121736009e-1,
+    1.4599015507232088e-1,
+    4.3599999455803773e-1,
+    6.912713216818933e-1,
+    4.8307904235368815e-1,
+    1.7953694421801158e-1,
+    3.104981838482604e-1,
+    3.337486534385874e-1,
+    -4.070203923615559e-1,
+    -1.0701527304848084e0,
+    -5.383605023970974e-1,
+    4.673247860738682e-1,
+    5.65368911605224e-1,
+    1.4635400925054542e-1,
+    4.245898733331982e-1,
+    1.0052854327504346e0,
+    6.746461774076034e-1,
+    -2.7030397584730304e-1,
+    -5.982034358109533e-1,
+    -2.1547984463327866e-1,
+    9.110924546237206e-2,
+    1.0100977182887624e-1,
+    -8.938221786227875e-4,
+    -2.6277383975016666e-1,
+    -4.8390979901096437e-1,
+    -1.4673536392761274e-1,
+    5.343562384112261e-1,
+    5.997466133327897e-1,
+    -1.6540569480934286e-1,
+    -7.481036745551692e-1,
+    -3.1722128163308355e-1,
+    6.200286471124549e-1,
+    8.648282843310277e-1,
+    4.829108445781307e-2,
+    -8.472662264362112e-1,
+    -7.356883617343113e-1,
+    7.539707116618831e-2,
+    3.963068967979685e-1,
+    5.83023776539267e-3,
+    -2.0725833079513564e-1,
+    6.544892543742524e-2,
+    1.760485925761728e-1,
+    -9.359773721526686e-2,
+    -2.7531543109749645e-1,
+    -3.3245322971664953e-1,
+    -5.609677797663453e-1,
+    -6.15917125702859e-1,
+    -9.897853532160625e-2,
+    3.799783441853432e-1,
+    1.6231345271788625e-1,
+    -2.0632751407240615e-1,
+    -8.014683240838354e-2,
+    -1.7940737886784103e-2,
+    -5.417398879041783e-1,
+    -8.211525001547344e-1,
+    -1.2294034056489078e-1,
+    6.531177100516476e-1,
+    4.1033509426546455e-1,
+    -3.0926576854348653e-1,
+    -3.7068372306287367e-1,
+    8.477056690856001e-2,
+    1.7511249274284788e-1,
+    -1.3960755048749798e-1,
+    -2.4170538284023788e-1,
+    -1.683184392222357e-2,
+    2.032285823844099e-1,
+    3.7326751220724264e-1,
+    5.350468085540108e-1,
+    3.4774405074154247e-1,
+    -3.320304710255386e-1,
+    -8.253814621241724e-1,
+    -4.7713828587040086e-1,
+    2.2798472402281025e-1,
+    3.313090470350798e-1,
+    -1.0385791987319486e-1,
+    -1.723524456737145e-1,
+    3.3611127281268793e-1,
+    6.031091792713839e-1,
+    7.827488496738032e-2,
+    -7.322553660867769e-1,
+    -1.0080288800049026e0,
+    -5.709056655644205e-1,
+    1.1838234433468034e-1,
+    5.193659577862597e-1,
+    3.810482646479013e-1,
+    -1.5302478862265634e-1,
+    -6.435971401469116e-1,
+    -7.605364096943027e-1,
+    -6.296138003276254e-1,
+    -5.234346971291641e-1,
+    -3.3468982021318383e-1,
+    1.31763546313452e-1,
+    5.218994812001968e-1,
+    4.0065357518321587e-1,
+    1.8904349651228616e-1,
+    5.459103313007475e-1,
+    1.0033069908504677e0,
+    6.275264928474994e-1,
+    -1.48189929149557e-1,
+    -1.002082972819262e-1,
+    5.170921213325428e-1,
+    3.3644094994369234e-1,
+    -5.296799663292255e-1,
+    -5.656395705492229e-1,
+    3.59108719924669e-1,
+    6.836742653241227e-1,
+    -1.07884281366718e-1,
+    -7.964726794544867e-1,
+    -7.408663205658059e-1,
+    -6.264002485768017e-1,
+    -7.029422520256665e-1,
+    -4.2649439024511365e-1,
+    4.57186358674674e-3,
+    -2.521982351390175e-1,
+    -9.744676241944414e-1,
+    -1.0169376661413239e0,
+    -1.638053968860286e-1,
+    6.1406202691307e-1,
+    6.692047463903986e-1,
+    3.1321788963498887e-1,
+    1.0089397171994646e-1,
+    2.655544448843498e-1,
+    6.528990381904718e-1,
+    8.231067641572453e-1,
+    5.599496077062551e-1,
+    3.00198501618159e-1,
+    4.7016064800817414e-1,
+    6.031588607561786e-1,
+    1.0431414004412049e-2,
+    -8.909802409885018e-1,
+    -1.0309886741708258e0,
+    -3.4894823918677226e-1,
+    1.880335643530169e-1,
+    1.905223571842437e-1,
+    2.1067333130678378e-1,
+    4.316334036372425e-1,
+    2.9749303104260716e-1,
+    -2.843272613555507e-1,
+    -5.561687394758511e-1,
+];
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::run_golden_vectors;
+
+    #[test]
+    fn golden_vectors() {
+        for result in run_golden_vectors() {
+            assert!(result.passed(), "{:?}", result);
+        }
+    }
+}
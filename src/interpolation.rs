@@ -1,3 +1,6 @@
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use num_integer as integer;
+
 /// Get the two nearest time points for time t in format (index, subindex)
 pub fn get_nearest_times_2(t: f64, factor: isize, points: &mut [(isize, isize); 2]) {
     let mut index = t.floor() as isize;
@@ -62,7 +65,207 @@ pub fn get_nearest_time(t: f64, factor: isize) -> (isize, isize) {
     (index, subindex)
 }
 
-#[cfg(test)]
+/// Sample rate in Hz from which the asynchronous resamplers created with `new_with_sample_rates`
+/// track the positions of the output frames exactly, when both rates are whole numbers.
+///
+/// The positions are normally advanced by the floating point resample ratio for every output frame.
+/// At high sample rates, like 352.8 kHz (DXD), 384 kHz and 768 kHz,
+/// many more frames are produced per second, and the rounding errors of the ratio
+/// accumulate faster. The exact tracking uses integer arithmetic and avoids this drift.
+pub const HIGH_SAMPLE_RATE: f64 = 352800.0;
+
+/// Get the sample rates as integers for exact position tracking,
+/// if both are whole numbers and at least one of them is at or above [HIGH_SAMPLE_RATE].
+pub(crate) fn exact_high_rates(
+    sample_rate_input: f64,
+    sample_rate_output: f64,
+) -> Option<(usize, usize)> {
+    let is_whole = |rate: f64| rate.fract() == 0.0 && rate > 0.0 && rate <= u32::MAX as f64;
+    if sample_rate_input.max(sample_rate_output) >= HIGH_SAMPLE_RATE
+        && is_whole(sample_rate_input)
+        && is_whole(sample_rate_output)
+    {
+        Some((sample_rate_input as usize, sample_rate_output as usize))
+    } else {
+        None
+    }
+}
+
+/// Calculate the resample ratio for a pair of sample rates that may be fractional.
+pub fn ratio_from_sample_rates(
+    sample_rate_input: f64,
+    sample_rate_output: f64,
+) -> Result<f64, ResamplerConstructionError> {
+    let valid = |rate: f64| rate.is_finite() && rate > 0.0;
+    if !valid(sample_rate_input) || !valid(sample_rate_output) {
+        return Err(ResamplerConstructionError::InvalidFractionalSampleRate {
+            input: sample_rate_input,
+            output: sample_rate_output,
+        });
+    }
+    Ok(sample_rate_output / sample_rate_input)
+}
+
+/// Get the needed input size and the length of the input buffer per channel
+/// of a fixed output resampler, as a tuple `(needed_input_size, buffer_channel_length)`.
+pub(crate) fn fixed_out_buffer_sizes(
+    chunk_size: usize,
+    resample_ratio: f64,
+    max_resample_ratio_relative: f64,
+    filter_len: usize,
+) -> (usize, usize) {
+    let needed_input_size =
+        ((chunk_size as f64 / resample_ratio).ceil() as usize).saturating_add(2 + filter_len / 2);
+    let buffer_channel_length = (((max_resample_ratio_relative + 1.0) * needed_input_size as f64)
+        as usize)
+        .saturating_add(2 * filter_len);
+    (needed_input_size, buffer_channel_length)
+}
+
+/// Check a new maximum relative ratio for an asynchronous resampler.
+/// The current and the target ratios must both be within the new range.
+pub(crate) fn validate_max_relative_ratio(
+    max_relative_ratio: f64,
+    original: f64,
+    resample_ratio: f64,
+    target_ratio: f64,
+) -> ResampleResult<()> {
+    if max_relative_ratio.is_nan() || max_relative_ratio < 1.0 {
+        return Err(ResampleError::InvalidRelativeRatio {
+            provided: max_relative_ratio,
+        });
+    }
+    for ratio in [resample_ratio, target_ratio] {
+        if ratio / original < 1.0 / max_relative_ratio || ratio / original > max_relative_ratio {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: ratio,
+                original,
+                max_relative_ratio,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The state of an asynchronous resampler, used to predict the sizes of future chunks.
+///
+/// The predictions repeat the index calculations of the process methods,
+/// so that they give exactly the same results.
+pub struct ChunkState {
+    /// Number of frames per chunk, for input (fixed in) or output (fixed out).
+    pub chunk_size: usize,
+    /// Length of the interpolation filter, in input frames.
+    pub filter_len: usize,
+    /// Position of the last output frame relative to the start of the next chunk.
+    pub last_index: f64,
+    /// The current resampling ratio.
+    pub resample_ratio: f64,
+    /// The ratio at the end of the next chunk, differs from `resample_ratio` while ramping.
+    pub target_ratio: f64,
+}
+
+impl ChunkState {
+    /// Get the number of input frames that a resampler with fixed input size
+    /// needs in order to produce at least `output_frames` frames of output.
+    /// This is always a multiple of the chunk size.
+    pub fn fixed_in_frames_needed(&self, output_frames: usize) -> usize {
+        let mut idx = self.last_index;
+        let mut ratio = self.resample_ratio;
+        let mut produced = 0;
+        let mut input_frames = 0;
+        while produced < output_frames {
+            let mut t_ratio = 1.0 / ratio;
+            let t_ratio_end = 1.0 / self.target_ratio;
+            let approximate_nbr_frames =
+                self.chunk_size as f64 * (0.5 * ratio + 0.5 * self.target_ratio);
+            let t_ratio_increment = (t_ratio_end - t_ratio) / approximate_nbr_frames;
+            let end_idx = self.chunk_size as isize
+                - (self.filter_len as isize + 1)
+                - t_ratio_end.ceil() as isize;
+            while idx < end_idx as f64 {
+                t_ratio += t_ratio_increment;
+                idx += t_ratio;
+                produced += 1;
+            }
+            idx -= self.chunk_size as f64;
+            ratio = self.target_ratio;
+            input_frames += self.chunk_size;
+        }
+        input_frames
+    }
+
+    /// Get the number of input frames that a resampler with fixed output size
+    /// needs in order to produce at least `output_frames` frames of output.
+    /// This is the sum of the input sizes of the chunks,
+    /// starting with `needed_input_size` for the next chunk.
+    pub fn fixed_out_frames_needed(&self, output_frames: usize, needed_input_size: usize) -> usize {
+        let mut idx = self.last_index;
+        let mut ratio = self.resample_ratio;
+        let mut needed = needed_input_size;
+        let mut produced = 0;
+        let mut input_frames = 0;
+        while produced < output_frames {
+            let mut t_ratio = 1.0 / ratio;
+            let t_ratio_end = 1.0 / self.target_ratio;
+            let t_ratio_increment = (t_ratio_end - t_ratio) / self.chunk_size as f64;
+            for _ in 0..self.chunk_size {
+                t_ratio += t_ratio_increment;
+                idx += t_ratio;
+            }
+            idx -= needed as f64;
+            ratio = self.target_ratio;
+            input_frames += needed;
+            produced += self.chunk_size;
+            needed = ((idx as f32 + self.chunk_size as f32 / ratio as f32 + self.filter_len as f32)
+                .ceil()
+                + 2.0) as usize;
+        }
+        input_frames
+    }
+}
+
+/// An exact position of the output frames of an asynchronous resampler,
+/// for a ratio given by two integer sample rates.
+///
+/// The resamplers step through the input with a floating point index,
+/// that is carried over from chunk to chunk. Over a very long stream the rounding
+/// errors of the steps add up, and the output slowly drifts from the ideal ratio.
+/// Here the position is instead kept as an integer number of input frames
+/// and a remainder in units of `1 / output_rate` input frames,
+/// and the index is recalculated from these after each chunk.
+/// The rounding errors then stay within one chunk.
+pub(crate) struct RationalPhase {
+    input_rate: u64,
+    output_rate: u64,
+    anchor: f64,
+    whole: i64,
+    remainder: u64,
+}
+
+impl RationalPhase {
+    /// Start tracking from index `anchor`, the position of the last output frame.
+    pub(crate) fn new(sample_rate_input: usize, sample_rate_output: usize, anchor: f64) -> Self {
+        let gcd = integer::gcd(sample_rate_input, sample_rate_output).max(1);
+        RationalPhase {
+            input_rate: (sample_rate_input / gcd) as u64,
+            output_rate: (sample_rate_output / gcd) as u64,
+            anchor,
+            whole: 0,
+            remainder: 0,
+        }
+    }
+
+    /// Advance by the number of output frames produced and input frames consumed in a chunk,
+    /// and return the exact index of the last output frame relative to the start of the next chunk.
+    pub(crate) fn advance(&mut self, frames_out: usize, frames_in: usize) -> f64 {
+        self.remainder += frames_out as u64 * self.input_rate;
+        self.whole += (self.remainder / self.output_rate) as i64 - frames_in as i64;
+        self.remainder %= self.output_rate;
+        self.anchor + self.whole as f64 + self.remainder as f64 / self.output_rate as f64
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     use crate::interpolation::get_nearest_time;
     use crate::interpolation::get_nearest_times_2;
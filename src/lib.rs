@@ -41,18 +41,48 @@
 //! and then inverse FFT:ed to get the resampled data.
 //! This type of resampler is considerably faster but doesn't support changing the resampling ratio.
 //!
+//! # Errors and panics
+//!
+//! The processing methods of the resamplers check all their arguments,
+//! and return a [ResampleError] for buffers of the wrong size or with the wrong number of channels,
+//! and for masks of the wrong length.
+//! Likewise, invalid resample ratios and chunk sizes give errors and leave the resampler unchanged.
+//! Processing is not expected to panic for any arguments,
+//! and the buffers allocated with [input_buffer_allocate](Resampler::input_buffer_allocate)
+//! and [output_buffer_allocate](Resampler::output_buffer_allocate) are always large enough.
+//! This is checked by running all resamplers with random sequences of calls with invalid arguments.
+//! For builds that must not contain any unchecked indexing, see the
+//! [forbid_unsafe feature](#forbid_unsafe-build-without-any-unsafe-code).
+//!
 //! # SIMD acceleration
 //!
 //! ## Asynchronous resampling with anti-aliasing
 //!
-//! The asynchronous resampler supports SIMD on x86_64 and on aarch64.
+//! The asynchronous resampler supports SIMD on x86_64, on 32-bit x86 and on aarch64.
 //! The SIMD capabilities of the CPU are determined at runtime.
 //! If no supported SIMD instruction set is available, it falls back to a scalar implementation.
 //!
 //! On x86_64 it will try to use AVX. If AVX isn't available, it will instead try SSE3.
 //!
+//! On 32-bit x86 it will use SSE2 if available.
+//!
 //! On aarch64 (64-bit Arm) it will use Neon if available.
 //!
+//! Stereo streams, where both channels are active, are interpolated with both channels
+//! in the same pass over the sinc filter. With AVX this loads each filter coefficient once
+//! for the two channels. The result is identical to processing the channels one by one.
+//!
+//! The runtime selection can be replaced by a fixed choice made at compile time,
+//! by enabling one of the cargo features `force_scalar`, `force_avx`, `force_sse3`,
+//! `force_sse2` or `force_neon`.
+//! The scalar implementation can always be forced, and gives the same code path on all platforms.
+//! Forcing one of the SIMD implementations requires the corresponding target features
+//! to be enabled at compile time, for example with `-C target-cpu=native`.
+//! Compiling fails if they are not, and no runtime checks of the CPU capabilities are then needed.
+//!
+//! Each SIMD implementation is included by a cargo feature, see
+//! [the SIMD features](#simd_avx-simd_sse3-simd_sse2-and-simd_neon-select-the-simd-implementations).
+//!
 //! ## Synchronous resampling
 //!
 //! The synchronous resamplers benefit from the SIMD support of the RustFFT library.
@@ -66,19 +96,69 @@
 //! These calls may take some (unpredictable) time to return, during which the application is blocked.
 //! This means that logging should be avoided if using this library in a realtime application.
 //!
+//! ## `f32` and `f64`: Select the supported sample types
+//!
+//! These features enable support for [f32] and [f64] samples, and both are enabled by default.
+//! A binary that only uses one of the types, for example on an embedded or wasm target,
+//! can disable the default features and enable only `f32` or only `f64`.
+//! This leaves out the SIMD code for the other type, and reduces the code size and the compile time.
+//! The [MixedPrecisionInterpolator](sinc_interpolator::sinc_interpolator_mixed::MixedPrecisionInterpolator)
+//! needs both types, and the delay measurement and golden vector functions need `f64`.
+//! The tests require the default features.
+//!
+//! ## `simd_avx`, `simd_sse3`, `simd_sse2` and `simd_neon`: Select the SIMD implementations
+//!
+//! These features include the AVX, SSE3, SSE2 and Neon interpolators, and all are enabled by default.
+//! Each implementation is only compiled for the targets where it can run,
+//! but all of those are compiled in and then selected at runtime.
+//! A binary for a known CPU can disable the default features,
+//! and enable only the implementations it can use, to reduce the code size.
+//! For example, a build for x86_64 CPUs that all support AVX can enable only `simd_avx`,
+//! and the sinc resamplers then fall back to the scalar interpolator on any other CPU.
+//! The `force_*` features enable the feature of the implementation they select.
+//! The interpolators that were left out are replaced by the scalar one at runtime,
+//! see [interpolator_backend](SincFixedIn::interpolator_backend).
+//!
+//! ## `forbid_unsafe`: Build without any unsafe code
+//!
+//! This feature leaves out all code that needs `unsafe`, and compiles the crate with `forbid(unsafe_code)`.
+//! It enables `force_scalar`, so that the sinc resamplers always use the scalar interpolator,
+//! and the SIMD interpolators are not available.
+//! Indexing in the inner loops is bounds checked, which makes the processing somewhat slower.
+//! The [DenormalGuard] does nothing, since the floating point control registers
+//! can only be accessed with unsafe code,
+//! and the lock-free ring buffer of [frame_channel] is left out.
+//!
+//! ## `audio-core`: Process buffers of the `audio` crate
+//!
+//! This feature implements the [AudioBufResampler] trait for all resamplers,
+//! that processes any buffer implementing the `Buf` and `BufMut` traits of the `audio-core` crate.
+//! This includes the sequential, interleaved and dynamic buffers of the `audio` crate.
+//! Channels that are not linear in memory, like those of interleaved buffers,
+//! are copied via a temporary buffer, which allocates.
+//! Note that the `audio-core` crate needs Rust 1.70 or later.
+//!
+//! ## `cli`: Build the command line resampler
+//!
+//! This feature builds the `rubato` binary, that resamples raw or wav files with any of the resamplers.
+//! It prints the latency and throughput, and the command line needed to reproduce the run.
+//! Please include this when reporting a bug.
+//! ```text
+//! cargo run --release --features cli -- --algo sincfixedin --quality high --out-rate 48000 input.wav output.wav
+//! ```
+//!
 //! # Example
 //!
 //! Resample a single chunk of a dummy audio file from 44100 to 48000 Hz.
 //! See also the "process_f64" example that can be used to process a file from disk.
 //! ```
 //! use rubato::{Resampler, SincFixedIn, SincInterpolationType, SincInterpolationParameters, WindowFunction};
-//! let params = SincInterpolationParameters {
-//!     sinc_len: 256,
-//!     f_cutoff: 0.95,
-//!     interpolation: SincInterpolationType::Linear,
-//!     oversampling_factor: 256,
-//!     window: WindowFunction::BlackmanHarris2,
-//! };
+//! let mut params = SincInterpolationParameters::default();
+//! params.sinc_len = 256;
+//! params.f_cutoff = 0.95;
+//! params.interpolation = SincInterpolationType::Linear;
+//! params.oversampling_factor = 256;
+//! params.window = WindowFunction::BlackmanHarris2;
 //! let mut resampler = SincFixedIn::<f64>::new(
 //!     48000 as f64 / 44100 as f64,
 //!     2.0,
@@ -140,6 +220,8 @@
 //!   - Accept any AsRef<\[T\]> as input.
 //!
 
+#![cfg_attr(feature = "forbid_unsafe", forbid(unsafe_code))]
+
 #[cfg(feature = "log")]
 extern crate log;
 
@@ -175,33 +257,175 @@ macro_rules! error { ($($x:tt)*) => (
     }
 ) }
 
+// Sanity checks of the features for forcing an interpolator backend
+#[cfg(all(
+    feature = "forced_backend",
+    not(any(
+        feature = "force_scalar",
+        feature = "force_avx",
+        feature = "force_sse3",
+        feature = "force_sse2",
+        feature = "force_neon"
+    ))
+))]
+compile_error!(
+    "The forced_backend feature is internal, enable one of the force_* features instead"
+);
+#[cfg(any(
+    all(
+        feature = "force_scalar",
+        any(
+            feature = "force_avx",
+            feature = "force_sse3",
+            feature = "force_sse2",
+            feature = "force_neon"
+        )
+    ),
+    all(
+        feature = "force_avx",
+        any(feature = "force_sse3", feature = "force_sse2", feature = "force_neon")
+    ),
+    all(
+        feature = "force_sse3",
+        any(feature = "force_sse2", feature = "force_neon")
+    ),
+    all(feature = "force_sse2", feature = "force_neon")
+))]
+compile_error!("Only one of the force_* features can be enabled");
+#[cfg(all(
+    feature = "force_avx",
+    not(all(target_arch = "x86_64", target_feature = "avx", target_feature = "fma"))
+))]
+compile_error!(
+    "The force_avx feature requires an x86_64 target with the avx and fma target features enabled"
+);
+#[cfg(all(
+    feature = "force_sse3",
+    not(all(target_arch = "x86_64", target_feature = "sse3"))
+))]
+compile_error!(
+    "The force_sse3 feature requires an x86_64 target with the sse3 target feature enabled"
+);
+#[cfg(all(
+    feature = "force_sse2",
+    not(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2"
+    ))
+))]
+compile_error!(
+    "The force_sse2 feature requires an x86 or x86_64 target with the sse2 target feature enabled"
+);
+#[cfg(all(
+    feature = "force_neon",
+    not(all(target_arch = "aarch64", target_feature = "neon"))
+))]
+compile_error!(
+    "The force_neon feature requires an aarch64 target with the neon target feature enabled"
+);
+
+mod adaptive;
 mod asynchro_fast;
 mod asynchro_sinc;
+#[cfg(feature = "audio-core")]
+mod audio_buf;
+mod background;
+mod batch;
+mod budget;
+mod complex;
+mod conceal;
+mod config;
+mod decimator;
+#[cfg(feature = "f64")]
+mod delay;
+mod delay_line;
+mod denormal;
 mod error;
+mod fixed_chunk;
+#[cfg(feature = "f64")]
+mod golden;
 mod interpolation;
+mod multi_output;
+mod multi_ratio;
+mod offline;
+mod peak;
+mod pitch;
+mod pool;
+mod remez;
+mod requantize;
+mod reverse;
 mod sample;
+mod sanitize;
 mod sinc;
+mod skew;
+mod soft_mute;
+#[cfg(not(feature = "forbid_unsafe"))]
+mod spsc;
 mod synchro;
+mod synchro_partitioned;
+mod timestamp;
+mod variable_chunk;
 mod windows;
 
 pub mod sinc_interpolator;
 
-pub use crate::asynchro_fast::{FastFixedIn, FastFixedOut, PolynomialDegree};
+pub use crate::adaptive::AdaptiveLatency;
+pub use crate::asynchro_fast::{polynomial_weights, FastFixedIn, FastFixedOut, PolynomialDegree};
 pub use crate::asynchro_sinc::{
-    SincFixedIn, SincFixedOut, SincInterpolationParameters, SincInterpolationType,
+    make_prototype_interpolator, make_table_interpolator, sinc_table_source, FilterDesign,
+    GainNormalization, SincFilterBank, SincFixedIn, SincFixedOut, SincInterpolationParameters,
+    SincInterpolationType, SpeechPreset,
 };
+#[cfg(feature = "audio-core")]
+pub use crate::audio_buf::AudioBufResampler;
+pub use crate::background::{build_in_background, PendingResampler};
+pub use crate::batch::BatchSincFixedIn;
+pub use crate::budget::{measure_worst_case_time, ChunkWork};
+pub use crate::complex::ComplexResampler;
+pub use crate::conceal::ConcealDropouts;
+pub use crate::config::{ResamplerAlgorithm, ResamplerConfig};
+pub use crate::decimator::SincDecimator;
+#[cfg(feature = "f64")]
+pub use crate::delay::{measure_delay, measure_resampler_delay};
+pub use crate::delay_line::DelayLine;
+pub use crate::denormal::{DenormalGuard, FlushDenormals};
 pub use crate::error::{
     CpuFeature, MissingCpuFeature, ResampleError, ResampleResult, ResamplerConstructionError,
 };
-pub use crate::sample::Sample;
-pub use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut};
-pub use crate::windows::{calculate_cutoff, WindowFunction};
+pub use crate::fixed_chunk::FixedChunkResampler;
+#[cfg(feature = "f64")]
+pub use crate::golden::{golden_input, run_golden_vectors, GoldenResult, GOLDEN_TOLERANCE};
+pub use crate::interpolation::HIGH_SAMPLE_RATE;
+pub use crate::multi_output::MultiOutputSinc;
+pub use crate::multi_ratio::MultiRatioSinc;
+pub use crate::offline::{process_all, process_all_with, CancellationToken};
+pub use crate::peak::{Peak, PeakDecimator};
+pub use crate::pitch::PitchShift;
+pub use crate::pool::ResamplerPool;
+pub use crate::requantize::{NoiseShaping, Requantizer};
+pub use crate::reverse::Reversible;
+pub use crate::sample::{CoerceFrom, Sample};
+pub use crate::sanitize::SanitizeInput;
+pub use crate::skew::ClockSkewEstimator;
+pub use crate::soft_mute::SoftMute;
+#[cfg(not(feature = "forbid_unsafe"))]
+pub use crate::spsc::{frame_channel, FrameConsumer, FrameProducer, ResamplingConsumer};
+pub use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut, FftPrecision};
+pub use crate::synchro_partitioned::FftLowLatency;
+pub use crate::timestamp::Timestamped;
+pub use crate::variable_chunk::VariableChunkResampler;
+pub use crate::windows::{
+    calculate_cutoff, calculate_sinc_len, select_window, window_attenuation, WindowFunction,
+};
 
 /// A resampler that is used to resample a chunk of audio to a new sample rate.
 /// For asynchronous resamplers, the rate can be adjusted as required.
 ///
 /// This trait is not object safe. If you need an object safe resampler,
 /// use the [VecResampler] wrapper trait.
+///
+/// The trait is implemented for `Box<R>` and `&mut R` of any resampler `R`,
+/// so that a resampler can be given by reference to generic code and to the wrappers.
 pub trait Resampler<T>: Send
 where
     T: Sample,
@@ -215,8 +439,9 @@ where
         wave_in: &[V],
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<Vec<Vec<T>>> {
-        let frames = self.output_frames_next();
-        let channels = self.nbr_channels();
+        // Dereference explicitly, since `&mut Self` also implements Resampler
+        let frames = (*self).output_frames_next();
+        let channels = (*self).nbr_channels();
         let mut wave_out = Vec::with_capacity(channels);
         for chan in 0..channels {
             let chan_out = if active_channels_mask.map(|mask| mask[chan]).unwrap_or(true) {
@@ -255,10 +480,18 @@ where
     /// Any channel marked as inactive by a false value will be skipped during processing
     /// and the corresponding output will be left unchanged.
     /// If `None` is given, all channels will be considered active.
+    /// When a mask is given, the input and output buffers may either contain all channels,
+    /// or only the active ones, in the same order as in the mask.
+    /// This avoids allocating buffers for inactive channels.
+    /// The input and output buffers are checked separately, so one may contain all channels
+    /// while the other only contains the active ones.
     ///
     /// Before processing, it checks that the input and outputs are valid.
     /// If either has the wrong number of channels, or if the buffer for any channel is too short,
     /// a [ResampleError] is returned.
+    /// The buffers are validated before any internal state is changed,
+    /// so after an error the call can be retried with corrected buffers,
+    /// without resetting the resampler.
     /// Both input and output are allowed to be longer than required.
     /// The number of input samples consumed and the number output samples written
     /// per channel is returned in a tuple, `(input_frames, output_frames)`.
@@ -269,6 +502,112 @@ where
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<(usize, usize)>;
 
+    /// Resample a buffer of audio where each input channel is given as two slices,
+    /// to a pre-allocated output buffer.
+    ///
+    /// This is intended for input that is read from a ring buffer,
+    /// which gives the available frames as two slices when the data wraps around the end.
+    /// Each channel is given as a tuple `(first, second)`, where the frames of `first`
+    /// are followed by the frames of `second`. Either slice may be empty.
+    /// Apart from that, this works like [process_into_buffer](Resampler::process_into_buffer),
+    /// and the number of frames needed is counted over both slices.
+    ///
+    /// The resamplers of this crate copy the slices directly into their internal buffers.
+    /// The default implementation, used by the wrappers, instead joins the slices
+    /// in a temporary buffer, and therefore allocates.
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let joined: Vec<Vec<T>> = wave_in
+            .iter()
+            .map(|(first, second)| first.iter().chain(second.iter()).copied().collect())
+            .collect();
+        self.process_into_buffer(&joined, wave_out, active_channels_mask)
+    }
+
+    /// Get the number of values in the scratch buffer needed by
+    /// [process_into_buffer_with_scratch](Resampler::process_into_buffer_with_scratch).
+    ///
+    /// The scratch buffer holds the temporary data of a call, that is not kept between calls.
+    /// The FFT resamplers use it for the FFTs and the spectra.
+    /// The other resamplers return 0, and the wrappers return the length needed by
+    /// the resampler they wrap.
+    /// The length depends on the FFT sizes, and may change when the sample rates
+    /// or the chunk size of the resampler are changed.
+    /// With the `forbid_unsafe` feature, the FFT resamplers can't use a scratch buffer of samples
+    /// for the complex spectra, and also return 0.
+    fn scratch_len(&self) -> usize {
+        0
+    }
+
+    /// Resample a buffer of audio to a pre-allocated output buffer,
+    /// using a scratch buffer provided by the caller for the temporary data.
+    ///
+    /// This is intended for integrations with strict rules for memory ownership,
+    /// such as realtime threads, FFI and arena allocators.
+    /// The resampler then uses the scratch buffer instead of its own temporary buffers,
+    /// and only touches its own state, meaning for example the filter and the overlap
+    /// or history between chunks.
+    /// The scratch buffer must have at least [scratch_len](Resampler::scratch_len) values,
+    /// otherwise [ResampleError::InsufficientScratchSize] is returned.
+    /// It can be reused between calls and shared between resamplers, its content doesn't matter.
+    ///
+    /// Apart from that, this works like [process_into_buffer](Resampler::process_into_buffer),
+    /// and gives the same result. The default implementation checks the length of the scratch
+    /// buffer and calls [process_into_buffer](Resampler::process_into_buffer).
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        validate_scratch(Self::scratch_len(self), scratch)?;
+        self.process_into_buffer(wave_in, wave_out, active_channels_mask)
+    }
+
+    /// Check that the input and output buffers are valid for the next call to
+    /// [process_into_buffer](Resampler::process_into_buffer), without processing any data.
+    ///
+    /// This performs the same checks as [process_into_buffer](Resampler::process_into_buffer)
+    /// and returns the same [ResampleError] if any check fails.
+    /// Use this to check a buffer configuration once, for example at setup time.
+    /// The check uses the current values of [input_frames_next](Resampler::input_frames_next)
+    /// and [output_frames_next](Resampler::output_frames_next). For asynchronous resamplers,
+    /// buffers with at least [input_frames_max](Resampler::input_frames_max) and
+    /// [output_frames_max](Resampler::output_frames_max) frames per channel are valid
+    /// regardless of the resampling ratio.
+    ///
+    /// Note that this method allocates a temporary mask if `active_channels_mask` is `None`.
+    fn validate_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<()> {
+        let channels = self.nbr_channels();
+        let all_active;
+        let mask = match active_channels_mask {
+            Some(mask) => mask,
+            None => {
+                all_active = vec![true; channels];
+                &all_active
+            }
+        };
+        validate_buffers(
+            wave_in,
+            wave_out,
+            mask,
+            channels,
+            self.input_frames_next(),
+            self.output_frames_next(),
+        )
+        .map(|_| ())
+    }
+
     /// This is a convenience method for processing the last frames at the end of a stream.
     /// Use this when there are fewer frames remaining than what the resampler requires as input.
     /// Calling this function is equivalent to padding the input buffer with zeros
@@ -283,13 +622,23 @@ where
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<(usize, usize)> {
-        let frames = self.input_frames_next();
-        let mut wave_in_padded = Vec::with_capacity(self.nbr_channels());
-        for _ in 0..self.nbr_channels() {
+        let frames = (*self).input_frames_next();
+        let mut wave_in_padded = Vec::with_capacity((*self).nbr_channels());
+        for _ in 0..(*self).nbr_channels() {
             wave_in_padded.push(vec![T::zero(); frames]);
         }
         if let Some(input) = wave_in {
-            for (ch_input, ch_padded) in input.iter().zip(wave_in_padded.iter_mut()) {
+            // An input containing only the active channels is expanded to all channels
+            let mut padded_channels: Vec<&mut Vec<T>> = match active_channels_mask {
+                Some(mask) if input.len() != mask.len() => wave_in_padded
+                    .iter_mut()
+                    .zip(mask.iter())
+                    .filter(|(_, active)| **active)
+                    .map(|(padded, _)| padded)
+                    .collect(),
+                _ => wave_in_padded.iter_mut().collect(),
+            };
+            for (ch_input, ch_padded) in input.iter().zip(padded_channels.iter_mut()) {
                 let mut frames_in = ch_input.as_ref().len();
                 if frames_in > frames {
                     frames_in = frames;
@@ -313,8 +662,8 @@ where
         wave_in: Option<&[V]>,
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<Vec<Vec<T>>> {
-        let frames = self.output_frames_next();
-        let channels = self.nbr_channels();
+        let frames = (*self).output_frames_next();
+        let channels = (*self).nbr_channels();
         let mut wave_out = Vec::with_capacity(channels);
         for chan in 0..channels {
             let chan_out = if active_channels_mask.map(|mask| mask[chan]).unwrap_or(true) {
@@ -376,14 +725,67 @@ where
     fn output_frames_next(&self) -> usize;
 
     /// Get the delay for the resampler, reported as a number of output frames.
+    /// See also [input_delay](Resampler::input_delay) and [buffering_delay](Resampler::buffering_delay).
     fn output_delay(&self) -> usize;
 
+    /// Get the delay for the resampler, reported as a number of input frames.
+    /// This is the same delay as [output_delay](Resampler::output_delay), but counted in input frames,
+    /// and the two differ by the resampling ratio.
+    /// It does not include the time needed to collect the input for a chunk,
+    /// see [buffering_delay](Resampler::buffering_delay).
+    ///
+    /// The default implementation converts the output delay with the ratio between
+    /// [input_frames_next](Resampler::input_frames_next) and
+    /// [output_frames_next](Resampler::output_frames_next).
+    fn input_delay(&self) -> usize {
+        let frames_out = Self::output_frames_next(self);
+        if frames_out == 0 {
+            return Self::output_delay(self);
+        }
+        (Self::output_delay(self) as f64 * Self::input_frames_next(self) as f64 / frames_out as f64)
+            .round() as usize
+    }
+
+    /// Get the latency caused by collecting the input for the next chunk, as a number of input frames.
+    /// This number of input frames must be available before the next call to
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    /// The total latency from input to output, in input frames,
+    /// is the sum of this and [input_delay](Resampler::input_delay).
+    ///
+    /// The default implementation returns [input_frames_next](Resampler::input_frames_next).
+    fn buffering_delay(&self) -> usize {
+        self.input_frames_next()
+    }
+
+    /// Get the delay for the resampler in seconds, as [output_delay](Resampler::output_delay)
+    /// converted with the output sample rate `sample_rate_output`.
+    /// This is the delay to report for plugin delay compensation, when the host runs at the output rate.
+    /// Multiply by 1000 for milliseconds.
+    fn output_delay_seconds(&self, sample_rate_output: f64) -> f64 {
+        self.output_delay() as f64 / sample_rate_output
+    }
+
+    /// Get the delay for the resampler in seconds, as [input_delay](Resampler::input_delay)
+    /// converted with the input sample rate `sample_rate_input`.
+    /// Like the delay in frames, this does not include the buffering delay.
+    fn input_delay_seconds(&self, sample_rate_input: f64) -> f64 {
+        self.input_delay() as f64 / sample_rate_input
+    }
+
+    /// Get the total latency from input to output in seconds, including the time to collect
+    /// the input for the next chunk. This is the sum of [buffering_delay](Resampler::buffering_delay)
+    /// and [input_delay](Resampler::input_delay), converted with the input sample rate `sample_rate_input`.
+    fn latency_seconds(&self, sample_rate_input: f64) -> f64 {
+        (self.buffering_delay() + self.input_delay()) as f64 / sample_rate_input
+    }
+
     /// Update the resample ratio
     ///
     /// For asynchronous resamplers, the ratio must be within
     /// `original / maximum` to `original * maximum`, where the original and maximum are the
-    /// resampling ratios that were provided to the constructor. Trying to set the ratio
-    /// outside these bounds will return [ResampleError::RatioOutOfBounds].
+    /// resampling ratios that were provided to the constructor,
+    /// or set later with [set_max_resample_ratio_relative](Resampler::set_max_resample_ratio_relative).
+    /// Trying to set the ratio outside these bounds will return [ResampleError::RatioOutOfBounds].
     ///
     /// For synchronous resamplers, this will always return [ResampleError::SyncNotAdjustable].
     ///
@@ -405,10 +807,271 @@ where
     /// For synchronous resamplers, this will always return [ResampleError::SyncNotAdjustable].
     fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()>;
 
+    /// Change the maximum relative ratio that was given to the constructor of an asynchronous resampler.
+    ///
+    /// This widens or narrows the range allowed by [set_resample_ratio](Resampler::set_resample_ratio)
+    /// to `original / maximum` to `original * maximum`, while the stream continues.
+    /// The original ratio, the filter and the current ratio are kept.
+    /// Narrowing the range is rejected with [ResampleError::RatioOutOfBounds]
+    /// if the current or the target ratio would fall outside it,
+    /// and a maximum below 1.0 returns [ResampleError::InvalidRelativeRatio].
+    /// A wider range may need longer buffers, check [input_frames_max](Resampler::input_frames_max)
+    /// and [output_frames_max](Resampler::output_frames_max).
+    /// Internal buffers are resized when needed, so this may allocate.
+    ///
+    /// For synchronous resamplers, this will always return [ResampleError::SyncNotAdjustable].
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        _max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Change the chunk size, for example when the host changes its block size.
+    ///
+    /// This is the number of input frames for resamplers with a fixed input size,
+    /// and the number of output frames for those with a fixed output size.
+    /// The filter history and the current ratio are kept, so that the stream continues
+    /// without a gap, instead of starting over with a new resampler.
+    /// Buffers allocated for the previous chunk size may be too short after the change,
+    /// check [input_frames_max](Resampler::input_frames_max) and
+    /// [output_frames_max](Resampler::output_frames_max).
+    /// Internal buffers are resized when needed, so this may allocate.
+    ///
+    /// A chunk size of zero returns [ResampleError::InvalidChunkSize].
+    /// For resamplers where the chunk size is given by other parameters,
+    /// such as [FftFixedInOut], this returns [ResampleError::ChunkSizeNotAdjustable].
+    fn set_chunk_size(&mut self, _chunk_size: usize) -> ResampleResult<()> {
+        Err(ResampleError::ChunkSizeNotAdjustable)
+    }
+
+    /// Reset the resampler, and then load its history with the input frames that precede the stream.
+    ///
+    /// Without a history, the resampler starts from silence, and the first output frames
+    /// contain the transient of the filter running into the signal.
+    /// When rendering a region of a longer recording, give the frames just before the region
+    /// as `history`, so that the first output chunk is already fully valid.
+    /// The output delay is the same as after a reset.
+    ///
+    /// The history is given as one slice per channel, with the last frame just before the stream.
+    /// Only the last frames that fit in the internal buffers are used,
+    /// and a shorter history is padded with zeros at the start.
+    /// The channels may have different lengths.
+    ///
+    /// Only the asynchronous resamplers keep their history as input frames.
+    /// The others return [ResampleError::HistoryNotSupported].
+    fn set_history<V: AsRef<[T]>>(&mut self, _history: &[V]) -> ResampleResult<()> {
+        Err(ResampleError::HistoryNotSupported)
+    }
+
+    /// Get the approximate heap memory used by the resampler, in bytes.
+    ///
+    /// This includes the filter coefficients, the input history and the FFT buffers,
+    /// as well as the buffers of any wrappers, but not the resampler struct itself.
+    /// The memory used by the FFT plans is an estimate, since it is allocated inside `realfft`.
+    /// The value can grow when calling methods that allocate,
+    /// such as [set_chunk_size](Resampler::set_chunk_size).
+    ///
+    /// The default implementation returns 0, meaning that the memory usage is unknown.
+    fn memory_usage(&self) -> usize {
+        0
+    }
+
+    /// Get a description of the resampler, with the algorithm, quality parameters,
+    /// ratio, chunk sizes and number of channels, for example for logging.
+    /// Wrappers add their names to the description of the resampler they wrap.
+    ///
+    /// The default implementation describes a [ResamplerAlgorithm::Custom] resampler
+    /// with an unknown ratio.
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(self, "Custom", ResamplerAlgorithm::Custom, None)
+    }
+
     /// Reset the resampler state and clear all internal buffers.
+    /// Wrap the resampler in a [SoftMute] to fade the output in and out around resets.
     fn reset(&mut self);
 }
 
+/// Implement [Resampler] for a pointer type, by forwarding all methods to the pointed-to resampler.
+/// All methods are forwarded, including those with default implementations,
+/// so that the specialized versions of the resampler are used.
+macro_rules! forward_resampler {
+    ($pointer:ty) => {
+        impl<T, R> Resampler<T> for $pointer
+        where
+            T: Sample,
+            R: Resampler<T> + ?Sized,
+        {
+            fn process<V: AsRef<[T]>>(
+                &mut self,
+                wave_in: &[V],
+                active_channels_mask: Option<&[bool]>,
+            ) -> ResampleResult<Vec<Vec<T>>> {
+                (**self).process(wave_in, active_channels_mask)
+            }
+
+            fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+                &mut self,
+                wave_in: &[Vin],
+                wave_out: &mut [Vout],
+                active_channels_mask: Option<&[bool]>,
+            ) -> ResampleResult<(usize, usize)> {
+                (**self).process_into_buffer(wave_in, wave_out, active_channels_mask)
+            }
+
+            fn process_split_into_buffer<Vout: AsMut<[T]>>(
+                &mut self,
+                wave_in: &[(&[T], &[T])],
+                wave_out: &mut [Vout],
+                active_channels_mask: Option<&[bool]>,
+            ) -> ResampleResult<(usize, usize)> {
+                (**self).process_split_into_buffer(wave_in, wave_out, active_channels_mask)
+            }
+
+            fn scratch_len(&self) -> usize {
+                (**self).scratch_len()
+            }
+
+            fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+                &mut self,
+                wave_in: &[Vin],
+                wave_out: &mut [Vout],
+                active_channels_mask: Option<&[bool]>,
+                scratch: &mut [T],
+            ) -> ResampleResult<(usize, usize)> {
+                (**self).process_into_buffer_with_scratch(
+                    wave_in,
+                    wave_out,
+                    active_channels_mask,
+                    scratch,
+                )
+            }
+
+            fn validate_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+                &self,
+                wave_in: &[Vin],
+                wave_out: &mut [Vout],
+                active_channels_mask: Option<&[bool]>,
+            ) -> ResampleResult<()> {
+                (**self).validate_buffers(wave_in, wave_out, active_channels_mask)
+            }
+
+            fn process_partial_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+                &mut self,
+                wave_in: Option<&[Vin]>,
+                wave_out: &mut [Vout],
+                active_channels_mask: Option<&[bool]>,
+            ) -> ResampleResult<(usize, usize)> {
+                (**self).process_partial_into_buffer(wave_in, wave_out, active_channels_mask)
+            }
+
+            fn process_partial<V: AsRef<[T]>>(
+                &mut self,
+                wave_in: Option<&[V]>,
+                active_channels_mask: Option<&[bool]>,
+            ) -> ResampleResult<Vec<Vec<T>>> {
+                (**self).process_partial(wave_in, active_channels_mask)
+            }
+
+            fn input_buffer_allocate(&self) -> Vec<Vec<T>> {
+                (**self).input_buffer_allocate()
+            }
+
+            fn input_frames_max(&self) -> usize {
+                (**self).input_frames_max()
+            }
+
+            fn input_frames_next(&self) -> usize {
+                (**self).input_frames_next()
+            }
+
+            fn nbr_channels(&self) -> usize {
+                (**self).nbr_channels()
+            }
+
+            fn output_buffer_allocate(&self) -> Vec<Vec<T>> {
+                (**self).output_buffer_allocate()
+            }
+
+            fn output_frames_max(&self) -> usize {
+                (**self).output_frames_max()
+            }
+
+            fn output_frames_next(&self) -> usize {
+                (**self).output_frames_next()
+            }
+
+            fn output_delay(&self) -> usize {
+                (**self).output_delay()
+            }
+
+            fn input_delay(&self) -> usize {
+                (**self).input_delay()
+            }
+
+            fn buffering_delay(&self) -> usize {
+                (**self).buffering_delay()
+            }
+
+            fn output_delay_seconds(&self, sample_rate_output: f64) -> f64 {
+                (**self).output_delay_seconds(sample_rate_output)
+            }
+
+            fn input_delay_seconds(&self, sample_rate_input: f64) -> f64 {
+                (**self).input_delay_seconds(sample_rate_input)
+            }
+
+            fn latency_seconds(&self, sample_rate_input: f64) -> f64 {
+                (**self).latency_seconds(sample_rate_input)
+            }
+
+            fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+                (**self).set_resample_ratio(new_ratio, ramp)
+            }
+
+            fn set_resample_ratio_relative(
+                &mut self,
+                rel_ratio: f64,
+                ramp: bool,
+            ) -> ResampleResult<()> {
+                (**self).set_resample_ratio_relative(rel_ratio, ramp)
+            }
+
+            fn set_max_resample_ratio_relative(
+                &mut self,
+                max_resample_ratio_relative: f64,
+            ) -> ResampleResult<()> {
+                (**self).set_max_resample_ratio_relative(max_resample_ratio_relative)
+            }
+
+            fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+                (**self).set_chunk_size(chunk_size)
+            }
+
+            fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+                (**self).set_history(history)
+            }
+
+            fn memory_usage(&self) -> usize {
+                (**self).memory_usage()
+            }
+
+            fn config(&self) -> ResamplerConfig {
+                (**self).config()
+            }
+
+            fn reset(&mut self) {
+                (**self).reset()
+            }
+        }
+    };
+}
+
+// A boxed resampler, or a mutable reference to one, can be used wherever a resampler is expected,
+// for example to give a resampler to a wrapper without moving it.
+forward_resampler!(Box<R>);
+forward_resampler!(&mut R);
+
 use crate as rubato;
 /// A macro for implementing wrapper traits for when a [Resampler] must be object safe.
 /// The wrapper trait locks the generic type parameters or the [Resampler] trait to specific types,
@@ -454,6 +1117,34 @@ macro_rules! implement_resampler {
                 active_channels_mask: Option<&[bool]>,
             ) -> rubato::ResampleResult<(usize, usize)>;
 
+            /// Refer to [Resampler::process_split_into_buffer]
+            fn process_split_into_buffer(
+                &mut self,
+                wave_in: &[(&[T], &[T])],
+                wave_out: $out_type,
+                active_channels_mask: Option<&[bool]>,
+            ) -> rubato::ResampleResult<(usize, usize)>;
+
+            /// Refer to [Resampler::scratch_len]
+            fn scratch_len(&self) -> usize;
+
+            /// Refer to [Resampler::process_into_buffer_with_scratch]
+            fn process_into_buffer_with_scratch(
+                &mut self,
+                wave_in: $in_type,
+                wave_out: $out_type,
+                active_channels_mask: Option<&[bool]>,
+                scratch: &mut [T],
+            ) -> rubato::ResampleResult<(usize, usize)>;
+
+            /// Refer to [Resampler::validate_buffers]
+            fn validate_buffers(
+                &self,
+                wave_in: $in_type,
+                wave_out: $out_type,
+                active_channels_mask: Option<&[bool]>,
+            ) -> rubato::ResampleResult<()>;
+
             /// Refer to [Resampler::process_partial]
             fn process_partial(
                 &mut self,
@@ -485,11 +1176,41 @@ macro_rules! implement_resampler {
             /// Refer to [Resampler::output_delay]
             fn output_delay(&self) -> usize;
 
+            /// Refer to [Resampler::input_delay]
+            fn input_delay(&self) -> usize;
+
+            /// Refer to [Resampler::buffering_delay]
+            fn buffering_delay(&self) -> usize;
+
+            /// Refer to [Resampler::output_delay_seconds]
+            fn output_delay_seconds(&self, sample_rate_output: f64) -> f64;
+
+            /// Refer to [Resampler::input_delay_seconds]
+            fn input_delay_seconds(&self, sample_rate_input: f64) -> f64;
+
+            /// Refer to [Resampler::latency_seconds]
+            fn latency_seconds(&self, sample_rate_input: f64) -> f64;
+
             /// Refer to [Resampler::set_resample_ratio]
             fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> rubato::ResampleResult<()>;
 
             /// Refer to [Resampler::set_resample_ratio_relative]
             fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> rubato::ResampleResult<()>;
+
+            /// Refer to [Resampler::set_max_resample_ratio_relative]
+            fn set_max_resample_ratio_relative(&mut self, max_resample_ratio_relative: f64) -> rubato::ResampleResult<()>;
+
+            /// Refer to [Resampler::set_chunk_size]
+            fn set_chunk_size(&mut self, chunk_size: usize) -> rubato::ResampleResult<()>;
+
+            /// Refer to [Resampler::set_history]
+            fn set_history(&mut self, history: $in_type) -> rubato::ResampleResult<()>;
+
+            /// Refer to [Resampler::memory_usage]
+            fn memory_usage(&self) -> usize;
+
+            /// Refer to [Resampler::config]
+            fn config(&self) -> rubato::ResamplerConfig;
         }
 
         impl<T, U> $trait_name<T> for U
@@ -528,6 +1249,38 @@ macro_rules! implement_resampler {
                 )
             }
 
+            fn process_split_into_buffer(
+                &mut self,
+                wave_in: &[(&[T], &[T])],
+                wave_out: $out_type,
+                active_channels_mask: Option<&[bool]>,
+            ) -> rubato::ResampleResult<(usize, usize)> {
+                rubato::Resampler::process_split_into_buffer(self, wave_in, wave_out, active_channels_mask)
+            }
+
+            fn scratch_len(&self) -> usize {
+                rubato::Resampler::scratch_len(self)
+            }
+
+            fn process_into_buffer_with_scratch(
+                &mut self,
+                wave_in: $in_type,
+                wave_out: $out_type,
+                active_channels_mask: Option<&[bool]>,
+                scratch: &mut [T],
+            ) -> rubato::ResampleResult<(usize, usize)> {
+                rubato::Resampler::process_into_buffer_with_scratch(self, wave_in, wave_out, active_channels_mask, scratch)
+            }
+
+            fn validate_buffers(
+                &self,
+                wave_in: $in_type,
+                wave_out: $out_type,
+                active_channels_mask: Option<&[bool]>,
+            ) -> rubato::ResampleResult<()> {
+                rubato::Resampler::validate_buffers(self, wave_in, wave_out, active_channels_mask)
+            }
+
             fn process_partial(
                 &mut self,
                 wave_in: Option<$in_type>,
@@ -556,6 +1309,26 @@ macro_rules! implement_resampler {
                 rubato::Resampler::output_delay(self)
             }
 
+            fn input_delay(&self) -> usize {
+                rubato::Resampler::input_delay(self)
+            }
+
+            fn buffering_delay(&self) -> usize {
+                rubato::Resampler::buffering_delay(self)
+            }
+
+            fn output_delay_seconds(&self, sample_rate_output: f64) -> f64 {
+                rubato::Resampler::output_delay_seconds(self, sample_rate_output)
+            }
+
+            fn input_delay_seconds(&self, sample_rate_input: f64) -> f64 {
+                rubato::Resampler::input_delay_seconds(self, sample_rate_input)
+            }
+
+            fn latency_seconds(&self, sample_rate_input: f64) -> f64 {
+                rubato::Resampler::latency_seconds(self, sample_rate_input)
+            }
+
             fn nbr_channels(&self) -> usize {
                 rubato::Resampler::nbr_channels(self)
             }
@@ -575,6 +1348,26 @@ macro_rules! implement_resampler {
             fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> rubato::ResampleResult<()> {
                 rubato::Resampler::set_resample_ratio_relative(self, rel_ratio, ramp)
             }
+
+            fn set_max_resample_ratio_relative(&mut self, max_resample_ratio_relative: f64) -> rubato::ResampleResult<()> {
+                rubato::Resampler::set_max_resample_ratio_relative(self, max_resample_ratio_relative)
+            }
+
+            fn set_chunk_size(&mut self, chunk_size: usize) -> rubato::ResampleResult<()> {
+                rubato::Resampler::set_chunk_size(self, chunk_size)
+            }
+
+            fn set_history(&mut self, history: $in_type) -> rubato::ResampleResult<()> {
+                rubato::Resampler::set_history(self, history)
+            }
+
+            fn memory_usage(&self) -> usize {
+                rubato::Resampler::memory_usage(self)
+            }
+
+            fn config(&self) -> rubato::ResamplerConfig {
+                rubato::Resampler::config(self)
+            }
         }
     }
 }
@@ -586,6 +1379,99 @@ fn update_mask_from_buffers(mask: &mut [bool]) {
     mask.iter_mut().for_each(|v| *v = true);
 }
 
+/// Describes if the input and output buffers contain all channels, or only the active ones.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BufferLayout {
+    compact_input: bool,
+    compact_output: bool,
+}
+
+impl BufferLayout {
+    /// Iterate over the active channels of the mask, as tuples of
+    /// `(channel, input buffer index, output buffer index)`.
+    pub(crate) fn active_channels(
+        self,
+        mask: &[bool],
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        mask.iter()
+            .enumerate()
+            .filter(|(_, active)| **active)
+            .enumerate()
+            .map(move |(idx, (chan, _))| {
+                let chan_in = if self.compact_input { idx } else { chan };
+                let chan_out = if self.compact_output { idx } else { chan };
+                (chan, chan_in, chan_out)
+            })
+    }
+}
+
+/// The input channels given to a resampler.
+/// Each channel is either a contiguous slice, or split in two segments.
+pub(crate) trait InputChannels<T> {
+    /// Get the number of channels.
+    fn len(&self) -> usize;
+
+    /// Get the number of frames of channel `chan`.
+    fn frames(&self, chan: usize) -> usize;
+
+    /// Copy frames of channel `chan`, starting from frame `start`, to fill `dst`.
+    fn copy_frames(&self, chan: usize, start: usize, dst: &mut [T]);
+}
+
+/// Input channels that are each given as a contiguous slice.
+pub(crate) struct Contiguous<'a, V>(pub(crate) &'a [V]);
+
+impl<'a, T, V> InputChannels<T> for Contiguous<'a, V>
+where
+    T: Copy,
+    V: AsRef<[T]>,
+{
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn frames(&self, chan: usize) -> usize {
+        self.0[chan].as_ref().len()
+    }
+
+    fn copy_frames(&self, chan: usize, start: usize, dst: &mut [T]) {
+        dst.copy_from_slice(&self.0[chan].as_ref()[start..start + dst.len()]);
+    }
+}
+
+/// Input channels that are each given as two segments, where the frames of the first
+/// are followed by those of the second.
+pub(crate) struct Split<'a, 'b, T>(pub(crate) &'a [(&'b [T], &'b [T])]);
+
+impl<'a, 'b, T> InputChannels<T> for Split<'a, 'b, T>
+where
+    T: Copy,
+{
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn frames(&self, chan: usize) -> usize {
+        self.0[chan].0.len() + self.0[chan].1.len()
+    }
+
+    fn copy_frames(&self, chan: usize, start: usize, dst: &mut [T]) {
+        let (first, second) = self.0[chan];
+        let end = start + dst.len();
+        if end <= first.len() {
+            dst.copy_from_slice(&first[start..end]);
+        } else if start >= first.len() {
+            dst.copy_from_slice(&second[start - first.len()..end - first.len()]);
+        } else {
+            let split = first.len() - start;
+            dst[..split].copy_from_slice(&first[start..]);
+            dst[split..].copy_from_slice(&second[..end - first.len()]);
+        }
+    }
+}
+
+/// Check that the buffers are valid, and determine their layout.
+/// Each buffer may either contain all channels, or only the active channels.
 pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
     wave_in: &[Vin],
     wave_out: &mut [Vout],
@@ -593,21 +1479,56 @@ pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
     channels: usize,
     min_input_len: usize,
     min_output_len: usize,
-) -> ResampleResult<()> {
-    if wave_in.len() != channels {
+) -> ResampleResult<BufferLayout>
+where
+    T: Copy,
+{
+    validate_channels(
+        &Contiguous(wave_in),
+        wave_out,
+        mask,
+        channels,
+        min_input_len,
+        min_output_len,
+    )
+}
+
+/// Check that the buffers are valid, and determine their layout, see [validate_buffers].
+pub(crate) fn validate_channels<T, I: InputChannels<T>, Vout: AsMut<[T]>>(
+    wave_in: &I,
+    wave_out: &mut [Vout],
+    mask: &[bool],
+    channels: usize,
+    min_input_len: usize,
+    min_output_len: usize,
+) -> ResampleResult<BufferLayout> {
+    if mask.len() != channels {
+        return Err(ResampleError::WrongNumberOfMaskChannels {
+            expected: channels,
+            actual: mask.len(),
+        });
+    }
+    let nbr_active = mask.iter().filter(|active| **active).count();
+    let compact_input = wave_in.len() != channels && wave_in.len() == nbr_active;
+    if wave_in.len() != channels && !compact_input {
         return Err(ResampleError::WrongNumberOfInputChannels {
             expected: channels,
             actual: wave_in.len(),
         });
     }
-    if mask.len() != channels {
-        return Err(ResampleError::WrongNumberOfMaskChannels {
+    let compact_output = wave_out.len() != channels && wave_out.len() == nbr_active;
+    if wave_out.len() != channels && !compact_output {
+        return Err(ResampleError::WrongNumberOfOutputChannels {
             expected: channels,
-            actual: wave_in.len(),
+            actual: wave_out.len(),
         });
     }
-    for (chan, wave_in) in wave_in.iter().enumerate().filter(|(chan, _)| mask[*chan]) {
-        let actual_len = wave_in.as_ref().len();
+    let layout = BufferLayout {
+        compact_input,
+        compact_output,
+    };
+    for (chan, chan_in, _) in layout.active_channels(mask) {
+        let actual_len = wave_in.frames(chan_in);
         if actual_len < min_input_len {
             return Err(ResampleError::InsufficientInputBufferSize {
                 channel: chan,
@@ -616,18 +1537,8 @@ pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
             });
         }
     }
-    if wave_out.len() != channels {
-        return Err(ResampleError::WrongNumberOfOutputChannels {
-            expected: channels,
-            actual: wave_out.len(),
-        });
-    }
-    for (chan, wave_out) in wave_out
-        .iter_mut()
-        .enumerate()
-        .filter(|(chan, _)| mask[*chan])
-    {
-        let actual_len = wave_out.as_mut().len();
+    for (chan, _, chan_out) in layout.active_channels(mask) {
+        let actual_len = wave_out[chan_out].as_mut().len();
         if actual_len < min_output_len {
             return Err(ResampleError::InsufficientOutputBufferSize {
                 channel: chan,
@@ -636,14 +1547,221 @@ pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
             });
         }
     }
+    Ok(layout)
+}
+
+/// Validate the buffers for the mask of a call, see [validate_channels],
+/// and then store the mask in `channel_mask`.
+/// The mask is only stored if the buffers are valid, so that a call that returns
+/// an error leaves the resampler unchanged, and can be retried with corrected buffers.
+/// Without a mask, all channels are marked as active.
+pub(crate) fn validate_and_update_mask<T, I: InputChannels<T>, Vout: AsMut<[T]>>(
+    channel_mask: &mut [bool],
+    active_channels_mask: Option<&[bool]>,
+    wave_in: &I,
+    wave_out: &mut [Vout],
+    min_input_len: usize,
+    min_output_len: usize,
+) -> ResampleResult<BufferLayout> {
+    let channels = channel_mask.len();
+    match active_channels_mask {
+        Some(mask) => {
+            let layout = validate_channels(
+                wave_in,
+                wave_out,
+                mask,
+                channels,
+                min_input_len,
+                min_output_len,
+            )?;
+            channel_mask.copy_from_slice(mask);
+            Ok(layout)
+        }
+        None => {
+            update_mask_from_buffers(channel_mask);
+            validate_channels(
+                wave_in,
+                wave_out,
+                channel_mask,
+                channels,
+                min_input_len,
+                min_output_len,
+            )
+        }
+    }
+}
+
+/// Copy the last frames of `history` into the region `start..start + history_len`
+/// of each internal buffer, padding with zeros at the start when the history is shorter.
+/// This is the region that the asynchronous resamplers keep as history between chunks.
+/// The number of channels must be checked with [validate_history] first.
+pub(crate) fn load_history<T: Sample, V: AsRef<[T]>>(
+    buffers: &mut [Vec<T>],
+    start: usize,
+    history_len: usize,
+    history: &[V],
+) {
+    for (buf, chan_history) in buffers.iter_mut().zip(history.iter()) {
+        let chan_history = chan_history.as_ref();
+        let frames = chan_history.len().min(history_len);
+        let region = &mut buf[start..start + history_len];
+        region[..history_len - frames].fill(T::zero());
+        region[history_len - frames..]
+            .copy_from_slice(&chan_history[chan_history.len() - frames..]);
+    }
+}
+
+/// Get the heap memory of a vector in bytes, from its capacity.
+pub(crate) fn heap_size<T>(values: &Vec<T>) -> usize {
+    values.capacity() * std::mem::size_of::<T>()
+}
+
+/// Get the heap memory of a vector of vectors in bytes, including the inner vectors.
+pub(crate) fn heap_size_nested<T>(values: &Vec<Vec<T>>) -> usize {
+    heap_size(values) + values.iter().map(heap_size).sum::<usize>()
+}
+
+/// Get the heap memory in bytes of a buffer with `frames` samples per channel,
+/// saturating instead of overflowing for absurd sizes.
+pub(crate) fn buffer_size<T>(nbr_channels: usize, frames: usize) -> usize {
+    nbr_channels
+        .saturating_mul(frames)
+        .saturating_mul(std::mem::size_of::<T>())
+}
+
+/// Check an estimated memory requirement against the limit of a `try_new_with_limit` constructor.
+pub(crate) fn check_memory_limit(
+    required: usize,
+    limit: usize,
+) -> Result<(), ResamplerConstructionError> {
+    debug!("Estimated memory {} bytes, limit {} bytes", required, limit);
+    if required > limit {
+        return Err(ResamplerConstructionError::MemoryLimitExceeded { required, limit });
+    }
+    Ok(())
+}
+
+/// Estimate the heap memory of a real FFT plan of length `len` in bytes.
+/// The plans are allocated inside `realfft`, and hold about one complex twiddle factor per frame.
+pub(crate) fn fft_plan_size<T>(len: usize) -> usize {
+    len * std::mem::size_of::<num_complex::Complex<T>>()
+}
+
+/// Check that a scratch buffer has at least the given length.
+pub(crate) fn validate_scratch<T>(scratch_len: usize, scratch: &[T]) -> ResampleResult<()> {
+    if scratch.len() < scratch_len {
+        return Err(ResampleError::InsufficientScratchSize {
+            expected: scratch_len,
+            actual: scratch.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Process a chunk with the resampler inside a wrapper,
+/// using the scratch buffer that was given to the wrapper, if any.
+pub(crate) fn process_wrapped<T, R, Vin, Vout>(
+    resampler: &mut R,
+    wave_in: &[Vin],
+    wave_out: &mut [Vout],
+    active_channels_mask: Option<&[bool]>,
+    scratch: Option<&mut [T]>,
+) -> ResampleResult<(usize, usize)>
+where
+    T: Sample,
+    R: Resampler<T>,
+    Vin: AsRef<[T]>,
+    Vout: AsMut<[T]>,
+{
+    match scratch {
+        Some(scratch) => resampler.process_into_buffer_with_scratch(
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            scratch,
+        ),
+        None => resampler.process_into_buffer(wave_in, wave_out, active_channels_mask),
+    }
+}
+
+/// Get the number of input frames that the fixed output resamplers step over
+/// when producing `chunk_size` frames, while the ratio changes from `resample_ratio` to `target_ratio`.
+/// The step is the inverse of the ratio, and it changes linearly over the chunk,
+/// from `1/resample_ratio` to `1/target_ratio` at the last frame.
+pub(crate) fn ramp_input_frames(chunk_size: usize, resample_ratio: f64, target_ratio: f64) -> f64 {
+    let chunk_size = chunk_size as f64;
+    0.5 * (chunk_size - 1.0) / resample_ratio + 0.5 * (chunk_size + 1.0) / target_ratio
+}
+
+/// Check that a history for [Resampler::set_history] has one slice per channel.
+pub(crate) fn validate_history<V>(nbr_channels: usize, history: &[V]) -> ResampleResult<()> {
+    if history.len() != nbr_channels {
+        return Err(ResampleError::WrongNumberOfInputChannels {
+            expected: nbr_channels,
+            actual: history.len(),
+        });
+    }
     Ok(())
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 pub mod tests {
     use crate::VecResampler;
+    use crate::{FastFixedIn, PolynomialDegree, Sample};
     use crate::{FftFixedIn, FftFixedInOut, FftFixedOut};
     use crate::{SincFixedIn, SincFixedOut};
+    use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+
+    /// Make the parameters of a short sinc filter, that is quick to build in the tests.
+    pub fn make_sinc_params(interpolation: SincInterpolationType) -> SincInterpolationParameters {
+        SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
+        }
+    }
+
+    /// Make a cheap asynchronous resampler, to be used as the inner resampler
+    /// in the tests of the wrappers.
+    pub fn make_fast_resampler<T: Sample>(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> FastFixedIn<T> {
+        FastFixedIn::<T>::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            PolynomialDegree::Cubic,
+            chunk_size,
+            nbr_channels,
+        )
+        .unwrap()
+    }
+
+    /// Process a chunk of the requested length, where every sample is one.
+    pub fn process_ones<T: Sample, R: crate::Resampler<T>>(resampler: &mut R) -> Vec<Vec<T>> {
+        let waves = vec![vec![T::one(); resampler.input_frames_next()]; resampler.nbr_channels()];
+        resampler.process(&waves, None).unwrap()
+    }
+
+    /// Process the same chunks with a reference resampler and with a wrapped one,
+    /// and check that the outputs are identical.
+    pub fn assert_same_output<T, A, B>(reference: &mut A, resampler: &mut B, chunks: &[Vec<Vec<T>>])
+    where
+        T: Sample,
+        A: crate::Resampler<T>,
+        B: crate::Resampler<T>,
+    {
+        for (n, waves) in chunks.iter().enumerate() {
+            let expected = reference.process(waves, None).unwrap();
+            let out = resampler.process(waves, None).unwrap();
+            assert_eq!(expected, out, "chunk {}", n);
+        }
+    }
 
     // This tests that a VecResampler can be boxed.
     #[test]
@@ -662,6 +1780,690 @@ pub mod tests {
         resampler.process(&waves, None).unwrap()
     }
 
+    fn process_stage<R: crate::Resampler<f64>>(mut resampler: R) -> (usize, usize) {
+        let frames = resampler.input_frames_next();
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let mut waves_out = vec![vec![0.0f64; resampler.output_frames_max()]; 2];
+        resampler
+            .process_into_buffer(&waves, &mut waves_out, None)
+            .unwrap()
+    }
+
+    // This tests that boxed resamplers and mutable references can be used as resamplers.
+    #[test]
+    fn pointer_resamplers() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 88200, 1024, 2, 2).unwrap();
+        assert_eq!(process_stage(&mut resampler), (1024, 2048));
+        let wrapped = crate::SoftMute::new(&mut resampler, 64);
+        assert_eq!(crate::Resampler::<f64>::config(&wrapped).name, "FftFixedIn");
+        assert_eq!(process_stage(wrapped), (1024, 2048));
+        crate::Resampler::<f64>::reset(&mut resampler);
+
+        let boxed = Box::new(FftFixedOut::<f64>::new(44100, 88200, 2048, 2, 2).unwrap());
+        assert_eq!(process_stage(boxed), (1024, 2048));
+    }
+
+    fn check_split_input<R: crate::Resampler<f64>>(mut reference: R, mut resampler: R) {
+        let wave: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut pos = 0;
+        for chunk in 0..6 {
+            let frames = reference.input_frames_next();
+            let input = &wave[pos..pos + frames];
+            let expected = reference.process(&[input, input], None).unwrap();
+            // Split at different places, including at the ends
+            let split = [0, 1, frames / 3, frames - 1, frames, frames / 2][chunk];
+            let wave_in = [(&input[..split], &input[split..]); 2];
+            let mut wave_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+            let (frames_in, frames_out) = resampler
+                .process_split_into_buffer(&wave_in, &mut wave_out, None)
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            assert_eq!(expected[1][..], wave_out[1][..frames_out]);
+            pos += frames;
+        }
+    }
+
+    #[test]
+    fn split_input() {
+        use crate::{FastFixedIn, FastFixedOut, FftLowLatency, PolynomialDegree, SoftMute};
+        use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+        let params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        let sinc_in = || SincFixedIn::<f64>::new(1.1, 1.0, params(), 500, 2).unwrap();
+        check_split_input(sinc_in(), sinc_in());
+        let sinc_out = || SincFixedOut::<f64>::new(1.1, 1.0, params(), 500, 2).unwrap();
+        check_split_input(sinc_out(), sinc_out());
+        let fast_in =
+            || FastFixedIn::<f64>::new(0.9, 1.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_split_input(fast_in(), fast_in());
+        let fast_out =
+            || FastFixedOut::<f64>::new(0.9, 1.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_split_input(fast_out(), fast_out());
+        let fft_in = || FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        check_split_input(fft_in(), fft_in());
+        let fft_out = || FftFixedOut::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        check_split_input(fft_out(), fft_out());
+        let fft_inout = || FftFixedInOut::<f64>::new(44100, 48000, 500, 2).unwrap();
+        check_split_input(fft_inout(), fft_inout());
+        let low_latency = || FftLowLatency::<f64>::new(48000, 96000, 64, 4, 2).unwrap();
+        check_split_input(low_latency(), low_latency());
+        // Wrappers use the default implementation
+        let wrapped = || SoftMute::new(fft_in(), 64);
+        check_split_input(wrapped(), wrapped());
+    }
+
+    fn check_retry_after_error<R: crate::Resampler<f64>>(mut reference: R, mut resampler: R) {
+        use crate::ResampleError;
+        let wave: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut pos = 0;
+        for _ in 0..4 {
+            let frames = reference.input_frames_next();
+            let input = &wave[pos..pos + frames];
+            let expected = reference.process(&[input, input], None).unwrap();
+            // Fail on the last channel, with a mask of the wrong length, and with short input
+            let mut short_out = vec![vec![0.0; resampler.output_frames_max()], vec![0.0; 1]];
+            let err = resampler
+                .process_into_buffer(&[input, input], &mut short_out, None)
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                ResampleError::InsufficientOutputBufferSize { channel: 1, .. }
+            ));
+            let mut wave_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+            let err = resampler
+                .process_into_buffer(&[input, input], &mut wave_out, Some(&[true]))
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                ResampleError::WrongNumberOfMaskChannels { .. }
+            ));
+            let err = resampler
+                .process_into_buffer(&[input, &input[1..]], &mut wave_out, None)
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                ResampleError::InsufficientInputBufferSize { channel: 1, .. }
+            ));
+            // The retry gives the same result as if the errors never happened
+            let (frames_in, frames_out) = resampler
+                .process_into_buffer(&[input, input], &mut wave_out, None)
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            assert_eq!(expected[1][..], wave_out[1][..frames_out]);
+            pos += frames;
+        }
+    }
+
+    #[test]
+    fn retry_after_error() {
+        use crate::{ConcealDropouts, FastFixedIn, FastFixedOut, FftLowLatency, PolynomialDegree};
+        use crate::{SanitizeInput, SincInterpolationParameters, SincInterpolationType};
+        use crate::{SoftMute, WindowFunction};
+        let params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        let sinc_in = || SincFixedIn::<f64>::new(1.1, 1.0, params(), 500, 2).unwrap();
+        check_retry_after_error(sinc_in(), sinc_in());
+        let sinc_out = || SincFixedOut::<f64>::new(1.1, 1.0, params(), 500, 2).unwrap();
+        check_retry_after_error(sinc_out(), sinc_out());
+        let fast_in =
+            || FastFixedIn::<f64>::new(0.9, 1.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_retry_after_error(fast_in(), fast_in());
+        let fast_out =
+            || FastFixedOut::<f64>::new(0.9, 1.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_retry_after_error(fast_out(), fast_out());
+        let fft_in = || FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        check_retry_after_error(fft_in(), fft_in());
+        let fft_out = || FftFixedOut::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        check_retry_after_error(fft_out(), fft_out());
+        let fft_inout = || FftFixedInOut::<f64>::new(44100, 48000, 500, 2).unwrap();
+        check_retry_after_error(fft_inout(), fft_inout());
+        let low_latency = || FftLowLatency::<f64>::new(48000, 96000, 64, 4, 2).unwrap();
+        check_retry_after_error(low_latency(), low_latency());
+        let soft_mute = || SoftMute::new(fft_in(), 64);
+        check_retry_after_error(soft_mute(), soft_mute());
+        let sanitize = || SanitizeInput::new(fft_in());
+        check_retry_after_error(sanitize(), sanitize());
+        let conceal = || ConcealDropouts::new(fft_in(), 64);
+        check_retry_after_error(conceal(), conceal());
+    }
+
+    #[cfg(not(feature = "forbid_unsafe"))]
+    fn check_wrapped_scratch<R: crate::Resampler<f64>>(mut resampler: R) {
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let scratch_len = crate::Resampler::scratch_len(&reference);
+        assert!(scratch_len > 0);
+        assert_eq!(resampler.scratch_len(), scratch_len);
+        let wave: Vec<f64> = (0..4096).map(|n| (n as f64 * 0.05).sin()).collect();
+        let mut scratch = vec![0.0; scratch_len];
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        let err = resampler
+            .process_into_buffer_with_scratch(
+                &[&wave[..1024], &wave[..1024]],
+                &mut out,
+                None,
+                &mut scratch[..scratch_len - 1],
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), 214);
+        for chunk in wave.chunks_exact(1024) {
+            let expected =
+                crate::Resampler::process(&mut reference, &[chunk, chunk], None).unwrap();
+            let (_, frames_out) = resampler
+                .process_into_buffer_with_scratch(&[chunk, chunk], &mut out, None, &mut scratch)
+                .unwrap();
+            assert_eq!(expected[0][..], out[0][..frames_out]);
+        }
+    }
+
+    // Without unsafe code, the FFT resamplers can't use a scratch buffer
+    #[cfg(not(feature = "forbid_unsafe"))]
+    #[test]
+    fn wrapped_scratch() {
+        use crate::{ConcealDropouts, FixedChunkResampler, FlushDenormals, PitchShift};
+        use crate::{Reversible, SanitizeInput, SoftMute, Timestamped};
+        let fft_in = || FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        check_wrapped_scratch(SoftMute::new(fft_in(), 64));
+        check_wrapped_scratch(SanitizeInput::new(fft_in()));
+        check_wrapped_scratch(ConcealDropouts::new(fft_in(), 64));
+        check_wrapped_scratch(FlushDenormals::new(fft_in()));
+        check_wrapped_scratch(PitchShift::new(fft_in()));
+        check_wrapped_scratch(Reversible::new(fft_in()));
+        check_wrapped_scratch(Timestamped::<_, ()>::new(fft_in(), 44100.0));
+        // The fixed chunk sizes delay the output, so only the length is checked
+        let fixed_chunk = FixedChunkResampler::new(fft_in(), 441, 480);
+        assert_eq!(
+            fixed_chunk.scratch_len(),
+            crate::Resampler::scratch_len(&fft_in())
+        );
+    }
+
+    fn check_no_panics<R: crate::Resampler<f64>>(mut resampler: R) {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(1234);
+        let ratios = [
+            0.0,
+            -1.0,
+            f64::NAN,
+            f64::INFINITY,
+            1.0e-9,
+            1.0e9,
+            0.5,
+            0.99,
+            1.01,
+            2.0,
+        ];
+        for _ in 0..1000 {
+            match rng.gen_range(0..6) {
+                0 => {
+                    let ratio = ratios[rng.gen_range(0..ratios.len())];
+                    let _ = resampler.set_resample_ratio_relative(ratio, rng.gen());
+                }
+                1 => {
+                    let ratio = ratios[rng.gen_range(0..ratios.len())];
+                    let _ = resampler.set_resample_ratio(ratio, rng.gen());
+                }
+                2 => {
+                    let _ = resampler.set_chunk_size(rng.gen_range(0..2000));
+                }
+                3 => resampler.reset(),
+                _ => {
+                    // Buffers with random sizes and numbers of channels, and random masks
+                    let frames_in = rng.gen_range(0..2 * resampler.input_frames_max() + 2);
+                    let frames_out = rng.gen_range(0..2 * resampler.output_frames_max() + 2);
+                    let wave_in = vec![vec![0.5; frames_in]; rng.gen_range(0..4)];
+                    let mut wave_out = vec![vec![0.0; frames_out]; rng.gen_range(0..4)];
+                    let mask = (0..rng.gen_range(0..4))
+                        .map(|_| rng.gen())
+                        .collect::<Vec<bool>>();
+                    let mask = if rng.gen() { Some(&mask[..]) } else { None };
+                    let split = rng.gen_range(0..frames_in + 1);
+                    let wave_split = wave_in
+                        .iter()
+                        .map(|wave| wave.split_at(split))
+                        .collect::<Vec<_>>();
+                    let _ = match rng.gen_range(0..3) {
+                        0 => resampler.process_into_buffer(&wave_in, &mut wave_out, mask),
+                        1 => resampler.process_split_into_buffer(&wave_split, &mut wave_out, mask),
+                        _ => resampler.process_partial_into_buffer(
+                            Some(&wave_in),
+                            &mut wave_out,
+                            mask,
+                        ),
+                    };
+                }
+            }
+            // Buffers allocated for the max sizes are always large enough
+            assert!(resampler.output_frames_next() <= resampler.output_frames_max());
+            assert!(resampler.input_frames_next() <= resampler.input_frames_max());
+        }
+    }
+
+    // Errors in the arguments give an error and never a panic.
+    #[test]
+    fn no_panics() {
+        use crate::{AdaptiveLatency, ConcealDropouts, FixedChunkResampler, FlushDenormals};
+        use crate::{FastFixedIn, FastFixedOut, FftLowLatency, PitchShift, PolynomialDegree};
+        use crate::{Reversible, SanitizeInput, SincDecimator, SoftMute};
+        use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+        let params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        check_no_panics(SincFixedIn::<f64>::new(1.1, 2.0, params(), 500, 2).unwrap());
+        check_no_panics(SincFixedOut::<f64>::new(1.1, 2.0, params(), 500, 2).unwrap());
+        check_no_panics(
+            FastFixedIn::<f64>::new(0.9, 2.0, PolynomialDegree::Cubic, 500, 2).unwrap(),
+        );
+        check_no_panics(
+            FastFixedOut::<f64>::new(0.9, 2.0, PolynomialDegree::Cubic, 500, 2).unwrap(),
+        );
+        check_no_panics(FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2).unwrap());
+        check_no_panics(FftFixedOut::<f64>::new(44100, 48000, 500, 2, 2).unwrap());
+        check_no_panics(FftFixedInOut::<f64>::new(44100, 48000, 500, 2).unwrap());
+        check_no_panics(FftLowLatency::<f64>::new(48000, 96000, 64, 4, 2).unwrap());
+        check_no_panics(SincDecimator::<f64>::new(3, params(), 500, 2).unwrap());
+        // Wrappers
+        let fast_in =
+            || FastFixedIn::<f64>::new(0.9, 2.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_no_panics(SoftMute::new(fast_in(), 64));
+        check_no_panics(SanitizeInput::new(fast_in()));
+        check_no_panics(ConcealDropouts::new(fast_in(), 64));
+        check_no_panics(FlushDenormals::new(fast_in()));
+        check_no_panics(PitchShift::new(fast_in()));
+        check_no_panics(Reversible::new(fast_in()));
+        check_no_panics(FixedChunkResampler::new(fast_in(), 300, 200));
+        check_no_panics(AdaptiveLatency::new(fast_in(), 1000, 1.1, 0.001).unwrap());
+    }
+
+    fn process_stream<R: crate::Resampler<f64>>(
+        resampler: &mut R,
+        wave: &[f64],
+        chunk_sizes: &[usize],
+    ) -> Vec<f64> {
+        let mut output = Vec::new();
+        let mut pos = 0;
+        for chunk in 0.. {
+            resampler
+                .set_chunk_size(chunk_sizes[chunk % chunk_sizes.len()])
+                .unwrap();
+            let frames = resampler.input_frames_next();
+            if pos + frames > wave.len() {
+                break;
+            }
+            let input = &wave[pos..pos + frames];
+            output.extend_from_slice(&resampler.process(&[input, input], None).unwrap()[0]);
+            pos += frames;
+        }
+        output
+    }
+
+    fn check_chunk_size_change<R: crate::Resampler<f64>>(mut reference: R, mut resampler: R) {
+        let wave: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let expected = process_stream(&mut reference, &wave, &[500]);
+        // Grow and shrink the chunks, the output continues as if nothing changed
+        let output = process_stream(&mut resampler, &wave, &[500, 1234, 77, 500, 1]);
+        let len = expected.len().min(output.len());
+        assert!(len > 15000);
+        for (n, (value, expected)) in output[..len].iter().zip(expected[..len].iter()).enumerate() {
+            assert!((value - expected).abs() < 1e-9, "frame {}", n);
+        }
+    }
+
+    // Process two chunks with the reference, and only the second chunk with the resampler
+    // after loading the first chunk as history. The resampler starts from the position
+    // of a reset, which is later than that of the reference, and must then give
+    // the same output as the end of the second chunk of the reference.
+    fn check_warm_start(
+        mut reference: impl crate::Resampler<f64>,
+        mut resampler: impl crate::Resampler<f64>,
+    ) {
+        let frames = reference.input_frames_next();
+        let wave: Vec<f64> = (0..2 * frames).map(|n| (n as f64 * 0.05).sin()).collect();
+        let (first, second) = wave.split_at(frames);
+        crate::Resampler::process(&mut reference, &[first, first], None).unwrap();
+        let expected = crate::Resampler::process(&mut reference, &[second, second], None).unwrap();
+        crate::Resampler::set_history(&mut resampler, &[first, first]).unwrap();
+        let output = crate::Resampler::process(&mut resampler, &[second, second], None).unwrap();
+        assert!(output[1].len() > 200);
+        let offset = expected[1].len() - output[1].len();
+        for (value, expected) in output[1].iter().zip(expected[1][offset..].iter()) {
+            assert!((value - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn set_history() {
+        use crate::{FastFixedIn, PolynomialDegree, ResampleError, Resampler, SanitizeInput};
+        use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+        let params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        let sinc_in = || SincFixedIn::<f64>::new(1.0, 1.0, params(), 256, 2).unwrap();
+        check_warm_start(sinc_in(), sinc_in());
+        let fast_in =
+            || FastFixedIn::<f64>::new(1.0, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        check_warm_start(fast_in(), SanitizeInput::new(fast_in()));
+
+        let mut resampler = fast_in();
+        let err = Resampler::set_history(&mut resampler, &[vec![0.0; 10]]).unwrap_err();
+        assert_eq!(err.code(), 202);
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        let err = Resampler::set_history(&mut resampler, &vec![vec![0.0; 10]; 2]).unwrap_err();
+        assert!(matches!(err, ResampleError::HistoryNotSupported));
+    }
+
+    #[test]
+    fn latency_components() {
+        use crate::{FastFixedOut, PolynomialDegree, Resampler, SincInterpolationParameters};
+        use crate::{SincInterpolationType, WindowFunction};
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        let resampler = SincFixedIn::<f64>::new(2.0, 1.0, params, 1024, 2).unwrap();
+        assert_eq!(Resampler::input_delay(&resampler), 128);
+        assert_eq!(Resampler::output_delay(&resampler), 256);
+        assert_eq!(Resampler::buffering_delay(&resampler), 1024);
+        let resampler =
+            FastFixedOut::<f64>::new(0.5, 1.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+        assert_eq!(Resampler::input_delay(&resampler), 4);
+        assert_eq!(Resampler::output_delay(&resampler), 2);
+        assert_eq!(
+            Resampler::buffering_delay(&resampler),
+            Resampler::input_frames_next(&resampler)
+        );
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        let input_delay = Resampler::input_delay(&resampler) as f64;
+        let output_delay = Resampler::output_delay(&resampler) as f64;
+        assert!((input_delay * 48000.0 / 44100.0 - output_delay).abs() < 1.0);
+        // The delays in time are the same, referred to the input or to the output
+        let input_seconds = Resampler::input_delay_seconds(&resampler, 44100.0);
+        let output_seconds = Resampler::output_delay_seconds(&resampler, 48000.0);
+        assert!((input_seconds - output_seconds).abs() < 1.0 / 44100.0);
+        let latency = Resampler::latency_seconds(&resampler, 44100.0);
+        let buffering = Resampler::buffering_delay(&resampler) as f64 / 44100.0;
+        assert!((latency - input_seconds - buffering).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn memory_usage() {
+        use crate::{ConcealDropouts, Resampler, SincInterpolationParameters};
+        use crate::{SincInterpolationType, WindowFunction};
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        let resampler = SincFixedIn::<f64>::new(1.0, 1.0, params, 1024, 2).unwrap();
+        // The sinc table dominates
+        let sincs = 256 * 128 * 8;
+        let usage = Resampler::memory_usage(&resampler);
+        assert!(usage > sincs && usage < 2 * sincs);
+        let mut resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let usage = Resampler::memory_usage(&resampler);
+        assert!(usage > 2 * 1024 * 4);
+        Resampler::set_chunk_size(&mut resampler, 4096).unwrap();
+        let grown = Resampler::memory_usage(&resampler);
+        assert!(grown > usage);
+        let wrapped = ConcealDropouts::new(resampler, 64);
+        assert!(Resampler::memory_usage(&wrapped) > grown);
+    }
+
+    #[test]
+    fn set_chunk_size() {
+        use crate::{ConcealDropouts, FastFixedIn, FastFixedOut, FftLowLatency, PolynomialDegree};
+        use crate::{ResampleError, Resampler, SincInterpolationParameters};
+        use crate::{SincInterpolationType, WindowFunction};
+        let params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        let sinc_in = || SincFixedIn::<f64>::new(1.1, 1.0, params(), 500, 2).unwrap();
+        check_chunk_size_change(sinc_in(), sinc_in());
+        let sinc_out = || SincFixedOut::<f64>::new(1.1, 1.0, params(), 500, 2).unwrap();
+        check_chunk_size_change(sinc_out(), sinc_out());
+        let fast_in =
+            || FastFixedIn::<f64>::new(0.9, 1.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_chunk_size_change(fast_in(), fast_in());
+        let fast_out =
+            || FastFixedOut::<f64>::new(0.9, 1.0, PolynomialDegree::Cubic, 500, 2).unwrap();
+        check_chunk_size_change(fast_out(), fast_out());
+        let fft_in = || FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        check_chunk_size_change(fft_in(), fft_in());
+        let fft_out = || FftFixedOut::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        check_chunk_size_change(fft_out(), fft_out());
+        let conceal = || ConcealDropouts::new(fft_in(), 64);
+        check_chunk_size_change(conceal(), conceal());
+
+        let mut resampler = fft_in();
+        let err = Resampler::set_chunk_size(&mut resampler, 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::InvalidChunkSize { provided: 0 }
+        ));
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 500, 2).unwrap();
+        let err = Resampler::set_chunk_size(&mut resampler, 1000).unwrap_err();
+        assert!(matches!(err, ResampleError::ChunkSizeNotAdjustable));
+        let mut resampler = FftLowLatency::<f64>::new(48000, 96000, 64, 4, 2).unwrap();
+        assert!(Resampler::set_chunk_size(&mut resampler, 128).is_err());
+    }
+
+    fn check_max_relative_ratio_change<R: crate::Resampler<f64>>(mut resampler: R) {
+        use crate::ResampleError;
+        assert!(resampler.set_resample_ratio_relative(1.5, false).is_err());
+        let output_frames_max = resampler.output_frames_max();
+        let input_frames_max = resampler.input_frames_max();
+        resampler.set_max_resample_ratio_relative(2.0).unwrap();
+        assert!(resampler.output_frames_max() >= output_frames_max);
+        assert!(resampler.input_frames_max() >= input_frames_max);
+        for rel_ratio in [1.9, 0.55] {
+            resampler
+                .set_resample_ratio_relative(rel_ratio, true)
+                .unwrap();
+            let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+            for _ in 0..3 {
+                let waves_in = vec![vec![0.0; resampler.input_frames_next()]; 2];
+                resampler
+                    .process_into_buffer(&waves_in, &mut waves_out, None)
+                    .unwrap();
+            }
+        }
+        // The current ratio must stay within a narrowed range
+        let err = resampler.set_max_resample_ratio_relative(1.2).unwrap_err();
+        assert!(matches!(err, ResampleError::RatioOutOfBounds { .. }));
+        resampler.set_resample_ratio_relative(1.1, false).unwrap();
+        resampler.set_max_resample_ratio_relative(1.2).unwrap();
+        assert!(resampler.set_resample_ratio_relative(1.3, false).is_err());
+        let err = resampler.set_max_resample_ratio_relative(0.5).unwrap_err();
+        assert_eq!(err.code(), 213);
+    }
+
+    #[test]
+    fn set_max_relative_ratio() {
+        use crate::{FastFixedIn, FastFixedOut, FixedChunkResampler, PolynomialDegree};
+        use crate::{ResampleError, Resampler, SincInterpolationParameters, SpeechPreset};
+        let params = || SincInterpolationParameters::speech(SpeechPreset::Narrowband);
+        check_max_relative_ratio_change(
+            SincFixedIn::<f64>::new(1.1, 1.1, params(), 256, 2).unwrap(),
+        );
+        check_max_relative_ratio_change(
+            SincFixedOut::<f64>::new(1.1, 1.1, params(), 256, 2).unwrap(),
+        );
+        check_max_relative_ratio_change(
+            FastFixedIn::<f64>::new(0.9, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap(),
+        );
+        let fast_out = FastFixedOut::<f64>::new(0.9, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        check_max_relative_ratio_change(crate::SoftMute::new(fast_out, 16));
+
+        let inner = FastFixedIn::<f64>::new(1.0, 1.1, PolynomialDegree::Linear, 64, 1).unwrap();
+        let mut resampler = FixedChunkResampler::new(inner, 100, 100);
+        Resampler::set_max_resample_ratio_relative(&mut resampler, 2.0).unwrap();
+        Resampler::set_resample_ratio_relative(&mut resampler, 1.5, false).unwrap();
+        let waves_in = vec![vec![0.0f64; 100]];
+        Resampler::process(&mut resampler, &waves_in, None).unwrap();
+
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2).unwrap();
+        let err = Resampler::set_max_resample_ratio_relative(&mut resampler, 2.0).unwrap_err();
+        assert!(matches!(err, ResampleError::SyncNotAdjustable));
+    }
+
+    #[test]
+    fn validate_buffers() {
+        use crate::{ResampleError, Resampler};
+        let resampler = FftFixedIn::<f64>::new(44100, 88200, 1024, 2, 2).unwrap();
+        let waves_in = vec![vec![0.0f64; 1024]; 2];
+        let mut waves_out = vec![vec![0.0f64; 2048]; 2];
+        assert!(Resampler::validate_buffers(&resampler, &waves_in, &mut waves_out, None).is_ok());
+        let mut short_out = vec![vec![0.0f64; 2048], vec![0.0f64; 100]];
+        let err =
+            Resampler::validate_buffers(&resampler, &waves_in, &mut short_out, None).unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::InsufficientOutputBufferSize { channel: 1, .. }
+        ));
+        // An inactive channel is not checked
+        assert!(Resampler::validate_buffers(
+            &resampler,
+            &waves_in,
+            &mut short_out,
+            Some(&[true, false])
+        )
+        .is_ok());
+        let err = Resampler::validate_buffers(&resampler, &waves_in, &mut waves_out, Some(&[true]))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::WrongNumberOfMaskChannels {
+                expected: 2,
+                actual: 1
+            }
+        ));
+    }
+
+    fn check_compact_buffers<R: crate::Resampler<f64>>(mut full: R, mut compact: R) {
+        let mask = [true, false, true];
+        for _ in 0..3 {
+            let frames = full.input_frames_next();
+            let waves: Vec<Vec<f64>> = (0..3)
+                .map(|chan| {
+                    (0..frames)
+                        .map(|n| ((n * (chan + 1)) as f64).sin())
+                        .collect()
+                })
+                .collect();
+            let waves_active = vec![waves[0].clone(), waves[2].clone()];
+            let out_full = full.process(&waves, Some(&mask)).unwrap();
+            let mut out_compact = vec![vec![0.0; compact.output_frames_max()]; 2];
+            let (_, frames_out) = compact
+                .process_into_buffer(&waves_active, &mut out_compact, Some(&mask))
+                .unwrap();
+            assert_eq!(out_full[0], out_compact[0][..frames_out]);
+            assert_eq!(out_full[2], out_compact[1][..frames_out]);
+        }
+        let out_full = full
+            .process_partial(Some(&vec![vec![0.5; 10]; 3][..]), Some(&mask))
+            .unwrap();
+        let out_compact = compact
+            .process_partial(Some(&vec![vec![0.5; 10]; 2][..]), Some(&mask))
+            .unwrap();
+        assert_eq!(out_full[0], out_compact[0]);
+        assert_eq!(out_full[2], out_compact[2]);
+    }
+
+    // This tests that the buffers may contain only the active channels.
+    #[test]
+    fn compact_buffers() {
+        use crate::{
+            FastFixedIn, FastFixedOut, PolynomialDegree, SincInterpolationParameters,
+            SincInterpolationType, WindowFunction,
+        };
+        let make_params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.9,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::Blackman,
+            filter_design: crate::FilterDesign::WindowedSinc,
+            normalization: crate::GainNormalization::Dc,
+            ..Default::default()
+        };
+        check_compact_buffers(
+            FftFixedIn::<f64>::new(44100, 48000, 256, 2, 3).unwrap(),
+            FftFixedIn::<f64>::new(44100, 48000, 256, 2, 3).unwrap(),
+        );
+        check_compact_buffers(
+            FftFixedOut::<f64>::new(44100, 48000, 256, 2, 3).unwrap(),
+            FftFixedOut::<f64>::new(44100, 48000, 256, 2, 3).unwrap(),
+        );
+        check_compact_buffers(
+            FftFixedInOut::<f64>::new(44100, 48000, 256, 3).unwrap(),
+            FftFixedInOut::<f64>::new(44100, 48000, 256, 3).unwrap(),
+        );
+        check_compact_buffers(
+            SincFixedIn::<f64>::new(1.1, 1.0, make_params(), 256, 3).unwrap(),
+            SincFixedIn::<f64>::new(1.1, 1.0, make_params(), 256, 3).unwrap(),
+        );
+        check_compact_buffers(
+            SincFixedOut::<f64>::new(1.1, 1.0, make_params(), 256, 3).unwrap(),
+            SincFixedOut::<f64>::new(1.1, 1.0, make_params(), 256, 3).unwrap(),
+        );
+        check_compact_buffers(
+            FastFixedIn::<f64>::new(1.1, 1.0, PolynomialDegree::Cubic, 256, 3).unwrap(),
+            FastFixedIn::<f64>::new(1.1, 1.0, PolynomialDegree::Cubic, 256, 3).unwrap(),
+        );
+        check_compact_buffers(
+            FastFixedOut::<f64>::new(1.1, 1.0, PolynomialDegree::Cubic, 256, 3).unwrap(),
+            FastFixedOut::<f64>::new(1.1, 1.0, PolynomialDegree::Cubic, 256, 3).unwrap(),
+        );
+    }
+
     fn impl_send<T: Send>() {
         fn is_send<T: Send>() {}
         is_send::<SincFixedOut<T>>();
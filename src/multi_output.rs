@@ -0,0 +1,302 @@
+use crate::asynchro_sinc::{frame_position, interpolate_block, make_interpolator, FramePosition};
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
+use crate::{validate_buffers, Sample, SincInterpolationParameters, SincInterpolationType};
+
+/// One output of a [MultiOutputSinc] resampler.
+struct OutputTap<T> {
+    resample_ratio: f64,
+    interpolator: Box<dyn SincInterpolator<T>>,
+    last_index: f64,
+    positions: Vec<FramePosition<T>>,
+}
+
+/// A sinc resampler that resamples the same input to several output sample rates.
+///
+/// This gives the same output as one [SincFixedIn](crate::SincFixedIn) per output rate,
+/// but the input is only validated and copied once, into a history buffer shared by all outputs.
+/// Each output uses the anti-aliasing filter designed for its own ratio.
+/// The positions of the output frames are calculated once per output and chunk,
+/// and then reused for all channels.
+///
+/// The input size is fixed, and the number of output frames of each output varies
+/// from chunk to chunk, like for [SincFixedIn](crate::SincFixedIn).
+/// The ratios are fixed when creating the resampler.
+/// ```
+/// use rubato::{MultiOutputSinc, SincInterpolationParameters};
+/// let mut params = SincInterpolationParameters::default();
+/// params.sinc_len = 128;
+/// // Resample 48 kHz to 16 kHz and 8 kHz.
+/// let mut resampler =
+///     MultiOutputSinc::<f64>::new(&[16000.0 / 48000.0, 8000.0 / 48000.0], params, 960, 1).unwrap();
+/// let waves_in = vec![vec![0.0f64; 960]; 1];
+/// let waves_out = resampler.process(&waves_in).unwrap();
+/// assert_eq!(waves_out.len(), 2);
+/// ```
+pub struct MultiOutputSinc<T> {
+    nbr_channels: usize,
+    chunk_size: usize,
+    sinc_len: usize,
+    buffer: Vec<Vec<T>>,
+    channel_mask: Vec<bool>,
+    interpolation: SincInterpolationType,
+    taps: Vec<OutputTap<T>>,
+}
+
+impl<T> MultiOutputSinc<T>
+where
+    T: Sample,
+{
+    /// Create a new MultiOutputSinc
+    ///
+    /// Parameters are:
+    /// - `resample_ratios`: Ratios between output and input sample rates, one per output, must be > 0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new(
+        resample_ratios: &[f64],
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new MultiOutputSinc, ratios: {:?}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratios, chunk_size, nbr_channels, parameters
+        );
+        let mut taps = Vec::with_capacity(resample_ratios.len());
+        for ratio in resample_ratios.iter() {
+            if *ratio <= 0.0 {
+                return Err(ResamplerConstructionError::InvalidRatio(*ratio));
+            }
+            let interpolator = make_interpolator(*ratio, &parameters)?;
+            let sinc_len = interpolator.len();
+            taps.push(OutputTap {
+                resample_ratio: *ratio,
+                interpolator,
+                last_index: -((sinc_len / 2) as f64),
+                positions: Vec::with_capacity((chunk_size as f64 * ratio + 10.0) as usize),
+            });
+        }
+        let sinc_len = taps
+            .iter()
+            .map(|tap| tap.interpolator.len())
+            .max()
+            .unwrap_or(0);
+        Ok(MultiOutputSinc {
+            nbr_channels,
+            chunk_size,
+            sinc_len,
+            buffer: vec![vec![T::zero(); chunk_size + 2 * sinc_len]; nbr_channels],
+            channel_mask: vec![true; nbr_channels],
+            interpolation: parameters.interpolation,
+            taps,
+        })
+    }
+
+    /// Get the number of outputs.
+    pub fn nbr_outputs(&self) -> usize {
+        self.taps.len()
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    /// Get the number of frames per channel needed for the next call to
+    /// [process_into_buffer](MultiOutputSinc::process_into_buffer).
+    /// This is always the chunk size.
+    pub fn input_frames_next(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of frames per channel that output `output` can produce
+    /// in one call.
+    pub fn output_frames_max(&self, output: usize) -> usize {
+        (self.chunk_size as f64 * self.taps[output].resample_ratio + 10.0) as usize
+    }
+
+    /// Get the delay of output `output`, in output frames.
+    pub fn output_delay(&self, output: usize) -> usize {
+        let tap = &self.taps[output];
+        (tap.interpolator.len() as f64 * tap.resample_ratio / 2.0) as usize
+    }
+
+    /// Get the implementation used by the interpolator of output `output` for the scalar products,
+    /// see [InterpolatorBackend].
+    pub fn interpolator_backend(&self, output: usize) -> InterpolatorBackend {
+        self.taps[output].interpolator.backend()
+    }
+
+    /// Resample a chunk of audio to all outputs, and return newly allocated output buffers.
+    /// The result contains one vector of channels per output.
+    pub fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<Vec<T>>>> {
+        let mut waves_out: Vec<Vec<Vec<T>>> = (0..self.taps.len())
+            .map(|output| vec![vec![T::zero(); self.output_frames_max(output)]; self.nbr_channels])
+            .collect();
+        let mut frames_out = vec![0; self.taps.len()];
+        self.process_into_buffer(wave_in, &mut waves_out, &mut frames_out)?;
+        for (wave_out, frames) in waves_out.iter_mut().zip(frames_out.iter()) {
+            for chan_out in wave_out.iter_mut() {
+                chan_out.truncate(*frames);
+            }
+        }
+        Ok(waves_out)
+    }
+
+    /// Resample a chunk of audio to all outputs, writing to pre-allocated output buffers.
+    ///
+    /// The `waves_out` slice contains one set of channel buffers per output,
+    /// each with room for at least [output_frames_max](MultiOutputSinc::output_frames_max) frames.
+    /// The number of frames written to each output is stored in `frames_out`,
+    /// that must have one element per output.
+    /// An error is returned if `waves_out` or `frames_out` does not match the number of outputs.
+    pub fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>, W: AsMut<[Vout]>>(
+        &mut self,
+        wave_in: &[Vin],
+        waves_out: &mut [W],
+        frames_out: &mut [usize],
+    ) -> ResampleResult<()> {
+        for len in [waves_out.len(), frames_out.len()] {
+            if len != self.taps.len() {
+                return Err(ResampleError::WrongNumberOfOutputChannels {
+                    expected: self.taps.len(),
+                    actual: len,
+                });
+            }
+        }
+        // Validate all buffers before changing any state
+        for (output, wave_out) in waves_out.iter_mut().enumerate() {
+            validate_buffers(
+                wave_in,
+                wave_out.as_mut(),
+                &self.channel_mask,
+                self.nbr_channels,
+                self.chunk_size,
+                self.output_frames_max(output),
+            )?;
+        }
+
+        let sinc_len = self.sinc_len;
+        for buf in self.buffer.iter_mut() {
+            buf.copy_within(self.chunk_size..self.chunk_size + 2 * sinc_len, 0);
+        }
+        for (chan, wave) in wave_in.iter().enumerate() {
+            self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size]
+                .copy_from_slice(&wave.as_ref()[..self.chunk_size]);
+        }
+
+        for (output, (tap, wave_out)) in self.taps.iter_mut().zip(waves_out.iter_mut()).enumerate()
+        {
+            let wave_out = wave_out.as_mut();
+            let tap_sinc_len = tap.interpolator.len();
+            // Align the shorter filters with the end of the shared buffer
+            let offset = 2 * (sinc_len - tap_sinc_len);
+            let t_ratio = 1.0 / tap.resample_ratio;
+            let end_idx =
+                self.chunk_size as isize - (tap_sinc_len as isize + 1) - t_ratio.ceil() as isize;
+            let mut idx = tap.last_index;
+            tap.positions.clear();
+            while idx < end_idx as f64 {
+                idx += t_ratio;
+                tap.positions.push(frame_position(
+                    idx,
+                    tap.interpolator.nbr_sincs(),
+                    &self.interpolation,
+                ));
+            }
+            let nbr_frames = tap.positions.len();
+            for (chan, chan_out) in wave_out.iter_mut().enumerate() {
+                interpolate_block(
+                    tap.interpolator.as_ref(),
+                    &self.interpolation,
+                    &self.buffer[chan][offset..],
+                    &tap.positions,
+                    &mut chan_out.as_mut()[..nbr_frames],
+                )?;
+            }
+            tap.last_index = idx - self.chunk_size as f64;
+            frames_out[output] = nbr_frames;
+        }
+        trace!(
+            "Resampling to {} outputs, {} frames in, {:?} frames out",
+            self.taps.len(),
+            self.chunk_size,
+            frames_out
+        );
+        Ok(())
+    }
+
+    /// Reset the resampler state and clear all internal buffers.
+    pub fn reset(&mut self) {
+        self.buffer
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
+        for tap in self.taps.iter_mut() {
+            tap.last_index = -((tap.interpolator.len() / 2) as f64);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_sinc_params;
+    use crate::{MultiOutputSinc, Resampler, SincFixedIn, SincInterpolationType};
+
+    #[test]
+    fn same_as_separate() {
+        let ratios = [1.0 / 3.0, 1.0 / 6.0, 1.5];
+        let mut resampler = MultiOutputSinc::<f64>::new(
+            &ratios,
+            make_sinc_params(SincInterpolationType::Cubic),
+            480,
+            2,
+        )
+        .unwrap();
+        let mut separate: Vec<SincFixedIn<f64>> = ratios
+            .iter()
+            .map(|ratio| {
+                SincFixedIn::<f64>::new(
+                    *ratio,
+                    1.0,
+                    make_sinc_params(SincInterpolationType::Cubic),
+                    480,
+                    2,
+                )
+                .unwrap()
+            })
+            .collect();
+        for chunk in 0..5 {
+            let waves_in: Vec<Vec<f64>> = (0..2)
+                .map(|chan| {
+                    (0..480)
+                        .map(|n| ((chunk * 480 + n) as f64 * 0.01 * (chan + 1) as f64).sin())
+                        .collect()
+                })
+                .collect();
+            let waves_out = resampler.process(&waves_in).unwrap();
+            for (wave_out, single) in waves_out.iter().zip(separate.iter_mut()) {
+                assert_eq!(*wave_out, single.process(&waves_in, None).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_number_of_outputs() {
+        let mut resampler = MultiOutputSinc::<f64>::new(
+            &[0.5, 0.25],
+            make_sinc_params(SincInterpolationType::Cubic),
+            480,
+            1,
+        )
+        .unwrap();
+        let waves_in = vec![vec![0.0f64; 480]; 1];
+        let mut waves_out = vec![vec![vec![0.0f64; 480]; 1]; 1];
+        let mut frames_out = [0; 2];
+        let err = resampler
+            .process_into_buffer(&waves_in, &mut waves_out, &mut frames_out)
+            .unwrap_err();
+        assert_eq!(err.expected(), Some(2));
+    }
+}
@@ -0,0 +1,394 @@
+use crate::asynchro_sinc::{frame_position, interpolate_block, make_interpolator, FramePosition};
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
+use crate::{validate_buffers, Sample, SincInterpolationParameters, SincInterpolationType};
+
+/// The ratio and position of one channel of a [MultiRatioSinc] resampler.
+struct ChannelRatio<T> {
+    resample_ratio: f64,
+    target_ratio: f64,
+    last_index: f64,
+    positions: Vec<FramePosition<T>>,
+}
+
+/// A sinc resampler where each channel has its own resample ratio.
+///
+/// This is intended for multiplexing many unrelated mono streams through one resampler,
+/// where the streams drift independently.
+/// It gives the same output as one single channel [SincFixedIn](crate::SincFixedIn) per stream,
+/// but all channels share one anti-aliasing filter, designed for the starting ratio,
+/// and one allocation for the input history.
+///
+/// The input size is fixed, and the number of output frames varies from chunk to chunk,
+/// and from channel to channel.
+/// The ratio of each channel can be changed within the range given when creating the resampler,
+/// with [set_resample_ratio](MultiRatioSinc::set_resample_ratio).
+/// ```
+/// use rubato::{MultiRatioSinc, SincInterpolationParameters};
+/// let params = SincInterpolationParameters::speech(rubato::SpeechPreset::Wideband);
+/// // Four streams at about 48 kHz to 16 kHz, drifting independently
+/// let mut resampler = MultiRatioSinc::<f32>::new(1.0 / 3.0, 1.01, params, 960, 4).unwrap();
+/// resampler.set_resample_ratio(1, 1.0 / 3.0 * 1.001, true).unwrap();
+/// resampler.set_resample_ratio(2, 1.0 / 3.0 * 0.998, true).unwrap();
+/// let waves_in = vec![vec![0.0f32; 960]; 4];
+/// let waves_out = resampler.process(&waves_in).unwrap();
+/// assert_eq!(waves_out.len(), 4);
+/// ```
+pub struct MultiRatioSinc<T> {
+    nbr_channels: usize,
+    chunk_size: usize,
+    resample_ratio_original: f64,
+    max_relative_ratio: f64,
+    interpolator: Box<dyn SincInterpolator<T>>,
+    interpolation: SincInterpolationType,
+    buffer: Vec<Vec<T>>,
+    channel_mask: Vec<bool>,
+    channels: Vec<ChannelRatio<T>>,
+}
+
+impl<T> MultiRatioSinc<T>
+where
+    T: Sample,
+{
+    /// Create a new MultiRatioSinc, with all channels starting at the same ratio.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set for a channel with
+    ///   [set_resample_ratio](MultiRatioSinc::set_resample_ratio) relative to `resample_ratio`, must be >= 1.0.
+    ///   The minimum relative ratio is the reciprocal of the maximum.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new MultiRatioSinc, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        if resample_ratio <= 0.0 {
+            return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+        }
+        if max_resample_ratio_relative < 1.0 {
+            return Err(ResamplerConstructionError::InvalidRelativeRatio(
+                max_resample_ratio_relative,
+            ));
+        }
+        let interpolator = make_interpolator(resample_ratio, &parameters)?;
+        let sinc_len = interpolator.len();
+        let positions_len =
+            (chunk_size as f64 * resample_ratio * max_resample_ratio_relative + 10.0) as usize;
+        let channels = (0..nbr_channels)
+            .map(|_| ChannelRatio {
+                resample_ratio,
+                target_ratio: resample_ratio,
+                last_index: -((sinc_len / 2) as f64),
+                positions: Vec::with_capacity(positions_len),
+            })
+            .collect();
+        Ok(MultiRatioSinc {
+            nbr_channels,
+            chunk_size,
+            resample_ratio_original: resample_ratio,
+            max_relative_ratio: max_resample_ratio_relative,
+            interpolator,
+            interpolation: parameters.interpolation,
+            buffer: vec![vec![T::zero(); chunk_size + 2 * sinc_len]; nbr_channels],
+            channel_mask: vec![true; nbr_channels],
+            channels,
+        })
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    /// Get the number of frames per channel needed for the next call to
+    /// [process_into_buffer](MultiRatioSinc::process_into_buffer).
+    /// This is always the chunk size.
+    pub fn input_frames_next(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of frames per channel that can be produced in one call,
+    /// for any of the ratios that can be set.
+    pub fn output_frames_max(&self) -> usize {
+        (self.chunk_size as f64 * self.resample_ratio_original * self.max_relative_ratio + 10.0)
+            as usize
+    }
+
+    /// Get the delay of channel `channel`, in output frames.
+    pub fn output_delay(&self, channel: usize) -> usize {
+        (self.interpolator.len() as f64 * self.channels[channel].resample_ratio / 2.0) as usize
+    }
+
+    /// Get the current resample ratio of channel `channel`.
+    /// While a ramp is in progress, this is the ratio at the start of the next chunk.
+    pub fn resample_ratio(&self, channel: usize) -> f64 {
+        self.channels[channel].resample_ratio
+    }
+
+    /// Get the implementation used by the shared interpolator for the scalar products,
+    /// see [InterpolatorBackend].
+    pub fn interpolator_backend(&self) -> InterpolatorBackend {
+        self.interpolator.backend()
+    }
+
+    /// Update the resample ratio of channel `channel`, leaving the other channels unchanged.
+    ///
+    /// This works like [Resampler::set_resample_ratio](crate::Resampler::set_resample_ratio).
+    /// The new ratio must be within the range given by `max_resample_ratio_relative`
+    /// when creating the resampler.
+    /// If `ramp` is true, the ratio of the channel changes gradually over the next chunk.
+    pub fn set_resample_ratio(
+        &mut self,
+        channel: usize,
+        new_ratio: f64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        trace!(
+            "Change resample ratio of channel {} to {}",
+            channel,
+            new_ratio
+        );
+        let relative = new_ratio / self.resample_ratio_original;
+        if relative < 1.0 / self.max_relative_ratio || relative > self.max_relative_ratio {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio_original,
+                max_relative_ratio: self.max_relative_ratio,
+            });
+        }
+        let state = &mut self.channels[channel];
+        if !ramp {
+            state.resample_ratio = new_ratio;
+        }
+        state.target_ratio = new_ratio;
+        Ok(())
+    }
+
+    /// Update the resample ratio of channel `channel` as a factor relative to the starting ratio,
+    /// see [set_resample_ratio](MultiRatioSinc::set_resample_ratio).
+    pub fn set_resample_ratio_relative(
+        &mut self,
+        channel: usize,
+        rel_ratio: f64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(channel, new_ratio, ramp)
+    }
+
+    /// Resample a chunk of audio, and return newly allocated output buffers.
+    /// The channels of the result can have different lengths.
+    pub fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let mut wave_out = vec![vec![T::zero(); self.output_frames_max()]; self.nbr_channels];
+        let mut frames_out = vec![0; self.nbr_channels];
+        self.process_into_buffer(wave_in, &mut wave_out, &mut frames_out)?;
+        for (chan_out, frames) in wave_out.iter_mut().zip(frames_out.iter()) {
+            chan_out.truncate(*frames);
+        }
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of audio, writing to pre-allocated output buffers.
+    ///
+    /// Each output channel needs room for the frames of the next chunk at the ratio of that channel,
+    /// [output_frames_max](MultiRatioSinc::output_frames_max) frames is always enough.
+    /// The number of frames written to each channel is stored in `frames_out`,
+    /// that must have one element per channel.
+    /// An error is returned if `frames_out` does not match the number of channels.
+    pub fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        frames_out: &mut [usize],
+    ) -> ResampleResult<()> {
+        if frames_out.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: self.nbr_channels,
+                actual: frames_out.len(),
+            });
+        }
+        // Set length to chunksize*ratio plus a safety margin of 10 elements.
+        let needed_len = self
+            .channels
+            .iter()
+            .map(|state| {
+                (self.chunk_size as f64 * (0.5 * state.resample_ratio + 0.5 * state.target_ratio)
+                    + 10.0) as usize
+            })
+            .max()
+            .unwrap_or(0);
+        validate_buffers(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            self.nbr_channels,
+            self.chunk_size,
+            needed_len,
+        )?;
+
+        let sinc_len = self.interpolator.len();
+        let oversampling_factor = self.interpolator.nbr_sincs();
+        for (buf, wave) in self.buffer.iter_mut().zip(wave_in.iter()) {
+            buf.copy_within(self.chunk_size..self.chunk_size + 2 * sinc_len, 0);
+            buf[2 * sinc_len..2 * sinc_len + self.chunk_size]
+                .copy_from_slice(&wave.as_ref()[..self.chunk_size]);
+        }
+
+        for (chan, (state, chan_out)) in self
+            .channels
+            .iter_mut()
+            .zip(wave_out.iter_mut())
+            .enumerate()
+        {
+            let mut t_ratio = 1.0 / state.resample_ratio;
+            let t_ratio_end = 1.0 / state.target_ratio;
+            let approximate_nbr_frames =
+                self.chunk_size as f64 * (0.5 * state.resample_ratio + 0.5 * state.target_ratio);
+            let t_ratio_increment = (t_ratio_end - t_ratio) / approximate_nbr_frames;
+            let end_idx =
+                self.chunk_size as isize - (sinc_len as isize + 1) - t_ratio_end.ceil() as isize;
+            let mut idx = state.last_index;
+            state.positions.clear();
+            while idx < end_idx as f64 {
+                t_ratio += t_ratio_increment;
+                idx += t_ratio;
+                state.positions.push(frame_position(
+                    idx,
+                    oversampling_factor,
+                    &self.interpolation,
+                ));
+            }
+            let nbr_frames = state.positions.len();
+            interpolate_block(
+                self.interpolator.as_ref(),
+                &self.interpolation,
+                &self.buffer[chan],
+                &state.positions,
+                &mut chan_out.as_mut()[..nbr_frames],
+            )?;
+            state.last_index = idx - self.chunk_size as f64;
+            state.resample_ratio = state.target_ratio;
+            frames_out[chan] = nbr_frames;
+        }
+        trace!(
+            "Resampling {} channels, {} frames in, {:?} frames out",
+            self.nbr_channels,
+            self.chunk_size,
+            frames_out
+        );
+        Ok(())
+    }
+
+    /// Reset the resampler state and clear all internal buffers.
+    /// The ratios of all channels are set back to the starting ratio.
+    pub fn reset(&mut self) {
+        self.buffer
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
+        let sinc_len = self.interpolator.len();
+        for state in self.channels.iter_mut() {
+            state.resample_ratio = self.resample_ratio_original;
+            state.target_ratio = self.resample_ratio_original;
+            state.last_index = -((sinc_len / 2) as f64);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_sinc_params;
+    use crate::{MultiRatioSinc, ResampleError, Resampler, SincFixedIn, SincInterpolationType};
+
+    #[test]
+    fn same_as_separate() {
+        let mut resampler = MultiRatioSinc::<f64>::new(
+            0.5,
+            1.1,
+            make_sinc_params(SincInterpolationType::Cubic),
+            480,
+            3,
+        )
+        .unwrap();
+        let mut separate: Vec<SincFixedIn<f64>> = (0..3)
+            .map(|_| {
+                SincFixedIn::<f64>::new(
+                    0.5,
+                    1.1,
+                    make_sinc_params(SincInterpolationType::Cubic),
+                    480,
+                    1,
+                )
+                .unwrap()
+            })
+            .collect();
+        for chunk in 0..5 {
+            if chunk == 1 {
+                resampler.set_resample_ratio(1, 0.52, false).unwrap();
+                separate[1].set_resample_ratio(0.52, false).unwrap();
+                resampler
+                    .set_resample_ratio_relative(2, 0.95, true)
+                    .unwrap();
+                separate[2].set_resample_ratio_relative(0.95, true).unwrap();
+            }
+            let waves_in: Vec<Vec<f64>> = (0..3)
+                .map(|chan| {
+                    (0..480)
+                        .map(|n| ((chunk * 480 + n) as f64 * 0.01 * (chan + 1) as f64).sin())
+                        .collect()
+                })
+                .collect();
+            let waves_out = resampler.process(&waves_in).unwrap();
+            for (chan, single) in separate.iter_mut().enumerate() {
+                let expected = single.process(&waves_in[chan..chan + 1], None).unwrap();
+                assert_eq!(waves_out[chan], expected[0]);
+            }
+        }
+        assert_eq!(resampler.resample_ratio(0), 0.5);
+        assert_eq!(resampler.resample_ratio(2), 0.5 * 0.95);
+        resampler.reset();
+        assert_eq!(resampler.resample_ratio(1), 0.5);
+    }
+
+    #[test]
+    fn ratio_out_of_bounds() {
+        let mut resampler = MultiRatioSinc::<f64>::new(
+            0.5,
+            1.1,
+            make_sinc_params(SincInterpolationType::Cubic),
+            480,
+            2,
+        )
+        .unwrap();
+        let err = resampler.set_resample_ratio(1, 0.6, false).unwrap_err();
+        assert!(matches!(err, ResampleError::RatioOutOfBounds { .. }));
+        assert_eq!(resampler.resample_ratio(1), 0.5);
+    }
+
+    #[test]
+    fn wrong_number_of_frame_counts() {
+        let mut resampler = MultiRatioSinc::<f64>::new(
+            0.5,
+            1.1,
+            make_sinc_params(SincInterpolationType::Cubic),
+            480,
+            2,
+        )
+        .unwrap();
+        let waves_in = vec![vec![0.0f64; 480]; 2];
+        let mut waves_out = vec![vec![0.0f64; resampler.output_frames_max()]; 2];
+        let mut frames_out = [0; 1];
+        let err = resampler
+            .process_into_buffer(&waves_in, &mut waves_out, &mut frames_out)
+            .unwrap_err();
+        assert_eq!(err.expected(), Some(2));
+    }
+}
@@ -0,0 +1,375 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{Resampler, Sample};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A token for cancelling a long running job, such as [process_all], from another thread.
+///
+/// The token is cheap to clone, and all clones share the same state.
+/// Keep one clone in the thread that runs the job, and call [cancel](CancellationToken::cancel)
+/// on another, for example from the abort button of a GUI.
+/// The job checks the token between chunks, and stops at the next chunk boundary.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new token, that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of the jobs that use this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Resample a complete signal in one call, for offline conversion of files and buffers.
+///
+/// The input is split into chunks of the size asked for by the resampler,
+/// and the last frames are processed with
+/// [process_partial_into_buffer](Resampler::process_partial_into_buffer).
+/// The resampler is then flushed until all output frames are available.
+/// The filter delay of [output_delay](Resampler::output_delay) is removed from the start,
+/// and the output is trimmed to `round(frames_in * resample_ratio)` frames.
+///
+/// After each chunk, `progress` is called with the number of input frames processed so far
+/// and the total number of input frames, which can be used to drive a progress bar.
+/// If a `cancel` token is given, it is checked before each chunk.
+/// When it has been cancelled, processing stops and [ResampleError::Cancelled] is returned.
+/// The resampler is then left in the middle of the stream, and should be reset before reuse.
+///
+/// Note that the resampler is not reset before processing.
+///
+/// Parameters are:
+/// - `resampler`: The resampler to use.
+/// - `waves_in`: The complete input signal, one slice or vector per channel.
+/// - `resample_ratio`: The ratio between output and input sample rates.
+/// - `progress`: A closure that is called with `(frames_processed, frames_total)` after each chunk.
+/// - `cancel`: An optional token for cancelling the job.
+///
+/// ```
+/// use rubato::{process_all, CancellationToken, FastFixedIn, PolynomialDegree};
+/// let mut resampler = FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+/// let waves_in = vec![vec![0.0f64; 10000]; 2];
+/// let token = CancellationToken::new();
+/// let mut last_progress = 0;
+/// let waves_out = process_all(
+///     &mut resampler,
+///     &waves_in,
+///     2.0,
+///     |processed, total| last_progress = 100 * processed / total,
+///     Some(&token),
+/// )
+/// .unwrap();
+/// assert_eq!(waves_out[0].len(), 20000);
+/// assert_eq!(last_progress, 100);
+/// ```
+pub fn process_all<T, R, V, F>(
+    resampler: &mut R,
+    waves_in: &[V],
+    resample_ratio: f64,
+    progress: F,
+    cancel: Option<&CancellationToken>,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+    V: AsRef<[T]>,
+    F: FnMut(usize, usize),
+{
+    let frames_total = waves_in
+        .first()
+        .map(|wave| wave.as_ref().len())
+        .unwrap_or(0);
+    let nbr_output_frames = (frames_total as f64 * resample_ratio).round() as usize;
+    let mut waves_out = vec![Vec::with_capacity(nbr_output_frames); waves_in.len()];
+    run_chunks(
+        resampler,
+        waves_in,
+        resample_ratio,
+        progress,
+        cancel,
+        |chunk: &[&[T]]| {
+            for (wave, block) in waves_out.iter_mut().zip(chunk.iter()) {
+                wave.extend_from_slice(block);
+            }
+        },
+    )?;
+    Ok(waves_out)
+}
+
+/// Resample a complete signal, and hand the output to a closure one block at a time.
+///
+/// This works like [process_all], but the output is not collected.
+/// Instead `output` is called after each chunk with the new output frames,
+/// as one slice per channel, so that a large conversion can stream the result
+/// to a file or the network without keeping it all in memory.
+/// The filter delay is removed and the output is trimmed in the same way,
+/// so the blocks joined together are identical to the result of [process_all].
+/// Chunks that only produce frames of the filter delay don't give a call.
+/// The slices point into an output buffer that is reused for all chunks,
+/// so the frames must be copied if they are needed after the call.
+///
+/// If a `cancel` token is given, it is checked before each chunk.
+/// When it has been cancelled, processing stops and [ResampleError::Cancelled] is returned.
+/// The blocks given to `output` before that are the start of the complete output.
+///
+/// Returns the total number of output frames per channel given to `output`.
+///
+/// Parameters are:
+/// - `resampler`: The resampler to use.
+/// - `waves_in`: The complete input signal, one slice or vector per channel.
+/// - `resample_ratio`: The ratio between output and input sample rates.
+/// - `output`: A closure that is called with the output frames of each chunk, one slice per channel.
+/// - `cancel`: An optional token for cancelling the job.
+///
+/// ```
+/// use rubato::{process_all_with, FastFixedIn, PolynomialDegree};
+/// let mut resampler = FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+/// let waves_in = vec![vec![0.0f64; 10000]; 2];
+/// let mut written = 0;
+/// let frames = process_all_with(
+///     &mut resampler,
+///     &waves_in,
+///     2.0,
+///     |block: &[&[f64]]| {
+///         // Write the block to a file here
+///         written += block[0].len();
+///     },
+///     None,
+/// )
+/// .unwrap();
+/// assert_eq!(frames, 20000);
+/// assert_eq!(written, 20000);
+/// ```
+pub fn process_all_with<T, R, V, F>(
+    resampler: &mut R,
+    waves_in: &[V],
+    resample_ratio: f64,
+    output: F,
+    cancel: Option<&CancellationToken>,
+) -> ResampleResult<usize>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+    V: AsRef<[T]>,
+    F: FnMut(&[&[T]]),
+{
+    run_chunks(
+        resampler,
+        waves_in,
+        resample_ratio,
+        |_, _| {},
+        cancel,
+        output,
+    )
+}
+
+/// Drive the chunk loop of [process_all] and [process_all_with],
+/// giving the output frames after the filter delay to `output`.
+fn run_chunks<T, R, V, P, F>(
+    resampler: &mut R,
+    waves_in: &[V],
+    resample_ratio: f64,
+    mut progress: P,
+    cancel: Option<&CancellationToken>,
+    mut output: F,
+) -> ResampleResult<usize>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+    V: AsRef<[T]>,
+    P: FnMut(usize, usize),
+    F: FnMut(&[&[T]]),
+{
+    let nbr_channels = resampler.nbr_channels();
+    if waves_in.len() != nbr_channels {
+        return Err(ResampleError::WrongNumberOfInputChannels {
+            expected: nbr_channels,
+            actual: waves_in.len(),
+        });
+    }
+    if nbr_channels == 0 {
+        return Ok(0);
+    }
+    let frames_total = waves_in
+        .first()
+        .map(|wave| wave.as_ref().len())
+        .unwrap_or(0);
+    let delay = resampler.output_delay();
+    let nbr_output_frames = (frames_total as f64 * resample_ratio).round() as usize;
+
+    let mut buffer = vec![vec![T::zero(); resampler.output_frames_max()]; nbr_channels];
+    let mut slices: Vec<&[T]> = waves_in.iter().map(|wave| wave.as_ref()).collect();
+    let mut processed = 0;
+    // Number of output frames produced so far, including the delay
+    let mut generated = 0;
+    while generated < delay + nbr_output_frames {
+        if cancel.map(|token| token.is_cancelled()).unwrap_or(false) {
+            debug!("Cancelled after {} of {} frames", processed, frames_total);
+            return Err(ResampleError::Cancelled { processed });
+        }
+        let remaining = slices.first().map(|slice| slice.len()).unwrap_or(0);
+        let (nbr_in, nbr_out) = if remaining >= resampler.input_frames_next() {
+            resampler.process_into_buffer(&slices, &mut buffer, None)?
+        } else if remaining > 0 {
+            resampler.process_partial_into_buffer(Some(&slices), &mut buffer, None)?
+        } else {
+            resampler.process_partial_into_buffer::<&[T], _>(None, &mut buffer, None)?
+        };
+        for slice in slices.iter_mut() {
+            *slice = &slice[nbr_in.min(slice.len())..];
+        }
+        let start = delay.saturating_sub(generated).min(nbr_out);
+        let end = (delay + nbr_output_frames - generated).min(nbr_out);
+        generated += nbr_out;
+        if end > start {
+            let blocks: Vec<&[T]> = buffer.iter().map(|buf| &buf[start..end]).collect();
+            output(&blocks);
+        }
+        processed = (processed + nbr_in).min(frames_total);
+        progress(processed, frames_total);
+    }
+    Ok(nbr_output_frames)
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{
+        process_all, process_all_with, CancellationToken, FastFixedIn, FftFixedIn, FftFixedOut,
+        PolynomialDegree, ResampleError, Resampler, SincFixedIn, SincInterpolationParameters,
+        SpeechPreset,
+    };
+
+    #[test]
+    fn process_all_with_progress() {
+        let waves_in = vec![
+            (0..5000)
+                .map(|n| (n as f64 * 0.01).sin())
+                .collect::<Vec<_>>();
+            2
+        ];
+        let mut resampler = FftFixedOut::<f64>::new(88200, 44100, 256, 2, 2).unwrap();
+        let mut calls = Vec::new();
+        let waves_out = process_all(
+            &mut resampler,
+            &waves_in,
+            0.5,
+            |processed, total| calls.push((processed, total)),
+            None,
+        )
+        .unwrap();
+        assert_eq!(waves_out.len(), 2);
+        assert_eq!(waves_out[0].len(), 2500);
+        // The delay is removed, so the output follows the input at half the rate
+        for (n, value) in waves_out[0].iter().enumerate().skip(100).take(2000) {
+            assert!((value - (n as f64 * 0.02).sin()).abs() < 1.0e-2);
+        }
+        assert!(calls.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert_eq!(calls.last(), Some(&(5000, 5000)));
+        assert!(calls.len() > 10);
+
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let err = process_all(&mut resampler, &waves_in[..1], 1.0, |_, _| {}, None);
+        assert_eq!(err.err().map(|err| err.code()), Some(202));
+    }
+
+    #[test]
+    fn cancel_process_all() {
+        let waves_in = vec![vec![0.0f32; 100000]];
+        let mut resampler =
+            FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Linear, 1024, 1).unwrap();
+        let token = CancellationToken::new();
+        let remote = token.clone();
+        let result = process_all(
+            &mut resampler,
+            &waves_in,
+            1.5,
+            |processed, _total| {
+                if processed >= 10240 {
+                    remote.cancel();
+                }
+            },
+            Some(&token),
+        );
+        assert!(token.is_cancelled());
+        match result {
+            Err(ResampleError::Cancelled { processed }) => assert_eq!(processed, 10240),
+            _ => panic!("Expected the job to be cancelled"),
+        }
+    }
+
+    #[test]
+    fn process_all_with_blocks() {
+        let waves_in = vec![
+            (0..5000)
+                .map(|n| (n as f64 * 0.01).sin())
+                .collect::<Vec<_>>();
+            2
+        ];
+        // The delay is longer than the output of the first chunks
+        let params = SincInterpolationParameters::speech(SpeechPreset::Wideband);
+        let mut resampler = SincFixedIn::<f64>::new(48000.0 / 44100.0, 1.0, params, 32, 2).unwrap();
+        assert!(resampler.output_delay() > resampler.output_frames_max());
+        let expected = process_all(
+            &mut resampler,
+            &waves_in,
+            48000.0 / 44100.0,
+            |_, _| {},
+            None,
+        )
+        .unwrap();
+        resampler.reset();
+        let mut joined = vec![Vec::new(); 2];
+        let mut calls = 0;
+        let frames = process_all_with(
+            &mut resampler,
+            &waves_in,
+            48000.0 / 44100.0,
+            |block: &[&[f64]]| {
+                assert_eq!(block.len(), 2);
+                assert!(!block[0].is_empty());
+                for (wave, chan) in joined.iter_mut().zip(block.iter()) {
+                    wave.extend_from_slice(chan);
+                }
+                calls += 1;
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(frames, expected[0].len());
+        assert_eq!(joined, expected);
+        assert!(calls > 10);
+    }
+
+    #[test]
+    fn cancel_process_all_with() {
+        let waves_in = vec![vec![0.0f32; 100000]];
+        let mut resampler =
+            FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Linear, 1024, 1).unwrap();
+        let token = CancellationToken::new();
+        let mut written = 0;
+        let result = process_all_with(
+            &mut resampler,
+            &waves_in,
+            1.5,
+            |block: &[&[f32]]| {
+                written += block[0].len();
+                if written >= 10000 {
+                    token.cancel();
+                }
+            },
+            Some(&token),
+        );
+        assert!(matches!(result, Err(ResampleError::Cancelled { .. })));
+        assert!((10000..20000).contains(&written));
+    }
+}
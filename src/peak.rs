@@ -0,0 +1,250 @@
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::Sample;
+
+/// The smallest and largest sample values of a bin of input frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Peak<T> {
+    pub min: T,
+    pub max: T,
+}
+
+/// A decimator that reduces audio to the minimum and maximum values of bins of input frames.
+///
+/// This is not a resampler, since the output is not band limited.
+/// Instead each output value gives the range of the input within a bin,
+/// which is what is needed to draw overviews of waveforms.
+/// The bins are `frames_per_bin` input frames long, which may be fractional.
+/// Bins then have lengths of `frames_per_bin` rounded up or down,
+/// so that the bins stay aligned with the input over long streams.
+///
+/// The input can be given in chunks of any size, and a bin can span several chunks.
+/// Processing does not allocate.
+/// ```
+/// use rubato::PeakDecimator;
+/// let mut decimator = PeakDecimator::<f32>::new(1000.0, 2).unwrap();
+/// let waves_in = vec![vec![0.0f32; 4500]; 2];
+/// let mut peaks = decimator.process(&waves_in).unwrap();
+/// assert_eq!(peaks[0].len(), 4);
+/// // Get the last, incomplete bin.
+/// let last = decimator.flush();
+/// assert_eq!(last.map(|last| last.len()), Some(2));
+/// ```
+pub struct PeakDecimator<T> {
+    frames_per_bin: f64,
+    nbr_channels: usize,
+    current: Vec<Peak<T>>,
+    bin_fill: usize,
+    bin_index: u64,
+    bin_end: u64,
+    position: u64,
+}
+
+impl<T> PeakDecimator<T>
+where
+    T: Sample + PartialOrd,
+{
+    /// Create a new PeakDecimator
+    ///
+    /// Parameters are:
+    /// - `frames_per_bin`: Number of input frames per output value, must be >= 1.0.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new(
+        frames_per_bin: f64,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if !(frames_per_bin >= 1.0 && frames_per_bin.is_finite()) {
+            return Err(ResamplerConstructionError::InvalidRatio(frames_per_bin));
+        }
+        let empty = Peak {
+            min: T::zero(),
+            max: T::zero(),
+        };
+        Ok(PeakDecimator {
+            frames_per_bin,
+            nbr_channels,
+            current: vec![empty; nbr_channels],
+            bin_fill: 0,
+            bin_index: 0,
+            bin_end: frames_per_bin.floor() as u64,
+            position: 0,
+        })
+    }
+
+    /// Get the maximum number of complete bins that `input_frames` frames can give.
+    pub fn output_frames_max(&self, input_frames: usize) -> usize {
+        (input_frames as f64 / self.frames_per_bin).ceil() as usize + 1
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    /// Decimate a chunk of audio, and return the complete bins in newly allocated buffers.
+    pub fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<Peak<T>>>> {
+        let frames_in = wave_in.first().map(|wave| wave.as_ref().len()).unwrap_or(0);
+        let empty = Peak {
+            min: T::zero(),
+            max: T::zero(),
+        };
+        let mut wave_out = vec![vec![empty; self.output_frames_max(frames_in)]; self.nbr_channels];
+        let frames_out = self.process_into_buffer(wave_in, &mut wave_out)?;
+        for chan_out in wave_out.iter_mut() {
+            chan_out.truncate(frames_out);
+        }
+        Ok(wave_out)
+    }
+
+    /// Decimate a chunk of audio, and write the complete bins to a pre-allocated buffer.
+    /// All input channels must have the same length, and each output channel must have room
+    /// for [output_frames_max](PeakDecimator::output_frames_max) values.
+    /// The frames of an incomplete bin at the end are kept for the next call.
+    /// Returns the number of bins written.
+    pub fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[Peak<T>]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+    ) -> ResampleResult<usize> {
+        if wave_in.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: self.nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: self.nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        let frames_in = wave_in.first().map(|wave| wave.as_ref().len()).unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() != frames_in {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames_in,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        let needed_len = self.output_frames_max(frames_in);
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if wave.as_mut().len() < needed_len {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: needed_len,
+                    actual: wave.as_mut().len(),
+                });
+            }
+        }
+
+        let mut frames_out = 0;
+        let mut start = 0;
+        while start < frames_in {
+            let end = ((self.bin_end - self.position) as usize).min(frames_in - start) + start;
+            for (chan, wave) in wave_in.iter().enumerate() {
+                let peak = &mut self.current[chan];
+                let mut values = wave.as_ref()[start..end].iter();
+                if self.bin_fill == 0 {
+                    if let Some(first) = values.next() {
+                        *peak = Peak {
+                            min: *first,
+                            max: *first,
+                        };
+                    }
+                }
+                for value in values {
+                    if *value < peak.min {
+                        peak.min = *value;
+                    }
+                    if *value > peak.max {
+                        peak.max = *value;
+                    }
+                }
+            }
+            self.bin_fill += end - start;
+            self.position += (end - start) as u64;
+            start = end;
+            if self.position == self.bin_end {
+                for (peak, chan_out) in self.current.iter().zip(wave_out.iter_mut()) {
+                    chan_out.as_mut()[frames_out] = *peak;
+                }
+                frames_out += 1;
+                self.next_bin();
+            }
+        }
+        trace!(
+            "Decimated {} frames to {} peak values",
+            frames_in,
+            frames_out
+        );
+        Ok(frames_out)
+    }
+
+    /// Get the values of the incomplete bin at the end of the input, if there is one,
+    /// with one value per channel. The next frames then start a new bin.
+    pub fn flush(&mut self) -> Option<Vec<Peak<T>>> {
+        if self.bin_fill == 0 {
+            return None;
+        }
+        let peaks = self.current.clone();
+        self.reset();
+        Some(peaks)
+    }
+
+    /// Clear the incomplete bin, and start over from the beginning.
+    pub fn reset(&mut self) {
+        self.bin_fill = 0;
+        self.position = 0;
+        self.bin_index = 0;
+        self.bin_end = self.frames_per_bin.floor() as u64;
+    }
+
+    fn next_bin(&mut self) {
+        self.bin_fill = 0;
+        self.bin_index += 1;
+        self.bin_end = ((self.bin_index + 1) as f64 * self.frames_per_bin).floor() as u64;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{Peak, PeakDecimator};
+
+    #[test]
+    fn min_max_per_bin() {
+        let mut decimator = PeakDecimator::<f64>::new(4.0, 1).unwrap();
+        let wave = vec![1.0, -2.0, 3.0, 0.0, 5.0, 4.0, -1.0, 2.0, 7.0];
+        // Split the input so that a bin spans two calls
+        let first = decimator.process(&[&wave[..6]]).unwrap();
+        let second = decimator.process(&[&wave[6..]]).unwrap();
+        assert_eq!(
+            first[0],
+            vec![Peak {
+                min: -2.0,
+                max: 3.0
+            }]
+        );
+        assert_eq!(
+            second[0],
+            vec![Peak {
+                min: -1.0,
+                max: 5.0
+            }]
+        );
+        assert_eq!(decimator.flush(), Some(vec![Peak { min: 7.0, max: 7.0 }]));
+        assert_eq!(decimator.flush(), None);
+    }
+
+    #[test]
+    fn fractional_bins() {
+        let mut decimator = PeakDecimator::<f32>::new(2.5, 2).unwrap();
+        let waves = vec![vec![0.0f32; 1000]; 2];
+        let mut bins = 0;
+        for _ in 0..10 {
+            bins += decimator.process(&waves).unwrap()[1].len();
+        }
+        assert_eq!(bins, 4000);
+        assert!(PeakDecimator::<f32>::new(0.5, 2).is_err());
+    }
+}
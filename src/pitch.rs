@@ -0,0 +1,250 @@
+use crate::error::ResampleResult;
+use crate::{Resampler, ResamplerConfig, Sample};
+
+/// A wrapper for an asynchronous [Resampler] that changes the pitch of the audio,
+/// with the shift given in semitones and cents.
+///
+/// The pitch is shifted by resampling, and playing the output at the input sample rate.
+/// Raising the pitch by a factor `f` uses a resample ratio of `1 / f`
+/// relative to the original ratio of the wrapped resampler.
+/// This also changes the length of the audio by the same factor,
+/// for example raising the pitch by one octave gives half as many output frames.
+/// Use [length_factor](PitchShift::length_factor) to get the current factor.
+/// Keeping the length while shifting the pitch needs time stretching,
+/// which is not provided by this crate.
+///
+/// The resampler must be created with a `max_resample_ratio_relative` that covers the
+/// wanted range, for example 2.0 allows shifts of up to one octave up or down.
+/// Pitch changes can be ramped, to glide smoothly from the old pitch to the new one
+/// during the next chunk.
+/// ```
+/// use rubato::{FastFixedIn, PitchShift, PolynomialDegree, Resampler};
+/// let resampler = FastFixedIn::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+/// let mut resampler = PitchShift::new(resampler);
+/// // Shift up by a fifth.
+/// resampler.set_pitch(7.0, 0.0, false).unwrap();
+/// let waves_in = vec![vec![0.0f64; 1024]; 2];
+/// let _waves_out = resampler.process(&waves_in, None).unwrap();
+/// ```
+pub struct PitchShift<R> {
+    resampler: R,
+    semitones: f64,
+}
+
+/// Get the frequency factor for a shift in semitones.
+fn pitch_factor(semitones: f64) -> f64 {
+    2.0f64.powf(semitones / 12.0)
+}
+
+impl<R> PitchShift<R> {
+    /// Create a new PitchShift wrapper, starting without any pitch shift.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    pub fn new(resampler: R) -> Self {
+        PitchShift {
+            resampler,
+            semitones: 0.0,
+        }
+    }
+
+    /// Set the pitch shift, as the sum of `semitones` and `cents`.
+    /// Positive values raise the pitch. Both values may be fractional.
+    /// If `ramp` is true, the pitch glides to the new value during the next chunk.
+    ///
+    /// Returns an error if the shift needs a ratio outside the range of the wrapped resampler,
+    /// and the pitch is then left unchanged.
+    pub fn set_pitch<T>(&mut self, semitones: f64, cents: f64, ramp: bool) -> ResampleResult<()>
+    where
+        R: Resampler<T>,
+        T: Sample,
+    {
+        let total = semitones + cents / 100.0;
+        trace!("Set pitch shift to {} semitones", total);
+        self.resampler
+            .set_resample_ratio_relative(1.0 / pitch_factor(total), ramp)?;
+        self.semitones = total;
+        Ok(())
+    }
+
+    /// Get the current pitch shift in semitones.
+    pub fn pitch(&self) -> f64 {
+        self.semitones
+    }
+
+    /// Get the factor by which the current pitch shift changes the length of the audio.
+    /// This is the number of output frames per input frame, relative to the original
+    /// ratio of the wrapped resampler.
+    pub fn length_factor(&self) -> f64 {
+        1.0 / pitch_factor(self.semitones)
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    /// Note that changing the ratio directly on the wrapped resampler
+    /// is not reflected by [pitch](PitchShift::pitch).
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+}
+
+impl<T, R> Resampler<T> for PitchShift<R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.resampler
+            .process_into_buffer(wave_in, wave_out, active_channels_mask)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.resampler.process_into_buffer_with_scratch(
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            scratch,
+        )
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.resampler
+            .process_split_into_buffer(wave_in, wave_out, active_channels_mask)
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    /// Load the history of the wrapped resampler. Like a reset, this removes the pitch shift.
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)?;
+        self.semitones = 0.0;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "PitchShift")
+    }
+
+    /// Reset the wrapped resampler, and remove the pitch shift.
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.semitones = 0.0;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_fast_resampler;
+    use crate::{FastFixedIn, PitchShift, Resampler};
+
+    fn make_resampler() -> FastFixedIn<f64> {
+        make_fast_resampler(1.0, 2.0, 1024, 1)
+    }
+
+    #[test]
+    fn octave_up() {
+        let mut resampler = PitchShift::new(make_resampler());
+        resampler.set_pitch(11.0, 100.0, false).unwrap();
+        assert_eq!(resampler.pitch(), 12.0);
+        assert!((resampler.length_factor() - 0.5).abs() < 1.0e-12);
+        let waves = vec![vec![0.0f64; 1024]; 1];
+        let mut frames = 0;
+        for _ in 0..10 {
+            frames += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        assert!((frames as i64 - 5120).abs() <= 8);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut resampler = PitchShift::new(make_resampler());
+        resampler.set_pitch(-5.0, 0.0, false).unwrap();
+        assert!(resampler.set_pitch(13.0, 0.0, false).is_err());
+        assert_eq!(resampler.pitch(), -5.0);
+        resampler.reset();
+        assert_eq!(resampler.pitch(), 0.0);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::error::ResamplerConstructionError;
+use crate::{Resampler, Sample};
+
+/// A pool of resamplers, for services that process many short streams.
+///
+/// Creating a resampler can take much longer than processing a short stream,
+/// mostly because of the filter design and the FFT planning.
+/// The pool keeps resamplers that are no longer used, and hands them out again
+/// instead of creating new ones.
+/// Resamplers are reset when they are returned to the pool,
+/// so a recycled resampler behaves exactly like a newly created one.
+///
+/// New resamplers are created by a factory closure, that is given the number of channels.
+/// The resamplers are matched by their number of channels,
+/// and a request for a number of channels that has no idle resampler creates a new one.
+/// At most `max_idle` resamplers are kept, and any further returned resamplers are dropped.
+///
+/// For the sinc resamplers, the factory can also share the filters between all resamplers
+/// of the pool by using a [SincFilterBank](crate::SincFilterBank).
+/// ```
+/// use rubato::{Resampler, ResamplerPool, SincFilterBank, SincFixedIn};
+/// use rubato::{SincInterpolationParameters, SincInterpolationType};
+/// let parameters =
+///     SincInterpolationParameters::from_specification(0.1, 100.0, SincInterpolationType::Linear, 128)
+///         .unwrap();
+/// let bank = SincFilterBank::<f32>::new(48000.0 / 44100.0, &parameters).unwrap();
+/// let mut pool = ResamplerPool::new(
+///     |channels| SincFixedIn::new_with_bank(&bank, 1.0, 1024, channels),
+///     8,
+/// );
+/// for _ in 0..3 {
+///     let mut resampler = pool.acquire(2).unwrap();
+///     let waves_in = vec![vec![0.0f32; 1024]; 2];
+///     let waves_out = resampler.process(&waves_in, None).unwrap();
+///     pool.release(resampler);
+/// }
+/// assert_eq!(pool.nbr_idle(), 1);
+/// ```
+pub struct ResamplerPool<R, F> {
+    factory: F,
+    idle: Vec<R>,
+    max_idle: usize,
+}
+
+impl<R, F> ResamplerPool<R, F>
+where
+    F: FnMut(usize) -> Result<R, ResamplerConstructionError>,
+{
+    /// Create a new empty pool.
+    ///
+    /// Parameters are:
+    /// - `factory`: Closure that creates a new resampler for the given number of channels.
+    /// - `max_idle`: Maximum number of idle resamplers to keep.
+    pub fn new(factory: F, max_idle: usize) -> Self {
+        ResamplerPool {
+            factory,
+            idle: Vec::with_capacity(max_idle),
+            max_idle,
+        }
+    }
+
+    /// Get a resampler with the given number of channels.
+    /// An idle resampler is returned if there is one, and otherwise a new one is created.
+    /// The error of the factory is returned if creating a new resampler fails.
+    pub fn acquire<T>(&mut self, nbr_channels: usize) -> Result<R, ResamplerConstructionError>
+    where
+        T: Sample,
+        R: Resampler<T>,
+    {
+        if let Some(index) = self
+            .idle
+            .iter()
+            .position(|resampler| resampler.nbr_channels() == nbr_channels)
+        {
+            return Ok(self.idle.swap_remove(index));
+        }
+        debug!(
+            "No idle resampler with {} channels, creating one",
+            nbr_channels
+        );
+        (self.factory)(nbr_channels)
+    }
+
+    /// Return a resampler to the pool. The resampler is reset,
+    /// and dropped if the pool already holds `max_idle` resamplers.
+    pub fn release<T>(&mut self, mut resampler: R)
+    where
+        T: Sample,
+        R: Resampler<T>,
+    {
+        if self.idle.len() < self.max_idle {
+            resampler.reset();
+            self.idle.push(resampler);
+        }
+    }
+
+    /// Create resamplers ahead of time, until the pool holds `count` idle resamplers
+    /// with the given number of channels, or is full.
+    pub fn prefill<T>(
+        &mut self,
+        nbr_channels: usize,
+        count: usize,
+    ) -> Result<(), ResamplerConstructionError>
+    where
+        T: Sample,
+        R: Resampler<T>,
+    {
+        let mut existing = self
+            .idle
+            .iter()
+            .filter(|resampler| resampler.nbr_channels() == nbr_channels)
+            .count();
+        while existing < count && self.idle.len() < self.max_idle {
+            let resampler = (self.factory)(nbr_channels)?;
+            self.idle.push(resampler);
+            existing += 1;
+        }
+        Ok(())
+    }
+
+    /// Get the number of idle resamplers in the pool.
+    pub fn nbr_idle(&self) -> usize {
+        self.idle.len()
+    }
+
+    /// Drop all idle resamplers.
+    pub fn clear(&mut self) {
+        self.idle.clear();
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{FastFixedIn, PolynomialDegree, Resampler, ResamplerPool};
+    use std::cell::Cell;
+
+    #[test]
+    fn recycle_resamplers() {
+        let created = Cell::new(0);
+        let mut pool = ResamplerPool::new(
+            |channels| {
+                created.set(created.get() + 1);
+                FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 64, channels)
+            },
+            2,
+        );
+        let waves = vec![vec![0.5f64; 64]; 2];
+        let mut reference = pool.acquire(2).unwrap();
+        let expected = reference.process(&waves, None).unwrap();
+        pool.release(reference);
+        // The recycled resampler is reset, and gives the same output as a new one
+        let mut resampler = pool.acquire(2).unwrap();
+        assert_eq!(created.get(), 1);
+        assert_eq!(resampler.process(&waves, None).unwrap(), expected);
+        // Another number of channels needs a new resampler
+        let mono = pool.acquire(1).unwrap();
+        assert_eq!(created.get(), 2);
+        assert_eq!(mono.nbr_channels(), 1);
+        pool.release(resampler);
+        pool.release(mono);
+        pool.prefill(2, 3).unwrap();
+        // The pool is full
+        assert_eq!(created.get(), 2);
+        assert_eq!(pool.nbr_idle(), 2);
+        let extra = FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 64, 2).unwrap();
+        pool.release(extra);
+        assert_eq!(pool.nbr_idle(), 2);
+        pool.clear();
+        assert_eq!(pool.nbr_idle(), 0);
+    }
+}
@@ -0,0 +1,259 @@
+/// Number of grid points per extremal frequency.
+const GRID_DENSITY: usize = 16;
+/// Maximum number of iterations of the exchange algorithm.
+const MAX_ITERATIONS: usize = 100;
+
+/// Helper function. Design a symmetric odd length lowpass FIR filter with the Parks-McClellan
+/// (Remez exchange) algorithm. The filter minimizes the maximum weighted deviation from
+/// unity gain in the passband and from zero in the stopband.
+///
+/// Parameters are:
+/// - `npoints`: Length of the filter, must be odd.
+/// - `f_pass`: Passband edge in cycles per sample, must be below `f_stop`.
+/// - `f_stop`: Stopband edge in cycles per sample, must be below 0.5.
+/// - `stop_weight`: Weight of the stopband deviation relative to the passband deviation.
+pub fn remez_lowpass(npoints: usize, f_pass: f64, f_stop: f64, stop_weight: f64) -> Vec<f64> {
+    assert!(npoints % 2 == 1, "Filter length must be odd");
+    assert!(f_pass < f_stop && f_stop < 0.5, "Invalid band edges");
+    let half = npoints / 2;
+    let nbr_coeffs = half + 1;
+    let nbr_ext = nbr_coeffs + 1;
+
+    // Frequency grid over the passband and the stopband, in cycles per sample
+    let grid_step = 0.5 / (GRID_DENSITY * nbr_coeffs) as f64;
+    let mut grid = Vec::new();
+    let nbr_pass = ((f_pass / grid_step).ceil() as usize).max(1);
+    for n in 0..=nbr_pass {
+        grid.push(f_pass * n as f64 / nbr_pass as f64);
+    }
+    let first_stop = grid.len();
+    let nbr_stop = (((0.5 - f_stop) / grid_step).ceil() as usize).max(1);
+    for n in 0..=nbr_stop {
+        grid.push(f_stop + (0.5 - f_stop) * n as f64 / nbr_stop as f64);
+    }
+    let desired = |idx: usize| if idx < first_stop { 1.0 } else { 0.0 };
+    let weight = |idx: usize| if idx < first_stop { 1.0 } else { stop_weight };
+    let grid_x: Vec<f64> = grid
+        .iter()
+        .map(|f| (2.0 * std::f64::consts::PI * f).cos())
+        .collect();
+
+    // Start with extremal frequencies spread evenly over the grid
+    let mut extremals: Vec<usize> = (0..nbr_ext)
+        .map(|n| n * (grid.len() - 1) / (nbr_ext - 1))
+        .collect();
+    let mut interp = Interpolation::new(&grid_x, &extremals, &desired, &weight);
+    for _ in 0..MAX_ITERATIONS {
+        let error: Vec<f64> = (0..grid.len())
+            .map(|idx| weight(idx) * (desired(idx) - interp.evaluate(grid_x[idx])))
+            .collect();
+        let new_extremals = find_extremals(&error, nbr_ext);
+        if new_extremals.len() < nbr_ext {
+            break;
+        }
+        let max_error = new_extremals
+            .iter()
+            .map(|idx| error[*idx].abs())
+            .fold(0.0, f64::max);
+        let converged = (max_error - interp.delta.abs()) <= 1.0e-6 * max_error;
+        trace!(
+            "Remez iteration, deviation {}, max error {}",
+            interp.delta,
+            max_error
+        );
+        extremals = new_extremals;
+        interp = Interpolation::new(&grid_x, &extremals, &desired, &weight);
+        if converged {
+            break;
+        }
+    }
+    debug!(
+        "Designed equiripple filter, length: {}, deviation: {}",
+        npoints, interp.delta
+    );
+
+    // Sample the frequency response and get the coefficients by an inverse DFT
+    let response: Vec<f64> = (0..=half)
+        .map(|n| interp.evaluate((2.0 * std::f64::consts::PI * n as f64 / npoints as f64).cos()))
+        .collect();
+    (0..npoints)
+        .map(|k| {
+            let offset = k as f64 - half as f64;
+            let sum: f64 = response
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(n, value)| {
+                    value * (2.0 * std::f64::consts::PI * n as f64 * offset / npoints as f64).cos()
+                })
+                .sum();
+            (response[0] + 2.0 * sum) / npoints as f64
+        })
+        .collect()
+}
+
+/// Barycentric Lagrange interpolation of the amplitude response through the extremal points.
+struct Interpolation {
+    points: Vec<f64>,
+    values: Vec<f64>,
+    weights: Vec<f64>,
+    delta: f64,
+}
+
+impl Interpolation {
+    fn new(
+        grid_x: &[f64],
+        extremals: &[usize],
+        desired: &dyn Fn(usize) -> f64,
+        weight: &dyn Fn(usize) -> f64,
+    ) -> Self {
+        let x: Vec<f64> = extremals.iter().map(|idx| grid_x[*idx]).collect();
+        let all_weights = barycentric_weights(&x);
+        let mut num = 0.0;
+        let mut den = 0.0;
+        let mut sign = 1.0;
+        for (n, idx) in extremals.iter().enumerate() {
+            num += all_weights[n] * desired(*idx);
+            den += sign * all_weights[n] / weight(*idx);
+            sign = -sign;
+        }
+        let delta = num / den;
+        // Interpolate through all but the last extremal point
+        let nbr_points = extremals.len() - 1;
+        let points = x[..nbr_points].to_vec();
+        let mut sign = 1.0;
+        let values = extremals[..nbr_points]
+            .iter()
+            .map(|idx| {
+                let value = desired(*idx) - sign * delta / weight(*idx);
+                sign = -sign;
+                value
+            })
+            .collect();
+        let weights = barycentric_weights(&points);
+        Interpolation {
+            points,
+            values,
+            weights,
+            delta,
+        }
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        let mut num = 0.0;
+        let mut den = 0.0;
+        for ((point, value), weight) in self
+            .points
+            .iter()
+            .zip(self.values.iter())
+            .zip(self.weights.iter())
+        {
+            let diff = x - point;
+            if diff.abs() < 1.0e-14 {
+                return *value;
+            }
+            let term = weight / diff;
+            num += term * value;
+            den += term;
+        }
+        num / den
+    }
+}
+
+/// Calculate barycentric weights `1 / prod(x_k - x_j)`, scaled to a maximum magnitude of one.
+/// The products are calculated in the log domain to avoid overflow for many points.
+fn barycentric_weights(x: &[f64]) -> Vec<f64> {
+    let logs: Vec<(f64, f64)> = x
+        .iter()
+        .enumerate()
+        .map(|(k, xk)| {
+            let mut log = 0.0;
+            let mut sign = 1.0;
+            for (j, xj) in x.iter().enumerate() {
+                if j != k {
+                    let diff = xk - xj;
+                    log -= diff.abs().ln();
+                    if diff < 0.0 {
+                        sign = -sign;
+                    }
+                }
+            }
+            (log, sign)
+        })
+        .collect();
+    let max_log = logs.iter().map(|(log, _)| *log).fold(f64::MIN, f64::max);
+    logs.iter()
+        .map(|(log, sign)| sign * (log - max_log).exp())
+        .collect()
+}
+
+/// Find the new set of extremal frequencies, as grid indices.
+/// The error alternates in sign between the returned points.
+fn find_extremals(error: &[f64], nbr_ext: usize) -> Vec<usize> {
+    // Keep the point with the largest error of each run of points with the same sign
+    let mut extremals: Vec<usize> = Vec::new();
+    for idx in 0..error.len() {
+        if let Some(last) = extremals.last_mut() {
+            if error[*last].signum() == error[idx].signum() {
+                if error[idx].abs() > error[*last].abs() {
+                    *last = idx;
+                }
+                continue;
+            }
+        }
+        extremals.push(idx);
+    }
+    // Remove surplus points from the ends
+    while extremals.len() > nbr_ext {
+        let first = error[extremals[0]].abs();
+        let last = error[*extremals.last().unwrap()].abs();
+        if first < last {
+            extremals.remove(0);
+        } else {
+            extremals.pop();
+        }
+    }
+    extremals
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use super::remez_lowpass;
+
+    fn response(filter: &[f64], f: f64) -> f64 {
+        let center = (filter.len() / 2) as f64;
+        filter
+            .iter()
+            .enumerate()
+            .map(|(n, h)| h * (2.0 * std::f64::consts::PI * f * (n as f64 - center)).cos())
+            .sum()
+    }
+
+    #[test]
+    fn lowpass() {
+        let filter = remez_lowpass(63, 0.1, 0.15, 10.0);
+        assert_eq!(filter.len(), 63);
+        for n in 0..31 {
+            assert!((filter[n] - filter[62 - n]).abs() < 1.0e-12);
+        }
+        let mut max_pass: f64 = 0.0;
+        let mut max_stop: f64 = 0.0;
+        for n in 0..=1000 {
+            let f = 0.5 * n as f64 / 1000.0;
+            let value = response(&filter, f);
+            if f <= 0.1 {
+                max_pass = max_pass.max((value - 1.0).abs());
+            } else if f >= 0.15 {
+                max_stop = max_stop.max(value.abs());
+            }
+        }
+        // Equiripple, the passband deviation is the stopband one times the weight
+        assert!(max_stop < 1.0e-3, "stopband deviation {}", max_stop);
+        assert!(
+            (max_pass / max_stop - 10.0).abs() < 0.5,
+            "passband deviation {}, stopband deviation {}",
+            max_pass,
+            max_stop
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::Sample;
+use num_traits::ToPrimitive;
+
+/// Second order error feedback, giving a noise transfer function of `(1 - z^-1)^2`.
+const SECOND_ORDER: [f64; 2] = [2.0, -1.0];
+/// Ninth order F-weighted error feedback by Wannamaker, optimized for 44.1 kHz.
+const F_WEIGHTED_9: [f64; 9] = [
+    2.412, -3.370, 3.937, -4.174, 3.353, -2.205, 1.281, -0.569, 0.0847,
+];
+/// Scale factor from the range -1.0 to 1.0 to 16-bit integers.
+const SCALE: f64 = 32768.0;
+
+/// Noise shaping curves that can be used when requantizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseShaping {
+    /// Plain TPDF dither without noise shaping. The noise spectrum is flat.
+    None,
+    /// A second order highpass shape, moving the noise towards the Nyquist frequency.
+    /// This works at any sample rate.
+    SecondOrder,
+    /// A ninth order F-weighted shape, that places the noise where the ear is least sensitive.
+    /// The curve is designed for 44.1 kHz and is less effective at other sample rates.
+    FWeighted9,
+}
+
+impl NoiseShaping {
+    fn coefficients(&self) -> &'static [f64] {
+        match self {
+            NoiseShaping::None => &[],
+            NoiseShaping::SecondOrder => &SECOND_ORDER,
+            NoiseShaping::FWeighted9 => &F_WEIGHTED_9,
+        }
+    }
+}
+
+/// A quantizer that reduces floating point samples to 16-bit integers,
+/// with TPDF dither and optional noise shaping.
+///
+/// This is intended as the last stage of a conversion chain,
+/// for example after a [Resampler](crate::Resampler) when writing 16-bit output.
+/// The input samples are expected in the range -1.0 to 1.0, and values outside are clipped.
+/// The quantization error of each channel is filtered and fed back,
+/// which shapes the spectrum of the added noise according to the selected [NoiseShaping].
+/// ```
+/// use rubato::{NoiseShaping, Requantizer};
+/// let mut requantizer = Requantizer::new(2, NoiseShaping::SecondOrder);
+/// let waves_in = vec![vec![0.25f64; 1024]; 2];
+/// let mut waves_out = vec![vec![0i16; 1024]; 2];
+/// requantizer.process_into_buffer(&waves_in, &mut waves_out).unwrap();
+/// ```
+pub struct Requantizer {
+    nbr_channels: usize,
+    noise_shaping: NoiseShaping,
+    errors: Vec<Vec<f64>>,
+    rng_state: u32,
+}
+
+impl Requantizer {
+    /// Create a new Requantizer.
+    ///
+    /// Parameters are:
+    /// - `nbr_channels`: Number of channels in input/output.
+    /// - `noise_shaping`: Noise shaping curve, see `NoiseShaping`.
+    pub fn new(nbr_channels: usize, noise_shaping: NoiseShaping) -> Self {
+        debug!(
+            "Create new Requantizer, channels: {}, noise shaping: {:?}",
+            nbr_channels, noise_shaping
+        );
+        Requantizer {
+            nbr_channels,
+            noise_shaping,
+            errors: vec![vec![0.0; noise_shaping.coefficients().len()]; nbr_channels],
+            rng_state: 0x12345678,
+        }
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    /// Get the noise shaping curve.
+    pub fn noise_shaping(&self) -> NoiseShaping {
+        self.noise_shaping
+    }
+
+    /// Quantize a chunk of samples.
+    /// All input channels must have the same length,
+    /// and the output channels must be at least as long as the input.
+    /// Returns the number of frames written to each output channel.
+    pub fn process_into_buffer<T, Vin, Vout>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+    ) -> ResampleResult<usize>
+    where
+        T: Sample + ToPrimitive,
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[i16]>,
+    {
+        if wave_in.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: self.nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: self.nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        let frames = wave_in.first().map(|wave| wave.as_ref().len()).unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() < frames {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if wave.as_mut().len() < frames {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual: wave.as_mut().len(),
+                });
+            }
+        }
+        let coefficients = self.noise_shaping.coefficients();
+        for chan in 0..self.nbr_channels {
+            let wave_in = &wave_in[chan].as_ref()[..frames];
+            let wave_out = &mut wave_out[chan].as_mut()[..frames];
+            for (sample_in, sample_out) in wave_in.iter().zip(wave_out.iter_mut()) {
+                let dither = self.tpdf();
+                let errors = &mut self.errors[chan];
+                let feedback: f64 = coefficients
+                    .iter()
+                    .zip(errors.iter())
+                    .map(|(c, e)| c * e)
+                    .sum();
+                let value = sample_in.to_f64().unwrap_or(0.0) * SCALE - feedback;
+                let quantized = (value + dither).round();
+                // The error is taken before clipping, to keep the feedback bounded
+                if !errors.is_empty() {
+                    errors.rotate_right(1);
+                    errors[0] = quantized - value;
+                }
+                *sample_out = quantized.clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Reset the noise shaping filters of all channels.
+    pub fn reset(&mut self) {
+        self.errors
+            .iter_mut()
+            .for_each(|errors| errors.iter_mut().for_each(|e| *e = 0.0));
+    }
+
+    /// Get a TPDF dither value, in the range -1.0 to 1.0 LSB.
+    fn tpdf(&mut self) -> f64 {
+        self.uniform() - self.uniform()
+    }
+
+    /// Get a uniformly distributed random value in the range 0.0 to 1.0, using a xorshift generator.
+    fn uniform(&mut self) -> f64 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        x as f64 / u32::MAX as f64
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{NoiseShaping, Requantizer};
+    use std::f64::consts::PI;
+
+    /// Quantize a quiet sine and return the low frequency power of the error.
+    fn low_frequency_noise(noise_shaping: NoiseShaping) -> f64 {
+        let mut requantizer = Requantizer::new(1, noise_shaping);
+        let wave_in: Vec<f64> = (0..8192).map(|n| 0.01 * (n as f64 * 0.01).sin()).collect();
+        let mut wave_out = vec![vec![0i16; 8192]];
+        requantizer
+            .process_into_buffer(&[&wave_in], &mut wave_out)
+            .unwrap();
+        let error: Vec<f64> = wave_in
+            .iter()
+            .zip(wave_out[0].iter())
+            .map(|(x, y)| *y as f64 - x * 32768.0)
+            .collect();
+        // Sum the power of the lowest 1/32 of the spectrum, with a Hann window
+        let len = error.len();
+        (1..len / 64)
+            .map(|k| {
+                let (re, im) = error
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (n, e)| {
+                        let window = 0.5 - 0.5 * (2.0 * PI * n as f64 / len as f64).cos();
+                        let phase = 2.0 * PI * (k * n) as f64 / len as f64;
+                        (re + window * e * phase.cos(), im - window * e * phase.sin())
+                    });
+                re * re + im * im
+            })
+            .sum()
+    }
+
+    #[test]
+    fn quantize_with_dither() {
+        let mut requantizer = Requantizer::new(2, NoiseShaping::None);
+        let wave_in = vec![vec![0.5f64; 1000], vec![-2.0f64; 1000]];
+        let mut wave_out = vec![vec![0i16; 1000]; 2];
+        let frames = requantizer
+            .process_into_buffer(&wave_in, &mut wave_out)
+            .unwrap();
+        assert_eq!(frames, 1000);
+        assert!(wave_out[0].iter().all(|v| (*v - 16384).abs() <= 1));
+        assert!(wave_out[1].iter().all(|v| *v == i16::MIN));
+    }
+
+    #[test]
+    fn shaped_noise() {
+        let flat = low_frequency_noise(NoiseShaping::None);
+        let second = low_frequency_noise(NoiseShaping::SecondOrder);
+        let fweighted = low_frequency_noise(NoiseShaping::FWeighted9);
+        assert!(second < 0.01 * flat, "flat {} shaped {}", flat, second);
+        assert!(
+            fweighted < 0.25 * flat,
+            "flat {} shaped {}",
+            flat,
+            fweighted
+        );
+    }
+
+    #[test]
+    fn wrong_number_of_channels() {
+        let mut requantizer = Requantizer::new(2, NoiseShaping::None);
+        let wave_in = vec![vec![0.0f32; 10]; 1];
+        let mut wave_out = vec![vec![0i16; 10]; 2];
+        assert!(requantizer
+            .process_into_buffer(&wave_in, &mut wave_out)
+            .is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::error::ResampleResult;
+use crate::{heap_size_nested, process_wrapped, Resampler, ResamplerConfig, Sample};
+
+/// A wrapper for a [Resampler] that can process a stream backwards, for reverse playback and scrubbing.
+///
+/// When playing backwards, the chunks are taken from the source going backwards in time,
+/// but each chunk is given in the normal forward order of the source.
+/// The wrapper reverses the frames of each chunk before passing it on to the wrapped resampler,
+/// so that the wrapped resampler sees one continuous stream, and keeps a valid history
+/// from one chunk to the next.
+/// The output is then in playback order, and needs no further reversing.
+///
+/// The rate can be changed while reversed in the same way as when playing forwards,
+/// by updating the ratio of the wrapped resampler.
+///
+/// The history of the wrapped resampler only applies to one direction.
+/// It is therefore cleared when the direction is changed with [set_reversed](Reversible::set_reversed),
+/// and the first chunk after a change starts with the same transient as after a reset.
+///
+/// The reversed chunks are copied to an internal buffer.
+/// This buffer is allocated the first time it is needed, and is then reused.
+/// ```
+/// use rubato::{FastFixedIn, PolynomialDegree, Resampler, Reversible};
+/// let resampler = FastFixedIn::<f64>::new(1.0, 2.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+/// let mut resampler = Reversible::new(resampler);
+/// resampler.set_reversed(true);
+/// let source = vec![vec![0.0f64; 1024]; 2];
+/// // Take the chunks from the end of the source
+/// for end in [1024, 768, 512, 256] {
+///     let chunk = source.iter().map(|chan| &chan[end - 256..end]).collect::<Vec<_>>();
+///     let waves_out = resampler.process(&chunk, None).unwrap();
+///     assert!(waves_out[0].len() <= resampler.output_frames_max());
+/// }
+/// ```
+pub struct Reversible<R, T> {
+    resampler: R,
+    buffer: Vec<Vec<T>>,
+    reversed: bool,
+}
+
+impl<R, T> Reversible<R, T>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    /// Create a new Reversible wrapper, that starts out processing forwards.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    pub fn new(resampler: R) -> Self {
+        Reversible {
+            resampler,
+            buffer: Vec::new(),
+            reversed: false,
+        }
+    }
+
+    /// Set the direction of processing. Changing the direction resets the wrapped resampler.
+    pub fn set_reversed(&mut self, reversed: bool) {
+        if reversed != self.reversed {
+            debug!("Changing direction, reversed: {}", reversed);
+            self.reversed = reversed;
+            self.resampler.reset();
+        }
+    }
+
+    /// Returns true if the input is processed backwards.
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)> {
+        if !self.reversed {
+            return process_wrapped(
+                &mut self.resampler,
+                wave_in,
+                wave_out,
+                active_channels_mask,
+                scratch,
+            );
+        }
+        if self.buffer.len() < wave_in.len() {
+            self.buffer.resize(wave_in.len(), Vec::new());
+        }
+        for (wave, buffer) in wave_in.iter().zip(self.buffer.iter_mut()) {
+            buffer.clear();
+            buffer.extend(wave.as_ref().iter().rev());
+        }
+        process_wrapped(
+            &mut self.resampler,
+            &self.buffer[..wave_in.len()],
+            wave_out,
+            active_channels_mask,
+            scratch,
+        )
+    }
+}
+
+impl<T, R> Resampler<T> for Reversible<R, T>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    /// Load the history of the wrapped resampler.
+    /// When reversed, the history is the frames that follow the stream in the source,
+    /// given in the forward order of the source.
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        if !self.reversed {
+            return self.resampler.set_history(history);
+        }
+        if self.buffer.len() < history.len() {
+            self.buffer.resize(history.len(), Vec::new());
+        }
+        for (wave, buffer) in history.iter().zip(self.buffer.iter_mut()) {
+            buffer.clear();
+            buffer.extend(wave.as_ref().iter().rev());
+        }
+        self.resampler.set_history(&self.buffer[..history.len()])
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage() + heap_size_nested(&self.buffer)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "Reversible")
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::make_fast_resampler;
+    use crate::{FastFixedIn, Resampler, Reversible};
+
+    fn make_resampler() -> FastFixedIn<f64> {
+        make_fast_resampler(1.5, 2.0, 64, 1)
+    }
+
+    #[test]
+    fn same_as_reversed_source() {
+        let source = (0..256).map(|n| (n as f64 * 0.1).sin()).collect::<Vec<_>>();
+        let reversed_source = source.iter().rev().copied().collect::<Vec<_>>();
+        let mut reference = make_resampler();
+        let mut resampler = Reversible::new(make_resampler());
+        resampler.set_reversed(true);
+        assert!(resampler.is_reversed());
+        for n in 0..4 {
+            let chunk = &source[256 - 64 * (n + 1)..256 - 64 * n];
+            let expected = reference
+                .process(&[&reversed_source[64 * n..64 * (n + 1)]], None)
+                .unwrap();
+            assert_eq!(resampler.process(&[chunk], None).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn change_direction() {
+        let waves = vec![vec![0.5f64; 64]; 1];
+        let mut reference = make_resampler();
+        let mut resampler = Reversible::new(make_resampler());
+        let expected = reference.process(&waves, None).unwrap();
+        assert_eq!(resampler.process(&waves, None).unwrap(), expected);
+        // Changing direction starts over from a cleared history
+        resampler.set_reversed(true);
+        assert_eq!(resampler.process(&waves, None).unwrap(), expected);
+        // Keeping the direction keeps the history
+        resampler.set_reversed(true);
+        assert_ne!(resampler.process(&waves, None).unwrap(), expected);
+    }
+}
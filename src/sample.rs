@@ -1,10 +1,22 @@
-use crate::sinc_interpolator::{AvxSample, NeonSample, SseSample};
+#[cfg(any(feature = "f32", feature = "f64"))]
+use crate::asynchro_sinc::select_simd_interpolator;
+use crate::sinc_interpolator::SincInterpolator;
 
 /// The trait governing a single sample.
 ///
 /// There are two types which implements this trait so far:
-/// * [f32]
-/// * [f64]
+/// * [f32], with the `f32` feature
+/// * [f64], with the `f64` feature
+///
+/// The trait can also be implemented for other numeric types,
+/// for example a fixed-point wrapper or an instrumented float,
+/// to use them with all the generic resamplers.
+/// Such a type must implement the arithmetic traits listed below,
+/// as well as [CoerceFrom] for [usize], [f64] and [f32].
+/// The FFT based resamplers use the generic scalar code paths of RustFFT for any type
+/// other than f32 and f64.
+/// The sinc resamplers use the scalar interpolator unless
+/// [simd_interpolator](Sample::simd_interpolator) is implemented.
 pub trait Sample
 where
     Self: Copy
@@ -21,9 +33,6 @@ where
         + std::ops::DivAssign
         + std::ops::SubAssign
         + std::ops::AddAssign
-        + AvxSample
-        + SseSample
-        + NeonSample
         + Send,
 {
     const PI: Self;
@@ -34,6 +43,18 @@ where
     /// Calculate the cosine of `self`.
     fn cos(self) -> Self;
 
+    /// Calculate the square root of `self`.
+    fn sqrt(self) -> Self;
+
+    /// Create a SIMD accelerated sinc interpolator for the given sincs,
+    /// or return `None` if there is none for this type or the current CPU.
+    /// The sinc resamplers then fall back to the scalar interpolator.
+    /// The default implementation returns `None`.
+    #[allow(unused_variables)]
+    fn simd_interpolator(sincs: &[Vec<Self>]) -> Option<Box<dyn SincInterpolator<Self> + Sync>> {
+        None
+    }
+
     /// Coerce `value` into the current type.
     ///
     /// Coercions are governed through the private `CoerceFrom` trait.
@@ -45,6 +66,7 @@ where
     }
 }
 
+#[cfg(feature = "f32")]
 impl Sample for f32 {
     const PI: Self = std::f32::consts::PI;
 
@@ -55,8 +77,17 @@ impl Sample for f32 {
     fn cos(self) -> Self {
         f32::cos(self)
     }
+
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+
+    fn simd_interpolator(sincs: &[Vec<Self>]) -> Option<Box<dyn SincInterpolator<Self> + Sync>> {
+        select_simd_interpolator(sincs)
+    }
 }
 
+#[cfg(feature = "f64")]
 impl Sample for f64 {
     const PI: Self = std::f64::consts::PI;
 
@@ -67,6 +98,14 @@ impl Sample for f64 {
     fn cos(self) -> Self {
         f64::cos(self)
     }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn simd_interpolator(sincs: &[Vec<Self>]) -> Option<Box<dyn SincInterpolator<Self> + Sync>> {
+        select_simd_interpolator(sincs)
+    }
 }
 
 /// The trait used to coerce a value infallibly from one type to another.
@@ -74,6 +113,7 @@ impl Sample for f64 {
 /// This is similar to doing `value as T` where `T` is a floating point type.
 /// Loss of precision may happen during coercions if the coerced from value
 /// doesn't fit fully within the target type.
+/// It must be implemented for [usize], [f64] and [f32] by any type implementing [Sample].
 pub trait CoerceFrom<T> {
     /// Perform a coercion from `value` into the current type.
     fn coerce_from(value: T) -> Self;
@@ -114,3 +154,193 @@ impl CoerceFrom<f32> for f64 {
         value as f64
     }
 }
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{CoerceFrom, FftFixedIn, Resampler, Sample, SincFixedIn};
+    use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+    use num_traits::{FromPrimitive, Num, One, Signed, Zero};
+    use std::cell::Cell;
+    use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign};
+    use std::ops::{Sub, SubAssign};
+
+    thread_local! {
+        static MULTIPLICATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// An f64 that counts the multiplications done with it.
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    struct Counting(f64);
+
+    macro_rules! impl_op {
+        ($trait:ident, $fn:ident, $assign_trait:ident, $assign_fn:ident, $op:tt) => {
+            impl $trait for Counting {
+                type Output = Self;
+                fn $fn(self, rhs: Self) -> Self {
+                    Counting(self.0 $op rhs.0)
+                }
+            }
+
+            impl $assign_trait for Counting {
+                fn $assign_fn(&mut self, rhs: Self) {
+                    *self = *self $op rhs;
+                }
+            }
+        };
+    }
+
+    impl_op!(Add, add, AddAssign, add_assign, +);
+    impl_op!(Sub, sub, SubAssign, sub_assign, -);
+    impl_op!(Div, div, DivAssign, div_assign, /);
+    impl_op!(Rem, rem, RemAssign, rem_assign, %);
+
+    impl Mul for Counting {
+        type Output = Self;
+        fn mul(self, rhs: Self) -> Self {
+            MULTIPLICATIONS.with(|count| count.set(count.get().wrapping_add(1)));
+            Counting(self.0 * rhs.0)
+        }
+    }
+
+    impl MulAssign for Counting {
+        fn mul_assign(&mut self, rhs: Self) {
+            *self = *self * rhs;
+        }
+    }
+
+    impl Neg for Counting {
+        type Output = Self;
+        fn neg(self) -> Self {
+            Counting(-self.0)
+        }
+    }
+
+    impl Zero for Counting {
+        fn zero() -> Self {
+            Counting(0.0)
+        }
+        fn is_zero(&self) -> bool {
+            self.0 == 0.0
+        }
+    }
+
+    impl One for Counting {
+        fn one() -> Self {
+            Counting(1.0)
+        }
+    }
+
+    impl Num for Counting {
+        type FromStrRadixErr = <f64 as Num>::FromStrRadixErr;
+        fn from_str_radix(value: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+            f64::from_str_radix(value, radix).map(Counting)
+        }
+    }
+
+    impl Signed for Counting {
+        fn abs(&self) -> Self {
+            Counting(self.0.abs())
+        }
+        fn abs_sub(&self, other: &Self) -> Self {
+            Counting((self.0 - other.0).max(0.0))
+        }
+        fn signum(&self) -> Self {
+            Counting(self.0.signum())
+        }
+        fn is_positive(&self) -> bool {
+            self.0 > 0.0
+        }
+        fn is_negative(&self) -> bool {
+            self.0 < 0.0
+        }
+    }
+
+    impl FromPrimitive for Counting {
+        fn from_i64(value: i64) -> Option<Self> {
+            Some(Counting(value as f64))
+        }
+        fn from_u64(value: u64) -> Option<Self> {
+            Some(Counting(value as f64))
+        }
+        fn from_f64(value: f64) -> Option<Self> {
+            Some(Counting(value))
+        }
+    }
+
+    impl CoerceFrom<usize> for Counting {
+        fn coerce_from(value: usize) -> Self {
+            Counting(value as f64)
+        }
+    }
+
+    impl CoerceFrom<f64> for Counting {
+        fn coerce_from(value: f64) -> Self {
+            Counting(value)
+        }
+    }
+
+    impl CoerceFrom<f32> for Counting {
+        fn coerce_from(value: f32) -> Self {
+            Counting(value as f64)
+        }
+    }
+
+    impl Sample for Counting {
+        const PI: Self = Counting(std::f64::consts::PI);
+
+        fn sin(self) -> Self {
+            Counting(self.0.sin())
+        }
+
+        fn cos(self) -> Self {
+            Counting(self.0.cos())
+        }
+
+        fn sqrt(self) -> Self {
+            Counting(self.0.sqrt())
+        }
+    }
+
+    fn check_same_output<R, S>(mut custom: R, mut reference: S)
+    where
+        R: Resampler<Counting>,
+        S: Resampler<f64>,
+    {
+        let wave = (0..1024)
+            .map(|n| (n as f64 * 0.1).sin())
+            .collect::<Vec<_>>();
+        let custom_wave = wave.iter().map(|v| Counting(*v)).collect::<Vec<_>>();
+        MULTIPLICATIONS.with(|count| count.set(0));
+        let out = custom
+            .process(&[&custom_wave[..custom.input_frames_next()]], None)
+            .unwrap();
+        assert!(MULTIPLICATIONS.with(|count| count.get()) > 0);
+        let expected = reference
+            .process(&[&wave[..reference.input_frames_next()]], None)
+            .unwrap();
+        assert_eq!(out[0].len(), expected[0].len());
+        for (value, expected) in out[0].iter().zip(expected[0].iter()) {
+            assert!((value.0 - expected).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn custom_sample_type() {
+        let params = || SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            ..Default::default()
+        };
+        check_same_output(
+            SincFixedIn::<Counting>::new(1.2, 1.0, params(), 512, 1).unwrap(),
+            SincFixedIn::<f64>::new(1.2, 1.0, params(), 512, 1).unwrap(),
+        );
+        check_same_output(
+            FftFixedIn::<Counting>::new(44100, 48000, 512, 2, 1).unwrap(),
+            FftFixedIn::<f64>::new(44100, 48000, 512, 2, 1).unwrap(),
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::ResampleResult;
+use crate::{heap_size_nested, process_wrapped, Resampler, ResamplerConfig, Sample};
+
+/// Returns true if the value is neither NaN nor infinite.
+fn is_finite<T: Sample>(value: T) -> bool {
+    // The product is NaN for both NaN and infinite values
+    value * T::zero() == T::zero()
+}
+
+/// A wrapper for a [Resampler] that replaces non-finite input samples with zeros.
+///
+/// A single NaN or infinite value in the input is spread by the filters of the resampler
+/// into its internal buffers, and corrupts the output until the resampler is reset.
+/// This wrapper scans the input of each chunk and replaces any NaN or infinite samples with zeros
+/// before passing the data on to the wrapped resampler.
+/// The number of replaced samples is counted, and can be read with
+/// [replaced_samples](SanitizeInput::replaced_samples).
+///
+/// In the common case where the input is clean, the input is passed through without copying.
+/// When non-finite samples are found, the chunk is copied to an internal buffer.
+/// This buffer is allocated the first time it is needed, and is then reused.
+/// ```
+/// use rubato::{FftFixedIn, Resampler, SanitizeInput};
+/// let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+/// let mut resampler = SanitizeInput::new(resampler);
+/// let mut waves_in = vec![vec![0.0f64; 1024]; 2];
+/// waves_in[1][100] = f64::NAN;
+/// let waves_out = resampler.process(&waves_in, None).unwrap();
+/// assert!(waves_out[1].iter().all(|v| v.is_finite()));
+/// assert_eq!(resampler.replaced_samples(), 1);
+/// ```
+pub struct SanitizeInput<R, T> {
+    resampler: R,
+    buffer: Vec<Vec<T>>,
+    replaced_samples: u64,
+}
+
+impl<R, T> SanitizeInput<R, T>
+where
+    T: Sample,
+{
+    /// Create a new SanitizeInput wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    pub fn new(resampler: R) -> Self {
+        SanitizeInput {
+            resampler,
+            buffer: Vec::new(),
+            replaced_samples: 0,
+        }
+    }
+
+    /// Get the total number of input samples that have been replaced with zeros.
+    pub fn replaced_samples(&self) -> u64 {
+        self.replaced_samples
+    }
+
+    /// Reset the count of replaced samples to zero.
+    pub fn clear_replaced_samples(&mut self) {
+        self.replaced_samples = 0;
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        R: Resampler<T>,
+    {
+        let clean = wave_in
+            .iter()
+            .all(|wave| wave.as_ref().iter().all(|value| is_finite(*value)));
+        if clean {
+            return process_wrapped(
+                &mut self.resampler,
+                wave_in,
+                wave_out,
+                active_channels_mask,
+                scratch,
+            );
+        }
+        if self.buffer.len() < wave_in.len() {
+            self.buffer.resize(wave_in.len(), Vec::new());
+        }
+        let mut replaced = 0;
+        for (wave, buffer) in wave_in.iter().zip(self.buffer.iter_mut()) {
+            buffer.clear();
+            buffer.extend(wave.as_ref().iter().map(|value| {
+                if is_finite(*value) {
+                    *value
+                } else {
+                    replaced += 1;
+                    T::zero()
+                }
+            }));
+        }
+        let result = process_wrapped(
+            &mut self.resampler,
+            &self.buffer[..wave_in.len()],
+            wave_out,
+            active_channels_mask,
+            scratch,
+        )?;
+        // Only count the replaced samples once the chunk has been processed
+        self.replaced_samples += replaced;
+        trace!("Replaced {} non-finite input samples", replaced);
+        Ok(result)
+    }
+}
+
+impl<T, R> Resampler<T> for SanitizeInput<R, T>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage() + heap_size_nested(&self.buffer)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "SanitizeInput")
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::{assert_same_output, make_fast_resampler};
+    use crate::{FastFixedIn, Resampler, SanitizeInput};
+
+    fn make_resampler() -> FastFixedIn<f32> {
+        make_fast_resampler(1.5, 1.0, 64, 2)
+    }
+
+    #[test]
+    fn replace_non_finite() {
+        let mut reference = make_resampler();
+        let mut resampler = SanitizeInput::new(make_resampler());
+        for n in 0..4 {
+            let waves = vec![vec![0.5f32; 64]; 2];
+            let mut dirty = waves.clone();
+            dirty[0][3] = f32::NAN;
+            dirty[1][10] = f32::INFINITY;
+            dirty[1][11] = f32::NEG_INFINITY;
+            let mut clean = waves.clone();
+            clean[0][3] = 0.0;
+            clean[1][10] = 0.0;
+            clean[1][11] = 0.0;
+            let expected = reference.process(&clean, None).unwrap();
+            let out = resampler.process(&dirty, None).unwrap();
+            assert_eq!(expected, out);
+            assert_eq!(resampler.replaced_samples(), 3 * (n + 1));
+        }
+        resampler.clear_replaced_samples();
+        assert_eq!(resampler.replaced_samples(), 0);
+    }
+
+    #[test]
+    fn pass_clean_input() {
+        let mut reference = make_resampler();
+        let mut resampler = SanitizeInput::new(make_resampler());
+        let waves = vec![vec![0.25f32; 64]; 2];
+        assert_same_output(&mut reference, &mut resampler, &[waves.clone(), waves]);
+        assert_eq!(resampler.replaced_samples(), 0);
+    }
+}
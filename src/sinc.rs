@@ -1,5 +1,11 @@
+use crate::remez::remez_lowpass;
 use crate::windows::{make_window, WindowFunction};
-use crate::Sample;
+use crate::{GainNormalization, Sample};
+
+/// Weight of the stopband deviation relative to the passband deviation in equiripple designs.
+const EQUIRIPPLE_STOP_WEIGHT: f64 = 100.0;
+/// Number of frequencies for averaging the gain over the passband.
+const NBR_PASSBAND_POINTS: usize = 32;
 
 /// Helper function: sinc(x) = sin(pi*x)/(pi*x)
 pub fn sinc<T>(value: T) -> T
@@ -49,10 +55,187 @@ where
     sincs
 }
 
-#[cfg(test)]
+/// Helper function. Make a set of sincs from an equiripple prototype filter,
+/// designed with the Parks-McClellan algorithm.
+/// The result has the same layout as the sincs made by [make_sincs].
+/// The gain is not normalized at DC, since that would double the passband ripple.
+/// The band edges `f_pass` and `f_stop` are given relative to the Nyquist frequency
+/// of the non-oversampled signal.
+pub fn make_equiripple_sincs<T>(
+    npoints: usize,
+    factor: usize,
+    f_pass: f32,
+    f_stop: f32,
+) -> Vec<Vec<T>>
+where
+    T: Sample,
+{
+    let totpoints = npoints * factor;
+    // The prototype is symmetric around totpoints/2, like the windowed sinc.
+    // It then has an odd length, and the first point is zero.
+    let mut y = vec![0.0];
+    y.extend(remez_lowpass(
+        totpoints - 1,
+        f_pass as f64 / (2 * factor) as f64,
+        f_stop as f64 / (2 * factor) as f64,
+        EQUIRIPPLE_STOP_WEIGHT,
+    ));
+    debug!(
+        "Generate equiripple sincs, length: {}, oversampling: {}, gain at DC: {:?}",
+        npoints,
+        factor,
+        y.iter().sum::<f64>()
+    );
+    let mut sincs = vec![vec![T::zero(); npoints]; factor];
+    for p in 0..npoints {
+        for n in 0..factor {
+            sincs[factor - n - 1][p] = T::coerce(y[factor * p + n] * factor as f64);
+        }
+    }
+    sincs
+}
+
+/// Helper function. Make a set of sincs from an externally designed prototype filter,
+/// given at the oversampled rate.
+/// The result has the same layout as the sincs made by [make_sincs],
+/// with the center tap of the prototype at the center of the oversampled filter.
+/// The prototype is padded with zeros to make the sinc length a multiple of 8.
+/// Only the overall gain is changed, so that the gain at DC is one.
+/// Returns `None` if the prototype is empty, the factor is zero,
+/// or the gain at DC is zero or not finite.
+pub fn prototype_sincs<T>(prototype: &[T], factor: usize) -> Option<Vec<Vec<T>>>
+where
+    T: Sample,
+{
+    let sum = prototype.iter().fold(T::zero(), |acc, value| acc + *value);
+    // The product is NaN for both NaN and infinite sums
+    if prototype.is_empty() || factor == 0 || sum == T::zero() || sum * T::zero() != T::zero() {
+        return None;
+    }
+    let center = (prototype.len() - 1) / 2;
+    let mut npoints = 8 * (((prototype.len() + factor - 1) / factor + 7) / 8);
+    while npoints * factor / 2 < center
+        || npoints * factor - npoints * factor / 2 < prototype.len() - center
+    {
+        npoints += 8;
+    }
+    let totpoints = npoints * factor;
+    let offset = totpoints / 2 - center;
+    let scale = T::coerce(factor) / sum;
+    debug!(
+        "Generate sincs from a prototype of {} taps, length: {}, oversampling: {}, normalized by: {:?}",
+        prototype.len(),
+        npoints,
+        factor,
+        sum / T::coerce(factor)
+    );
+    let mut sincs = vec![vec![T::zero(); npoints]; factor];
+    for (x, value) in prototype.iter().enumerate() {
+        let pos = x + offset;
+        sincs[factor - pos % factor - 1][pos / factor] = *value * scale;
+    }
+    Some(sincs)
+}
+
+/// Helper function. Calculate the frequency response of a set of sincs as made by [make_sincs],
+/// at the angular frequency `omega` (in radians per sample of the sinc length).
+fn sincs_response<T>(sincs: &[Vec<T>], omega: T) -> T
+where
+    T: Sample,
+{
+    let factor = sincs.len();
+    let npoints = sincs[0].len();
+    let center = T::coerce(npoints * factor / 2);
+    let mut response = T::zero();
+    for (n, sinc) in sincs.iter().enumerate() {
+        for (p, value) in sinc.iter().enumerate() {
+            // Position in the oversampled prototype filter
+            let x = T::coerce(factor * p + factor - n - 1);
+            response += *value * (omega * (x - center) / T::coerce(factor)).cos();
+        }
+    }
+    response / T::coerce(factor)
+}
+
+/// Helper function. Compensate the passband droop of a set of sincs as made by [make_sincs].
+/// A three-tap shelf filter `[-a, 1 + 2a, -a]` is fused into each sinc,
+/// with `a` chosen to give unity gain at 90% of the cutoff frequency.
+pub fn compensate_droop<T>(sincs: &mut [Vec<T>], f_cutoff: f32)
+where
+    T: Sample,
+{
+    let omega = T::coerce(0.9 * f_cutoff) * T::PI;
+    let dc_gain = sincs_response(sincs, T::zero());
+    let gain = sincs_response(sincs, omega);
+    let one_minus_cos = T::one() - omega.cos();
+    if !gain.is_positive() || !one_minus_cos.is_positive() {
+        return;
+    }
+    let mut a = (dc_gain / gain - T::one()) / (T::coerce(2.0) * one_minus_cos);
+    if a.is_negative() {
+        a = T::zero();
+    }
+    debug!(
+        "Compensate sinc droop, gain at 90% of cutoff: {:?}, shelf coefficient: {:?}",
+        gain / dc_gain,
+        a
+    );
+    let center = T::one() + T::coerce(2.0) * a;
+    for sinc in sincs.iter_mut() {
+        let original = sinc.clone();
+        for (p, value) in sinc.iter_mut().enumerate() {
+            let prev = if p > 0 { original[p - 1] } else { T::zero() };
+            let next = original.get(p + 1).copied().unwrap_or_else(T::zero);
+            *value = center * original[p] - a * (prev + next);
+        }
+    }
+}
+
+/// Helper function. Scale a set of sincs as made by [make_sincs] to the gain given by `normalization`.
+/// The passband extends to `f_pass`, relative to the Nyquist frequency of the non-oversampled signal.
+/// The average gains over the passband are calculated at evenly spaced frequencies.
+pub fn normalize_gain<T>(sincs: &mut [Vec<T>], f_pass: f32, normalization: GainNormalization)
+where
+    T: Sample,
+{
+    let passband_response = |n: usize| {
+        let omega = T::coerce(f_pass) * T::PI * (T::coerce(n) + T::coerce(0.5))
+            / T::coerce(NBR_PASSBAND_POINTS);
+        sincs_response(sincs, omega)
+    };
+    let gain = match normalization {
+        GainNormalization::Dc => sincs_response(sincs, T::zero()),
+        GainNormalization::Passband => {
+            (0..NBR_PASSBAND_POINTS).fold(T::zero(), |acc, n| acc + passband_response(n))
+                / T::coerce(NBR_PASSBAND_POINTS)
+        }
+        GainNormalization::Energy => ((0..NBR_PASSBAND_POINTS).fold(T::zero(), |acc, n| {
+            let response = passband_response(n);
+            acc + response * response
+        }) / T::coerce(NBR_PASSBAND_POINTS))
+        .sqrt(),
+    };
+    if !gain.is_positive() {
+        return;
+    }
+    debug!(
+        "Normalize sincs to {:?}, gain before: {:?}",
+        normalization, gain
+    );
+    let scale = T::one() / gain;
+    for sinc in sincs.iter_mut() {
+        for value in sinc.iter_mut() {
+            *value *= scale;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
-    use crate::sinc::make_sincs;
-    use crate::WindowFunction;
+    use crate::sinc::normalize_gain;
+    use crate::sinc::prototype_sincs;
+    use crate::sinc::{compensate_droop, make_equiripple_sincs, make_sincs, sincs_response};
+    use crate::{GainNormalization, WindowFunction};
 
     #[test]
     fn sincs() {
@@ -61,4 +244,103 @@ mod tests {
         let sum: f64 = sincs.iter().map(|v| v.iter().sum::<f64>()).sum();
         assert!((sum - 8.0).abs() < 0.00001);
     }
+
+    #[test]
+    fn sincs_from_prototype() {
+        let sincs = make_sincs::<f64>(32, 8, 0.9, WindowFunction::Blackman);
+        // Reassemble the oversampled filter. The first tap is zero with a Blackman window,
+        // and is left out to give a symmetric prototype with an odd length and a different gain.
+        let mut prototype = vec![0.0; 32 * 8];
+        for (n, sinc) in sincs.iter().enumerate() {
+            for (p, value) in sinc.iter().enumerate() {
+                prototype[8 * p + 8 - n - 1] = 3.0 * value;
+            }
+        }
+        let from_prototype = prototype_sincs(&prototype[1..], 8).unwrap();
+        assert_eq!(from_prototype.len(), 8);
+        for (sinc, expected) in from_prototype.iter().zip(sincs.iter()) {
+            assert_eq!(sinc.len(), 32);
+            for (value, expected) in sinc.iter().zip(expected.iter()) {
+                assert!((value - expected).abs() < 1.0e-12);
+            }
+        }
+        // Short prototypes are padded to a multiple of 8
+        let padded = prototype_sincs(&[1.0f64, 2.0, 1.0], 2).unwrap();
+        assert_eq!(padded[0].len(), 8);
+        assert!(prototype_sincs::<f64>(&[], 8).is_none());
+        assert!(prototype_sincs(&[1.0f64, -1.0], 8).is_none());
+        assert!(prototype_sincs(&[1.0f64], 0).is_none());
+    }
+
+    #[test]
+    fn droop_compensation() {
+        let mut sincs = make_sincs::<f64>(64, 16, 0.9, WindowFunction::BlackmanHarris2);
+        let omega = 0.9 * 0.9 * std::f64::consts::PI;
+        let dc_before = sincs_response(&sincs, 0.0);
+        let droop_before = sincs_response(&sincs, omega) / dc_before;
+        compensate_droop(&mut sincs, 0.9);
+        let dc_after = sincs_response(&sincs, 0.0);
+        let droop_after = sincs_response(&sincs, omega) / dc_after;
+        assert!(droop_before < 0.999, "droop before {}", droop_before);
+        assert!(
+            (droop_after - 1.0).abs() < 1.0e-6,
+            "droop after {}",
+            droop_after
+        );
+        assert!((dc_after - dc_before).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn gain_normalization() {
+        // A short filter, with a clear droop in the passband
+        let passband = |sincs: &[Vec<f64>], power: i32| {
+            (0..1000)
+                .map(|n| {
+                    let omega = 0.81 * std::f64::consts::PI * (n as f64 + 0.5) / 1000.0;
+                    sincs_response(sincs, omega).powi(power)
+                })
+                .sum::<f64>()
+                / 1000.0
+        };
+        let mut sincs = make_sincs::<f64>(16, 8, 0.9, WindowFunction::Hann);
+        normalize_gain(&mut sincs, 0.81, GainNormalization::Dc);
+        assert!((sincs_response(&sincs, 0.0) - 1.0).abs() < 1.0e-12);
+        let average = passband(&sincs, 1);
+        assert!(average < 0.999, "average {}", average);
+
+        normalize_gain(&mut sincs, 0.81, GainNormalization::Passband);
+        let average = passband(&sincs, 1);
+        assert!((average - 1.0).abs() < 1.0e-4, "average {}", average);
+        assert!(sincs_response(&sincs, 0.0) > 1.001);
+
+        normalize_gain(&mut sincs, 0.81, GainNormalization::Energy);
+        let power = passband(&sincs, 2);
+        assert!((power - 1.0).abs() < 1.0e-3, "power {}", power);
+        assert!(passband(&sincs, 1) < 1.0);
+    }
+
+    #[test]
+    fn equiripple_sincs() {
+        let sincs = make_equiripple_sincs::<f64>(32, 16, 0.8, 1.0);
+        let sum: f64 = sincs.iter().map(|v| v.iter().sum::<f64>()).sum();
+        assert!((sum - 16.0).abs() < 0.02 * 16.0);
+        let windowed = make_sincs::<f64>(32, 16, 0.9, WindowFunction::Blackman);
+        let stopband_max = |sincs: &[Vec<f64>]| {
+            (0..=1000)
+                .map(|n| {
+                    let omega = std::f64::consts::PI * (1.0 + 15.0 * n as f64 / 1000.0);
+                    sincs_response(sincs, omega).abs()
+                })
+                .fold(0.0, f64::max)
+        };
+        let equiripple_stop = stopband_max(&sincs);
+        let windowed_stop = stopband_max(&windowed);
+        assert!(equiripple_stop < 2.0e-4, "equiripple {}", equiripple_stop);
+        assert!(windowed_stop > 1.0e-2, "windowed {}", windowed_stop);
+        for n in 0..=100 {
+            let omega = 0.8 * std::f64::consts::PI * n as f64 / 100.0;
+            let gain = sincs_response(&sincs, omega);
+            assert!((gain - 1.0).abs() < 0.02, "gain {}", gain);
+        }
+    }
 }
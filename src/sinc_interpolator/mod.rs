@@ -1,6 +1,7 @@
 use crate::sinc::make_sincs;
 use crate::windows::WindowFunction;
 use crate::Sample;
+use std::fmt;
 
 /// Helper macro to define a dummy implementation of the sample trait if a
 /// feature is not supported.
@@ -31,28 +32,231 @@ macro_rules! interpolator {
 }
 
 interpolator! {
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(
+        target_arch = "x86_64",
+        feature = "simd_avx",
+        not(feature = "forbid_unsafe")
+    ))]
     mod sinc_interpolator_avx;
     trait AvxSample;
 }
 
 interpolator! {
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(
+        target_arch = "x86_64",
+        feature = "simd_sse3",
+        not(feature = "forbid_unsafe")
+    ))]
     mod sinc_interpolator_sse;
     trait SseSample;
 }
 
 interpolator! {
-    #[cfg(target_arch = "aarch64")]
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        feature = "simd_sse2",
+        not(feature = "forbid_unsafe")
+    ))]
+    mod sinc_interpolator_sse2;
+    trait Sse2Sample;
+}
+
+interpolator! {
+    #[cfg(all(
+        target_arch = "aarch64",
+        feature = "simd_neon",
+        not(feature = "forbid_unsafe")
+    ))]
     mod sinc_interpolator_neon;
     trait NeonSample;
 }
 
+#[cfg(all(feature = "f32", feature = "f64"))]
+pub mod sinc_interpolator_mixed;
+
+pub mod sinc_interpolator_quantized;
+
+/// The implementation used by an interpolator for the scalar products,
+/// as reported by [SincInterpolator::backend].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolatorBackend {
+    /// Plain scalar code, see [ScalarInterpolator].
+    Scalar,
+    /// The straightforward scalar code of [ReferenceInterpolator].
+    Reference,
+    /// SSE2 instructions on x86 and x86_64.
+    Sse2,
+    /// SSE3 instructions on x86_64.
+    Sse3,
+    /// AVX and FMA instructions on x86_64.
+    Avx,
+    /// Neon instructions on aarch64.
+    Neon,
+    /// f32 samples accumulated in f64, with scalar code,
+    /// see [MixedPrecisionInterpolator](sinc_interpolator_mixed::MixedPrecisionInterpolator).
+    MixedPrecisionScalar,
+    /// f32 samples accumulated in f64, with AVX and FMA instructions.
+    MixedPrecisionAvx,
+    /// Coefficients stored as 16-bit integers, with scalar code,
+    /// see [QuantizedInterpolator](sinc_interpolator_quantized::QuantizedInterpolator).
+    QuantizedI16,
+    /// Coefficients stored as half precision floats, with scalar code.
+    QuantizedF16,
+    /// An interpolator implemented outside of this crate.
+    Custom,
+}
+
+impl fmt::Display for InterpolatorBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            InterpolatorBackend::Scalar => "scalar",
+            InterpolatorBackend::Reference => "reference",
+            InterpolatorBackend::Sse2 => "sse2",
+            InterpolatorBackend::Sse3 => "sse3",
+            InterpolatorBackend::Avx => "avx+fma",
+            InterpolatorBackend::Neon => "neon",
+            InterpolatorBackend::MixedPrecisionScalar => "mixed precision scalar",
+            InterpolatorBackend::MixedPrecisionAvx => "mixed precision avx+fma",
+            InterpolatorBackend::QuantizedI16 => "quantized i16 scalar",
+            InterpolatorBackend::QuantizedF16 => "quantized f16 scalar",
+            InterpolatorBackend::Custom => "custom",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Functions for making the scalar product with a sinc
+///
+/// This is the kernel of the asynchronous sinc resamplers [SincFixedIn](crate::SincFixedIn)
+/// and [SincFixedOut](crate::SincFixedOut), which handle the ratio, the buffering
+/// and the interpolation between the sincs.
+/// Besides the interpolators of this crate, a custom kernel can be given to their
+/// `new_with_interpolator` constructors, for example one that evaluates the filter
+/// on the fly instead of storing a table, or an experimental filter design.
+///
+/// The filter is a prototype lowpass at `nbr_sincs()` times the input rate,
+/// split into `nbr_sincs()` sincs of `len()` points each.
+/// Point `p` of the sinc `subindex` is the value of the prototype at the time
+/// `p + (nbr_sincs - 1 - subindex) / nbr_sincs - len / 2`, counted in input frames
+/// with `len / 2` rounded down.
+/// The resamplers expect each sinc to have a gain close to one at DC.
+/// The waveform given to [get_sinc_interpolated](SincInterpolator::get_sinc_interpolated)
+/// always has at least `index + len() + 1` frames.
+///
+/// A kernel that evaluates a Kaiser windowed sinc for each point, without a table:
+/// ```
+/// use rubato::sinc_interpolator::SincInterpolator;
+/// use rubato::{Resampler, SincFixedIn, SincInterpolationType};
+/// use std::f64::consts::PI;
+///
+/// struct KaiserKernel {
+///     len: usize,
+///     nbr_sincs: usize,
+///     cutoff: f64,
+///     beta: f64,
+///     gain: f64,
+/// }
+///
+/// fn bessel_i0(x: f64) -> f64 {
+///     let mut term = 1.0;
+///     let mut sum = 1.0;
+///     for k in 1..32 {
+///         term *= (x / (2.0 * k as f64)).powi(2);
+///         sum += term;
+///     }
+///     sum
+/// }
+///
+/// impl KaiserKernel {
+///     fn new(len: usize, nbr_sincs: usize, cutoff: f64, beta: f64) -> Self {
+///         let mut kernel = KaiserKernel { len, nbr_sincs, cutoff, beta, gain: 1.0 };
+///         let total: f64 = (0..nbr_sincs)
+///             .map(|sub| (0..len).map(|p| kernel.point(p, sub)).sum::<f64>())
+///             .sum();
+///         kernel.gain = nbr_sincs as f64 / total;
+///         kernel
+///     }
+///
+///     fn point(&self, p: usize, subindex: usize) -> f64 {
+///         let half = (self.len / 2) as f64;
+///         let time = p as f64 + (self.nbr_sincs - 1 - subindex) as f64 / self.nbr_sincs as f64 - half;
+///         let rel = time / half;
+///         if rel.abs() >= 1.0 {
+///             return 0.0;
+///         }
+///         let window = bessel_i0(self.beta * (1.0 - rel * rel).sqrt()) / bessel_i0(self.beta);
+///         let x = PI * self.cutoff * time;
+///         let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+///         self.gain * window * sinc
+///     }
+/// }
+///
+/// impl SincInterpolator<f64> for KaiserKernel {
+///     fn get_sinc_interpolated(&self, wave: &[f64], index: usize, subindex: usize) -> f64 {
+///         wave[index..index + self.len]
+///             .iter()
+///             .enumerate()
+///             .map(|(p, value)| value * self.point(p, subindex))
+///             .sum()
+///     }
+///
+///     fn len(&self) -> usize {
+///         self.len
+///     }
+///
+///     fn nbr_sincs(&self) -> usize {
+///         self.nbr_sincs
+///     }
+///
+///     fn memory_usage(&self) -> usize {
+///         0
+///     }
+/// }
+///
+/// let kernel = KaiserKernel::new(32, 256, 0.9, 8.0);
+/// let mut resampler = SincFixedIn::<f64>::new_with_interpolator(
+///     48000.0 / 44100.0,
+///     1.1,
+///     SincInterpolationType::Linear,
+///     Box::new(kernel),
+///     256,
+///     1,
+/// )
+/// .unwrap();
+/// let waves_in = vec![vec![1.0f64; 256]; 1];
+/// let waves_out = resampler.process(&waves_in, None).unwrap();
+/// assert!(waves_out[0].len() > 0);
+/// ```
 pub trait SincInterpolator<T>: Send {
     /// Make the scalar product between the waveform starting at `index` and the sinc of `subindex`.
+    ///
+    /// The interpolators of this module panic if `index + len()` is not less than the length of `wave`,
+    /// or if `subindex` is not less than `nbr_sincs()`.
+    /// The resamplers check the positions of each block of output frames before interpolating,
+    /// and return an error instead of calling this with positions out of range.
     fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T;
 
+    /// Make the scalar products between the waveforms of two channels starting at `index`
+    /// and the sinc of `subindex`, and return the results as `(left, right)`.
+    ///
+    /// This is used by the sinc resamplers when processing stereo,
+    /// and lets an implementation load each sinc coefficient once for both channels.
+    /// The results must be identical to calling
+    /// [get_sinc_interpolated](SincInterpolator::get_sinc_interpolated) for each channel,
+    /// which is what the default implementation does.
+    fn get_sinc_interpolated_stereo(
+        &self,
+        left: &[T],
+        right: &[T],
+        index: usize,
+        subindex: usize,
+    ) -> (T, T) {
+        (
+            self.get_sinc_interpolated(left, index, subindex),
+            self.get_sinc_interpolated(right, index, subindex),
+        )
+    }
+
     /// Get sinc length
     fn len(&self) -> usize;
 
@@ -63,6 +267,42 @@ pub trait SincInterpolator<T>: Send {
 
     /// Get number of sincs used for oversampling
     fn nbr_sincs(&self) -> usize;
+
+    /// Get the approximate heap memory used by the stored sincs, in bytes.
+    fn memory_usage(&self) -> usize {
+        self.len() * self.nbr_sincs() * std::mem::size_of::<T>()
+    }
+
+    /// Get the implementation used for the scalar products.
+    /// The default implementation returns [InterpolatorBackend::Custom].
+    fn backend(&self) -> InterpolatorBackend {
+        InterpolatorBackend::Custom
+    }
+
+    /// Get the filter coefficients, as one vector of `len()` values for each of the `nbr_sincs()` sincs.
+    ///
+    /// The default implementation recovers the coefficients by interpolating unit impulses,
+    /// so it returns the values as they are used, independently of how they are stored.
+    /// This is slow, and intended for inspecting the filter rather than for processing.
+    fn coefficients(&self) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        let len = self.len();
+        let mut impulse = vec![T::zero(); len + 1];
+        (0..self.nbr_sincs())
+            .map(|subindex| {
+                (0..len)
+                    .map(|n| {
+                        impulse[n] = T::one();
+                        let value = self.get_sinc_interpolated(&impulse, 0, subindex);
+                        impulse[n] = T::zero();
+                        value
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 /// A plain scalar interpolator
@@ -92,6 +332,7 @@ where
         );
         let wave_cut = &wave[index..(index + self.sincs[subindex].len())];
         let sinc = &self.sincs[subindex];
+        #[cfg(not(feature = "forbid_unsafe"))]
         unsafe {
             let mut acc0 = T::zero();
             let mut acc1 = T::zero();
@@ -115,6 +356,16 @@ where
             }
             acc0 + acc1 + acc2 + acc3 + acc4 + acc5 + acc6 + acc7
         }
+        #[cfg(feature = "forbid_unsafe")]
+        {
+            let mut acc = [T::zero(); 8];
+            for (wave_chunk, sinc_chunk) in wave_cut.chunks_exact(8).zip(sinc.chunks_exact(8)) {
+                for n in 0..8 {
+                    acc[n] += wave_chunk[n] * sinc_chunk[n];
+                }
+            }
+            acc[0] + acc[1] + acc[2] + acc[3] + acc[4] + acc[5] + acc[6] + acc[7]
+        }
     }
 
     fn len(&self) -> usize {
@@ -124,6 +375,14 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn backend(&self) -> InterpolatorBackend {
+        InterpolatorBackend::Scalar
+    }
+
+    fn coefficients(&self) -> Vec<Vec<T>> {
+        self.sincs.clone()
+    }
 }
 
 impl<T> ScalarInterpolator<T>
@@ -137,6 +396,97 @@ where
     /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
     /// - `f_cutoff`: Relative cutoff frequency.
     /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Self {
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::from_sincs(sincs)
+    }
+
+    /// Create a new ScalarInterpolator from a set of precalculated sincs.
+    /// The outer vector holds one sinc per oversampling step,
+    /// and all sincs must have the same length, which must be a multiple of 8.
+    pub(crate) fn from_sincs(sincs: Vec<Vec<T>>) -> Self {
+        let sinc_len = sincs[0].len();
+        let oversampling_factor = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        Self {
+            sincs,
+            length: sinc_len,
+            nbr_sincs: oversampling_factor,
+        }
+    }
+}
+
+/// A plain scalar interpolator with a fixed summation order.
+///
+/// The scalar product is calculated with a single accumulator,
+/// adding the products in order from the first to the last point of the sinc.
+/// The result therefore only depends on the floating point arithmetic,
+/// and not on the available instruction sets or on how the compiler vectorizes the code.
+/// This makes it suitable as a reference when verifying other interpolators,
+/// or a port to a new architecture, see [run_golden_vectors](crate::run_golden_vectors).
+/// It is slower than the other interpolators and is not selected automatically.
+pub struct ReferenceInterpolator<T> {
+    sincs: Vec<Vec<T>>,
+    length: usize,
+    nbr_sincs: usize,
+}
+
+impl<T> SincInterpolator<T> for ReferenceInterpolator<T>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        let wave_cut = &wave[index..(index + self.length)];
+        let mut acc = T::zero();
+        for (x, y) in wave_cut.iter().zip(self.sincs[subindex].iter()) {
+            acc += *x * *y;
+        }
+        acc
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+
+    fn backend(&self) -> InterpolatorBackend {
+        InterpolatorBackend::Reference
+    }
+}
+
+impl<T> ReferenceInterpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new ReferenceInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
     pub fn new(
         sinc_len: usize,
         oversampling_factor: usize,
@@ -153,10 +503,10 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
-    use super::ScalarInterpolator;
     use super::SincInterpolator;
+    use super::{ReferenceInterpolator, ScalarInterpolator};
     use crate::WindowFunction;
     use num_traits::Float;
     use rand::Rng;
@@ -206,4 +556,30 @@ mod tests {
         let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
         assert!((value - check).abs() < 1.0e-6);
     }
+
+    #[test]
+    fn test_reference_interpolator_64() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f64>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.947_337_15;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+
+        let interpolator =
+            ReferenceInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
+        assert_eq!(value, check);
+    }
+
+    #[test]
+    fn test_default_coefficients() {
+        let interpolator =
+            ReferenceInterpolator::<f64>::new(64, 16, 0.9, WindowFunction::BlackmanHarris2);
+        assert_eq!(interpolator.coefficients(), interpolator.sincs);
+    }
 }
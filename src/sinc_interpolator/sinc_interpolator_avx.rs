@@ -1,4 +1,6 @@
-use crate::sinc_interpolator::SincInterpolator;
+// The intrinsics for a sample type are unused when support for that type is disabled.
+#![cfg_attr(not(all(feature = "f32", feature = "f64")), allow(unused_imports))]
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
 use crate::error::{CpuFeature, MissingCpuFeature};
 use crate::sinc::make_sincs;
 use crate::windows::WindowFunction;
@@ -20,7 +22,7 @@ static FEATURES: &[CpuFeature] = &[CpuFeature::Avx, CpuFeature::Fma];
 
 /// Trait governing what can be done with an AvxSample.
 pub trait AvxSample: Sized + Send {
-    type Sinc: Send;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -43,8 +45,54 @@ pub trait AvxSample: Sized + Send {
         sincs: &[Vec<Self::Sinc>],
         length: usize,
     ) -> Self;
+
+    /// Interpolate a sinc sample for two channels at once,
+    /// loading each sinc vector once for both.
+    /// The accumulation order is the same as for
+    /// [get_sinc_interpolated_unsafe](AvxSample::get_sinc_interpolated_unsafe),
+    /// so the results are identical.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_stereo_unsafe(
+        left: &[Self],
+        right: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> (Self, Self);
+}
+
+/// Sum the lanes of an f32 accumulator.
+#[cfg(feature = "f32")]
+#[target_feature(enable = "avx", enable = "fma")]
+unsafe fn sum_ps(acc: __m256) -> f32 {
+    let acc_high = _mm256_extractf128_ps(acc, 1);
+    let acc_low = _mm_add_ps(acc_high, _mm256_castps256_ps128(acc));
+    let temp2 = _mm_hadd_ps(acc_low, acc_low);
+    let temp1 = _mm_hadd_ps(temp2, temp2);
+    let mut result = 0.0;
+    _mm_store_ss(&mut result, temp1);
+    result
+}
+
+/// Sum the lanes of a pair of f64 accumulators.
+#[cfg(feature = "f64")]
+#[target_feature(enable = "avx", enable = "fma")]
+unsafe fn sum_pd(acc0: __m256d, acc1: __m256d) -> f64 {
+    let acc_all = _mm256_add_pd(acc0, acc1);
+    let acc_high = _mm256_extractf128_pd(acc_all, 1);
+    let temp2 = _mm_add_pd(acc_high, _mm256_castpd256_pd128(acc_all));
+    let temp1 = _mm_hadd_pd(temp2, temp2);
+    let mut result = 0.0;
+    _mm_store_sd(&mut result, temp1);
+    result
 }
 
+#[cfg(feature = "f32")]
 impl AvxSample for f32 {
     type Sinc = __m256;
 
@@ -79,16 +127,37 @@ impl AvxSample for f32 {
             acc = _mm256_fmadd_ps(w, *sinc.get_unchecked(s_idx), acc);
             w_idx += 8;
         }
-        let acc_high = _mm256_extractf128_ps(acc, 1);
-        let acc_low = _mm_add_ps(acc_high, _mm256_castps256_ps128(acc));
-        let temp2 = _mm_hadd_ps(acc_low, acc_low);
-        let temp1 = _mm_hadd_ps(temp2, temp2);
-        let mut result = 0.0;
-        _mm_store_ss(&mut result, temp1);
-        result
+        sum_ps(acc)
+    }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_stereo_unsafe(
+        left: &[f32],
+        right: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> (f32, f32) {
+        let sinc = sincs.get_unchecked(subindex);
+        let left_cut = &left[index..(index + length)];
+        let right_cut = &right[index..(index + length)];
+        let mut acc_left = _mm256_setzero_ps();
+        let mut acc_right = _mm256_setzero_ps();
+        let mut w_idx = 0;
+        for s_idx in 0..length / 8 {
+            let s = *sinc.get_unchecked(s_idx);
+            let w_left = _mm256_loadu_ps(left_cut.get_unchecked(w_idx));
+            let w_right = _mm256_loadu_ps(right_cut.get_unchecked(w_idx));
+            acc_left = _mm256_fmadd_ps(w_left, s, acc_left);
+            acc_right = _mm256_fmadd_ps(w_right, s, acc_right);
+            w_idx += 8;
+        }
+        (sum_ps(acc_left), sum_ps(acc_right))
     }
 }
 
+#[cfg(feature = "f64")]
 impl AvxSample for f64 {
     type Sinc = __m256d;
 
@@ -128,13 +197,42 @@ impl AvxSample for f64 {
             w_idx += 8;
             s_idx += 2;
         }
-        let acc_all = _mm256_add_pd(acc0, acc1);
-        let acc_high = _mm256_extractf128_pd(acc_all, 1);
-        let temp2 = _mm_add_pd(acc_high, _mm256_castpd256_pd128(acc_all));
-        let temp1 = _mm_hadd_pd(temp2, temp2);
-        let mut result = 0.0;
-        _mm_store_sd(&mut result, temp1);
-        result
+        sum_pd(acc0, acc1)
+    }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_stereo_unsafe(
+        left: &[f64],
+        right: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> (f64, f64) {
+        let sinc = sincs.get_unchecked(subindex);
+        let left_cut = &left[index..(index + length)];
+        let right_cut = &right[index..(index + length)];
+        let mut acc0_left = _mm256_setzero_pd();
+        let mut acc1_left = _mm256_setzero_pd();
+        let mut acc0_right = _mm256_setzero_pd();
+        let mut acc1_right = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..length / 8 {
+            let s0 = *sinc.get_unchecked(s_idx);
+            let s1 = *sinc.get_unchecked(s_idx + 1);
+            let w0_left = _mm256_loadu_pd(left_cut.get_unchecked(w_idx));
+            let w1_left = _mm256_loadu_pd(left_cut.get_unchecked(w_idx + 4));
+            let w0_right = _mm256_loadu_pd(right_cut.get_unchecked(w_idx));
+            let w1_right = _mm256_loadu_pd(right_cut.get_unchecked(w_idx + 4));
+            acc0_left = _mm256_fmadd_pd(w0_left, s0, acc0_left);
+            acc1_left = _mm256_fmadd_pd(w1_left, s1, acc1_left);
+            acc0_right = _mm256_fmadd_pd(w0_right, s0, acc0_right);
+            acc1_right = _mm256_fmadd_pd(w1_right, s1, acc1_right);
+            w_idx += 8;
+            s_idx += 2;
+        }
+        (sum_pd(acc0_left, acc1_left), sum_pd(acc0_right, acc1_right))
     }
 }
 
@@ -169,6 +267,39 @@ where
         unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
     }
 
+    /// Calculate the scalar products of the waves of two channels and the selected sinc filter
+    fn get_sinc_interpolated_stereo(
+        &self,
+        left: &[T],
+        right: &[T],
+        index: usize,
+        subindex: usize,
+    ) -> (T, T) {
+        let wave_len = left.len().min(right.len());
+        assert!(
+            (index + self.length) < wave_len,
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave_len - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        unsafe {
+            T::get_sinc_interpolated_stereo_unsafe(
+                left,
+                right,
+                index,
+                subindex,
+                &self.sincs,
+                self.length,
+            )
+        }
+    }
+
     fn len(&self) -> usize {
         self.length
     }
@@ -176,11 +307,15 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn backend(&self) -> InterpolatorBackend {
+        InterpolatorBackend::Avx
+    }
 }
 
 impl<T> AvxInterpolator<T>
 where
-    T: Sample,
+    T: Sample + AvxSample,
 {
     /// Create a new AvxInterpolator
     ///
@@ -201,6 +336,20 @@ where
 
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::from_sincs(sincs)
+    }
+
+    /// Create a new AvxInterpolator from a set of precalculated sincs.
+    /// The outer vector holds one sinc per oversampling step,
+    /// and all sincs must have the same length, which must be a multiple of 8.
+    pub(crate) fn from_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let oversampling_factor = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = unsafe { <T as AvxSample>::pack_sincs(sincs) };
 
         Ok(Self {
@@ -211,7 +360,7 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     use crate::sinc_interpolator::SincInterpolator;
     use crate::sinc_interpolator::sinc_interpolator_avx::AvxInterpolator;
@@ -281,4 +430,41 @@ mod tests {
         let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
         assert!((value - check).abs() < 1.0e-5);
     }
+
+    #[test]
+    fn test_avx_interpolator_stereo() {
+        let mut rng = rand::thread_rng();
+        let left_64: Vec<f64> = (0..2048).map(|_| rng.gen::<f64>()).collect();
+        let right_64: Vec<f64> = (0..2048).map(|_| rng.gen::<f64>()).collect();
+        let left_32: Vec<f32> = left_64.iter().map(|v| *v as f32).collect();
+        let right_32: Vec<f32> = right_64.iter().map(|v| *v as f32).collect();
+        let window = WindowFunction::BlackmanHarris2;
+        let (interpolator_64, interpolator_32) = match (
+            AvxInterpolator::<f64>::new(128, 64, 0.95, window),
+            AvxInterpolator::<f32>::new(128, 64, 0.95, window),
+        ) {
+            (Ok(interpolator_64), Ok(interpolator_32)) => (interpolator_64, interpolator_32),
+            _ => {
+                assert!(!(is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma")));
+                return;
+            }
+        };
+        for (index, subindex) in [(0, 0), (333, 17), (1919, 63)] {
+            // Must be bit-identical to interpolating each channel separately
+            assert_eq!(
+                interpolator_64.get_sinc_interpolated_stereo(&left_64, &right_64, index, subindex),
+                (
+                    interpolator_64.get_sinc_interpolated(&left_64, index, subindex),
+                    interpolator_64.get_sinc_interpolated(&right_64, index, subindex)
+                )
+            );
+            assert_eq!(
+                interpolator_32.get_sinc_interpolated_stereo(&left_32, &right_32, index, subindex),
+                (
+                    interpolator_32.get_sinc_interpolated(&left_32, index, subindex),
+                    interpolator_32.get_sinc_interpolated(&right_32, index, subindex)
+                )
+            );
+        }
+    }
 }
@@ -0,0 +1,242 @@
+use crate::asynchro_sinc::make_interpolator_sincs;
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    not(feature = "forbid_unsafe")
+))]
+use crate::error::CpuFeature;
+use crate::error::ResamplerConstructionError;
+use crate::sinc::make_sincs;
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
+use crate::windows::WindowFunction;
+use crate::SincInterpolationParameters;
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    not(feature = "forbid_unsafe")
+))]
+use core::arch::x86_64::{
+    _mm256_add_pd, _mm256_castpd256_pd128, _mm256_cvtps_pd, _mm256_extractf128_pd, _mm256_fmadd_pd,
+    _mm256_setzero_pd, _mm_add_pd, _mm_add_sd, _mm_cvtsd_f64, _mm_loadu_ps, _mm_unpackhi_pd,
+};
+
+/// Calculate the scalar product of `wave` and `sinc`, accumulating in f64.
+fn dot_scalar(wave: &[f32], sinc: &[f32]) -> f64 {
+    let mut acc = [0.0f64; 4];
+    for (w, s) in wave.chunks_exact(4).zip(sinc.chunks_exact(4)) {
+        for n in 0..4 {
+            acc[n] += w[n] as f64 * s[n] as f64;
+        }
+    }
+    (acc[0] + acc[1]) + (acc[2] + acc[3])
+}
+
+/// Calculate the scalar product of `wave` and `sinc`, accumulating in f64 using AVX and FMA.
+///
+/// # Safety
+///
+/// The CPU must support AVX and FMA, and the length of `sinc` must be a multiple of 8
+/// and not larger than the length of `wave`.
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "simd_avx",
+    not(feature = "forbid_unsafe")
+))]
+#[target_feature(enable = "avx,fma")]
+unsafe fn dot_avx(wave: &[f32], sinc: &[f32]) -> f64 {
+    let mut acc0 = _mm256_setzero_pd();
+    let mut acc1 = _mm256_setzero_pd();
+    let mut idx = 0;
+    for _ in 0..sinc.len() / 8 {
+        let w0 = _mm256_cvtps_pd(_mm_loadu_ps(wave.as_ptr().add(idx)));
+        let w1 = _mm256_cvtps_pd(_mm_loadu_ps(wave.as_ptr().add(idx + 4)));
+        let s0 = _mm256_cvtps_pd(_mm_loadu_ps(sinc.as_ptr().add(idx)));
+        let s1 = _mm256_cvtps_pd(_mm_loadu_ps(sinc.as_ptr().add(idx + 4)));
+        acc0 = _mm256_fmadd_pd(w0, s0, acc0);
+        acc1 = _mm256_fmadd_pd(w1, s1, acc1);
+        idx += 8;
+    }
+    let acc = _mm256_add_pd(acc0, acc1);
+    let sum2 = _mm_add_pd(_mm256_castpd256_pd128(acc), _mm256_extractf128_pd(acc, 1));
+    let sum1 = _mm_add_sd(sum2, _mm_unpackhi_pd(sum2, sum2));
+    _mm_cvtsd_f64(sum1)
+}
+
+/// A mixed precision interpolator for f32 data,
+/// that stores the sincs as f32 but accumulates the scalar products in f64.
+///
+/// With long sincs, the rounding errors of f32 accumulation add up and limit the precision.
+/// This interpolator gives most of the precision of processing in f64,
+/// while keeping the coefficients and the audio data at half the memory size.
+/// AVX and FMA are used when supported by the CPU, otherwise a scalar implementation is used.
+///
+/// It is not selected automatically, and is used via `new_with_interpolator`:
+/// ```
+/// use rubato::sinc_interpolator::sinc_interpolator_mixed::MixedPrecisionInterpolator;
+/// use rubato::{SincFixedIn, SincInterpolationParameters};
+/// let mut params = SincInterpolationParameters::default();
+/// params.sinc_len = 512;
+/// let interpolator = MixedPrecisionInterpolator::from_parameters(0.5, &params).unwrap();
+/// let resampler = SincFixedIn::<f32>::new_with_interpolator(
+///     0.5,
+///     1.1,
+///     params.interpolation,
+///     Box::new(interpolator),
+///     1024,
+///     2,
+/// ).unwrap();
+/// ```
+pub struct MixedPrecisionInterpolator {
+    sincs: Vec<Vec<f32>>,
+    length: usize,
+    nbr_sincs: usize,
+    #[cfg(all(
+        target_arch = "x86_64",
+        feature = "simd_avx",
+        not(feature = "forbid_unsafe")
+    ))]
+    use_avx: bool,
+}
+
+impl SincInterpolator<f32> for MixedPrecisionInterpolator {
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[f32], index: usize, subindex: usize) -> f32 {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        let wave_cut = &wave[index..(index + self.length)];
+        let sinc = &self.sincs[subindex];
+        #[cfg(all(
+            target_arch = "x86_64",
+            feature = "simd_avx",
+            not(feature = "forbid_unsafe")
+        ))]
+        if self.use_avx {
+            return unsafe { dot_avx(wave_cut, sinc) } as f32;
+        }
+        dot_scalar(wave_cut, sinc) as f32
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+
+    fn backend(&self) -> InterpolatorBackend {
+        #[cfg(all(
+            target_arch = "x86_64",
+            feature = "simd_avx",
+            not(feature = "forbid_unsafe")
+        ))]
+        if self.use_avx {
+            return InterpolatorBackend::MixedPrecisionAvx;
+        }
+        InterpolatorBackend::MixedPrecisionScalar
+    }
+}
+
+impl MixedPrecisionInterpolator {
+    /// Create a new MixedPrecisionInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Self {
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::from_sincs(sincs)
+    }
+
+    /// Create a new MixedPrecisionInterpolator with the sincs that a sinc resampler
+    /// would use for the given resample ratio and parameters.
+    /// The `interpolation` parameter is not used here,
+    /// and must instead be given to the resampler.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates, must be > 0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    pub fn from_parameters(
+        resample_ratio: f64,
+        parameters: &SincInterpolationParameters,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if resample_ratio <= 0.0 {
+            return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+        }
+        let sincs = make_interpolator_sincs::<f32>(resample_ratio, parameters)?;
+        Ok(Self::from_sincs(sincs))
+    }
+
+    fn from_sincs(sincs: Vec<Vec<f32>>) -> Self {
+        let sinc_len = sincs[0].len();
+        let oversampling_factor = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        Self {
+            sincs,
+            length: sinc_len,
+            nbr_sincs: oversampling_factor,
+            #[cfg(all(
+                target_arch = "x86_64",
+                feature = "simd_avx",
+                not(feature = "forbid_unsafe")
+            ))]
+            use_avx: CpuFeature::Avx.is_detected() && CpuFeature::Fma.is_detected(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use super::{dot_scalar, MixedPrecisionInterpolator};
+    use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
+    use crate::WindowFunction;
+    use rand::Rng;
+
+    fn dot_f64(wave: &[f32], sinc: &[f32]) -> f64 {
+        wave.iter()
+            .zip(sinc.iter())
+            .map(|(w, s)| *w as f64 * *s as f64)
+            .sum()
+    }
+
+    #[test]
+    fn test_mixed_interpolator() {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<f32> = (0..4096).map(|_| rng.gen::<f32>()).collect();
+        let sinc_len = 1024;
+        let oversampling_factor = 64;
+        let interpolator = MixedPrecisionInterpolator::new(
+            sinc_len,
+            oversampling_factor,
+            0.95,
+            WindowFunction::BlackmanHarris2,
+        );
+        let check = dot_f64(&wave[333..333 + sinc_len], &interpolator.sincs[12]);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 12);
+        assert!((value as f64 - check).abs() < 1.0e-6 * check.abs().max(1.0));
+        let scalar = dot_scalar(&wave[333..333 + sinc_len], &interpolator.sincs[12]);
+        assert!((scalar - check).abs() < 1.0e-12);
+        assert!(matches!(
+            interpolator.backend(),
+            InterpolatorBackend::MixedPrecisionScalar | InterpolatorBackend::MixedPrecisionAvx
+        ));
+    }
+}
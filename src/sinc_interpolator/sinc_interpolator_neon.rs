@@ -1,4 +1,6 @@
-use crate::sinc_interpolator::SincInterpolator;
+// The intrinsics for a sample type are unused when support for that type is disabled.
+#![cfg_attr(not(all(feature = "f32", feature = "f64")), allow(unused_imports))]
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
 use crate::error::{CpuFeature, MissingCpuFeature};
 use crate::sinc::make_sincs;
 use crate::windows::WindowFunction;
@@ -12,7 +14,7 @@ static FEATURES: &[CpuFeature] = &[CpuFeature::Neon];
 
 /// Trait governing what can be done with an NeonSample.
 pub trait NeonSample: Sized + Send {
-    type Sinc: Send;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -37,6 +39,7 @@ pub trait NeonSample: Sized + Send {
     ) -> Self;
 }
 
+#[cfg(feature = "f32")]
 impl NeonSample for f32 {
     type Sinc = float32x4_t;
 
@@ -86,6 +89,7 @@ impl NeonSample for f32 {
     }
 }
 
+#[cfg(feature = "f64")]
 impl NeonSample for f64 {
     type Sinc = float64x2_t;
 
@@ -152,7 +156,7 @@ where
 
 impl<T> SincInterpolator<T> for NeonInterpolator<T>
 where
-    T: Sample,
+    T: Sample + NeonSample,
 {
     /// Calculate the scalar produt of an input wave and the selected sinc filter
     fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
@@ -178,11 +182,15 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn backend(&self) -> InterpolatorBackend {
+        InterpolatorBackend::Neon
+    }
 }
 
 impl<T> NeonInterpolator<T>
 where
-    T: Sample,
+    T: Sample + NeonSample,
 {
     /// Create a new NeonInterpolator
     ///
@@ -203,6 +211,20 @@ where
 
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::from_sincs(sincs)
+    }
+
+    /// Create a new NeonInterpolator from a set of precalculated sincs.
+    /// The outer vector holds one sinc per oversampling step,
+    /// and all sincs must have the same length, which must be a multiple of 8.
+    pub(crate) fn from_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let oversampling_factor = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = unsafe { <T as NeonSample>::pack_sincs(sincs) };
 
         Ok(Self {
@@ -213,7 +235,7 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     use crate::sinc_interpolator::SincInterpolator;
     use crate::sinc_interpolator::sinc_interpolator_neon::NeonInterpolator;
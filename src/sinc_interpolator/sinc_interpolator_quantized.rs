@@ -0,0 +1,336 @@
+use crate::asynchro_sinc::make_interpolator_sincs;
+use crate::error::ResamplerConstructionError;
+use crate::sinc::make_sincs;
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
+use crate::windows::WindowFunction;
+use crate::{Sample, SincInterpolationParameters};
+use num_traits::ToPrimitive;
+
+/// The format used by a [QuantizedInterpolator] to store the sinc coefficients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoefficientFormat {
+    /// 16-bit integers, with one scale factor for each sinc.
+    /// The coefficients are rounded to steps of 1/32767 of the largest value of their sinc.
+    /// The rounding errors add up over the length of the sinc,
+    /// and give a noise floor in the filter response at about -84 dB for a sinc length of 64,
+    /// -79 dB for 256, and -73 dB for 1024.
+    I16,
+    /// IEEE 754 half precision floats, with an 11-bit significand.
+    /// The relative rounding error of each coefficient is at most 2^-11.
+    /// The small coefficients in the tails of the sinc keep their relative precision,
+    /// which gives a noise floor in the filter response at about -70 dB for any sinc length.
+    /// The conversion to f32 takes a few more operations than for [CoefficientFormat::I16].
+    F16,
+}
+
+/// The stored coefficients, one vector per sinc.
+enum Coefficients<T> {
+    I16 {
+        sincs: Vec<Vec<i16>>,
+        scales: Vec<T>,
+    },
+    F16 {
+        sincs: Vec<Vec<u16>>,
+    },
+}
+
+/// Convert a value to the bits of the nearest half precision float.
+/// Values outside the range of f16 are saturated to the largest finite value.
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let sign = if value.is_sign_negative() { 0x8000 } else { 0 };
+    let abs = (value as f64).abs();
+    if abs < 6.103515625e-5 {
+        // Subnormal, in steps of 2^-24. Rounding up to 1024 gives the smallest normal value.
+        return sign | (abs * 16777216.0).round() as u16;
+    }
+    let exponent = ((abs.to_bits() >> 52) & 0x7ff) as i32 - 1023;
+    let mut mantissa = ((abs / 2.0f64.powi(exponent) - 1.0) * 1024.0).round() as u32;
+    let mut biased = exponent + 15;
+    if mantissa == 1024 {
+        mantissa = 0;
+        biased += 1;
+    }
+    if biased > 30 {
+        return sign | 0x7bff;
+    }
+    sign | ((biased as u16) << 10) | mantissa as u16
+}
+
+/// Convert the bits of a finite half precision float to f32.
+/// The exponent is adjusted by a multiplication, which also handles subnormal values.
+#[inline]
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let magnitude = f32::from_bits(((bits & 0x7fff) as u32) << 13) * f32::from_bits(0x7780_0000);
+    f32::from_bits(magnitude.to_bits() | (((bits & 0x8000) as u32) << 16))
+}
+
+/// A scalar interpolator that stores the sincs as 16-bit values,
+/// and dequantizes them in the inner loop.
+///
+/// This uses a half (for f32 data) or a quarter (for f64 data) of the memory of the other interpolators.
+/// That helps on microcontrollers with little RAM, and when many resamplers run on one machine
+/// and their coefficients compete for the CPU caches.
+/// The price is a lower precision of the filter, and a slower inner loop
+/// since no SIMD implementation is provided.
+/// The noise floor from the quantization limits the stopband attenuation, see [CoefficientFormat].
+/// This is enough for speech, and for 12-bit or 14-bit converters,
+/// but not for high quality 16-bit or 24-bit audio.
+///
+/// It is not selected automatically, and is used via `new_with_interpolator`:
+/// ```
+/// use rubato::sinc_interpolator::sinc_interpolator_quantized::{
+///     CoefficientFormat, QuantizedInterpolator,
+/// };
+/// use rubato::sinc_interpolator::SincInterpolator;
+/// use rubato::{SincFixedIn, SincInterpolationParameters, SpeechPreset};
+/// let params = SincInterpolationParameters::speech(SpeechPreset::Wideband);
+/// let interpolator =
+///     QuantizedInterpolator::<f32>::from_parameters(0.5, &params, CoefficientFormat::I16)
+///         .unwrap();
+/// assert_eq!(interpolator.memory_usage(), 96 * 256 * 2 + 256 * 4);
+/// let resampler = SincFixedIn::<f32>::new_with_interpolator(
+///     0.5,
+///     1.1,
+///     params.interpolation,
+///     Box::new(interpolator),
+///     1024,
+///     2,
+/// ).unwrap();
+/// ```
+pub struct QuantizedInterpolator<T> {
+    coefficients: Coefficients<T>,
+    length: usize,
+    nbr_sincs: usize,
+}
+
+impl<T> SincInterpolator<T> for QuantizedInterpolator<T>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        let wave_cut = &wave[index..(index + self.length)];
+        let mut acc = [T::zero(); 8];
+        match &self.coefficients {
+            Coefficients::I16 { sincs, scales } => {
+                let sinc = &sincs[subindex];
+                for (wave_chunk, sinc_chunk) in wave_cut.chunks_exact(8).zip(sinc.chunks_exact(8)) {
+                    for n in 0..8 {
+                        acc[n] += wave_chunk[n] * T::coerce(sinc_chunk[n] as f32);
+                    }
+                }
+                let sum = acc[0] + acc[1] + acc[2] + acc[3] + acc[4] + acc[5] + acc[6] + acc[7];
+                sum * scales[subindex]
+            }
+            Coefficients::F16 { sincs } => {
+                let sinc = &sincs[subindex];
+                for (wave_chunk, sinc_chunk) in wave_cut.chunks_exact(8).zip(sinc.chunks_exact(8)) {
+                    for n in 0..8 {
+                        acc[n] += wave_chunk[n] * T::coerce(f16_bits_to_f32(sinc_chunk[n]));
+                    }
+                }
+                acc[0] + acc[1] + acc[2] + acc[3] + acc[4] + acc[5] + acc[6] + acc[7]
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+
+    fn memory_usage(&self) -> usize {
+        let scales = match &self.coefficients {
+            Coefficients::I16 { scales, .. } => scales.len() * std::mem::size_of::<T>(),
+            Coefficients::F16 { .. } => 0,
+        };
+        self.length * self.nbr_sincs * 2 + scales
+    }
+
+    fn backend(&self) -> InterpolatorBackend {
+        match self.coefficients {
+            Coefficients::I16 { .. } => InterpolatorBackend::QuantizedI16,
+            Coefficients::F16 { .. } => InterpolatorBackend::QuantizedF16,
+        }
+    }
+}
+
+impl<T> QuantizedInterpolator<T>
+where
+    T: Sample + ToPrimitive,
+{
+    /// Create a new QuantizedInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    /// - `format`: Format for storing the coefficients.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+        format: CoefficientFormat,
+    ) -> Self {
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        let sincs = make_sincs::<T>(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::from_sincs(&sincs, format)
+    }
+
+    /// Create a new QuantizedInterpolator with the sincs that a sinc resampler
+    /// would use for the given resample ratio and parameters.
+    /// The `interpolation` parameter is not used here,
+    /// and must instead be given to the resampler.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates, must be > 0.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `format`: Format for storing the coefficients.
+    pub fn from_parameters(
+        resample_ratio: f64,
+        parameters: &SincInterpolationParameters,
+        format: CoefficientFormat,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if resample_ratio <= 0.0 {
+            return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+        }
+        let sincs = make_interpolator_sincs::<T>(resample_ratio, parameters)?;
+        Ok(Self::from_sincs(&sincs, format))
+    }
+
+    /// Create a new QuantizedInterpolator by quantizing a set of precalculated sincs.
+    /// The outer slice holds one sinc per oversampling step,
+    /// and all sincs must have the same length, which must be a multiple of 8.
+    pub fn from_sincs(sincs: &[Vec<T>], format: CoefficientFormat) -> Self {
+        let sinc_len = sincs[0].len();
+        let oversampling_factor = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        let to_f64 = |value: &T| value.to_f64().unwrap_or(0.0);
+        let coefficients = match format {
+            CoefficientFormat::I16 => {
+                let mut scales = Vec::with_capacity(oversampling_factor);
+                let quantized = sincs
+                    .iter()
+                    .map(|sinc| {
+                        let peak = sinc.iter().map(|c| to_f64(c).abs()).fold(0.0, f64::max);
+                        let scale = if peak > 0.0 { peak / 32767.0 } else { 1.0 };
+                        scales.push(T::coerce(scale));
+                        sinc.iter()
+                            .map(|c| (to_f64(c) / scale).round() as i16)
+                            .collect()
+                    })
+                    .collect();
+                Coefficients::I16 {
+                    sincs: quantized,
+                    scales,
+                }
+            }
+            CoefficientFormat::F16 => Coefficients::F16 {
+                sincs: sincs
+                    .iter()
+                    .map(|sinc| {
+                        sinc.iter()
+                            .map(|c| f32_to_f16_bits(to_f64(c) as f32))
+                            .collect()
+                    })
+                    .collect(),
+            },
+        };
+        Self {
+            coefficients,
+            length: sinc_len,
+            nbr_sincs: oversampling_factor,
+        }
+    }
+
+    /// Get the format used for storing the coefficients.
+    pub fn format(&self) -> CoefficientFormat {
+        match self.coefficients {
+            Coefficients::I16 { .. } => CoefficientFormat::I16,
+            Coefficients::F16 { .. } => CoefficientFormat::F16,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use super::{f16_bits_to_f32, f32_to_f16_bits, CoefficientFormat, QuantizedInterpolator};
+    use crate::sinc::make_sincs;
+    use crate::sinc_interpolator::{InterpolatorBackend, ScalarInterpolator, SincInterpolator};
+    use crate::WindowFunction;
+    use rand::Rng;
+
+    #[test]
+    fn half_precision_conversion() {
+        for (value, bits) in [
+            (0.0f32, 0x0000u16),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (0.333_333_34, 0x3555),
+            (65504.0, 0x7bff),
+            (1.0e6, 0x7bff),
+            (6.103_515_6e-5, 0x0400),
+            (5.960_464_5e-8, 0x0001),
+        ] {
+            assert_eq!(f32_to_f16_bits(value), bits, "value {}", value);
+        }
+        for bits in (0..0x7c00u16).chain(0x8000..0xfc00) {
+            assert_eq!(f32_to_f16_bits(f16_bits_to_f32(bits)), bits);
+        }
+    }
+
+    #[test]
+    fn quantized_interpolators() {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<f64> = (0..1024).map(|_| rng.gen::<f64>() - 0.5).collect();
+        let sincs = make_sincs::<f64>(256, 64, 0.95, WindowFunction::BlackmanHarris2);
+        let reference = ScalarInterpolator::new(256, 64, 0.95, WindowFunction::BlackmanHarris2);
+        for (format, backend, tolerance) in [
+            (
+                CoefficientFormat::I16,
+                InterpolatorBackend::QuantizedI16,
+                1.0e-4,
+            ),
+            (
+                CoefficientFormat::F16,
+                InterpolatorBackend::QuantizedF16,
+                1.0e-3,
+            ),
+        ] {
+            let interpolator = QuantizedInterpolator::from_sincs(&sincs, format);
+            assert_eq!(interpolator.format(), format);
+            assert_eq!(interpolator.backend(), backend);
+            assert_eq!(interpolator.len(), 256);
+            assert_eq!(interpolator.nbr_sincs(), 64);
+            assert!(interpolator.memory_usage() < reference.memory_usage() / 3);
+            for (index, subindex) in [(0, 0), (333, 12), (700, 63)] {
+                let expected = reference.get_sinc_interpolated(&wave, index, subindex);
+                let value = interpolator.get_sinc_interpolated(&wave, index, subindex);
+                assert!(
+                    (value - expected).abs() < tolerance,
+                    "{:?}: {} != {}",
+                    format,
+                    value,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::sinc_interpolator::SincInterpolator;
+// The intrinsics for a sample type are unused when support for that type is disabled.
+#![cfg_attr(not(all(feature = "f32", feature = "f64")), allow(unused_imports))]
+use crate::sinc_interpolator::{InterpolatorBackend, SincInterpolator};
 use crate::error::{CpuFeature, MissingCpuFeature};
 use crate::sinc::make_sincs;
 use crate::windows::WindowFunction;
@@ -16,7 +18,7 @@ static FEATURES: &[CpuFeature] = &[CpuFeature::Sse3];
 
 /// Trait governing what can be done with an SseSample.
 pub trait SseSample: Sized + Send {
-    type Sinc: Send;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -41,6 +43,7 @@ pub trait SseSample: Sized + Send {
     ) -> Self;
 }
 
+#[cfg(feature = "f32")]
 impl SseSample for f32 {
     type Sinc = __m128;
 
@@ -91,6 +94,7 @@ impl SseSample for f32 {
     }
 }
 
+#[cfg(feature = "f64")]
 impl SseSample for f64 {
     type Sinc = __m128d;
 
@@ -188,11 +192,15 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn backend(&self) -> InterpolatorBackend {
+        InterpolatorBackend::Sse3
+    }
 }
 
 impl<T> SseInterpolator<T>
 where
-    T: Sample,
+    T: Sample + SseSample,
 {
     /// Create a new SseInterpolator
     ///
@@ -213,6 +221,20 @@ where
 
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::from_sincs(sincs)
+    }
+
+    /// Create a new SseInterpolator from a set of precalculated sincs.
+    /// The outer vector holds one sinc per oversampling step,
+    /// and all sincs must have the same length, which must be a multiple of 8.
+    pub(crate) fn from_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let oversampling_factor = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = unsafe { <T as SseSample>::pack_sincs(sincs) };
 
         Ok(Self {
@@ -223,7 +245,7 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     use crate::sinc_interpolator::SincInterpolator;
     use crate::sinc_interpolator::sinc_interpolator_sse::SseInterpolator;
@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+
+/// Number of median absolute deviations beyond which a timestamp is rejected as an outlier.
+const OUTLIER_THRESHOLD: f64 = 4.0;
+/// Scale factor from the median absolute deviation to the standard deviation of normal noise.
+const MAD_SCALE: f64 = 1.4826;
+
+/// An estimator of the true sample rate of an audio device, from periodic timestamps.
+///
+/// The clock of an audio device never runs at exactly its nominal rate,
+/// and the difference to the host clock, or to the clock of another device, must be compensated
+/// by adjusting the ratio of an asynchronous resampler.
+/// This estimator is the measurement part of that.
+/// It is given pairs of a sample count and a host timestamp, typically one pair from each callback
+/// of the device, with [add_timestamp](ClockSkewEstimator::add_timestamp).
+///
+/// The rate is found by a straight line fit of the timestamps against the sample counts,
+/// over the last `window` pairs. The timestamps of callbacks are often delayed by scheduling,
+/// so timestamps that deviate from the line by more than four times the median absolute deviation
+/// are rejected, and the line is fitted again without them.
+/// The fitted rates are then smoothed with an exponential average, with the weight `smoothing`
+/// for each new value.
+///
+/// The resulting skew can be applied with
+/// [set_resample_ratio_relative](crate::Resampler::set_resample_ratio_relative),
+/// using [input_relative_ratio](ClockSkewEstimator::input_relative_ratio) when the device
+/// provides the input of the resampler, and
+/// [output_relative_ratio](ClockSkewEstimator::output_relative_ratio) when it consumes
+/// the output.
+/// The buffers are allocated when the estimator is created, and adding timestamps does not allocate.
+/// ```
+/// use rubato::ClockSkewEstimator;
+/// let mut estimator = ClockSkewEstimator::new(48000.0, 64, 0.1);
+/// // A capture device that really runs at 48004 Hz, with a callback every 480 frames
+/// for n in 0..100u64 {
+///     estimator.add_timestamp(480 * n, 480.0 * n as f64 / 48004.0);
+/// }
+/// let rate = estimator.measured_rate().unwrap();
+/// assert!((rate - 48004.0).abs() < 0.01);
+/// let relative_ratio = estimator.input_relative_ratio();
+/// ```
+pub struct ClockSkewEstimator {
+    nominal_rate: f64,
+    window: usize,
+    smoothing: f64,
+    points: VecDeque<(u64, f64)>,
+    residuals: Vec<f64>,
+    sorted: Vec<f64>,
+    accepted: Vec<bool>,
+    rate: Option<f64>,
+    nbr_rejected: usize,
+}
+
+impl ClockSkewEstimator {
+    /// Create a new ClockSkewEstimator.
+    ///
+    /// Parameters are:
+    /// - `nominal_rate`: The nominal sample rate of the device, must be > 0.
+    /// - `window`: Number of timestamps to fit the rate to, at least 2 are used.
+    /// - `smoothing`: Weight of each new fitted rate in the estimate, between 0.0 and 1.0.
+    ///   A value of 1.0 disables the smoothing.
+    pub fn new(nominal_rate: f64, window: usize, smoothing: f64) -> Self {
+        let window = window.max(2);
+        debug!(
+            "Create new ClockSkewEstimator, nominal_rate: {}, window: {}, smoothing: {}",
+            nominal_rate, window, smoothing
+        );
+        ClockSkewEstimator {
+            nominal_rate,
+            window,
+            smoothing: smoothing.clamp(f64::EPSILON, 1.0),
+            points: VecDeque::with_capacity(window + 1),
+            residuals: Vec::with_capacity(window),
+            sorted: Vec::with_capacity(window),
+            accepted: Vec::with_capacity(window),
+            rate: None,
+            nbr_rejected: 0,
+        }
+    }
+
+    /// Add a pair of a sample count and a host timestamp.
+    ///
+    /// Parameters are:
+    /// - `sample_count`: The number of frames the device has processed, counted from any starting point.
+    /// - `timestamp`: The host time in seconds, when the device was at `sample_count`.
+    pub fn add_timestamp(&mut self, sample_count: u64, timestamp: f64) {
+        if let Some((last_count, _)) = self.points.back() {
+            if sample_count <= *last_count {
+                debug!(
+                    "Sample count {} is not increasing, restarting the estimation",
+                    sample_count
+                );
+                self.points.clear();
+            }
+        }
+        self.points.push_back((sample_count, timestamp));
+        if self.points.len() > self.window {
+            self.points.pop_front();
+        }
+        if let Some(rate) = self.fit_rate() {
+            let smoothed = match self.rate {
+                Some(previous) => previous + self.smoothing * (rate - previous),
+                None => rate,
+            };
+            trace!("Fitted rate {}, smoothed rate {}", rate, smoothed);
+            self.rate = Some(smoothed);
+        }
+    }
+
+    /// Fit a line to the timestamps, reject the outliers and fit again.
+    /// Returns the rate in frames per second, or `None` if there are too few timestamps.
+    fn fit_rate(&mut self) -> Option<f64> {
+        if self.points.len() < 2 {
+            return None;
+        }
+        self.accepted.clear();
+        self.accepted.resize(self.points.len(), true);
+        let (slope, offset) = self.fit_line()?;
+        // Reject the timestamps that are too far from the line
+        let first_count = self.points[0].0;
+        let first_time = self.points[0].1;
+        self.residuals.clear();
+        self.residuals
+            .extend(self.points.iter().map(|(count, time)| {
+                ((time - first_time) - (offset + slope * (count - first_count) as f64)).abs()
+            }));
+        self.sorted.clear();
+        self.sorted.extend_from_slice(&self.residuals);
+        let middle = self.sorted.len() / 2;
+        let median = *self
+            .sorted
+            .select_nth_unstable_by(middle, |a, b| {
+                a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .1;
+        let limit = OUTLIER_THRESHOLD * MAD_SCALE * median;
+        let mut nbr_rejected = 0;
+        if limit > 0.0 {
+            for (residual, accepted) in self.residuals.iter().zip(self.accepted.iter_mut()) {
+                if *residual > limit {
+                    *accepted = false;
+                    nbr_rejected += 1;
+                }
+            }
+        }
+        self.nbr_rejected = nbr_rejected;
+        let (slope, _) = if nbr_rejected > 0 {
+            self.fit_line()?
+        } else {
+            (slope, offset)
+        };
+        if slope > 0.0 {
+            Some(1.0 / slope)
+        } else {
+            None
+        }
+    }
+
+    /// Least squares fit of the accepted timestamps against the sample counts,
+    /// relative to the first pair. Returns the slope in seconds per frame and the offset.
+    fn fit_line(&self) -> Option<(f64, f64)> {
+        let first_count = self.points[0].0;
+        let first_time = self.points[0].1;
+        let mut n = 0.0;
+        let mut sum_x = 0.0;
+        let mut sum_y = 0.0;
+        let mut sum_xx = 0.0;
+        let mut sum_xy = 0.0;
+        for ((count, time), _) in self
+            .points
+            .iter()
+            .zip(self.accepted.iter())
+            .filter(|(_, accepted)| **accepted)
+        {
+            let x = (count - first_count) as f64;
+            let y = time - first_time;
+            n += 1.0;
+            sum_x += x;
+            sum_y += y;
+            sum_xx += x * x;
+            sum_xy += x * y;
+        }
+        let denominator = n * sum_xx - sum_x * sum_x;
+        if n < 2.0 || denominator <= 0.0 {
+            return None;
+        }
+        let slope = (n * sum_xy - sum_x * sum_y) / denominator;
+        let offset = (sum_y - slope * sum_x) / n;
+        Some((slope, offset))
+    }
+
+    /// Get the estimated true sample rate of the device,
+    /// or `None` until at least two timestamps have been added.
+    pub fn measured_rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// Get the estimated skew, as the ratio between the true and the nominal sample rate.
+    /// This is 1.0 until at least two timestamps have been added.
+    pub fn skew(&self) -> f64 {
+        self.rate
+            .map(|rate| rate / self.nominal_rate)
+            .unwrap_or(1.0)
+    }
+
+    /// Get the relative resample ratio that compensates the skew,
+    /// when the device provides the input of the resampler.
+    pub fn input_relative_ratio(&self) -> f64 {
+        1.0 / self.skew()
+    }
+
+    /// Get the relative resample ratio that compensates the skew,
+    /// when the device consumes the output of the resampler.
+    pub fn output_relative_ratio(&self) -> f64 {
+        self.skew()
+    }
+
+    /// Get the number of timestamps that were rejected as outliers in the last fit.
+    pub fn nbr_rejected(&self) -> usize {
+        self.nbr_rejected
+    }
+
+    /// Discard all timestamps and the current estimate.
+    pub fn reset(&mut self) {
+        self.points.clear();
+        self.rate = None;
+        self.nbr_rejected = 0;
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::ClockSkewEstimator;
+    use rand::Rng;
+
+    #[test]
+    fn jitter_and_outliers() {
+        let mut rng = rand::thread_rng();
+        let mut estimator = ClockSkewEstimator::new(48000.0, 500, 0.2);
+        assert_eq!(estimator.measured_rate(), None);
+        assert_eq!(estimator.skew(), 1.0);
+        let true_rate = 47995.0;
+        for n in 0..1000u64 {
+            let count = 256 * n;
+            let mut time = 10.0 + count as f64 / true_rate + rng.gen_range(-0.00005..0.00005);
+            // Every 20th callback is delayed by the scheduler
+            if n % 20 == 7 {
+                time += 0.01;
+            }
+            estimator.add_timestamp(count, time);
+        }
+        let rate = estimator.measured_rate().unwrap();
+        assert!((rate - true_rate).abs() < 0.5, "{}", rate);
+        assert!(estimator.nbr_rejected() >= 24);
+        let skew = true_rate / 48000.0;
+        assert!((estimator.output_relative_ratio() - skew).abs() < 1.0e-5);
+        assert!((estimator.input_relative_ratio() - 1.0 / skew).abs() < 1.0e-5);
+
+        // A restarted device starts a new estimation
+        estimator.add_timestamp(0, 1000.0);
+        estimator.reset();
+        assert_eq!(estimator.measured_rate(), None);
+    }
+}
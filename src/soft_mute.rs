@@ -0,0 +1,418 @@
+use crate::error::ResampleResult;
+use crate::{process_wrapped, Resampler, ResamplerConfig, Sample};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FadeState {
+    Idle,
+    FadingIn(usize),
+    FadingOut(usize),
+    Muted,
+}
+
+/// A wrapper for a [Resampler] that applies a short fade to the output around resets.
+///
+/// Calling [reset](Resampler::reset) on a resampler clears all internal buffers.
+/// The first chunk after a reset then starts abruptly, which can cause an audible click
+/// if the sink was still playing the output from before the reset.
+/// This wrapper avoids the discontinuity by fading in the output during the first
+/// `fade_frames` output frames after a reset.
+///
+/// A planned reset can be prepared with [fade_out_and_reset](SoftMute::fade_out_and_reset).
+/// This fades out the output during the next `fade_frames` output frames,
+/// and then resets the wrapped resampler before the following chunk is processed.
+///
+/// The fades only affect the output data, the processing of the wrapped resampler is unchanged.
+/// ```
+/// use rubato::{FftFixedIn, Resampler, SoftMute};
+/// let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+/// let mut resampler = SoftMute::new(resampler, 256);
+/// let waves_in = vec![vec![0.0f64; 1024]; 2];
+/// let _waves_out = resampler.process(&waves_in, None).unwrap();
+/// // Recover from an error, the next output will be faded in.
+/// resampler.reset();
+/// let _waves_out = resampler.process(&waves_in, None).unwrap();
+/// ```
+pub struct SoftMute<R> {
+    resampler: R,
+    fade_frames: usize,
+    state: FadeState,
+    reset_pending: bool,
+}
+
+impl<R> SoftMute<R> {
+    /// Create a new SoftMute wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    /// - `fade_frames`: Length of the fades, in output frames. A value of zero disables the fades.
+    pub fn new(resampler: R, fade_frames: usize) -> Self {
+        SoftMute {
+            resampler,
+            fade_frames,
+            state: FadeState::Idle,
+            reset_pending: false,
+        }
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    /// Note that calling [reset](Resampler::reset) directly on the wrapped resampler
+    /// bypasses the fades.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Get the length of the fades, in output frames.
+    pub fn fade_frames(&self) -> usize {
+        self.fade_frames
+    }
+
+    /// Set the length of the fades, in output frames.
+    /// Any fade that is already in progress is completed with the new length.
+    pub fn set_fade_frames(&mut self, fade_frames: usize) {
+        self.fade_frames = fade_frames;
+        self.state = match self.state {
+            FadeState::FadingIn(pos) if pos >= fade_frames => FadeState::Idle,
+            FadeState::FadingOut(pos) if pos >= fade_frames => FadeState::Muted,
+            state => state,
+        };
+    }
+
+    /// Returns true while a fade is in progress, or while the output is muted
+    /// while waiting for a pending reset.
+    pub fn is_fading(&self) -> bool {
+        self.state != FadeState::Idle
+    }
+
+    /// Fade out the output during the next `fade_frames` output frames,
+    /// and then reset the wrapped resampler.
+    ///
+    /// The reset is performed at the start of the first call to
+    /// [process_into_buffer](Resampler::process_into_buffer) after the fade out has completed.
+    /// The output of that call is faded in.
+    /// If the fade length is zero, the resampler is reset immediately.
+    pub fn fade_out_and_reset<T>(&mut self)
+    where
+        R: Resampler<T>,
+        T: Sample,
+    {
+        if self.fade_frames == 0 {
+            self.reset_now();
+            return;
+        }
+        self.state = match self.state {
+            FadeState::Idle => FadeState::FadingOut(0),
+            // Start the fade out from the current gain
+            FadeState::FadingIn(pos) => FadeState::FadingOut(self.fade_frames - pos),
+            state => state,
+        };
+        self.reset_pending = true;
+    }
+
+    fn reset_now<T>(&mut self)
+    where
+        R: Resampler<T>,
+        T: Sample,
+    {
+        self.resampler.reset();
+        self.reset_pending = false;
+        self.state = if self.fade_frames > 0 {
+            FadeState::FadingIn(0)
+        } else {
+            FadeState::Idle
+        };
+    }
+
+    /// Apply the current fade to the first `frames` frames of the active output channels.
+    fn apply_fade<T: Sample, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        frames: usize,
+    ) {
+        let fade_frames = self.fade_frames;
+        let (start, gain_at): (usize, fn(usize, usize) -> f64) = match self.state {
+            FadeState::Idle => return,
+            FadeState::FadingIn(pos) => (pos, |n, len| n as f64 / len as f64),
+            FadeState::FadingOut(pos) => (pos, |n, len| 1.0 - n as f64 / len as f64),
+            FadeState::Muted => (fade_frames, |_, _| 0.0),
+        };
+        let muted_after = matches!(self.state, FadeState::FadingOut(_) | FadeState::Muted);
+        let fade_len = fade_frames.saturating_sub(start).min(frames);
+        // An output buffer with fewer channels than the mask only contains the active channels
+        let mask = active_channels_mask.filter(|mask| mask.len() == wave_out.len());
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if !mask.map(|mask| mask[chan]).unwrap_or(true) {
+                continue;
+            }
+            let wave = &mut wave.as_mut()[..frames];
+            for (n, sample) in wave.iter_mut().take(fade_len).enumerate() {
+                *sample *= T::coerce(gain_at(start + n, fade_frames));
+            }
+            if muted_after {
+                wave.iter_mut()
+                    .skip(fade_len)
+                    .for_each(|sample| *sample = T::zero());
+            }
+        }
+        let pos = start + fade_len;
+        self.state = match self.state {
+            FadeState::FadingIn(_) if pos >= fade_frames => FadeState::Idle,
+            FadeState::FadingIn(_) => FadeState::FadingIn(pos),
+            FadeState::FadingOut(_) if pos >= fade_frames => FadeState::Muted,
+            FadeState::FadingOut(_) => FadeState::FadingOut(pos),
+            state => state,
+        };
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<T, Vin, Vout>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        T: Sample,
+        R: Resampler<T>,
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[T]>,
+    {
+        if self.reset_pending && self.state == FadeState::Muted {
+            self.reset_now();
+        }
+        let (frames_in, frames_out) = process_wrapped(
+            &mut self.resampler,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            scratch,
+        )?;
+        self.apply_fade(wave_out, active_channels_mask, frames_out);
+        Ok((frames_in, frames_out))
+    }
+}
+
+impl<T, R> Resampler<T> for SoftMute<R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        if self.reset_pending && self.state == FadeState::Muted {
+            self.reset_now();
+        }
+        let (frames_in, frames_out) =
+            self.resampler
+                .process_split_into_buffer(wave_in, wave_out, active_channels_mask)?;
+        self.apply_fade(wave_out, active_channels_mask, frames_out);
+        Ok((frames_in, frames_out))
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    /// Load the history of the wrapped resampler. The output starts from a valid signal,
+    /// so there is no fade in, and any pending reset is dropped.
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)?;
+        self.reset_pending = false;
+        self.state = FadeState::Idle;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "SoftMute")
+    }
+
+    /// Reset the wrapped resampler, and fade in the output during the next `fade_frames` output frames.
+    fn reset(&mut self) {
+        self.reset_now();
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::tests::{assert_same_output, make_fast_resampler, process_ones};
+    use crate::{FastFixedIn, Resampler, SoftMute};
+
+    fn make_resampler() -> FastFixedIn<f64> {
+        make_fast_resampler(1.0, 1.0, 64, 2)
+    }
+
+    #[test]
+    fn fade_in_after_reset() {
+        let mut reference = make_resampler();
+        let mut resampler = SoftMute::new(make_resampler(), 100);
+        for _ in 0..3 {
+            process_ones(&mut reference);
+            process_ones(&mut resampler);
+        }
+        reference.reset();
+        resampler.reset();
+        assert!(resampler.is_fading());
+        let mut frame = 0;
+        for _ in 0..3 {
+            let expected = process_ones(&mut reference);
+            let out = process_ones(&mut resampler);
+            for (exp, val) in expected[0].iter().zip(out[0].iter()) {
+                let gain = (frame as f64 / 100.0).min(1.0);
+                assert!((exp * gain - val).abs() < 1.0e-9, "frame {}", frame);
+                frame += 1;
+            }
+        }
+        assert!(!resampler.is_fading());
+    }
+
+    #[test]
+    fn fade_out_and_reset() {
+        let mut resampler = SoftMute::new(make_resampler(), 100);
+        for _ in 0..3 {
+            process_ones(&mut resampler);
+        }
+        resampler.fade_out_and_reset();
+        let first = process_ones(&mut resampler);
+        assert!((first[0][0] - 1.0).abs() < 1.0e-9);
+        assert!(first[0][63] < 0.4);
+        let second = process_ones(&mut resampler);
+        assert!(second[0].iter().skip(36).all(|v| *v == 0.0));
+        // The reset is performed at the start of the next chunk, which is faded in.
+        let mut reference = make_resampler();
+        let expected = process_ones(&mut reference);
+        let third = process_ones(&mut resampler);
+        for (n, (exp, val)) in expected[0].iter().zip(third[0].iter()).enumerate() {
+            assert!((exp * n as f64 / 100.0 - val).abs() < 1.0e-9, "frame {}", n);
+        }
+    }
+
+    #[test]
+    fn zero_fade_length() {
+        let mut reference = make_resampler();
+        let mut resampler = SoftMute::new(make_resampler(), 0);
+        process_ones(&mut reference);
+        process_ones(&mut resampler);
+        reference.reset();
+        resampler.fade_out_and_reset();
+        assert!(!resampler.is_fading());
+        let waves = vec![vec![1.0f64; 64]; 2];
+        assert_same_output(&mut reference, &mut resampler, &[waves.clone(), waves]);
+    }
+
+    #[test]
+    fn split_input_and_forwarding() {
+        let mut reference = SoftMute::new(make_resampler(), 100);
+        let mut resampler = SoftMute::new(make_resampler(), 100);
+        assert_eq!(
+            resampler.buffering_delay(),
+            resampler.inner().buffering_delay()
+        );
+        assert_eq!(resampler.memory_usage(), resampler.inner().memory_usage());
+        assert_eq!(resampler.scratch_len(), resampler.inner().scratch_len());
+        reference.reset();
+        resampler.reset();
+        let wave: Vec<f64> = (0..64).map(|n| n as f64).collect();
+        let mut expected = vec![vec![0.0; reference.output_frames_max()]; 2];
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        for _ in 0..3 {
+            reference
+                .process_into_buffer(&[&wave, &wave], &mut expected, None)
+                .unwrap();
+            let split = (&wave[..10], &wave[10..]);
+            resampler
+                .process_split_into_buffer(&[split, split], &mut out, None)
+                .unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+}
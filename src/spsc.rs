@@ -0,0 +1,463 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::{ResampleError, ResampleResult};
+use crate::{Resampler, Sample};
+
+/// The storage shared by a [FrameProducer] and a [FrameConsumer].
+///
+/// The samples are stored channel by channel, with `capacity` frames per channel.
+/// The positions count the frames written and read, modulo twice the capacity,
+/// so that a full buffer can be told apart from an empty one.
+/// The producer only writes to the frames between the write and read positions
+/// that are free, and the consumer only reads the frames that are filled,
+/// so the two never access the same samples at the same time.
+struct Shared<T> {
+    nbr_channels: usize,
+    capacity: usize,
+    data: Box<[UnsafeCell<T>]>,
+    write_pos: AtomicUsize,
+    read_pos: AtomicUsize,
+}
+
+// Safety: the producer and consumer only access disjoint parts of the data,
+// and hand them over to each other via the atomic positions.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn index(&self, chan: usize, pos: usize) -> usize {
+        chan * self.capacity + pos % self.capacity
+    }
+
+    /// Advance a position by `frames` frames, that must not be more than the capacity.
+    fn advance(&self, pos: usize, frames: usize) -> usize {
+        (pos + frames) % (2 * self.capacity)
+    }
+
+    /// Get the number of filled frames between a read and a write position.
+    fn filled(&self, read: usize, write: usize) -> usize {
+        (write + 2 * self.capacity - read) % (2 * self.capacity)
+    }
+}
+
+/// Create a lock-free single producer, single consumer ring buffer for audio frames.
+///
+/// The returned [FrameProducer] and [FrameConsumer] can be moved to different threads.
+/// Neither side blocks, allocates or locks when writing or reading.
+/// The consumer can be wrapped in a [ResamplingConsumer],
+/// that runs a resampler on the consumer side, for example in an audio callback.
+///
+/// Parameters are:
+/// - `nbr_channels`: Number of channels.
+/// - `capacity`: Number of frames per channel that the buffer can hold.
+pub fn frame_channel<T: Sample>(
+    nbr_channels: usize,
+    capacity: usize,
+) -> (FrameProducer<T>, FrameConsumer<T>) {
+    let capacity = capacity.max(1);
+    let data = (0..nbr_channels * capacity)
+        .map(|_| UnsafeCell::new(T::zero()))
+        .collect();
+    let shared = Arc::new(Shared {
+        nbr_channels,
+        capacity,
+        data,
+        write_pos: AtomicUsize::new(0),
+        read_pos: AtomicUsize::new(0),
+    });
+    (
+        FrameProducer {
+            shared: shared.clone(),
+        },
+        FrameConsumer { shared },
+    )
+}
+
+/// The writing end of a ring buffer created by [frame_channel].
+pub struct FrameProducer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> FrameProducer<T>
+where
+    T: Sample,
+{
+    /// Get the number of frames per channel that can be written without overwriting unread frames.
+    pub fn free_frames(&self) -> usize {
+        let write = self.shared.write_pos.load(Ordering::Relaxed);
+        let read = self.shared.read_pos.load(Ordering::Acquire);
+        self.shared.capacity - self.shared.filled(read, write)
+    }
+
+    /// Write frames to the buffer. All channels must have the same length.
+    /// If there is not room for all frames, as many as fit are written
+    /// and the rest are dropped.
+    /// Returns the number of frames written per channel.
+    pub fn push<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<usize> {
+        let shared = &*self.shared;
+        if wave_in.len() != shared.nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: shared.nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        let frames_in = wave_in.first().map(|wave| wave.as_ref().len()).unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() != frames_in {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames_in,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        let frames = frames_in.min(self.free_frames());
+        let write = shared.write_pos.load(Ordering::Relaxed);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            for (n, value) in wave.as_ref()[..frames].iter().enumerate() {
+                let cell = &shared.data[shared.index(chan, write + n)];
+                // Safety: the frames after the write position are free, and only touched here.
+                unsafe { *cell.get() = *value };
+            }
+        }
+        shared
+            .write_pos
+            .store(shared.advance(write, frames), Ordering::Release);
+        Ok(frames)
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.shared.nbr_channels
+    }
+}
+
+/// The reading end of a ring buffer created by [frame_channel].
+pub struct FrameConsumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> FrameConsumer<T>
+where
+    T: Sample,
+{
+    /// Get the number of frames per channel that are available for reading.
+    pub fn available_frames(&self) -> usize {
+        let read = self.shared.read_pos.load(Ordering::Relaxed);
+        let write = self.shared.write_pos.load(Ordering::Acquire);
+        self.shared.filled(read, write)
+    }
+
+    /// Get the next `frames` frames of channel `chan` without consuming them.
+    /// The frames are returned as two slices, where the second one is
+    /// non-empty when the frames wrap around the end of the buffer.
+    /// Returns `None` if fewer than `frames` frames are available.
+    pub fn peek(&self, chan: usize, frames: usize) -> Option<(&[T], &[T])> {
+        let shared = &*self.shared;
+        if chan >= shared.nbr_channels || frames > self.available_frames() {
+            return None;
+        }
+        let read = shared.read_pos.load(Ordering::Relaxed);
+        let start = shared.index(chan, read);
+        let first_len = frames.min(shared.capacity - read % shared.capacity);
+        let chan_start = chan * shared.capacity;
+        // Safety: the frames after the read position are filled, and are not written
+        // by the producer until the read position is advanced by `consume`,
+        // which needs a mutable borrow of self.
+        // UnsafeCell<T> has the same memory layout as T.
+        unsafe {
+            let first = std::slice::from_raw_parts(shared.data[start].get() as *const T, first_len);
+            let second = std::slice::from_raw_parts(
+                shared.data[chan_start].get() as *const T,
+                frames - first_len,
+            );
+            Some((first, second))
+        }
+    }
+
+    /// Consume `frames` frames, making room for the producer.
+    /// At most the available number of frames are consumed.
+    /// Returns the number of frames consumed per channel.
+    pub fn consume(&mut self, frames: usize) -> usize {
+        let frames = frames.min(self.available_frames());
+        let read = self.shared.read_pos.load(Ordering::Relaxed);
+        self.shared
+            .read_pos
+            .store(self.shared.advance(read, frames), Ordering::Release);
+        frames
+    }
+
+    /// Get the number of channels.
+    pub fn nbr_channels(&self) -> usize {
+        self.shared.nbr_channels
+    }
+}
+
+/// Runs a resampler on the consumer side of a ring buffer created by [frame_channel].
+///
+/// The producer thread writes frames at the input sample rate with [FrameProducer::push],
+/// and the consumer thread calls [process_into_buffer](ResamplingConsumer::process_into_buffer)
+/// to get resampled frames, for example from an audio callback.
+/// Each call waits for nothing: if not enough frames are available for the next chunk,
+/// it returns `None` and consumes nothing, so the caller can output silence instead.
+/// The frames are given to the resampler directly from the ring buffer,
+/// with [process_split_into_buffer](Resampler::process_split_into_buffer),
+/// and processing does not allocate.
+/// This holds for the resamplers of this crate, and for the wrappers that pass the slices on,
+/// like [SoftMute](crate::SoftMute).
+/// Other wrappers, like [SanitizeInput](crate::SanitizeInput), join the slices in a temporary buffer,
+/// which allocates.
+/// ```
+/// use rubato::{frame_channel, FftFixedOut, Resampler, ResamplingConsumer};
+/// let (mut producer, consumer) = frame_channel::<f64>(2, 4096);
+/// let resampler = FftFixedOut::<f64>::new(44100, 48000, 512, 2, 2).unwrap();
+/// let mut resampling = ResamplingConsumer::new(consumer, resampler).unwrap();
+/// let mut waves_out = vec![vec![0.0f64; 512]; 2];
+/// // Nothing has been written yet.
+/// assert_eq!(resampling.process_into_buffer(&mut waves_out, None).unwrap(), None);
+/// producer.push(&vec![vec![0.0f64; 2048]; 2]).unwrap();
+/// let (_frames_in, frames_out) = resampling
+///     .process_into_buffer(&mut waves_out, None)
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(frames_out, 512);
+/// ```
+pub struct ResamplingConsumer<R, T: 'static> {
+    consumer: FrameConsumer<T>,
+    resampler: R,
+    /// Storage for the slices of each channel. It is always empty between calls,
+    /// and is only kept to reuse the allocation.
+    wave_in: Vec<(&'static [T], &'static [T])>,
+}
+
+/// Clear a vector of slices, and reuse its allocation for slices with another lifetime.
+fn recycle_slices<'a, 'b, T>(mut slices: Vec<(&'a [T], &'a [T])>) -> Vec<(&'b [T], &'b [T])> {
+    slices.clear();
+    let mut slices = std::mem::ManuallyDrop::new(slices);
+    // Safety: the vector is empty, so it holds no slice that could outlive its data,
+    // and the element type only differs in the lifetimes, which don't change the layout.
+    unsafe { Vec::from_raw_parts(slices.as_mut_ptr() as *mut _, 0, slices.capacity()) }
+}
+
+impl<R, T> ResamplingConsumer<R, T>
+where
+    R: Resampler<T>,
+    T: Sample,
+{
+    /// Create a new ResamplingConsumer
+    ///
+    /// Parameters are:
+    /// - `consumer`: The reading end of the ring buffer.
+    /// - `resampler`: The resampler to run, with the same number of channels as the buffer.
+    ///
+    /// The ring buffer must be able to hold the longest chunk of the resampler,
+    /// [input_frames_max](Resampler::input_frames_max) frames, otherwise the next chunk
+    /// might never become available. A smaller buffer gives
+    /// [ResampleError::InsufficientInputBufferSize].
+    pub fn new(consumer: FrameConsumer<T>, resampler: R) -> ResampleResult<Self> {
+        if consumer.nbr_channels() != resampler.nbr_channels() {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: resampler.nbr_channels(),
+                actual: consumer.nbr_channels(),
+            });
+        }
+        if consumer.shared.capacity < resampler.input_frames_max() {
+            return Err(ResampleError::InsufficientInputBufferSize {
+                channel: 0,
+                expected: resampler.input_frames_max(),
+                actual: consumer.shared.capacity,
+            });
+        }
+        let wave_in = Vec::with_capacity(consumer.nbr_channels());
+        Ok(ResamplingConsumer {
+            consumer,
+            resampler,
+            wave_in,
+        })
+    }
+
+    /// Get the number of input frames available in the ring buffer.
+    pub fn available_frames(&self) -> usize {
+        self.consumer.available_frames()
+    }
+
+    /// Resample the next chunk from the ring buffer, writing to a pre-allocated output buffer.
+    ///
+    /// Returns `Ok(None)` without consuming anything if fewer than
+    /// [input_frames_next](Resampler::input_frames_next) frames are available.
+    /// Otherwise the frames are consumed, and the numbers of input and output frames
+    /// are returned like for [Resampler::process_into_buffer].
+    ///
+    /// The resampler may need more frames after it was changed with
+    /// [resampler_mut](ResamplingConsumer::resampler_mut), for example to a larger chunk size.
+    /// If the ring buffer can't hold the frames needed for the next chunk,
+    /// this returns [ResampleError::InsufficientInputBufferSize].
+    pub fn process_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<Option<(usize, usize)>> {
+        let frames_needed = self.resampler.input_frames_next();
+        if self.consumer.shared.capacity < frames_needed {
+            return Err(ResampleError::InsufficientInputBufferSize {
+                channel: 0,
+                expected: frames_needed,
+                actual: self.consumer.shared.capacity,
+            });
+        }
+        if self.consumer.available_frames() < frames_needed {
+            return Ok(None);
+        }
+        let mut wave_in = recycle_slices(std::mem::take(&mut self.wave_in));
+        for chan in 0..self.consumer.nbr_channels() {
+            if let Some(halves) = self.consumer.peek(chan, frames_needed) {
+                wave_in.push(halves);
+            }
+        }
+        let result =
+            self.resampler
+                .process_split_into_buffer(&wave_in, wave_out, active_channels_mask);
+        self.wave_in = recycle_slices(wave_in);
+        let (frames_in, frames_out) = result?;
+        self.consumer.consume(frames_in);
+        Ok(Some((frames_in, frames_out)))
+    }
+
+    /// Get a reference to the resampler.
+    pub fn resampler(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the resampler.
+    pub fn resampler_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Split into the ring buffer consumer and the resampler.
+    pub fn into_parts(self) -> (FrameConsumer<T>, R) {
+        (self.consumer, self.resampler)
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{
+        frame_channel, FastFixedIn, FftFixedIn, PolynomialDegree, ResampleError, Resampler,
+        ResamplingConsumer,
+    };
+
+    #[test]
+    fn wrap_around() {
+        let (mut producer, mut consumer) = frame_channel::<f64>(2, 5);
+        let wave: Vec<Vec<f64>> = vec![vec![1.0, 2.0, 3.0, 4.0], vec![-1.0, -2.0, -3.0, -4.0]];
+        assert_eq!(producer.push(&wave).unwrap(), 4);
+        assert_eq!(consumer.consume(3), 3);
+        // Only 4 frames fit, and the last two wrap around the end
+        let wave: Vec<Vec<f64>> = vec![vec![5.0, 6.0, 7.0, 8.0, 9.0], vec![0.0; 5]];
+        assert_eq!(producer.push(&wave).unwrap(), 4);
+        assert_eq!(producer.free_frames(), 0);
+        assert_eq!(
+            consumer.peek(0, 5),
+            Some((&[4.0, 5.0][..], &[6.0, 7.0, 8.0][..]))
+        );
+        assert_eq!(consumer.peek(1, 6), None);
+    }
+
+    #[test]
+    fn repeated_wrap_around() {
+        let (mut producer, mut consumer) = frame_channel::<f64>(1, 5);
+        for n in 0..100 {
+            let start = 3.0 * n as f64;
+            assert_eq!(
+                producer.push(&[[start, start + 1.0, start + 2.0]]).unwrap(),
+                3
+            );
+            assert_eq!(consumer.available_frames(), 3);
+            let (first, second) = consumer.peek(0, 3).unwrap();
+            let values: Vec<f64> = first.iter().chain(second.iter()).copied().collect();
+            assert_eq!(values, vec![start, start + 1.0, start + 2.0]);
+            assert_eq!(consumer.consume(3), 3);
+            assert_eq!(producer.free_frames(), 5);
+        }
+    }
+
+    #[test]
+    fn too_small_buffer() {
+        let (_producer, consumer) = frame_channel::<f64>(1, 1000);
+        let resampler = FftFixedIn::<f64>::new(48000, 44100, 1024, 2, 1).unwrap();
+        let err = ResamplingConsumer::new(consumer, resampler).err().unwrap();
+        assert!(matches!(
+            err,
+            ResampleError::InsufficientInputBufferSize {
+                expected: 1024,
+                actual: 1000,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn buffer_too_small_after_change() {
+        let (mut producer, consumer) = frame_channel::<f64>(1, 1500);
+        let resampler =
+            FastFixedIn::<f64>::new(1.0, 1.0, PolynomialDegree::Cubic, 1024, 1).unwrap();
+        let mut resampling = ResamplingConsumer::new(consumer, resampler).unwrap();
+        producer.push(&[vec![0.0; 1500]]).unwrap();
+        let mut wave_out = vec![vec![0.0; 4096]];
+        assert!(resampling
+            .process_into_buffer(&mut wave_out, None)
+            .unwrap()
+            .is_some());
+        // The larger chunks can never become available, which is an error instead of None
+        resampling.resampler_mut().set_chunk_size(2048).unwrap();
+        let err = resampling
+            .process_into_buffer(&mut wave_out, None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ResampleError::InsufficientInputBufferSize {
+                expected: 2048,
+                actual: 1500,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn threaded_resampling() {
+        let (mut producer, consumer) = frame_channel::<f64>(1, 4096);
+        let resampler = FftFixedIn::<f64>::new(48000, 44100, 1024, 2, 1).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(48000, 44100, 1024, 2, 1).unwrap();
+        let mut resampling = ResamplingConsumer::new(consumer, resampler).unwrap();
+        let input: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let thread_input = input.clone();
+        let writer = std::thread::spawn(move || {
+            let mut written = 0;
+            while written < thread_input.len() {
+                let end = (written + 300).min(thread_input.len());
+                written += producer.push(&[&thread_input[written..end]]).unwrap();
+                std::thread::yield_now();
+            }
+        });
+        let mut output = Vec::new();
+        let mut wave_out = vec![vec![0.0f64; resampling.resampler().output_frames_max()]; 1];
+        let mut frames_read = 0;
+        while frames_read + 1024 <= input.len() {
+            if let Some((frames_in, frames_out)) =
+                resampling.process_into_buffer(&mut wave_out, None).unwrap()
+            {
+                frames_read += frames_in;
+                output.extend_from_slice(&wave_out[0][..frames_out]);
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        writer.join().unwrap();
+        let mut expected = Vec::new();
+        for chunk in input.chunks_exact(1024) {
+            expected.extend_from_slice(&reference.process(&[chunk], None).unwrap()[0]);
+        }
+        assert_eq!(output, expected);
+    }
+}
@@ -1,28 +1,124 @@
+use crate::denormal::flush_scope;
 use crate::error::ResamplerConstructionError;
 use crate::sinc::make_sincs;
 use crate::windows::WindowFunction;
 use num_complex::Complex;
 use num_integer as integer;
 use num_traits::Zero;
+use std::any::TypeId;
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use crate::error::{ResampleError, ResampleResult};
-use crate::{calculate_cutoff, update_mask_from_buffers, validate_buffers, Resampler, Sample};
+use crate::{
+    buffer_size, calculate_cutoff, check_memory_limit, fft_plan_size, heap_size, heap_size_nested,
+    validate_and_update_mask, validate_scratch, CoerceFrom, Contiguous, InputChannels, Resampler,
+    ResamplerAlgorithm, ResamplerConfig, Sample, Split,
+};
 use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
 
+/// The precision of the FFTs done internally by the FFT resamplers.
+///
+/// By default the FFTs use the sample type of the resampler.
+/// Doing them in [f32] while the samples are [f64] halves the memory used by the FFT plans
+/// and the work buffers, and the memory bandwidth when processing.
+/// The result then has the accuracy of f32, which gives a noise floor around -140 dB.
+/// The other way around, doing the FFTs in [f64] for [f32] samples
+/// lowers the rounding noise of long FFTs below the resolution of the f32 output.
+///
+/// Choosing the precision of the sample type gives the same resampler as the plain constructors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FftPrecision {
+    /// Use the sample type of the resampler. This is the default.
+    SampleType,
+    /// Do the FFTs in single precision, [f32].
+    Single,
+    /// Do the FFTs in double precision, [f64].
+    Double,
+}
+
+impl Default for FftPrecision {
+    fn default() -> Self {
+        FftPrecision::SampleType
+    }
+}
+
+/// The FFT plans of an [FftResampler], in the internal precision `U`.
+struct FftPlans<U> {
+    fft_size_in: usize,
+    fft_size_out: usize,
+    fft: Arc<dyn RealToComplex<U>>,
+    ifft: Arc<dyn ComplexToReal<U>>,
+}
+
+/// The temporary buffers used when resampling a chunk,
+/// borrowed either from an [FftUnit] or from a scratch buffer given by the caller.
+struct WorkBuffers<'a, U> {
+    input_buf: &'a mut [U],
+    input_f: &'a mut [Complex<U>],
+    output_f: &'a mut [Complex<U>],
+    output_buf: &'a mut [U],
+    scratch_fw: &'a mut [Complex<U>],
+    scratch_inv: &'a mut [Complex<U>],
+}
+
+/// The FFT plans, the filter spectrum and the work buffers of an [FftResampler],
+/// in the internal precision `U`.
+struct FftUnit<U> {
+    plans: FftPlans<U>,
+    filter_f: Vec<Complex<U>>,
+    scratch_fw: Vec<Complex<U>>,
+    scratch_inv: Vec<Complex<U>>,
+    input_buf: Vec<U>,
+    input_f: Vec<Complex<U>>,
+    output_f: Vec<Complex<U>>,
+    output_buf: Vec<U>,
+}
+
+/// The FFTs of an [FftResampler], done either in the sample type `T` or in another precision.
+trait FftKernel<T>: Send + Sync
+where
+    T: Sample,
+{
+    /// Change the fft sizes.
+    fn resize(&mut self, fft_size_in: usize, fft_size_out: usize);
+
+    /// Get the approximate heap memory used by the FFT plans and the buffers, in bytes.
+    fn memory_usage(&self) -> usize;
+
+    /// Get the length of a scratch buffer that can replace the work buffers,
+    /// or 0 if the kernel can't use one.
+    fn scratch_len(&self) -> usize;
+
+    /// Calculate and store the spectrum of a filter, where `load_filter` copies
+    /// the time domain filter to the start of the input buffer.
+    fn transform_filter(&mut self, load_filter: &mut dyn FnMut(&mut [T]));
+
+    /// Get the stored filter spectrum, converted to the sample type if needed.
+    fn filter_spectrum(&self) -> Cow<'_, [Complex<T>]>;
+
+    /// Resample a small chunk, where `load_input` copies the input to the start of the input buffer.
+    /// The work buffers are taken from `scratch` if given, and if the kernel can use it.
+    fn resample_unit(
+        &mut self,
+        load_input: &mut dyn FnMut(&mut [T]),
+        wave_out: &mut [T],
+        overlap: &mut [T],
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<()>;
+}
+
+/// A kernel doing the FFTs in the precision `U`, converting the samples of type `T`.
+struct ConvertingKernel<T, U> {
+    unit: FftUnit<U>,
+    staging: Vec<T>,
+}
+
 /// A helper for resampling a single chunk of data.
 struct FftResampler<T> {
     fft_size_in: usize,
     fft_size_out: usize,
-    filter_f: Vec<Complex<T>>,
-    fft: Arc<dyn RealToComplex<T>>,
-    ifft: Arc<dyn ComplexToReal<T>>,
-    scratch_fw: Vec<Complex<T>>,
-    scratch_inv: Vec<Complex<T>>,
-    input_buf: Vec<T>,
-    input_f: Vec<Complex<T>>,
-    output_f: Vec<Complex<T>>,
-    output_buf: Vec<T>,
+    kernel: Box<dyn FftKernel<T>>,
 }
 
 /// A synchronous resampler that needs a fixed number of audio frames for input
@@ -31,9 +127,15 @@ struct FftResampler<T> {
 /// The resampling is done by FFT:ing the input data. The spectrum is then extended or
 /// truncated as well as multiplied with an antialiasing filter
 /// before it's inverse transformed to get the resampled waveforms.
+///
+/// The FFT works on sub-chunks of a size that depends on the ratio between the sample rates,
+/// but the input chunk size can be any number of frames.
+/// Input frames that do not fill a whole sub-chunk are kept until the next call,
+/// which is why the number of output frames varies.
 pub struct FftFixedIn<T> {
     nbr_channels: usize,
     chunk_size_in: usize,
+    sub_chunks: usize,
     fft_size_in: usize,
     fft_size_out: usize,
     overlaps: Vec<Vec<T>>,
@@ -49,9 +151,15 @@ pub struct FftFixedIn<T> {
 /// The resampling is done by FFT:ing the input data. The spectrum is then extended or
 /// truncated as well as multiplied with an antialiasing filter
 /// before it's inverse transformed to get the resampled waveforms.
+///
+/// The FFT works on sub-chunks of a size that depends on the ratio between the sample rates,
+/// but the output chunk size can be any number of frames.
+/// Output frames beyond the chunk size are kept until the next call,
+/// which is why the number of input frames needed varies.
 pub struct FftFixedOut<T> {
     nbr_channels: usize,
     chunk_size_out: usize,
+    sub_chunks: usize,
     fft_size_in: usize,
     fft_size_out: usize,
     overlaps: Vec<Vec<T>>,
@@ -72,88 +180,149 @@ pub struct FftFixedInOut<T> {
     nbr_channels: usize,
     chunk_size_in: usize,
     chunk_size_out: usize,
+    chunk_size_wanted: usize,
     fft_size_in: usize,
     channel_mask: Vec<bool>,
     overlaps: Vec<Vec<T>>,
     resampler: FftResampler<T>,
 }
 
-fn validate_sample_rates(input: usize, output: usize) -> Result<(), ResamplerConstructionError> {
+pub(crate) fn validate_sample_rates(
+    input: usize,
+    output: usize,
+) -> Result<(), ResamplerConstructionError> {
     if input == 0 || output == 0 {
         return Err(ResamplerConstructionError::InvalidSampleRate { input, output });
     }
     Ok(())
 }
 
-impl<T> FftResampler<T>
+/// Calculate the fft sizes `(fft_size_in, fft_size_out)` for the given sample rates,
+/// where `wanted` is the desired size on the side given by `fixed_input`.
+pub(crate) fn calculate_fft_sizes(
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+    wanted: usize,
+    fixed_input: bool,
+) -> (usize, usize) {
+    let gcd = integer::gcd(sample_rate_input, sample_rate_output);
+    let min_chunk = if fixed_input {
+        sample_rate_input / gcd
+    } else {
+        sample_rate_output / gcd
+    };
+    // Use at least one unit, so that very small chunks are collected into a whole FFT
+    let fft_chunks = ((wanted as f32 / min_chunk as f32).ceil() as usize).max(1);
+    let fft_size_out = fft_chunks * sample_rate_output / gcd;
+    let fft_size_in = fft_chunks * sample_rate_input / gcd;
+    (fft_size_in, fft_size_out)
+}
+
+/// Set all values of a buffer to zero, and resize each channel to the given length.
+fn clear_and_resize<T: Sample>(buffers: &mut [Vec<T>], length: usize) {
+    for buf in buffers.iter_mut() {
+        buf.iter_mut().for_each(|s| *s = T::zero());
+        buf.resize(length, T::zero());
+    }
+}
+
+impl<U> FftPlans<U>
 where
-    T: Sample,
+    U: realfft::FftNum,
 {
-    //
-    pub fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
-        // calculate antialiasing cutoff
-        let cutoff = if fft_size_in > fft_size_out {
-            calculate_cutoff::<f32>(fft_size_out, WindowFunction::BlackmanHarris2)
-                * fft_size_out as f32
-                / fft_size_in as f32
-        } else {
-            calculate_cutoff::<f32>(fft_size_in, WindowFunction::BlackmanHarris2)
-        };
-        debug!(
-            "Create new FftResampler, fft_size_in: {}, fft_size_out: {}, cutoff: {}",
-            fft_size_in, fft_size_out, cutoff
-        );
-        let sinc = make_sincs::<T>(fft_size_in, 1, cutoff, WindowFunction::BlackmanHarris2);
-        let mut filter_t: Vec<T> = vec![T::zero(); 2 * fft_size_in];
-        let mut filter_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
-        for (n, f) in filter_t.iter_mut().enumerate().take(fft_size_in) {
-            *f = sinc[0][n] / T::coerce(2 * fft_size_in);
-        }
-
-        let input_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
-        let input_buf: Vec<T> = vec![T::zero(); 2 * fft_size_in];
-        let output_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_out + 1];
-        let output_buf: Vec<T> = vec![T::zero(); 2 * fft_size_out];
-        let mut planner = RealFftPlanner::<T>::new();
-        let fft = planner.plan_fft_forward(2 * fft_size_in);
-        let ifft = planner.plan_fft_inverse(2 * fft_size_out);
-        fft.process(&mut filter_t, &mut filter_f).unwrap();
-        let scratch_fw = fft.make_scratch_vec();
-        let scratch_inv = ifft.make_scratch_vec();
-
-        FftResampler {
+    fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
+        let mut planner = RealFftPlanner::<U>::new();
+        FftPlans {
             fft_size_in,
             fft_size_out,
-            filter_f,
-            fft,
-            ifft,
-            scratch_fw,
-            scratch_inv,
+            fft: planner.plan_fft_forward(2 * fft_size_in),
+            ifft: planner.plan_fft_inverse(2 * fft_size_out),
+        }
+    }
+
+    /// Change the fft sizes, keeping the plans that don't change.
+    fn resize(&mut self, fft_size_in: usize, fft_size_out: usize) {
+        let mut planner = RealFftPlanner::<U>::new();
+        if fft_size_in != self.fft_size_in {
+            self.fft = planner.plan_fft_forward(2 * fft_size_in);
+        }
+        if fft_size_out != self.fft_size_out {
+            self.ifft = planner.plan_fft_inverse(2 * fft_size_out);
+        }
+        self.fft_size_in = fft_size_in;
+        self.fft_size_out = fft_size_out;
+    }
+
+    /// Get the number of values of type `U` needed to hold all the work buffers.
+    fn scratch_len(&self) -> usize {
+        2 * self.fft_size_in
+            + 2 * self.fft_size_out
+            + 2 * (self.fft_size_in + 1)
+            + 2 * (self.fft_size_out + 1)
+            + 2 * self.fft.get_scratch_len()
+            + 2 * self.ifft.get_scratch_len()
+    }
+
+    /// Split a scratch buffer of at least [scratch_len](FftPlans::scratch_len) values
+    /// into the work buffers.
+    #[cfg(not(feature = "forbid_unsafe"))]
+    fn split_scratch<'a>(&self, scratch: &'a mut [U]) -> ResampleResult<WorkBuffers<'a, U>> {
+        let expected = self.scratch_len();
+        if scratch.len() < expected {
+            return Err(ResampleError::InsufficientScratchSize {
+                expected,
+                actual: scratch.len(),
+            });
+        }
+        let (input_buf, rest) = scratch.split_at_mut(2 * self.fft_size_in);
+        let (output_buf, rest) = rest.split_at_mut(2 * self.fft_size_out);
+        let complex_len = self.fft_size_in
+            + self.fft_size_out
+            + 2
+            + self.fft.get_scratch_len()
+            + self.ifft.get_scratch_len();
+        // Safety: Complex<U> is repr(C) with two fields of type U, so it has the size
+        // of two values of type U and the same alignment. The slice holds at least
+        // 2 * complex_len values since the length of the scratch buffer was checked above,
+        // and is borrowed mutably for the lifetime of the result.
+        let complex: &'a mut [Complex<U>] = unsafe {
+            std::slice::from_raw_parts_mut(rest.as_mut_ptr() as *mut Complex<U>, complex_len)
+        };
+        let (input_f, rest) = complex.split_at_mut(self.fft_size_in + 1);
+        let (output_f, rest) = rest.split_at_mut(self.fft_size_out + 1);
+        let (scratch_fw, scratch_inv) = rest.split_at_mut(self.fft.get_scratch_len());
+        Ok(WorkBuffers {
             input_buf,
             input_f,
             output_f,
             output_buf,
-        }
+            scratch_fw,
+            scratch_inv,
+        })
     }
 
-    /// Resample a small chunk
-    fn resample_unit(&mut self, wave_in: &[T], wave_out: &mut [T], overlap: &mut [T]) {
-        // Copy to input buffer and clear padding area
-        self.input_buf[0..self.fft_size_in].copy_from_slice(wave_in);
-        for item in self
+    /// Clear the padding area of the input buffer, and transform it to `input_f`.
+    fn forward(&self, buffers: &mut WorkBuffers<U>) -> ResampleResult<()> {
+        for item in buffers
             .input_buf
             .iter_mut()
             .skip(self.fft_size_in)
             .take(self.fft_size_in)
         {
-            *item = T::zero();
+            *item = U::zero();
         }
-
-        // FFT and store result in history, update index
         self.fft
-            .process_with_scratch(&mut self.input_buf, &mut self.input_f, &mut self.scratch_fw)
-            .unwrap();
+            .process_with_scratch(buffers.input_buf, buffers.input_f, buffers.scratch_fw)
+            .map_err(|_| ResampleError::FftFailed)
+    }
 
+    /// Multiply the spectrum in `input_f` with the filter,
+    /// and inverse transform the truncated or extended spectrum to `output_buf`.
+    fn filter_and_inverse(
+        &self,
+        buffers: &mut WorkBuffers<U>,
+        filter_f: &[Complex<U>],
+    ) -> ResampleResult<()> {
         let new_len = if self.fft_size_in < self.fft_size_out {
             self.fft_size_in + 1
         } else {
@@ -161,29 +330,431 @@ where
         };
 
         // multiply with filter FT
-        self.input_f
+        buffers
+            .input_f
             .iter_mut()
             .take(new_len)
-            .zip(self.filter_f.iter())
-            .for_each(|(spec, filt)| *spec *= filt);
+            .zip(filter_f.iter())
+            .for_each(|(spec, filt)| *spec = *spec * filt);
 
         // copy to modified spectrum
-        self.output_f[0..new_len].copy_from_slice(&self.input_f[0..new_len]);
-        for val in self.output_f[new_len..].iter_mut() {
+        buffers.output_f[0..new_len].copy_from_slice(&buffers.input_f[0..new_len]);
+        for val in buffers.output_f[new_len..].iter_mut() {
             *val = Complex::zero();
         }
-        // IFFT result, store result and overlap
         self.ifft
-            .process_with_scratch(
-                &mut self.output_f,
-                &mut self.output_buf,
-                &mut self.scratch_inv,
-            )
-            .unwrap();
-        for (n, item) in wave_out.iter_mut().enumerate().take(self.fft_size_out) {
-            *item = self.output_buf[n] + overlap[n];
+            .process_with_scratch(buffers.output_f, buffers.output_buf, buffers.scratch_inv)
+            .map_err(|_| ResampleError::FftFailed)
+    }
+}
+
+impl<U> FftUnit<U>
+where
+    U: realfft::FftNum,
+{
+    fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
+        let plans = FftPlans::new(fft_size_in, fft_size_out);
+        let scratch_fw = plans.fft.make_scratch_vec();
+        let scratch_inv = plans.ifft.make_scratch_vec();
+        FftUnit {
+            plans,
+            filter_f: vec![Complex::zero(); fft_size_in + 1],
+            scratch_fw,
+            scratch_inv,
+            input_buf: vec![U::zero(); 2 * fft_size_in],
+            input_f: vec![Complex::zero(); fft_size_in + 1],
+            output_f: vec![Complex::zero(); fft_size_out + 1],
+            output_buf: vec![U::zero(); 2 * fft_size_out],
         }
-        overlap.copy_from_slice(&self.output_buf[self.fft_size_out..]);
+    }
+
+    /// Change the fft sizes, reusing the existing FFT plans and buffers as far as possible.
+    fn resize(&mut self, fft_size_in: usize, fft_size_out: usize) {
+        self.plans.resize(fft_size_in, fft_size_out);
+        self.filter_f.resize(fft_size_in + 1, Complex::zero());
+        self.scratch_fw
+            .resize(self.plans.fft.get_scratch_len(), Complex::zero());
+        self.scratch_inv
+            .resize(self.plans.ifft.get_scratch_len(), Complex::zero());
+        self.input_buf.resize(2 * fft_size_in, U::zero());
+        self.input_f.resize(fft_size_in + 1, Complex::zero());
+        self.output_f.resize(fft_size_out + 1, Complex::zero());
+        self.output_buf.resize(2 * fft_size_out, U::zero());
+    }
+
+    fn memory_usage(&self) -> usize {
+        fft_plan_size::<U>(self.plans.fft.len())
+            + fft_plan_size::<U>(self.plans.ifft.len())
+            + heap_size(&self.filter_f)
+            + heap_size(&self.scratch_fw)
+            + heap_size(&self.scratch_inv)
+            + heap_size(&self.input_buf)
+            + heap_size(&self.input_f)
+            + heap_size(&self.output_f)
+            + heap_size(&self.output_buf)
+    }
+
+    /// Get the plans, the filter spectrum, and the work buffers owned by the unit.
+    fn buffers(&mut self) -> (&FftPlans<U>, &[Complex<U>], WorkBuffers<'_, U>) {
+        (
+            &self.plans,
+            &self.filter_f,
+            WorkBuffers {
+                input_buf: &mut self.input_buf,
+                input_f: &mut self.input_f,
+                output_f: &mut self.output_f,
+                output_buf: &mut self.output_buf,
+                scratch_fw: &mut self.scratch_fw,
+                scratch_inv: &mut self.scratch_inv,
+            },
+        )
+    }
+
+    /// Transform the filter at the start of the input buffer, and store its spectrum.
+    fn store_filter(&mut self) {
+        let (plans, _, mut buffers) = self.buffers();
+        // The buffers of the unit are always resized together with the plans
+        plans
+            .forward(&mut buffers)
+            .expect("the buffers of the unit match its FFT plans");
+        self.filter_f.copy_from_slice(&self.input_f);
+    }
+}
+
+impl<T> FftKernel<T> for FftUnit<T>
+where
+    T: Sample,
+{
+    fn resize(&mut self, fft_size_in: usize, fft_size_out: usize) {
+        FftUnit::resize(self, fft_size_in, fft_size_out);
+    }
+
+    fn memory_usage(&self) -> usize {
+        FftUnit::memory_usage(self)
+    }
+
+    fn scratch_len(&self) -> usize {
+        if cfg!(feature = "forbid_unsafe") {
+            0
+        } else {
+            self.plans.scratch_len()
+        }
+    }
+
+    fn transform_filter(&mut self, load_filter: &mut dyn FnMut(&mut [T])) {
+        load_filter(&mut self.input_buf[0..self.plans.fft_size_in]);
+        self.store_filter();
+    }
+
+    fn filter_spectrum(&self) -> Cow<'_, [Complex<T>]> {
+        Cow::Borrowed(&self.filter_f)
+    }
+
+    fn resample_unit(
+        &mut self,
+        load_input: &mut dyn FnMut(&mut [T]),
+        wave_out: &mut [T],
+        overlap: &mut [T],
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<()> {
+        #[cfg(not(feature = "forbid_unsafe"))]
+        let (plans, filter_f, mut buffers) = match scratch {
+            Some(scratch) => (
+                &self.plans,
+                &self.filter_f[..],
+                self.plans.split_scratch(scratch)?,
+            ),
+            None => self.buffers(),
+        };
+        #[cfg(feature = "forbid_unsafe")]
+        let (plans, filter_f, mut buffers) = {
+            let _ = scratch;
+            self.buffers()
+        };
+        load_input(&mut buffers.input_buf[0..plans.fft_size_in]);
+        plans.forward(&mut buffers)?;
+        plans.filter_and_inverse(&mut buffers, filter_f)?;
+        // store result and overlap
+        let fft_size_out = plans.fft_size_out;
+        for (n, item) in wave_out.iter_mut().enumerate().take(fft_size_out) {
+            *item = buffers.output_buf[n] + overlap[n];
+        }
+        overlap.copy_from_slice(&buffers.output_buf[fft_size_out..]);
+        Ok(())
+    }
+}
+
+impl<T, U> ConvertingKernel<T, U>
+where
+    T: Sample + CoerceFrom<U>,
+    U: realfft::FftNum + CoerceFrom<T>,
+{
+    /// Check if the work buffers can be placed in a scratch buffer of the sample type.
+    /// This needs a sample type where any bit pattern is a valid value, like f32 and f64.
+    fn can_use_scratch() -> bool {
+        !cfg!(feature = "forbid_unsafe")
+            && (TypeId::of::<T>() == TypeId::of::<f32>()
+                || TypeId::of::<T>() == TypeId::of::<f64>())
+    }
+
+    /// Get the number of samples needed to hold the work buffers in the precision `U`,
+    /// including the padding needed to align them.
+    fn work_len(&self) -> usize {
+        let bytes =
+            self.unit.plans.scratch_len() * std::mem::size_of::<U>() + std::mem::align_of::<U>();
+        (bytes + std::mem::size_of::<T>() - 1) / std::mem::size_of::<T>()
+    }
+
+    /// Convert the input in `staging` to the input buffer, and resample it with the work buffers.
+    fn resample_buffers(
+        plans: &FftPlans<U>,
+        filter_f: &[Complex<U>],
+        buffers: &mut WorkBuffers<U>,
+        staging: &[T],
+        wave_out: &mut [T],
+        overlap: &mut [T],
+    ) -> ResampleResult<()> {
+        for (dst, src) in buffers.input_buf.iter_mut().zip(staging.iter()) {
+            *dst = U::coerce_from(*src);
+        }
+        plans.forward(buffers)?;
+        plans.filter_and_inverse(buffers, filter_f)?;
+        // store result and overlap
+        let fft_size_out = plans.fft_size_out;
+        for (n, item) in wave_out.iter_mut().enumerate().take(fft_size_out) {
+            *item = T::coerce(buffers.output_buf[n]) + overlap[n];
+        }
+        for (item, value) in overlap
+            .iter_mut()
+            .zip(buffers.output_buf[fft_size_out..].iter())
+        {
+            *item = T::coerce(*value);
+        }
+        Ok(())
+    }
+}
+
+impl<T, U> FftKernel<T> for ConvertingKernel<T, U>
+where
+    T: Sample + CoerceFrom<U>,
+    U: realfft::FftNum + CoerceFrom<T>,
+{
+    fn resize(&mut self, fft_size_in: usize, fft_size_out: usize) {
+        self.unit.resize(fft_size_in, fft_size_out);
+        self.staging.resize(fft_size_in, T::zero());
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.unit.memory_usage() + heap_size(&self.staging)
+    }
+
+    /// The scratch buffer holds the converted input, followed by the work buffers in the precision `U`.
+    fn scratch_len(&self) -> usize {
+        if Self::can_use_scratch() {
+            self.unit.plans.fft_size_in + self.work_len()
+        } else {
+            0
+        }
+    }
+
+    fn transform_filter(&mut self, load_filter: &mut dyn FnMut(&mut [T])) {
+        load_filter(&mut self.staging);
+        for (dst, src) in self.unit.input_buf.iter_mut().zip(self.staging.iter()) {
+            *dst = U::coerce_from(*src);
+        }
+        self.unit.store_filter();
+    }
+
+    fn filter_spectrum(&self) -> Cow<'_, [Complex<T>]> {
+        Cow::Owned(
+            self.unit
+                .filter_f
+                .iter()
+                .map(|value| Complex::new(T::coerce(value.re), T::coerce(value.im)))
+                .collect(),
+        )
+    }
+
+    fn resample_unit(
+        &mut self,
+        load_input: &mut dyn FnMut(&mut [T]),
+        wave_out: &mut [T],
+        overlap: &mut [T],
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<()> {
+        #[cfg(not(feature = "forbid_unsafe"))]
+        if let Some(scratch) = scratch.filter(|_| Self::can_use_scratch()) {
+            let expected = self.scratch_len();
+            if scratch.len() < expected {
+                return Err(ResampleError::InsufficientScratchSize {
+                    expected,
+                    actual: scratch.len(),
+                });
+            }
+            let (staging, rest) = scratch.split_at_mut(self.unit.plans.fft_size_in);
+            load_input(staging);
+            // Safety: the sample type is f32 or f64, checked by can_use_scratch,
+            // and U is a floating point type of the FFTs. Any bit pattern is a valid value
+            // of both, so the memory can be viewed as either type.
+            let (_, work, _) = unsafe { rest.align_to_mut::<U>() };
+            let mut buffers = self.unit.plans.split_scratch(work)?;
+            return Self::resample_buffers(
+                &self.unit.plans,
+                &self.unit.filter_f,
+                &mut buffers,
+                staging,
+                wave_out,
+                overlap,
+            );
+        }
+        #[cfg(feature = "forbid_unsafe")]
+        let _ = scratch;
+        load_input(&mut self.staging);
+        let (plans, filter_f, mut buffers) = self.unit.buffers();
+        Self::resample_buffers(
+            plans,
+            filter_f,
+            &mut buffers,
+            &self.staging,
+            wave_out,
+            overlap,
+        )
+    }
+}
+
+impl<T> FftResampler<T>
+where
+    T: Sample,
+{
+    /// Get the approximate heap memory used by the filter, the FFT plans and the buffers, in bytes.
+    fn memory_usage(&self) -> usize {
+        self.kernel.memory_usage()
+    }
+
+    /// Estimate the heap memory of a new FftResampler in bytes, before it is built.
+    /// This counts the FFT plans, the filter and the work buffers, but not the scratch buffers of the plans.
+    fn estimate_memory(fft_size_in: usize, fft_size_out: usize) -> usize {
+        let spectra = fft_size_in
+            .saturating_mul(2)
+            .saturating_add(fft_size_out)
+            .saturating_add(3);
+        let plans = fft_size_in.saturating_add(fft_size_out).saturating_mul(2);
+        buffer_size::<Complex<T>>(1, spectra)
+            .saturating_add(buffer_size::<Complex<T>>(1, plans))
+            .saturating_add(buffer_size::<T>(1, plans))
+    }
+
+    /// Create a new FftResampler, doing the FFTs in the sample type.
+    pub fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
+        Self::with_kernel(
+            fft_size_in,
+            fft_size_out,
+            Box::new(FftUnit::<T>::new(fft_size_in, fft_size_out)),
+        )
+    }
+
+    /// Create a new FftResampler, doing the FFTs in the given precision.
+    pub fn with_precision(fft_size_in: usize, fft_size_out: usize, precision: FftPrecision) -> Self
+    where
+        f32: CoerceFrom<T>,
+        f64: CoerceFrom<T>,
+    {
+        let same_as_sample = match precision {
+            FftPrecision::SampleType => true,
+            FftPrecision::Single => TypeId::of::<T>() == TypeId::of::<f32>(),
+            FftPrecision::Double => TypeId::of::<T>() == TypeId::of::<f64>(),
+        };
+        if same_as_sample {
+            return Self::new(fft_size_in, fft_size_out);
+        }
+        debug!(
+            "Create FftResampler with internal precision {:?}",
+            precision
+        );
+        let kernel: Box<dyn FftKernel<T>> = match precision {
+            FftPrecision::Double => Box::new(ConvertingKernel::<T, f64> {
+                unit: FftUnit::new(fft_size_in, fft_size_out),
+                staging: vec![T::zero(); fft_size_in],
+            }),
+            _ => Box::new(ConvertingKernel::<T, f32> {
+                unit: FftUnit::new(fft_size_in, fft_size_out),
+                staging: vec![T::zero(); fft_size_in],
+            }),
+        };
+        Self::with_kernel(fft_size_in, fft_size_out, kernel)
+    }
+
+    fn with_kernel(fft_size_in: usize, fft_size_out: usize, kernel: Box<dyn FftKernel<T>>) -> Self {
+        let mut resampler = FftResampler {
+            fft_size_in,
+            fft_size_out,
+            kernel,
+        };
+        resampler.update_filter();
+        resampler
+    }
+
+    /// Change the fft sizes, reusing the existing FFT plans and buffers as far as possible.
+    fn resize(&mut self, fft_size_in: usize, fft_size_out: usize) {
+        if fft_size_in == self.fft_size_in && fft_size_out == self.fft_size_out {
+            return;
+        }
+        debug!(
+            "Resize FftResampler, fft_size_in: {} -> {}, fft_size_out: {} -> {}",
+            self.fft_size_in, fft_size_in, self.fft_size_out, fft_size_out
+        );
+        self.kernel.resize(fft_size_in, fft_size_out);
+        self.fft_size_in = fft_size_in;
+        self.fft_size_out = fft_size_out;
+        self.update_filter();
+    }
+
+    /// Calculate the antialiasing filter for the current fft sizes.
+    fn update_filter(&mut self) {
+        let fft_size_in = self.fft_size_in;
+        let fft_size_out = self.fft_size_out;
+        // calculate antialiasing cutoff
+        let cutoff = if fft_size_in > fft_size_out {
+            calculate_cutoff::<f32>(fft_size_out, WindowFunction::BlackmanHarris2)
+                * fft_size_out as f32
+                / fft_size_in as f32
+        } else {
+            calculate_cutoff::<f32>(fft_size_in, WindowFunction::BlackmanHarris2)
+        };
+        debug!(
+            "Calculate FftResampler filter, fft_size_in: {}, fft_size_out: {}, cutoff: {}",
+            fft_size_in, fft_size_out, cutoff
+        );
+        let sinc = make_sincs::<T>(fft_size_in, 1, cutoff, WindowFunction::BlackmanHarris2);
+        let scale = T::coerce(2 * fft_size_in);
+        self.kernel.transform_filter(&mut |buf| {
+            for (f, s) in buf.iter_mut().zip(sinc[0].iter()) {
+                *f = *s / scale;
+            }
+        });
+    }
+
+    /// Resample a small chunk, where `load_input` copies the input to the start of the input buffer.
+    /// The work buffers are taken from `scratch` if given.
+    fn resample_unit<F>(
+        &mut self,
+        mut load_input: F,
+        wave_out: &mut [T],
+        overlap: &mut [T],
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<()>
+    where
+        F: FnMut(&mut [T]),
+    {
+        // Flush denormals in the inner loops only, if requested by FlushDenormals
+        let _flush = flush_scope();
+        self.kernel
+            .resample_unit(&mut load_input, wave_out, overlap, scratch)
+    }
+
+    /// Get the length of a scratch buffer that can replace the work buffers.
+    fn scratch_len(&self) -> usize {
+        self.kernel.scratch_len()
     }
 }
 
@@ -204,6 +775,26 @@ where
         chunk_size_in: usize,
         nbr_channels: usize,
     ) -> Result<Self, ResamplerConstructionError> {
+        Self::build(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            nbr_channels,
+            FftResampler::new,
+        )
+    }
+
+    /// Create a new FftFixedInOut, with a resampler made by `make_resampler` for the fft sizes.
+    fn build<F>(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        make_resampler: F,
+    ) -> Result<Self, ResamplerConstructionError>
+    where
+        F: FnOnce(usize, usize) -> FftResampler<T>,
+    {
         validate_sample_rates(sample_rate_input, sample_rate_output)?;
 
         debug!(
@@ -211,14 +802,10 @@ where
             sample_rate_input, sample_rate_output, chunk_size_in, nbr_channels
         );
 
-        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
-        let min_chunk_out = sample_rate_output / gcd;
-        let wanted = chunk_size_in;
-        let fft_chunks = (wanted as f32 / min_chunk_out as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * sample_rate_output / gcd;
-        let fft_size_in = fft_chunks * sample_rate_input / gcd;
+        let (fft_size_in, fft_size_out) =
+            calculate_fft_sizes(sample_rate_input, sample_rate_output, chunk_size_in, false);
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let resampler = make_resampler(fft_size_in, fft_size_out);
 
         let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
 
@@ -228,50 +815,228 @@ where
             nbr_channels,
             chunk_size_in: fft_size_in,
             chunk_size_out: fft_size_out,
+            chunk_size_wanted: chunk_size_in,
             fft_size_in,
             overlaps,
             resampler,
             channel_mask,
         })
     }
+
+    /// Create a new FftFixedInOut like [FftFixedInOut::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory.
+    ///
+    /// The memory of the FFTs and the buffers is estimated before anything is allocated,
+    /// and [ResamplerConstructionError::MemoryLimitExceeded] is returned if the estimate exceeds the limit.
+    /// This avoids allocating gigabytes for an absurd chunk size,
+    /// or for a pair of sample rates with a very small common divisor, like 44100 and 44101 Hz.
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let (fft_size_in, fft_size_out) =
+            calculate_fft_sizes(sample_rate_input, sample_rate_output, chunk_size_in, false);
+        let required = FftResampler::<T>::estimate_memory(fft_size_in, fft_size_out)
+            .saturating_add(buffer_size::<T>(nbr_channels, fft_size_out))
+            .saturating_add(nbr_channels);
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new FftFixedInOut from a rational resample ratio, instead of a pair of sample rates.
+    ///
+    /// The resample ratio, the ratio between the output and input sample rates, is `numerator / denominator`.
+    /// Only the ratio matters for the FFT sizes, so for example giving 160 and 147
+    /// makes the same resampler as the sample rates 44100 and 48000 Hz.
+    /// The ratio does not need to be reduced, the chunk sizes are derived from the reduced ratio.
+    ///
+    /// Parameters are:
+    /// - `numerator`: Numerator of the resample ratio, must be > 0.
+    /// - `denominator`: Denominator of the resample ratio, must be > 0.
+    /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn new_with_ratio(
+        numerator: usize,
+        denominator: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new(denominator, numerator, chunk_size_in, nbr_channels)
+    }
+
+    /// Change the input and output sample rates.
+    ///
+    /// The FFT plans and internal buffers of the resampler are reused as far as possible,
+    /// which makes this cheaper than constructing a new resampler.
+    /// The chunk sizes are recalculated from the `chunk_size_in` value that was given to the constructor,
+    /// use [input_frames_next](Resampler::input_frames_next) and
+    /// [output_frames_next](Resampler::output_frames_next) to get the new values.
+    /// The resampler is also [reset](Resampler::reset).
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    pub fn set_sample_rates(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> Result<(), ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        debug!(
+            "Change FftFixedInOut sample rates, sample_rate_input: {}, sample_rate_output: {}",
+            sample_rate_input, sample_rate_output
+        );
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            self.chunk_size_wanted,
+            false,
+        );
+        self.resampler.resize(fft_size_in, fft_size_out);
+        self.chunk_size_in = fft_size_in;
+        self.chunk_size_out = fft_size_out;
+        self.fft_size_in = fft_size_in;
+        clear_and_resize(&mut self.overlaps, fft_size_out);
+        self.reset();
+        Ok(())
+    }
+
+    /// Get the spectrum of the anti-aliasing filter in use, as the bins of the forward FFT
+    /// from zero to half the input sample rate.
+    /// The spectrum includes the scaling of the inverse FFT.
+    /// It is recalculated when the sample rates are changed.
+    /// When the FFTs are done in another precision than the sample type,
+    /// the spectrum is converted to the sample type, which allocates.
+    pub fn filter_spectrum(&self) -> Cow<'_, [Complex<T>]> {
+        self.resampler.kernel.filter_spectrum()
+    }
 }
 
-impl<T> Resampler<T> for FftFixedInOut<T>
+impl<T> FftFixedInOut<T>
 where
     T: Sample,
+    f32: CoerceFrom<T>,
+    f64: CoerceFrom<T>,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Create a new FftFixedInOut that does the FFTs in the given precision,
+    /// independently of the sample type. See [FftPrecision].
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    /// - `precision`: precision of the internal FFTs.
+    pub fn new_with_precision(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        precision: FftPrecision,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::build(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            nbr_channels,
+            |fft_size_in, fft_size_out| {
+                FftResampler::with_precision(fft_size_in, fft_size_out, precision)
+            },
+        )
+    }
+}
+
+impl<T> FftFixedInOut<T>
+where
+    T: Sample,
+{
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    /// The work buffers of the FFTs are taken from `scratch` if given.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
         &mut self,
-        wave_in: &[Vin],
+        wave_in: &I,
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        mut scratch: Option<&mut [T]>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.chunk_size_in,
             self.chunk_size_out,
         )?;
 
-        for (channel, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                self.resampler.resample_unit(
-                    &wave_in[channel].as_ref()[..self.chunk_size_in],
-                    &mut wave_out[channel].as_mut()[..self.chunk_size_out],
-                    &mut self.overlaps[channel],
-                )
-            }
+        for (channel, channel_in, channel_out) in layout.active_channels(&self.channel_mask) {
+            self.resampler.resample_unit(
+                |buf| wave_in.copy_frames(channel_in, 0, buf),
+                &mut wave_out[channel_out].as_mut()[..self.chunk_size_out],
+                &mut self.overlaps[channel],
+                scratch.as_deref_mut(),
+            )?;
         }
         Ok((self.chunk_size_in, self.chunk_size_out))
     }
+}
+
+impl<T> Resampler<T> for FftFixedInOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask, None)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        validate_scratch(self.resampler.scratch_len(), scratch)?;
+        self.process_channels(
+            &Contiguous(wave_in),
+            wave_out,
+            active_channels_mask,
+            Some(scratch),
+        )
+    }
 
     fn input_frames_max(&self) -> usize {
         self.fft_size_in
@@ -297,6 +1062,10 @@ where
         self.chunk_size_out / 2
     }
 
+    fn input_delay(&self) -> usize {
+        self.chunk_size_in / 2
+    }
+
     /// Update the resample ratio. This is not supported by this resampler and
     /// always returns an [ResampleError::SyncNotAdjustable].
     fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
@@ -309,6 +1078,24 @@ where
         Err(ResampleError::SyncNotAdjustable)
     }
 
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+            + heap_size_nested(&self.overlaps)
+            + heap_size(&self.channel_mask)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "FftFixedInOut",
+            ResamplerAlgorithm::Fft {
+                fft_size_in: self.fft_size_in,
+                fft_size_out: self.resampler.fft_size_out,
+            },
+            Some(self.resampler.fft_size_out as f64 / self.fft_size_in as f64),
+        )
+    }
+
     fn reset(&mut self) {
         self.overlaps
             .iter_mut()
@@ -326,7 +1113,7 @@ where
     /// Parameters are:
     /// - `sample_rate_input`: Input sample rate, must be > 0.
     /// - `sample_rate_output`: Output sample rate, must be > 0.
-    /// - `chunk_size_out`: length of output data in frames.
+    /// - `chunk_size_out`: length of output data in frames, can be any value > 0.
     /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
     /// - `nbr_channels`: number of channels in input/output.
     pub fn new(
@@ -336,16 +1123,39 @@ where
         sub_chunks: usize,
         nbr_channels: usize,
     ) -> Result<Self, ResamplerConstructionError> {
+        Self::build(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            FftResampler::new,
+        )
+    }
+
+    /// Create a new FftFixedOut, with a resampler made by `make_resampler` for the fft sizes.
+    fn build<F>(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        make_resampler: F,
+    ) -> Result<Self, ResamplerConstructionError>
+    where
+        F: FnOnce(usize, usize) -> FftResampler<T>,
+    {
         validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let sub_chunks = sub_chunks.max(1);
 
-        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
-        let min_chunk_out = sample_rate_output / gcd;
-        let wanted_subsize = chunk_size_out / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * sample_rate_output / gcd;
-        let fft_size_in = fft_chunks * sample_rate_input / gcd;
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out / sub_chunks,
+            false,
+        );
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let resampler = make_resampler(fft_size_in, fft_size_out);
 
         debug!(
             "Create new FftFixedOut, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
@@ -365,6 +1175,7 @@ where
         Ok(FftFixedOut {
             nbr_channels,
             chunk_size_out,
+            sub_chunks,
             fft_size_in,
             fft_size_out,
             overlaps,
@@ -375,46 +1186,197 @@ where
             channel_mask,
         })
     }
+
+    /// Create a new FftFixedOut like [FftFixedOut::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory, see [FftFixedInOut::try_new_with_limit].
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_out`: length of output data in frames, can be any value > 0.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out / sub_chunks.max(1),
+            false,
+        );
+        let buffer_frames = chunk_size_out
+            .saturating_add(fft_size_out)
+            .saturating_add(fft_size_out);
+        let required = FftResampler::<T>::estimate_memory(fft_size_in, fft_size_out)
+            .saturating_add(buffer_size::<T>(nbr_channels, buffer_frames))
+            .saturating_add(nbr_channels);
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new FftFixedOut from a rational resample ratio, instead of a pair of sample rates.
+    ///
+    /// The resample ratio, the ratio between the output and input sample rates, is `numerator / denominator`.
+    /// Only the ratio matters for the FFT sizes, so for example giving 160 and 147
+    /// makes the same resampler as the sample rates 44100 and 48000 Hz.
+    /// The ratio does not need to be reduced, the chunk sizes are derived from the reduced ratio.
+    ///
+    /// Parameters are:
+    /// - `numerator`: Numerator of the resample ratio, must be > 0.
+    /// - `denominator`: Denominator of the resample ratio, must be > 0.
+    /// - `chunk_size_out`: length of output data in frames, can be any value > 0.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn new_with_ratio(
+        numerator: usize,
+        denominator: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new(
+            denominator,
+            numerator,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+        )
+    }
+
+    /// Change the input and output sample rates.
+    ///
+    /// The FFT plans and internal buffers of the resampler are reused as far as possible,
+    /// which makes this cheaper than constructing a new resampler.
+    /// The output chunk size is kept, while the number of input frames needed
+    /// is recalculated for the new sample rates.
+    /// The resampler is also [reset](Resampler::reset).
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    pub fn set_sample_rates(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> Result<(), ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        debug!(
+            "Change FftFixedOut sample rates, sample_rate_input: {}, sample_rate_output: {}",
+            sample_rate_input, sample_rate_output
+        );
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            self.chunk_size_out / self.sub_chunks,
+            false,
+        );
+        self.resampler.resize(fft_size_in, fft_size_out);
+        self.fft_size_in = fft_size_in;
+        self.fft_size_out = fft_size_out;
+        clear_and_resize(&mut self.overlaps, fft_size_out);
+        clear_and_resize(&mut self.output_buffers, self.chunk_size_out + fft_size_out);
+        self.reset();
+        Ok(())
+    }
+
+    /// Get the spectrum of the anti-aliasing filter in use, as the bins of the forward FFT
+    /// from zero to half the input sample rate.
+    /// The spectrum includes the scaling of the inverse FFT.
+    /// It is recalculated when the sample rates are changed.
+    /// When the FFTs are done in another precision than the sample type,
+    /// the spectrum is converted to the sample type, which allocates.
+    pub fn filter_spectrum(&self) -> Cow<'_, [Complex<T>]> {
+        self.resampler.kernel.filter_spectrum()
+    }
 }
 
-impl<T> Resampler<T> for FftFixedOut<T>
+impl<T> FftFixedOut<T>
 where
     T: Sample,
+    f32: CoerceFrom<T>,
+    f64: CoerceFrom<T>,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Create a new FftFixedOut that does the FFTs in the given precision,
+    /// independently of the sample type. See [FftPrecision].
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_out`: length of output data in frames, can be any value > 0.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    /// - `precision`: precision of the internal FFTs.
+    pub fn new_with_precision(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        precision: FftPrecision,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::build(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            |fft_size_in, fft_size_out| {
+                FftResampler::with_precision(fft_size_in, fft_size_out, precision)
+            },
+        )
+    }
+}
+
+impl<T> FftFixedOut<T>
+where
+    T: Sample,
+{
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    /// The work buffers of the FFTs are taken from `scratch` if given.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
         &mut self,
-        wave_in: &[Vin],
+        wave_in: &I,
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        mut scratch: Option<&mut [T]>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.frames_needed,
             self.chunk_size_out,
         )?;
 
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                debug_assert!(self.chunk_size_out <= wave_out[chan].as_mut().len());
-                for (in_chunk, out_chunk) in wave_in[chan].as_ref()[..self.frames_needed]
-                    .chunks(self.fft_size_in)
-                    .zip(
-                        self.output_buffers[chan][self.saved_frames..]
-                            .chunks_mut(self.fft_size_out),
-                    )
-                {
-                    self.resampler
-                        .resample_unit(in_chunk, out_chunk, &mut self.overlaps[chan]);
-                }
+        for (chan, chan_in, chan_out) in layout.active_channels(&self.channel_mask) {
+            debug_assert!(self.chunk_size_out <= wave_out[chan_out].as_mut().len());
+            let fft_size_in = self.fft_size_in;
+            for (idx, out_chunk) in self.output_buffers[chan][self.saved_frames..]
+                .chunks_mut(self.fft_size_out)
+                .take(self.frames_needed / fft_size_in)
+                .enumerate()
+            {
+                self.resampler.resample_unit(
+                    |buf| wave_in.copy_frames(chan_in, idx * fft_size_in, buf),
+                    out_chunk,
+                    &mut self.overlaps[chan],
+                    scratch.as_deref_mut(),
+                )?;
             }
         }
         let processed_frames =
@@ -422,31 +1384,68 @@ where
 
         // copy to output, and save extra frames for next round
         if processed_frames >= self.chunk_size_out {
-            self.saved_frames = processed_frames - self.chunk_size_out;
-            for (chan, active) in self.channel_mask.iter().enumerate() {
-                if *active {
-                    wave_out[chan].as_mut()[..self.chunk_size_out]
-                        .copy_from_slice(&self.output_buffers[chan][..self.chunk_size_out]);
-                    self.output_buffers[chan].copy_within(
-                        self.chunk_size_out..(self.chunk_size_out + self.saved_frames),
-                        0,
-                    );
-                }
+            self.saved_frames = processed_frames - self.chunk_size_out;
+            for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+                wave_out[chan_out].as_mut()[..self.chunk_size_out]
+                    .copy_from_slice(&self.output_buffers[chan][..self.chunk_size_out]);
+                self.output_buffers[chan].copy_within(
+                    self.chunk_size_out..(self.chunk_size_out + self.saved_frames),
+                    0,
+                );
             }
         } else {
             self.saved_frames = processed_frames;
         }
         //calculate number of needed frames from next round
-        let frames_needed_out = if self.chunk_size_out > self.saved_frames {
-            self.chunk_size_out - self.saved_frames
-        } else {
-            0
-        };
+        let frames_needed_out = self.chunk_size_out.saturating_sub(self.saved_frames);
         let input_frames_used = self.frames_needed;
         let chunks_needed = (frames_needed_out as f32 / self.fft_size_out as f32).ceil() as usize;
         self.frames_needed = chunks_needed * self.fft_size_in;
         Ok((input_frames_used, self.chunk_size_out))
     }
+}
+
+impl<T> Resampler<T> for FftFixedOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask, None)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        validate_scratch(self.resampler.scratch_len(), scratch)?;
+        self.process_channels(
+            &Contiguous(wave_in),
+            wave_out,
+            active_channels_mask,
+            Some(scratch),
+        )
+    }
 
     fn input_frames_max(&self) -> usize {
         (self.chunk_size_out as f32 / self.fft_size_out as f32).ceil() as usize * self.fft_size_in
@@ -472,6 +1471,10 @@ where
         self.fft_size_out / 2
     }
 
+    fn input_delay(&self) -> usize {
+        self.fft_size_in / 2
+    }
+
     /// Update the resample ratio. This is not supported by this resampler and
     /// always returns [ResampleError::SyncNotAdjustable].
     fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
@@ -484,6 +1487,45 @@ where
         Err(ResampleError::SyncNotAdjustable)
     }
 
+    /// Change the output chunk size, see [Resampler::set_chunk_size].
+    /// The FFT sizes are kept, and the output frames saved from the previous chunk
+    /// are returned first.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        self.chunk_size_out = chunk_size;
+        for buf in self.output_buffers.iter_mut() {
+            buf.resize(chunk_size + self.fft_size_out, T::zero());
+        }
+        let frames_needed_out = self.chunk_size_out.saturating_sub(self.saved_frames);
+        let chunks_needed = (frames_needed_out as f32 / self.fft_size_out as f32).ceil() as usize;
+        self.frames_needed = chunks_needed * self.fft_size_in;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+            + heap_size_nested(&self.overlaps)
+            + heap_size_nested(&self.output_buffers)
+            + heap_size(&self.channel_mask)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "FftFixedOut",
+            ResamplerAlgorithm::Fft {
+                fft_size_in: self.fft_size_in,
+                fft_size_out: self.fft_size_out,
+            },
+            Some(self.fft_size_out as f64 / self.fft_size_in as f64),
+        )
+    }
+
     fn reset(&mut self) {
         self.overlaps
             .iter_mut()
@@ -507,7 +1549,7 @@ where
     /// Parameters are:
     /// - `sample_rate_input`: Input sample rate, must be > 0.
     /// - `sample_rate_output`: Output sample rate, must be > 0.
-    /// - `chunk_size_in`: length of input data in frames.
+    /// - `chunk_size_in`: length of input data in frames, can be any value > 0.
     /// - `sub_chunks`: desired number of subchunks for processing, actual number used may be different.
     /// - `nbr_channels`: number of channels in input/output.
     pub fn new(
@@ -517,18 +1559,41 @@ where
         sub_chunks: usize,
         nbr_channels: usize,
     ) -> Result<Self, ResamplerConstructionError> {
+        Self::build(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            FftResampler::new,
+        )
+    }
+
+    /// Create a new FftFixedIn, with a resampler made by `make_resampler` for the fft sizes.
+    fn build<F>(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        make_resampler: F,
+    ) -> Result<Self, ResamplerConstructionError>
+    where
+        F: FnOnce(usize, usize) -> FftResampler<T>,
+    {
         validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let sub_chunks = sub_chunks.max(1);
 
-        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
-        let min_chunk_in = sample_rate_input / gcd;
-        let wanted_subsize = chunk_size_in / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_in as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * sample_rate_output / gcd;
-        let fft_size_in = fft_chunks * sample_rate_input / gcd;
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in / sub_chunks,
+            true,
+        );
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let resampler = make_resampler(fft_size_in, fft_size_out);
         debug!(
-            "Create new FftFixedOut, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
+            "Create new FftFixedIn, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
             sample_rate_input, sample_rate_output, chunk_size_in, nbr_channels, fft_size_in, fft_size_out
         );
 
@@ -543,6 +1608,7 @@ where
         Ok(FftFixedIn {
             nbr_channels,
             chunk_size_in,
+            sub_chunks,
             fft_size_in,
             fft_size_out,
             overlaps,
@@ -552,65 +1618,213 @@ where
             channel_mask,
         })
     }
+
+    /// Create a new FftFixedIn like [FftFixedIn::new], but refuse configurations
+    /// that would need more than `max_bytes` of memory, see [FftFixedInOut::try_new_with_limit].
+    ///
+    /// Parameters are:
+    /// - `max_bytes`: Maximum memory for the resampler, in bytes.
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_in`: length of input data in frames, can be any value > 0.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number used may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn try_new_with_limit(
+        max_bytes: usize,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in / sub_chunks.max(1),
+            true,
+        );
+        let buffer_frames = chunk_size_in
+            .saturating_add(fft_size_in)
+            .saturating_add(fft_size_out);
+        let required = FftResampler::<T>::estimate_memory(fft_size_in, fft_size_out)
+            .saturating_add(buffer_size::<T>(nbr_channels, buffer_frames))
+            .saturating_add(nbr_channels);
+        check_memory_limit(required, max_bytes)?;
+        Self::new(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new FftFixedIn from a rational resample ratio, instead of a pair of sample rates.
+    ///
+    /// The resample ratio, the ratio between the output and input sample rates, is `numerator / denominator`.
+    /// Only the ratio matters for the FFT sizes, so for example giving 160 and 147
+    /// makes the same resampler as the sample rates 44100 and 48000 Hz.
+    /// The ratio does not need to be reduced, the chunk sizes are derived from the reduced ratio.
+    ///
+    /// Parameters are:
+    /// - `numerator`: Numerator of the resample ratio, must be > 0.
+    /// - `denominator`: Denominator of the resample ratio, must be > 0.
+    /// - `chunk_size_in`: length of input data in frames, can be any value > 0.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number used may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn new_with_ratio(
+        numerator: usize,
+        denominator: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new(
+            denominator,
+            numerator,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+        )
+    }
+
+    /// Change the input and output sample rates.
+    ///
+    /// The FFT plans and internal buffers of the resampler are reused as far as possible,
+    /// which makes this cheaper than constructing a new resampler.
+    /// The input chunk size is kept, while the number of output frames
+    /// is recalculated for the new sample rates.
+    /// The resampler is also [reset](Resampler::reset).
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    pub fn set_sample_rates(
+        &mut self,
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+    ) -> Result<(), ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        debug!(
+            "Change FftFixedIn sample rates, sample_rate_input: {}, sample_rate_output: {}",
+            sample_rate_input, sample_rate_output
+        );
+        let (fft_size_in, fft_size_out) = calculate_fft_sizes(
+            sample_rate_input,
+            sample_rate_output,
+            self.chunk_size_in / self.sub_chunks,
+            true,
+        );
+        self.resampler.resize(fft_size_in, fft_size_out);
+        self.fft_size_in = fft_size_in;
+        self.fft_size_out = fft_size_out;
+        clear_and_resize(&mut self.overlaps, fft_size_out);
+        clear_and_resize(&mut self.input_buffers, self.chunk_size_in + fft_size_in);
+        self.reset();
+        Ok(())
+    }
+
+    /// Get the spectrum of the anti-aliasing filter in use, as the bins of the forward FFT
+    /// from zero to half the input sample rate.
+    /// The spectrum includes the scaling of the inverse FFT.
+    /// It is recalculated when the sample rates are changed.
+    /// When the FFTs are done in another precision than the sample type,
+    /// the spectrum is converted to the sample type, which allocates.
+    pub fn filter_spectrum(&self) -> Cow<'_, [Complex<T>]> {
+        self.resampler.kernel.filter_spectrum()
+    }
 }
 
-impl<T> Resampler<T> for FftFixedIn<T>
+impl<T> FftFixedIn<T>
 where
     T: Sample,
+    f32: CoerceFrom<T>,
+    f64: CoerceFrom<T>,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Create a new FftFixedIn that does the FFTs in the given precision,
+    /// independently of the sample type. See [FftPrecision].
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_in`: length of input data in frames, can be any value > 0.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number used may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    /// - `precision`: precision of the internal FFTs.
+    pub fn new_with_precision(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        precision: FftPrecision,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::build(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            |fft_size_in, fft_size_out| {
+                FftResampler::with_precision(fft_size_in, fft_size_out, precision)
+            },
+        )
+    }
+}
+
+impl<T> FftFixedIn<T>
+where
+    T: Sample,
+{
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    /// The work buffers of the FFTs are taken from `scratch` if given.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
         &mut self,
-        wave_in: &[Vin],
+        wave_in: &I,
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        mut scratch: Option<&mut [T]>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
         let next_saved_frames = self.saved_frames + self.chunk_size_in;
         let nbr_chunks_ready =
             (next_saved_frames as f32 / self.fft_size_in as f32).floor() as usize;
         let needed_len = nbr_chunks_ready * self.fft_size_out;
 
-        validate_buffers(
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
             wave_in,
             wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
             self.chunk_size_in,
             needed_len,
         )?;
 
         // copy new samples to input buffer
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                for (input, buffer) in wave_in[chan].as_ref().iter().zip(
-                    self.input_buffers[chan]
-                        .iter_mut()
-                        .skip(self.saved_frames)
-                        .take(self.chunk_size_in),
-                ) {
-                    *buffer = *input;
-                }
-            }
+        for (chan, chan_in, _) in layout.active_channels(&self.channel_mask) {
+            wave_in.copy_frames(
+                chan_in,
+                0,
+                &mut self.input_buffers[chan]
+                    [self.saved_frames..self.saved_frames + self.chunk_size_in],
+            );
         }
 
         self.saved_frames = next_saved_frames;
 
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                debug_assert!(needed_len <= wave_out[chan].as_mut().len());
-                for (in_chunk, out_chunk) in self.input_buffers[chan]
-                    .chunks(self.fft_size_in)
-                    .take(nbr_chunks_ready)
-                    .zip(wave_out[chan].as_mut().chunks_mut(self.fft_size_out))
-                {
-                    self.resampler
-                        .resample_unit(in_chunk, out_chunk, &mut self.overlaps[chan]);
-                }
+        for (chan, _, chan_out) in layout.active_channels(&self.channel_mask) {
+            debug_assert!(needed_len <= wave_out[chan_out].as_mut().len());
+            for (in_chunk, out_chunk) in self.input_buffers[chan]
+                .chunks(self.fft_size_in)
+                .take(nbr_chunks_ready)
+                .zip(wave_out[chan_out].as_mut().chunks_mut(self.fft_size_out))
+            {
+                self.resampler.resample_unit(
+                    |buf| buf.copy_from_slice(in_chunk),
+                    out_chunk,
+                    &mut self.overlaps[chan],
+                    scratch.as_deref_mut(),
+                )?;
             }
         }
 
@@ -628,6 +1842,49 @@ where
         self.saved_frames = extra;
         Ok((self.chunk_size_in, needed_len))
     }
+}
+
+impl<T> Resampler<T> for FftFixedIn<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask, None)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        validate_scratch(self.resampler.scratch_len(), scratch)?;
+        self.process_channels(
+            &Contiguous(wave_in),
+            wave_out,
+            active_channels_mask,
+            Some(scratch),
+        )
+    }
 
     fn input_frames_max(&self) -> usize {
         self.chunk_size_in
@@ -642,7 +1899,8 @@ where
     }
 
     fn output_frames_max(&self) -> usize {
-        self.chunk_size_in * (self.fft_size_out / self.fft_size_in + 1)
+        // Up to fft_size_in - 1 frames can be saved from the previous chunk
+        ((self.chunk_size_in + self.fft_size_in - 1) / self.fft_size_in) * self.fft_size_out
     }
 
     fn output_frames_next(&self) -> usize {
@@ -655,6 +1913,10 @@ where
         self.fft_size_out / 2
     }
 
+    fn input_delay(&self) -> usize {
+        self.fft_size_in / 2
+    }
+
     /// Update the resample ratio. This is not supported by this resampler and
     /// always returns [ResampleError::SyncNotAdjustable].
     fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
@@ -667,6 +1929,42 @@ where
         Err(ResampleError::SyncNotAdjustable)
     }
 
+    /// Change the input chunk size, see [Resampler::set_chunk_size].
+    /// The FFT sizes are kept, and the input frames saved from the previous chunk
+    /// are processed first.
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::InvalidChunkSize {
+                provided: chunk_size,
+            });
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        self.chunk_size_in = chunk_size;
+        for buf in self.input_buffers.iter_mut() {
+            buf.resize(chunk_size + self.fft_size_in, T::zero());
+        }
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage()
+            + heap_size_nested(&self.overlaps)
+            + heap_size_nested(&self.input_buffers)
+            + heap_size(&self.channel_mask)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "FftFixedIn",
+            ResamplerAlgorithm::Fft {
+                fft_size_in: self.fft_size_in,
+                fft_size_out: self.fft_size_out,
+            },
+            Some(self.fft_size_out as f64 / self.fft_size_in as f64),
+        )
+    }
+
     fn reset(&mut self) {
         self.overlaps
             .iter_mut()
@@ -679,11 +1977,12 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     use crate::check_output;
-    use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut, FftResampler};
+    use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut, FftPrecision, FftResampler};
     use crate::Resampler;
+    use crate::ResamplerConstructionError;
     use rand::Rng;
 
     #[test]
@@ -700,7 +1999,14 @@ mod tests {
 
         let mut wave_out = vec![0.0; 1000];
         let mut overlap = vec![0.0; 1000];
-        resampler.resample_unit(&wave_in, &mut wave_out, &mut overlap);
+        resampler
+            .resample_unit(
+                |buf| buf.copy_from_slice(&wave_in),
+                &mut wave_out,
+                &mut overlap,
+                None,
+            )
+            .unwrap();
         let vecsum = wave_out.iter().sum::<f64>();
         let maxval = wave_out.iter().cloned().fold(0. / 0., f64::max);
         assert!((vecsum - 4.0 * 1000.0 / 147.0).abs() < 1.0e-6);
@@ -925,6 +2231,81 @@ mod tests {
         assert_eq!(out[0].len(), 1024);
     }
 
+    #[test]
+    fn set_sample_rates_fio() {
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        let mut reference = FftFixedInOut::<f64>::new(48000, 96000, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; resampler.input_frames_next()]; 2];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_sample_rates(48000, 96000).unwrap();
+        assert_eq!(resampler.input_frames_next(), reference.input_frames_next());
+        assert_eq!(
+            resampler.output_frames_next(),
+            reference.output_frames_next()
+        );
+
+        let mut rng = rand::thread_rng();
+        let mut waves = vec![vec![0.0f64; resampler.input_frames_next()]; 2];
+        waves
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
+        let out = resampler.process(&waves, None).unwrap();
+        let expected = reference.process(&waves, None).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn set_sample_rates_fo() {
+        let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut reference = FftFixedOut::<f64>::new(96000, 48000, 1024, 2, 2).unwrap();
+        let waves = vec![vec![0.0f64; resampler.input_frames_next()]; 2];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_sample_rates(96000, 48000).unwrap();
+        assert_eq!(resampler.input_frames_next(), reference.input_frames_next());
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let mut waves = vec![vec![0.0f64; resampler.input_frames_next()]; 2];
+            waves
+                .iter_mut()
+                .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
+            let out = resampler.process(&waves, None).unwrap();
+            let expected = reference.process(&waves, None).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn set_sample_rates_fi() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(48000, 44100, 1024, 2, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        resampler.process(&waves, None).unwrap();
+        resampler.set_sample_rates(48000, 44100).unwrap();
+        assert_eq!(
+            resampler.output_frames_next(),
+            reference.output_frames_next()
+        );
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let mut waves = vec![vec![0.0f64; 1024]; 2];
+            waves
+                .iter_mut()
+                .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
+            let out = resampler.process(&waves, None).unwrap();
+            let expected = reference.process(&waves, None).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn set_sample_rates_invalid() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        assert!(resampler.set_sample_rates(0, 48000).is_err());
+        assert_eq!(resampler.output_frames_next(), 640);
+    }
+
     #[test]
     fn check_fo_output() {
         let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 4096, 4, 2).unwrap();
@@ -942,4 +2323,282 @@ mod tests {
         let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 4096, 2).unwrap();
         check_output!(check_fo_output, resampler);
     }
+
+    #[test]
+    fn filter_spectrum() {
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        let spectrum = resampler.filter_spectrum();
+        assert_eq!(spectrum.len(), resampler.input_frames_next() + 1);
+        // The filter passes low frequencies and stops the ones near the Nyquist frequency
+        let dc = spectrum[0].norm();
+        assert!(spectrum[spectrum.len() - 1].norm() < 1.0e-3 * dc);
+    }
+
+    #[test]
+    fn any_chunk_size_fi() {
+        // Chunks of 1 and 97 frames both use sub-chunks of 147 frames
+        let mut single = FftFixedIn::<f64>::new(44100, 48000, 1, 2, 1).unwrap();
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 97, 1, 1).unwrap();
+        let wave: Vec<f64> = (0..97 * 20).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut out_single: Vec<f64> = Vec::new();
+        for frame in wave.chunks(1) {
+            out_single.extend(single.process(&[frame], None).unwrap()[0].iter());
+        }
+        let mut out: Vec<f64> = Vec::new();
+        for chunk in wave.chunks(97) {
+            out.extend(resampler.process(&[chunk], None).unwrap()[0].iter());
+        }
+        assert_eq!(out.len(), 13 * 160);
+        assert_eq!(out_single, out);
+    }
+
+    #[test]
+    fn any_chunk_size_fo() {
+        let mut single = FftFixedOut::<f64>::new(44100, 48000, 1, 0, 1).unwrap();
+        let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 101, 1, 1).unwrap();
+        let wave: Vec<f64> = (0..147 * 20).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut out_single: Vec<f64> = Vec::new();
+        let mut pos = 0;
+        while pos + single.input_frames_next() <= wave.len() {
+            let frames = single.input_frames_next();
+            out_single.extend(single.process(&[&wave[pos..pos + frames]], None).unwrap()[0].iter());
+            pos += frames;
+        }
+        let mut out: Vec<f64> = Vec::new();
+        let mut pos = 0;
+        while pos + resampler.input_frames_next() <= wave.len() {
+            let frames = resampler.input_frames_next();
+            out.extend(
+                resampler
+                    .process(&[&wave[pos..pos + frames]], None)
+                    .unwrap()[0]
+                    .iter(),
+            );
+            pos += frames;
+        }
+        assert_eq!(out_single.len(), 20 * 160);
+        assert_eq!(out_single[..out.len()], out[..]);
+    }
+
+    #[test]
+    fn rational_ratio() {
+        let resampler = FftFixedInOut::<f64>::new_with_ratio(160, 147, 1024, 2).unwrap();
+        let reference = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        assert_eq!(resampler.input_frames_next(), reference.input_frames_next());
+        assert_eq!(
+            resampler.output_frames_next(),
+            reference.output_frames_next()
+        );
+        // The ratio does not need to be reduced
+        let resampler = FftFixedIn::<f64>::new_with_ratio(320, 294, 1024, 2, 2).unwrap();
+        let reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        assert_eq!(resampler.output_frames_max(), reference.output_frames_max());
+        assert_eq!(resampler.config().algorithm, reference.config().algorithm);
+        let resampler = FftFixedOut::<f64>::new_with_ratio(147, 160, 1000, 2, 1).unwrap();
+        assert_eq!(resampler.input_frames_max(), resampler.input_frames_next());
+        let err = FftFixedOut::<f64>::new_with_ratio(0, 160, 1000, 2, 1);
+        assert_eq!(err.err().map(|err| err.code()), Some(100));
+    }
+
+    #[test]
+    fn memory_limit() {
+        let resampler =
+            FftFixedIn::<f64>::try_new_with_limit(usize::MAX, 44100, 48000, 1024, 2, 2).unwrap();
+        let used = resampler.memory_usage();
+        let err = FftFixedIn::<f64>::try_new_with_limit(0, 44100, 48000, 1024, 2, 2)
+            .err()
+            .unwrap();
+        match err {
+            ResamplerConstructionError::MemoryLimitExceeded { required, limit } => {
+                assert_eq!(limit, 0);
+                assert!(required > used / 2 && required < 2 * used);
+            }
+            _ => panic!("unexpected error {}", err),
+        }
+        let resampler =
+            FftFixedOut::<f64>::try_new_with_limit(usize::MAX, 44100, 48000, 1024, 2, 2).unwrap();
+        assert!(resampler.memory_usage() < 1 << 20);
+        let resampler =
+            FftFixedInOut::<f64>::try_new_with_limit(usize::MAX, 44100, 48000, 1024, 2).unwrap();
+        assert!(resampler.memory_usage() < 1 << 20);
+
+        // Rates with a very small common divisor need long FFTs
+        let err = FftFixedInOut::<f64>::try_new_with_limit(1 << 20, 44100, 44101, 1024, 2);
+        assert_eq!(err.err().map(|err| err.code()), Some(114));
+        let err = FftFixedIn::<f32>::try_new_with_limit(1 << 30, 44100, 48000, 1 << 40, 1, 2);
+        assert_eq!(err.err().map(|err| err.code()), Some(114));
+    }
+
+    #[test]
+    fn fft_precision() {
+        let wave: Vec<f64> = (0..4096).map(|n| (n as f64 * 0.05).sin()).collect();
+        let wave_32: Vec<f32> = wave.iter().map(|v| *v as f32).collect();
+
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        let mut same =
+            FftFixedIn::<f64>::new_with_precision(44100, 48000, 1024, 2, 1, FftPrecision::Double)
+                .unwrap();
+        let mut single =
+            FftFixedIn::<f64>::new_with_precision(44100, 48000, 1024, 2, 1, FftPrecision::Single)
+                .unwrap();
+        // The plans, the work buffers and the filter spectrum are all in f32
+        assert!(single.memory_usage() < 2 * reference.memory_usage() / 3);
+        let spectrum = single.filter_spectrum();
+        assert_eq!(spectrum.len(), reference.filter_spectrum().len());
+        for (value, expected) in spectrum.iter().zip(reference.filter_spectrum().iter()) {
+            assert!((value - expected).norm() < 1.0e-7);
+        }
+        assert_eq!(same.memory_usage(), reference.memory_usage());
+        for chunk in wave.chunks_exact(1024) {
+            let expected = reference.process(&[chunk], None).unwrap();
+            assert_eq!(same.process(&[chunk], None).unwrap(), expected);
+            let out = single.process(&[chunk], None).unwrap();
+            assert_eq!(out[0].len(), expected[0].len());
+            for (value, expected) in out[0].iter().zip(expected[0].iter()) {
+                assert!((value - expected).abs() < 1.0e-5);
+            }
+        }
+
+        let mut reference = FftFixedOut::<f32>::new(48000, 44100, 1000, 2, 1).unwrap();
+        let mut double =
+            FftFixedOut::<f32>::new_with_precision(48000, 44100, 1000, 2, 1, FftPrecision::Double)
+                .unwrap();
+        let mut start = 0;
+        for _ in 0..3 {
+            let frames = reference.input_frames_next();
+            assert_eq!(frames, double.input_frames_next());
+            let chunk = &wave_32[start..start + frames];
+            let expected = reference.process(&[chunk], None).unwrap();
+            let out = double.process(&[chunk], None).unwrap();
+            for (value, expected) in out[0].iter().zip(expected[0].iter()) {
+                assert!((value - expected).abs() < 1.0e-5);
+            }
+            start += frames;
+        }
+
+        // The internal precision is kept when the sample rates change
+        let mut single =
+            FftFixedInOut::<f64>::new_with_precision(44100, 48000, 1024, 1, FftPrecision::Single)
+                .unwrap();
+        single.set_sample_rates(48000, 96000).unwrap();
+        let mut reference = FftFixedInOut::<f64>::new(48000, 96000, 1024, 1).unwrap();
+        let frames = reference.input_frames_next();
+        let expected = reference.process(&[&wave[..frames]], None).unwrap();
+        let out = single.process(&[&wave[..frames]], None).unwrap();
+        assert!(out[0]
+            .iter()
+            .zip(expected[0].iter())
+            .all(|(value, expected)| (value - expected).abs() < 1.0e-5));
+        assert!(out[0]
+            .iter()
+            .zip(expected[0].iter())
+            .any(|(value, expected)| value != expected));
+    }
+
+    // Without unsafe code, the FFT resamplers can't use a scratch buffer
+    #[cfg(not(feature = "forbid_unsafe"))]
+    #[test]
+    fn process_with_scratch() {
+        let wave: Vec<f64> = (0..4096).map(|n| (n as f64 * 0.05).sin()).collect();
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let scratch_len = resampler.scratch_len();
+        assert!(scratch_len > 4 * 512);
+        let mut scratch = vec![0.0; scratch_len];
+        let mut expected = vec![vec![0.0; reference.output_frames_max()]; 2];
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+
+        let err = resampler
+            .process_into_buffer_with_scratch(
+                &[&wave[..1024], &wave[..1024]],
+                &mut out,
+                None,
+                &mut scratch[..scratch_len - 1],
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), 214);
+        assert_eq!(err.expected(), Some(scratch_len));
+
+        for chunk in wave.chunks_exact(1024) {
+            let frames = reference
+                .process_into_buffer(&[chunk, chunk], &mut expected, None)
+                .unwrap();
+            // The content of the scratch buffer does not matter
+            scratch.iter_mut().for_each(|value| *value = 1.0);
+            assert_eq!(
+                resampler
+                    .process_into_buffer_with_scratch(&[chunk, chunk], &mut out, None, &mut scratch)
+                    .unwrap(),
+                frames
+            );
+            assert_eq!(out, expected);
+        }
+
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+        let scratch_len = resampler.scratch_len();
+        resampler.set_sample_rates(44100, 96000).unwrap();
+        assert_ne!(resampler.scratch_len(), scratch_len);
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]];
+        let mut scratch = vec![0.0; resampler.scratch_len()];
+        let frames = resampler.input_frames_next();
+        resampler
+            .process_into_buffer_with_scratch(&[&wave[..frames]], &mut out, None, &mut scratch)
+            .unwrap();
+
+        // Kernels in another precision place the converted input and their work buffers in the scratch
+        let mut reference =
+            FftFixedOut::<f64>::new_with_precision(44100, 48000, 1024, 2, 1, FftPrecision::Single)
+                .unwrap();
+        let mut resampler =
+            FftFixedOut::<f64>::new_with_precision(44100, 48000, 1024, 2, 1, FftPrecision::Single)
+                .unwrap();
+        let scratch_len = resampler.scratch_len();
+        assert!(scratch_len > 0);
+        let mut scratch = vec![0.0; scratch_len];
+        let mut expected = vec![vec![0.0; reference.output_frames_max()]];
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]];
+        let frames = resampler.input_frames_next();
+        let err = resampler
+            .process_into_buffer_with_scratch(
+                &[&wave[..frames]],
+                &mut out,
+                None,
+                &mut scratch[..scratch_len - 1],
+            )
+            .unwrap_err();
+        assert_eq!(err.expected(), Some(scratch_len));
+        for _ in 0..3 {
+            let frames = reference.input_frames_next();
+            reference
+                .process_into_buffer(&[&wave[..frames]], &mut expected, None)
+                .unwrap();
+            scratch.iter_mut().for_each(|value| *value = 1.0);
+            resampler
+                .process_into_buffer_with_scratch(&[&wave[..frames]], &mut out, None, &mut scratch)
+                .unwrap();
+            assert_eq!(out, expected);
+        }
+
+        // The same with a scratch buffer of a smaller type than the work buffers
+        let wave: Vec<f32> = wave.iter().map(|value| *value as f32).collect();
+        let mut reference =
+            FftFixedIn::<f32>::new_with_precision(44100, 48000, 1024, 2, 1, FftPrecision::Double)
+                .unwrap();
+        let mut resampler =
+            FftFixedIn::<f32>::new_with_precision(44100, 48000, 1024, 2, 1, FftPrecision::Double)
+                .unwrap();
+        let mut expected = vec![vec![0.0; reference.output_frames_max()]];
+        let mut out = vec![vec![0.0; resampler.output_frames_max()]];
+        // Start the scratch at an odd offset, so that it isn't aligned for f64
+        let mut scratch = vec![1.0f32; resampler.scratch_len() + 1];
+        for chunk in wave.chunks_exact(1024) {
+            reference
+                .process_into_buffer(&[chunk], &mut expected, None)
+                .unwrap();
+            resampler
+                .process_into_buffer_with_scratch(&[chunk], &mut out, None, &mut scratch[1..])
+                .unwrap();
+            assert_eq!(out, expected);
+        }
+    }
 }
@@ -0,0 +1,484 @@
+use crate::denormal::flush_scope;
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::sinc::make_sincs;
+use crate::synchro::{calculate_fft_sizes, validate_sample_rates};
+use crate::windows::WindowFunction;
+use crate::{
+    calculate_cutoff, fft_plan_size, heap_size, heap_size_nested, validate_and_update_mask,
+    Contiguous, InputChannels, Resampler, ResamplerAlgorithm, ResamplerConfig, Sample, Split,
+};
+use num_complex::Complex;
+use num_integer as integer;
+use num_traits::Zero;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+/// A synchronous FFT resampler for low latency, that accepts a fixed number of audio frames
+/// for input and returns a fixed number of frames.
+///
+/// The FFT resamplers [FftFixedIn](crate::FftFixedIn), [FftFixedOut](crate::FftFixedOut)
+/// and [FftFixedInOut](crate::FftFixedInOut) use an anti-aliasing filter as long as the chunk,
+/// so a good filter needs large chunks, and a high latency.
+/// This resampler instead splits a long filter into `nbr_partitions` blocks of the chunk size,
+/// and convolves each chunk with all the blocks using uniformly partitioned convolution.
+/// The spectra of the last `nbr_partitions` input chunks are kept,
+/// so that each chunk only needs one forward and one inverse FFT.
+///
+/// The convolution is calculated at the common multiple of the two sample rates,
+/// where the spectrum of a chunk is found by repeating its spectrum at the input rate.
+/// The result is then decimated to the output rate in the frequency domain.
+/// The resampling is exact apart from rounding errors, for any chunk size.
+/// The amount of work per input frame grows with `nbr_partitions` times
+/// `sample_rate_output / gcd(sample_rate_input, sample_rate_output)`,
+/// so this is most efficient for simple ratios such as 2:1 or 1:3.
+///
+/// The latency is one chunk for the buffering, plus the delay of the filter,
+/// see [output_delay](Resampler::output_delay).
+/// With a filter of the same length, this is a third of the latency of [FftFixedInOut](crate::FftFixedInOut).
+/// ```
+/// use rubato::{FftLowLatency, Resampler};
+/// // Resample 48 kHz to 96 kHz in chunks of 64 frames (1.3 ms),
+/// // with a filter as long as a chunk of 512 frames.
+/// let mut resampler = FftLowLatency::<f64>::new(48000, 96000, 64, 8, 2).unwrap();
+/// let waves_in = vec![vec![0.0f64; 64]; 2];
+/// let waves_out = resampler.process(&waves_in, None).unwrap();
+/// assert_eq!(waves_out[0].len(), 128);
+/// ```
+pub struct FftLowLatency<T> {
+    nbr_channels: usize,
+    chunk_size_in: usize,
+    chunk_size_out: usize,
+    nbr_partitions: usize,
+    /// Length of a chunk at the common rate.
+    chunk_size_common: usize,
+    /// Decimation factor from the common rate to the output rate.
+    decimation: usize,
+    filter_f: Vec<Vec<Complex<T>>>,
+    history: Vec<Vec<Vec<Complex<T>>>>,
+    history_pos: Vec<usize>,
+    overlaps: Vec<Vec<T>>,
+    channel_mask: Vec<bool>,
+    fft: Arc<dyn RealToComplex<T>>,
+    ifft: Arc<dyn ComplexToReal<T>>,
+    scratch_fw: Vec<Complex<T>>,
+    scratch_inv: Vec<Complex<T>>,
+    input_buf: Vec<T>,
+    input_f: Vec<Complex<T>>,
+    product_f: Vec<Complex<T>>,
+    output_f: Vec<Complex<T>>,
+    output_buf: Vec<T>,
+}
+
+impl<T> FftLowLatency<T>
+where
+    T: Sample,
+{
+    /// Create a new FftLowLatency
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
+    /// - `nbr_partitions`: number of chunks the filter is split into, must be > 0.
+    ///   The filter is `nbr_partitions` times as long as a chunk.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn new(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_partitions: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        if nbr_partitions == 0 {
+            return Err(ResamplerConstructionError::EmptyFilter);
+        }
+        debug!(
+            "Create new FftLowLatency, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, partitions: {}, channels: {}",
+            sample_rate_input, sample_rate_output, chunk_size_in, nbr_partitions, nbr_channels
+        );
+        let (chunk_size_in, chunk_size_out) =
+            calculate_fft_sizes(sample_rate_input, sample_rate_output, chunk_size_in, true);
+        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
+        let upsampling = sample_rate_output / gcd;
+        let decimation = sample_rate_input / gcd;
+        let chunk_size_common = chunk_size_in * upsampling;
+
+        let mut planner = RealFftPlanner::<T>::new();
+        let fft = planner.plan_fft_forward(2 * chunk_size_in);
+        let ifft = planner.plan_fft_inverse(2 * chunk_size_out);
+        let scratch_fw = fft.make_scratch_vec();
+        let scratch_inv = ifft.make_scratch_vec();
+
+        // Design the filter at the common rate. The transition band is set by the length
+        // of the filter, counted in frames at the lower of the two rates.
+        let cutoff = calculate_cutoff::<f32>(
+            nbr_partitions * chunk_size_in.min(chunk_size_out),
+            WindowFunction::BlackmanHarris2,
+        ) / upsampling.max(decimation) as f32;
+        let filter_len = nbr_partitions * chunk_size_common;
+        let sinc = make_sincs::<T>(filter_len, 1, cutoff, WindowFunction::BlackmanHarris2);
+        // The gain compensates for the zero stuffing, and includes the scaling of the inverse FFT
+        let gain = T::coerce(upsampling as f64 / (2 * chunk_size_common) as f64);
+        let common_fft = planner.plan_fft_forward(2 * chunk_size_common);
+        let mut common_scratch = common_fft.make_scratch_vec();
+        let mut common_buf = vec![T::zero(); 2 * chunk_size_common];
+        let mut filter_f = Vec::with_capacity(nbr_partitions);
+        for part in sinc[0].chunks(chunk_size_common) {
+            for (n, value) in common_buf.iter_mut().enumerate() {
+                *value = if n < chunk_size_common {
+                    part[n] * gain
+                } else {
+                    T::zero()
+                };
+            }
+            let mut part_f = vec![Complex::zero(); chunk_size_common + 1];
+            common_fft
+                .process_with_scratch(&mut common_buf, &mut part_f, &mut common_scratch)
+                .unwrap();
+            filter_f.push(part_f);
+        }
+
+        Ok(FftLowLatency {
+            nbr_channels,
+            chunk_size_in,
+            chunk_size_out,
+            nbr_partitions,
+            chunk_size_common,
+            decimation,
+            filter_f,
+            history: vec![
+                vec![vec![Complex::zero(); 2 * chunk_size_in]; nbr_partitions];
+                nbr_channels
+            ],
+            history_pos: vec![0; nbr_channels],
+            overlaps: vec![vec![T::zero(); chunk_size_out]; nbr_channels],
+            channel_mask: vec![true; nbr_channels],
+            fft,
+            ifft,
+            scratch_fw,
+            scratch_inv,
+            input_buf: vec![T::zero(); 2 * chunk_size_in],
+            input_f: vec![Complex::zero(); chunk_size_in + 1],
+            product_f: vec![Complex::zero(); chunk_size_common + 1],
+            output_f: vec![Complex::zero(); chunk_size_out + 1],
+            output_buf: vec![T::zero(); 2 * chunk_size_out],
+        })
+    }
+
+    /// Create a new FftLowLatency from a rational resample ratio, instead of a pair of sample rates.
+    ///
+    /// The resample ratio, the ratio between the output and input sample rates, is `numerator / denominator`.
+    /// Only the ratio matters for the FFT sizes, so for example giving 160 and 147
+    /// makes the same resampler as the sample rates 44100 and 48000 Hz.
+    /// The ratio does not need to be reduced, the chunk sizes are derived from the reduced ratio.
+    ///
+    /// Parameters are:
+    /// - `numerator`: Numerator of the resample ratio, must be > 0.
+    /// - `denominator`: Denominator of the resample ratio, must be > 0.
+    /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
+    /// - `nbr_partitions`: number of chunks the filter is split into, must be > 0.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn new_with_ratio(
+        numerator: usize,
+        denominator: usize,
+        chunk_size_in: usize,
+        nbr_partitions: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new(
+            denominator,
+            numerator,
+            chunk_size_in,
+            nbr_partitions,
+            nbr_channels,
+        )
+    }
+
+    /// Get the number of partitions of the filter.
+    pub fn nbr_partitions(&self) -> usize {
+        self.nbr_partitions
+    }
+
+    /// Resample one chunk of a channel, where `load_input` copies the input chunk
+    /// to the start of the input buffer.
+    fn resample_chunk<F>(
+        &mut self,
+        channel: usize,
+        load_input: F,
+        wave_out: &mut [T],
+    ) -> ResampleResult<()>
+    where
+        F: FnOnce(&mut [T]),
+    {
+        let chunk_in = self.chunk_size_in;
+        let chunk_out = self.chunk_size_out;
+        load_input(&mut self.input_buf[..chunk_in]);
+        // Flush denormals in the inner loops only, if requested by FlushDenormals
+        let _flush = flush_scope();
+        self.input_buf[chunk_in..]
+            .iter_mut()
+            .for_each(|value| *value = T::zero());
+        self.fft
+            .process_with_scratch(&mut self.input_buf, &mut self.input_f, &mut self.scratch_fw)
+            .map_err(|_| ResampleError::FftFailed)?;
+
+        // Store the full spectrum, which is then periodic in the spectrum at the common rate
+        let pos = self.history_pos[channel];
+        let spectrum = &mut self.history[channel][pos];
+        spectrum[..chunk_in + 1].copy_from_slice(&self.input_f);
+        for (value, mirrored) in spectrum[chunk_in + 1..]
+            .iter_mut()
+            .zip(self.input_f[1..chunk_in].iter().rev())
+        {
+            *value = mirrored.conj();
+        }
+
+        // Sum the products of the partitions of the filter and the delayed input spectra
+        self.product_f
+            .iter_mut()
+            .for_each(|value| *value = Complex::zero());
+        for (part, part_f) in self.filter_f.iter().enumerate() {
+            let slot = (pos + self.nbr_partitions - part) % self.nbr_partitions;
+            let spectrum = &self.history[channel][slot];
+            for (product, filt) in self
+                .product_f
+                .chunks_mut(2 * chunk_in)
+                .zip(part_f.chunks(2 * chunk_in))
+            {
+                for ((value, filt), spec) in product.iter_mut().zip(filt.iter()).zip(spectrum) {
+                    *value += *spec * *filt;
+                }
+            }
+        }
+        self.history_pos[channel] = (pos + 1) % self.nbr_partitions;
+
+        // Decimate by folding the spectrum to the output rate
+        let len_common = 2 * self.chunk_size_common;
+        self.output_f
+            .iter_mut()
+            .for_each(|value| *value = Complex::zero());
+        for fold in 0..self.decimation {
+            for (n, value) in self.output_f.iter_mut().enumerate() {
+                let idx = n + fold * 2 * chunk_out;
+                *value += if idx <= self.chunk_size_common {
+                    self.product_f[idx]
+                } else {
+                    self.product_f[len_common - idx].conj()
+                };
+            }
+        }
+        self.output_f[0].im = T::zero();
+        self.output_f[chunk_out].im = T::zero();
+        self.ifft
+            .process_with_scratch(
+                &mut self.output_f,
+                &mut self.output_buf,
+                &mut self.scratch_inv,
+            )
+            .map_err(|_| ResampleError::FftFailed)?;
+        let overlap = &mut self.overlaps[channel];
+        for (n, value) in wave_out.iter_mut().enumerate().take(chunk_out) {
+            *value = self.output_buf[n] + overlap[n];
+        }
+        overlap.copy_from_slice(&self.output_buf[chunk_out..]);
+        Ok(())
+    }
+}
+
+impl<T> FftLowLatency<T>
+where
+    T: Sample,
+{
+    /// Resample the input channels, given either as contiguous slices or as split segments.
+    fn process_channels<I: InputChannels<T>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let layout = validate_and_update_mask(
+            &mut self.channel_mask,
+            active_channels_mask,
+            wave_in,
+            wave_out,
+            self.chunk_size_in,
+            self.chunk_size_out,
+        )?;
+
+        // The mask is taken out while processing, since the processing borrows self mutably
+        let channel_mask = std::mem::take(&mut self.channel_mask);
+        let result = layout.active_channels(&channel_mask).try_for_each(
+            |(channel, channel_in, channel_out)| {
+                self.resample_chunk(
+                    channel,
+                    |buf| wave_in.copy_frames(channel_in, 0, buf),
+                    &mut wave_out[channel_out].as_mut()[..self.chunk_size_out],
+                )
+            },
+        );
+        self.channel_mask = channel_mask;
+        result.map(|_| (self.chunk_size_in, self.chunk_size_out))
+    }
+}
+
+impl<T> Resampler<T> for FftLowLatency<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Contiguous(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn process_split_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[(&[T], &[T])],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_channels(&Split(wave_in), wave_out, active_channels_mask)
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.output_frames_max()
+    }
+
+    /// Get the delay of the filter, which is half the filter length, in output frames.
+    fn output_delay(&self) -> usize {
+        self.nbr_partitions * self.chunk_size_out / 2
+    }
+
+    /// Get the delay of the filter, which is half the filter length, in input frames.
+    fn input_delay(&self) -> usize {
+        self.nbr_partitions * self.chunk_size_in / 2
+    }
+
+    /// Update the resample ratio. This is not supported by this resampler and
+    /// always returns an [ResampleError::SyncNotAdjustable].
+    fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Update the resample ratio relative to the original one. This is not
+    /// supported by this resampler and always returns an [ResampleError::SyncNotAdjustable].
+    fn set_resample_ratio_relative(&mut self, _rel_ratio: f64, _ramp: bool) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    fn memory_usage(&self) -> usize {
+        heap_size_nested(&self.filter_f)
+            + heap_size(&self.history)
+            + self.history.iter().map(heap_size_nested).sum::<usize>()
+            + heap_size(&self.history_pos)
+            + heap_size_nested(&self.overlaps)
+            + heap_size(&self.channel_mask)
+            + fft_plan_size::<T>(self.fft.len())
+            + fft_plan_size::<T>(self.ifft.len())
+            + heap_size(&self.scratch_fw)
+            + heap_size(&self.scratch_inv)
+            + heap_size(&self.input_buf)
+            + heap_size(&self.input_f)
+            + heap_size(&self.product_f)
+            + heap_size(&self.output_f)
+            + heap_size(&self.output_buf)
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        ResamplerConfig::describe(
+            self,
+            "FftLowLatency",
+            ResamplerAlgorithm::PartitionedFft {
+                nbr_partitions: self.nbr_partitions,
+            },
+            Some(self.chunk_size_out as f64 / self.chunk_size_in as f64),
+        )
+    }
+
+    fn reset(&mut self) {
+        self.overlaps
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
+        self.history.iter_mut().for_each(|ch| {
+            ch.iter_mut()
+                .for_each(|spec| spec.iter_mut().for_each(|s| *s = Complex::zero()))
+        });
+        self.history_pos.iter_mut().for_each(|pos| *pos = 0);
+        self.channel_mask.iter_mut().for_each(|val| *val = true);
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{FftLowLatency, Resampler, ResamplerConstructionError};
+
+    fn check_sine(rate_in: usize, rate_out: usize, chunk_size: usize, partitions: usize) {
+        let mut resampler =
+            FftLowLatency::<f64>::new(rate_in, rate_out, chunk_size, partitions, 1).unwrap();
+        let chunk_in = resampler.input_frames_next();
+        let freq = 1000.0;
+        let mut output = Vec::new();
+        for chunk in 0..40 {
+            let wave: Vec<f64> = (0..chunk_in)
+                .map(|n| {
+                    let t = (chunk * chunk_in + n) as f64 / rate_in as f64;
+                    (2.0 * std::f64::consts::PI * freq * t).sin()
+                })
+                .collect();
+            output.extend_from_slice(&resampler.process(&[wave], None).unwrap()[0]);
+        }
+        let delay = resampler.output_delay();
+        // Skip the start, where the history is still filling up
+        for (n, value) in output.iter().enumerate().skip(2 * delay + 1) {
+            let t = (n - delay) as f64 / rate_out as f64;
+            let expected = (2.0 * std::f64::consts::PI * freq * t).sin();
+            assert!(
+                (value - expected).abs() < 1.0e-4,
+                "frame {}: got {}, expected {}",
+                n,
+                value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn upsample_sine() {
+        check_sine(48000, 96000, 64, 8);
+        check_sine(44100, 48000, 147, 4);
+    }
+
+    #[test]
+    fn downsample_sine() {
+        check_sine(48000, 16000, 48, 8);
+        check_sine(48000, 44100, 160, 4);
+    }
+
+    #[test]
+    fn invalid_partitions() {
+        assert!(matches!(
+            FftLowLatency::<f64>::new(44100, 48000, 147, 0, 2),
+            Err(ResamplerConstructionError::EmptyFilter)
+        ));
+    }
+}
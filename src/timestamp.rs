@@ -0,0 +1,462 @@
+use crate::error::ResampleResult;
+use crate::{process_wrapped, Resampler, ResamplerConfig, Sample};
+use std::collections::VecDeque;
+
+/// Number of marks that fit in the queue without allocating.
+const MARK_CAPACITY: usize = 16;
+
+/// A timestamp or metadata attached to an input frame.
+struct Mark<M> {
+    position: u64,
+    timestamp: Option<f64>,
+    metadata: Option<M>,
+}
+
+/// A wrapper for a [Resampler] that carries timestamps and metadata from the input to the output.
+///
+/// Call [set_timestamp](Timestamped::set_timestamp) and [attach_metadata](Timestamped::attach_metadata)
+/// before processing a chunk, to attach a timestamp or a metadata value to the first frame of that chunk.
+/// The timestamps are given in seconds, and the time of any later input frame
+/// is extrapolated from the last timestamp using the input sample rate.
+/// After each call to [process_into_buffer](Resampler::process_into_buffer),
+/// [output_timestamp](Timestamped::output_timestamp) gives the time of the first frame of the output chunk,
+/// and [pop_metadata](Timestamped::pop_metadata) gives the metadata that has reached the output,
+/// together with the offset of the frame it is attached to within the output chunk.
+///
+/// The mapping from output to input frames takes the delay of the filter and the resample ratio into account,
+/// also while the ratio is ramped, and is accurate to about one frame.
+/// The wrapper follows the ratio by watching the calls to [set_resample_ratio](Resampler::set_resample_ratio)
+/// and [set_resample_ratio_relative](Resampler::set_resample_ratio_relative).
+/// The ratio must therefore be changed through the wrapper, and not directly on the wrapped resampler,
+/// and the wrapper should be created before the ratio is changed for the first time.
+/// For example, to combine it with [AdaptiveLatency](crate::AdaptiveLatency),
+/// place the Timestamped wrapper inside the AdaptiveLatency wrapper.
+/// This means that the timestamps of the output describe when the input that was resampled into
+/// the output was captured, on the time base of the input.
+/// Setting a new timestamp for a chunk, for example after a discontinuity in the source,
+/// affects the output from the point where that chunk reaches the output.
+///
+/// The queue of marks that have not reached the output has room for 16 entries
+/// when the wrapper is created, and only allocates when more are pending.
+/// Metadata that is not taken with [pop_metadata](Timestamped::pop_metadata)
+/// before the next chunk is processed is discarded.
+/// ```
+/// use rubato::{FastFixedIn, PolynomialDegree, Resampler, Timestamped};
+/// let resampler = FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 480, 1).unwrap();
+/// let mut resampler = Timestamped::new(resampler, 48000.0);
+/// let waves_in = vec![vec![0.0f64; 480]; 1];
+/// resampler.set_timestamp(12.5);
+/// resampler.attach_metadata("first packet");
+/// let _waves_out = resampler.process(&waves_in, None).unwrap();
+/// // The first output frame was captured slightly before the first input frame,
+/// // because of the delay of the interpolation.
+/// let time = resampler.output_timestamp().unwrap();
+/// assert!(time < 12.5 && time > 12.499);
+/// assert_eq!(resampler.pop_metadata(), Some((8, "first packet")));
+/// ```
+pub struct Timestamped<R, M = ()> {
+    resampler: R,
+    sample_rate_input: f64,
+    marks: VecDeque<Mark<M>>,
+    base: Option<(u64, f64)>,
+    frames_in: u64,
+    original_ratio: Option<f64>,
+    resample_ratio: Option<f64>,
+    target_ratio: Option<f64>,
+    next_position: f64,
+    chunk_start: f64,
+    chunk_len: f64,
+    chunk_frames_out: usize,
+    output_timestamp: Option<f64>,
+}
+
+impl<R, M> Timestamped<R, M> {
+    /// Create a new Timestamped wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    /// - `sample_rate_input`: The input sample rate, used to extrapolate the timestamps.
+    pub fn new<T>(resampler: R, sample_rate_input: f64) -> Self
+    where
+        R: Resampler<T>,
+        T: Sample,
+    {
+        let original_ratio = resampler.config().resample_ratio;
+        let mut timestamped = Timestamped {
+            resampler,
+            sample_rate_input,
+            marks: VecDeque::with_capacity(MARK_CAPACITY),
+            base: None,
+            frames_in: 0,
+            original_ratio,
+            resample_ratio: original_ratio,
+            target_ratio: original_ratio,
+            next_position: 0.0,
+            chunk_start: 0.0,
+            chunk_len: 0.0,
+            chunk_frames_out: 0,
+            output_timestamp: None,
+        };
+        timestamped.clear();
+        timestamped
+    }
+
+    /// Set the timestamp, in seconds, of the first input frame of the next chunk.
+    pub fn set_timestamp(&mut self, timestamp: f64) {
+        self.marks.push_back(Mark {
+            position: self.frames_in,
+            timestamp: Some(timestamp),
+            metadata: None,
+        });
+    }
+
+    /// Attach a metadata value to the first input frame of the next chunk.
+    pub fn attach_metadata(&mut self, metadata: M) {
+        self.marks.push_back(Mark {
+            position: self.frames_in,
+            timestamp: None,
+            metadata: Some(metadata),
+        });
+    }
+
+    /// Get the timestamp, in seconds, of the first frame of the last output chunk.
+    /// Returns `None` if no timestamp has been set since the wrapper was created or reset.
+    pub fn output_timestamp(&self) -> Option<f64> {
+        self.output_timestamp
+    }
+
+    /// Take the next metadata value that has reached the last output chunk.
+    /// Returns the offset, in output frames, of the frame the value is attached to within the chunk,
+    /// together with the value.
+    /// Returns `None` when there is no more metadata for the chunk.
+    pub fn pop_metadata(&mut self) -> Option<(usize, M)> {
+        let chunk_end = self.chunk_start + self.chunk_len;
+        while let Some(mark) = self.marks.front() {
+            if mark.position as f64 >= chunk_end {
+                return None;
+            }
+            let mark = self.marks.pop_front()?;
+            if let Some(timestamp) = mark.timestamp {
+                self.base = Some((mark.position, timestamp));
+            }
+            if let Some(metadata) = mark.metadata {
+                let offset = ((mark.position as f64 - self.chunk_start).max(0.0)
+                    * self.chunk_frames_out as f64
+                    / self.chunk_len.max(1.0)) as usize;
+                return Some((
+                    offset.min(self.chunk_frames_out.saturating_sub(1)),
+                    metadata,
+                ));
+            }
+        }
+        None
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Update the marks after a chunk has been processed.
+    /// The output of the chunk corresponds to the input frames from `start`, and spans `len` input frames.
+    fn update(&mut self, start: f64, len: f64, frames_in: usize, frames_out: usize) {
+        // Marks before the start of the chunk belonged to earlier output chunks
+        while let Some(mark) = self.marks.front() {
+            if mark.position as f64 >= start {
+                break;
+            }
+            if let Some(timestamp) = mark.timestamp {
+                self.base = Some((mark.position, timestamp));
+            }
+            self.marks.pop_front();
+        }
+        // Before the first timestamp reaches the output, extrapolate backwards from it
+        let mut base = self.base.or_else(|| {
+            self.marks
+                .iter()
+                .find_map(|mark| mark.timestamp.map(|timestamp| (mark.position, timestamp)))
+        });
+        for mark in self
+            .marks
+            .iter()
+            .take_while(|mark| mark.position as f64 <= start)
+        {
+            if let Some(timestamp) = mark.timestamp {
+                base = Some((mark.position, timestamp));
+            }
+        }
+        self.output_timestamp = base.map(|(position, timestamp)| {
+            timestamp + (start - position as f64) / self.sample_rate_input
+        });
+        self.frames_in += frames_in as u64;
+        self.chunk_start = start;
+        self.chunk_len = len;
+        self.chunk_frames_out = frames_out;
+    }
+
+    /// Discard all marks, and restart the frame counting from the original ratio.
+    fn clear<T>(&mut self)
+    where
+        R: Resampler<T>,
+        T: Sample,
+    {
+        self.marks.clear();
+        self.base = None;
+        self.frames_in = 0;
+        self.resample_ratio = self.original_ratio;
+        self.target_ratio = self.original_ratio;
+        // The first output frame comes from before the start of the input, because of the delay
+        self.next_position = match self.original_ratio {
+            Some(ratio) => -(self.resampler.output_delay() as f64) / ratio,
+            None => -(self.resampler.input_delay() as f64),
+        };
+        self.chunk_start = 0.0;
+        self.chunk_len = 0.0;
+        self.chunk_frames_out = 0;
+        self.output_timestamp = None;
+    }
+
+    /// Process a chunk, passing the scratch buffer on to the wrapped resampler if one is given.
+    fn process_chunk<T, Vin, Vout>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: Option<&mut [T]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        T: Sample,
+        R: Resampler<T>,
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[T]>,
+    {
+        let (frames_in, frames_out) = process_wrapped(
+            &mut self.resampler,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            scratch,
+        )?;
+        // A ramped ratio changes linearly over the chunk
+        let len = match (self.resample_ratio, self.target_ratio) {
+            (Some(ratio), Some(target)) => frames_out as f64 * (0.5 / ratio + 0.5 / target),
+            _ => frames_in as f64,
+        };
+        let start = self.next_position;
+        self.next_position += len;
+        self.resample_ratio = self.target_ratio;
+        self.update(start, len, frames_in, frames_out);
+        Ok((frames_in, frames_out))
+    }
+}
+
+impl<T, R, M> Resampler<T> for Timestamped<R, M>
+where
+    T: Sample,
+    R: Resampler<T>,
+    M: Send,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, None)
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.resampler.scratch_len()
+    }
+
+    fn process_into_buffer_with_scratch<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_chunk(wave_in, wave_out, active_channels_mask, Some(scratch))
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.resampler.input_frames_max()
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.resampler.output_frames_max()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.resampler.output_frames_next()
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn input_delay(&self) -> usize {
+        self.resampler.input_delay()
+    }
+
+    fn buffering_delay(&self) -> usize {
+        self.resampler.buffering_delay()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)?;
+        if !ramp {
+            self.resample_ratio = Some(new_ratio);
+        }
+        self.target_ratio = Some(new_ratio);
+        Ok(())
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler
+            .set_resample_ratio_relative(rel_ratio, ramp)?;
+        let new_ratio = self.original_ratio.map(|ratio| ratio * rel_ratio);
+        if !ramp {
+            self.resample_ratio = new_ratio;
+        }
+        self.target_ratio = new_ratio;
+        Ok(())
+    }
+
+    fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+    }
+
+    fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        self.resampler.set_chunk_size(chunk_size)
+    }
+
+    /// Load the history of the wrapped resampler, and discard all timestamps and metadata.
+    fn set_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        self.resampler.set_history(history)?;
+        self.clear::<T>();
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.resampler.memory_usage() + self.marks.capacity() * std::mem::size_of::<Mark<M>>()
+    }
+
+    fn config(&self) -> ResamplerConfig {
+        self.resampler.config().wrapped(self, "Timestamped")
+    }
+
+    /// Reset the wrapped resampler, and discard all timestamps and metadata.
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.clear::<T>();
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{FastFixedIn, PolynomialDegree, Resampler, SincFixedOut, Timestamped};
+    use crate::{SincInterpolationParameters, SpeechPreset};
+
+    #[test]
+    fn timestamps_follow_ratio() {
+        let resampler = SincFixedOut::<f64>::new(
+            2.0,
+            1.5,
+            SincInterpolationParameters::speech(SpeechPreset::Wideband),
+            256,
+            1,
+        )
+        .unwrap();
+        let mut resampler = Timestamped::<_, ()>::new(resampler, 48000.0);
+        let delay = resampler.output_delay() as f64;
+        let mut frames_in = 0;
+        let mut frames_out = 0;
+        let mut last_time = 0.0;
+        for chunk in 0..40 {
+            if chunk == 20 {
+                resampler.set_resample_ratio(2.5, true).unwrap();
+            }
+            if chunk % 10 == 0 {
+                resampler.set_timestamp(100.0 + frames_in as f64 / 48000.0);
+            }
+            let waves_in = vec![vec![0.0f64; resampler.input_frames_next()]; 1];
+            let (nbr_in, nbr_out) = resampler
+                .process_into_buffer(&waves_in, &mut [vec![0.0f64; 256]], None)
+                .unwrap();
+            let time = resampler.output_timestamp().unwrap();
+            if chunk <= 20 {
+                let expected = 100.0 + (frames_out as f64 - delay) / 96000.0;
+                assert!(
+                    (time - expected).abs() < 1.0e-9,
+                    "chunk {}: {} != {}",
+                    chunk,
+                    time,
+                    expected
+                );
+            } else if chunk > 21 {
+                // After the ramp, each output frame is 1 / 2.5 input frames long
+                let expected = last_time + 256.0 / 120000.0;
+                assert!((time - expected).abs() < 1.0e-9);
+            }
+            last_time = time;
+            frames_in += nbr_in;
+            frames_out += nbr_out;
+        }
+        assert!(last_time < 100.0 + frames_in as f64 / 48000.0);
+        resampler.reset();
+        assert!(resampler.output_timestamp().is_none());
+    }
+
+    #[test]
+    fn metadata_reaches_output() {
+        let resampler =
+            FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Linear, 100, 1).unwrap();
+        let mut resampler = Timestamped::new(resampler, 48000.0);
+        let mut received = Vec::new();
+        for chunk in 0..6 {
+            let mut waves_in = vec![vec![0.0f64; 100]; 1];
+            if chunk == 2 || chunk == 3 {
+                // Mark the first frame of the chunk with an impulse
+                waves_in[0][0] = 1.0;
+                resampler.attach_metadata(chunk);
+            }
+            let waves_out = resampler.process(&waves_in, None).unwrap();
+            while let Some((offset, value)) = resampler.pop_metadata() {
+                let peak = waves_out[0]
+                    .iter()
+                    .enumerate()
+                    .fold(
+                        (0, 0.0),
+                        |max, (n, v)| if *v > max.1 { (n, *v) } else { max },
+                    );
+                assert!((offset as i64 - peak.0 as i64).abs() <= 1);
+                received.push((chunk, value));
+            }
+        }
+        assert!(resampler.output_timestamp().is_none());
+        assert_eq!(received, vec![(2, 2), (3, 3)]);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{Resampler, Sample};
+
+/// A wrapper for a [Resampler] that accepts any number of input frames,
+/// and fills as much of the output buffers as possible.
+///
+/// The wrapped resamplers work on chunks, where either the input or the output size is fixed.
+/// This wrapper instead suits sources and sinks that are both irregular.
+/// Input frames are collected in an internal buffer until there is enough for a chunk.
+/// The chunk is then processed, and the output frames that don't fit in the output buffers
+/// are kept in a second internal buffer and returned by the following calls.
+/// Both buffers are allocated when the wrapper is created, and processing does not allocate.
+///
+/// Processing returns the number of input frames consumed and output frames produced.
+/// All input frames are consumed, unless the output buffers fill up first.
+/// The frames that were not consumed must then be passed again in the next call.
+/// ```
+/// use rubato::{FastFixedIn, PolynomialDegree, VariableChunkResampler};
+/// let resampler = FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+/// let mut resampler = VariableChunkResampler::new(resampler);
+/// let waves_in = vec![vec![0.0f64; 1000]; 2];
+/// let mut waves_out = vec![vec![0.0f64; 700]; 2];
+/// let (consumed, produced) = resampler
+///     .process_into_buffer(&waves_in, &mut waves_out)
+///     .unwrap();
+/// ```
+pub struct VariableChunkResampler<R, T> {
+    resampler: R,
+    input_buffer: Vec<Vec<T>>,
+    input_fill: usize,
+    output_buffer: Vec<Vec<T>>,
+    output_start: usize,
+    output_end: usize,
+}
+
+impl<R, T> VariableChunkResampler<R, T>
+where
+    R: Resampler<T>,
+    T: Sample,
+{
+    /// Create a new VariableChunkResampler wrapper.
+    ///
+    /// Parameters are:
+    /// - `resampler`: The resampler to wrap.
+    pub fn new(resampler: R) -> Self {
+        let nbr_channels = resampler.nbr_channels();
+        let input_buffer = vec![vec![T::zero(); resampler.input_frames_max()]; nbr_channels];
+        let output_buffer = vec![vec![T::zero(); resampler.output_frames_max()]; nbr_channels];
+        VariableChunkResampler {
+            resampler,
+            input_buffer,
+            input_fill: 0,
+            output_buffer,
+            output_start: 0,
+            output_end: 0,
+        }
+    }
+
+    /// Resample the frames in `wave_in`, and write as many output frames as fit to `wave_out`.
+    /// All input channels must have the same length, as must all output channels.
+    /// Returns the number of input frames consumed and output frames written,
+    /// as `(consumed, produced)`.
+    pub fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+    ) -> ResampleResult<(usize, usize)> {
+        let nbr_channels = self.resampler.nbr_channels();
+        if wave_in.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        let frames_in = wave_in.first().map(|wave| wave.as_ref().len()).unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() < frames_in {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames_in,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        let frames_out = wave_out
+            .first_mut()
+            .map(|wave| wave.as_mut().len())
+            .unwrap_or(0);
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if wave.as_mut().len() < frames_out {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: frames_out,
+                    actual: wave.as_mut().len(),
+                });
+            }
+        }
+
+        let mut consumed = 0;
+        let mut produced = 0;
+        loop {
+            // Return the frames left over from the previous chunk
+            let nbr_frames = (self.output_end - self.output_start).min(frames_out - produced);
+            for (wave, buffer) in wave_out.iter_mut().zip(self.output_buffer.iter()) {
+                wave.as_mut()[produced..produced + nbr_frames]
+                    .copy_from_slice(&buffer[self.output_start..self.output_start + nbr_frames]);
+            }
+            self.output_start += nbr_frames;
+            produced += nbr_frames;
+            if produced == frames_out {
+                break;
+            }
+
+            // Collect input frames until there is a complete chunk
+            let needed = self.resampler.input_frames_next();
+            let nbr_frames = needed
+                .saturating_sub(self.input_fill)
+                .min(frames_in - consumed);
+            for (wave, buffer) in wave_in.iter().zip(self.input_buffer.iter_mut()) {
+                buffer[self.input_fill..self.input_fill + nbr_frames]
+                    .copy_from_slice(&wave.as_ref()[consumed..consumed + nbr_frames]);
+            }
+            self.input_fill += nbr_frames;
+            consumed += nbr_frames;
+            if self.input_fill < needed {
+                break;
+            }
+
+            let (frames_used, frames_produced) = self.resampler.process_into_buffer(
+                &self.input_buffer,
+                &mut self.output_buffer,
+                None,
+            )?;
+            for buffer in self.input_buffer.iter_mut() {
+                buffer.copy_within(frames_used..self.input_fill, 0);
+            }
+            self.input_fill -= frames_used;
+            self.output_start = 0;
+            self.output_end = frames_produced;
+        }
+        trace!(
+            "Processed variable chunk, {} frames in, {} frames out",
+            consumed,
+            produced
+        );
+        Ok((consumed, produced))
+    }
+
+    /// Get the number of input frames that are buffered, waiting for a complete chunk.
+    pub fn buffered_input_frames(&self) -> usize {
+        self.input_fill
+    }
+
+    /// Get the number of output frames that are buffered, waiting to be returned.
+    pub fn buffered_output_frames(&self) -> usize {
+        self.output_end - self.output_start
+    }
+
+    /// Update the resample ratio of the wrapped resampler, see [Resampler::set_resample_ratio].
+    pub fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio, ramp)
+    }
+
+    /// Update the resample ratio of the wrapped resampler as a factor relative to the original one,
+    /// see [Resampler::set_resample_ratio_relative].
+    pub fn set_resample_ratio_relative(
+        &mut self,
+        rel_ratio: f64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio, ramp)
+    }
+
+    /// Change the maximum relative ratio of the wrapped resampler,
+    /// see [Resampler::set_max_resample_ratio_relative].
+    /// The internal buffers are extended when the wrapped resampler needs more room,
+    /// and the buffered frames are kept.
+    pub fn set_max_resample_ratio_relative(
+        &mut self,
+        max_resample_ratio_relative: f64,
+    ) -> ResampleResult<()> {
+        self.resampler
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)?;
+        let input_frames = self.resampler.input_frames_max();
+        let output_frames = self.resampler.output_frames_max();
+        for buf in self.input_buffer.iter_mut() {
+            if buf.len() < input_frames {
+                buf.resize(input_frames, T::zero());
+            }
+        }
+        for buf in self.output_buffer.iter_mut() {
+            if buf.len() < output_frames {
+                buf.resize(output_frames, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reset the wrapped resampler, and discard all buffered input and output frames.
+    pub fn reset(&mut self) {
+        self.resampler.reset();
+        self.input_fill = 0;
+        self.output_start = 0;
+        self.output_end = 0;
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn inner(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Unwrap the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
+mod tests {
+    use crate::{FastFixedIn, FastFixedOut, PolynomialDegree, Resampler, VariableChunkResampler};
+
+    /// Process the input with irregular input and output sizes, and return the output.
+    fn process_irregular<R: Resampler<f64>>(resampler: R, input: &[f64]) -> Vec<f64> {
+        let mut resampler = VariableChunkResampler::new(resampler);
+        let mut output = Vec::new();
+        let mut position = 0;
+        let mut step = 0;
+        while position < input.len() {
+            step += 1;
+            let frames_in = (step * 37 % 101).min(input.len() - position);
+            let mut wave_out = vec![vec![0.0; step * 53 % 89]];
+            let (consumed, produced) = resampler
+                .process_into_buffer(&[&input[position..position + frames_in]], &mut wave_out)
+                .unwrap();
+            assert!(consumed <= frames_in);
+            output.extend_from_slice(&wave_out[0][..produced]);
+            position += consumed;
+        }
+        output
+    }
+
+    /// Process the input in chunks of the sizes requested by the resampler, and return the output.
+    fn process_chunks<R: Resampler<f64>>(mut resampler: R, input: &[f64]) -> Vec<f64> {
+        let mut output = Vec::new();
+        let mut position = 0;
+        while position + resampler.input_frames_next() <= input.len() {
+            let frames = resampler.input_frames_next();
+            let wave_out = resampler
+                .process(&[&input[position..position + frames]], None)
+                .unwrap();
+            output.extend_from_slice(&wave_out[0]);
+            position += frames;
+        }
+        output
+    }
+
+    #[test]
+    fn irregular_sizes() {
+        let input: Vec<f64> = (0..5000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let make_fi =
+            || FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let expected = process_chunks(make_fi(), &input);
+        let output = process_irregular(make_fi(), &input);
+        assert!(output.len() > 7000);
+        assert_eq!(output[..], expected[..output.len()]);
+
+        let make_fo =
+            || FastFixedOut::<f64>::new(0.7, 1.0, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let expected = process_chunks(make_fo(), &input);
+        let output = process_irregular(make_fo(), &input);
+        assert!(output.len() > 3000);
+        assert_eq!(output[..], expected[..output.len()]);
+    }
+
+    #[test]
+    fn output_full() {
+        let resampler = FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 100, 1).unwrap();
+        let mut resampler = VariableChunkResampler::new(resampler);
+        let wave_in = vec![vec![0.0f64; 1000]];
+        let mut wave_out = vec![vec![0.0f64; 50]];
+        let (consumed, produced) = resampler
+            .process_into_buffer(&wave_in, &mut wave_out)
+            .unwrap();
+        assert_eq!(produced, 50);
+        assert_eq!(consumed, 100);
+        assert!(resampler.buffered_output_frames() > 0);
+        assert_eq!(resampler.buffered_input_frames(), 0);
+    }
+}
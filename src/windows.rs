@@ -1,4 +1,7 @@
+use crate::sample::CoerceFrom;
 use crate::Sample;
+use num_traits::Float;
+use realfft::RealFftPlanner;
 
 /// Different window functions that can be used to window the sinc function.
 #[derive(Debug, Clone, Copy)]
@@ -15,6 +18,31 @@ pub enum WindowFunction {
     Hann,
     /// Squared Hann. Slower rolloff and higher attenuation than simple Hann.
     Hann2,
+    /// Blackman-Nuttall. Similar rolloff and attenuation as Blackman-Harris, with lower far sidelobes.
+    BlackmanNuttall,
+    /// Flat-top. Very slow rolloff, mainly intended for comparisons.
+    FlatTop,
+    /// Tukey, or tapered cosine, with the given fraction of the window inside the cosine tapers.
+    /// A value of 0.0 gives a rectangular window, and 1.0 gives a Hann window.
+    /// Note that [calculate_cutoff] returns the same value as for [WindowFunction::BlackmanHarris2].
+    Tukey(f64),
+    /// Gaussian, with the given standard deviation relative to half the window length.
+    /// Values around 0.4 are typical, smaller values give a slower rolloff but higher attenuation.
+    /// Values below 0.01, and NaN, are replaced by 0.01.
+    /// Note that [calculate_cutoff] returns the same value as for [WindowFunction::BlackmanHarris2].
+    Gaussian(f64),
+    /// Dolph-Chebyshev, with the given sidelobe attenuation in dB.
+    /// All sidelobes of the window have the same level, which gives the narrowest main lobe
+    /// for that attenuation. Higher attenuations give a slower rolloff.
+    /// Note that [calculate_cutoff] returns the same value as for [WindowFunction::BlackmanHarris2].
+    DolphChebyshev(f64),
+    /// Custom window function.
+    /// The function is called with the relative position in the window, from 0.0 at the start
+    /// to 1.0 at the end, and should return the window value at that position.
+    /// The window should be symmetric around the center at 0.5, and have its maximum there.
+    /// Note that [calculate_cutoff] can't know the properties of a custom window,
+    /// and returns the same value as for [WindowFunction::BlackmanHarris2].
+    Custom(fn(f64) -> f64),
 }
 
 /// Helper function. Standard Blackman-Harris window
@@ -77,6 +105,175 @@ where
     window
 }
 
+/// Helper function. Standard Blackman-Nuttall window
+pub fn blackman_nuttall<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a BlackmanNuttall windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    let pi2 = T::coerce(2.0) * T::PI;
+    let pi4 = T::coerce(4.0) * T::PI;
+    let pi6 = T::coerce(6.0) * T::PI;
+    let np_f = T::coerce(npoints);
+    let a = T::coerce(0.3635819);
+    let b = T::coerce(0.4891775);
+    let c = T::coerce(0.1365995);
+    let d = T::coerce(0.0106411);
+    for (x, item) in window.iter_mut().enumerate() {
+        let x_float = T::coerce(x);
+        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
+            - d * (pi6 * x_float / np_f).cos();
+    }
+    window
+}
+
+/// Helper function. Standard flat-top window
+pub fn flat_top<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a FlatTop windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    let pi2 = T::coerce(2.0) * T::PI;
+    let pi4 = T::coerce(4.0) * T::PI;
+    let pi6 = T::coerce(6.0) * T::PI;
+    let pi8 = T::coerce(8.0) * T::PI;
+    let np_f = T::coerce(npoints);
+    let a = T::coerce(0.21557895);
+    let b = T::coerce(0.41663158);
+    let c = T::coerce(0.277263158);
+    let d = T::coerce(0.083578947);
+    let e = T::coerce(0.006947368);
+    for (x, item) in window.iter_mut().enumerate() {
+        let x_float = T::coerce(x);
+        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
+            - d * (pi6 * x_float / np_f).cos()
+            + e * (pi8 * x_float / np_f).cos();
+    }
+    window
+}
+
+/// Helper function. Tukey window, `alpha` is the fraction of the window inside the tapers
+pub fn tukey<T>(npoints: usize, alpha: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Tukey windows with {} points, alpha {}",
+        npoints,
+        alpha
+    );
+    let alpha = alpha.clamp(0.0, 1.0);
+    (0..npoints)
+        .map(|x| {
+            let pos = x as f64 / npoints as f64;
+            let edge = pos.min(1.0 - pos);
+            if edge < alpha / 2.0 {
+                T::coerce(0.5 - 0.5 * (2.0 * std::f64::consts::PI * edge / alpha).cos())
+            } else {
+                T::one()
+            }
+        })
+        .collect()
+}
+
+/// Helper function. Gaussian window, `sigma` is the standard deviation relative to half the window length
+pub fn gaussian<T>(npoints: usize, sigma: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Gaussian windows with {} points, sigma {}",
+        npoints,
+        sigma
+    );
+    // A standard deviation of zero or NaN would give NaN values
+    let sigma = sigma.max(0.01);
+    (0..npoints)
+        .map(|x| {
+            let rel = (x as f64 / npoints as f64 - 0.5) / (0.5 * sigma);
+            T::coerce((-0.5 * rel * rel).exp())
+        })
+        .collect()
+}
+
+/// Helper function. Dolph-Chebyshev window, `attenuation` is the sidelobe attenuation in dB
+pub fn dolph_chebyshev<T>(npoints: usize, attenuation: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Dolph-Chebyshev windows with {} points, attenuation {} dB",
+        npoints,
+        attenuation
+    );
+    if npoints == 0 {
+        return Vec::new();
+    }
+    // Make a symmetric window with one more point, and drop the last one
+    // to get the same periodic form as the other windows.
+    let len = npoints + 1;
+    let order = (len - 1) as f64;
+    let ripple = 10.0f64.powf(attenuation.abs() / 20.0);
+    let beta = ((1.0 / order) * ripple.acosh()).cosh();
+    let odd_sign = if len % 2 == 1 { 1.0 } else { -1.0 };
+    // The Chebyshev polynomial of the given order, sampled around the unit circle
+    let mut spectrum_in = (0..2 * len)
+        .map(|k| {
+            if k >= len {
+                return 0.0;
+            }
+            let x = beta * (std::f64::consts::PI * k as f64 / len as f64).cos();
+            if x > 1.0 {
+                (order * x.acosh()).cosh()
+            } else if x < -1.0 {
+                odd_sign * (order * (-x).acosh()).cosh()
+            } else {
+                (order * x.acos()).cos()
+            }
+        })
+        .collect::<Vec<f64>>();
+    // A transform of twice the length gives the window values at both whole and half steps,
+    // as needed for the even and odd lengths.
+    let mut planner = RealFftPlanner::<f64>::new();
+    let fft = planner.plan_fft_forward(2 * len);
+    let mut spectrum = fft.make_output_vec();
+    fft.process(&mut spectrum_in, &mut spectrum).unwrap();
+    let half = if len % 2 == 1 {
+        (0..(len + 1) / 2)
+            .map(|n| spectrum[2 * n].re)
+            .collect::<Vec<_>>()
+    } else {
+        (0..len / 2 + 1)
+            .map(|n| spectrum[(2 * n as isize - 1).unsigned_abs()].re)
+            .collect::<Vec<_>>()
+    };
+    let mut window = half[1..].iter().rev().copied().collect::<Vec<_>>();
+    if len % 2 == 1 {
+        window.extend_from_slice(&half);
+    } else {
+        window.extend_from_slice(&half[1..]);
+    }
+    let max = window.iter().fold(0.0f64, |acc, value| acc.max(*value));
+    window
+        .iter()
+        .take(npoints)
+        .map(|value| T::coerce(value / max))
+        .collect()
+}
+
+/// Helper function. Evaluate a custom window function
+fn custom<T>(npoints: usize, func: fn(f64) -> f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a custom window with {} points", npoints);
+    (0..npoints)
+        .map(|x| T::coerce(func(x as f64 / npoints as f64)))
+        .collect()
+}
+
 /// Make the selected window function
 pub fn make_window<T>(npoints: usize, windowfunc: WindowFunction) -> Vec<T>
 where
@@ -88,6 +285,12 @@ where
         }
         WindowFunction::Blackman | WindowFunction::Blackman2 => blackman::<T>(npoints),
         WindowFunction::Hann | WindowFunction::Hann2 => hann::<T>(npoints),
+        WindowFunction::BlackmanNuttall => blackman_nuttall::<T>(npoints),
+        WindowFunction::FlatTop => flat_top::<T>(npoints),
+        WindowFunction::Tukey(alpha) => tukey::<T>(npoints, alpha),
+        WindowFunction::Gaussian(sigma) => gaussian::<T>(npoints, sigma),
+        WindowFunction::DolphChebyshev(attenuation) => dolph_chebyshev::<T>(npoints, attenuation),
+        WindowFunction::Custom(func) => custom::<T>(npoints, func),
     };
     match windowfunc {
         WindowFunction::Blackman2 | WindowFunction::BlackmanHarris2 | WindowFunction::Hann2 => {
@@ -102,56 +305,129 @@ where
 /// The result is based on an approximation, which gives good results for sinc lengths from 32 to 2048.
 pub fn calculate_cutoff<T>(npoints: usize, windowfunc: WindowFunction) -> T
 where
-    T: Sample,
+    T: Float + CoerceFrom<f64> + CoerceFrom<usize>,
 {
     // Coefficient values generated by cutoff_fit_cubic.py
     let (k1, k2, k3) = match windowfunc {
         WindowFunction::BlackmanHarris => (
-            T::coerce(8.041443677716476),
-            T::coerce(55.9506779343387),
-            T::coerce(898.0287985384213),
+            T::coerce_from(8.041443677716476),
+            T::coerce_from(55.9506779343387),
+            T::coerce_from(898.0287985384213),
         ),
-        WindowFunction::BlackmanHarris2 => (
-            T::coerce(13.745202940783823),
-            T::coerce(121.73532586374934),
-            T::coerce(5964.163279612051),
+        WindowFunction::BlackmanHarris2
+        | WindowFunction::Tukey(_)
+        | WindowFunction::Gaussian(_)
+        | WindowFunction::DolphChebyshev(_)
+        | WindowFunction::Custom(_) => (
+            T::coerce_from(13.745202940783823),
+            T::coerce_from(121.73532586374934),
+            T::coerce_from(5964.163279612051),
+        ),
+        WindowFunction::BlackmanNuttall => (
+            T::coerce_from(8.063277542562128),
+            T::coerce_from(55.45521975016896),
+            T::coerce_from(897.9864240199787),
+        ),
+        WindowFunction::FlatTop => (
+            T::coerce_from(10.092874055571219),
+            T::coerce_from(64.87485969186638),
+            T::coerce_from(1926.9486603826517),
         ),
         WindowFunction::Blackman => (
-            T::coerce(6.159598046201173),
-            T::coerce(18.926415097606878),
-            T::coerce(653.4247430458968),
+            T::coerce_from(6.159598046201173),
+            T::coerce_from(18.926415097606878),
+            T::coerce_from(653.4247430458968),
         ),
         WindowFunction::Blackman2 => (
-            T::coerce(9.506235102129398),
-            T::coerce(79.13120634953742),
-            T::coerce(1502.2316160588925),
+            T::coerce_from(9.506235102129398),
+            T::coerce_from(79.13120634953742),
+            T::coerce_from(1502.2316160588925),
         ),
         WindowFunction::Hann => (
-            T::coerce(3.3481080887677166),
-            T::coerce(10.106519434875038),
-            T::coerce(78.96345249024414),
+            T::coerce_from(3.3481080887677166),
+            T::coerce_from(10.106519434875038),
+            T::coerce_from(78.96345249024414),
         ),
         WindowFunction::Hann2 => (
-            T::coerce(5.38751148378734),
-            T::coerce(29.69451915489501),
-            T::coerce(184.82117462266237),
+            T::coerce_from(5.38751148378734),
+            T::coerce_from(29.69451915489501),
+            T::coerce_from(184.82117462266237),
         ),
     };
     let one = T::one();
-    one / (k1 / T::coerce(npoints)
-        + k2 / T::coerce(npoints.pow(2))
-        + k3 / T::coerce(npoints.pow(3))
+    one / (k1 / T::coerce_from(npoints)
+        + k2 / T::coerce_from(npoints.pow(2))
+        + k3 / T::coerce_from(npoints.pow(3))
         + one)
 }
 
-#[cfg(test)]
+/// Approximate stopband attenuation in dB of a sinc filter using the given window function,
+/// when the cutoff is given by [calculate_cutoff].
+/// Returns `None` for windows where the attenuation isn't known.
+pub fn window_attenuation(windowfunc: WindowFunction) -> Option<f32> {
+    match windowfunc {
+        WindowFunction::Hann => Some(43.0),
+        WindowFunction::Hann2 => Some(61.0),
+        WindowFunction::Blackman => Some(75.0),
+        WindowFunction::Blackman2 => Some(102.0),
+        WindowFunction::BlackmanHarris => Some(109.0),
+        WindowFunction::BlackmanNuttall => Some(113.0),
+        WindowFunction::FlatTop => Some(112.0),
+        WindowFunction::BlackmanHarris2 => Some(142.0),
+        WindowFunction::Tukey(_)
+        | WindowFunction::Gaussian(_)
+        | WindowFunction::DolphChebyshev(_)
+        | WindowFunction::Custom(_) => None,
+    }
+}
+
+/// Select the window function that reaches the given stopband attenuation (in dB)
+/// with the shortest sinc length. Returns `None` if no window reaches the attenuation.
+pub fn select_window(attenuation: f32) -> Option<WindowFunction> {
+    // Ordered by the sinc length needed for a given transition bandwidth
+    [
+        WindowFunction::Hann,
+        WindowFunction::Hann2,
+        WindowFunction::Blackman,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::BlackmanNuttall,
+        WindowFunction::BlackmanHarris2,
+    ]
+    .into_iter()
+    .find(|window| window_attenuation(*window).unwrap_or(0.0) >= attenuation)
+}
+
+/// Calculate the shortest sinc length, as a multiple of 8, that gives a transition band
+/// no wider than `transition_bandwidth` with the given window function.
+/// The transition bandwidth is relative to the Nyquist frequency, like the cutoff,
+/// and the transition band ends at the Nyquist frequency.
+/// The cutoff for the resulting length is given by [calculate_cutoff].
+/// Returns `None` if the transition bandwidth is not between 0.0 and 1.0,
+/// or if the required length is longer than 65536.
+pub fn calculate_sinc_len(transition_bandwidth: f32, windowfunc: WindowFunction) -> Option<usize> {
+    if transition_bandwidth <= 0.0 || transition_bandwidth >= 1.0 {
+        return None;
+    }
+    (1..=65536 / 8)
+        .map(|n| 8 * n)
+        .find(|npoints| 1.0 - calculate_cutoff::<f32>(*npoints, windowfunc) <= transition_bandwidth)
+}
+
+#[cfg(all(test, feature = "f32", feature = "f64"))]
 mod tests {
     extern crate approx;
     use crate::windows::blackman;
     use crate::windows::blackman_harris;
+    use crate::windows::blackman_nuttall;
     use crate::windows::calculate_cutoff;
+    use crate::windows::calculate_sinc_len;
+    use crate::windows::dolph_chebyshev;
+    use crate::windows::flat_top;
+    use crate::windows::gaussian;
     use crate::windows::hann;
     use crate::windows::make_window;
+    use crate::windows::select_window;
+    use crate::windows::tukey;
     use crate::windows::WindowFunction;
     use approx::assert_abs_diff_eq;
 
@@ -209,6 +485,14 @@ mod tests {
         assert_abs_diff_eq!(cutoff, 0.894, epsilon = 0.001);
         let cutoff = calculate_cutoff::<f64>(256, WindowFunction::BlackmanHarris2);
         assert_abs_diff_eq!(cutoff, 0.947, epsilon = 0.001);
+        let cutoff = calculate_cutoff::<f64>(128, WindowFunction::BlackmanNuttall);
+        assert_abs_diff_eq!(cutoff, 0.937, epsilon = 0.001);
+        let cutoff = calculate_cutoff::<f64>(256, WindowFunction::BlackmanNuttall);
+        assert_abs_diff_eq!(cutoff, 0.969, epsilon = 0.001);
+        let cutoff = calculate_cutoff::<f64>(128, WindowFunction::FlatTop);
+        assert_abs_diff_eq!(cutoff, 0.923, epsilon = 0.001);
+        let cutoff = calculate_cutoff::<f64>(256, WindowFunction::FlatTop);
+        assert_abs_diff_eq!(cutoff, 0.961, epsilon = 0.001);
         let cutoff = calculate_cutoff::<f64>(128, WindowFunction::Hann);
         assert_abs_diff_eq!(cutoff, 0.974, epsilon = 0.001);
         let cutoff = calculate_cutoff::<f64>(256, WindowFunction::Hann);
@@ -218,4 +502,121 @@ mod tests {
         let cutoff = calculate_cutoff::<f64>(256, WindowFunction::Hann2);
         assert_abs_diff_eq!(cutoff, 0.979, epsilon = 0.001);
     }
+
+    #[test]
+    fn test_blackman_nuttall() {
+        let wnd = blackman_nuttall::<f64>(16);
+        assert_abs_diff_eq!(wnd[8], 1.0, epsilon = 0.000001);
+        assert!(wnd[0] < 0.001);
+        assert!(wnd[15] < 0.1);
+    }
+
+    #[test]
+    fn test_flat_top() {
+        let wnd = flat_top::<f64>(16);
+        assert_abs_diff_eq!(wnd[8], 1.0, epsilon = 0.0001);
+        assert!(wnd[0].abs() < 0.001);
+    }
+
+    #[test]
+    fn test_tukey() {
+        let wnd = tukey::<f64>(16, 0.5);
+        assert_abs_diff_eq!(wnd[8], 1.0, epsilon = 0.000001);
+        assert_abs_diff_eq!(wnd[4], 1.0, epsilon = 0.000001);
+        assert!(wnd[0] < 0.000001);
+        assert!(wnd[2] > 0.1 && wnd[2] < 0.9);
+        let wnd_hann = tukey::<f64>(16, 1.0);
+        let hann = hann::<f64>(16);
+        for (a, b) in wnd_hann.iter().zip(hann.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = 0.000001);
+        }
+    }
+
+    #[test]
+    fn test_gaussian() {
+        let wnd = gaussian::<f64>(16, 0.4);
+        assert_abs_diff_eq!(wnd[8], 1.0, epsilon = 0.000001);
+        assert_abs_diff_eq!(wnd[0], (-0.5f64 * 2.5 * 2.5).exp(), epsilon = 0.000001);
+        assert_abs_diff_eq!(wnd[4], wnd[12], epsilon = 0.000001);
+        for sigma in [0.0, -0.4, f64::NAN] {
+            let wnd = gaussian::<f64>(16, sigma);
+            assert!(wnd.iter().all(|value| value.is_finite()));
+            assert_abs_diff_eq!(wnd[8], 1.0, epsilon = 0.000001);
+        }
+    }
+
+    #[test]
+    fn test_dolph_chebyshev() {
+        for npoints in [31, 32] {
+            let wnd = dolph_chebyshev::<f64>(npoints, 60.0);
+            assert_eq!(wnd.len(), npoints);
+            for x in 1..npoints {
+                assert_abs_diff_eq!(wnd[x], wnd[npoints - x], epsilon = 0.000001);
+            }
+            // Restore the dropped point to get the symmetric window, which has equal sidelobes
+            let mut symmetric = wnd.clone();
+            symmetric.push(wnd[0]);
+            let response = (0..2048)
+                .map(|n| {
+                    let omega = std::f64::consts::PI * n as f64 / 2048.0;
+                    let (re, im) =
+                        symmetric
+                            .iter()
+                            .enumerate()
+                            .fold((0.0, 0.0), |(re, im), (k, value)| {
+                                (
+                                    re + value * (omega * k as f64).cos(),
+                                    im + value * (omega * k as f64).sin(),
+                                )
+                            });
+                    (re * re + im * im).sqrt()
+                })
+                .collect::<Vec<f64>>();
+            let mainlobe_end = (1..response.len())
+                .find(|n| response[*n] < response[n + 1])
+                .unwrap();
+            let sidelobe = response[mainlobe_end..]
+                .iter()
+                .fold(0.0f64, |acc, value| acc.max(*value));
+            let sidelobe_db = 20.0 * (sidelobe / response[0]).log10();
+            assert_abs_diff_eq!(sidelobe_db, -60.0, epsilon = 0.1);
+        }
+    }
+
+    #[test]
+    fn test_custom() {
+        fn hann_custom(x: f64) -> f64 {
+            0.5 - 0.5 * (2.0 * std::f64::consts::PI * x).cos()
+        }
+        let wnd = make_window::<f64>(16, WindowFunction::Hann);
+        let wnd_custom = make_window::<f64>(16, WindowFunction::Custom(hann_custom));
+        for (a, b) in wnd.iter().zip(wnd_custom.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = 0.000001);
+        }
+    }
+
+    #[test]
+    fn test_sinc_len() {
+        let len = calculate_sinc_len(0.05, WindowFunction::Blackman).unwrap();
+        assert_eq!(len % 8, 0);
+        assert!(1.0 - calculate_cutoff::<f32>(len, WindowFunction::Blackman) <= 0.05);
+        assert!(1.0 - calculate_cutoff::<f32>(len - 8, WindowFunction::Blackman) > 0.05);
+        assert!(calculate_sinc_len(0.0, WindowFunction::Blackman).is_none());
+        assert!(calculate_sinc_len(1.0, WindowFunction::Blackman).is_none());
+        assert!(calculate_sinc_len(1.0e-6, WindowFunction::Blackman).is_none());
+    }
+
+    #[test]
+    fn test_select_window() {
+        assert!(matches!(select_window(40.0), Some(WindowFunction::Hann)));
+        assert!(matches!(
+            select_window(100.0),
+            Some(WindowFunction::BlackmanHarris)
+        ));
+        assert!(matches!(
+            select_window(140.0),
+            Some(WindowFunction::BlackmanHarris2)
+        ));
+        assert!(select_window(200.0).is_none());
+    }
 }